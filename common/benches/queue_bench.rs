@@ -224,6 +224,7 @@ fn bench_element_sizes(c: &mut Criterion) {
 
     // Test with a larger struct similar to order data
     #[derive(Clone, Copy)]
+    #[allow(dead_code)]
     struct OrderData {
         order_id: u64,
         client_id: u32,
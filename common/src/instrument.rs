@@ -0,0 +1,178 @@
+//! Registry of instrument specifications shared by the exchange and clients.
+//!
+//! Tick size, lot size, price scale, notional bounds and display precision
+//! live in one `InstrumentSpec` per ticker. Both binaries load the same
+//! registry from an instruments file at startup, so the matching engine's
+//! validation, the risk manager's checks and the strategies' price rounding
+//! all agree. Each line names a ticker followed by `key=value` fields; fields
+//! that are absent keep their defaults.
+//!
+//! ```text
+//! # ticker  fields
+//! 1 tick_size=5 lot_size=10 min_notional=10000
+//! 2 price_scale=4 display_decimals=2 max_notional=5000000
+//! ```
+
+use crate::types::{InstrumentSpec, TickerId};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Instrument specifications keyed by ticker
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstrumentRegistry {
+    /// Specs of tickers that override the default
+    specs: HashMap<TickerId, InstrumentSpec>,
+}
+
+impl InstrumentRegistry {
+    /// Creates an empty registry; every ticker uses the default spec
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the spec of a ticker
+    ///
+    /// # Returns
+    /// The spec previously registered for the ticker, if any
+    pub fn insert(&mut self, spec: InstrumentSpec) -> Option<InstrumentSpec> {
+        self.specs.insert(spec.ticker_id, spec)
+    }
+
+    /// Returns the spec of a ticker, the default spec if none was registered
+    #[inline]
+    pub fn get(&self, ticker_id: TickerId) -> InstrumentSpec {
+        self.specs
+            .get(&ticker_id)
+            .copied()
+            .unwrap_or_else(|| InstrumentSpec::new(ticker_id))
+    }
+
+    /// Returns true if the ticker has a registered spec
+    #[inline]
+    pub fn contains(&self, ticker_id: TickerId) -> bool {
+        self.specs.contains_key(&ticker_id)
+    }
+
+    /// Returns the number of registered specs
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.specs.len()
+    }
+
+    /// Returns true if no spec is registered
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// Returns the registered specs ordered by ticker
+    pub fn specs(&self) -> Vec<InstrumentSpec> {
+        let mut specs: Vec<_> = self.specs.values().copied().collect();
+        specs.sort_unstable_by_key(|spec| spec.ticker_id);
+        specs
+    }
+
+    /// Parses one spec per line. Blank lines and `#` comments are ignored.
+    ///
+    /// # Returns
+    /// The parsed registry, or an `InvalidData` error naming the offending line
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut registry = Self::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: invalid instrument '{}'", line_no + 1, line),
+                )
+            };
+            let mut fields = line.split_whitespace();
+            let ticker_id = fields.next().and_then(|t| t.parse().ok()).ok_or_else(invalid)?;
+            let mut spec = registry.get(ticker_id);
+
+            for field in fields {
+                let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+                spec = match key {
+                    "price_scale" => spec.with_price_scale(value.parse().map_err(|_| invalid())?),
+                    "tick_size" => spec.with_tick_size(value.parse().map_err(|_| invalid())?),
+                    "lot_size" => spec.with_lot_size(value.parse().map_err(|_| invalid())?),
+                    "min_notional" => {
+                        let min_notional = value.parse().map_err(|_| invalid())?;
+                        spec.with_notional_limits(min_notional, spec.max_notional)
+                    }
+                    "max_notional" => {
+                        let max_notional = value.parse().map_err(|_| invalid())?;
+                        spec.with_notional_limits(spec.min_notional, max_notional)
+                    }
+                    "display_decimals" => {
+                        spec.with_display_decimals(value.parse().map_err(|_| invalid())?)
+                    }
+                    _ => return Err(invalid()),
+                };
+            }
+            registry.insert(spec);
+        }
+
+        Ok(registry)
+    }
+
+    /// Reads and parses an instruments file
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+impl FromIterator<InstrumentSpec> for InstrumentRegistry {
+    fn from_iter<I: IntoIterator<Item = InstrumentSpec>>(specs: I) -> Self {
+        let mut registry = Self::new();
+        for spec in specs {
+            registry.insert(spec);
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_parse() {
+        let registry = InstrumentRegistry::parse(
+            "# instruments\n\
+             1 tick_size=5 lot_size=10 min_notional=10000\n\
+             \n\
+             2 price_scale=4 display_decimals=2 max_notional=5000000 # fx\n",
+        )
+        .unwrap();
+
+        assert_eq!(registry.len(), 2);
+        let first = registry.get(1);
+        assert_eq!((first.tick_size, first.lot_size, first.min_notional), (5, 10, 10_000));
+        assert_eq!(first.price_scale, crate::DEFAULT_PRICE_SCALE);
+        let second = registry.get(2);
+        assert_eq!((second.price_scale, second.display_decimals), (4, 2));
+        assert_eq!(second.max_notional, 5_000_000);
+
+        // Unregistered tickers fall back to the default spec
+        assert!(!registry.contains(3));
+        assert_eq!(registry.get(3), InstrumentSpec::new(3));
+        assert_eq!(registry.specs().iter().map(|s| s.ticker_id).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_registry_parse_rejects_bad_lines() {
+        for text in ["x tick_size=5", "1 tick_size", "1 tick_size=abc", "1 colour=red"] {
+            let err = InstrumentRegistry::parse(text).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("line 1"));
+        }
+    }
+}
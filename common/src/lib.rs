@@ -1,10 +1,14 @@
 pub mod types;
 pub mod lf_queue;
+pub mod spsc;
 pub mod mem_pool;
 pub mod time;
 pub mod logging;
 pub mod net;
+pub mod replay;
+pub mod instrument;
 
 // Re-export commonly used types at crate root for convenience
 pub use types::*;
 pub use net::*;
+pub use instrument::InstrumentRegistry;
@@ -8,7 +8,7 @@
 // 4. Static message preference - avoid allocations on the hot path
 
 use crate::lf_queue::LFQueue;
-use crate::time::{now_nanos, Nanos};
+use crate::time::{ClockSource, Nanos};
 
 use std::io::Write;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -113,6 +113,8 @@ pub struct Logger {
     writer_thread: Option<JoinHandle<()>>,
     /// Minimum log level to record
     min_level: LogLevel,
+    /// Clock entries are timestamped with
+    clock: ClockSource,
 }
 
 impl Logger {
@@ -142,6 +144,7 @@ impl Logger {
             shared,
             writer_thread: Some(writer_thread),
             min_level,
+            clock: ClockSource::Realtime,
         }
     }
 
@@ -215,7 +218,7 @@ impl Logger {
         }
 
         let entry = LogEntry {
-            timestamp: now_nanos(),
+            timestamp: self.clock.now(),
             level,
             message: LogMessage::Static(msg),
         };
@@ -235,7 +238,7 @@ impl Logger {
         }
 
         let entry = LogEntry {
-            timestamp: now_nanos(),
+            timestamp: self.clock.now(),
             level,
             message: LogMessage::StaticWithI64(msg, value),
         };
@@ -253,7 +256,7 @@ impl Logger {
         }
 
         let entry = LogEntry {
-            timestamp: now_nanos(),
+            timestamp: self.clock.now(),
             level,
             message: LogMessage::StaticWithU64(msg, value),
         };
@@ -271,7 +274,7 @@ impl Logger {
         }
 
         let entry = LogEntry {
-            timestamp: now_nanos(),
+            timestamp: self.clock.now(),
             level,
             message: LogMessage::StaticWithF64(msg, value),
         };
@@ -291,7 +294,7 @@ impl Logger {
         }
 
         let entry = LogEntry {
-            timestamp: now_nanos(),
+            timestamp: self.clock.now(),
             level,
             message: LogMessage::Formatted(format!("{}: {}", msg, value)),
         };
@@ -344,6 +347,18 @@ impl Logger {
     pub fn level(&self) -> LogLevel {
         self.min_level
     }
+
+    /// Set the clock entries are timestamped with (realtime by default)
+    #[inline]
+    pub fn set_clock(&mut self, clock: ClockSource) {
+        self.clock = clock;
+    }
+
+    /// Get the clock entries are timestamped with
+    #[inline]
+    pub fn clock(&self) -> ClockSource {
+        self.clock
+    }
 }
 
 impl Default for Logger {
@@ -443,6 +458,14 @@ mod tests {
         assert_eq!(logger.level(), LogLevel::Warn);
     }
 
+    #[test]
+    fn test_logger_clock() {
+        let mut logger = Logger::new();
+        assert_eq!(logger.clock(), ClockSource::Realtime);
+        logger.set_clock(ClockSource::Monotonic);
+        assert_eq!(logger.clock(), ClockSource::Monotonic);
+    }
+
     #[test]
     fn test_log_static_message() {
         let logger = Logger::new();
@@ -539,7 +562,7 @@ mod tests {
 
         log_debug!(logger, "debug with value", 42);
         log_info!(logger, "info with value", "hello");
-        log_warn!(logger, "warn with value", 3.14);
+        log_warn!(logger, "warn with value", 2.5);
         log_error!(logger, "error with value", -1);
 
         logger.flush();
@@ -14,16 +14,50 @@
 // 3. A PoolPtr must only be used with the pool that created it
 // 4. A PoolPtr must not be used after deallocation (use-after-free)
 // 5. Each slot must be deallocated exactly once (no double-free)
-
-use std::cell::UnsafeCell;
+//
+// A pool's capacity is either fixed at compile time (`MemPool::new`) or chosen
+// at runtime (`MemPool::with_capacity`), for deployments that size their pools
+// from configuration. Pools track utilization (`PoolStats`) so capacities can
+// be right-sized from observed peaks. A pool can be `reset` to hand out its
+// slots in index order again, which owners use to rebuild their contents
+// contiguously after heavy churn.
+
+use std::cell::{Cell, UnsafeCell};
 use std::mem::MaybeUninit;
 use std::marker::PhantomData;
 
-/// A pre-allocated pool of N objects of type T.
+/// Utilization metrics of a pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Total number of slots
+    pub capacity: usize,
+    /// Slots currently allocated
+    pub allocated: usize,
+    /// Highest number of slots allocated at once
+    pub peak_allocated: usize,
+    /// Allocations refused because the pool was exhausted
+    pub failed_allocations: u64,
+}
+
+impl PoolStats {
+    /// Fraction of the capacity at the peak, from 0.0 to 1.0
+    #[inline]
+    pub fn peak_utilization(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.peak_allocated as f64 / self.capacity as f64
+    }
+}
+
+/// A pre-allocated pool of objects of type T.
 ///
 /// Provides O(1) allocation and deallocation with zero heap allocations
 /// after initialization. Uses a free-list implemented as a stack of indices.
 ///
+/// `new` creates a pool of N slots. `with_capacity` picks the number of
+/// slots at runtime instead, leaving N at its default of 0.
+///
 /// # Safety
 ///
 /// This type is designed for **single-threaded use only**. Using it from
@@ -47,19 +81,32 @@ use std::marker::PhantomData;
 ///
 /// // Return the slot to the pool
 /// pool.deallocate(ptr);
+///
+/// // A pool sized at runtime
+/// let sized: MemPool<u64> = MemPool::with_capacity(1024);
+/// assert_eq!(sized.capacity(), 1024);
 /// ```
-pub struct MemPool<T, const N: usize> {
+pub struct MemPool<T, const N: usize = 0> {
     /// Storage for pool objects. Objects are uninitialized until allocated
     /// and written to by the user.
-    storage: UnsafeCell<[MaybeUninit<T>; N]>,
+    storage: UnsafeCell<Box<[MaybeUninit<T>]>>,
 
     /// Stack of free indices. free_list[0..free_count] contains valid free indices.
     /// The top of the stack is at free_list[free_count - 1].
-    free_list: UnsafeCell<[usize; N]>,
+    free_list: UnsafeCell<Box<[usize]>>,
 
     /// Number of available (free) slots. Also serves as the stack pointer
     /// for the free list.
     free_count: UnsafeCell<usize>,
+
+    /// Total number of slots
+    capacity: usize,
+
+    /// Highest number of slots allocated at once
+    peak_allocated: Cell<usize>,
+
+    /// Allocations refused because the pool was exhausted
+    failed_allocations: Cell<u64>,
 }
 
 /// A pointer to an allocated slot in a MemPool.
@@ -91,83 +138,44 @@ impl<T, const N: usize> MemPool<T, N> {
     /// Creates a new memory pool with all N slots available.
     ///
     /// The storage is uninitialized - objects are only initialized
-    /// when the user writes to an allocated slot.
-    ///
-    /// # Warning
-    ///
-    /// For large pools (N > 1024), this may cause stack overflow because
-    /// the arrays are created on the stack before being returned. Use
-    /// `new_boxed()` instead for large pools.
+    /// when the user writes to an allocated slot. It is allocated on the
+    /// heap, so large pools do not risk overflowing the stack.
     ///
     /// # Panics
     ///
     /// Panics if N is 0 (a zero-capacity pool is not useful).
     pub fn new() -> Self {
-        assert!(N > 0, "MemPool capacity must be greater than 0");
-
-        // Initialize free list with all indices [0, 1, 2, ..., N-1]
-        // We use a const fn approach to initialize at compile time where possible
-        let mut free_list = [0usize; N];
-        let mut i = 0;
-        while i < N {
-            free_list[i] = i;
-            i += 1;
-        }
-
-        Self {
-            // SAFETY: MaybeUninit doesn't require initialization
-            storage: UnsafeCell::new(unsafe {
-                MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init()
-            }),
-            free_list: UnsafeCell::new(free_list),
-            free_count: UnsafeCell::new(N),
-        }
+        Self::with_capacity(N)
     }
 
-    /// Creates a new memory pool directly on the heap, avoiding stack overflow
-    /// for large pools.
-    ///
-    /// This is the preferred way to create large pools (N > 1024).
+    /// Creates a new boxed memory pool with all N slots available.
     ///
     /// # Panics
     ///
     /// Panics if N is 0 (a zero-capacity pool is not useful).
     pub fn new_boxed() -> Box<Self> {
-        assert!(N > 0, "MemPool capacity must be greater than 0");
-
-        // Use alloc API to allocate zeroed memory directly on heap
-        // This avoids any stack allocation of the large arrays
-        use std::alloc::{alloc_zeroed, Layout};
-
-        // SAFETY: Layout is valid for MemPool<T, N>
-        let layout = Layout::new::<Self>();
-        let ptr = unsafe { alloc_zeroed(layout) as *mut Self };
-
-        if ptr.is_null() {
-            std::alloc::handle_alloc_error(layout);
-        }
+        Box::new(Self::new())
+    }
 
-        // SAFETY: We allocated valid memory and will initialize it properly.
-        // UnsafeCell has the same memory layout as its inner type, so zeroed
-        // memory is a valid representation. We just need to write the actual
-        // values we need (free_count and free_list indices).
-        unsafe {
-            // Write free_count value (N) directly into the UnsafeCell's inner value
-            // UnsafeCell<usize> has the same layout as usize
-            let free_count_inner = std::ptr::addr_of_mut!((*ptr).free_count) as *mut usize;
-            std::ptr::write(free_count_inner, N);
-
-            // Storage is zeroed which is fine - MaybeUninit doesn't require initialization
-            // The UnsafeCell wrapper is transparent in memory layout
-
-            // Initialize free_list with indices 0..N
-            // UnsafeCell<[usize; N]> has same layout as [usize; N]
-            let free_list_inner = std::ptr::addr_of_mut!((*ptr).free_list) as *mut [usize; N];
-            for i in 0..N {
-                (*free_list_inner)[i] = i;
-            }
+    /// Creates a new memory pool with `capacity` slots available.
+    ///
+    /// Lets deployments size a pool from configuration at startup; N does
+    /// not limit the capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if capacity is 0 (a zero-capacity pool is not useful).
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "MemPool capacity must be greater than 0");
 
-            Box::from_raw(ptr)
+        Self {
+            storage: UnsafeCell::new((0..capacity).map(|_| MaybeUninit::uninit()).collect()),
+            // Initialize free list with all indices [0, 1, 2, ..., capacity-1]
+            free_list: UnsafeCell::new((0..capacity).collect()),
+            free_count: UnsafeCell::new(capacity),
+            capacity,
+            peak_allocated: Cell::new(0),
+            failed_allocations: Cell::new(0),
         }
     }
 
@@ -191,6 +199,7 @@ impl<T, const N: usize> MemPool<T, N> {
             let free_count = &mut *self.free_count.get();
 
             if *free_count == 0 {
+                self.failed_allocations.set(self.failed_allocations.get() + 1);
                 return None;
             }
 
@@ -198,6 +207,7 @@ impl<T, const N: usize> MemPool<T, N> {
             *free_count -= 1;
             let free_list = &*self.free_list.get();
             let index = free_list[*free_count];
+            self.peak_allocated.set(self.peak_allocated.get().max(self.capacity - *free_count));
 
             // Get pointer to the storage slot
             let storage = &mut *self.storage.get();
@@ -237,7 +247,7 @@ impl<T, const N: usize> MemPool<T, N> {
     /// ```
     #[inline]
     pub fn deallocate(&self, ptr: PoolPtr<T>) {
-        debug_assert!(ptr.index < N, "PoolPtr index out of bounds - wrong pool?");
+        debug_assert!(ptr.index < self.capacity, "PoolPtr index out of bounds - wrong pool?");
 
         // SAFETY: Single-threaded access is required by the type's contract
         unsafe {
@@ -245,7 +255,7 @@ impl<T, const N: usize> MemPool<T, N> {
             let free_list = &mut *self.free_list.get();
 
             debug_assert!(
-                *free_count < N,
+                *free_count < self.capacity,
                 "Double-free detected: pool already has all slots free"
             );
 
@@ -279,7 +289,7 @@ impl<T, const N: usize> MemPool<T, N> {
     /// the caller must explicitly drop it before deallocating.
     #[inline]
     pub unsafe fn deallocate_by_index(&self, index: usize) {
-        debug_assert!(index < N, "index out of bounds - wrong pool?");
+        debug_assert!(index < self.capacity, "index out of bounds - wrong pool?");
 
         // SAFETY: Single-threaded access is required by the type's contract.
         // Caller guarantees the index refers to an allocated slot.
@@ -287,7 +297,7 @@ impl<T, const N: usize> MemPool<T, N> {
         let free_list = &mut *self.free_list.get();
 
         debug_assert!(
-            *free_count < N,
+            *free_count < self.capacity,
             "Double-free detected: pool already has all slots free"
         );
 
@@ -305,7 +315,7 @@ impl<T, const N: usize> MemPool<T, N> {
     /// - No mutable reference to the same slot must exist
     #[inline]
     pub fn get(&self, ptr: &PoolPtr<T>) -> &T {
-        debug_assert!(ptr.index < N, "PoolPtr index out of bounds");
+        debug_assert!(ptr.index < self.capacity, "PoolPtr index out of bounds");
 
         // SAFETY: Caller guarantees the slot is allocated, initialized,
         // and no mutable references exist
@@ -319,8 +329,9 @@ impl<T, const N: usize> MemPool<T, N> {
     /// - The PoolPtr must have been allocated from this pool and not yet deallocated
     /// - No other references (shared or mutable) to the same slot must exist
     #[inline]
+    #[allow(clippy::mut_from_ref)]
     pub fn get_mut(&self, ptr: &PoolPtr<T>) -> &mut T {
-        debug_assert!(ptr.index < N, "PoolPtr index out of bounds");
+        debug_assert!(ptr.index < self.capacity, "PoolPtr index out of bounds");
 
         // SAFETY: Caller guarantees the slot is allocated and no other
         // references exist. Interior mutability is used intentionally.
@@ -338,7 +349,18 @@ impl<T, const N: usize> MemPool<T, N> {
     /// Returns the total capacity of the pool.
     #[inline]
     pub const fn capacity(&self) -> usize {
-        N
+        self.capacity
+    }
+
+    /// Returns the pool's utilization metrics.
+    #[inline]
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            capacity: self.capacity,
+            allocated: self.capacity - self.available(),
+            peak_allocated: self.peak_allocated.get(),
+            failed_allocations: self.failed_allocations.get(),
+        }
     }
 
     /// Returns a mutable reference to the object at the given index.
@@ -368,17 +390,14 @@ impl<T, const N: usize> MemPool<T, N> {
     /// The caller must ensure the index refers to a valid, allocated slot.
     /// Using an index for a free (deallocated) slot is undefined behavior.
     #[inline]
+    #[allow(clippy::mut_from_ref)]
     pub fn get_by_index(&self, index: usize) -> Option<&mut T> {
-        if index >= N {
-            return None;
-        }
-
         // SAFETY: Caller guarantees the slot at index is allocated, initialized,
         // and no other references exist. Interior mutability is used intentionally
         // for single-threaded performance.
         unsafe {
             let storage = &mut *self.storage.get();
-            Some(&mut *storage[index].as_mut_ptr())
+            storage.get_mut(index).map(|slot| &mut *slot.as_mut_ptr())
         }
     }
 
@@ -389,7 +408,7 @@ impl<T, const N: usize> MemPool<T, N> {
     ///
     /// # Safety
     ///
-    /// - The index must be less than N (the pool capacity)
+    /// - The index must be less than the pool capacity
     /// - The index must refer to an allocated slot (not a free slot)
     /// - The slot must have been initialized (written to) before reading
     /// - No other references (shared or mutable) to the same slot must exist
@@ -397,12 +416,26 @@ impl<T, const N: usize> MemPool<T, N> {
     ///
     /// Violating any of these conditions results in undefined behavior.
     #[inline]
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn get_by_index_unchecked(&self, index: usize) -> &mut T {
-        debug_assert!(index < N, "index out of bounds");
+        debug_assert!(index < self.capacity, "index out of bounds");
 
         // SAFETY: Caller guarantees all safety requirements are met
         let storage = &mut *self.storage.get();
-        &mut *storage[index].as_mut_ptr()
+        &mut *storage.get_unchecked_mut(index).as_mut_ptr()
+    }
+
+    /// Frees every slot, so allocations hand out indices 0, 1, 2, ... again.
+    ///
+    /// Values in the slots are not dropped, and any outstanding PoolPtr
+    /// becomes invalid; callers copy out what they need first. The peak and
+    /// failed allocation counters are kept.
+    pub fn reset(&mut self) {
+        let capacity = self.capacity;
+        for (slot, index) in self.free_list.get_mut().iter_mut().zip((0..capacity).rev()) {
+            *slot = index;
+        }
+        *self.free_count.get_mut() = capacity;
     }
 }
 
@@ -671,4 +704,70 @@ mod tests {
         pool.deallocate(ptr1);
         pool.deallocate(ptr2);
     }
+
+    #[test]
+    fn test_pool_stats_track_peak_and_failures() {
+        let pool: MemPool<u32, 2> = MemPool::new();
+        let ptr1 = pool.allocate().unwrap();
+        let ptr2 = pool.allocate().unwrap();
+        assert!(pool.allocate().is_none());
+        pool.deallocate(ptr1);
+
+        let stats = pool.stats();
+        assert_eq!(stats.capacity, 2);
+        assert_eq!(stats.allocated, 1);
+        assert_eq!(stats.peak_allocated, 2);
+        assert_eq!(stats.failed_allocations, 1);
+        assert_eq!(stats.peak_utilization(), 1.0);
+        pool.deallocate(ptr2);
+
+        let boxed: Box<MemPool<u32, 8>> = MemPool::new_boxed();
+        assert_eq!(boxed.stats(), PoolStats { capacity: 8, ..Default::default() });
+    }
+
+    #[test]
+    fn test_with_capacity_sizes_pool_at_runtime() {
+        let pool: MemPool<u64> = MemPool::with_capacity(3);
+        assert_eq!(pool.capacity(), 3);
+
+        let ptrs: Vec<_> = (0..3).map(|i| {
+            let ptr = pool.allocate().expect("should allocate");
+            *pool.get_mut(&ptr) = i * 10;
+            ptr
+        }).collect();
+        assert!(pool.allocate().is_none());
+        assert_eq!(*pool.get_by_index(ptrs[2].index()).unwrap(), 20);
+        assert!(pool.get_by_index(3).is_none());
+
+        for ptr in ptrs {
+            pool.deallocate(ptr);
+        }
+        let stats = pool.stats();
+        assert_eq!(stats.allocated, 0);
+        assert_eq!(stats.peak_allocated, 3);
+        assert_eq!(stats.failed_allocations, 1);
+
+        // Freed slots are reused
+        let ptr = pool.allocate().unwrap();
+        assert!(ptr.index() < 3);
+        pool.deallocate(ptr);
+    }
+
+    #[test]
+    fn test_reset_allocates_in_order() {
+        let mut pool: MemPool<u64> = MemPool::with_capacity(4);
+        let first = pool.allocate().unwrap();
+        pool.allocate().unwrap();
+        pool.deallocate(first);
+        pool.allocate().unwrap();
+
+        pool.reset();
+        assert_eq!(pool.available(), 4);
+        let indices: Vec<_> = (0..4).map(|_| pool.allocate().unwrap().index()).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+
+        let stats = pool.stats();
+        assert_eq!(stats.peak_allocated, 4);
+        assert_eq!(stats.failed_allocations, 0);
+    }
 }
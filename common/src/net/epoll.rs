@@ -0,0 +1,157 @@
+//! Readiness notification over Linux epoll.
+//!
+//! Lets an event loop block until one of its sockets is readable instead of
+//! sleeping between non-blocking polls. Sockets are registered level-triggered
+//! for input, so a socket that still has unread data is reported again on the
+//! next wait. A socket is removed automatically when it is closed.
+
+use socket2::Socket;
+use std::io;
+use std::time::Duration;
+
+/// Most ready sockets reported by a single wait.
+const MAX_EVENTS: usize = 64;
+
+/// An epoll instance watching sockets for readability.
+pub struct Epoll {
+    fd: i32,
+}
+
+impl Epoll {
+    /// Creates a new epoll instance.
+    ///
+    /// # Returns
+    /// The epoll instance, or an `Unsupported` error on platforms other than
+    /// Linux
+    pub fn new() -> io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            // SAFETY: epoll_create1 takes no pointers
+            let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { fd })
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "epoll is only available on Linux"))
+        }
+    }
+
+    /// Starts watching a socket for readability.
+    ///
+    /// # Arguments
+    /// * `socket` - The socket to watch; a listener is readable when a
+    ///   connection is pending
+    pub fn add(&self, socket: &Socket) -> io::Result<()> {
+        self.control(socket, true)
+    }
+
+    /// Stops watching a socket.
+    ///
+    /// # Arguments
+    /// * `socket` - A socket previously passed to `add`
+    pub fn delete(&self, socket: &Socket) -> io::Result<()> {
+        self.control(socket, false)
+    }
+
+    /// Blocks until a watched socket is readable or the timeout elapses.
+    ///
+    /// The timeout is rounded up to whole milliseconds. A wait interrupted
+    /// by a signal returns zero.
+    ///
+    /// # Arguments
+    /// * `timeout` - The longest time to block
+    ///
+    /// # Returns
+    /// The number of readable sockets, zero on timeout
+    pub fn wait(&self, timeout: Duration) -> io::Result<usize> {
+        #[cfg(target_os = "linux")]
+        {
+            let millis = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+            let mut events = [libc::epoll_event { events: 0, u64: 0 }; MAX_EVENTS];
+            // SAFETY: events is valid for MAX_EVENTS entries
+            let ready = unsafe { libc::epoll_wait(self.fd, events.as_mut_ptr(), MAX_EVENTS as i32, millis) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    return Ok(0);
+                }
+                return Err(err);
+            }
+            Ok(ready as usize)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (timeout, MAX_EVENTS);
+            Err(io::Error::new(io::ErrorKind::Unsupported, "epoll is only available on Linux"))
+        }
+    }
+
+    /// Adds or removes a socket from the interest list.
+    fn control(&self, socket: &Socket, add: bool) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+
+            let fd = socket.as_raw_fd();
+            let (op, mut event) = if add {
+                (libc::EPOLL_CTL_ADD, libc::epoll_event { events: libc::EPOLLIN as u32, u64: fd as u64 })
+            } else {
+                (libc::EPOLL_CTL_DEL, libc::epoll_event { events: 0, u64: 0 })
+            };
+            // SAFETY: event outlives the call
+            if unsafe { libc::epoll_ctl(self.fd, op, fd, &mut event) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (socket, add);
+            Err(io::Error::new(io::ErrorKind::Unsupported, "epoll is only available on Linux"))
+        }
+    }
+}
+
+impl Drop for Epoll {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        // SAFETY: the descriptor is owned by this instance
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use socket2::{Domain, Protocol, Type};
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+    use std::time::Instant;
+
+    #[test]
+    fn test_wait_reports_readable_socket() {
+        let epoll = Epoll::new().unwrap();
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).unwrap();
+        socket.bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).into()).unwrap();
+        let addr = socket.local_addr().unwrap().as_socket().unwrap();
+        epoll.add(&socket).unwrap();
+
+        // Nothing to read: the wait runs to its timeout
+        let start = Instant::now();
+        assert_eq!(epoll.wait(Duration::from_millis(20)).unwrap(), 0);
+        assert!(start.elapsed() >= Duration::from_millis(15));
+
+        // A datagram wakes the wait, and keeps doing so until it is read
+        UdpSocket::bind("127.0.0.1:0").unwrap().send_to(&[1, 2, 3], addr).unwrap();
+        assert_eq!(epoll.wait(Duration::from_secs(5)).unwrap(), 1);
+        assert_eq!(epoll.wait(Duration::from_secs(5)).unwrap(), 1);
+
+        // Once removed the socket is no longer reported
+        epoll.delete(&socket).unwrap();
+        assert_eq!(epoll.wait(Duration::from_millis(1)).unwrap(), 0);
+    }
+}
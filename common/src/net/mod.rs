@@ -8,9 +8,12 @@
 //!
 //! - [`tcp`] - TCP socket and listener with pre-allocated buffers
 //! - [`multicast`] - UDP multicast for market data feeds
+//! - [`epoll`] - Blocking readiness wait over Linux epoll
 
+pub mod epoll;
 pub mod multicast;
 pub mod tcp;
 
+pub use epoll::Epoll;
 pub use multicast::MulticastSocket;
 pub use tcp::{TcpListener, TcpSocket};
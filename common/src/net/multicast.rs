@@ -172,6 +172,42 @@ impl MulticastSocket {
         self.socket.set_multicast_if_v4(&interface_addr)
     }
 
+    /// Sets the kernel receive buffer size (SO_RCVBUF).
+    ///
+    /// A larger buffer absorbs bursts while the application is busy. The
+    /// kernel may cap the value (see `net.core.rmem_max` on Linux).
+    ///
+    /// # Arguments
+    /// * `size` - Requested buffer size in bytes
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.socket.set_recv_buffer_size(size)
+    }
+
+    /// Returns the kernel receive buffer size in bytes, as granted by the kernel.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.socket.recv_buffer_size()
+    }
+
+    /// Returns the number of datagrams the kernel dropped for this socket.
+    ///
+    /// Drops are counted when a datagram arrives while the receive buffer is
+    /// full, so they indicate the application isn't draining the socket fast
+    /// enough rather than loss on the network. Read from `/proc/net/udp` on
+    /// Linux; other platforms return an `Unsupported` error.
+    pub fn kernel_drops(&self) -> io::Result<u64> {
+        #[cfg(target_os = "linux")]
+        {
+            linux_udp_drops(&self.socket)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Kernel drop counters are only available on Linux",
+            ))
+        }
+    }
+
     /// Leaves a multicast group.
     ///
     /// # Arguments
@@ -195,6 +231,33 @@ impl MulticastSocket {
     }
 }
 
+/// Looks up the `drops` column of `/proc/net/udp` for the socket's inode.
+#[cfg(target_os = "linux")]
+fn linux_udp_drops(socket: &Socket) -> io::Result<u64> {
+    use std::os::fd::AsRawFd;
+
+    let link = std::fs::read_link(format!("/proc/self/fd/{}", socket.as_raw_fd()))?;
+    let inode = link
+        .to_str()
+        .and_then(|link| link.strip_prefix("socket:["))
+        .and_then(|link| link.strip_suffix(']'))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Descriptor is not a socket"))?;
+
+    let table = std::fs::read_to_string("/proc/net/udp")?;
+    for line in table.lines().skip(1) {
+        // Columns: sl local rem st queues timers retrnsmt uid timeout inode ref pointer drops
+        let mut fields = line.split_whitespace();
+        if fields.nth(9) == Some(inode) {
+            return fields
+                .nth(2)
+                .and_then(|drops| drops.parse().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Malformed /proc/net/udp"));
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound, "Socket not listed in /proc/net/udp"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +275,34 @@ mod tests {
         assert!(socket.set_nonblocking(false).is_ok());
     }
 
+    #[test]
+    fn test_recv_buffer_size_and_drops() {
+        let socket = MulticastSocket::new().unwrap();
+        let bind_addr = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0);
+        socket.socket().bind(&SocketAddr::V4(bind_addr).into()).unwrap();
+
+        let before = socket.recv_buffer_size().unwrap();
+        socket.set_recv_buffer_size(4 * 1024 * 1024).unwrap();
+        // The kernel may cap the request, but it never shrinks the buffer
+        assert!(socket.recv_buffer_size().unwrap() >= before);
+
+        // Only Linux exposes the counter; a fresh socket has dropped nothing
+        if cfg!(target_os = "linux") {
+            assert_eq!(socket.kernel_drops().unwrap(), 0);
+
+            // Overflow a minimal buffer to see the counter move
+            socket.set_recv_buffer_size(1).unwrap();
+            let port = socket.socket().local_addr().unwrap().as_socket().unwrap().port();
+            let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            for _ in 0..64 {
+                sender.send_to(&[0u8; 1024], ("127.0.0.1", port)).unwrap();
+            }
+            assert!(socket.kernel_drops().unwrap() > 0);
+        } else {
+            assert!(socket.kernel_drops().is_err());
+        }
+    }
+
     #[test]
     fn test_invalid_multicast_address() {
         // 192.168.1.1 is not a multicast address
@@ -7,6 +7,7 @@ use socket2::{Domain, Protocol, Socket, Type};
 use std::io;
 use std::mem::MaybeUninit;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
 
 /// Buffer size for send and receive operations (64KB).
 const BUFFER_SIZE: usize = 65536;
@@ -38,6 +39,27 @@ impl TcpSocket {
     /// # Returns
     /// A connected TcpSocket on success
     pub fn connect(addr: &str, port: u16) -> io::Result<Self> {
+        Self::connect_with(addr, port, None)
+    }
+
+    /// Connects to a remote address, giving up after `timeout`.
+    ///
+    /// Bounds how long a caller on a latency-sensitive path can be held up
+    /// by an unreachable host, instead of the kernel's connect timeout.
+    ///
+    /// # Arguments
+    /// * `addr` - The IP address or hostname to connect to
+    /// * `port` - The port number to connect to
+    /// * `timeout` - The longest to wait for the connection
+    ///
+    /// # Returns
+    /// A connected TcpSocket on success, or a `TimedOut` error
+    pub fn connect_timeout(addr: &str, port: u16, timeout: Duration) -> io::Result<Self> {
+        Self::connect_with(addr, port, Some(timeout))
+    }
+
+    /// Connects, waiting at most `timeout` if one is given.
+    fn connect_with(addr: &str, port: u16, timeout: Option<Duration>) -> io::Result<Self> {
         let address = format!("{}:{}", addr, port);
         let socket_addr: SocketAddr = address
             .to_socket_addrs()?
@@ -55,7 +77,10 @@ impl TcpSocket {
         // Set TCP_NODELAY for low latency
         socket.set_nodelay(true)?;
 
-        socket.connect(&socket_addr.into())?;
+        match timeout {
+            Some(timeout) => socket.connect_timeout(&socket_addr.into(), timeout)?,
+            None => socket.connect(&socket_addr.into())?,
+        }
 
         Ok(Self::from_socket(socket))
     }
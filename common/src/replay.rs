@@ -0,0 +1,199 @@
+// Paced replay of timestamped records
+//
+// A `Replayer` hands back recorded events (journal entries, captured market
+// data) in order, spacing them on its clock according to a `ReplaySpeed`:
+// as fast as possible for tests and CI, at the recorded pace for live-like
+// demos, or at a multiple of it. The clock is injectable so pacing can be
+// driven by a simulated clock.
+//
+// Delivery is polled, like the rest of the event loop: `poll` returns the
+// next record once it is due and None before that, so a replay never blocks
+// the thread it runs on.
+
+use crate::time::monotonic_nanos;
+use std::collections::VecDeque;
+
+/// How recorded gaps between records map onto the replay clock
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplaySpeed {
+    /// Deliver every record as soon as it is polled
+    #[default]
+    AsFastAsPossible,
+    /// Keep the recorded gaps between records
+    RealTime,
+    /// Divide the recorded gaps by this factor (2.0 replays twice as fast);
+    /// a factor of 0 or less replays as fast as possible
+    Multiplier(f64),
+}
+
+impl ReplaySpeed {
+    /// Returns the replay-clock gap for a recorded gap, in nanoseconds
+    #[inline]
+    pub fn scaled_gap(&self, recorded_gap_ns: u64) -> u64 {
+        match *self {
+            ReplaySpeed::AsFastAsPossible => 0,
+            ReplaySpeed::RealTime => recorded_gap_ns,
+            ReplaySpeed::Multiplier(factor) if factor > 0.0 => (recorded_gap_ns as f64 / factor) as u64,
+            ReplaySpeed::Multiplier(_) => 0,
+        }
+    }
+}
+
+/// Source of the replayer's timestamps, in nanoseconds
+pub type ReplayClock = Box<dyn Fn() -> u64 + Send>;
+
+/// Delivers timestamped records in order, paced by a `ReplaySpeed`
+pub struct Replayer<T> {
+    /// Records not yet delivered, with their recorded timestamps
+    records: VecDeque<(u64, T)>,
+    /// Pacing of deliveries
+    speed: ReplaySpeed,
+    /// Timestamp source for pacing
+    clock: ReplayClock,
+    /// Replay-clock time and recorded timestamp of the first delivery
+    origin: Option<(u64, u64)>,
+    /// Records delivered so far
+    delivered: u64,
+}
+
+impl<T> Replayer<T> {
+    /// Creates a replayer over records ordered by recorded timestamp
+    ///
+    /// # Arguments
+    /// * `records` - (recorded timestamp in nanoseconds, record) pairs
+    /// * `speed` - Pacing of deliveries
+    pub fn new(records: impl IntoIterator<Item = (u64, T)>, speed: ReplaySpeed) -> Self {
+        Self {
+            records: records.into_iter().collect(),
+            speed,
+            clock: Box::new(|| monotonic_nanos().as_u64()),
+            origin: None,
+            delivered: 0,
+        }
+    }
+
+    /// Replaces the replayer's clock
+    ///
+    /// Useful for simulations and tests that need to control time.
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Returns the pacing of deliveries
+    #[inline]
+    pub fn speed(&self) -> ReplaySpeed {
+        self.speed
+    }
+
+    /// Returns the replay-clock time the next record is due
+    ///
+    /// # Returns
+    /// The due time, or None if every record has been delivered. Before the
+    /// first delivery the next record is due immediately.
+    pub fn next_due_ns(&self) -> Option<u64> {
+        let &(recorded_ns, _) = self.records.front()?;
+        Some(match self.origin {
+            Some((origin_clock_ns, origin_recorded_ns)) => {
+                origin_clock_ns + self.speed.scaled_gap(recorded_ns.saturating_sub(origin_recorded_ns))
+            }
+            None => (self.clock)(),
+        })
+    }
+
+    /// Returns the next record if it is due
+    ///
+    /// The first record is due on the first poll; each later record is due
+    /// its scaled recorded gap after the first. A record timestamped before
+    /// its predecessor is due at once.
+    pub fn poll(&mut self) -> Option<T> {
+        let now = (self.clock)();
+        let &(recorded_ns, _) = self.records.front()?;
+        let (origin_clock_ns, origin_recorded_ns) = *self.origin.get_or_insert((now, recorded_ns));
+        let due_ns = origin_clock_ns + self.speed.scaled_gap(recorded_ns.saturating_sub(origin_recorded_ns));
+        if now < due_ns {
+            return None;
+        }
+
+        self.delivered += 1;
+        self.records.pop_front().map(|(_, record)| record)
+    }
+
+    /// Returns the number of records delivered so far
+    #[inline]
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Returns the number of records not yet delivered
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns true once every record has been delivered
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    /// Ten records 1µs apart
+    fn records() -> Vec<(u64, u32)> {
+        (0..10).map(|i| (5_000 + i as u64 * 1_000, i)).collect()
+    }
+
+    fn mock_clock() -> (Arc<AtomicU64>, impl Fn() -> u64 + Send + 'static) {
+        let now = Arc::new(AtomicU64::new(1_000_000));
+        let clock = Arc::clone(&now);
+        (now, move || clock.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn test_as_fast_as_possible_delivers_everything_at_once() {
+        let (_, clock) = mock_clock();
+        let mut replayer = Replayer::new(records(), ReplaySpeed::default()).with_clock(clock);
+
+        let delivered: Vec<u32> = std::iter::from_fn(|| replayer.poll()).collect();
+        assert_eq!(delivered, (0..10).collect::<Vec<_>>());
+        assert!(replayer.is_finished());
+        assert_eq!(replayer.delivered(), 10);
+    }
+
+    #[test]
+    fn test_real_time_keeps_recorded_gaps() {
+        let (now, clock) = mock_clock();
+        let mut replayer = Replayer::new(records(), ReplaySpeed::RealTime).with_clock(clock);
+
+        assert_eq!(replayer.poll(), Some(0));
+        for i in 1..10 {
+            // Not before its gap has passed on the replay clock
+            assert_eq!(replayer.next_due_ns(), Some(1_000_000 + i as u64 * 1_000));
+            now.fetch_add(999, Ordering::Relaxed);
+            assert_eq!(replayer.poll(), None);
+            now.fetch_add(1, Ordering::Relaxed);
+            assert_eq!(replayer.poll(), Some(i));
+        }
+        assert_eq!(replayer.poll(), None);
+        assert_eq!(replayer.next_due_ns(), None);
+    }
+
+    #[test]
+    fn test_multiplier_scales_gaps() {
+        let (now, clock) = mock_clock();
+        let mut replayer = Replayer::new(records(), ReplaySpeed::Multiplier(4.0)).with_clock(clock);
+
+        assert_eq!(replayer.poll(), Some(0));
+        now.fetch_add(249, Ordering::Relaxed);
+        assert_eq!(replayer.poll(), None);
+        now.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(replayer.poll(), Some(1));
+        assert_eq!(ReplaySpeed::Multiplier(0.0).scaled_gap(1_000), 0);
+    }
+}
@@ -0,0 +1,183 @@
+// Bounded single-producer single-consumer ring buffer
+//
+// Hands data from one component thread to another, e.g. a market data thread
+// feeding a strategy thread, without locks or allocation. The ring and its
+// cache-line padded head and tail indices are those of `LFQueue`; this type
+// gives them the `Option`-based interface component hand-off uses.
+
+use crate::lf_queue::LFQueue;
+
+/// A bounded lock-free single-producer single-consumer ring buffer.
+///
+/// # Type Parameters
+/// - `T`: The type of elements stored in the buffer
+/// - `N`: The capacity of the buffer (must be a power of 2)
+///
+/// # Safety
+/// - Only one thread may call `push` (the producer)
+/// - Only one thread may call `pop` (the consumer)
+///
+/// # Example
+/// ```
+/// use common::spsc::RingBuffer;
+///
+/// let ring: RingBuffer<u32, 2> = RingBuffer::new();
+/// assert_eq!(ring.push(1), None);
+/// assert_eq!(ring.push(2), None);
+/// // Full: the item is handed back
+/// assert_eq!(ring.push(3), Some(3));
+/// assert_eq!(ring.pop(), Some(1));
+/// ```
+pub struct RingBuffer<T, const N: usize> {
+    queue: LFQueue<T, N>,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// Creates a new empty ring buffer.
+    ///
+    /// # Panics
+    /// Panics if N is not a power of 2 or if N is 0.
+    pub fn new() -> Self {
+        Self { queue: LFQueue::new() }
+    }
+
+    /// Attempts to push an item onto the ring buffer.
+    ///
+    /// # Returns
+    /// * `None` if the item was pushed
+    /// * `Some(item)` if the buffer is full, returning ownership of the item
+    ///
+    /// # Safety
+    /// This method must only be called from the producer thread.
+    #[inline]
+    pub fn push(&self, item: T) -> Option<T> {
+        self.queue.push(item).err()
+    }
+
+    /// Attempts to pop the oldest item from the ring buffer.
+    ///
+    /// # Returns
+    /// * `Some(item)` if an item was available
+    /// * `None` if the buffer is empty
+    ///
+    /// # Safety
+    /// This method must only be called from the consumer thread.
+    #[inline]
+    pub fn pop(&self) -> Option<T> {
+        self.queue.pop()
+    }
+
+    /// Returns the current number of items in the buffer.
+    ///
+    /// Note: This is an approximation in a concurrent context.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if the buffer is empty.
+    ///
+    /// Note: This is an approximation in a concurrent context.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns true if the buffer is full.
+    ///
+    /// Note: This is an approximation in a concurrent context.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.queue.is_full()
+    }
+
+    /// Returns the capacity of the buffer.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_full_and_empty_boundaries_across_wraparound() {
+        let ring: RingBuffer<u32, 4> = RingBuffer::new();
+        assert_eq!(ring.capacity(), 4);
+
+        // Offset the indices so the boundaries fall mid-buffer
+        for i in 0..3 {
+            assert_eq!(ring.push(i), None);
+            assert_eq!(ring.pop(), Some(i));
+        }
+
+        // Exactly N items fit, the next is handed back
+        for i in 0..4 {
+            assert_eq!(ring.push(i), None);
+        }
+        assert!(ring.is_full());
+        assert_eq!(ring.push(4), Some(4));
+
+        // One slot freed admits exactly one more item
+        assert_eq!(ring.pop(), Some(0));
+        assert!(!ring.is_full());
+        assert_eq!(ring.push(4), None);
+        assert_eq!(ring.push(5), Some(5));
+
+        // Draining stops at exactly the items pushed
+        for i in 1..=4 {
+            assert_eq!(ring.pop(), Some(i));
+        }
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn test_producer_consumer_threads_lose_and_reorder_nothing() {
+        const ITEMS: u64 = 1_000_000;
+        let ring: Arc<RingBuffer<u64, 1024>> = Arc::new(RingBuffer::new());
+
+        let producer = {
+            let ring = Arc::clone(&ring);
+            thread::spawn(move || {
+                for i in 0..ITEMS {
+                    let mut item = i;
+                    while let Some(rejected) = ring.push(item) {
+                        item = rejected;
+                        std::hint::spin_loop();
+                    }
+                }
+            })
+        };
+        let consumer = {
+            let ring = Arc::clone(&ring);
+            thread::spawn(move || {
+                let mut expected = 0;
+                while expected < ITEMS {
+                    match ring.pop() {
+                        Some(item) => {
+                            assert_eq!(item, expected);
+                            expected += 1;
+                        }
+                        None => std::hint::spin_loop(),
+                    }
+                }
+                expected
+            })
+        };
+
+        producer.join().unwrap();
+        assert_eq!(consumer.join().unwrap(), ITEMS);
+        assert!(ring.is_empty());
+    }
+}
@@ -1,6 +1,25 @@
 // Timing utilities for low-latency measurement
-
-use std::time::Instant;
+//
+// Two clocks are available. The monotonic clock (`monotonic_nanos`, also
+// `now_nanos`) counts from an arbitrary per-process epoch and never goes
+// backwards, so it is the one to use for intervals: latency, rate limiting,
+// cooldowns and timeouts. The realtime clock (`realtime_nanos`) is wall-clock
+// time since the Unix epoch, comparable across processes and hosts, and is
+// the one to use for timestamps that end up in logs and records. It can jump
+// when the system clock is adjusted, so never subtract two realtime readings
+// to time something. `ClockSource` picks one of them where it is configurable.
+//
+// Components that time intervals can take a `Clock` instead of reading the
+// monotonic clock directly. `SystemClock` is the monotonic clock;
+// `ManualClock` only moves when told to, which makes tests and backtests
+// deterministic and lets them skip past intervals without sleeping.
+//
+// `LatencyStats` keeps count, mean, min and max. `LatencyHistogram` also
+// answers percentiles: it buckets each sample by its power of two, split into
+// 16 sub-buckets, so a percentile is within about 6% of the true value.
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
 
 /// Global anchor point for converting Instant to nanoseconds
@@ -70,11 +89,126 @@ impl From<Nanos> for u64 {
 /// Uses std::time::Instant for monotonic time
 #[inline]
 pub fn now_nanos() -> Nanos {
+    monotonic_nanos()
+}
+
+/// Get monotonic time in nanoseconds since an arbitrary per-process epoch
+/// Unaffected by wall-clock adjustments; use it for intervals and latency
+#[inline]
+pub fn monotonic_nanos() -> Nanos {
     let epoch = get_epoch();
     let elapsed = Instant::now().duration_since(*epoch);
     Nanos(elapsed.as_nanos() as u64)
 }
 
+/// Get wall-clock time in nanoseconds since the Unix epoch
+/// May jump with clock adjustments; use it for log and record timestamps.
+/// Returns 0 if the system clock is set before the Unix epoch.
+#[inline]
+pub fn realtime_nanos() -> Nanos {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(Nanos(0), |elapsed| Nanos(elapsed.as_nanos() as u64))
+}
+
+/// Which clock a timestamp is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockSource {
+    /// Monotonic time since an arbitrary per-process epoch
+    #[default]
+    Monotonic,
+    /// Wall-clock time since the Unix epoch
+    Realtime,
+}
+
+impl ClockSource {
+    /// Read the current time from this clock
+    #[inline]
+    pub fn now(self) -> Nanos {
+        match self {
+            ClockSource::Monotonic => monotonic_nanos(),
+            ClockSource::Realtime => realtime_nanos(),
+        }
+    }
+
+    /// Returns the name of the clock, as accepted by `from_str`
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ClockSource::Monotonic => "monotonic",
+            ClockSource::Realtime => "realtime",
+        }
+    }
+}
+
+impl std::str::FromStr for ClockSource {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "monotonic" => Ok(ClockSource::Monotonic),
+            "realtime" | "wall" => Ok(ClockSource::Realtime),
+            other => Err(format!("unknown clock '{}', expected monotonic or realtime", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A source of monotonic time that can be injected for testing
+pub trait Clock: Send + Sync {
+    /// Current time in nanoseconds, never going backwards
+    fn now_nanos(&self) -> Nanos;
+}
+
+/// The process's monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now_nanos(&self) -> Nanos {
+        monotonic_nanos()
+    }
+}
+
+/// A clock that stands still until set or advanced
+///
+/// Shared through an `Arc`, a test can advance the clock a component reads.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    nanos: AtomicU64,
+}
+
+impl ManualClock {
+    /// Create a clock reading `start`
+    pub fn new(start: Nanos) -> Self {
+        Self { nanos: AtomicU64::new(start.0) }
+    }
+
+    /// Move the clock forward by `nanos`
+    #[inline]
+    pub fn advance(&self, nanos: u64) {
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Set the clock to `now`, ignored if that would move it backwards
+    #[inline]
+    pub fn set(&self, now: Nanos) {
+        self.nanos.fetch_max(now.0, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    #[inline]
+    fn now_nanos(&self) -> Nanos {
+        Nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
 /// Calculate elapsed nanoseconds since the given start time
 #[inline]
 pub fn nanos_since(start: Nanos) -> u64 {
@@ -158,6 +292,112 @@ impl LatencyStats {
     }
 }
 
+/// Sub-buckets per power of two in `LatencyHistogram`, as a bit count
+const HISTOGRAM_SUB_BUCKET_BITS: u32 = 4;
+
+/// Sub-buckets per power of two in `LatencyHistogram`
+const HISTOGRAM_SUB_BUCKETS: u64 = 1 << HISTOGRAM_SUB_BUCKET_BITS;
+
+/// Log-bucketed latency histogram for percentile reporting
+///
+/// Values below 16 get a bucket each; larger values share a bucket with
+/// values that agree in their top five bits. Buckets are allocated as larger
+/// values arrive, up to 976 for the full `u64` range.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    max: u64,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            buckets: Vec::new(),
+            count: 0,
+            max: 0,
+        }
+    }
+
+    /// Record a latency measurement in nanoseconds
+    #[inline]
+    pub fn record(&mut self, latency_nanos: u64) {
+        let index = Self::bucket_index(latency_nanos);
+        if index >= self.buckets.len() {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.max = self.max.max(latency_nanos);
+    }
+
+    /// Get the number of recorded measurements
+    #[inline]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Get the maximum recorded latency in nanoseconds
+    /// Returns 0 if no measurements have been recorded
+    #[inline]
+    pub const fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Get the latency at a quantile, e.g. 0.99 for p99
+    ///
+    /// Reports the top of the bucket holding the quantile, capped at the
+    /// maximum recorded value. Returns 0 if no measurements have been recorded
+    pub fn quantile(&self, quantile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &bucket) in self.buckets.iter().enumerate() {
+            seen += bucket;
+            if seen >= rank {
+                return Self::bucket_high(index).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Reset all statistics
+    #[inline]
+    pub fn reset(&mut self) {
+        self.buckets.clear();
+        self.count = 0;
+        self.max = 0;
+    }
+
+    /// Bucket holding a value
+    #[inline]
+    fn bucket_index(value: u64) -> usize {
+        if value < HISTOGRAM_SUB_BUCKETS {
+            return value as usize;
+        }
+        let exponent = 63 - value.leading_zeros();
+        let shift = exponent - HISTOGRAM_SUB_BUCKET_BITS;
+        let sub_bucket = (value >> shift) & (HISTOGRAM_SUB_BUCKETS - 1);
+        (((shift + 1) as u64) << HISTOGRAM_SUB_BUCKET_BITS | sub_bucket) as usize
+    }
+
+    /// Largest value that falls in a bucket
+    #[inline]
+    fn bucket_high(index: usize) -> u64 {
+        let index = index as u64;
+        if index < HISTOGRAM_SUB_BUCKETS {
+            return index;
+        }
+        let shift = (index >> HISTOGRAM_SUB_BUCKET_BITS) - 1;
+        let low = (HISTOGRAM_SUB_BUCKETS | (index & (HISTOGRAM_SUB_BUCKETS - 1))) << shift;
+        low + ((1 << shift) - 1)
+    }
+}
+
 /// Read the CPU Time Stamp Counter (TSC)
 /// This provides very low overhead cycle counting for latency measurement
 /// Note: TSC frequency may vary; use for relative measurements
@@ -287,6 +527,40 @@ mod tests {
         assert!(t2 > t1, "Time should advance");
     }
 
+    #[test]
+    fn test_clock_sources() {
+        // Monotonic time is since process start, realtime since 1970
+        let monotonic = ClockSource::Monotonic.now();
+        let realtime = ClockSource::Realtime.now();
+        assert!(realtime > monotonic);
+        let system = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        assert!(system.abs_diff(realtime.as_u64()) < 1_000_000_000);
+        assert!(monotonic_nanos() >= monotonic);
+
+        assert_eq!("monotonic".parse::<ClockSource>(), Ok(ClockSource::Monotonic));
+        assert_eq!(" Realtime ".parse::<ClockSource>(), Ok(ClockSource::Realtime));
+        assert_eq!("wall".parse::<ClockSource>(), Ok(ClockSource::Realtime));
+        assert!("tsc".parse::<ClockSource>().is_err());
+        assert_eq!(ClockSource::default(), ClockSource::Monotonic);
+        assert_eq!(ClockSource::Realtime.to_string(), "realtime");
+    }
+
+    #[test]
+    fn test_injectable_clocks() {
+        let clock = ManualClock::new(Nanos(1_000));
+        assert_eq!(clock.now_nanos(), Nanos(1_000));
+        clock.advance(500);
+        assert_eq!(clock.now_nanos(), Nanos(1_500));
+        clock.set(Nanos(10_000));
+        assert_eq!(clock.now_nanos(), Nanos(10_000));
+        // Never backwards
+        clock.set(Nanos(2_000));
+        assert_eq!(clock.now_nanos(), Nanos(10_000));
+
+        let before = monotonic_nanos();
+        assert!(SystemClock.now_nanos() >= before);
+    }
+
     #[test]
     fn test_nanos_since() {
         let start = now_nanos();
@@ -352,6 +626,41 @@ mod tests {
         assert_eq!(stats.count(), 0);
     }
 
+    #[test]
+    fn test_latency_histogram_quantiles() {
+        let mut hist = LatencyHistogram::new();
+        assert_eq!(hist.quantile(0.5), 0);
+
+        // Small values are exact
+        for latency in 1..=10 {
+            hist.record(latency);
+        }
+        assert_eq!(hist.quantile(0.5), 5);
+        assert_eq!(hist.quantile(1.0), 10);
+
+        // Larger values land within a bucket's width, capped at the max
+        for _ in 0..990 {
+            hist.record(1_000);
+        }
+        hist.record(1_000_000);
+        assert_eq!(hist.count(), 1_001);
+        let p50 = hist.quantile(0.5);
+        assert!((1_000..1_000 + 1_000 / 16).contains(&p50));
+        assert_eq!(hist.quantile(1.0), 1_000_000);
+        assert_eq!(hist.max(), 1_000_000);
+
+        // Every bucket's top maps back to the same bucket
+        for value in [15, 16, 17, 31, 32, 1_023, 1_024, u64::MAX / 3, u64::MAX] {
+            let index = LatencyHistogram::bucket_index(value);
+            assert!(LatencyHistogram::bucket_high(index) >= value);
+            assert_eq!(LatencyHistogram::bucket_index(LatencyHistogram::bucket_high(index)), index);
+        }
+
+        hist.reset();
+        assert_eq!(hist.count(), 0);
+        assert_eq!(hist.quantile(0.99), 0);
+    }
+
     #[test]
     fn test_scoped_timer() {
         let mut stats = LatencyStats::new();
@@ -393,10 +702,10 @@ mod tests {
         }
 
         let elapsed = timer.elapsed();
-        assert!(elapsed > 0 || elapsed == 0, "elapsed() should return a value");
 
         drop(timer);
         assert_eq!(stats.count(), 1);
+        assert!(stats.mean() >= elapsed as f64, "the recorded time covers the elapsed read");
     }
 
     #[cfg(target_arch = "x86_64")]
@@ -15,6 +15,12 @@ pub const INVALID_CLIENT_ID: ClientId = u32::MAX;
 pub const INVALID_PRICE: Price = i64::MAX;
 pub const INVALID_QTY: Qty = u32::MAX;
 
+/// Decimal places of an integer price when no instrument spec says otherwise
+pub const DEFAULT_PRICE_SCALE: u8 = 2;
+
+/// Largest supported price scale, the most decimal places an i64 can carry
+pub const MAX_PRICE_SCALE: u8 = 18;
+
 /// Represents the side of an order (buy or sell)
 #[repr(i8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -40,6 +46,170 @@ impl Side {
     }
 }
 
+/// Static description of an instrument's prices and order sizes
+///
+/// Prices travel as integers; `price_scale` is the number of implied decimal
+/// places, so 12345 is 123.45 at scale 2 and 1.2345 at scale 4. Notional is
+/// always reported in cents so values can be compared across instruments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstrumentSpec {
+    /// Instrument the spec describes
+    pub ticker_id: TickerId,
+    /// Implied decimal places of an integer price
+    pub price_scale: u8,
+    /// Smallest price increment, in integer price units
+    pub tick_size: Price,
+    /// Order quantities must be a multiple of this
+    pub lot_size: Qty,
+    /// Smallest order notional in cents (0 = no minimum)
+    pub min_notional: i64,
+    /// Largest order notional in cents (0 = no maximum)
+    pub max_notional: i64,
+    /// Decimal places prices are displayed with
+    pub display_decimals: u8,
+}
+
+impl InstrumentSpec {
+    /// Create a spec with the default scale, one-unit ticks and lots, and
+    /// no notional bounds
+    pub fn new(ticker_id: TickerId) -> Self {
+        Self {
+            ticker_id,
+            price_scale: DEFAULT_PRICE_SCALE,
+            tick_size: 1,
+            lot_size: 1,
+            min_notional: 0,
+            max_notional: 0,
+            display_decimals: DEFAULT_PRICE_SCALE,
+        }
+    }
+
+    /// Set the number of implied decimal places, capped at MAX_PRICE_SCALE
+    ///
+    /// Prices are displayed with the same number of decimals unless
+    /// `with_display_decimals` is applied afterwards.
+    pub fn with_price_scale(mut self, price_scale: u8) -> Self {
+        self.price_scale = price_scale.min(MAX_PRICE_SCALE);
+        self.display_decimals = self.price_scale;
+        self
+    }
+
+    /// Set the tick size; values below one unit are raised to one
+    pub fn with_tick_size(mut self, tick_size: Price) -> Self {
+        self.tick_size = tick_size.max(1);
+        self
+    }
+
+    /// Set the lot size; values below one are raised to one
+    pub fn with_lot_size(mut self, lot_size: Qty) -> Self {
+        self.lot_size = lot_size.max(1);
+        self
+    }
+
+    /// Set the order notional bounds in cents (0 = unbounded)
+    pub fn with_notional_limits(mut self, min_notional: i64, max_notional: i64) -> Self {
+        self.min_notional = min_notional.max(0);
+        self.max_notional = max_notional.max(0);
+        self
+    }
+
+    /// Set the decimal places prices are displayed with, capped at MAX_PRICE_SCALE
+    pub fn with_display_decimals(mut self, display_decimals: u8) -> Self {
+        self.display_decimals = display_decimals.min(MAX_PRICE_SCALE);
+        self
+    }
+
+    /// Returns 10 raised to the price scale
+    #[inline]
+    pub fn scale_factor(&self) -> i64 {
+        10i64.pow(self.price_scale as u32)
+    }
+
+    /// Converts an integer price to its decimal value
+    #[inline]
+    pub fn to_decimal(&self, price: Price) -> f64 {
+        price as f64 / self.scale_factor() as f64
+    }
+
+    /// Converts a decimal value to the nearest integer price
+    #[inline]
+    pub fn from_decimal(&self, value: f64) -> Price {
+        (value * self.scale_factor() as f64).round() as Price
+    }
+
+    /// Returns true if the price lies on the tick grid
+    #[inline]
+    pub fn is_valid_tick(&self, price: Price) -> bool {
+        price.rem_euclid(self.tick_size) == 0
+    }
+
+    /// Rounds a price onto the tick grid without crossing it: down for a buy,
+    /// up for a sell
+    #[inline]
+    pub fn round_to_tick(&self, price: Price, side: Side) -> Price {
+        let below = price - price.rem_euclid(self.tick_size);
+        match side {
+            Side::Buy => below,
+            Side::Sell if below == price => price,
+            Side::Sell => below + self.tick_size,
+        }
+    }
+
+    /// Returns true if the quantity is a whole number of lots
+    #[inline]
+    pub fn is_valid_lot(&self, qty: Qty) -> bool {
+        qty.is_multiple_of(self.lot_size)
+    }
+
+    /// Rounds a quantity down to a whole number of lots
+    #[inline]
+    pub fn round_to_lot(&self, qty: Qty) -> Qty {
+        qty - qty % self.lot_size
+    }
+
+    /// Returns true if the notional of `qty` at `price` lies within the
+    /// instrument's notional bounds
+    pub fn is_valid_notional(&self, price: Price, qty: Qty) -> bool {
+        let notional = self.notional_cents(price, qty);
+        notional >= self.min_notional && (self.max_notional == 0 || notional <= self.max_notional)
+    }
+
+    /// Returns the notional of `qty` at `price` in cents, rounded to the nearest cent
+    pub fn notional_cents(&self, price: Price, qty: Qty) -> i64 {
+        let raw = price as i128 * qty as i128 * 100;
+        let factor = self.scale_factor() as i128;
+        let half = factor / 2;
+        let cents = if raw >= 0 { (raw + half) / factor } else { (raw - half) / factor };
+        cents.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    /// Formats an integer price with the instrument's display decimals,
+    /// rounding half away from zero when they are fewer than the price scale
+    pub fn format_price(&self, price: Price) -> String {
+        let abs = price.unsigned_abs() as u128;
+        let display = self.display_decimals as u32;
+        let scale = self.price_scale as u32;
+        let abs = if display >= scale {
+            abs * 10u128.pow(display - scale)
+        } else {
+            let divisor = 10u128.pow(scale - display);
+            (abs + divisor / 2) / divisor
+        };
+        let sign = if price < 0 && abs != 0 { "-" } else { "" };
+        if display == 0 {
+            return format!("{}{}", sign, abs);
+        }
+        let factor = 10u128.pow(display);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            abs / factor,
+            abs % factor,
+            width = display as usize
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,4 +234,68 @@ mod tests {
         assert_eq!(INVALID_PRICE, i64::MAX);
         assert_eq!(INVALID_QTY, u32::MAX);
     }
+
+    #[test]
+    fn test_instrument_spec_conversions() {
+        let cents = InstrumentSpec::new(1);
+        assert_eq!(cents.price_scale, DEFAULT_PRICE_SCALE);
+        assert_eq!(cents.format_price(12345), "123.45");
+        assert_eq!(cents.format_price(-5), "-0.05");
+        assert_eq!(cents.from_decimal(123.45), 12345);
+
+        let fine = InstrumentSpec::new(2).with_price_scale(4).with_tick_size(5);
+        assert_eq!(fine.scale_factor(), 10_000);
+        assert_eq!(fine.format_price(12345), "1.2345");
+        assert!((fine.to_decimal(12345) - 1.2345).abs() < 1e-12);
+        assert!(fine.is_valid_tick(12340));
+        assert!(!fine.is_valid_tick(12342));
+
+        assert_eq!(InstrumentSpec::new(3).with_price_scale(0).format_price(42), "42");
+        assert_eq!(InstrumentSpec::new(4).with_price_scale(40).price_scale, MAX_PRICE_SCALE);
+    }
+
+    #[test]
+    fn test_instrument_spec_notional_cents() {
+        let cents = InstrumentSpec::new(1);
+        let fine = InstrumentSpec::new(2).with_price_scale(4);
+
+        // Same integer price: $123.45 vs $1.2345, times 100
+        assert_eq!(cents.notional_cents(12345, 100), 1_234_500);
+        assert_eq!(fine.notional_cents(12345, 100), 12_345);
+        // Sub-cent notional rounds to the nearest cent
+        assert_eq!(fine.notional_cents(12345, 1), 123);
+    }
+
+    #[test]
+    fn test_instrument_spec_ticks_lots_and_bounds() {
+        let spec = InstrumentSpec::new(1)
+            .with_tick_size(5)
+            .with_lot_size(10)
+            .with_notional_limits(10_000, 1_000_000);
+
+        assert_eq!(spec.round_to_tick(10_003, Side::Buy), 10_000);
+        assert_eq!(spec.round_to_tick(10_003, Side::Sell), 10_005);
+        assert_eq!(spec.round_to_tick(10_005, Side::Sell), 10_005);
+        assert_eq!(spec.round_to_tick(-3, Side::Buy), -5);
+
+        assert!(spec.is_valid_lot(30));
+        assert!(!spec.is_valid_lot(35));
+        assert_eq!(spec.round_to_lot(35), 30);
+
+        // $100 minimum and $10,000 maximum at $100.00
+        assert!(!spec.is_valid_notional(10_000, 0));
+        assert!(spec.is_valid_notional(10_000, 1));
+        assert!(spec.is_valid_notional(10_000, 100));
+        assert!(!spec.is_valid_notional(10_000, 101));
+    }
+
+    #[test]
+    fn test_instrument_spec_display_decimals() {
+        let spec = InstrumentSpec::new(1).with_price_scale(4);
+        assert_eq!(spec.display_decimals, 4);
+        assert_eq!(spec.with_display_decimals(2).format_price(12_355), "1.24");
+        assert_eq!(spec.with_display_decimals(2).format_price(-12_355), "-1.24");
+        assert_eq!(spec.with_display_decimals(0).format_price(-4_000), "0");
+        assert_eq!(spec.with_display_decimals(6).format_price(12_345), "1.234500");
+    }
 }
@@ -292,7 +292,7 @@ fn bench_market_data_serialization(c: &mut Criterion) {
             100,
         );
         b.iter(|| {
-            let mut send_buffer = [0u8; 64];
+            let mut send_buffer = [0u8; CLIENT_RESPONSE_SIZE];
             let bytes = response.as_bytes();
             send_buffer[..CLIENT_RESPONSE_SIZE].copy_from_slice(bytes);
             black_box(send_buffer)
@@ -342,7 +342,7 @@ fn bench_full_e2e_flow(c: &mut Criterion) {
         let request_bytes = request.as_bytes();
 
         let mut recv_buffer = [0u8; 64];
-        let mut response_buffer = [0u8; 64];
+        let mut response_buffer = [0u8; CLIENT_RESPONSE_SIZE];
         let mut market_buffer = [0u8; 64];
 
         recv_buffer[..CLIENT_REQUEST_SIZE].copy_from_slice(request_bytes);
@@ -254,7 +254,7 @@ fn bench_mixed_workload(c: &mut Criterion) {
                     let result = order_book.add_order(
                         black_box(100),
                         black_box(order_id),
-                        black_box(if order_id % 2 == 0 { Side::Buy } else { Side::Sell }),
+                        black_box(if order_id.is_multiple_of(2) { Side::Buy } else { Side::Sell }),
                         black_box(10000 + (order_id as i64 % 100)),
                         black_box(100),
                     );
@@ -263,7 +263,7 @@ fn bench_mixed_workload(c: &mut Criterion) {
                 }
                 7 | 8 => {
                     // 20% lookup
-                    if iteration % 2 == 0 {
+                    if iteration.is_multiple_of(2) {
                         black_box(order_book.best_bid());
                     } else {
                         black_box(order_book.best_ask());
@@ -271,7 +271,7 @@ fn bench_mixed_workload(c: &mut Criterion) {
                 }
                 _ => {
                     // 10% cancel (will mostly fail since cancel isn't fully implemented)
-                    let cancel_id = if order_id > 10 { order_id - 10 } else { 0 };
+                    let cancel_id = order_id.saturating_sub(10);
                     black_box(order_book.cancel_order(black_box(cancel_id)));
                 }
             }
@@ -0,0 +1,112 @@
+//! Matching engine stress test.
+//!
+//! Drives a seeded synthetic order stream through a MatchingEngine and
+//! reports throughput and latency percentiles, validating the books as it
+//! goes.
+
+use clap::Parser;
+use exchange::matching_engine::MatchingEngine;
+use exchange::stress::{run_stress, PriceDistribution, RequestMix, StressConfig};
+
+/// Stress test for the matching engine
+#[derive(Parser, Debug)]
+#[command(name = "stress")]
+#[command(about = "Synthetic order flow stress test for the matching engine")]
+struct Args {
+    /// Seed of the request stream
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Number of requests to process
+    #[arg(short, long, default_value_t = 1_000_000)]
+    requests: usize,
+
+    /// Number of tickers
+    #[arg(long, default_value_t = 1)]
+    tickers: u32,
+
+    /// Number of clients
+    #[arg(long, default_value_t = 8)]
+    clients: u32,
+
+    /// Request mix weights as ADD,CANCEL,MODIFY,MARKETABLE
+    #[arg(long, default_value = "50,25,15,10")]
+    mix: String,
+
+    /// Furthest passive level from the mid, in ticks
+    #[arg(long, default_value_t = 50)]
+    depth_ticks: u32,
+
+    /// Mean distance of passive orders from the mid, in ticks (0 for uniform)
+    #[arg(long, default_value_t = 5.0)]
+    mean_ticks: f64,
+
+    /// Largest order quantity
+    #[arg(long, default_value_t = 100)]
+    max_qty: u32,
+
+    /// Validate the books every this many requests (0 only at the end)
+    #[arg(long, default_value_t = 10_000)]
+    validate_every: usize,
+}
+
+/// Parse "ADD,CANCEL,MODIFY,MARKETABLE" weights
+fn parse_mix(mix: &str) -> Option<RequestMix> {
+    let weights: Vec<u32> = mix
+        .split(',')
+        .map(|w| w.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match weights[..] {
+        [add, cancel, modify, marketable] if add + cancel + modify + marketable > 0 => {
+            Some(RequestMix::new(add, cancel, modify, marketable))
+        }
+        _ => None,
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let Some(mix) = parse_mix(&args.mix) else {
+        eprintln!("Error: Invalid mix '{}'", args.mix);
+        std::process::exit(1);
+    };
+    let distribution = if args.mean_ticks > 0.0 {
+        PriceDistribution::Exponential { mean_ticks: args.mean_ticks }
+    } else {
+        PriceDistribution::Uniform
+    };
+    let config = StressConfig::new()
+        .with_seed(args.seed)
+        .with_requests(args.requests)
+        .with_participants(args.tickers, args.clients)
+        .with_mix(mix)
+        .with_prices(10_000, 1, args.depth_ticks)
+        .with_price_distribution(distribution)
+        .with_qty_range(1, args.max_qty)
+        .with_validate_every(args.validate_every);
+
+    println!("Running {} requests (seed {})...", args.requests, args.seed);
+    let mut engine = MatchingEngine::new();
+    let report = match run_stress(&mut engine, &config) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Invariant violation {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "  Requests:    {} ({} new, {} cancels, {} modifies, {} rejected)",
+        report.requests, report.new_orders, report.cancels, report.modifies, report.rejects
+    );
+    println!("  Maker fills: {}", report.maker_fills);
+    println!("  Updates:     {}", report.market_updates);
+    println!("  Resting:     {}", report.resting_orders);
+    println!("  Throughput:  {:.0} requests/s", report.throughput());
+    let latency = report.latency;
+    println!(
+        "  Latency ns:  p50 {} p90 {} p99 {} p99.9 {} max {} mean {}",
+        latency.p50, latency.p90, latency.p99, latency.p999, latency.max, latency.mean
+    );
+}
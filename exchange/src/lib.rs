@@ -3,3 +3,4 @@ pub mod order_book;
 pub mod matching_engine;
 pub mod order_server;
 pub mod market_data;
+pub mod stress;
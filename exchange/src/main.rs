@@ -6,13 +6,19 @@
 //! - MarketDataPublisher: Multicast market data feed
 
 use clap::Parser;
+use common::time::ClockSource;
+use common::InstrumentRegistry;
 use exchange::market_data::{MarketDataPublisher, MarketDataPublisherConfig};
-use exchange::matching_engine::MatchingEngine;
+use exchange::matching_engine::{
+    AonResidual, FlickerAction, FlickerConfig, MatchingEngine, MatchingEngineConfig,
+    ModifyCrossMode, PriorityPolicy, SelfTradePrevention,
+};
 use exchange::order_server::{OrderServer, OrderServerConfig};
+use exchange::protocol::ClientRequestType;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Exchange server for low-latency trading
 #[derive(Parser, Debug)]
@@ -42,6 +48,161 @@ struct Args {
     /// Multicast TTL (time-to-live)
     #[arg(long, default_value_t = 1)]
     ttl: u32,
+
+    /// Treat a resent client order ID as a duplicate instead of a new order
+    #[arg(long, default_value_t = false)]
+    dedup_orders: bool,
+
+    /// Accepted orders remembered for duplicate detection (0 uses the default)
+    #[arg(long, default_value_t = 0)]
+    dedup_orders_window: usize,
+
+    /// Last look window in microseconds (0 disables last look)
+    #[arg(long, default_value_t = 0)]
+    last_look_window_us: u64,
+
+    /// Largest adverse mid move, in cents, tolerated by last look
+    #[arg(long, default_value_t = 0)]
+    last_look_threshold: i64,
+
+    /// Trade a Modify that crosses the book instead of rejecting it
+    #[arg(long, default_value_t = false)]
+    match_on_modify: bool,
+
+    /// Keep time priority on a Modify that makes the price less aggressive
+    #[arg(long, default_value_t = false)]
+    keep_priority_on_less_aggressive: bool,
+
+    /// Let an Amend increase quantity, moving the order to the back of its queue
+    #[arg(long, default_value_t = false)]
+    amend_qty_up: bool,
+
+    /// Cancels of orders younger than this, in microseconds, count as
+    /// flicker (0 disables flicker detection)
+    #[arg(long, default_value_t = 0)]
+    flicker_lifetime_us: u64,
+
+    /// Window over which flicker cancels are counted, in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    flicker_window_ms: u64,
+
+    /// Flicker cancels per window that flag a client
+    #[arg(long, default_value_t = 100)]
+    flicker_threshold: u32,
+
+    /// Action against flagged clients: flag, throttle or disconnect
+    #[arg(long, default_value = "flag")]
+    flicker_action: String,
+
+    /// Self-trade prevention: off, cancel-resting or cancel-incoming
+    #[arg(long, default_value = "off")]
+    self_trade_prevention: String,
+
+    /// Route a ticker's market data to its own group, as TICKER=ADDR:PORT
+    /// (repeatable)
+    #[arg(long = "route", value_name = "TICKER=ADDR:PORT")]
+    routes: Vec<String>,
+
+    /// Require clients to log in; allows a client ID with its token, as
+    /// ID=TOKEN (repeatable)
+    #[arg(long = "credential", value_name = "ID=TOKEN")]
+    credentials: Vec<String>,
+
+    /// Timestamp each order through the server and engine and report a
+    /// latency breakdown
+    #[arg(long, default_value_t = false)]
+    timestamps: bool,
+
+    /// Track per-ticker order flow statistics and report them on shutdown
+    #[arg(long, default_value_t = false)]
+    order_flow_stats: bool,
+
+    /// Accept stop-market and stop-limit orders
+    #[arg(long, default_value_t = false)]
+    stop_orders: bool,
+
+    /// Accept all-or-none orders
+    #[arg(long, default_value_t = false)]
+    aon_orders: bool,
+
+    /// Cancel all-or-none orders that cannot fill on arrival instead of
+    /// resting them until they can
+    #[arg(long, default_value_t = false)]
+    aon_cancel_unfilled: bool,
+
+    /// Batch each client's responses into one write per cycle
+    #[arg(long, default_value_t = false)]
+    batch_responses: bool,
+
+    /// Sleep in epoll until a client socket is readable instead of
+    /// busy-polling (Linux only)
+    #[arg(long, default_value_t = false)]
+    blocking_poll: bool,
+
+    /// Longest an idle blocking poll waits before the loop runs its periodic
+    /// work, in microseconds
+    #[arg(long, default_value_t = 1000)]
+    poll_timeout_us: u64,
+
+    /// Resting orders each order book's pool is sized for
+    #[arg(long, default_value_t = 65536)]
+    max_orders: usize,
+
+    /// Most tickers the exchange accepts (0 for no limit)
+    #[arg(long, default_value_t = 0)]
+    max_tickers: usize,
+
+    /// Most distinct price levels a client may rest at per ticker (0 for no
+    /// limit)
+    #[arg(long, default_value_t = 0)]
+    max_levels_per_client: usize,
+
+    /// Fills retained per client for replay after a reconnect (0 disables)
+    #[arg(long, default_value_t = 0)]
+    fill_retention: usize,
+
+    /// New-order requests per client whose client order IDs the order server
+    /// checks for resends, dropping a resent one (0 disables)
+    #[arg(long, default_value_t = 0)]
+    request_dedup_window: usize,
+
+    /// Publish depth aggregated into price bands of this width, in cents
+    /// (0 publishes every order)
+    #[arg(long, default_value_t = 0)]
+    depth_band_width: i64,
+
+    /// UDP port late-joining subscribers request snapshots on (0 disables)
+    #[arg(long, default_value_t = 0)]
+    snapshot_request_port: u16,
+
+    /// Implied decimal places of a ticker's prices, as TICKER=DECIMALS
+    /// (repeatable, default 2)
+    #[arg(long = "price-scale", value_name = "TICKER=DECIMALS")]
+    price_scales: Vec<String>,
+
+    /// Instruments file of per-ticker tick size, lot size, price scale and
+    /// notional bounds; `--price-scale` overrides its scales
+    #[arg(long)]
+    instruments: Option<String>,
+
+    /// Accept mass quotes: many two-sided quotes replaced in one message
+    #[arg(long, default_value_t = false)]
+    mass_quotes: bool,
+
+    /// Compact fragmented order books after this long without requests, in
+    /// milliseconds (0 disables)
+    #[arg(long, default_value_t = 0)]
+    compact_idle_ms: u64,
+
+    /// Fragmentation ratio a book must exceed to be compacted (0 compacts any
+    /// fragmented book)
+    #[arg(long, default_value_t = 0.0)]
+    compaction_threshold: f64,
+
+    /// Clock the engine stamps fills with: realtime (wall clock, comparable
+    /// with clients in other processes) or monotonic
+    #[arg(long, default_value_t = ClockSource::Realtime)]
+    exec_clock: ClockSource,
 }
 
 fn parse_tickers(tickers_str: &str) -> Vec<u32> {
@@ -51,6 +212,40 @@ fn parse_tickers(tickers_str: &str) -> Vec<u32> {
         .collect()
 }
 
+fn parse_route(route: &str) -> Option<(u32, &str, u16)> {
+    let (ticker, destination) = route.split_once('=')?;
+    let (addr, port) = destination.rsplit_once(':')?;
+    Some((ticker.trim().parse().ok()?, addr.trim(), port.trim().parse().ok()?))
+}
+
+fn parse_price_scale(price_scale: &str) -> Option<(u32, u8)> {
+    let (ticker, decimals) = price_scale.split_once('=')?;
+    Some((ticker.trim().parse().ok()?, decimals.trim().parse().ok()?))
+}
+
+fn parse_credential(credential: &str) -> Option<(u32, u64)> {
+    let (client_id, token) = credential.split_once('=')?;
+    Some((client_id.trim().parse().ok()?, token.trim().parse().ok()?))
+}
+
+fn parse_flicker_action(action: &str) -> Option<FlickerAction> {
+    match action.trim() {
+        "flag" => Some(FlickerAction::Flag),
+        "throttle" => Some(FlickerAction::Throttle),
+        "disconnect" => Some(FlickerAction::Disconnect),
+        _ => None,
+    }
+}
+
+fn parse_self_trade_prevention(mode: &str) -> Option<SelfTradePrevention> {
+    match mode.trim() {
+        "off" => Some(SelfTradePrevention::Off),
+        "cancel-resting" => Some(SelfTradePrevention::CancelResting),
+        "cancel-incoming" => Some(SelfTradePrevention::CancelIncoming),
+        _ => None,
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
@@ -66,9 +261,37 @@ fn main() {
         std::process::exit(1);
     }
     println!("  Tickers: {:?}", tickers);
+    if args.max_tickers > 0 && tickers.len() > args.max_tickers {
+        eprintln!(
+            "Error: {} tickers configured but --max-tickers is {}",
+            tickers.len(),
+            args.max_tickers
+        );
+        std::process::exit(1);
+    }
+    println!("  Order pool: {} orders per book", args.max_orders);
 
     // Initialize components
-    let order_server_config = OrderServerConfig::new(&args.interface, args.port);
+    let mut order_server_config = OrderServerConfig::new(&args.interface, args.port)
+        .with_timestamps(args.timestamps)
+        .with_response_batching(args.batch_responses)
+        .with_fill_retention(args.fill_retention)
+        .with_request_dedup(args.request_dedup_window)
+        .with_blocking_poll(args.blocking_poll);
+    for credential in &args.credentials {
+        match parse_credential(credential) {
+            Some((client_id, token)) => {
+                order_server_config = order_server_config.with_credential(client_id, token);
+            }
+            None => {
+                eprintln!("Error: Invalid credential '{}', expected ID=TOKEN", credential);
+                std::process::exit(1);
+            }
+        }
+    }
+    if order_server_config.requires_login() {
+        println!("  Login required: {} client(s)", order_server_config.credentials.len());
+    }
     let mut order_server = match OrderServer::new(order_server_config) {
         Ok(server) => server,
         Err(e) => {
@@ -77,19 +300,133 @@ fn main() {
         }
     };
 
-    let mut matching_engine = MatchingEngine::new();
+    let mut engine_config = MatchingEngineConfig::new()
+        .with_duplicate_detection(args.dedup_orders)
+        .with_duplicate_window(args.dedup_orders_window)
+        .with_timestamps(args.timestamps)
+        .with_order_flow_stats(args.order_flow_stats)
+        .with_stop_orders(args.stop_orders)
+        .with_max_orders_per_book(args.max_orders)
+        .with_max_tickers(args.max_tickers)
+        .with_max_levels_per_client(args.max_levels_per_client)
+        .with_compaction_threshold(args.compaction_threshold)
+        .with_mass_quotes(args.mass_quotes)
+        .with_priority_policy(
+            PriorityPolicy::new().with_less_aggressive_keeps(args.keep_priority_on_less_aggressive),
+        );
+    if args.last_look_window_us > 0 {
+        engine_config = engine_config
+            .with_last_look(args.last_look_window_us * 1_000, args.last_look_threshold);
+        println!(
+            "  Last look: {}us window, {} threshold",
+            args.last_look_window_us, args.last_look_threshold
+        );
+    }
+    if args.match_on_modify {
+        engine_config = engine_config.with_modify_cross_mode(ModifyCrossMode::Match);
+    }
+    if args.amend_qty_up {
+        engine_config = engine_config.with_amend_qty_up(true);
+    }
+    if args.aon_orders {
+        let residual = if args.aon_cancel_unfilled { AonResidual::Cancel } else { AonResidual::Rest };
+        engine_config = engine_config.with_aon_orders(residual);
+    }
+    match parse_self_trade_prevention(&args.self_trade_prevention) {
+        Some(SelfTradePrevention::Off) => {}
+        Some(mode) => {
+            engine_config = engine_config.with_self_trade_prevention(mode);
+            println!("  Self-trade prevention: {}", args.self_trade_prevention);
+        }
+        None => {
+            eprintln!("Error: Invalid self-trade prevention '{}'", args.self_trade_prevention);
+            std::process::exit(1);
+        }
+    }
+    if args.flicker_lifetime_us > 0 {
+        let Some(action) = parse_flicker_action(&args.flicker_action) else {
+            eprintln!("Error: Invalid flicker action '{}'", args.flicker_action);
+            std::process::exit(1);
+        };
+        let flicker = FlickerConfig::new(
+            args.flicker_lifetime_us * 1_000,
+            args.flicker_window_ms * 1_000_000,
+            args.flicker_threshold,
+        )
+        .with_action(action);
+        engine_config = engine_config.with_flicker_detection(flicker);
+        println!(
+            "  Flicker detection: <{}us lifetime, {} per {}ms, {}",
+            args.flicker_lifetime_us, args.flicker_threshold, args.flicker_window_ms, args.flicker_action
+        );
+    }
+    let exec_clock = args.exec_clock;
+    let mut matching_engine = MatchingEngine::with_config(engine_config).with_clock(move || exec_clock.now().as_u64());
     for &ticker_id in &tickers {
         matching_engine.add_ticker(ticker_id);
     }
+    if let Some(ref path) = args.instruments {
+        match InstrumentRegistry::load(path) {
+            Ok(instruments) => {
+                for spec in instruments.specs() {
+                    println!(
+                        "  Instrument: ticker {} -> tick {}, lot {}",
+                        spec.ticker_id,
+                        spec.format_price(spec.tick_size),
+                        spec.lot_size
+                    );
+                }
+                matching_engine.set_instruments(instruments);
+            }
+            Err(e) => {
+                eprintln!("Error: Failed to load instruments from '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    for price_scale in &args.price_scales {
+        match parse_price_scale(price_scale) {
+            Some((ticker_id, decimals)) => {
+                let spec = matching_engine.instrument(ticker_id).with_price_scale(decimals);
+                println!("  Price scale: ticker {} -> {} decimals", ticker_id, spec.price_scale);
+                matching_engine.set_instrument(spec);
+            }
+            None => {
+                eprintln!("Error: Invalid price scale '{}', expected TICKER=DECIMALS", price_scale);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let md_config = MarketDataPublisherConfig {
+    let mut md_config = MarketDataPublisherConfig {
         multicast_addr: args.multicast_addr.clone(),
         port: args.multicast_port,
         interface: args.interface.clone(),
         ttl: args.ttl,
         enable_snapshots: true,
         snapshot_interval: 1000,
-    };
+        ..MarketDataPublisherConfig::default()
+    }
+    .with_depth_bands(args.depth_band_width);
+    if args.depth_band_width > 0 {
+        println!("  Depth bands: {} cents", args.depth_band_width);
+    }
+    if args.snapshot_request_port > 0 {
+        md_config = md_config.with_snapshot_requests(args.snapshot_request_port);
+        println!("  Snapshot requests: port {}", args.snapshot_request_port);
+    }
+    for route in &args.routes {
+        match parse_route(route) {
+            Some((ticker_id, addr, port)) => {
+                println!("  Route: ticker {} -> {}:{}", ticker_id, addr, port);
+                md_config = md_config.with_route(ticker_id, addr, port);
+            }
+            None => {
+                eprintln!("Error: Invalid route '{}', expected TICKER=ADDR:PORT", route);
+                std::process::exit(1);
+            }
+        }
+    }
 
     let mut market_data_publisher = match MarketDataPublisher::new(md_config) {
         Ok(publisher) => publisher,
@@ -118,50 +455,158 @@ fn main() {
 
     // Main event loop
     let mut stats_interval = 0u64;
+    let compact_idle = Duration::from_millis(args.compact_idle_ms);
+    let mut last_request = Instant::now();
+    let mut compacted_since_request = false;
+    let poll_timeout = Duration::from_micros(args.poll_timeout_us);
     while running.load(Ordering::SeqCst) {
-        // Poll for incoming client requests
-        let requests = order_server.poll();
+        // Poll for incoming client requests, sleeping in epoll while idle
+        // when blocking poll is enabled
+        let requests = if args.blocking_poll {
+            order_server.poll_blocking(poll_timeout)
+        } else {
+            order_server.poll()
+        };
+        if !requests.is_empty() {
+            last_request = Instant::now();
+            compacted_since_request = false;
+        } else if args.compact_idle_ms > 0
+            && !compacted_since_request
+            && last_request.elapsed() >= compact_idle
+        {
+            // Quiet period: rebuild fragmented books once until traffic resumes
+            let books = matching_engine.compact();
+            if books > 0 {
+                println!("Compacted {} order book(s) after {}ms idle", books, args.compact_idle_ms);
+            }
+            compacted_since_request = true;
+        }
 
         for seq_request in requests {
-            // Process request through matching engine
-            let (response, market_updates) =
-                matching_engine.process_request(&seq_request.request);
-
-            // Send response back to client
-            if let Err(e) = order_server.send_response(seq_request.client_id, &response) {
-                eprintln!(
-                    "Failed to send response to client {}: {}",
-                    seq_request.client_id, e
-                );
-            }
+            // The session's client ID is authoritative, so fills on resting
+            // orders can be routed back to the right connection
+            let mut request = seq_request.request;
+            request.client_id = seq_request.client_id;
+
+            // Process request through matching engine and send the response
+            // back to the client; a mass quote answers with one per quote
+            let market_updates = if request.request_type() == Some(ClientRequestType::MassQuote) {
+                let entries = order_server.take_quote_entries(seq_request.sequence_number);
+                let (responses, market_updates) = matching_engine.process_mass_quote(&request, &entries);
+                for response in &responses {
+                    if let Err(e) = order_server.send_response(seq_request.client_id, response) {
+                        eprintln!(
+                            "Failed to send response to client {}: {}",
+                            seq_request.client_id, e
+                        );
+                    }
+                }
+                market_updates
+            } else {
+                let (response, market_updates) = matching_engine.process_request(&request);
+                let matched = matching_engine.last_match_timestamps();
+                if let Err(e) = order_server.send_timed_response(&seq_request, &response, matched) {
+                    eprintln!(
+                        "Failed to send response to client {}: {}",
+                        seq_request.client_id, e
+                    );
+                }
+                market_updates
+            };
 
-            // Publish market data updates
-            for update in &market_updates {
-                if let Err(e) = market_data_publisher.publish(update) {
-                    eprintln!("Failed to publish market update: {}", e);
+            // Send fills to the owners of any resting orders that traded
+            for fill in matching_engine.drain_maker_fills() {
+                let client_id = fill.client_id;
+                if let Err(e) = order_server.send_response(client_id, &fill) {
+                    eprintln!("Failed to send fill to client {}: {}", client_id, e);
                 }
             }
+
+            // Publish market data updates, banded together when configured
+            if let Err(e) = market_data_publisher.publish_batch(&market_updates) {
+                eprintln!("Failed to publish market update: {}", e);
+            }
+        }
+
+        // Write the cycle's batched responses, one write per client
+        if args.batch_responses {
+            order_server.flush_responses();
+        }
+
+        // Report clients flagged for flicker and drop them if configured
+        for alert in matching_engine.drain_flicker_alerts() {
+            eprintln!(
+                "Flicker: client {} canceled {} short-lived orders in the window ({:?})",
+                alert.client_id, alert.flicker_cancels, alert.action
+            );
+            if alert.action == FlickerAction::Disconnect {
+                order_server.disconnect_client(alert.client_id);
+            }
+        }
+
+        // Answer late joiners asking for a snapshot
+        if let Err(e) = market_data_publisher.serve_snapshot_requests() {
+            eprintln!("Failed to answer snapshot request: {}", e);
         }
 
         // Print stats periodically
         stats_interval += 1;
-        if stats_interval % 100000 == 0 {
+        if stats_interval.is_multiple_of(100000) {
             println!(
                 "Stats: clients={}, seq={}, md_updates={}",
                 order_server.client_count(),
                 order_server.current_sequence(),
                 market_data_publisher.total_updates_sent()
             );
+            if args.timestamps {
+                let latency = order_server.latency_breakdown();
+                println!(
+                    "Latency (mean ns): inbound={:.0}, matching={:.0}, outbound={:.0}, total={:.0} over {} orders",
+                    latency.inbound.mean(),
+                    latency.matching.mean(),
+                    latency.outbound.mean(),
+                    latency.total.mean(),
+                    latency.total.count()
+                );
+            }
         }
 
-        // Small sleep to prevent busy-waiting when idle
-        // In production, this would use epoll/kqueue for efficient waiting
-        thread::sleep(Duration::from_micros(10));
+        // Small sleep to prevent busy-waiting when idle; blocking poll
+        // already waited for the next request
+        if !args.blocking_poll {
+            thread::sleep(Duration::from_micros(10));
+        }
     }
 
     // Graceful shutdown
     println!("Shutting down...");
     order_server.disconnect_all();
+    for book in matching_engine.pool_stats() {
+        println!(
+            "Pools: ticker {} orders peak {}/{} ({} refused), levels peak {}/{}, fragmentation {:.2}",
+            book.ticker_id,
+            book.orders.peak_allocated,
+            book.orders.capacity,
+            book.orders.failed_allocations,
+            book.levels.peak_allocated,
+            book.levels.capacity,
+            book.fragmentation.ratio()
+        );
+    }
+    for &ticker_id in &tickers {
+        if let Some(flow) = matching_engine.ticker_stats(ticker_id) {
+            println!(
+                "Order flow: ticker {} adds {} cancels {} modifies {} trades {}, cancel/trade {:.2}, avg resting {}us",
+                ticker_id,
+                flow.adds,
+                flow.cancels,
+                flow.modifies,
+                flow.trades,
+                flow.cancel_to_trade_ratio().unwrap_or(0.0),
+                flow.avg_resting_ns() / 1_000
+            );
+        }
+    }
     println!(
         "Exchange server stopped. Total updates sent: {}",
         market_data_publisher.total_updates_sent()
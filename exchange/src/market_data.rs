@@ -2,12 +2,35 @@
 //!
 //! Multicasts market data updates (order adds, modifies, cancels, trades)
 //! to all subscribed clients. Supports snapshot generation for late joiners.
+//!
+//! Tickers can be routed to separate multicast groups to segregate feeds;
+//! each group keeps its own sequence space.
+//!
+//! Every published update is stamped with its group's sequence number,
+//! starting at 1, so receivers can skip updates delivered twice. Snapshot
+//! updates are unsequenced and carry the sequence they reflect in `priority`.
+//!
+//! Depth can optionally be published aggregated into price bands instead of
+//! per order: each band level carries the total quantity resting within
+//! `depth_band_width` prices, which cuts feed volume for consumers that do
+//! not need full granularity.
+//!
+//! With a snapshot request port configured, a subscriber joining between
+//! periodic snapshots can ask for one: it sends a `SnapshotRequest` over
+//! UDP and `serve_snapshot_requests` answers it directly with an
+//! unsequenced Clear and the ticker's Snapshot levels, leaving the
+//! multicast feed untouched.
 
 use common::net::multicast::MulticastSocket;
-use common::{Price, Qty, Side, TickerId};
-use crate::protocol::{MarketUpdate, MarketUpdateType};
-use std::collections::HashMap;
+use common::time::monotonic_nanos;
+use common::{Price, Qty, Side, TickerId, INVALID_PRICE};
+use crate::protocol::{
+    MarketUpdate, MarketUpdateType, SnapshotRequest, MARKET_UPDATE_FLAG_BANDED, MARKET_UPDATE_FLAG_STALE,
+    SNAPSHOT_REQUEST_SIZE,
+};
+use std::collections::{BTreeMap, HashMap};
 use std::io;
+use std::net::UdpSocket;
 
 /// Configuration for the market data publisher.
 #[derive(Debug, Clone)]
@@ -24,6 +47,70 @@ pub struct MarketDataPublisherConfig {
     pub enable_snapshots: bool,
     /// Interval between automatic snapshots (in number of updates)
     pub snapshot_interval: usize,
+    /// Time in nanoseconds after which an unchanged level is stale;
+    /// 0 disables aging
+    pub max_level_age_ns: u64,
+    /// What snapshots do with stale levels
+    pub stale_level_policy: StaleLevelPolicy,
+    /// Per-ticker destinations; tickers without a route use
+    /// `multicast_addr`/`port`
+    pub routes: HashMap<TickerId, FeedRoute>,
+    /// Width of the price bands depth is aggregated into; 0 publishes
+    /// every order
+    pub depth_band_width: Price,
+    /// UDP port snapshot requests are accepted on (0 picks a free port);
+    /// None disables snapshot requests
+    pub snapshot_request_port: Option<u16>,
+}
+
+/// Multicast destination for a subset of tickers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FeedRoute {
+    /// Multicast group address
+    pub multicast_addr: String,
+    /// Port number for multicast
+    pub port: u16,
+}
+
+impl FeedRoute {
+    /// Creates a new route to the given group and port.
+    pub fn new(multicast_addr: &str, port: u16) -> Self {
+        Self {
+            multicast_addr: multicast_addr.to_string(),
+            port,
+        }
+    }
+}
+
+impl MarketDataPublisherConfig {
+    /// Routes a ticker's updates to the given multicast group and port.
+    pub fn with_route(mut self, ticker_id: TickerId, multicast_addr: &str, port: u16) -> Self {
+        self.routes.insert(ticker_id, FeedRoute::new(multicast_addr, port));
+        self
+    }
+
+    /// Publishes depth aggregated into price bands of the given width
+    /// (0 keeps full granularity).
+    pub fn with_depth_bands(mut self, band_width: Price) -> Self {
+        self.depth_band_width = band_width.max(0);
+        self
+    }
+
+    /// Accepts snapshot requests on the given UDP port (0 picks a free one).
+    pub fn with_snapshot_requests(mut self, port: u16) -> Self {
+        self.snapshot_request_port = Some(port);
+        self
+    }
+}
+
+/// How snapshots treat levels older than `max_level_age_ns`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StaleLevelPolicy {
+    /// Leave stale levels out of the snapshot
+    #[default]
+    Skip,
+    /// Publish stale levels with `MARKET_UPDATE_FLAG_STALE` set
+    Flag,
 }
 
 impl Default for MarketDataPublisherConfig {
@@ -35,12 +122,144 @@ impl Default for MarketDataPublisherConfig {
             ttl: 1,
             enable_snapshots: true,
             snapshot_interval: 1000,
+            max_level_age_ns: 0,
+            stale_level_policy: StaleLevelPolicy::Skip,
+            routes: HashMap::new(),
+            depth_band_width: 0,
+            snapshot_request_port: None,
         }
     }
 }
 
+/// Returns the band a price falls in, identified by its lowest price.
+///
+/// # Arguments
+/// * `price` - The price to band
+/// * `band_width` - Width of each band; 0 or 1 leaves prices unbanded
+#[inline]
+pub fn band_price(price: Price, band_width: Price) -> Price {
+    if band_width <= 1 {
+        price
+    } else {
+        price.div_euclid(band_width) * band_width
+    }
+}
+
+/// Depth for one ticker aggregated into price bands.
+///
+/// Each level holds the total quantity resting within a band. The publisher
+/// builds it from order updates; receivers rebuild it from banded updates.
+#[derive(Debug, Clone, Default)]
+pub struct BandedDepth {
+    bids: BTreeMap<Price, Qty>,
+    asks: BTreeMap<Price, Qty>,
+}
+
+impl BandedDepth {
+    /// Creates an empty banded book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn side(&self, side: Side) -> &BTreeMap<Price, Qty> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Price, Qty> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// Returns the total quantity resting in a band, 0 if empty.
+    #[inline]
+    pub fn level_qty(&self, side: Side, band: Price) -> Qty {
+        self.side(side).get(&band).copied().unwrap_or(0)
+    }
+
+    /// Sets the total quantity of a band, removing it when 0.
+    pub fn set_level(&mut self, side: Side, band: Price, qty: Qty) {
+        if qty == 0 {
+            self.side_mut(side).remove(&band);
+        } else {
+            self.side_mut(side).insert(band, qty);
+        }
+    }
+
+    /// Adds quantity to a band and returns its new total.
+    fn add(&mut self, side: Side, band: Price, qty: Qty) -> Qty {
+        let total = self.level_qty(side, band).saturating_add(qty);
+        self.set_level(side, band, total);
+        total
+    }
+
+    /// Removes quantity from a band and returns its new total.
+    fn remove(&mut self, side: Side, band: Price, qty: Qty) -> Qty {
+        let total = self.level_qty(side, band).saturating_sub(qty);
+        self.set_level(side, band, total);
+        total
+    }
+
+    /// Applies a banded level update, as published with band aggregation.
+    ///
+    /// # Returns
+    /// true if the update was a banded level and was applied
+    pub fn apply_level(&mut self, update: &MarketUpdate) -> bool {
+        let side = match update.side {
+            1 => Side::Buy,
+            -1 => Side::Sell,
+            _ => return false,
+        };
+        if !update.is_banded() {
+            return false;
+        }
+        self.set_level(side, update.price, update.qty);
+        true
+    }
+
+    /// Returns the best (highest) bid band and its quantity.
+    #[inline]
+    pub fn best_bid(&self) -> Option<(Price, Qty)> {
+        self.bids.iter().next_back().map(|(&price, &qty)| (price, qty))
+    }
+
+    /// Returns the best (lowest) ask band and its quantity.
+    #[inline]
+    pub fn best_ask(&self) -> Option<(Price, Qty)> {
+        self.asks.iter().next().map(|(&price, &qty)| (price, qty))
+    }
+
+    /// Returns the bands on a side, best first.
+    pub fn levels(&self, side: Side) -> Vec<(Price, Qty)> {
+        let levels = self.side(side).iter().map(|(&price, &qty)| (price, qty));
+        match side {
+            Side::Buy => levels.rev().collect(),
+            Side::Sell => levels.collect(),
+        }
+    }
+
+    /// Returns the number of bands on a side.
+    #[inline]
+    pub fn level_count(&self, side: Side) -> usize {
+        self.side(side).len()
+    }
+
+    /// Removes all bands.
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+}
+
 /// Best bid and offer state for a single ticker (used for snapshots).
-#[derive(Debug, Clone, Copy, Default)]
+///
+/// An empty side is marked with `INVALID_PRICE` rather than zero so that
+/// instruments trading at zero or negative prices are representable.
+#[derive(Debug, Clone, Copy)]
 struct TickerState {
     /// Best bid price
     bid_price: Price,
@@ -52,6 +271,170 @@ struct TickerState {
     ask_qty: Qty,
     /// Sequence number of last update
     last_seq: u64,
+    /// Time (ns) at which the bid level was last set
+    bid_set_at: u64,
+    /// Time (ns) at which the ask level was last set
+    ask_set_at: u64,
+}
+
+impl Default for TickerState {
+    fn default() -> Self {
+        Self {
+            bid_price: INVALID_PRICE,
+            bid_qty: 0,
+            ask_price: INVALID_PRICE,
+            ask_qty: 0,
+            last_seq: 0,
+            bid_set_at: 0,
+            ask_set_at: 0,
+        }
+    }
+}
+
+impl TickerState {
+    /// Returns true if there is a resting bid.
+    #[inline]
+    fn has_bid(&self) -> bool {
+        self.bid_price != INVALID_PRICE && self.bid_qty > 0
+    }
+
+    /// Returns true if there is a resting ask.
+    #[inline]
+    fn has_ask(&self) -> bool {
+        self.ask_price != INVALID_PRICE && self.ask_qty > 0
+    }
+
+    /// Applies a market update at `now_ns` and records which levels it
+    /// touched.
+    ///
+    /// A level counts as touched if its price or quantity changed, or if the
+    /// update refers to that level directly.
+    fn apply_at(&mut self, update: &MarketUpdate, seq: u64, now_ns: u64) {
+        let side = update.side;
+        let price = update.price;
+        let before = (self.bid_price, self.bid_qty, self.ask_price, self.ask_qty);
+
+        self.apply(update);
+
+        if (self.bid_price, self.bid_qty) != (before.0, before.1)
+            || (side == Side::Buy as i8 && price == self.bid_price)
+        {
+            self.bid_set_at = now_ns;
+        }
+        if (self.ask_price, self.ask_qty) != (before.2, before.3)
+            || (side == Side::Sell as i8 && price == self.ask_price)
+        {
+            self.ask_set_at = now_ns;
+        }
+        self.last_seq = seq;
+    }
+
+    /// Builds the snapshot updates for this ticker at sequence `seq`.
+    ///
+    /// Levels last set more than `max_age_ns` before `now_ns` are skipped or
+    /// flagged according to `policy`.
+    fn snapshot_updates(
+        &self,
+        ticker_id: TickerId,
+        seq: u64,
+        now_ns: u64,
+        max_age_ns: u64,
+        policy: StaleLevelPolicy,
+    ) -> Vec<MarketUpdate> {
+        let sides = [
+            (self.has_bid(), Side::Buy, self.bid_price, self.bid_qty, self.bid_set_at),
+            (self.has_ask(), Side::Sell, self.ask_price, self.ask_qty, self.ask_set_at),
+        ];
+
+        let mut updates = Vec::with_capacity(2);
+        for (present, side, price, qty, set_at) in sides {
+            if !present {
+                continue;
+            }
+
+            let stale = max_age_ns > 0 && now_ns.saturating_sub(set_at) > max_age_ns;
+            if stale && policy == StaleLevelPolicy::Skip {
+                continue;
+            }
+
+            let mut update = MarketUpdate::new(
+                MarketUpdateType::Snapshot,
+                ticker_id,
+                0, // No specific order ID for snapshot
+                side as i8,
+                price,
+                qty,
+                seq,
+            );
+            if stale {
+                update = update.with_flags(MARKET_UPDATE_FLAG_STALE);
+            }
+            updates.push(update);
+        }
+
+        updates
+    }
+
+    /// Applies a market update to the BBO state.
+    fn apply(&mut self, update: &MarketUpdate) {
+        // Extract fields from packed struct
+        let update_type = update.update_type();
+        let side = update.side;
+        let price = update.price;
+        let qty = update.qty;
+
+        match update_type {
+            Some(MarketUpdateType::Add) | Some(MarketUpdateType::Modify) | Some(MarketUpdateType::Snapshot) => {
+                if side == Side::Buy as i8 {
+                    // Update bid if better or same price
+                    if self.bid_price == INVALID_PRICE || price > self.bid_price {
+                        self.bid_price = price;
+                        self.bid_qty = qty;
+                    } else if price == self.bid_price {
+                        self.bid_qty = qty;
+                    }
+                } else if side == Side::Sell as i8 {
+                    // Update ask if better (lower) or same price
+                    if self.ask_price == INVALID_PRICE || price < self.ask_price {
+                        self.ask_price = price;
+                        self.ask_qty = qty;
+                    } else if price == self.ask_price {
+                        self.ask_qty = qty;
+                    }
+                }
+            }
+            Some(MarketUpdateType::Cancel) => {
+                if side == Side::Buy as i8 && price == self.bid_price {
+                    // Bid at BBO cancelled - reduce qty
+                    self.bid_qty = self.bid_qty.saturating_sub(qty);
+                    if self.bid_qty == 0 {
+                        self.bid_price = INVALID_PRICE;
+                    }
+                } else if side == Side::Sell as i8 && price == self.ask_price {
+                    // Ask at BBO cancelled - reduce qty
+                    self.ask_qty = self.ask_qty.saturating_sub(qty);
+                    if self.ask_qty == 0 {
+                        self.ask_price = INVALID_PRICE;
+                    }
+                }
+            }
+            Some(MarketUpdateType::Trade) => {
+                // Trade reduces quantity at the trade price
+                if side == Side::Buy as i8 && price == self.ask_price {
+                    self.ask_qty = self.ask_qty.saturating_sub(qty);
+                } else if side == Side::Sell as i8 && price == self.bid_price {
+                    self.bid_qty = self.bid_qty.saturating_sub(qty);
+                }
+            }
+            Some(MarketUpdateType::Clear) => {
+                // Clear the entire state for this ticker
+                *self = TickerState::default();
+            }
+            None => {
+                // Invalid update type - ignore
+            }
+        }
+    }
 }
 
 /// Market data publisher that multicasts updates to subscribers.
@@ -68,14 +451,31 @@ pub struct MarketDataPublisher {
     config: MarketDataPublisherConfig,
     /// Current state per ticker (for snapshots)
     ticker_state: HashMap<TickerId, TickerState>,
-    /// Sequence number for updates
-    sequence: u64,
+    /// Banded depth per ticker, when publishing price bands
+    banded_depth: HashMap<TickerId, BandedDepth>,
+    /// Multicast groups, the default one first
+    groups: Vec<FeedGroup>,
+    /// Index into `groups` for each routed ticker
+    ticker_groups: HashMap<TickerId, usize>,
     /// Update count since last snapshot
     updates_since_snapshot: usize,
     /// Statistics: total updates sent
     total_updates_sent: u64,
     /// Statistics: total bytes sent
     total_bytes_sent: u64,
+    /// Socket snapshot requests arrive on, when enabled
+    request_socket: Option<UdpSocket>,
+    /// Statistics: snapshot requests answered
+    snapshot_requests_served: u64,
+    /// Timestamp source for level aging
+    clock: Box<dyn Fn() -> u64 + Send>,
+}
+
+/// A multicast destination and its sequence space.
+#[derive(Debug)]
+struct FeedGroup {
+    route: FeedRoute,
+    sequence: u64,
 }
 
 impl MarketDataPublisher {
@@ -95,17 +495,56 @@ impl MarketDataPublisher {
         // Set the outgoing interface
         socket.set_multicast_interface(&config.interface)?;
 
+        // Tickers sharing a destination share a group and its sequence
+        let mut groups = vec![FeedGroup {
+            route: FeedRoute::new(&config.multicast_addr, config.port),
+            sequence: 0,
+        }];
+        let mut ticker_groups = HashMap::new();
+        for (&ticker_id, route) in &config.routes {
+            let index = match groups.iter().position(|group| group.route == *route) {
+                Some(index) => index,
+                None => {
+                    groups.push(FeedGroup { route: route.clone(), sequence: 0 });
+                    groups.len() - 1
+                }
+            };
+            ticker_groups.insert(ticker_id, index);
+        }
+
+        let request_socket = match config.snapshot_request_port {
+            Some(port) => {
+                let socket = UdpSocket::bind((config.interface.as_str(), port))?;
+                socket.set_nonblocking(true)?;
+                Some(socket)
+            }
+            None => None,
+        };
+
         Ok(Self {
             socket,
             config,
             ticker_state: HashMap::new(),
-            sequence: 0,
+            banded_depth: HashMap::new(),
+            groups,
+            ticker_groups,
             updates_since_snapshot: 0,
             total_updates_sent: 0,
             total_bytes_sent: 0,
+            request_socket,
+            snapshot_requests_served: 0,
+            clock: Box::new(|| monotonic_nanos().as_u64()),
         })
     }
 
+    /// Replaces the clock levels are aged by.
+    ///
+    /// Useful for simulations and tests that need to control time.
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
     /// Creates a new market data publisher with default configuration.
     pub fn with_defaults() -> io::Result<Self> {
         Self::new(MarketDataPublisherConfig::default())
@@ -113,26 +552,39 @@ impl MarketDataPublisher {
 
     /// Publishes a market update to all subscribers.
     ///
+    /// With price bands configured, order updates are folded into their
+    /// band and the band's new total is published instead.
+    ///
     /// # Arguments
     /// * `update` - The market update to publish
     ///
     /// # Returns
     /// The number of bytes sent, or an IO error
     pub fn publish(&mut self, update: &MarketUpdate) -> io::Result<usize> {
+        if self.config.depth_band_width > 0 {
+            return self.publish_banded(std::slice::from_ref(update));
+        }
+        self.publish_raw(update)
+    }
+
+    /// Sends a single update as-is and runs the snapshot schedule.
+    fn publish_raw(&mut self, update: &MarketUpdate) -> io::Result<usize> {
         // Extract ticker_id from packed struct to avoid unaligned references
         let ticker_id = update.ticker_id;
 
-        // Update internal state for snapshots
-        if self.config.enable_snapshots {
+        // Update internal state for snapshots; banded snapshots come from
+        // the banded depth instead
+        if self.config.enable_snapshots && self.config.depth_band_width == 0 {
             self.update_ticker_state(ticker_id, update);
         }
 
         // Serialize and send
-        let bytes = update.as_bytes();
-        let sent = self.socket.send_to(bytes, &self.config.multicast_addr, self.config.port)?;
+        let group = self.group_index(ticker_id);
+        let update = update.with_seq(self.groups[group].sequence + 1);
+        let sent = self.send_to_group(group, &update)?;
 
         // Update statistics
-        self.sequence += 1;
+        self.groups[group].sequence += 1;
         self.updates_since_snapshot += 1;
         self.total_updates_sent += 1;
         self.total_bytes_sent += sent as u64;
@@ -151,7 +603,8 @@ impl MarketDataPublisher {
     /// Publishes multiple market updates in a batch.
     ///
     /// This is more efficient than calling `publish` multiple times
-    /// as it can amortize any per-call overhead.
+    /// as it can amortize any per-call overhead. With price bands
+    /// configured, each band touched by the batch is published once.
     ///
     /// # Arguments
     /// * `updates` - Iterator of market updates to publish
@@ -162,6 +615,11 @@ impl MarketDataPublisher {
     where
         I: IntoIterator<Item = &'a MarketUpdate>,
     {
+        if self.config.depth_band_width > 0 {
+            let updates: Vec<MarketUpdate> = updates.into_iter().copied().collect();
+            return self.publish_banded(&updates);
+        }
+
         let mut total_sent = 0;
         for update in updates {
             total_sent += self.publish(update)?;
@@ -169,69 +627,88 @@ impl MarketDataPublisher {
         Ok(total_sent)
     }
 
-    /// Updates internal ticker state based on a market update.
-    fn update_ticker_state(&mut self, ticker_id: TickerId, update: &MarketUpdate) {
-        let state = self.ticker_state.entry(ticker_id).or_default();
-
-        // Extract fields from packed struct
-        let update_type = update.update_type();
-        let side = update.side;
-        let price = update.price;
-        let qty = update.qty;
+    /// Folds order updates into price bands and publishes each touched band.
+    ///
+    /// Adds and cancels change their own side's band and trades reduce the
+    /// resting side's band. Trade prints, clears and updates that cannot be
+    /// banded are sent as-is.
+    fn publish_banded(&mut self, updates: &[MarketUpdate]) -> io::Result<usize> {
+        let band_width = self.config.depth_band_width;
+        let mut touched: Vec<(TickerId, Side, Price)> = Vec::new();
+        let mut total_sent = 0;
 
-        match update_type {
-            Some(MarketUpdateType::Add) | Some(MarketUpdateType::Modify) | Some(MarketUpdateType::Snapshot) => {
-                if side == Side::Buy as i8 {
-                    // Update bid if better or same price
-                    if price > state.bid_price || state.bid_price == 0 {
-                        state.bid_price = price;
-                        state.bid_qty = qty;
-                    } else if price == state.bid_price {
-                        state.bid_qty = qty;
-                    }
-                } else if side == Side::Sell as i8 {
-                    // Update ask if better (lower) or same price
-                    if state.ask_price == 0 || price < state.ask_price {
-                        state.ask_price = price;
-                        state.ask_qty = qty;
-                    } else if price == state.ask_price {
-                        state.ask_qty = qty;
-                    }
+        for update in updates {
+            let ticker_id = update.ticker_id;
+            let side = match update.side {
+                1 => Some(Side::Buy),
+                -1 => Some(Side::Sell),
+                _ => None,
+            };
+            let band = band_price(update.price, band_width);
+            let depth = self.banded_depth.entry(ticker_id).or_default();
+
+            let level = match (update.update_type(), side) {
+                (Some(MarketUpdateType::Add), Some(side)) => {
+                    depth.add(side, band, update.qty);
+                    Some((ticker_id, side, band))
                 }
-            }
-            Some(MarketUpdateType::Cancel) => {
-                if side == Side::Buy as i8 && price == state.bid_price {
-                    // Bid at BBO cancelled - reduce qty
-                    state.bid_qty = state.bid_qty.saturating_sub(qty);
-                    if state.bid_qty == 0 {
-                        state.bid_price = 0;
-                    }
-                } else if side == Side::Sell as i8 && price == state.ask_price {
-                    // Ask at BBO cancelled - reduce qty
-                    state.ask_qty = state.ask_qty.saturating_sub(qty);
-                    if state.ask_qty == 0 {
-                        state.ask_price = 0;
-                    }
+                (Some(MarketUpdateType::Cancel), Some(side)) => {
+                    depth.remove(side, band, update.qty);
+                    Some((ticker_id, side, band))
                 }
-            }
-            Some(MarketUpdateType::Trade) => {
-                // Trade reduces quantity at the trade price
-                if side == Side::Buy as i8 && price == state.ask_price {
-                    state.ask_qty = state.ask_qty.saturating_sub(qty);
-                } else if side == Side::Sell as i8 && price == state.bid_price {
-                    state.bid_qty = state.bid_qty.saturating_sub(qty);
+                (Some(MarketUpdateType::Trade), Some(aggressor)) => {
+                    let resting = aggressor.opposite();
+                    depth.remove(resting, band, update.qty);
+                    total_sent += self.publish_raw(update)?;
+                    Some((ticker_id, resting, band))
+                }
+                (Some(MarketUpdateType::Clear), _) => {
+                    depth.clear();
+                    touched.retain(|&(touched_ticker, _, _)| touched_ticker != ticker_id);
+                    total_sent += self.publish_raw(update)?;
+                    None
+                }
+                _ => {
+                    total_sent += self.publish_raw(update)?;
+                    None
+                }
+            };
+
+            if let Some(level) = level {
+                if !touched.contains(&level) {
+                    touched.push(level);
                 }
             }
-            Some(MarketUpdateType::Clear) => {
-                // Clear the entire state for this ticker
-                *state = TickerState::default();
-            }
-            None => {
-                // Invalid update type - ignore
-            }
         }
 
-        state.last_seq = self.sequence;
+        for (ticker_id, side, band) in touched {
+            let qty = self.banded_depth[&ticker_id].level_qty(side, band);
+            let update = MarketUpdate::new(MarketUpdateType::Modify, ticker_id, 0, side as i8, band, qty, 0)
+                .with_flags(MARKET_UPDATE_FLAG_BANDED);
+            total_sent += self.publish_raw(&update)?;
+        }
+
+        Ok(total_sent)
+    }
+
+    /// Updates internal ticker state based on a market update.
+    fn update_ticker_state(&mut self, ticker_id: TickerId, update: &MarketUpdate) {
+        let sequence = self.groups[self.group_index(ticker_id)].sequence;
+        let now = (self.clock)();
+        let state = self.ticker_state.entry(ticker_id).or_default();
+        state.apply_at(update, sequence, now);
+    }
+
+    /// Returns the index of the group a ticker is published to.
+    #[inline]
+    fn group_index(&self, ticker_id: TickerId) -> usize {
+        self.ticker_groups.get(&ticker_id).copied().unwrap_or(0)
+    }
+
+    /// Sends one update to a group's destination.
+    fn send_to_group(&self, group: usize, update: &MarketUpdate) -> io::Result<usize> {
+        let route = &self.groups[group].route;
+        self.socket.send_to(update.as_bytes(), &route.multicast_addr, route.port)
     }
 
     /// Publishes a snapshot of the current market state for all tickers.
@@ -244,42 +721,14 @@ impl MarketDataPublisher {
         let mut total_sent = 0;
 
         // Collect ticker IDs first to avoid borrow issues
-        let ticker_ids: Vec<TickerId> = self.ticker_state.keys().copied().collect();
+        let ticker_ids: Vec<TickerId> = if self.config.depth_band_width > 0 {
+            self.banded_depth.keys().copied().collect()
+        } else {
+            self.ticker_state.keys().copied().collect()
+        };
 
         for ticker_id in ticker_ids {
-            let state = self.ticker_state.get(&ticker_id).copied().unwrap_or_default();
-
-            // Send bid snapshot if we have a valid bid
-            if state.bid_price > 0 && state.bid_qty > 0 {
-                let bid_update = MarketUpdate::new(
-                    MarketUpdateType::Snapshot,
-                    ticker_id,
-                    0, // No specific order ID for snapshot
-                    Side::Buy as i8,
-                    state.bid_price,
-                    state.bid_qty,
-                    self.sequence,
-                );
-
-                let bytes = bid_update.as_bytes();
-                total_sent += self.socket.send_to(bytes, &self.config.multicast_addr, self.config.port)?;
-            }
-
-            // Send ask snapshot if we have a valid ask
-            if state.ask_price > 0 && state.ask_qty > 0 {
-                let ask_update = MarketUpdate::new(
-                    MarketUpdateType::Snapshot,
-                    ticker_id,
-                    0, // No specific order ID for snapshot
-                    Side::Sell as i8,
-                    state.ask_price,
-                    state.ask_qty,
-                    self.sequence,
-                );
-
-                let bytes = ask_update.as_bytes();
-                total_sent += self.socket.send_to(bytes, &self.config.multicast_addr, self.config.port)?;
-            }
+            total_sent += self.publish_ticker_snapshot(ticker_id)?;
         }
 
         self.updates_since_snapshot = 0;
@@ -294,46 +743,98 @@ impl MarketDataPublisher {
     /// # Returns
     /// The number of bytes sent, or an IO error
     pub fn publish_ticker_snapshot(&mut self, ticker_id: TickerId) -> io::Result<usize> {
-        let state = match self.ticker_state.get(&ticker_id) {
-            Some(s) => *s,
-            None => return Ok(0),
-        };
-
+        let group = self.group_index(ticker_id);
         let mut total_sent = 0;
+        for update in &self.ticker_snapshot_updates(ticker_id) {
+            total_sent += self.send_to_group(group, update)?;
+        }
+        Ok(total_sent)
+    }
 
-        // Send bid snapshot
-        if state.bid_price > 0 && state.bid_qty > 0 {
-            let bid_update = MarketUpdate::new(
-                MarketUpdateType::Snapshot,
-                ticker_id,
-                0,
-                Side::Buy as i8,
-                state.bid_price,
-                state.bid_qty,
-                self.sequence,
-            );
-
-            let bytes = bid_update.as_bytes();
-            total_sent += self.socket.send_to(bytes, &self.config.multicast_addr, self.config.port)?;
+    /// Builds a ticker's Snapshot updates, stamped with the sequence of its
+    /// group: every band of its banded depth when publishing price bands,
+    /// otherwise its BBO.
+    fn ticker_snapshot_updates(&self, ticker_id: TickerId) -> Vec<MarketUpdate> {
+        let sequence = self.groups[self.group_index(ticker_id)].sequence;
+
+        if self.config.depth_band_width > 0 {
+            let Some(depth) = self.banded_depth.get(&ticker_id) else {
+                return Vec::new();
+            };
+            return [Side::Buy, Side::Sell]
+                .into_iter()
+                .flat_map(|side| depth.levels(side).into_iter().map(move |level| (side, level)))
+                .map(|(side, (band, qty))| {
+                    MarketUpdate::new(MarketUpdateType::Snapshot, ticker_id, 0, side as i8, band, qty, sequence)
+                        .with_flags(MARKET_UPDATE_FLAG_BANDED)
+                })
+                .collect();
         }
 
-        // Send ask snapshot
-        if state.ask_price > 0 && state.ask_qty > 0 {
-            let ask_update = MarketUpdate::new(
-                MarketUpdateType::Snapshot,
+        self.ticker_state.get(&ticker_id).map_or_else(Vec::new, |state| {
+            state.snapshot_updates(
                 ticker_id,
-                0,
-                Side::Sell as i8,
-                state.ask_price,
-                state.ask_qty,
-                self.sequence,
-            );
+                sequence,
+                (self.clock)(),
+                self.config.max_level_age_ns,
+                self.config.stale_level_policy,
+            )
+        })
+    }
+
+    /// Answers the snapshot requests waiting on the request socket.
+    ///
+    /// Each request is answered to its sender with an unsequenced Clear
+    /// carrying the sequence the snapshot reflects in `priority`, followed
+    /// by the ticker's Snapshot levels as `publish_ticker_snapshot` builds
+    /// them. Every reply update echoes the request's `client_seq` in its
+    /// order_id. Malformed requests are ignored.
+    ///
+    /// # Returns
+    /// The number of requests answered, or an IO error
+    pub fn serve_snapshot_requests(&mut self) -> io::Result<usize> {
+        let Some(socket) = &self.request_socket else {
+            return Ok(0);
+        };
 
-            let bytes = ask_update.as_bytes();
-            total_sent += self.socket.send_to(bytes, &self.config.multicast_addr, self.config.port)?;
+        // One byte spare so an oversized datagram is not mistaken for a request
+        let mut buf = [0u8; SNAPSHOT_REQUEST_SIZE + 1];
+        let mut served = 0;
+        loop {
+            let (len, sender) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            };
+            let Some(request) = SnapshotRequest::from_bytes(&buf[..len]) else {
+                continue;
+            };
+            let (ticker_id, client_seq) = (request.ticker_id, request.client_seq);
+
+            let sequence = self.groups[self.group_index(ticker_id)].sequence;
+            let clear = MarketUpdate::new(MarketUpdateType::Clear, ticker_id, client_seq, 0, 0, 0, sequence);
+            socket.send_to(clear.as_bytes(), sender)?;
+            for update in self.ticker_snapshot_updates(ticker_id) {
+                let update = MarketUpdate { order_id: client_seq, ..update };
+                socket.send_to(update.as_bytes(), sender)?;
+            }
+            served += 1;
         }
 
-        Ok(total_sent)
+        self.snapshot_requests_served += served as u64;
+        Ok(served)
+    }
+
+    /// Returns the UDP port snapshot requests are accepted on, if enabled.
+    pub fn snapshot_request_port(&self) -> Option<u16> {
+        let socket = self.request_socket.as_ref()?;
+        socket.local_addr().ok().map(|addr| addr.port())
+    }
+
+    /// Returns the number of snapshot requests answered.
+    #[inline]
+    pub fn snapshot_requests_served(&self) -> u64 {
+        self.snapshot_requests_served
     }
 
     /// Publishes a clear message for a ticker.
@@ -346,6 +847,7 @@ impl MarketDataPublisher {
     /// # Returns
     /// The number of bytes sent, or an IO error
     pub fn publish_clear(&mut self, ticker_id: TickerId) -> io::Result<usize> {
+        let group = self.group_index(ticker_id);
         let update = MarketUpdate::new(
             MarketUpdateType::Clear,
             ticker_id,
@@ -353,16 +855,17 @@ impl MarketDataPublisher {
             0,
             0,
             0,
-            self.sequence,
-        );
+            self.groups[group].sequence,
+        )
+        .with_seq(self.groups[group].sequence + 1);
 
         // Clear internal state
         self.ticker_state.remove(&ticker_id);
+        self.banded_depth.remove(&ticker_id);
 
-        let bytes = update.as_bytes();
-        let sent = self.socket.send_to(bytes, &self.config.multicast_addr, self.config.port)?;
+        let sent = self.send_to_group(group, &update)?;
 
-        self.sequence += 1;
+        self.groups[group].sequence += 1;
         self.total_updates_sent += 1;
         self.total_bytes_sent += sent as u64;
 
@@ -379,10 +882,28 @@ impl MarketDataPublisher {
         self.ticker_state.entry(ticker_id).or_default();
     }
 
-    /// Returns the current sequence number.
+    /// Returns the current sequence number of the default group.
     #[inline]
     pub fn sequence(&self) -> u64 {
-        self.sequence
+        self.groups[0].sequence
+    }
+
+    /// Returns the current sequence number of the group a ticker is published to.
+    #[inline]
+    pub fn ticker_sequence(&self, ticker_id: TickerId) -> u64 {
+        self.groups[self.group_index(ticker_id)].sequence
+    }
+
+    /// Returns the destination a ticker's updates are sent to.
+    #[inline]
+    pub fn route_for(&self, ticker_id: TickerId) -> &FeedRoute {
+        &self.groups[self.group_index(ticker_id)].route
+    }
+
+    /// Returns the number of distinct multicast groups published to.
+    #[inline]
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
     }
 
     /// Returns the total number of updates sent.
@@ -421,6 +942,12 @@ impl MarketDataPublisher {
         &self.config
     }
 
+    /// Returns a ticker's banded depth, when publishing price bands.
+    #[inline]
+    pub fn banded_depth(&self, ticker_id: TickerId) -> Option<&BandedDepth> {
+        self.banded_depth.get(&ticker_id)
+    }
+
     /// Returns the current state for a ticker (for testing/debugging).
     #[inline]
     pub fn get_ticker_state(&self, ticker_id: TickerId) -> Option<(Price, Qty, Price, Qty)> {
@@ -431,6 +958,7 @@ impl MarketDataPublisher {
 }
 
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod tests {
     use super::*;
     use crate::protocol::MARKET_UPDATE_SIZE;
@@ -443,6 +971,11 @@ mod tests {
             ttl: 1,
             enable_snapshots: true,
             snapshot_interval: 100,
+            max_level_age_ns: 0,
+            stale_level_policy: StaleLevelPolicy::Skip,
+            routes: HashMap::new(),
+            depth_band_width: 0,
+            snapshot_request_port: None,
         }
     }
 
@@ -455,6 +988,8 @@ mod tests {
         assert_eq!(config.ttl, 1);
         assert!(config.enable_snapshots);
         assert_eq!(config.snapshot_interval, 1000);
+        assert_eq!(config.max_level_age_ns, 0);
+        assert_eq!(config.stale_level_policy, StaleLevelPolicy::Skip);
     }
 
     #[test]
@@ -496,11 +1031,49 @@ mod tests {
     #[test]
     fn test_ticker_state_default() {
         let state = TickerState::default();
-        assert_eq!(state.bid_price, 0);
+        assert_eq!(state.bid_price, INVALID_PRICE);
         assert_eq!(state.bid_qty, 0);
-        assert_eq!(state.ask_price, 0);
+        assert_eq!(state.ask_price, INVALID_PRICE);
         assert_eq!(state.ask_qty, 0);
         assert_eq!(state.last_seq, 0);
+        assert!(!state.has_bid());
+        assert!(!state.has_ask());
+    }
+
+    #[test]
+    fn test_ticker_state_zero_and_negative_prices() {
+        let mut state = TickerState::default();
+
+        // A bid at exactly zero is a real level, not "no bid"
+        state.apply(&MarketUpdate::new(MarketUpdateType::Add, 1, 1, 1, 0, 100, 1));
+        assert!(state.has_bid());
+        assert_eq!(state.bid_price, 0);
+
+        // A negative bid is worse than zero and doesn't replace it
+        state.apply(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, 1, -50, 200, 2));
+        assert_eq!(state.bid_price, 0);
+        assert_eq!(state.bid_qty, 100);
+
+        // Cancelling the zero bid empties the side
+        state.apply(&MarketUpdate::new(MarketUpdateType::Cancel, 1, 1, 1, 0, 100, 1));
+        assert!(!state.has_bid());
+        assert_eq!(state.bid_price, INVALID_PRICE);
+
+        // A negative bid on an empty side is tracked
+        state.apply(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, 1, -50, 200, 2));
+        assert!(state.has_bid());
+        assert_eq!(state.bid_price, -50);
+        assert_eq!(state.bid_qty, 200);
+
+        // Negative asks work the same way
+        state.apply(&MarketUpdate::new(MarketUpdateType::Add, 1, 3, -1, -10, 300, 3));
+        assert!(state.has_ask());
+        assert_eq!(state.ask_price, -10);
+
+        // Clear resets both sides to empty
+        state.apply(&MarketUpdate::new(MarketUpdateType::Clear, 1, 0, 0, 0, 0, 4));
+        assert!(!state.has_bid());
+        assert!(!state.has_ask());
     }
 
     #[test]
@@ -542,6 +1115,45 @@ mod tests {
         assert_eq!(state.bid_qty, 0);
     }
 
+    #[test]
+    fn test_snapshot_skips_stale_levels() {
+        let mut state = TickerState::default();
+        state.apply_at(&MarketUpdate::new(MarketUpdateType::Add, 1, 1, 1, 10000, 100, 1), 1, 1_000);
+        state.apply_at(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, -1, 10100, 50, 2), 2, 2_000);
+
+        // Within the window both levels are published
+        let updates = state.snapshot_updates(1, 2, 10_000, 10_000, StaleLevelPolicy::Skip);
+        assert_eq!(updates.len(), 2);
+        assert!(updates.iter().all(|u| !u.is_stale()));
+
+        // Refresh only the ask; the bid ages out
+        state.apply_at(&MarketUpdate::new(MarketUpdateType::Modify, 1, 2, -1, 10100, 60, 2), 3, 15_000);
+        let updates = state.snapshot_updates(1, 3, 20_000, 10_000, StaleLevelPolicy::Skip);
+        assert_eq!(updates.len(), 1);
+        let side = updates[0].side;
+        let qty = updates[0].qty;
+        assert_eq!(side, Side::Sell as i8);
+        assert_eq!(qty, 60);
+
+        // Aging disabled publishes everything
+        let updates = state.snapshot_updates(1, 3, 20_000, 0, StaleLevelPolicy::Skip);
+        assert_eq!(updates.len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_flags_stale_levels() {
+        let mut state = TickerState::default();
+        state.apply_at(&MarketUpdate::new(MarketUpdateType::Add, 1, 1, 1, 10000, 100, 1), 1, 1_000);
+        state.apply_at(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, -1, 10100, 50, 2), 2, 15_000);
+
+        let updates = state.snapshot_updates(1, 2, 20_000, 10_000, StaleLevelPolicy::Flag);
+        assert_eq!(updates.len(), 2);
+        let bid = updates.iter().find(|u| u.side == Side::Buy as i8).unwrap();
+        let ask = updates.iter().find(|u| u.side == Side::Sell as i8).unwrap();
+        assert!(bid.is_stale());
+        assert!(!ask.is_stale());
+    }
+
     #[test]
     fn test_market_update_types() {
         // Test all update types can be created
@@ -582,6 +1194,180 @@ mod tests {
         assert_eq!(cloned.snapshot_interval, config.snapshot_interval);
     }
 
+    #[test]
+    fn test_config_with_route() {
+        let config = MarketDataPublisherConfig::default()
+            .with_route(1, "239.255.0.2", 5002)
+            .with_route(2, "239.255.0.3", 5003);
+
+        assert_eq!(config.routes.len(), 2);
+        assert_eq!(config.routes[&1], FeedRoute::new("239.255.0.2", 5002));
+        assert_eq!(config.routes[&2], FeedRoute::new("239.255.0.3", 5003));
+    }
+
+    #[test]
+    fn test_publisher_routes_tickers_to_groups() {
+        use std::net::UdpSocket;
+        use std::time::Duration;
+
+        // Loopback receivers stand in for the two multicast groups
+        let group_a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let group_b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for socket in [&group_a, &group_b] {
+            socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        }
+        let port_a = group_a.local_addr().unwrap().port();
+        let port_b = group_b.local_addr().unwrap().port();
+
+        let mut config = create_test_config()
+            .with_route(1, "127.0.0.1", port_a)
+            .with_route(2, "127.0.0.1", port_b);
+        config.enable_snapshots = false;
+        let mut publisher = MarketDataPublisher::new(config).unwrap();
+        assert_eq!(publisher.group_count(), 3);
+        assert_eq!(publisher.route_for(1), &FeedRoute::new("127.0.0.1", port_a));
+        assert_eq!(publisher.route_for(3), &FeedRoute::new("239.255.0.1", 5001));
+
+        let update = |ticker_id, order_id| {
+            MarketUpdate::new(MarketUpdateType::Add, ticker_id, order_id, Side::Buy as i8, 10000, 100, 1)
+        };
+        publisher.publish(&update(1, 1)).unwrap();
+        publisher.publish(&update(2, 2)).unwrap();
+        publisher.publish(&update(1, 3)).unwrap();
+
+        let mut buf = [0u8; MARKET_UPDATE_SIZE];
+        for (expected_order_id, expected_seq) in [(1u64, 1u64), (3, 2)] {
+            group_a.recv(&mut buf).unwrap();
+            let received = MarketUpdate::from_bytes(&buf).unwrap();
+            let (ticker_id, order_id, seq) = (received.ticker_id, received.order_id, received.seq);
+            assert_eq!((ticker_id, order_id, seq), (1, expected_order_id, expected_seq));
+        }
+        group_b.recv(&mut buf).unwrap();
+        let received = MarketUpdate::from_bytes(&buf).unwrap();
+        let (ticker_id, order_id) = (received.ticker_id, received.order_id);
+        assert_eq!((ticker_id, order_id), (2, 2));
+
+        // Each group counts its own updates
+        assert_eq!(publisher.ticker_sequence(1), 2);
+        assert_eq!(publisher.ticker_sequence(2), 1);
+        assert_eq!(publisher.sequence(), 0);
+        assert_eq!(publisher.total_updates_sent(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_request_answered_to_sender() {
+        use crate::protocol::SnapshotRequest;
+        use std::net::UdpSocket;
+        use std::time::{Duration, Instant};
+
+        let feed = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = create_test_config()
+            .with_route(1, "127.0.0.1", feed.local_addr().unwrap().port())
+            .with_snapshot_requests(0);
+        config.enable_snapshots = true;
+        let mut publisher = MarketDataPublisher::new(config).unwrap();
+        let request_port = publisher.snapshot_request_port().unwrap();
+
+        publisher
+            .publish(&MarketUpdate::new(MarketUpdateType::Add, 1, 1, Side::Buy as i8, 10000, 100, 1))
+            .unwrap();
+        publisher
+            .publish(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, Side::Sell as i8, 10010, 50, 2))
+            .unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        client
+            .send_to(SnapshotRequest::new(1, 9).as_bytes(), ("127.0.0.1", request_port))
+            .unwrap();
+        // Garbage is ignored
+        client.send_to(&[1, 2, 3], ("127.0.0.1", request_port)).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while publisher.snapshot_requests_served() == 0 && Instant::now() < deadline {
+            publisher.serve_snapshot_requests().unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(publisher.snapshot_requests_served(), 1);
+
+        // An unsequenced Clear at the snapshot's sequence, then both sides
+        let mut buf = [0u8; MARKET_UPDATE_SIZE];
+        let mut reply = Vec::new();
+        for _ in 0..3 {
+            client.recv(&mut buf).unwrap();
+            let update = *MarketUpdate::from_bytes(&buf).unwrap();
+            let (order_id, side, price, priority, seq) =
+                (update.order_id, update.side, update.price, update.priority, update.seq);
+            reply.push((update.update_type().unwrap(), order_id, side, price, priority, seq));
+        }
+        assert_eq!(
+            reply,
+            vec![
+                (MarketUpdateType::Clear, 9, 0, 0, 2, 0),
+                (MarketUpdateType::Snapshot, 9, Side::Buy as i8, 10000, 2, 0),
+                (MarketUpdateType::Snapshot, 9, Side::Sell as i8, 10010, 2, 0),
+            ]
+        );
+
+        // The feed itself carried only the two increments
+        assert_eq!(publisher.ticker_sequence(1), 2);
+        assert_eq!(publisher.total_updates_sent(), 2);
+    }
+
+    #[test]
+    fn test_band_price() {
+        assert_eq!(band_price(102, 0), 102);
+        assert_eq!(band_price(102, 1), 102);
+        assert_eq!(band_price(104, 5), 100);
+        assert_eq!(band_price(105, 5), 105);
+        assert_eq!(band_price(-1, 5), -5);
+    }
+
+    #[test]
+    fn test_banded_depth_aggregates_orders_into_bands() {
+        use std::net::UdpSocket;
+        use std::time::Duration;
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let port = receiver.local_addr().unwrap().port();
+
+        let mut config = create_test_config()
+            .with_route(1, "127.0.0.1", port)
+            .with_depth_bands(5);
+        config.enable_snapshots = false;
+        let mut publisher = MarketDataPublisher::new(config).unwrap();
+
+        let add = |order_id, price, qty| {
+            MarketUpdate::new(MarketUpdateType::Add, 1, order_id, Side::Buy as i8, price, qty, order_id)
+        };
+        publisher
+            .publish_batch(&[add(1, 100, 10), add(2, 101, 20), add(3, 102, 30)])
+            .unwrap();
+        assert_eq!(publisher.total_updates_sent(), 1);
+
+        // The three orders arrive as one band with their summed quantity
+        let mut buf = [0u8; MARKET_UPDATE_SIZE];
+        receiver.recv(&mut buf).unwrap();
+        let level = *MarketUpdate::from_bytes(&buf).unwrap();
+        let (side, price, qty) = (level.side, level.price, level.qty);
+        assert!(level.is_banded());
+        assert_eq!((side, price, qty), (Side::Buy as i8, 100, 60));
+
+        // A receiver rebuilds the same banded book from the level
+        let mut rebuilt = BandedDepth::new();
+        assert!(rebuilt.apply_level(&level));
+        assert_eq!(rebuilt.best_bid(), Some((100, 60)));
+
+        // A sell hitting the band reduces it; the print is still published
+        let trade = MarketUpdate::new(MarketUpdateType::Trade, 1, 3, Side::Sell as i8, 102, 25, 3);
+        publisher.publish(&trade).unwrap();
+        let depth = publisher.banded_depth(1).unwrap();
+        assert_eq!(depth.levels(Side::Buy), vec![(100, 35)]);
+        assert_eq!(depth.level_count(Side::Sell), 0);
+        assert_eq!(publisher.total_updates_sent(), 3);
+    }
+
     // Note: The following tests require network access and may fail in sandboxed environments.
     // They are marked with #[ignore] and can be run manually with `cargo test -- --ignored`
 
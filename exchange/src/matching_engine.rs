@@ -4,16 +4,813 @@
 // 1. Receives client requests from the order server
 // 2. Routes orders to the appropriate order book by ticker
 // 3. Executes matching logic (price-time priority)
-// 4. Generates ClientResponse messages for acknowledgments
+// 4. Generates ClientResponse messages for acknowledgments and fills
 // 5. Generates MarketUpdate messages for market data feed
+//
+// Optional venue behavior, such as last look, stop and all-or-none orders or
+// self-trade prevention, is enabled through `MatchingEngineConfig`.
 
-use common::{TickerId, OrderId, ClientId, Price, Qty, Side};
-use crate::order_book::OrderBook;
+use common::{TickerId, OrderId, ClientId, Price, Qty, Side, InstrumentSpec, InstrumentRegistry, INVALID_PRICE};
+use common::mem_pool::PoolStats;
+use common::time::{monotonic_nanos, Nanos};
+use crate::order_book::{BookFragmentation, Fill, Order, OrderBook, DEFAULT_MAX_LEVELS_PER_SIDE, DEFAULT_MAX_ORDERS};
 use crate::protocol::{
-    ClientRequest, ClientResponse, MarketUpdate,
-    ClientRequestType, ClientResponseType, MarketUpdateType,
+    ClientRequest, ClientResponse, MarketUpdate, QuoteEntry,
+    ClientRequestType, ClientResponseType, MarketUpdateType, LiquidityFlag, TimeInForce,
+    MARKET_UPDATE_FLAG_PRICE_IMPROVEMENT, MAX_MASS_QUOTE_ENTRIES,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Default number of accepted orders remembered for duplicate detection
+pub const DEFAULT_DUPLICATE_WINDOW: usize = 65536;
+
+/// Maker/taker fee rates in basis points of notional
+///
+/// Negative rates are rebates paid to the order owner.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeeSchedule {
+    /// Rate charged to resting orders when they are filled
+    pub maker_fee_bps: f64,
+    /// Rate charged to aggressing orders
+    pub taker_fee_bps: f64,
+}
+
+impl FeeSchedule {
+    /// Create a fee schedule with the given maker and taker rates
+    pub fn new(maker_fee_bps: f64, taker_fee_bps: f64) -> Self {
+        Self { maker_fee_bps, taker_fee_bps }
+    }
+
+    /// Returns the rate that applies to the given liquidity flag
+    #[inline]
+    pub fn rate_bps(&self, liquidity: LiquidityFlag) -> f64 {
+        match liquidity {
+            LiquidityFlag::Maker => self.maker_fee_bps,
+            LiquidityFlag::Taker => self.taker_fee_bps,
+            LiquidityFlag::None => 0.0,
+        }
+    }
+
+    /// Returns the fee in cents for an execution, rounded to the nearest cent
+    ///
+    /// Assumes prices in cents; use `fee_on_notional` for other price scales.
+    #[inline]
+    pub fn fee(&self, liquidity: LiquidityFlag, price: Price, qty: Qty) -> i64 {
+        self.fee_on_notional(liquidity, price * qty as i64)
+    }
+
+    /// Returns the fee in cents on a notional in cents, rounded to the nearest cent
+    #[inline]
+    pub fn fee_on_notional(&self, liquidity: LiquidityFlag, notional_cents: i64) -> i64 {
+        (notional_cents as f64 * self.rate_bps(liquidity) / 10_000.0).round() as i64
+    }
+}
+
+/// Last look parameters
+///
+/// A marketable order is rejected if the mid price moved against the resting
+/// side by more than `threshold` since the start of the window: up for an
+/// aggressive buy, down for an aggressive sell. The engine keeps a short
+/// history of each book's mid price for this, simulating FX-style liquidity
+/// providers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastLookConfig {
+    /// How far back the reference mid is taken, in nanoseconds
+    pub window_ns: u64,
+    /// Largest adverse mid move tolerated, in price units
+    pub threshold: Price,
+}
+
+impl LastLookConfig {
+    /// Create last look parameters
+    pub fn new(window_ns: u64, threshold: Price) -> Self {
+        Self { window_ns, threshold }
+    }
+}
+
+/// What to do with a Modify whose new price crosses the opposite side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModifyCrossMode {
+    /// Reject the modify with ModifyRejected and leave the order as it was
+    #[default]
+    Reject,
+    /// Pull the order and match it as an aggressor at the new price,
+    /// resting any remainder
+    Match,
+}
+
+/// What to do when an incoming order would trade with a resting order of
+/// the same client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelfTradePrevention {
+    /// Let clients trade with themselves
+    #[default]
+    Off,
+    /// Cancel the resting order and keep matching the incoming one; the
+    /// resting order's owner gets Canceled and a Cancel update is published
+    CancelResting,
+    /// Cancel the remainder of the incoming order: Filled with zero leaves
+    /// for what traded, Canceled if nothing did
+    CancelIncoming,
+}
+
+/// Which Modify requests keep a resting order's time priority
+///
+/// Venues differ here, so each case is configurable where venues disagree:
+///
+/// - Quantity reduced at the same price: keeps priority when
+///   `qty_down_keeps` is set (the default)
+/// - Price made less aggressive (a lower buy, a higher sell): keeps priority
+///   when `less_aggressive_keeps` is set (off by default); a quantity
+///   reduction in the same Modify must also be allowed by `qty_down_keeps`
+/// - Price made more aggressive or quantity increased: always loses priority
+///
+/// A Modify that changes nothing keeps priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityPolicy {
+    /// Keep priority when only the quantity goes down
+    pub qty_down_keeps: bool,
+    /// Keep priority when the price moves away from the opposite side
+    pub less_aggressive_keeps: bool,
+}
+
+impl Default for PriorityPolicy {
+    fn default() -> Self {
+        Self {
+            qty_down_keeps: true,
+            less_aggressive_keeps: false,
+        }
+    }
+}
+
+impl PriorityPolicy {
+    /// Create the default policy: quantity reductions keep priority, any
+    /// price change loses it
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether quantity reductions keep priority
+    pub fn with_qty_down_keeps(mut self, keeps: bool) -> Self {
+        self.qty_down_keeps = keeps;
+        self
+    }
+
+    /// Set whether less aggressive reprices keep priority
+    pub fn with_less_aggressive_keeps(mut self, keeps: bool) -> Self {
+        self.less_aggressive_keeps = keeps;
+        self
+    }
+
+    /// Returns true if a Modify from (old_price, old_qty) to (price, qty)
+    /// keeps the order's time priority
+    pub fn keeps_priority(&self, side: Side, old_price: Price, old_qty: Qty, price: Price, qty: Qty) -> bool {
+        let qty_keeps = qty == old_qty || (qty < old_qty && self.qty_down_keeps);
+        let less_aggressive = match side {
+            Side::Buy => price < old_price,
+            Side::Sell => price > old_price,
+        };
+        let price_keeps = price == old_price || (less_aggressive && self.less_aggressive_keeps);
+        qty_keeps && price_keeps
+    }
+}
+
+/// What happens to the part of an order stopped at the protected price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtectedResidual {
+    /// Drop the residual; an order that executed nothing is rejected
+    #[default]
+    Reject,
+    /// Rest the residual at the protected price
+    Hold,
+}
+
+/// What happens to an all-or-none order the book cannot fill on arrival
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AonResidual {
+    /// Rest in the AON book until it can be filled in full. Resting AONs
+    /// are not published and never trade against incoming orders; they are
+    /// re-checked in arrival order after every request on their ticker.
+    #[default]
+    Rest,
+    /// Cancel the order
+    Cancel,
+}
+
+/// How aggressive orders are matched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchingAlgo {
+    /// Price-time priority up to the order's limit price
+    #[default]
+    PriceTime,
+    /// Price-time priority, but never at a price worse than the ticker's
+    /// protected best price (see `MatchingEngine::set_protected_price`), as
+    /// under Reg NMS order protection: the sweep stops at the protected price
+    /// and the residual is handled per `ProtectedResidual`
+    ProtectBestPrice(ProtectedResidual),
+}
+
+/// Response to a client flagged for flicker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlickerAction {
+    /// Record the flag and raise an alert only
+    #[default]
+    Flag,
+    /// Also reject the client's new orders until its window resets
+    Throttle,
+    /// Also ask the order server to disconnect the client
+    Disconnect,
+}
+
+/// Flicker (quote stuffing) detection parameters
+///
+/// A cancel of an order that rested for less than `max_lifetime_ns` counts
+/// as flicker. A client with `threshold` or more flicker cancels within one
+/// `window_ns` window is flagged: an alert is queued for
+/// `MatchingEngine::drain_flicker_alerts`, and `action` decides what else
+/// happens to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlickerConfig {
+    /// Longest order lifetime counted as flicker, in nanoseconds
+    pub max_lifetime_ns: u64,
+    /// Length of the counting window, in nanoseconds
+    pub window_ns: u64,
+    /// Flicker cancels per window that flag a client
+    pub threshold: u32,
+    /// What happens to a flagged client
+    pub action: FlickerAction,
+}
+
+impl FlickerConfig {
+    /// Create flicker detection parameters that only flag clients
+    pub fn new(max_lifetime_ns: u64, window_ns: u64, threshold: u32) -> Self {
+        Self {
+            max_lifetime_ns,
+            window_ns,
+            threshold,
+            action: FlickerAction::Flag,
+        }
+    }
+
+    /// Set the action taken against flagged clients
+    pub fn with_action(mut self, action: FlickerAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+/// Per-client flicker metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientFlickerStats {
+    /// Orders canceled by the client
+    pub cancels: u64,
+    /// Cancels of orders younger than the flicker lifetime
+    pub flicker_cancels: u64,
+    /// Times the client has been flagged
+    pub flags: u64,
+    /// New orders rejected while the client was throttled
+    pub throttled_orders: u64,
+    /// Flicker cancels in the current window
+    pub window_flicker: u32,
+    /// Start of the current window, in nanoseconds
+    pub window_start: u64,
+    /// Whether the client is flagged in the current window
+    pub flagged: bool,
+}
+
+impl ClientFlickerStats {
+    /// Start a new window if the current one has expired
+    fn roll_window(&mut self, now: u64, window_ns: u64) {
+        if now.saturating_sub(self.window_start) >= window_ns {
+            self.window_start = now;
+            self.window_flicker = 0;
+            self.flagged = false;
+        }
+    }
+}
+
+/// Raised when a client is flagged for flicker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlickerAlert {
+    /// Flagged client
+    pub client_id: ClientId,
+    /// Flicker cancels in the window that tripped the threshold
+    pub flicker_cancels: u32,
+    /// When the client was flagged, in nanoseconds
+    pub timestamp: u64,
+    /// Configured action for the client
+    pub action: FlickerAction,
+}
+
+/// Source of the engine's timestamps, in nanoseconds
+pub type EngineClock = Box<dyn Fn() -> u64 + Send>;
+
+/// Configuration for the matching engine
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchingEngineConfig {
+    /// Flag Add updates for orders that set a new best price
+    pub detect_price_improvement: bool,
+    /// Fees applied to executions
+    pub fee_schedule: FeeSchedule,
+    /// Treat a repeated (client_id, client_order_id) on a New request as a
+    /// resend: replay the original response instead of adding a second order
+    pub detect_duplicate_orders: bool,
+    /// Most recent accepted orders remembered for duplicate detection
+    /// (0 uses the default)
+    pub duplicate_window: usize,
+    /// Reject marketable orders after an adverse mid move (None disables)
+    pub last_look: Option<LastLookConfig>,
+    /// Cap on price levels per side of each book (0 uses the default)
+    pub max_levels_per_side: usize,
+    /// Capacity of each book's order pool (0 uses the default)
+    pub max_orders_per_book: usize,
+    /// Most tickers the engine accepts (0 for no limit)
+    pub max_tickers: usize,
+    /// Handling of a Modify that would cross the book
+    pub modify_cross_mode: ModifyCrossMode,
+    /// Matching of aggressive orders
+    pub matching_algo: MatchingAlgo,
+    /// Flag clients that cancel short-lived orders too often (None disables)
+    pub flicker: Option<FlickerConfig>,
+    /// Record matching start and end times of each request
+    pub record_timestamps: bool,
+    /// Default time-priority rules for Modify, overridable per ticker
+    pub priority_policy: PriorityPolicy,
+    /// Keep per-ticker order flow statistics
+    pub order_flow_stats: bool,
+    /// Most distinct price levels a client may rest at per ticker (0 for no limit)
+    pub max_levels_per_client: usize,
+    /// Accept stop-market and stop-limit orders
+    pub stop_orders: bool,
+    /// Accept all-or-none orders, handling ones that cannot fill on arrival
+    /// as given (None disables)
+    pub aon_orders: Option<AonResidual>,
+    /// Track each client's net position per ticker from its fills
+    pub client_positions: bool,
+    /// Fragmentation ratio a book must exceed to be rebuilt by `compact`
+    /// (0.0 compacts any fragmented book)
+    pub compaction_threshold: f64,
+    /// Accept mass quotes
+    pub mass_quotes: bool,
+    /// Handling of an incoming order that would trade with its own client's
+    /// resting orders
+    pub self_trade_prevention: SelfTradePrevention,
+    /// Let an Amend increase quantity, at the cost of time priority
+    pub amend_qty_up: bool,
+}
+
+impl MatchingEngineConfig {
+    /// Create a new configuration with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable price improvement detection
+    pub fn with_price_improvement_detection(mut self, enabled: bool) -> Self {
+        self.detect_price_improvement = enabled;
+        self
+    }
+
+    /// Enable or disable duplicate client order ID detection
+    pub fn with_duplicate_detection(mut self, enabled: bool) -> Self {
+        self.detect_duplicate_orders = enabled;
+        self
+    }
+
+    /// Remember only the last `window` accepted orders for duplicate
+    /// detection; a resend of an older order is treated as a new one
+    pub fn with_duplicate_window(mut self, window: usize) -> Self {
+        self.duplicate_window = window;
+        self
+    }
+
+    /// Cap the number of price levels per side of each order book
+    pub fn with_max_levels_per_side(mut self, max_levels: usize) -> Self {
+        self.max_levels_per_side = max_levels;
+        self
+    }
+
+    /// Set how aggressive orders are matched
+    pub fn with_matching_algo(mut self, algo: MatchingAlgo) -> Self {
+        self.matching_algo = algo;
+        self
+    }
+
+    /// Size each order book's order pool for this many resting orders
+    pub fn with_max_orders_per_book(mut self, max_orders: usize) -> Self {
+        self.max_orders_per_book = max_orders;
+        self
+    }
+
+    /// Cap the number of tickers the engine accepts
+    pub fn with_max_tickers(mut self, max_tickers: usize) -> Self {
+        self.max_tickers = max_tickers;
+        self
+    }
+
+    /// Set how a Modify that would cross the book is handled
+    pub fn with_modify_cross_mode(mut self, mode: ModifyCrossMode) -> Self {
+        self.modify_cross_mode = mode;
+        self
+    }
+
+    /// Set how orders that would trade with their own client are handled
+    pub fn with_self_trade_prevention(mut self, mode: SelfTradePrevention) -> Self {
+        self.self_trade_prevention = mode;
+        self
+    }
+
+    /// Enable or disable quantity increases by Amend
+    pub fn with_amend_qty_up(mut self, enabled: bool) -> Self {
+        self.amend_qty_up = enabled;
+        self
+    }
+
+    /// Set the default time-priority rules for Modify
+    pub fn with_priority_policy(mut self, policy: PriorityPolicy) -> Self {
+        self.priority_policy = policy;
+        self
+    }
+
+    /// Enable or disable recording of matching timestamps, reported by
+    /// `MatchingEngine::last_match` for the order server's latency breakdown
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.record_timestamps = enabled;
+        self
+    }
+
+    /// Set the most distinct price levels a client may rest at per ticker
+    /// (0 for no limit)
+    pub fn with_max_levels_per_client(mut self, max_levels: usize) -> Self {
+        self.max_levels_per_client = max_levels;
+        self
+    }
+
+    /// Enable or disable stop-market and stop-limit orders
+    ///
+    /// A stop is activated when a trade prints at or through its trigger (at
+    /// or above it for a buy, at or below for a sell). Its owner gets
+    /// Triggered followed by the order's accept or fill, queued with the
+    /// maker fills, and trades made by activated stops may trigger further
+    /// stops.
+    pub fn with_stop_orders(mut self, enabled: bool) -> Self {
+        self.stop_orders = enabled;
+        self
+    }
+
+    /// Enable all-or-none orders
+    pub fn with_aon_orders(mut self, residual: AonResidual) -> Self {
+        self.aon_orders = Some(residual);
+        self
+    }
+
+    /// Enable or disable per-client position tracking
+    pub fn with_client_positions(mut self, enabled: bool) -> Self {
+        self.client_positions = enabled;
+        self
+    }
+
+    /// Enable or disable mass quotes
+    pub fn with_mass_quotes(mut self, enabled: bool) -> Self {
+        self.mass_quotes = enabled;
+        self
+    }
+
+    /// Set the fragmentation ratio above which `compact` rebuilds a book
+    pub fn with_compaction_threshold(mut self, threshold: f64) -> Self {
+        self.compaction_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Enable or disable per-ticker order flow statistics: counts of adds,
+    /// cancels, modifies and trades, and how long orders rested before being
+    /// canceled or fully filled
+    pub fn with_order_flow_stats(mut self, enabled: bool) -> Self {
+        self.order_flow_stats = enabled;
+        self
+    }
+
+    /// Enable flicker detection
+    pub fn with_flicker_detection(mut self, flicker: FlickerConfig) -> Self {
+        self.flicker = Some(flicker);
+        self
+    }
+
+    /// Enable last look with the given window and threshold
+    pub fn with_last_look(mut self, window_ns: u64, threshold: Price) -> Self {
+        self.last_look = Some(LastLookConfig::new(window_ns, threshold));
+        self
+    }
+
+    /// Set the maker/taker fee schedule
+    pub fn with_fee_schedule(mut self, fee_schedule: FeeSchedule) -> Self {
+        self.fee_schedule = fee_schedule;
+        self
+    }
+}
+
+/// Counters maintained by the matching engine
+#[derive(Debug, Clone, Default)]
+pub struct MatchingEngineStats {
+    /// Orders that set a new best price on their side of the book
+    pub price_improvements: u64,
+    /// Executions against resting orders
+    pub fills: u64,
+    /// Fees charged to makers, in cents (negative for net rebates)
+    pub maker_fees: i64,
+    /// Fees charged to takers, in cents
+    pub taker_fees: i64,
+    /// New requests answered from the duplicate-detection cache
+    pub duplicate_orders: u64,
+    /// Marketable orders rejected by last look
+    pub last_look_rejects: u64,
+    /// Modifies rejected because the new price crossed the book
+    pub modify_cross_rejects: u64,
+    /// Orders whose sweep was stopped at the protected price
+    pub protected_stops: u64,
+    /// New orders and modifies rejected for a price off the tick grid
+    pub off_tick_rejects: u64,
+    /// New orders and modifies rejected for a quantity that is not whole lots
+    pub off_lot_rejects: u64,
+    /// New orders and modifies rejected for a notional outside the
+    /// instrument's bounds
+    pub notional_rejects: u64,
+    /// New orders rejected by the per-client price level cap
+    pub level_limit_rejects: u64,
+    /// Stop orders activated by a trade at their trigger price
+    pub stops_triggered: u64,
+    /// All-or-none orders filled, on arrival or from the AON book
+    pub aon_executions: u64,
+    /// Market orders that traded
+    pub market_orders: u64,
+    /// Fill-or-kill orders rejected for lack of crossing liquidity
+    pub fok_rejects: u64,
+    /// New orders rejected by a client position limit
+    pub risk_rejects: u64,
+    /// Books rebuilt by `compact`
+    pub compactions: u64,
+    /// Mass quotes processed
+    pub mass_quotes: u64,
+    /// Trades with the same client on both sides prevented
+    pub self_trades_prevented: u64,
+}
+
+impl MatchingEngineStats {
+    /// Reset all counters to zero
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Order flow counters for one ticker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EngineStats {
+    /// New orders accepted
+    pub adds: u64,
+    /// Orders canceled
+    pub cancels: u64,
+    /// Modifies accepted
+    pub modifies: u64,
+    /// Executions against resting orders
+    pub trades: u64,
+    /// Orders that left the book by cancel or full fill with a known add time
+    pub closed_orders: u64,
+    /// Total time those orders rested, in nanoseconds
+    pub total_resting_ns: u64,
+}
+
+impl EngineStats {
+    /// Cancels per trade, None before the first trade
+    pub fn cancel_to_trade_ratio(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.cancels as f64 / self.trades as f64)
+    }
+
+    /// Average time an order rested before it was canceled or fully filled,
+    /// in nanoseconds (0 before any order left the book)
+    pub fn avg_resting_ns(&self) -> u64 {
+        self.total_resting_ns.checked_div(self.closed_orders).unwrap_or(0)
+    }
+
+    /// Reset all counters to zero
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Pool utilization of one ticker's order book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookPoolStats {
+    /// Ticker of the book
+    pub ticker_id: TickerId,
+    /// Order pool utilization
+    pub orders: PoolStats,
+    /// Price level pool utilization
+    pub levels: PoolStats,
+    /// How scattered the book is across its pools
+    pub fragmentation: BookFragmentation,
+}
+
+/// Client-side identity of a resting order, needed to report its fills
+#[derive(Debug, Clone, Copy)]
+struct RestingOrderInfo {
+    client_order_id: OrderId,
+    tag: u64,
+    /// When the order started resting, if flicker detection or order flow
+    /// statistics are enabled
+    added_at: u64,
+}
+
+/// Resting orders per price level for each client and ticker
+#[derive(Debug, Default)]
+struct ClientLevels {
+    levels: HashMap<(ClientId, TickerId), HashMap<(Side, Price), u32>>,
+}
+
+impl ClientLevels {
+    /// Count an order resting at a level
+    fn add(&mut self, client_id: ClientId, ticker_id: TickerId, side: Side, price: Price) {
+        *self
+            .levels
+            .entry((client_id, ticker_id))
+            .or_default()
+            .entry((side, price))
+            .or_insert(0) += 1;
+    }
+
+    /// Forget an order that left a level
+    fn remove(&mut self, client_id: ClientId, ticker_id: TickerId, side: Side, price: Price) {
+        let Some(levels) = self.levels.get_mut(&(client_id, ticker_id)) else {
+            return;
+        };
+        if let Some(count) = levels.get_mut(&(side, price)) {
+            *count -= 1;
+            if *count == 0 {
+                levels.remove(&(side, price));
+            }
+        }
+        if levels.is_empty() {
+            self.levels.remove(&(client_id, ticker_id));
+        }
+    }
+
+    /// Number of distinct levels a client rests at on a ticker
+    fn count(&self, client_id: ClientId, ticker_id: TickerId) -> usize {
+        self.levels.get(&(client_id, ticker_id)).map_or(0, HashMap::len)
+    }
+
+    /// Returns true if the client already rests at the level
+    fn contains(&self, client_id: ClientId, ticker_id: TickerId, side: Side, price: Price) -> bool {
+        self.levels
+            .get(&(client_id, ticker_id))
+            .is_some_and(|levels| levels.contains_key(&(side, price)))
+    }
+}
+
+/// Net position and position limit per client and ticker
+#[derive(Debug, Default)]
+struct ClientPositions {
+    positions: HashMap<(ClientId, TickerId), i64>,
+    limits: HashMap<(ClientId, TickerId), i64>,
+}
+
+impl ClientPositions {
+    /// Apply a fill to a client's position
+    fn record_fill(&mut self, client_id: ClientId, ticker_id: TickerId, side: Side, qty: Qty) {
+        *self.positions.entry((client_id, ticker_id)).or_insert(0) += side as i64 * qty as i64;
+    }
+
+    /// Net position of a client on a ticker
+    fn position(&self, client_id: ClientId, ticker_id: TickerId) -> i64 {
+        self.positions.get(&(client_id, ticker_id)).copied().unwrap_or(0)
+    }
+
+    /// Returns true if an order would take the client past its limit
+    ///
+    /// Only orders that grow the absolute position are checked, assuming
+    /// they fill in full; resting orders are not counted.
+    fn breaches_limit(&self, client_id: ClientId, ticker_id: TickerId, side: Side, qty: Qty) -> bool {
+        let Some(&limit) = self.limits.get(&(client_id, ticker_id)) else {
+            return false;
+        };
+        let position = self.position(client_id, ticker_id);
+        let projected = position + side as i64 * qty as i64;
+        projected.abs() > limit && projected.abs() > position.abs()
+    }
+}
+
+/// A stop order waiting for its trigger price
+#[derive(Debug, Clone, Copy)]
+struct StopOrder {
+    client_id: ClientId,
+    client_order_id: OrderId,
+    market_order_id: OrderId,
+    side: Side,
+    stop_price: Price,
+    /// Limit price once activated, None for a stop-market order
+    limit: Option<Price>,
+    qty: Qty,
+    tag: u64,
+}
+
+/// Stop orders of one ticker, by side and trigger price
+#[derive(Debug, Default)]
+struct StopBook {
+    buys: BTreeMap<Price, Vec<StopOrder>>,
+    sells: BTreeMap<Price, Vec<StopOrder>>,
+    /// Side and trigger price of every stop, by exchange order ID
+    index: HashMap<OrderId, (Side, Price)>,
+}
+
+impl StopBook {
+    fn insert(&mut self, stop: StopOrder) {
+        let stops = match stop.side {
+            Side::Buy => &mut self.buys,
+            Side::Sell => &mut self.sells,
+        };
+        stops.entry(stop.stop_price).or_default().push(stop);
+        self.index.insert(stop.market_order_id, (stop.side, stop.stop_price));
+    }
+
+    fn get(&self, order_id: OrderId) -> Option<&StopOrder> {
+        let (side, stop_price) = self.index.get(&order_id)?;
+        let stops = match side {
+            Side::Buy => &self.buys,
+            Side::Sell => &self.sells,
+        };
+        stops.get(stop_price)?.iter().find(|stop| stop.market_order_id == order_id)
+    }
+
+    fn remove(&mut self, order_id: OrderId) -> Option<StopOrder> {
+        let (side, stop_price) = self.index.remove(&order_id)?;
+        let stops = match side {
+            Side::Buy => &mut self.buys,
+            Side::Sell => &mut self.sells,
+        };
+        let level = stops.get_mut(&stop_price)?;
+        let position = level.iter().position(|stop| stop.market_order_id == order_id)?;
+        let stop = level.remove(position);
+        if level.is_empty() {
+            stops.remove(&stop_price);
+        }
+        Some(stop)
+    }
+
+    /// Remove and return the stops touched by trades between `low` and
+    /// `high`: buys triggering at or below `high`, then sells triggering at
+    /// or above `low`, each in trigger then arrival order
+    fn take_triggered(&mut self, high: Price, low: Price) -> Vec<StopOrder> {
+        let mut triggered = Vec::new();
+        let untouched_buys = self.buys.split_off(&high.saturating_add(1));
+        triggered.extend(std::mem::replace(&mut self.buys, untouched_buys).into_values().flatten());
+        let touched_sells = self.sells.split_off(&low);
+        triggered.extend(touched_sells.into_values().rev().flatten());
+        for stop in &triggered {
+            self.index.remove(&stop.market_order_id);
+        }
+        triggered
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
+/// An order about to be matched and rested
+#[derive(Debug, Clone, Copy)]
+struct OrderEntry {
+    client_id: ClientId,
+    ticker_id: TickerId,
+    client_order_id: OrderId,
+    market_order_id: OrderId,
+    side: Side,
+    price: Price,
+    qty: Qty,
+    tag: u64,
+    /// Drop any quantity that does not trade instead of resting it
+    immediate: bool,
+}
+
+/// Returns the average price of executions totalling `notional` over `qty`,
+/// rounded to the nearest price unit with halves rounded up
+///
+/// The notional is accumulated in 128 bits, so large fills can't overflow.
+#[inline]
+fn average_price(notional: i128, qty: Qty) -> Price {
+    let qty = qty as i128;
+    (2 * notional + qty).div_euclid(2 * qty) as Price
+}
+
+/// A resting order pulled by self-trade prevention, to be reported after
+/// the fills that preceded it
+#[derive(Debug, Clone, Copy)]
+struct SelfTradeCancel {
+    /// Number of the incoming order's fills that came before the cancel
+    after_fills: usize,
+    /// Canceled response for the resting order's owner
+    response: ClientResponse,
+    /// Cancel market update for the resting order
+    update: MarketUpdate,
+}
 
 /// The matching engine routes orders to order books and generates responses
 pub struct MatchingEngine {
@@ -21,25 +818,234 @@ pub struct MatchingEngine {
     order_books: HashMap<TickerId, OrderBook>,
     /// Next order ID to assign (exchange-assigned IDs)
     next_order_id: OrderId,
+    /// Engine configuration
+    config: MatchingEngineConfig,
+    /// Engine counters
+    stats: MatchingEngineStats,
+    /// Client order ID and tag of every resting order, by exchange order ID
+    resting_orders: HashMap<OrderId, RestingOrderInfo>,
+    /// Fill responses for resting orders and responses for triggered stops,
+    /// waiting to be delivered
+    maker_fills: Vec<ClientResponse>,
+    /// Original response to each recently accepted New request, when
+    /// duplicate detection is enabled
+    submitted_orders: HashMap<(ClientId, OrderId), ClientResponse>,
+    /// Keys of `submitted_orders`, oldest first
+    submitted_order_keys: VecDeque<(ClientId, OrderId)>,
+    /// Timestamped mid price changes per ticker, kept when last look is enabled
+    mid_history: HashMap<TickerId, VecDeque<(u64, Price)>>,
+    /// Flicker metrics per client, kept when flicker detection is enabled
+    flicker_stats: HashMap<ClientId, ClientFlickerStats>,
+    /// Flicker alerts waiting to be collected
+    flicker_alerts: Vec<FlickerAlert>,
+    /// Timestamp source for last look and flicker detection
+    clock: EngineClock,
+    /// Matching start and end of the last request, when timestamps are enabled
+    last_match: Option<(Nanos, Nanos)>,
+    /// Modify priority rules for tickers that override the configured default
+    priority_policies: HashMap<TickerId, PriorityPolicy>,
+    /// Protected (bid, ask) per ticker, INVALID_PRICE for an unprotected side
+    protected_prices: HashMap<TickerId, (Price, Price)>,
+    /// Instrument specs for tickers that override the default
+    instruments: InstrumentRegistry,
+    /// Order flow counters per ticker, when enabled
+    order_flow: HashMap<TickerId, EngineStats>,
+    /// Price levels each client rests at, kept when the level cap is enabled
+    client_levels: ClientLevels,
+    /// Stop orders waiting for their trigger, per ticker
+    stop_books: HashMap<TickerId, StopBook>,
+    /// Last trade price per ticker, kept when stop orders are enabled
+    last_trades: HashMap<TickerId, Price>,
+    /// All-or-none orders waiting for enough liquidity, per ticker in
+    /// arrival order
+    aon_books: HashMap<TickerId, Vec<OrderEntry>>,
+    /// Positions each client holds, kept when position tracking is enabled
+    client_positions: ClientPositions,
+    /// Exchange order IDs of each client's last mass quote, per ticker
+    quotes: HashMap<(ClientId, TickerId), Vec<OrderId>>,
 }
 
 impl MatchingEngine {
     /// Creates a new matching engine with no order books
     pub fn new() -> Self {
+        Self::with_config(MatchingEngineConfig::default())
+    }
+
+    /// Creates a new matching engine with the given configuration
+    pub fn with_config(config: MatchingEngineConfig) -> Self {
         Self {
             order_books: HashMap::new(),
             next_order_id: 1,
+            config,
+            stats: MatchingEngineStats::default(),
+            resting_orders: HashMap::new(),
+            maker_fills: Vec::new(),
+            submitted_orders: HashMap::new(),
+            submitted_order_keys: VecDeque::new(),
+            mid_history: HashMap::new(),
+            flicker_stats: HashMap::new(),
+            flicker_alerts: Vec::new(),
+            clock: Box::new(|| monotonic_nanos().as_u64()),
+            last_match: None,
+            priority_policies: HashMap::new(),
+            protected_prices: HashMap::new(),
+            instruments: InstrumentRegistry::new(),
+            order_flow: HashMap::new(),
+            client_levels: ClientLevels::default(),
+            stop_books: HashMap::new(),
+            last_trades: HashMap::new(),
+            aon_books: HashMap::new(),
+            client_positions: ClientPositions::default(),
+            quotes: HashMap::new(),
         }
     }
 
+    /// Replaces the engine's clock
+    ///
+    /// Useful for simulations and tests that need to control time.
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
     /// Adds a new ticker to the matching engine
     ///
-    /// Creates an order book for the given ticker ID.
-    /// Does nothing if the ticker already exists.
-    pub fn add_ticker(&mut self, ticker_id: TickerId) {
+    /// Creates an order book for the given ticker ID, with pools sized from
+    /// the configuration. Does nothing if the ticker already exists.
+    ///
+    /// # Returns
+    /// True if the ticker has a book, false if adding it would exceed
+    /// `max_tickers`
+    pub fn add_ticker(&mut self, ticker_id: TickerId) -> bool {
+        if self.order_books.contains_key(&ticker_id) {
+            return true;
+        }
+        if self.config.max_tickers > 0 && self.order_books.len() >= self.config.max_tickers {
+            return false;
+        }
+
+        let max_levels = match self.config.max_levels_per_side {
+            0 => DEFAULT_MAX_LEVELS_PER_SIDE,
+            max_levels => max_levels,
+        };
+        let max_orders = match self.config.max_orders_per_book {
+            0 => DEFAULT_MAX_ORDERS,
+            max_orders => max_orders,
+        };
         self.order_books
-            .entry(ticker_id)
-            .or_insert_with(|| OrderBook::new(ticker_id));
+            .insert(ticker_id, OrderBook::with_capacity(ticker_id, max_orders, max_levels));
+        true
+    }
+
+    /// Sets the Modify priority rules for one ticker
+    pub fn set_priority_policy(&mut self, ticker_id: TickerId, policy: PriorityPolicy) {
+        self.priority_policies.insert(ticker_id, policy);
+    }
+
+    /// Returns the Modify priority rules in effect for a ticker
+    pub fn priority_policy(&self, ticker_id: TickerId) -> PriorityPolicy {
+        self.priority_policies
+            .get(&ticker_id)
+            .copied()
+            .unwrap_or(self.config.priority_policy)
+    }
+
+    /// Caps a client's absolute net position on a ticker
+    ///
+    /// This is the venue's own pre-trade risk gate, independent of any
+    /// client-side checks: a New order that would take the client past the
+    /// cap, were it to fill in full, gets RiskRejected. Enables position
+    /// tracking if it is not on already; fills before that are not counted.
+    pub fn set_client_position_limit(&mut self, client_id: ClientId, ticker_id: TickerId, max_position: i64) {
+        self.config.client_positions = true;
+        self.client_positions.limits.insert((client_id, ticker_id), max_position);
+    }
+
+    /// Removes a client's position limit on a ticker
+    pub fn clear_client_position_limit(&mut self, client_id: ClientId, ticker_id: TickerId) {
+        self.client_positions.limits.remove(&(client_id, ticker_id));
+    }
+
+    /// Returns a client's net position on a ticker as tracked by the engine
+    pub fn client_position(&self, client_id: ClientId, ticker_id: TickerId) -> i64 {
+        self.client_positions.position(client_id, ticker_id)
+    }
+
+    /// Sets the instrument spec of a ticker
+    ///
+    /// Prices off the spec's tick grid are rejected, and fees are computed on
+    /// the instrument's notional in cents rather than the raw integer price.
+    pub fn set_instrument(&mut self, spec: InstrumentSpec) {
+        self.instruments.insert(spec);
+    }
+
+    /// Replaces every instrument spec with those of a registry
+    pub fn set_instruments(&mut self, instruments: InstrumentRegistry) {
+        self.instruments = instruments;
+    }
+
+    /// Returns the instrument specs the engine validates against
+    pub fn instruments(&self) -> &InstrumentRegistry {
+        &self.instruments
+    }
+
+    /// Returns the instrument spec of a ticker, the default spec if none was set
+    pub fn instrument(&self, ticker_id: TickerId) -> InstrumentSpec {
+        self.instruments.get(ticker_id)
+    }
+
+    /// Checks a limit order's quantity against the lot size and its notional
+    /// against the bounds of its instrument, counting a failure
+    ///
+    /// # Returns
+    /// `true` if the order fits the instrument
+    fn check_lot_and_notional(&mut self, ticker_id: TickerId, price: Price, qty: Qty) -> bool {
+        let instrument = self.instrument(ticker_id);
+        if !instrument.is_valid_lot(qty) {
+            self.stats.off_lot_rejects += 1;
+            return false;
+        }
+        if !instrument.is_valid_notional(price, qty) {
+            self.stats.notional_rejects += 1;
+            return false;
+        }
+        true
+    }
+
+    /// Returns the number of implied decimal places in a ticker's prices
+    pub fn price_scale(&self, ticker_id: TickerId) -> u8 {
+        self.instrument(ticker_id).price_scale
+    }
+
+    /// Sets the protected best prices of a ticker
+    ///
+    /// Under `MatchingAlgo::ProtectBestPrice`, no buy executes above `ask`
+    /// and no sell below `bid`. Pass INVALID_PRICE to leave a side
+    /// unprotected.
+    pub fn set_protected_price(&mut self, ticker_id: TickerId, bid: Price, ask: Price) {
+        self.protected_prices.insert(ticker_id, (bid, ask));
+    }
+
+    /// Removes a ticker's protected prices
+    pub fn clear_protected_price(&mut self, ticker_id: TickerId) {
+        self.protected_prices.remove(&ticker_id);
+    }
+
+    /// Returns a ticker's protected (bid, ask), if set
+    pub fn protected_price(&self, ticker_id: TickerId) -> Option<(Price, Price)> {
+        self.protected_prices.get(&ticker_id).copied()
+    }
+
+    /// Returns the protected price an order's limit would trade through, if any
+    fn protected_limit(&self, ticker_id: TickerId, side: Side, price: Price) -> Option<Price> {
+        let MatchingAlgo::ProtectBestPrice(_) = self.config.matching_algo else {
+            return None;
+        };
+        let &(bid, ask) = self.protected_prices.get(&ticker_id)?;
+        match side {
+            Side::Buy => (ask != INVALID_PRICE && price > ask).then_some(ask),
+            Side::Sell => (bid != INVALID_PRICE && price < bid).then_some(bid),
+        }
     }
 
     /// Process a client request and generate responses
@@ -47,30 +1053,247 @@ impl MatchingEngine {
     /// Returns a tuple of:
     /// - ClientResponse: acknowledgment to send back to the client
     /// - Vec<MarketUpdate>: market data updates to broadcast
+    ///
+    /// If the request traded against resting orders, the fills for those
+    /// orders' owners are queued; collect them with `drain_maker_fills`.
     pub fn process_request(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+        let match_start = self.config.record_timestamps.then(monotonic_nanos);
+
         // Extract fields from packed struct to avoid unaligned reference issues
         let msg_type = request.msg_type;
+        let ticker_id = request.ticker_id;
+        let tag = request.tag;
+        let origin_ts = request.origin_ts;
 
-        match ClientRequestType::from_u8(msg_type) {
-            Some(ClientRequestType::New) => self.handle_new_order(request),
+        let (response, mut updates) = match ClientRequestType::from_u8(msg_type) {
+            Some(ClientRequestType::New) => self.handle_new_order(request, false),
             Some(ClientRequestType::Cancel) => self.handle_cancel(request),
+            Some(ClientRequestType::Modify) => self.handle_modify(request),
+            Some(ClientRequestType::Amend) => self.handle_amend(request),
+            // Logins are handled by the order server and never reach a book
+            Some(ClientRequestType::Login) => self.handle_invalid_request(request),
+            // Fill queries are answered by the order server from its retained fills
+            Some(ClientRequestType::FillQuery) => self.handle_invalid_request(request),
+            Some(ClientRequestType::NewStop) => self.handle_new_stop(request, false),
+            Some(ClientRequestType::NewStopLimit) => self.handle_new_stop(request, true),
+            Some(ClientRequestType::NewAon) => self.handle_new_order(request, true),
+            Some(ClientRequestType::NewMarket) => self.handle_new_market(request),
+            // Mass quotes carry their entries and go through process_mass_quote
+            Some(ClientRequestType::MassQuote) => self.handle_invalid_request(request),
             None => self.handle_invalid_request(request),
+        };
+
+        // A changed book may now fill a waiting all-or-none order
+        if self.config.aon_orders.is_some() && !updates.is_empty() {
+            self.match_resting_aons(ticker_id, &mut updates);
         }
-    }
 
-    /// Handle a new order request
-    ///
-    /// Attempts to add the order to the appropriate order book.
-    /// Returns an Accepted response and Add market update on success.
-    fn handle_new_order(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
-        // Extract fields from packed struct
-        let client_id = request.client_id;
-        let ticker_id = request.ticker_id;
-        let client_order_id = request.order_id;
-        let side_raw = request.side;
+        if self.config.stop_orders {
+            self.trigger_stops(ticker_id, &mut updates);
+        }
+
+        if !updates.is_empty() {
+            self.record_mid(ticker_id);
+        }
+
+        if self.config.order_flow_stats {
+            self.record_order_flow(msg_type, ticker_id, &response, &updates);
+        }
+
+        if let Some(match_start) = match_start {
+            self.last_match = Some((match_start, monotonic_nanos()));
+        }
+
+        // Echo the client's tag and origin timestamp on every response
+        (response.with_tag(tag).with_origin_ts(origin_ts), updates)
+    }
+
+    /// Process a mass quote
+    ///
+    /// For each entry, cancels the client's quotes still resting on the
+    /// entry's ticker and enters the new bid and ask (sides with zero
+    /// quantity are skipped). Every sub-request goes through
+    /// `process_request`, so quotes are matched, checked and published like
+    /// any other order, their trades activate stops as a New's would, and
+    /// maker fills are queued as usual.
+    ///
+    /// # Arguments
+    /// * `header` - The MassQuote request; its qty must equal the entry count
+    /// * `entries` - The two-sided quotes, at most `MAX_MASS_QUOTE_ENTRIES`
+    ///
+    /// # Returns
+    /// The Canceled responses for replaced quotes and the responses to the
+    /// new ones, in processing order, and the market data updates. A single
+    /// InvalidRequest if mass quotes are disabled or the entries don't match
+    /// the header.
+    pub fn process_mass_quote(
+        &mut self,
+        header: &ClientRequest,
+        entries: &[QuoteEntry],
+    ) -> (Vec<ClientResponse>, Vec<MarketUpdate>) {
+        let client_id = header.client_id;
+        let count = header.qty as usize;
+        let tag = header.tag;
+        let origin_ts = header.origin_ts;
+
+        if !self.config.mass_quotes
+            || header.request_type() != Some(ClientRequestType::MassQuote)
+            || entries.is_empty()
+            || entries.len() > MAX_MASS_QUOTE_ENTRIES
+            || entries.len() != count
+        {
+            let (response, _) = self.handle_invalid_request(header);
+            return (vec![response.with_tag(tag).with_origin_ts(origin_ts)], Vec::new());
+        }
+        self.stats.mass_quotes += 1;
+
+        let mut responses = Vec::with_capacity(entries.len() * 4);
+        let mut updates = Vec::new();
+        for entry in entries {
+            let ticker_id = entry.ticker_id;
+
+            // Pull whatever is left of the previous quotes on this ticker
+            for market_order_id in self.quotes.remove(&(client_id, ticker_id)).unwrap_or_default() {
+                if !self.resting_orders.contains_key(&market_order_id) {
+                    continue;
+                }
+                let cancel = ClientRequest::new(ClientRequestType::Cancel, client_id, ticker_id, market_order_id, 0, 0, 0)
+                    .with_tag(tag)
+                    .with_origin_ts(origin_ts);
+                let (response, cancel_updates) = self.process_request(&cancel);
+                responses.push(response);
+                updates.extend(cancel_updates);
+            }
+
+            let mut resting = Vec::with_capacity(2);
+            let sides = [
+                (Side::Buy, entry.bid_order_id, entry.bid_price, entry.bid_qty),
+                (Side::Sell, entry.ask_order_id, entry.ask_price, entry.ask_qty),
+            ];
+            for (side, order_id, price, qty) in sides {
+                if qty == 0 {
+                    continue;
+                }
+                let request = ClientRequest::new(ClientRequestType::New, client_id, ticker_id, order_id, side as i8, price, qty)
+                    .with_tag(tag)
+                    .with_origin_ts(origin_ts);
+                let (response, new_updates) = self.process_request(&request);
+                let market_order_id = response.market_order_id;
+                if self.resting_orders.contains_key(&market_order_id) {
+                    resting.push(market_order_id);
+                }
+                responses.push(response);
+                updates.extend(new_updates);
+            }
+            if !resting.is_empty() {
+                self.quotes.insert((client_id, ticker_id), resting);
+            }
+        }
+
+        (responses, updates)
+    }
+
+    /// Returns the exchange order IDs of a client's last mass quote on a
+    /// ticker (some may have traded since)
+    pub fn client_quotes(&self, client_id: ClientId, ticker_id: TickerId) -> &[OrderId] {
+        self.quotes.get(&(client_id, ticker_id)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns when matching of the last request started and ended
+    ///
+    /// # Returns
+    /// The start and end timestamps, or None if timestamps are disabled or
+    /// no request has been processed
+    #[inline]
+    pub fn last_match_timestamps(&self) -> Option<(Nanos, Nanos)> {
+        self.last_match
+    }
+
+    /// Handle a new order request
+    ///
+    /// Matches the order against the opposite side of the book, then rests
+    /// any remainder. Returns Accepted if nothing traded, otherwise a Filled
+    /// response carrying the total executed quantity at its average price.
+    /// Each execution produces a Trade market update, and a resting
+    /// remainder produces an Add update.
+    ///
+    /// With duplicate detection enabled, a New request reusing a recently
+    /// accepted client order ID gets the original response back and changes
+    /// nothing. A client throttled for flicker, or a price off the ticker's
+    /// tick grid, gets InvalidRequest. A client at its price level cap gets
+    /// LevelLimitRejected for an order at a level it does not rest at yet;
+    /// the cap is checked before matching. An order that would take the
+    /// client past its position limit on the ticker gets RiskRejected.
+    ///
+    /// An all-or-none order executes only if its full quantity can; if not,
+    /// it rests in the AON book (Accepted, no market data) or is Canceled,
+    /// per the configured `AonResidual`. All-or-none orders get
+    /// InvalidRequest while disabled.
+    ///
+    /// An IOC order's untraded remainder is canceled instead of resting, and
+    /// an IOC order that trades nothing gets Canceled. A FOK order the book
+    /// cannot fill in full gets InvalidRequest. An unknown time in force
+    /// gets InvalidRequest.
+    fn handle_new_order(&mut self, request: &ClientRequest, all_or_none: bool) -> (ClientResponse, Vec<MarketUpdate>) {
+        // Extract fields from packed struct
+        let client_id = request.client_id;
+        let ticker_id = request.ticker_id;
+        let client_order_id = request.order_id;
+        let side_raw = request.side;
         let price = request.price;
         let qty = request.qty;
 
+        let aon_residual = match (all_or_none, self.config.aon_orders) {
+            (false, _) => None,
+            (true, Some(residual)) => Some(residual),
+            (true, None) => return self.handle_invalid_request(request),
+        };
+        let Some(time_in_force) = request.time_in_force() else {
+            return self.handle_invalid_request(request);
+        };
+
+        if self.config.detect_duplicate_orders {
+            if let Some(original) = self.submitted_orders.get(&(client_id, client_order_id)) {
+                self.stats.duplicate_orders += 1;
+                return (*original, Vec::new());
+            }
+        }
+
+        // Flagged clients are turned away while throttled
+        if self.is_throttled(client_id) {
+            return self.create_reject_response(
+                client_id,
+                ticker_id,
+                client_order_id,
+                side_raw,
+                price,
+                qty,
+            );
+        }
+
+        if !self.instrument(ticker_id).is_valid_tick(price) {
+            self.stats.off_tick_rejects += 1;
+            return self.create_reject_response(
+                client_id,
+                ticker_id,
+                client_order_id,
+                side_raw,
+                price,
+                qty,
+            );
+        }
+
+        if !self.check_lot_and_notional(ticker_id, price, qty) {
+            return self.create_reject_response(
+                client_id,
+                ticker_id,
+                client_order_id,
+                side_raw,
+                price,
+                qty,
+            );
+        }
+
         // Validate ticker exists
         let order_book = match self.order_books.get_mut(&ticker_id) {
             Some(book) => book,
@@ -103,50 +1326,140 @@ impl MatchingEngine {
             }
         };
 
+        let max_levels = self.config.max_levels_per_client;
+        if max_levels > 0
+            && !self.client_levels.contains(client_id, ticker_id, side, price)
+            && self.client_levels.count(client_id, ticker_id) >= max_levels
+        {
+            self.stats.level_limit_rejects += 1;
+            let response = ClientResponse::new(
+                ClientResponseType::LevelLimitRejected,
+                client_id,
+                ticker_id,
+                client_order_id,
+                0,
+                side_raw,
+                price,
+                0,
+                0,
+            );
+            return (response, Vec::new());
+        }
+
+        if self.client_positions.breaches_limit(client_id, ticker_id, side, qty) {
+            self.stats.risk_rejects += 1;
+            let response = ClientResponse::new(
+                ClientResponseType::RiskRejected,
+                client_id,
+                ticker_id,
+                client_order_id,
+                0,
+                side_raw,
+                price,
+                0,
+                0,
+            );
+            return (response, Vec::new());
+        }
+
+        if let Some(last_look) = self.config.last_look {
+            let now = (self.clock)();
+            let history = self.mid_history.get(&ticker_id);
+            if Self::last_look_rejects(history, order_book, side, price, last_look, now) {
+                self.stats.last_look_rejects += 1;
+                let response = ClientResponse::new(
+                    ClientResponseType::LastLookRejected,
+                    client_id,
+                    ticker_id,
+                    client_order_id,
+                    0,
+                    side_raw,
+                    price,
+                    0,
+                    0,
+                );
+                return (response, Vec::new());
+            }
+        }
+
+        // A fill-or-kill order that cannot fill in full never gets an ID
+        if time_in_force == TimeInForce::Fok && !self.fully_fillable(client_id, ticker_id, side, price, qty) {
+            self.stats.fok_rejects += 1;
+            return self.create_reject_response(
+                client_id,
+                ticker_id,
+                client_order_id,
+                side_raw,
+                price,
+                qty,
+            );
+        }
+
         // Assign a market order ID
         let market_order_id = self.next_order_id;
         self.next_order_id += 1;
 
-        // Add order to the book
-        let result = order_book.add_order(
+        let entry = OrderEntry {
             client_id,
+            ticker_id,
+            client_order_id,
             market_order_id,
             side,
             price,
             qty,
-        );
+            tag: request.tag,
+            immediate: all_or_none || time_in_force != TimeInForce::Day,
+        };
 
-        match result {
-            Some(_ptr) => {
-                // Order accepted
+        if let Some(residual) = aon_residual {
+            if self.aon_fillable(&entry) {
+                self.stats.aon_executions += 1;
+            } else {
+                let (response_type, leaves_qty) = match residual {
+                    AonResidual::Rest => {
+                        self.aon_books.entry(ticker_id).or_default().push(entry);
+                        (ClientResponseType::Accepted, qty)
+                    }
+                    AonResidual::Cancel => (ClientResponseType::Canceled, 0),
+                };
                 let response = ClientResponse::new(
-                    ClientResponseType::Accepted,
+                    response_type,
                     client_id,
                     ticker_id,
                     client_order_id,
                     market_order_id,
                     side_raw,
                     price,
-                    0,    // exec_qty - no execution yet
-                    qty,  // leaves_qty - full quantity remains
-                );
-
-                // Generate market update for the new order
-                let update = MarketUpdate::new(
-                    MarketUpdateType::Add,
-                    ticker_id,
-                    market_order_id,
-                    side_raw,
-                    price,
-                    qty,
-                    market_order_id, // Use order ID as priority for now
+                    0,
+                    leaves_qty,
                 );
-
-                (response, vec![update])
+                if self.config.detect_duplicate_orders {
+                    self.remember_submitted(client_id, client_order_id, response);
+                }
+                return (response, Vec::new());
             }
+        }
+
+        let mut updates = Vec::new();
+        let response = self.execute_order(entry, &mut updates);
+        let response = match response {
+            Some(response) => response,
+            // An immediate order that traded nothing is canceled
+            None if time_in_force != TimeInForce::Day => ClientResponse::new(
+                ClientResponseType::Canceled,
+                client_id,
+                ticker_id,
+                client_order_id,
+                market_order_id,
+                side_raw,
+                price,
+                0,
+                0,
+            ),
+            // Failed to add order (pool exhausted, duplicate, or stopped at
+            // the protected price)
             None => {
-                // Failed to add order (pool exhausted or duplicate)
-                self.create_reject_response(
+                return self.create_reject_response(
                     client_id,
                     ticker_id,
                     client_order_id,
@@ -155,403 +1468,3546 @@ impl MatchingEngine {
                     qty,
                 )
             }
+        };
+
+        if self.config.detect_duplicate_orders {
+            self.remember_submitted(client_id, client_order_id, response);
         }
+
+        (response, updates)
     }
 
-    /// Handle a cancel order request
+    /// Handle a market order request
     ///
-    /// Attempts to cancel an order from the appropriate order book.
-    /// Returns Canceled response and Cancel market update on success.
-    /// Returns CancelRejected response if order not found.
-    fn handle_cancel(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+    /// The order matches the opposite side at any price, walking as many
+    /// levels as its quantity needs (no further than the protected price
+    /// under best price protection). Returns Filled with the executed
+    /// quantity at its average price (rounded to the nearest price unit,
+    /// halves up) and zero leaves: an unfilled remainder
+    /// never rests. Each execution produces a Trade market update.
+    ///
+    /// Returns InvalidRequest for an unknown ticker, invalid side, throttled
+    /// client or a quantity off the lot grid, RiskRejected past the client's
+    /// position limit, LastLookRejected after an adverse mid move under last
+    /// look, and Canceled if nothing could trade.
+    fn handle_new_market(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
         // Extract fields from packed struct
         let client_id = request.client_id;
         let ticker_id = request.ticker_id;
-        let order_id = request.order_id;
+        let client_order_id = request.order_id;
         let side_raw = request.side;
-        let price = request.price;
+        let qty = request.qty;
 
-        // Validate ticker exists
-        let order_book = match self.order_books.get_mut(&ticker_id) {
-            Some(book) => book,
-            None => {
-                // Ticker not found - reject the cancel
-                return self.create_cancel_reject_response(
-                    client_id,
-                    ticker_id,
-                    order_id,
-                    side_raw,
-                    price,
-                );
+        if self.config.detect_duplicate_orders {
+            if let Some(original) = self.submitted_orders.get(&(client_id, client_order_id)) {
+                self.stats.duplicate_orders += 1;
+                return (*original, Vec::new());
+            }
+        }
+
+        if self.is_throttled(client_id) || !self.order_books.contains_key(&ticker_id) {
+            return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, 0, qty);
+        }
+
+        if !self.instrument(ticker_id).is_valid_lot(qty) {
+            self.stats.off_lot_rejects += 1;
+            return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, 0, qty);
+        }
+
+        // A market order takes any price the book offers
+        let (side, price) = match side_raw {
+            1 => (Side::Buy, Price::MAX),
+            -1 => (Side::Sell, Price::MIN),
+            _ => {
+                return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, 0, qty);
             }
         };
 
-        // Attempt to cancel the order
-        match order_book.cancel_order(order_id) {
-            Some(canceled_order) => {
-                // Order successfully canceled
+        if self.client_positions.breaches_limit(client_id, ticker_id, side, qty) {
+            self.stats.risk_rejects += 1;
+            let response = ClientResponse::new(
+                ClientResponseType::RiskRejected,
+                client_id,
+                ticker_id,
+                client_order_id,
+                0,
+                side_raw,
+                0,
+                0,
+                0,
+            );
+            return (response, Vec::new());
+        }
+
+        // Last look applies to market orders as to marketable limit orders
+        if let Some(last_look) = self.config.last_look {
+            let now = (self.clock)();
+            let history = self.mid_history.get(&ticker_id);
+            let rejected = self.order_books.get(&ticker_id).is_some_and(|order_book| {
+                Self::last_look_rejects(history, order_book, side, price, last_look, now)
+            });
+            if rejected {
+                self.stats.last_look_rejects += 1;
                 let response = ClientResponse::new(
-                    ClientResponseType::Canceled,
+                    ClientResponseType::LastLookRejected,
                     client_id,
                     ticker_id,
-                    order_id,
-                    order_id, // market_order_id same as client's order_id for cancels
-                    canceled_order.side as i8,
-                    canceled_order.price,
-                    0,                  // exec_qty
-                    canceled_order.qty, // leaves_qty (remaining at cancel time)
+                    client_order_id,
+                    0,
+                    side_raw,
+                    0,
+                    0,
+                    0,
                 );
+                return (response, Vec::new());
+            }
+        }
 
-                // Generate market update for the cancel
-                let update = MarketUpdate::new(
-                    MarketUpdateType::Cancel,
-                    ticker_id,
-                    order_id,
-                    canceled_order.side as i8,
-                    canceled_order.price,
-                    canceled_order.qty,
-                    canceled_order.priority,
-                );
+        let market_order_id = self.next_order_id;
+        self.next_order_id += 1;
 
-                (response, vec![update])
-            }
-            None => {
-                // Order not found - reject the cancel
-                self.create_cancel_reject_response(
-                    client_id,
-                    ticker_id,
-                    order_id,
-                    side_raw,
-                    price,
-                )
+        let entry = OrderEntry {
+            client_id,
+            ticker_id,
+            client_order_id,
+            market_order_id,
+            side,
+            price,
+            qty,
+            tag: request.tag,
+            immediate: true,
+        };
+        let mut updates = Vec::new();
+        let response = match self.execute_order(entry, &mut updates) {
+            Some(response) => {
+                self.stats.market_orders += 1;
+                response
             }
+            None => ClientResponse::new(
+                ClientResponseType::Canceled,
+                client_id,
+                ticker_id,
+                client_order_id,
+                market_order_id,
+                side_raw,
+                0,
+                0,
+                0,
+            ),
+        };
+        if self.config.detect_duplicate_orders {
+            self.remember_submitted(client_id, client_order_id, response);
         }
+
+        (response, updates)
     }
 
-    /// Handle an invalid request type
-    fn handle_invalid_request(&self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+    /// Handle a stop-market or stop-limit order request
+    ///
+    /// The stop rests in the ticker's stop book, invisible to market data,
+    /// until a trade touches its `stop_price`. Returns Accepted carrying the
+    /// limit price (the trigger price for a stop-market order) and the full
+    /// quantity as leaves.
+    ///
+    /// Returns InvalidRequest when stop orders are disabled, for an unknown
+    /// ticker, invalid side, throttled client, a price off the tick grid, or
+    /// a trigger the last trade has already reached.
+    fn handle_new_stop(&mut self, request: &ClientRequest, limit: bool) -> (ClientResponse, Vec<MarketUpdate>) {
+        // Extract fields from packed struct
         let client_id = request.client_id;
         let ticker_id = request.ticker_id;
-        let order_id = request.order_id;
-        let side = request.side;
+        let client_order_id = request.order_id;
+        let side_raw = request.side;
         let price = request.price;
         let qty = request.qty;
+        let stop_price = request.stop_price;
+
+        if !self.config.stop_orders {
+            return self.handle_invalid_request(request);
+        }
+
+        if self.config.detect_duplicate_orders {
+            if let Some(original) = self.submitted_orders.get(&(client_id, client_order_id)) {
+                self.stats.duplicate_orders += 1;
+                return (*original, Vec::new());
+            }
+        }
+
+        if self.is_throttled(client_id) || !self.order_books.contains_key(&ticker_id) {
+            return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, price, qty);
+        }
+
+        let instrument = self.instrument(ticker_id);
+        if !instrument.is_valid_tick(stop_price) || (limit && !instrument.is_valid_tick(price)) {
+            self.stats.off_tick_rejects += 1;
+            return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, price, qty);
+        }
+        if !instrument.is_valid_lot(qty) {
+            self.stats.off_lot_rejects += 1;
+            return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, price, qty);
+        }
+
+        let side = match side_raw {
+            1 => Side::Buy,
+            -1 => Side::Sell,
+            _ => {
+                return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, price, qty);
+            }
+        };
+
+        // A stop the market has already reached would trigger on arrival
+        if let Some(&last_trade) = self.last_trades.get(&ticker_id) {
+            let reached = match side {
+                Side::Buy => stop_price <= last_trade,
+                Side::Sell => stop_price >= last_trade,
+            };
+            if reached {
+                return self.create_reject_response(client_id, ticker_id, client_order_id, side_raw, price, qty);
+            }
+        }
+
+        let market_order_id = self.next_order_id;
+        self.next_order_id += 1;
+
+        let limit = limit.then_some(price);
+        self.stop_books.entry(ticker_id).or_default().insert(StopOrder {
+            client_id,
+            client_order_id,
+            market_order_id,
+            side,
+            stop_price,
+            limit,
+            qty,
+            tag: request.tag,
+        });
 
         let response = ClientResponse::new(
-            ClientResponseType::InvalidRequest,
+            ClientResponseType::Accepted,
             client_id,
             ticker_id,
-            order_id,
-            0, // no market order ID
-            side,
-            price,
-            0,   // exec_qty
-            qty, // leaves_qty
+            client_order_id,
+            market_order_id,
+            side_raw,
+            limit.unwrap_or(stop_price),
+            0,
+            qty,
         );
+        if self.config.detect_duplicate_orders {
+            self.remember_submitted(client_id, client_order_id, response);
+        }
 
         (response, Vec::new())
     }
 
-    /// Create a reject response for a new order
-    fn create_reject_response(
+    /// Activate the stops touched by new trades on a ticker
+    ///
+    /// Looks at the Trade updates in `updates`, records the last trade price
+    /// and activates every stop whose trigger they reached, appending the
+    /// activated orders' updates. Repeats while activated stops trade, so a
+    /// cascade of stops runs to completion within one request.
+    fn trigger_stops(&mut self, ticker_id: TickerId, updates: &mut Vec<MarketUpdate>) {
+        let mut scanned = 0;
+        loop {
+            let mut last_trade = None;
+            let (mut high, mut low) = (Price::MIN, Price::MAX);
+            for update in &updates[scanned..] {
+                if update.msg_type == MarketUpdateType::Trade as u8 {
+                    let price = update.price;
+                    high = high.max(price);
+                    low = low.min(price);
+                    last_trade = Some(price);
+                }
+            }
+            scanned = updates.len();
+
+            let Some(last_trade) = last_trade else {
+                return;
+            };
+            self.last_trades.insert(ticker_id, last_trade);
+
+            let Some(stop_book) = self.stop_books.get_mut(&ticker_id) else {
+                return;
+            };
+            let triggered = stop_book.take_triggered(high, low);
+            if triggered.is_empty() {
+                return;
+            }
+            for stop in triggered {
+                self.activate_stop(ticker_id, stop, updates);
+            }
+        }
+    }
+
+    /// Convert a triggered stop into a market or limit order and match it
+    ///
+    /// Queues Triggered for the stop's owner, followed by the activated
+    /// order's Accepted or Filled, or Canceled if it could neither trade nor
+    /// rest. A stop-market order never rests: whatever does not trade on
+    /// activation is canceled.
+    fn activate_stop(&mut self, ticker_id: TickerId, stop: StopOrder, updates: &mut Vec<MarketUpdate>) {
+        self.stats.stops_triggered += 1;
+        let side_raw = stop.side as i8;
+        self.maker_fills.push(
+            ClientResponse::new(
+                ClientResponseType::Triggered,
+                stop.client_id,
+                ticker_id,
+                stop.client_order_id,
+                stop.market_order_id,
+                side_raw,
+                stop.stop_price,
+                0,
+                stop.qty,
+            )
+            .with_tag(stop.tag),
+        );
+
+        // A stop-market order takes any price the book offers
+        let price = stop.limit.unwrap_or(match stop.side {
+            Side::Buy => Price::MAX,
+            Side::Sell => Price::MIN,
+        });
+        let entry = OrderEntry {
+            client_id: stop.client_id,
+            ticker_id,
+            client_order_id: stop.client_order_id,
+            market_order_id: stop.market_order_id,
+            side: stop.side,
+            price,
+            qty: stop.qty,
+            tag: stop.tag,
+            immediate: stop.limit.is_none(),
+        };
+        let response = self.execute_order(entry, updates).unwrap_or_else(|| {
+            ClientResponse::new(
+                ClientResponseType::Canceled,
+                stop.client_id,
+                ticker_id,
+                stop.client_order_id,
+                stop.market_order_id,
+                side_raw,
+                stop.limit.unwrap_or(stop.stop_price),
+                0,
+                0,
+            )
+        });
+        self.maker_fills.push(response.with_tag(stop.tag));
+    }
+
+    /// Returns true if the book holds enough crossing liquidity to fill an
+    /// order's full quantity, going no further than the protected price
+    fn aon_fillable(&self, entry: &OrderEntry) -> bool {
+        self.fully_fillable(entry.client_id, entry.ticker_id, entry.side, entry.price, entry.qty)
+    }
+
+    /// Returns true if the book holds `qty` of crossing liquidity at or
+    /// better than `price`, going no further than the protected price
+    ///
+    /// Under self-trade prevention, liquidity of `client_id` does not count:
+    /// CancelResting pulls those orders, and CancelIncoming stops matching at
+    /// the first of them.
+    fn fully_fillable(
         &self,
         client_id: ClientId,
         ticker_id: TickerId,
-        client_order_id: OrderId,
-        side: i8,
+        side: Side,
         price: Price,
         qty: Qty,
-    ) -> (ClientResponse, Vec<MarketUpdate>) {
-        let response = ClientResponse::new(
-            ClientResponseType::InvalidRequest,
-            client_id,
-            ticker_id,
-            client_order_id,
-            0, // no market order ID assigned
-            side,
+    ) -> bool {
+        let price = self.protected_limit(ticker_id, side, price).unwrap_or(price);
+        let Some(order_book) = self.order_books.get(&ticker_id) else {
+            return false;
+        };
+        let available = match self.config.self_trade_prevention {
+            SelfTradePrevention::Off => order_book.crossing_qty(side, price),
+            SelfTradePrevention::CancelResting => order_book.crossing_qty_for_client(side, price, client_id, false),
+            SelfTradePrevention::CancelIncoming => order_book.crossing_qty_for_client(side, price, client_id, true),
+        };
+        available >= qty
+    }
+
+    /// Fill the waiting all-or-none orders a ticker's book can now fill
+    ///
+    /// Checks each order in arrival order against the current book and
+    /// executes the ones that can fill in full, appending their updates and
+    /// queueing their owners' Filled responses with the maker fills.
+    fn match_resting_aons(&mut self, ticker_id: TickerId, updates: &mut Vec<MarketUpdate>) {
+        let Some(waiting) = self.aon_books.get_mut(&ticker_id).map(std::mem::take) else {
+            return;
+        };
+
+        let mut still_waiting = Vec::with_capacity(waiting.len());
+        for entry in waiting {
+            if !self.aon_fillable(&entry) {
+                still_waiting.push(entry);
+                continue;
+            }
+            self.stats.aon_executions += 1;
+            if let Some(response) = self.execute_order(entry, updates) {
+                self.maker_fills.push(response.with_tag(entry.tag));
+            }
+        }
+        self.aon_books.insert(ticker_id, still_waiting);
+    }
+
+    /// Match an order against the book and rest any remainder
+    ///
+    /// Appends a Trade update per execution and an Add update for a resting
+    /// remainder to `updates`, and queues the resting orders' fills. Under
+    /// best price protection the order matches no further than the protected
+    /// price, and a residual is dropped or rests there.
+    ///
+    /// # Returns
+    /// Accepted if nothing traded, otherwise a taker Filled response at the
+    /// average execution price. None if nothing traded and the order could
+    /// not rest, is immediate, or its residual was rejected by best price
+    /// protection.
+    fn execute_order(
+        &mut self,
+        entry: OrderEntry,
+        updates: &mut Vec<MarketUpdate>,
+    ) -> Option<ClientResponse> {
+        let OrderEntry {
+            client_id,
+            ticker_id,
+            client_order_id,
+            market_order_id,
+            side,
             price,
-            0,   // exec_qty
-            qty, // leaves_qty
+            qty,
+            tag,
+            immediate,
+        } = entry;
+        let side_raw = side as i8;
+        let fee_schedule = self.config.fee_schedule;
+        let instrument = self.instrument(ticker_id);
+        let track_levels = self.config.max_levels_per_client > 0;
+        let added_at = if self.config.flicker.is_some() || self.config.order_flow_stats {
+            (self.clock)()
+        } else {
+            0
+        };
+
+        // Never sweep through the protected price; the residual is handled below
+        let protected = self.protected_limit(ticker_id, side, price);
+        let price = protected.unwrap_or(price);
+
+        // Match against resting orders on the opposite side
+        let (fills, cancels, self_trade_stopped) = self.match_incoming(client_id, ticker_id, side, price, qty)?;
+        let mut cancels = cancels.into_iter().peekable();
+        let order_book = self.order_books.get_mut(&ticker_id)?;
+        let track_positions = self.config.client_positions;
+        let mut exec_qty: Qty = 0;
+        let mut notional: i128 = 0;
+        let exec_time_ns = if fills.is_empty() { 0 } else { (self.clock)() };
+
+        for (index, fill) in fills.iter().enumerate() {
+            // Orders pulled by self-trade prevention, after the fills before them
+            while let Some(cancel) = cancels.next_if(|cancel| cancel.after_fills <= index) {
+                self.maker_fills.push(cancel.response);
+                updates.push(cancel.update);
+            }
+
+            exec_qty += fill.qty;
+            notional += fill.price as i128 * fill.qty as i128;
+
+            // Report the execution to the resting order's owner
+            let info = if fill.leaves_qty == 0 {
+                if track_levels {
+                    self.client_levels.remove(fill.client_id, ticker_id, fill.side, fill.price);
+                }
+                let info = self.resting_orders.remove(&fill.order_id);
+                if let Some(info) = info.filter(|info| self.config.order_flow_stats && info.added_at != 0) {
+                    // Inline record_resting_time: the order book is still borrowed
+                    let stats = self.order_flow.entry(ticker_id).or_default();
+                    stats.closed_orders += 1;
+                    stats.total_resting_ns += (self.clock)().saturating_sub(info.added_at);
+                }
+                info
+            } else {
+                self.resting_orders.get(&fill.order_id).copied()
+            };
+            let (maker_order_id, maker_tag) = info
+                .map_or((fill.order_id, 0), |info| (info.client_order_id, info.tag));
+
+            self.maker_fills.push(
+                ClientResponse::new(
+                    ClientResponseType::Filled,
+                    fill.client_id,
+                    ticker_id,
+                    maker_order_id,
+                    fill.order_id,
+                    fill.side as i8,
+                    fill.price,
+                    fill.qty,
+                    fill.leaves_qty,
+                )
+                .with_tag(maker_tag)
+                .with_liquidity(LiquidityFlag::Maker)
+                .with_exec_time(exec_time_ns),
+            );
+
+            updates.push(MarketUpdate::new(
+                MarketUpdateType::Trade,
+                ticker_id,
+                fill.order_id,
+                side_raw, // aggressor side
+                fill.price,
+                fill.qty,
+                fill.priority,
+            ));
+
+            if track_positions {
+                self.client_positions.record_fill(fill.client_id, ticker_id, fill.side, fill.qty);
+            }
+
+            self.stats.fills += 1;
+            let fill_notional = instrument.notional_cents(fill.price, fill.qty);
+            self.stats.maker_fees += fee_schedule.fee_on_notional(LiquidityFlag::Maker, fill_notional);
+            self.stats.taker_fees += fee_schedule.fee_on_notional(LiquidityFlag::Taker, fill_notional);
+        }
+
+        for cancel in cancels {
+            self.maker_fills.push(cancel.response);
+            updates.push(cancel.update);
+        }
+
+        if track_positions && exec_qty > 0 {
+            self.client_positions.record_fill(client_id, ticker_id, side, exec_qty);
+        }
+
+        // Rest whatever did not trade
+        let mut leaves_qty = qty - exec_qty;
+        if leaves_qty > 0 && protected.is_some() {
+            self.stats.protected_stops += 1;
+            if self.config.matching_algo == MatchingAlgo::ProtectBestPrice(ProtectedResidual::Reject) {
+                if exec_qty == 0 {
+                    return None;
+                }
+                leaves_qty = 0;
+            }
+        }
+        if leaves_qty > 0 && self_trade_stopped {
+            if exec_qty == 0 {
+                return Some(ClientResponse::new(
+                    ClientResponseType::Canceled,
+                    client_id,
+                    ticker_id,
+                    client_order_id,
+                    market_order_id,
+                    side_raw,
+                    price,
+                    0,
+                    0,
+                ));
+            }
+            leaves_qty = 0;
+        }
+        if leaves_qty > 0 && immediate {
+            if exec_qty == 0 {
+                return None;
+            }
+            leaves_qty = 0;
+        }
+        if leaves_qty > 0 {
+            // Check whether this order sets a new best price before it rests
+            let improves_price = self.config.detect_price_improvement
+                && order_book.improves_best_price(side, price);
+
+            match order_book.add_order(client_id, market_order_id, side, price, leaves_qty) {
+                Some(_ptr) => {
+                    self.resting_orders.insert(
+                        market_order_id,
+                        RestingOrderInfo { client_order_id, tag, added_at },
+                    );
+                    if track_levels {
+                        self.client_levels.add(client_id, ticker_id, side, price);
+                    }
+
+                    // Generate market update for the resting order
+                    let mut update = MarketUpdate::new(
+                        MarketUpdateType::Add,
+                        ticker_id,
+                        market_order_id,
+                        side_raw,
+                        price,
+                        leaves_qty,
+                        market_order_id, // Use order ID as priority for now
+                    );
+
+                    if improves_price {
+                        update = update.with_flags(MARKET_UPDATE_FLAG_PRICE_IMPROVEMENT);
+                        self.stats.price_improvements += 1;
+                    }
+
+                    updates.push(update);
+                }
+                None if exec_qty == 0 => return None,
+                None => {
+                    // Partially filled but the remainder could not rest - drop it
+                    leaves_qty = 0;
+                }
+            }
+        }
+
+        let response = if exec_qty == 0 {
+            // Order accepted and resting, no execution yet
+            ClientResponse::new(
+                ClientResponseType::Accepted,
+                client_id,
+                ticker_id,
+                client_order_id,
+                market_order_id,
+                side_raw,
+                price,
+                0,
+                leaves_qty,
+            )
+        } else {
+            // Aggressor fill at the average execution price
+            ClientResponse::new(
+                ClientResponseType::Filled,
+                client_id,
+                ticker_id,
+                client_order_id,
+                market_order_id,
+                side_raw,
+                average_price(notional, exec_qty),
+                exec_qty,
+                leaves_qty,
+            )
+            .with_liquidity(LiquidityFlag::Taker)
+            .with_exec_time(exec_time_ns)
+        };
+
+        Some(response)
+    }
+
+    /// Match an incoming order against the book, applying self-trade
+    /// prevention
+    ///
+    /// Resting orders of the same client pulled under
+    /// `SelfTradePrevention::CancelResting` are returned as cancels, each
+    /// with the number of fills that preceded it, so the caller can report
+    /// them in execution order.
+    ///
+    /// # Returns
+    /// The fills, the pulled resting orders, and true if the incoming order's
+    /// remainder must be canceled because it reached one of its client's own
+    /// orders. None if the ticker has no book.
+    fn match_incoming(
+        &mut self,
+        client_id: ClientId,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+    ) -> Option<(Vec<Fill>, Vec<SelfTradeCancel>, bool)> {
+        let mode = self.config.self_trade_prevention;
+        if mode == SelfTradePrevention::Off {
+            let order_book = self.order_books.get_mut(&ticker_id)?;
+            return Some((order_book.match_order(side, price, qty), Vec::new(), false));
+        }
+
+        let mut fills = Vec::new();
+        let mut cancels = Vec::new();
+        let mut remaining = qty;
+        loop {
+            let order_book = self.order_books.get_mut(&ticker_id)?;
+            let (batch, own_order) = order_book.match_order_until_client(side, price, remaining, client_id);
+            remaining -= batch.iter().map(|fill| fill.qty).sum::<Qty>();
+            fills.extend(batch);
+            let Some(order_id) = own_order else {
+                return Some((fills, cancels, false));
+            };
+
+            self.stats.self_trades_prevented += 1;
+            if mode == SelfTradePrevention::CancelIncoming {
+                return Some((fills, cancels, true));
+            }
+            let Some(order) = order_book.cancel_order(order_id) else {
+                return Some((fills, cancels, false));
+            };
+            self.level_left(&order);
+            let tag = self.resting_orders.remove(&order_id).map_or(0, |info| {
+                self.record_resting_time(ticker_id, info.added_at);
+                info.tag
+            });
+
+            // Addressed like a client cancel, by the exchange order ID
+            cancels.push(SelfTradeCancel {
+                after_fills: fills.len(),
+                response: ClientResponse::new(
+                    ClientResponseType::Canceled,
+                    order.client_id,
+                    ticker_id,
+                    order_id,
+                    order_id,
+                    order.side as i8,
+                    order.price,
+                    0,
+                    order.qty,
+                )
+                .with_tag(tag),
+                update: MarketUpdate::new(
+                    MarketUpdateType::Cancel,
+                    ticker_id,
+                    order_id,
+                    order.side as i8,
+                    order.price,
+                    order.qty,
+                    order.priority,
+                ),
+            });
+        }
+    }
+
+    /// Decide whether last look rejects an order
+    ///
+    /// Only marketable orders are subject to last look. The reference mid is
+    /// the one in effect at the start of the window, or the oldest recorded
+    /// one if the history does not reach that far back.
+    ///
+    /// # Returns
+    /// true if the mid moved against the resting side by more than the threshold
+    fn last_look_rejects(
+        history: Option<&VecDeque<(u64, Price)>>,
+        order_book: &OrderBook,
+        side: Side,
+        price: Price,
+        last_look: LastLookConfig,
+        now: u64,
+    ) -> bool {
+        let marketable = match side {
+            Side::Buy => order_book.best_ask().is_some_and(|ask| price >= ask),
+            Side::Sell => order_book.best_bid().is_some_and(|bid| price <= bid),
+        };
+        if !marketable {
+            return false;
+        }
+
+        let (Some(history), Some(current_mid)) = (history, order_book.mid_price()) else {
+            return false;
+        };
+        let window_start = now.saturating_sub(last_look.window_ns);
+        let reference_mid = match history.iter().rev().find(|(ts, _)| *ts <= window_start) {
+            Some(&(_, mid)) => mid,
+            None => match history.front() {
+                Some(&(_, mid)) => mid,
+                None => return false,
+            },
+        };
+
+        let adverse_move = match side {
+            Side::Buy => current_mid - reference_mid,
+            Side::Sell => reference_mid - current_mid,
+        };
+        adverse_move > last_look.threshold
+    }
+
+    /// Record the ticker's current mid price for last look
+    ///
+    /// Only changes are recorded. Samples older than the window are dropped,
+    /// keeping the one in effect at the start of the window as the reference.
+    fn record_mid(&mut self, ticker_id: TickerId) {
+        let Some(last_look) = self.config.last_look else {
+            return;
+        };
+        let Some(mid) = self.order_books.get(&ticker_id).and_then(OrderBook::mid_price) else {
+            return;
+        };
+
+        let now = (self.clock)();
+        let history = self.mid_history.entry(ticker_id).or_default();
+        if history.back().is_none_or(|&(_, last)| last != mid) {
+            history.push_back((now, mid));
+        }
+
+        let window_start = now.saturating_sub(last_look.window_ns);
+        while history.len() > 1 && history[1].0 <= window_start {
+            history.pop_front();
+        }
+    }
+
+    /// Handle a cancel order request
+    ///
+    /// Attempts to cancel an order from the appropriate order book.
+    /// Returns Canceled response and Cancel market update on success.
+    /// Returns CancelRejected response if order not found or owned by
+    /// another client.
+    fn handle_cancel(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+        // Extract fields from packed struct
+        let client_id = request.client_id;
+        let ticker_id = request.ticker_id;
+        let order_id = request.order_id;
+        let side_raw = request.side;
+        let price = request.price;
+
+        // Validate ticker exists
+        let order_book = match self.order_books.get_mut(&ticker_id) {
+            Some(book) => book,
+            None => {
+                // Ticker not found - reject the cancel
+                return self.create_cancel_reject_response(
+                    client_id,
+                    ticker_id,
+                    order_id,
+                    side_raw,
+                    price,
+                );
+            }
+        };
+
+        // Only the owner may cancel a resting order
+        if order_book.get_order(order_id).is_some_and(|order| order.client_id != client_id) {
+            return self.create_cancel_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        }
+
+        // Attempt to cancel the order
+        match order_book.cancel_order(order_id) {
+            Some(canceled_order) => {
+                self.level_left(&canceled_order);
+                if let Some(info) = self.resting_orders.remove(&order_id) {
+                    self.record_cancel(canceled_order.client_id, info.added_at);
+                    self.record_resting_time(ticker_id, info.added_at);
+                }
+
+                // Order successfully canceled
+                let response = ClientResponse::new(
+                    ClientResponseType::Canceled,
+                    client_id,
+                    ticker_id,
+                    order_id,
+                    order_id, // market_order_id same as client's order_id for cancels
+                    canceled_order.side as i8,
+                    canceled_order.price,
+                    0,                  // exec_qty
+                    canceled_order.qty, // leaves_qty (remaining at cancel time)
+                );
+
+                // Generate market update for the cancel
+                let update = MarketUpdate::new(
+                    MarketUpdateType::Cancel,
+                    ticker_id,
+                    order_id,
+                    canceled_order.side as i8,
+                    canceled_order.price,
+                    canceled_order.qty,
+                    canceled_order.priority,
+                );
+
+                (response, vec![update])
+            }
+            None => {
+                // A stop waiting for its trigger has nothing in the book
+                let stop_owner = self
+                    .stop_books
+                    .get(&ticker_id)
+                    .and_then(|stop_book| stop_book.get(order_id))
+                    .map(|stop| stop.client_id);
+                if stop_owner.is_some_and(|owner| owner != client_id) {
+                    return self.create_cancel_reject_response(client_id, ticker_id, order_id, side_raw, price);
+                }
+                if let Some(stop) = self
+                    .stop_books
+                    .get_mut(&ticker_id)
+                    .and_then(|stop_book| stop_book.remove(order_id))
+                {
+                    let response = ClientResponse::new(
+                        ClientResponseType::Canceled,
+                        client_id,
+                        ticker_id,
+                        order_id,
+                        order_id,
+                        stop.side as i8,
+                        stop.limit.unwrap_or(stop.stop_price),
+                        0,
+                        stop.qty,
+                    );
+                    return (response, Vec::new());
+                }
+
+                // Neither has an all-or-none order waiting for liquidity
+                let aon_owner = self
+                    .aon_books
+                    .get(&ticker_id)
+                    .and_then(|aons| aons.iter().find(|aon| aon.market_order_id == order_id))
+                    .map(|aon| aon.client_id);
+                if aon_owner.is_some_and(|owner| owner != client_id) {
+                    return self.create_cancel_reject_response(client_id, ticker_id, order_id, side_raw, price);
+                }
+                if let Some(aon) = self.aon_books.get_mut(&ticker_id).and_then(|aons| {
+                    let position = aons.iter().position(|aon| aon.market_order_id == order_id)?;
+                    Some(aons.remove(position))
+                }) {
+                    let response = ClientResponse::new(
+                        ClientResponseType::Canceled,
+                        client_id,
+                        ticker_id,
+                        order_id,
+                        order_id,
+                        aon.side as i8,
+                        aon.price,
+                        0,
+                        aon.qty,
+                    );
+                    return (response, Vec::new());
+                }
+
+                // Order not found - reject the cancel
+                self.create_cancel_reject_response(
+                    client_id,
+                    ticker_id,
+                    order_id,
+                    side_raw,
+                    price,
+                )
+            }
+        }
+    }
+
+    /// Handle a modify order request
+    ///
+    /// Moves a resting order, identified by its exchange order ID, to a new
+    /// price and quantity, producing a Cancel update for the old order and an
+    /// Add for the new. The ticker's `PriorityPolicy` decides whether the
+    /// order keeps its place in the queue or is re-queued behind later orders.
+    ///
+    /// If the new price would cross the opposite side, `ModifyCrossMode`
+    /// decides: Reject leaves the order untouched and returns ModifyRejected;
+    /// Match trades it as an aggressor and rests any remainder under the same
+    /// exchange order ID. ModifyRejected is also returned for an unknown
+    /// ticker or order, an order owned by another client, a zero quantity,
+    /// or a price off the ticker's tick grid.
+    fn handle_modify(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+        // Extract fields from packed struct
+        let client_id = request.client_id;
+        let ticker_id = request.ticker_id;
+        let order_id = request.order_id;
+        let side_raw = request.side;
+        let price = request.price;
+        let qty = request.qty;
+
+        if !self.instrument(ticker_id).is_valid_tick(price) {
+            self.stats.off_tick_rejects += 1;
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        }
+        if !self.check_lot_and_notional(ticker_id, price, qty) {
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        }
+
+        let Some(order_book) = self.order_books.get_mut(&ticker_id) else {
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        };
+        let side = match order_book.get_order(order_id) {
+            Some(order) if order.client_id == client_id && qty > 0 => order.side,
+            _ => {
+                return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+            }
+        };
+
+        let crosses = match side {
+            Side::Buy => order_book.best_ask().is_some_and(|ask| price >= ask),
+            Side::Sell => order_book.best_bid().is_some_and(|bid| price <= bid),
+        };
+        if crosses && self.config.modify_cross_mode == ModifyCrossMode::Reject {
+            self.stats.modify_cross_rejects += 1;
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        }
+
+        let Some(old_order) = order_book.cancel_order(order_id) else {
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        };
+        self.level_left(&old_order);
+        let info = self
+            .resting_orders
+            .remove(&order_id)
+            .unwrap_or(RestingOrderInfo { client_order_id: order_id, tag: 0, added_at: 0 });
+
+        let mut updates = vec![MarketUpdate::new(
+            MarketUpdateType::Cancel,
+            ticker_id,
+            order_id,
+            old_order.side as i8,
+            old_order.price,
+            old_order.qty,
+            old_order.priority,
+        )];
+
+        let keeps_priority = !crosses
+            && self
+                .priority_policy(ticker_id)
+                .keeps_priority(side, old_order.price, old_order.qty, price, qty);
+        if keeps_priority {
+            let Some(order_book) = self.order_books.get_mut(&ticker_id) else {
+                return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+            };
+            if order_book
+                .add_order_with_priority(client_id, order_id, side, price, qty, old_order.priority)
+                .is_none()
+            {
+                // The new level could not be opened - put the order back as it was
+                order_book.add_order_with_priority(
+                    client_id, order_id, side, old_order.price, old_order.qty, old_order.priority,
+                );
+                self.resting_orders.insert(order_id, info);
+                self.level_rested(&old_order);
+                return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+            }
+            self.resting_orders.insert(order_id, info);
+            if self.config.max_levels_per_client > 0 {
+                self.client_levels.add(client_id, ticker_id, side, price);
+            }
+            updates.push(MarketUpdate::new(
+                MarketUpdateType::Add,
+                ticker_id,
+                order_id,
+                side as i8,
+                price,
+                qty,
+                order_id, // Use order ID as priority, as for new orders
+            ));
+            let response = ClientResponse::new(
+                ClientResponseType::Accepted,
+                client_id,
+                ticker_id,
+                info.client_order_id,
+                order_id,
+                side as i8,
+                price,
+                0,
+                qty,
+            );
+            return (response, updates);
+        }
+
+        let entry = OrderEntry {
+            client_id,
+            ticker_id,
+            client_order_id: info.client_order_id,
+            market_order_id: order_id,
+            side,
+            price,
+            qty,
+            tag: info.tag,
+            immediate: false,
+        };
+        match self.execute_order(entry, &mut updates) {
+            Some(response) => (response, updates),
+            None => {
+                // The new price could not rest - put the order back as it was.
+                // Its slot and level were just freed, so this cannot fail.
+                if let Some(order_book) = self.order_books.get_mut(&ticker_id) {
+                    order_book.add_order_with_priority(
+                        client_id, order_id, side, old_order.price, old_order.qty, old_order.priority,
+                    );
+                }
+                self.resting_orders.insert(order_id, info);
+                self.level_rested(&old_order);
+                self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price)
+            }
+        }
+    }
+
+    /// Handle an amend request
+    ///
+    /// Changes the quantity of a resting order, identified by its exchange
+    /// order ID, leaving its price alone. A reduction keeps the order's place
+    /// in the queue. An increase re-queues it behind every other order at its
+    /// price, or is answered with InvalidRequest if quantity increases are
+    /// disabled. A changed order is published as a Modify update.
+    /// ModifyRejected is returned for an unknown ticker or order, an order
+    /// owned by another client, a zero quantity, or a quantity that does not
+    /// fit the instrument.
+    fn handle_amend(&mut self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+        // Extract fields from packed struct
+        let client_id = request.client_id;
+        let ticker_id = request.ticker_id;
+        let order_id = request.order_id;
+        let side_raw = request.side;
+        let request_price = request.price;
+        let qty = request.qty;
+
+        let Some(order_book) = self.order_books.get(&ticker_id) else {
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, request_price);
+        };
+        let (side, price, old_qty, priority) = match order_book.get_order(order_id) {
+            Some(order) if order.client_id == client_id && qty > 0 => {
+                (order.side, order.price, order.qty, order.priority)
+            }
+            _ => {
+                return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, request_price);
+            }
+        };
+        if qty > old_qty && !self.config.amend_qty_up {
+            return self.handle_invalid_request(request);
+        }
+        if !self.check_lot_and_notional(ticker_id, price, qty) {
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        }
+
+        let Some(order_book) = self.order_books.get_mut(&ticker_id) else {
+            return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+        };
+        if qty < old_qty {
+            order_book.reduce_order(order_id, qty);
+        } else if qty > old_qty {
+            // Re-queue at the back of the level. The order's slot and level
+            // are freed first, so this only fails if the book is corrupt;
+            // then the order is put back as it was.
+            order_book.cancel_order(order_id);
+            if order_book.add_order(client_id, order_id, side, price, qty).is_none() {
+                order_book.add_order_with_priority(client_id, order_id, side, price, old_qty, priority);
+                return self.create_modify_reject_response(client_id, ticker_id, order_id, side_raw, price);
+            }
+        }
+
+        let updates = if qty == old_qty {
+            Vec::new()
+        } else {
+            vec![MarketUpdate::new(
+                MarketUpdateType::Modify,
+                ticker_id,
+                order_id,
+                side as i8,
+                price,
+                qty,
+                order_id, // Use order ID as priority, as for new orders
+            )]
+        };
+        let client_order_id = self
+            .resting_orders
+            .get(&order_id)
+            .map_or(order_id, |info| info.client_order_id);
+        let response = ClientResponse::new(
+            ClientResponseType::Accepted,
+            client_id,
+            ticker_id,
+            client_order_id,
+            order_id,
+            side as i8,
+            price,
+            0,
+            qty,
         );
+        (response, updates)
+    }
+
+    /// Count an order resting again at its level, when the level cap is enabled
+    fn level_rested(&mut self, order: &Order) {
+        if self.config.max_levels_per_client > 0 {
+            self.client_levels.add(order.client_id, order.ticker_id, order.side, order.price);
+        }
+    }
+
+    /// Forget an order that left its level, when the level cap is enabled
+    fn level_left(&mut self, order: &Order) {
+        if self.config.max_levels_per_client > 0 {
+            self.client_levels.remove(order.client_id, order.ticker_id, order.side, order.price);
+        }
+    }
+
+    /// Count a processed request in its ticker's order flow statistics
+    fn record_order_flow(
+        &mut self,
+        msg_type: u8,
+        ticker_id: TickerId,
+        response: &ClientResponse,
+        updates: &[MarketUpdate],
+    ) {
+        if !self.order_books.contains_key(&ticker_id) {
+            return;
+        }
+        let response_type = response.msg_type;
+        let succeeded = response_type == ClientResponseType::Accepted as u8
+            || response_type == ClientResponseType::Filled as u8;
+        let stats = self.order_flow.entry(ticker_id).or_default();
+        match ClientRequestType::from_u8(msg_type) {
+            Some(ClientRequestType::New) if succeeded => stats.adds += 1,
+            Some(ClientRequestType::Modify | ClientRequestType::Amend) if succeeded => stats.modifies += 1,
+            Some(ClientRequestType::Cancel) if response_type == ClientResponseType::Canceled as u8 => {
+                stats.cancels += 1
+            }
+            _ => {}
+        }
+        stats.trades += updates
+            .iter()
+            .filter(|update| update.msg_type == MarketUpdateType::Trade as u8)
+            .count() as u64;
+    }
+
+    /// Add the lifetime of an order that left the book to its ticker's statistics
+    fn record_resting_time(&mut self, ticker_id: TickerId, added_at: u64) {
+        if !self.config.order_flow_stats || added_at == 0 {
+            return;
+        }
+        let now = (self.clock)();
+        let stats = self.order_flow.entry(ticker_id).or_default();
+        stats.closed_orders += 1;
+        stats.total_resting_ns += now.saturating_sub(added_at);
+    }
+
+    /// Update a client's flicker metrics for a canceled order
+    ///
+    /// Flags the client and queues an alert when its flicker cancels in the
+    /// current window reach the threshold.
+    fn record_cancel(&mut self, client_id: ClientId, added_at: u64) {
+        let Some(flicker) = self.config.flicker else {
+            return;
+        };
+
+        let now = (self.clock)();
+        let stats = self.flicker_stats.entry(client_id).or_default();
+        stats.roll_window(now, flicker.window_ns);
+        stats.cancels += 1;
+
+        if now.saturating_sub(added_at) >= flicker.max_lifetime_ns {
+            return;
+        }
+        stats.flicker_cancels += 1;
+        stats.window_flicker += 1;
+
+        if !stats.flagged && stats.window_flicker >= flicker.threshold {
+            stats.flagged = true;
+            stats.flags += 1;
+            self.flicker_alerts.push(FlickerAlert {
+                client_id,
+                flicker_cancels: stats.window_flicker,
+                timestamp: now,
+                action: flicker.action,
+            });
+        }
+    }
+
+    /// Remembers an accepted order's response for duplicate detection,
+    /// forgetting the oldest once the window is full
+    fn remember_submitted(&mut self, client_id: ClientId, client_order_id: OrderId, response: ClientResponse) {
+        let window = match self.config.duplicate_window {
+            0 => DEFAULT_DUPLICATE_WINDOW,
+            window => window,
+        };
+        let key = (client_id, client_order_id);
+        if self.submitted_orders.insert(key, response).is_some() {
+            return;
+        }
+        if self.submitted_order_keys.len() >= window {
+            if let Some(oldest) = self.submitted_order_keys.pop_front() {
+                self.submitted_orders.remove(&oldest);
+            }
+        }
+        self.submitted_order_keys.push_back(key);
+    }
+
+    /// Returns true if the client's new orders should be rejected for flicker
+    fn is_throttled(&mut self, client_id: ClientId) -> bool {
+        let Some(flicker) = self.config.flicker else {
+            return false;
+        };
+        if flicker.action != FlickerAction::Throttle {
+            return false;
+        }
+        let Some(stats) = self.flicker_stats.get_mut(&client_id) else {
+            return false;
+        };
+
+        stats.roll_window((self.clock)(), flicker.window_ns);
+        if stats.flagged {
+            stats.throttled_orders += 1;
+        }
+        stats.flagged
+    }
+
+    /// Handle an invalid request type
+    fn handle_invalid_request(&self, request: &ClientRequest) -> (ClientResponse, Vec<MarketUpdate>) {
+        let client_id = request.client_id;
+        let ticker_id = request.ticker_id;
+        let order_id = request.order_id;
+        let side = request.side;
+        let price = request.price;
+        let qty = request.qty;
+
+        let response = ClientResponse::new(
+            ClientResponseType::InvalidRequest,
+            client_id,
+            ticker_id,
+            order_id,
+            0, // no market order ID
+            side,
+            price,
+            0,   // exec_qty
+            qty, // leaves_qty
+        );
+
+        (response, Vec::new())
+    }
+
+    /// Create a reject response for a new order
+    fn create_reject_response(
+        &self,
+        client_id: ClientId,
+        ticker_id: TickerId,
+        client_order_id: OrderId,
+        side: i8,
+        price: Price,
+        qty: Qty,
+    ) -> (ClientResponse, Vec<MarketUpdate>) {
+        let response = ClientResponse::new(
+            ClientResponseType::InvalidRequest,
+            client_id,
+            ticker_id,
+            client_order_id,
+            0, // no market order ID assigned
+            side,
+            price,
+            0,   // exec_qty
+            qty, // leaves_qty
+        );
+
+        (response, Vec::new())
+    }
+
+    /// Create a cancel rejected response
+    fn create_cancel_reject_response(
+        &self,
+        client_id: ClientId,
+        ticker_id: TickerId,
+        order_id: OrderId,
+        side: i8,
+        price: Price,
+    ) -> (ClientResponse, Vec<MarketUpdate>) {
+        let response = ClientResponse::new(
+            ClientResponseType::CancelRejected,
+            client_id,
+            ticker_id,
+            order_id,
+            0, // no market order ID
+            side,
+            price,
+            0, // exec_qty
+            0, // leaves_qty
+        );
+
+        (response, Vec::new())
+    }
+
+    /// Create a modify rejected response
+    fn create_modify_reject_response(
+        &self,
+        client_id: ClientId,
+        ticker_id: TickerId,
+        order_id: OrderId,
+        side: i8,
+        price: Price,
+    ) -> (ClientResponse, Vec<MarketUpdate>) {
+        let response = ClientResponse::new(
+            ClientResponseType::ModifyRejected,
+            client_id,
+            ticker_id,
+            order_id,
+            0, // no market order ID
+            side,
+            price,
+            0, // exec_qty
+            0, // leaves_qty
+        );
+
+        (response, Vec::new())
+    }
+
+    /// Returns a reference to an order book for the given ticker
+    #[inline]
+    pub fn get_order_book(&self, ticker_id: TickerId) -> Option<&OrderBook> {
+        self.order_books.get(&ticker_id)
+    }
+
+    /// Returns a mutable reference to an order book for the given ticker
+    #[inline]
+    pub fn get_order_book_mut(&mut self, ticker_id: TickerId) -> Option<&mut OrderBook> {
+        self.order_books.get_mut(&ticker_id)
+    }
+
+    /// Returns the number of tickers in the matching engine
+    #[inline]
+    pub fn ticker_count(&self) -> usize {
+        self.order_books.len()
+    }
+
+    /// Returns the pool utilization of every book, by ticker
+    ///
+    /// Pools are sized from the configuration when a ticker is added; these
+    /// stats let operators right-size them.
+    pub fn pool_stats(&self) -> Vec<BookPoolStats> {
+        let mut stats: Vec<BookPoolStats> = self
+            .order_books
+            .iter()
+            .map(|(&ticker_id, book)| BookPoolStats {
+                ticker_id,
+                orders: book.order_pool_stats(),
+                levels: book.level_pool_stats(),
+                fragmentation: book.fragmentation(),
+            })
+            .collect();
+        stats.sort_by_key(|book| book.ticker_id);
+        stats
+    }
+
+    /// Rebuilds fragmented order books for cache locality
+    ///
+    /// Books whose fragmentation ratio is at or below the configured
+    /// threshold are left alone (any fragmentation counts with a threshold
+    /// of 0.0). Also releases excess capacity of the resting order index.
+    /// Book contents, priorities and depth are unchanged. Linear in the size
+    /// of the books, so call it when the engine is idle.
+    ///
+    /// # Returns
+    /// The number of books rebuilt
+    pub fn compact(&mut self) -> usize {
+        let threshold = self.config.compaction_threshold;
+        let mut compacted = 0;
+        for book in self.order_books.values_mut() {
+            let fragmentation = book.fragmentation();
+            if fragmentation.is_fragmented() && (threshold <= 0.0 || fragmentation.ratio() > threshold) {
+                book.compact();
+                compacted += 1;
+            }
+        }
+        self.resting_orders.shrink_to_fit();
+        self.stats.compactions += compacted as u64;
+        compacted
+    }
+
+    /// Returns the next order ID that will be assigned
+    #[inline]
+    pub fn next_order_id(&self) -> OrderId {
+        self.next_order_id
+    }
+
+    /// Returns the engine configuration
+    #[inline]
+    pub fn config(&self) -> &MatchingEngineConfig {
+        &self.config
+    }
+
+    /// Returns the engine counters
+    #[inline]
+    pub fn stats(&self) -> &MatchingEngineStats {
+        &self.stats
+    }
+
+    /// Resets the engine counters, including order flow statistics
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+        self.order_flow.clear();
+    }
+
+    /// Returns the number of distinct price levels a client rests at on a
+    /// ticker, tracked when the per-client level cap is enabled
+    #[inline]
+    pub fn client_level_count(&self, client_id: ClientId, ticker_id: TickerId) -> usize {
+        self.client_levels.count(client_id, ticker_id)
+    }
+
+    /// Returns the number of stop orders waiting for their trigger on a ticker
+    #[inline]
+    pub fn pending_stops(&self, ticker_id: TickerId) -> usize {
+        self.stop_books.get(&ticker_id).map_or(0, StopBook::len)
+    }
+
+    /// Returns the number of all-or-none orders waiting for liquidity on a
+    /// ticker
+    #[inline]
+    pub fn pending_aons(&self, ticker_id: TickerId) -> usize {
+        self.aon_books.get(&ticker_id).map_or(0, Vec::len)
+    }
+
+    /// Returns a ticker's order flow statistics, if order flow statistics are
+    /// enabled and the ticker has seen a request
+    #[inline]
+    pub fn ticker_stats(&self, ticker_id: TickerId) -> Option<&EngineStats> {
+        self.order_flow.get(&ticker_id)
+    }
+
+    /// Returns the flicker metrics for a client, if it has canceled anything
+    /// while flicker detection was enabled
+    #[inline]
+    pub fn flicker_stats(&self, client_id: ClientId) -> Option<&ClientFlickerStats> {
+        self.flicker_stats.get(&client_id)
+    }
+
+    /// Removes and returns flicker alerts raised since the last call
+    pub fn drain_flicker_alerts(&mut self) -> std::vec::Drain<'_, FlickerAlert> {
+        self.flicker_alerts.drain(..)
+    }
+
+    /// Returns the number of queued fill responses for resting orders and
+    /// responses for triggered stops
+    #[inline]
+    pub fn pending_maker_fills(&self) -> usize {
+        self.maker_fills.len()
+    }
+
+    /// Removes and returns queued fill responses for resting orders and
+    /// responses for triggered stops
+    ///
+    /// Each response is addressed to the order's owner via its `client_id`
+    /// and should be delivered after the aggressor's response.
+    pub fn drain_maker_fills(&mut self) -> std::vec::Drain<'_, ClientResponse> {
+        self.maker_fills.drain(..)
+    }
+}
+
+impl Default for MatchingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_matching_engine() {
+        let engine = MatchingEngine::new();
+        assert_eq!(engine.ticker_count(), 0);
+        assert_eq!(engine.next_order_id(), 1);
+    }
+
+    #[test]
+    fn test_add_ticker() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        engine.add_ticker(2);
+
+        assert_eq!(engine.ticker_count(), 2);
+        assert!(engine.get_order_book(1).is_some());
+        assert!(engine.get_order_book(2).is_some());
+        assert!(engine.get_order_book(3).is_none());
+    }
+
+    #[test]
+    fn test_add_duplicate_ticker() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        engine.add_ticker(1); // Should be idempotent
+
+        assert_eq!(engine.ticker_count(), 1);
+    }
+
+    #[test]
+    fn test_new_order_accepted() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(
+            ClientRequestType::New,
+            100,   // client_id
+            1,     // ticker_id
+            12345, // order_id
+            1,     // side (Buy)
+            10050, // price
+            100,   // qty
+        );
+
+        let (response, updates) = engine.process_request(&request);
+
+        // Copy fields to local variables to avoid unaligned reference issues
+        let msg_type = response.msg_type;
+        let client_id = response.client_id;
+        let ticker_id = response.ticker_id;
+        let client_order_id = response.client_order_id;
+        let market_order_id = response.market_order_id;
+        let side = response.side;
+        let price = response.price;
+        let exec_qty = response.exec_qty;
+        let leaves_qty = response.leaves_qty;
+
+        // Verify response
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(client_id, 100);
+        assert_eq!(ticker_id, 1);
+        assert_eq!(client_order_id, 12345);
+        assert_eq!(market_order_id, 1); // First order ID
+        assert_eq!(side, 1);
+        assert_eq!(price, 10050);
+        assert_eq!(exec_qty, 0);
+        assert_eq!(leaves_qty, 100);
+
+        // Verify market update
+        assert_eq!(updates.len(), 1);
+        let update = &updates[0];
+        let upd_msg_type = update.msg_type;
+        let upd_ticker_id = update.ticker_id;
+        let upd_order_id = update.order_id;
+        let upd_side = update.side;
+        let upd_price = update.price;
+        let upd_qty = update.qty;
+
+        assert_eq!(upd_msg_type, MarketUpdateType::Add as u8);
+        assert_eq!(upd_ticker_id, 1);
+        assert_eq!(upd_order_id, 1);
+        assert_eq!(upd_side, 1);
+        assert_eq!(upd_price, 10050);
+        assert_eq!(upd_qty, 100);
+
+        // Verify order ID incremented
+        assert_eq!(engine.next_order_id(), 2);
+    }
+
+    #[test]
+    fn test_new_order_unknown_ticker() {
+        let mut engine = MatchingEngine::new();
+        // Don't add any tickers
+
+        let request = ClientRequest::new(
+            ClientRequestType::New,
+            100,   // client_id
+            999,   // ticker_id - doesn't exist
+            12345, // order_id
+            1,     // side
+            10050, // price
+            100,   // qty
+        );
+
+        let (response, updates) = engine.process_request(&request);
+
+        assert_eq!(response.msg_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_new_order_invalid_side() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(
+            ClientRequestType::New,
+            100,   // client_id
+            1,     // ticker_id
+            12345, // order_id
+            0,     // side - invalid!
+            10050, // price
+            100,   // qty
+        );
+
+        let (response, updates) = engine.process_request(&request);
+
+        assert_eq!(response.msg_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_order_not_found() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(
+            ClientRequestType::Cancel,
+            100,   // client_id
+            1,     // ticker_id
+            99999, // order_id - doesn't exist
+            1,     // side
+            10050, // price
+            0,     // qty (not used for cancel)
+        );
+
+        let (response, updates) = engine.process_request(&request);
+
+        assert_eq!(response.msg_type, ClientResponseType::CancelRejected as u8);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_order_unknown_ticker() {
+        let mut engine = MatchingEngine::new();
+        // Don't add any tickers
+
+        let request = ClientRequest::new(
+            ClientRequestType::Cancel,
+            100,   // client_id
+            999,   // ticker_id - doesn't exist
+            12345, // order_id
+            1,     // side
+            10050, // price
+            0,     // qty
+        );
+
+        let (response, updates) = engine.process_request(&request);
+
+        assert_eq!(response.msg_type, ClientResponseType::CancelRejected as u8);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_request_type() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        // Create a request with invalid msg_type
+        let request = ClientRequest {
+            msg_type: 255, // Invalid type
+            client_id: 100,
+            ticker_id: 1,
+            order_id: 12345,
+            side: 1,
+            price: 10050,
+            qty: 100,
+            tag: 0,
+            origin_ts: 0,
+            stop_price: 0,
+            time_in_force: 0,
+        };
+
+        let (response, updates) = engine.process_request(&request);
+
+        assert_eq!(response.msg_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_multiple_orders_increment_id() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        for i in 0..5 {
+            let request = ClientRequest::new(
+                ClientRequestType::New,
+                100,
+                1,
+                i as u64,
+                1,
+                10050 + i as i64,
+                100,
+            );
+
+            let (response, _) = engine.process_request(&request);
+            let market_order_id = response.market_order_id;
+            assert_eq!(market_order_id, (i + 1) as u64);
+        }
+
+        assert_eq!(engine.next_order_id(), 6);
+    }
+
+    #[test]
+    fn test_tag_echoed_on_responses() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 100)
+            .with_tag(0xABCD);
+        let (response, _) = engine.process_request(&request);
+        let tag = response.tag;
+        assert_eq!(tag, 0xABCD);
+
+        // Rejects echo the tag too
+        let request = ClientRequest::new(ClientRequestType::Cancel, 100, 1, 999, 1, 10000, 0)
+            .with_tag(0x1234);
+        let (response, _) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        let tag = response.tag;
+        assert_eq!(msg_type, ClientResponseType::CancelRejected as u8);
+        assert_eq!(tag, 0x1234);
+    }
+
+    #[test]
+    fn test_price_improvement_flagged() {
+        let config = MatchingEngineConfig::new().with_price_improvement_detection(true);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+
+        // First bid sets the best price
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(updates[0].is_price_improvement());
+
+        // Joining at a new, higher bid improves the book
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 2, 1, 10010, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(updates[0].is_price_improvement());
+
+        // Joining behind the best bid does not
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 3, 1, 10000, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(!updates[0].is_price_improvement());
+
+        // Joining at the same best price does not either
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 4, 1, 10010, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(!updates[0].is_price_improvement());
+
+        // A lower ask improves the ask side
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 5, -1, 10100, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(updates[0].is_price_improvement());
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 6, -1, 10090, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(updates[0].is_price_improvement());
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 7, -1, 10095, 100);
+        let (_, updates) = engine.process_request(&request);
+        assert!(!updates[0].is_price_improvement());
+
+        assert_eq!(engine.stats().price_improvements, 4);
+
+        engine.reset_stats();
+        assert_eq!(engine.stats().price_improvements, 0);
+    }
+
+    #[test]
+    fn test_price_improvement_disabled_by_default() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 100);
+        let (_, updates) = engine.process_request(&request);
+
+        assert!(!updates[0].is_price_improvement());
+        assert_eq!(engine.stats().price_improvements, 0);
+    }
+
+    #[test]
+    fn test_cross_flags_maker_and_taker() {
+        let config = MatchingEngineConfig::new()
+            .with_fee_schedule(FeeSchedule::new(-1.0, 3.0));
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+
+        // Client 100 rests a sell
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 11, -1, 10000, 100)
+            .with_tag(0xAA);
+        let (response, _) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(response.liquidity_flag(), Some(LiquidityFlag::None));
+
+        // Client 200 buys through it
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 22, 1, 10010, 60);
+        let (taker, updates) = engine.process_request(&request);
+
+        let msg_type = taker.msg_type;
+        let client_id = taker.client_id;
+        let price = taker.price;
+        let exec_qty = taker.exec_qty;
+        let leaves_qty = taker.leaves_qty;
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!(client_id, 200);
+        assert_eq!(price, 10000);
+        assert_eq!(exec_qty, 60);
+        assert_eq!(leaves_qty, 0);
+        assert_eq!(taker.liquidity_flag(), Some(LiquidityFlag::Taker));
+
+        // Resting seller gets a maker fill with its own order ID and tag
+        let maker_fills: Vec<_> = engine.drain_maker_fills().collect();
+        assert_eq!(maker_fills.len(), 1);
+        let maker = maker_fills[0];
+        let msg_type = maker.msg_type;
+        let client_id = maker.client_id;
+        let client_order_id = maker.client_order_id;
+        let side = maker.side;
+        let exec_qty = maker.exec_qty;
+        let leaves_qty = maker.leaves_qty;
+        let tag = maker.tag;
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!(client_id, 100);
+        assert_eq!(client_order_id, 11);
+        assert_eq!(side, -1);
+        assert_eq!(exec_qty, 60);
+        assert_eq!(leaves_qty, 40);
+        assert_eq!(tag, 0xAA);
+        assert_eq!(maker.liquidity_flag(), Some(LiquidityFlag::Maker));
+        assert_eq!(engine.pending_maker_fills(), 0);
+
+        // One trade, nothing rested for the fully filled aggressor
+        assert_eq!(updates.len(), 1);
+        let upd_msg_type = updates[0].msg_type;
+        let upd_qty = updates[0].qty;
+        assert_eq!(upd_msg_type, MarketUpdateType::Trade as u8);
+        assert_eq!(upd_qty, 60);
+        assert_eq!(engine.get_order_book(1).unwrap().get_order(1).unwrap().qty, 40);
+
+        // Notional 600,000 cents: maker rebate 1bp, taker fee 3bp
+        assert_eq!(engine.stats().fills, 1);
+        assert_eq!(engine.stats().maker_fees, -60);
+        assert_eq!(engine.stats().taker_fees, 180);
+    }
+
+    #[test]
+    fn test_limit_buy_matches_resting_sells_in_price_time_priority() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        let sell = |client_id, order_id, price, qty| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, -1, price, qty)
+        };
+        // The worse price arrives first; 10050 has two orders in arrival order
+        engine.process_request(&sell(101, 1, 10100, 50));
+        engine.process_request(&sell(102, 2, 10050, 30));
+        engine.process_request(&sell(103, 3, 10050, 30));
+
+        // Better price first, then time within the level, then 10100
+        let buy = ClientRequest::new(ClientRequestType::New, 200, 1, 9, 1, 10100, 100);
+        let (response, updates) = engine.process_request(&buy);
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((exec_qty, leaves_qty), (100, 0));
+
+        let trades: Vec<(u8, OrderId, Price, Qty)> = updates
+            .iter()
+            .map(|update| (update.msg_type, update.order_id, update.price, update.qty))
+            .collect();
+        let trade = MarketUpdateType::Trade as u8;
+        assert_eq!(trades, vec![(trade, 2, 10050, 30), (trade, 3, 10050, 30), (trade, 1, 10100, 40)]);
+
+        // Each resting seller hears about its own fill, in match order
+        let maker_fills: Vec<(ClientId, OrderId, Qty, Qty)> = engine
+            .drain_maker_fills()
+            .map(|fill| {
+                assert_eq!({ fill.msg_type }, ClientResponseType::Filled as u8);
+                (fill.client_id, fill.client_order_id, fill.exec_qty, fill.leaves_qty)
+            })
+            .collect();
+        assert_eq!(maker_fills, vec![(102, 2, 30, 0), (103, 3, 30, 0), (101, 1, 40, 10)]);
+
+        // Consumed orders are gone; the partial rests with its leaves
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.order_count(), 1);
+        assert_eq!(book.best_ask(), Some(10100));
+        assert_eq!(book.get_order(1).unwrap().qty, 10);
+        assert!(book.get_order(2).is_none() && book.get_order(3).is_none());
+    }
+
+    #[test]
+    fn test_cancel_rejects_another_clients_order() {
+        let config = MatchingEngineConfig::new()
+            .with_stop_orders(true)
+            .with_aon_orders(AonResidual::Rest);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let cancel = |client_id, order_id| ClientRequest::new(ClientRequestType::Cancel, client_id, 1, order_id, 1, 0, 0);
+
+        // Client 100 rests a bid, a stop and an AON
+        let (resting, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 9900, 10));
+        let stop = ClientRequest::new(ClientRequestType::NewStop, 100, 1, 2, 1, 0, 20).with_stop_price(10100);
+        let (stop, _) = engine.process_request(&stop);
+        let (aon, _) = engine.process_request(&ClientRequest::new(ClientRequestType::NewAon, 100, 1, 3, 1, 10000, 50));
+        let order_ids = [resting.market_order_id, stop.market_order_id, aon.market_order_id];
+
+        // Client 200 cannot cancel any of them
+        for order_id in order_ids {
+            let (response, updates) = engine.process_request(&cancel(200, order_id));
+            let msg_type = response.msg_type;
+            assert_eq!(msg_type, ClientResponseType::CancelRejected as u8);
+            assert!(updates.is_empty());
+        }
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 1);
+        assert_eq!(engine.pending_stops(1), 1);
+        assert_eq!(engine.pending_aons(1), 1);
+
+        // The owner can
+        for order_id in order_ids {
+            let (response, _) = engine.process_request(&cancel(100, order_id));
+            let msg_type = response.msg_type;
+            assert_eq!(msg_type, ClientResponseType::Canceled as u8);
+        }
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 0);
+        assert_eq!(engine.pending_stops(1), 0);
+        assert_eq!(engine.pending_aons(1), 0);
+    }
+
+    #[test]
+    fn test_self_trade_cancel_resting_pulls_own_order_and_continues() {
+        let config = MatchingEngineConfig::new()
+            .with_self_trade_prevention(SelfTradePrevention::CancelResting);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let order = |client_id, order_id, side, price, qty| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, side, price, qty).with_tag(order_id)
+        };
+        engine.process_request(&order(100, 1, -1, 10000, 50));
+        engine.process_request(&order(200, 2, -1, 10050, 50));
+
+        // Client 100's own ask at 10000 is pulled; the buy trades at 10050
+        // and rests the rest
+        let (response, updates) = engine.process_request(&order(100, 3, 1, 10050, 60));
+        let (msg_type, price, exec_qty, leaves_qty) =
+            (response.msg_type, response.price, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((price, exec_qty, leaves_qty), (10050, 50, 10));
+
+        let published: Vec<(u8, OrderId, Qty)> = updates
+            .iter()
+            .map(|update| (update.msg_type, update.order_id, update.qty))
+            .collect();
+        assert_eq!(
+            published,
+            vec![
+                (MarketUpdateType::Cancel as u8, 1, 50),
+                (MarketUpdateType::Trade as u8, 2, 50),
+                (MarketUpdateType::Add as u8, 3, 10),
+            ]
+        );
+
+        // The resting owner hears of the cancel, the other seller of its fill
+        let responses: Vec<(u8, ClientId, OrderId, u64)> = engine
+            .drain_maker_fills()
+            .map(|response| (response.msg_type, response.client_id, response.market_order_id, response.tag))
+            .collect();
+        assert_eq!(
+            responses,
+            vec![
+                (ClientResponseType::Canceled as u8, 100, 1, 1),
+                (ClientResponseType::Filled as u8, 200, 2, 2),
+            ]
+        );
+        assert_eq!(engine.stats().self_trades_prevented, 1);
+
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_ask(), None);
+        assert_eq!(book.best_bid(), Some(10050));
+        assert!(book.validate_book().is_ok());
+    }
+
+    #[test]
+    fn test_self_trade_cancel_follows_earlier_trades() {
+        let config = MatchingEngineConfig::new()
+            .with_self_trade_prevention(SelfTradePrevention::CancelResting);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let order = |client_id, order_id, side, price, qty| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, side, price, qty)
+        };
+        engine.process_request(&order(200, 1, -1, 10000, 30));
+        engine.process_request(&order(100, 2, -1, 10000, 50));
+        engine.process_request(&order(300, 3, -1, 10050, 50));
+
+        // Client 200 ahead in the queue trades first, then client 100's own
+        // ask is pulled, then client 300 trades
+        let (_, updates) = engine.process_request(&order(100, 4, 1, 10050, 80));
+        let published: Vec<(u8, OrderId)> = updates.iter().map(|update| (update.msg_type, update.order_id)).collect();
+        assert_eq!(
+            published,
+            vec![
+                (MarketUpdateType::Trade as u8, 1),
+                (MarketUpdateType::Cancel as u8, 2),
+                (MarketUpdateType::Trade as u8, 3),
+            ]
+        );
+
+        let responses: Vec<(u8, ClientId)> = engine
+            .drain_maker_fills()
+            .map(|response| (response.msg_type, response.client_id))
+            .collect();
+        assert_eq!(
+            responses,
+            vec![
+                (ClientResponseType::Filled as u8, 200),
+                (ClientResponseType::Canceled as u8, 100),
+                (ClientResponseType::Filled as u8, 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_self_trade_cancel_incoming_stops_at_own_order() {
+        let config = MatchingEngineConfig::new()
+            .with_self_trade_prevention(SelfTradePrevention::CancelIncoming);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let order = |client_id, order_id, side, price, qty| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, side, price, qty)
+        };
+        engine.process_request(&order(200, 1, -1, 10000, 30));
+        engine.process_request(&order(100, 2, -1, 10000, 50));
+
+        // Trades with client 200 ahead in the queue, then stops at its own ask
+        let (response, updates) = engine.process_request(&order(100, 3, 1, 10000, 60));
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((exec_qty, leaves_qty), (30, 0));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(engine.drain_maker_fills().count(), 1);
+
+        // Nothing to trade but its own order: canceled outright
+        let (response, updates) = engine.process_request(&order(100, 4, 1, 10000, 10));
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Canceled as u8);
+        assert_eq!((exec_qty, leaves_qty), (0, 0));
+        assert!(updates.is_empty());
+        assert_eq!(engine.stats().self_trades_prevented, 2);
+
+        // The resting order is untouched and another client still trades with it
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.order_count(), 1);
+        assert_eq!(book.get_order(2).map(|order| order.qty), Some(50));
+        let (response, _) = engine.process_request(&order(300, 5, 1, 10000, 10));
+        assert_eq!({ response.exec_qty }, 10);
+
+        // Off by default: a client can trade with itself
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        engine.process_request(&order(100, 1, -1, 10000, 10));
+        let (response, _) = engine.process_request(&order(100, 2, 1, 10000, 10));
+        assert_eq!({ response.exec_qty }, 10);
+    }
+
+    #[test]
+    fn test_partial_cross_rests_remainder() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, -1, 10000, 50);
+        engine.process_request(&request);
+
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 2, 1, 10000, 80);
+        let (response, updates) = engine.process_request(&request);
+        let exec_qty = response.exec_qty;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(exec_qty, 50);
+        assert_eq!(leaves_qty, 30);
+
+        // Trade then Add for the resting remainder
+        assert_eq!(updates.len(), 2);
+        let trade_type = updates[0].msg_type;
+        let add_type = updates[1].msg_type;
+        let add_qty = updates[1].qty;
+        assert_eq!(trade_type, MarketUpdateType::Trade as u8);
+        assert_eq!(add_type, MarketUpdateType::Add as u8);
+        assert_eq!(add_qty, 30);
+
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_ask(), None);
+        assert_eq!(book.best_bid(), Some(10000));
+        assert_eq!(engine.drain_maker_fills().count(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_order_replays_original_response() {
+        let config = MatchingEngineConfig::new().with_duplicate_detection(true);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 7, 1, 10000, 100);
+        let (first, updates) = engine.process_request(&request);
+        assert_eq!(updates.len(), 1);
+
+        // Resending the same client order ID does not add a second order
+        let (second, updates) = engine.process_request(&request);
+        let first_id = first.market_order_id;
+        let second_id = second.market_order_id;
+        let msg_type = second.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(second_id, first_id);
+        assert!(updates.is_empty());
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 1);
+        assert_eq!(engine.stats().duplicate_orders, 1);
+
+        // The same ID from another client is a different order
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 7, 1, 10000, 100);
+        engine.process_request(&request);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_window_forgets_oldest_orders() {
+        let config = MatchingEngineConfig::new()
+            .with_duplicate_detection(true)
+            .with_duplicate_window(2);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+
+        for order_id in 1..=3 {
+            let request = ClientRequest::new(ClientRequestType::New, 100, 1, order_id, 1, 10000, 100);
+            engine.process_request(&request);
+        }
+        assert_eq!(engine.submitted_orders.len(), 2);
+
+        // Order 3 is still remembered, order 1 has aged out
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 3, 1, 10000, 100);
+        engine.process_request(&request);
+        assert_eq!(engine.stats().duplicate_orders, 1);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 100);
+        engine.process_request(&request);
+        assert_eq!(engine.stats().duplicate_orders, 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 4);
+        assert_eq!(engine.submitted_orders.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_detection_disabled_by_default() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 7, 1, 10000, 100);
+        engine.process_request(&request);
+        engine.process_request(&request);
+
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 2);
+        assert_eq!(engine.stats().duplicate_orders, 0);
+    }
+
+    #[test]
+    fn test_last_look_rejects_after_adverse_mid_move() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = Arc::clone(&now);
+        let config = MatchingEngineConfig::new().with_last_look(1_000, 50);
+        let mut engine = MatchingEngine::with_config(config)
+            .with_clock(move || clock.load(Ordering::Relaxed));
+        engine.add_ticker(1);
+
+        // Mid 10000 at t=0
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, 1, 9900, 100));
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 2, -1, 10100, 100));
+
+        // A higher bid moves the mid up to 10075 within the window
+        now.store(500, Ordering::Relaxed);
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 300, 1, 1, 1, 10050, 100));
+
+        // Lifting the offer now is rejected: the mid rose 75 > 50 against the seller
+        now.store(600, Ordering::Relaxed);
+        let buy = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10100, 50);
+        let (response, updates) = engine.process_request(&buy);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::LastLookRejected as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.stats().last_look_rejects, 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 3);
+
+        // Non-marketable orders are never subject to last look
+        let passive = ClientRequest::new(ClientRequestType::New, 100, 1, 2, 1, 10000, 50);
+        let (response, _) = engine.process_request(&passive);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::Accepted as u8);
+
+        // Once the move is older than the window the same order trades
+        now.store(2_000, Ordering::Relaxed);
+        let buy = ClientRequest::new(ClientRequestType::New, 100, 1, 3, 1, 10100, 50);
+        let (response, _) = engine.process_request(&buy);
+        let response_type = response.msg_type;
+        let exec_qty = response.exec_qty;
+        assert_eq!(response_type, ClientResponseType::Filled as u8);
+        assert_eq!(exec_qty, 50);
+        assert_eq!(engine.stats().last_look_rejects, 1);
+    }
+
+    #[test]
+    fn test_last_look_rejects_market_orders() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = Arc::clone(&now);
+        let config = MatchingEngineConfig::new().with_last_look(1_000, 50);
+        let mut engine = MatchingEngine::with_config(config)
+            .with_clock(move || clock.load(Ordering::Relaxed));
+        engine.add_ticker(1);
+
+        // Mid 10000 at t=0, then up to 10075 within the window
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, 1, 9900, 100));
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 2, -1, 10100, 100));
+        now.store(500, Ordering::Relaxed);
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 300, 1, 1, 1, 10050, 100));
+
+        // A market buy gets the same last look as a marketable limit buy
+        now.store(600, Ordering::Relaxed);
+        let buy = ClientRequest::new(ClientRequestType::NewMarket, 100, 1, 1, 1, 0, 50);
+        let (response, updates) = engine.process_request(&buy);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::LastLookRejected as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.stats().last_look_rejects, 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 3);
+
+        // A market sell faces a favorable move and trades
+        let sell = ClientRequest::new(ClientRequestType::NewMarket, 100, 1, 2, -1, 0, 50);
+        let (response, _) = engine.process_request(&sell);
+        let (response_type, exec_qty) = (response.msg_type, response.exec_qty);
+        assert_eq!((response_type, exec_qty), (ClientResponseType::Filled as u8, 50));
+
+        // Once the move is older than the window the market buy trades
+        now.store(2_000, Ordering::Relaxed);
+        let buy = ClientRequest::new(ClientRequestType::NewMarket, 100, 1, 3, 1, 0, 50);
+        let (response, _) = engine.process_request(&buy);
+        let (response_type, exec_qty) = (response.msg_type, response.exec_qty);
+        assert_eq!((response_type, exec_qty), (ClientResponseType::Filled as u8, 50));
+        assert_eq!(engine.stats().last_look_rejects, 1);
+    }
+
+    #[test]
+    fn test_client_position_limit_rejects_new_orders() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        engine.set_client_position_limit(100, 1, 100);
+
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10000, 300));
+        let (fill, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 100));
+        let msg_type = fill.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!(engine.client_position(100, 1), 100);
+        assert_eq!(engine.client_position(200, 1), -100);
+
+        // At the limit: adding is rejected before it reaches the book
+        let (response, updates) =
+            engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 2, 1, 9000, 10));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::RiskRejected as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.stats().risk_rejects, 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 1);
+
+        // Reducing, and other clients, are unaffected
+        let (response, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 3, -1, 10100, 50));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        let (response, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 300, 1, 1, 1, 10000, 150));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+
+        engine.clear_client_position_limit(100, 1);
+        let (response, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 4, 1, 9000, 10));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+    }
+
+    #[test]
+    fn test_fills_stamped_with_exec_time() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let now = Arc::new(AtomicU64::new(1_000));
+        let clock = Arc::clone(&now);
+        let mut engine = MatchingEngine::new().with_clock(move || clock.load(Ordering::Relaxed));
+        engine.add_ticker(1);
+
+        let (ack, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10000, 100));
+        let exec_time_ns = ack.exec_time_ns;
+        assert_eq!(exec_time_ns, 0);
+
+        now.store(5_000, Ordering::Relaxed);
+        let (fill, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 40));
+        let exec_time_ns = fill.exec_time_ns;
+        assert_eq!(exec_time_ns, 5_000);
+
+        let maker_fills: Vec<_> = engine.drain_maker_fills().collect();
+        let exec_time_ns = maker_fills[0].exec_time_ns;
+        assert_eq!(exec_time_ns, 5_000);
+    }
+
+    #[test]
+    fn test_price_level_cap_rejects_and_recycles() {
+        let config = MatchingEngineConfig::new().with_max_levels_per_side(3);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let free_levels = engine.get_order_book(1).unwrap().free_level_count();
+
+        let mut top_bid_id = 0;
+        for (order_id, price) in [(1, 10000), (2, 9990), (3, 9980)] {
+            let request = ClientRequest::new(ClientRequestType::New, 100, 1, order_id, 1, price, 10);
+            let (response, _) = engine.process_request(&request);
+            let response_type = response.msg_type;
+            assert_eq!(response_type, ClientResponseType::Accepted as u8);
+            if price == 10000 {
+                top_bid_id = response.market_order_id;
+            }
+        }
+
+        // A fourth distinct price is rejected, but an existing level still accepts orders
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 4, 1, 9970, 10);
+        let (response, updates) = engine.process_request(&request);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 5, 1, 9980, 10);
+        let (response, _) = engine.process_request(&request);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::Accepted as u8);
+
+        // The ask side has its own cap
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10100, 10);
+        let (response, _) = engine.process_request(&request);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::Accepted as u8);
+
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.bid_level_count(), 3);
+        assert_eq!(book.level_rejects(), 1);
+        assert_eq!(book.free_level_count(), free_levels - 4);
+
+        // Emptying a level returns it to the pool and makes room for a new price
+        let cancel = ClientRequest::new(ClientRequestType::Cancel, 100, 1, top_bid_id, 1, 10000, 0);
+        engine.process_request(&cancel);
+        assert_eq!(engine.get_order_book(1).unwrap().free_level_count(), free_levels - 3);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 6, 1, 9970, 10);
+        let (response, _) = engine.process_request(&request);
+        let response_type = response.msg_type;
+        assert_eq!(response_type, ClientResponseType::Accepted as u8);
+
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.bid_level_count(), 3);
+        assert_eq!(book.best_bid(), Some(9990));
+        assert_eq!(book.free_level_count(), free_levels - 4);
+    }
+
+    /// Asks at 10010, 10020 and 10030, 10 each, protected ask at 10020, then
+    /// a buy for 30 at 10030
+    fn sweep_through_protected_ask(residual: ProtectedResidual) -> (MatchingEngine, ClientResponse) {
+        let config = MatchingEngineConfig::new()
+            .with_matching_algo(MatchingAlgo::ProtectBestPrice(residual));
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        for (i, price) in [10010, 10020, 10030].into_iter().enumerate() {
+            let request = ClientRequest::new(ClientRequestType::New, 200, 1, i as u64, -1, price, 10);
+            engine.process_request(&request);
+        }
+        engine.set_protected_price(1, INVALID_PRICE, 10020);
+        assert_eq!(engine.protected_price(1), Some((INVALID_PRICE, 10020)));
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 9, 1, 10030, 30);
+        let (response, _) = engine.process_request(&request);
+        (engine, response)
+    }
+
+    #[test]
+    fn test_protected_price_stops_sweep_and_rejects_residual() {
+        let (mut engine, response) = sweep_through_protected_ask(ProtectedResidual::Reject);
+        let msg_type = response.msg_type;
+        let exec_qty = response.exec_qty;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!(exec_qty, 20);
+        assert_eq!(leaves_qty, 0);
+        assert_eq!(engine.stats().protected_stops, 1);
+
+        // The level beyond the protected price is untouched, nothing rests
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_ask(), Some(10030));
+        assert_eq!(book.get_order(3).unwrap().qty, 10);
+        assert_eq!(book.best_bid(), None);
+
+        // With nothing executable inside the protection the order is rejected
+        engine.set_protected_price(1, INVALID_PRICE, 10025);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 10, 1, 10030, 5);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+
+        // Without protection the same order trades
+        engine.clear_protected_price(1);
+        let (response, _) = engine.process_request(&request);
+        let exec_qty = response.exec_qty;
+        assert_eq!(exec_qty, 5);
+    }
+
+    #[test]
+    fn test_protected_price_holds_residual() {
+        let (engine, response) = sweep_through_protected_ask(ProtectedResidual::Hold);
+        let exec_qty = response.exec_qty;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(exec_qty, 20);
+        assert_eq!(leaves_qty, 10);
+
+        // The residual rests at the protected price, below the remaining ask
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_bid(), Some(10020));
+        assert_eq!(book.best_ask(), Some(10030));
+        book.validate_book().unwrap();
+    }
+
+    #[test]
+    fn test_mass_quote_replaces_quotes_per_ticker() {
+        let mut engine = MatchingEngine::with_config(MatchingEngineConfig::new().with_mass_quotes(true));
+        for ticker_id in 1..=3 {
+            engine.add_ticker(ticker_id);
+        }
+        let response_types = |responses: &[ClientResponse]| -> Vec<u8> {
+            responses.iter().map(|response| response.msg_type).collect()
+        };
+
+        let first = [
+            QuoteEntry::new(1, 9990, 10, 10010, 10).with_order_ids(1, 2),
+            QuoteEntry::new(2, 4990, 20, 5010, 20).with_order_ids(3, 4),
+            QuoteEntry::new(3, 1990, 30, 2010, 30).with_order_ids(5, 6),
+        ];
+        let (responses, updates) = engine.process_mass_quote(&ClientRequest::mass_quote(100, 1, 3), &first);
+        assert_eq!(response_types(&responses), vec![ClientResponseType::Accepted as u8; 6]);
+        assert_eq!(updates.len(), 6);
+
+        // Another client lifts the whole ask on ticker 3
+        let lift = ClientRequest::new(ClientRequestType::New, 200, 3, 1, 1, 2010, 30);
+        engine.process_request(&lift);
+        engine.drain_maker_fills();
+
+        let second = [
+            QuoteEntry::new(1, 9980, 15, 10000, 15).with_order_ids(7, 8),
+            QuoteEntry::new(2, 4995, 25, 5005, 0).with_order_ids(9, 10),
+            QuoteEntry::new(3, 2000, 35, 2020, 35).with_order_ids(11, 12),
+        ];
+        let (responses, _) = engine.process_mass_quote(&ClientRequest::mass_quote(100, 2, 3), &second);
+        let canceled = ClientResponseType::Canceled as u8;
+        let accepted = ClientResponseType::Accepted as u8;
+        assert_eq!(
+            response_types(&responses),
+            vec![canceled, canceled, accepted, accepted, canceled, canceled, accepted, canceled, accepted, accepted]
+        );
+        assert_eq!(engine.stats().mass_quotes, 2);
+
+        // Each book holds exactly the new quotes; ticker 2 is bid only
+        let expected = [(1, Some(9980), Some(10000), 2), (2, Some(4995), None, 1), (3, Some(2000), Some(2020), 2)];
+        for (ticker_id, bid, ask, orders) in expected {
+            let book = engine.get_order_book(ticker_id).unwrap();
+            assert_eq!((book.best_bid(), book.best_ask(), book.order_count()), (bid, ask, orders));
+            assert_eq!(engine.client_quotes(100, ticker_id).len(), orders);
+            book.validate_book().unwrap();
+        }
+        assert_eq!(engine.get_order_book(1).unwrap().get_depth(Side::Buy, 5), vec![(9980, 15)]);
+        assert_eq!(engine.get_order_book(3).unwrap().get_depth(Side::Sell, 5), vec![(2020, 35)]);
+    }
+
+    #[test]
+    fn test_mass_quote_rejected_when_disabled_or_malformed() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        let entries = [QuoteEntry::new(1, 9990, 10, 10010, 10).with_order_ids(1, 2)];
+        let (responses, updates) = engine.process_mass_quote(&ClientRequest::mass_quote(100, 1, 1), &entries);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].response_type(), Some(ClientResponseType::InvalidRequest));
+        assert!(updates.is_empty());
+
+        let mut engine = MatchingEngine::with_config(MatchingEngineConfig::new().with_mass_quotes(true));
+        engine.add_ticker(1);
+        let (responses, _) = engine.process_mass_quote(&ClientRequest::mass_quote(100, 1, 2), &entries);
+        assert_eq!(responses[0].response_type(), Some(ClientResponseType::InvalidRequest));
+
+        // The header alone is not a request
+        let (response, _) = engine.process_request(&ClientRequest::mass_quote(100, 1, 1));
+        assert_eq!(response.response_type(), Some(ClientResponseType::InvalidRequest));
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 0);
+    }
+
+    /// Adds and cancels orders pseudo-randomly, leaving the pools scattered
+    fn churn(engine: &mut MatchingEngine) {
+        let mut seed: u64 = 7;
+        let mut next = move |bound: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % bound
+        };
+        let client_of = |order_id: u64| 100 + (order_id % 3) as u32;
+        for order_id in 1..=2000u64 {
+            if next(2) == 0 {
+                let target = 1 + next(order_id);
+                let cancel = ClientRequest::new(ClientRequestType::Cancel, client_of(target), 1, target, 1, 0, 0);
+                engine.process_request(&cancel);
+            }
+            let client_id = client_of(order_id);
+            let side = if next(2) == 0 { 1 } else { -1 };
+            let price = 10000 - side as i64 * (1 + next(20) as i64);
+            let request = ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, side, price, 1 + next(50) as u32);
+            engine.process_request(&request);
+        }
+        // Thin the book out so freed slots are left behind
+        for target in (1..=2000u64).step_by(4) {
+            let cancel = ClientRequest::new(ClientRequestType::Cancel, client_of(target), 1, target, 1, 0, 0);
+            engine.process_request(&cancel);
+        }
+    }
+
+    #[test]
+    fn test_compact_preserves_book_after_churn() {
+        let mut compacted = MatchingEngine::new().with_clock(|| 1);
+        let mut reference = MatchingEngine::new().with_clock(|| 1);
+        for engine in [&mut compacted, &mut reference] {
+            engine.add_ticker(1);
+            churn(engine);
+        }
+
+        let before = compacted.pool_stats()[0].fragmentation;
+        assert!(before.is_fragmented());
+        assert!(before.ratio() > 0.0);
+        let book = compacted.get_order_book(1).unwrap();
+        let dump = book.dump_book();
+        let bids = book.get_depth(Side::Buy, 100);
+        let asks = book.get_depth(Side::Sell, 100);
+        assert!(!bids.is_empty() && !asks.is_empty());
+
+        assert_eq!(compacted.compact(), 1);
+        assert_eq!(compacted.stats().compactions, 1);
+        let book = compacted.get_order_book(1).unwrap();
+        book.validate_book().unwrap();
+        assert_eq!(book.dump_book(), dump);
+        assert_eq!(book.get_depth(Side::Buy, 100), bids);
+        assert_eq!(book.get_depth(Side::Sell, 100), asks);
+        let after = compacted.pool_stats()[0].fragmentation;
+        assert_eq!(after.orders, before.orders);
+        assert_eq!(after.order_span, after.orders);
+        assert_eq!(after.level_span, after.levels);
+        assert_eq!(after.scattered_links, 0);
+        assert!(!after.is_fragmented());
+        assert_eq!(compacted.pool_stats()[0].orders, reference.pool_stats()[0].orders);
+
+        // Nothing left to do
+        assert_eq!(compacted.compact(), 0);
+
+        // Both engines keep trading identically
+        let sweep = ClientRequest::new(ClientRequestType::New, 300, 1, 1, 1, 10010, 400);
+        let (response, updates) = compacted.process_request(&sweep);
+        let (expected, expected_updates) = reference.process_request(&sweep);
+        assert_eq!(response.as_bytes(), expected.as_bytes());
+        assert_eq!(updates.len(), expected_updates.len());
+        let maker_fills: Vec<_> = compacted.drain_maker_fills().map(|fill| fill.as_bytes().to_vec()).collect();
+        let expected_fills: Vec<_> = reference.drain_maker_fills().map(|fill| fill.as_bytes().to_vec()).collect();
+        assert_eq!(maker_fills, expected_fills);
+        assert_eq!(
+            compacted.get_order_book(1).unwrap().dump_book(),
+            reference.get_order_book(1).unwrap().dump_book()
+        );
+    }
+
+    #[test]
+    fn test_compaction_threshold_skips_lightly_fragmented_books() {
+        let config = MatchingEngineConfig::new().with_compaction_threshold(0.99);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        churn(&mut engine);
+        assert!(engine.pool_stats()[0].fragmentation.is_fragmented());
+        assert_eq!(engine.compact(), 0);
+        assert_eq!(engine.stats().compactions, 0);
+    }
+
+    #[test]
+    fn test_small_order_pool_exhausts_cleanly() {
+        let config = MatchingEngineConfig::new()
+            .with_max_orders_per_book(4)
+            .with_max_tickers(1);
+        let mut engine = MatchingEngine::with_config(config);
+        assert!(engine.add_ticker(1));
+        assert!(engine.add_ticker(1));
+        assert!(!engine.add_ticker(2));
+        assert_eq!(engine.ticker_count(), 1);
+
+        for i in 0..4 {
+            let request = ClientRequest::new(ClientRequestType::New, 100, 1, i, 1, 10000 - i as i64, 10);
+            let (response, _) = engine.process_request(&request);
+            let msg_type = response.msg_type;
+            assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        }
+
+        // The fifth order finds the pool full and is rejected without updates
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 4, 1, 9990, 10);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+
+        let stats = engine.pool_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].ticker_id, 1);
+        assert_eq!(stats[0].orders.capacity, 4);
+        assert_eq!(stats[0].orders.peak_allocated, 4);
+        assert_eq!(stats[0].orders.failed_allocations, 1);
+        assert_eq!(stats[0].orders.peak_utilization(), 1.0);
+        assert_eq!(stats[0].levels.allocated, 4);
+
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.order_count(), 4);
+        book.validate_book().unwrap();
+    }
+
+    #[test]
+    fn test_modify_reprices_resting_order() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 7, 1, 10000, 50);
+        engine.process_request(&request);
+
+        // Move the bid up, still below the (empty) ask side
+        let request = ClientRequest::new(ClientRequestType::Modify, 100, 1, 1, 1, 10020, 30);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        let client_order_id = response.client_order_id;
+        let market_order_id = response.market_order_id;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(client_order_id, 7);
+        assert_eq!(market_order_id, 1);
+        assert_eq!(leaves_qty, 30);
+
+        assert_eq!(updates.len(), 2);
+        let cancel_type = updates[0].msg_type;
+        let cancel_price = updates[0].price;
+        let add_type = updates[1].msg_type;
+        let add_price = updates[1].price;
+        assert_eq!(cancel_type, MarketUpdateType::Cancel as u8);
+        assert_eq!(cancel_price, 10000);
+        assert_eq!(add_type, MarketUpdateType::Add as u8);
+        assert_eq!(add_price, 10020);
+        assert_eq!(engine.get_order_book(1).unwrap().best_bid(), Some(10020));
+
+        // Unknown order, another client's order, and zero quantity are rejected
+        for (client_id, order_id, qty) in [(100, 99, 30), (200, 1, 30), (100, 1, 0)] {
+            let request = ClientRequest::new(ClientRequestType::Modify, client_id, 1, order_id, 1, 10010, qty);
+            let (response, updates) = engine.process_request(&request);
+            let msg_type = response.msg_type;
+            assert_eq!(msg_type, ClientResponseType::ModifyRejected as u8);
+            assert!(updates.is_empty());
+        }
+    }
+
+    /// Rest bids A and B of 50 at 10000, for clients 100 and 200
+    fn rest_two_bids(engine: &mut MatchingEngine) -> (OrderId, OrderId) {
+        engine.add_ticker(1);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 50);
+        let a = engine.process_request(&request).0.market_order_id;
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 2, 1, 10000, 50);
+        let b = engine.process_request(&request).0.market_order_id;
+        (a, b)
+    }
+
+    /// Sell `qty` at 10000 and return the exchange order ID of the first
+    /// resting order filled
+    fn hit_bid(engine: &mut MatchingEngine, qty: Qty) -> OrderId {
+        let request = ClientRequest::new(ClientRequestType::New, 300, 1, 3, -1, 10000, qty);
+        engine.process_request(&request);
+        engine.drain_maker_fills().next().unwrap().market_order_id
+    }
+
+    #[test]
+    fn test_amend_qty_down_keeps_priority() {
+        let mut engine = MatchingEngine::new();
+        let (a, b) = rest_two_bids(&mut engine);
+
+        let request = ClientRequest::new(ClientRequestType::Amend, 100, 1, a, 1, 0, 30);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        let client_order_id = response.client_order_id;
+        let price = response.price;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(client_order_id, 1);
+        assert_eq!(price, 10000);
+        assert_eq!(leaves_qty, 30);
+
+        assert_eq!(updates.len(), 1);
+        let update_type = updates[0].msg_type;
+        let update_order_id = updates[0].order_id;
+        let update_qty = updates[0].qty;
+        assert_eq!(update_type, MarketUpdateType::Modify as u8);
+        assert_eq!(update_order_id, a);
+        assert_eq!(update_qty, 30);
+        assert_eq!(engine.get_order_book(1).unwrap().get_depth(Side::Buy, 1), vec![(10000, 80)]);
+        engine.get_order_book(1).unwrap().validate_book().unwrap();
+
+        // A is still first in the queue
+        assert_eq!(hit_bid(&mut engine, 30), a);
+
+        // Amending to the same quantity changes nothing
+        let request = ClientRequest::new(ClientRequestType::Amend, 200, 1, b, 1, 0, 50);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_amend_qty_up_rejected_by_default() {
+        let mut engine = MatchingEngine::new();
+        let (a, _) = rest_two_bids(&mut engine);
+
+        let request = ClientRequest::new(ClientRequestType::Amend, 100, 1, a, 1, 0, 80);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
+        assert!(updates.is_empty());
+        let order_qty = engine.get_order_book(1).unwrap().get_order(a).unwrap().qty;
+        assert_eq!(order_qty, 50);
+
+        // Unknown order, another client's order, and zero quantity are rejected
+        for (client_id, order_id, qty) in [(100, 99, 30), (200, a, 30), (100, a, 0)] {
+            let request = ClientRequest::new(ClientRequestType::Amend, client_id, 1, order_id, 1, 0, qty);
+            let (response, updates) = engine.process_request(&request);
+            let msg_type = response.msg_type;
+            assert_eq!(msg_type, ClientResponseType::ModifyRejected as u8);
+            assert!(updates.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_amend_qty_up_moves_to_back_of_queue() {
+        let config = MatchingEngineConfig::new().with_amend_qty_up(true);
+        let mut engine = MatchingEngine::with_config(config);
+        let (a, b) = rest_two_bids(&mut engine);
+
+        let request = ClientRequest::new(ClientRequestType::Amend, 100, 1, a, 1, 0, 80);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        let market_order_id = response.market_order_id;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(market_order_id, a);
+        assert_eq!(leaves_qty, 80);
+
+        assert_eq!(updates.len(), 1);
+        let update_type = updates[0].msg_type;
+        let update_qty = updates[0].qty;
+        assert_eq!(update_type, MarketUpdateType::Modify as u8);
+        assert_eq!(update_qty, 80);
+        assert_eq!(engine.get_order_book(1).unwrap().get_depth(Side::Buy, 1), vec![(10000, 130)]);
+        engine.get_order_book(1).unwrap().validate_book().unwrap();
+
+        // B now trades first
+        assert_eq!(hit_bid(&mut engine, 50), b);
+    }
+
+    /// Rest bids A at 10000 and B at 9990, lower A to 9990, then sell one
+    /// bid's worth at 9990 and return the exchange order ID that was filled
+    fn lower_first_bid_and_hit(engine: &mut MatchingEngine) -> (OrderId, OrderId, OrderId) {
+        engine.add_ticker(1);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10000, 50);
+        let a = engine.process_request(&request).0.market_order_id;
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 2, 1, 9990, 50);
+        let b = engine.process_request(&request).0.market_order_id;
+
+        let request = ClientRequest::new(ClientRequestType::Modify, 100, 1, a, 1, 9990, 50);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(updates.len(), 2);
+
+        let request = ClientRequest::new(ClientRequestType::New, 300, 1, 3, -1, 9990, 50);
+        engine.process_request(&request);
+        let fill = engine.drain_maker_fills().next().unwrap();
+        let filled = fill.market_order_id;
+        (a, b, filled)
+    }
+
+    #[test]
+    fn test_modify_less_aggressive_keeps_priority_when_configured() {
+        let policy = PriorityPolicy::new().with_less_aggressive_keeps(true);
+        let mut engine = MatchingEngine::with_config(MatchingEngineConfig::new().with_priority_policy(policy));
+        let (a, _, filled) = lower_first_bid_and_hit(&mut engine);
+        assert_eq!(filled, a);
+
+        // Under the default policy the lowered bid goes behind B
+        let mut engine = MatchingEngine::new();
+        let (_, b, filled) = lower_first_bid_and_hit(&mut engine);
+        assert_eq!(filled, b);
+
+        // A per-ticker policy overrides the configured default
+        let mut engine = MatchingEngine::new();
+        engine.set_priority_policy(1, policy);
+        assert_eq!(engine.priority_policy(1), policy);
+        assert_eq!(engine.priority_policy(2), PriorityPolicy::default());
+        let (a, _, filled) = lower_first_bid_and_hit(&mut engine);
+        assert_eq!(filled, a);
+    }
+
+    #[test]
+    fn test_priority_policy_cases() {
+        let default = PriorityPolicy::new();
+        let lenient = default.with_less_aggressive_keeps(true);
+        let strict = default.with_qty_down_keeps(false);
+
+        // Quantity down at the same price
+        assert!(default.keeps_priority(Side::Buy, 10000, 50, 10000, 30));
+        assert!(!strict.keeps_priority(Side::Buy, 10000, 50, 10000, 30));
+        // Unchanged
+        assert!(strict.keeps_priority(Side::Sell, 10000, 50, 10000, 50));
+        // Less aggressive: lower buy, higher sell
+        assert!(!default.keeps_priority(Side::Buy, 10000, 50, 9990, 50));
+        assert!(lenient.keeps_priority(Side::Buy, 10000, 50, 9990, 50));
+        assert!(lenient.keeps_priority(Side::Sell, 10000, 50, 10010, 40));
+        // More aggressive or larger always loses
+        assert!(!lenient.keeps_priority(Side::Buy, 10000, 50, 10010, 50));
+        assert!(!lenient.keeps_priority(Side::Sell, 10000, 50, 9990, 50));
+        assert!(!lenient.keeps_priority(Side::Buy, 10000, 50, 10000, 60));
+    }
+
+    #[test]
+    fn test_modify_through_best_ask_rejected_by_default() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 20, -1, 10100, 40);
+        engine.process_request(&request);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 10, 1, 10000, 50);
+        let (response, _) = engine.process_request(&request);
+        let bid_id = response.market_order_id;
+
+        let request = ClientRequest::new(ClientRequestType::Modify, 100, 1, bid_id, 1, 10100, 50);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::ModifyRejected as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.stats().modify_cross_rejects, 1);
+        assert_eq!(engine.pending_maker_fills(), 0);
+
+        // The bid is untouched
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_bid(), Some(10000));
+        assert_eq!(book.best_ask(), Some(10100));
+        assert_eq!(book.get_order(bid_id).unwrap().qty, 50);
+    }
+
+    #[test]
+    fn test_modify_through_best_ask_matches_when_configured() {
+        let config = MatchingEngineConfig::new().with_modify_cross_mode(ModifyCrossMode::Match);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 20, -1, 10100, 40);
+        engine.process_request(&request);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 10, 1, 10000, 50)
+            .with_tag(0xBB);
+        let (response, _) = engine.process_request(&request);
+        let bid_id = response.market_order_id;
+
+        let request = ClientRequest::new(ClientRequestType::Modify, 100, 1, bid_id, 1, 10100, 50);
+        let (response, updates) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        let client_order_id = response.client_order_id;
+        let price = response.price;
+        let exec_qty = response.exec_qty;
+        let leaves_qty = response.leaves_qty;
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!(client_order_id, 10);
+        assert_eq!(price, 10100);
+        assert_eq!(exec_qty, 40);
+        assert_eq!(leaves_qty, 10);
+        assert_eq!(response.liquidity_flag(), Some(LiquidityFlag::Taker));
+
+        // Cancel of the old bid, the trade, then the remainder resting
+        let types: Vec<u8> = updates.iter().map(|u| u.msg_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                MarketUpdateType::Cancel as u8,
+                MarketUpdateType::Trade as u8,
+                MarketUpdateType::Add as u8,
+            ]
+        );
+        assert_eq!(engine.drain_maker_fills().count(), 1);
+        assert_eq!(engine.stats().modify_cross_rejects, 0);
+
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_ask(), None);
+        assert_eq!(book.best_bid(), Some(10100));
+        assert_eq!(book.get_order(bid_id).unwrap().qty, 10);
+
+        // The remainder keeps the original client order ID for later fills
+        let request = ClientRequest::new(ClientRequestType::New, 300, 1, 30, -1, 10100, 10);
+        engine.process_request(&request);
+        let maker = engine.drain_maker_fills().next().unwrap();
+        let maker_order_id = maker.client_order_id;
+        let maker_tag = maker.tag;
+        assert_eq!(maker_order_id, 10);
+        assert_eq!(maker_tag, 0xBB);
+    }
+
+    #[test]
+    fn test_flicker_flags_rapid_add_cancel_only() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = Arc::clone(&now);
+        let config = MatchingEngineConfig::new()
+            .with_flicker_detection(FlickerConfig::new(1_000, 1_000_000, 3));
+        let mut engine = MatchingEngine::with_config(config)
+            .with_clock(move || clock.load(Ordering::Relaxed));
+        engine.add_ticker(1);
+        let new = |client_id, order_id, side, price| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, side, price, 10)
+        };
+        let cancel = |client_id, order_id| {
+            ClientRequest::new(ClientRequestType::Cancel, client_id, 1, order_id, 0, 0, 0)
+        };
+
+        // Client 100 flickers: each order is canceled 100ns after it rests.
+        // Client 200 quotes normally: orders rest 10us before being canceled.
+        for i in 0..4u64 {
+            let t = i * 20_000;
+            now.store(t, Ordering::Relaxed);
+            let (flicker, _) = engine.process_request(&new(100, i, 1, 9900));
+            let (normal, _) = engine.process_request(&new(200, i, -1, 10100));
+
+            now.store(t + 100, Ordering::Relaxed);
+            engine.process_request(&cancel(100, flicker.market_order_id));
+            now.store(t + 10_000, Ordering::Relaxed);
+            engine.process_request(&cancel(200, normal.market_order_id));
+        }
+
+        let flickerer = *engine.flicker_stats(100).unwrap();
+        assert_eq!(flickerer.cancels, 4);
+        assert_eq!(flickerer.flicker_cancels, 4);
+        assert!(flickerer.flagged);
+        assert_eq!(flickerer.flags, 1);
+
+        let quoter = *engine.flicker_stats(200).unwrap();
+        assert_eq!(quoter.cancels, 4);
+        assert_eq!(quoter.flicker_cancels, 0);
+        assert!(!quoter.flagged);
+
+        // One alert, raised on the third flicker cancel
+        let alerts: Vec<_> = engine.drain_flicker_alerts().collect();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].client_id, 100);
+        assert_eq!(alerts[0].flicker_cancels, 3);
+        assert_eq!(alerts[0].action, FlickerAction::Flag);
+
+        // Flag-only mode still accepts the client's orders
+        let (response, _) = engine.process_request(&new(100, 9, 1, 9900));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+    }
+
+    #[test]
+    fn test_flicker_throttle_rejects_until_window_resets() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = Arc::clone(&now);
+        let flicker = FlickerConfig::new(1_000, 1_000_000, 2).with_action(FlickerAction::Throttle);
+        let config = MatchingEngineConfig::new().with_flicker_detection(flicker);
+        let mut engine = MatchingEngine::with_config(config)
+            .with_clock(move || clock.load(Ordering::Relaxed));
+        engine.add_ticker(1);
+        let new = |client_id, order_id| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, 1, 9900, 10)
+        };
+
+        for i in 0..2u64 {
+            let (response, _) = engine.process_request(&new(100, i));
+            let order_id = response.market_order_id;
+            engine.process_request(&ClientRequest::new(ClientRequestType::Cancel, 100, 1, order_id, 1, 9900, 0));
+        }
+
+        let (response, _) = engine.process_request(&new(100, 5));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
+        assert_eq!(engine.flicker_stats(100).unwrap().throttled_orders, 1);
+
+        // Other clients are unaffected
+        let (response, _) = engine.process_request(&new(200, 5));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+
+        // A fresh window lifts the throttle
+        now.store(1_000_000, Ordering::Relaxed);
+        let (response, _) = engine.process_request(&new(100, 6));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert!(!engine.flicker_stats(100).unwrap().flagged);
+    }
+
+    #[test]
+    fn test_order_flow_stats_count_adds_cancels_and_trades() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let now = Arc::new(AtomicU64::new(1_000));
+        let clock = Arc::clone(&now);
+        let config = MatchingEngineConfig::new().with_order_flow_stats(true);
+        let mut engine = MatchingEngine::with_config(config)
+            .with_clock(move || clock.load(Ordering::Relaxed));
+        engine.add_ticker(1);
+        assert!(engine.ticker_stats(1).is_none());
+
+        // Submit then cancel 500ns later
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 9900, 10);
+        let (response, _) = engine.process_request(&request);
+        let order_id = response.market_order_id;
+        now.store(1_500, Ordering::Relaxed);
+        engine.process_request(&ClientRequest::new(ClientRequestType::Cancel, 100, 1, order_id, 1, 9900, 0));
+
+        let stats = *engine.ticker_stats(1).unwrap();
+        assert_eq!(stats.adds, 1);
+        assert_eq!(stats.cancels, 1);
+        assert_eq!(stats.trades, 0);
+        assert_eq!(stats.avg_resting_ns(), 500);
+        assert_eq!(stats.cancel_to_trade_ratio(), None);
+
+        // A resting sell is crossed 1500ns after it was added
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 2, -1, 10000, 10));
+        now.store(3_000, Ordering::Relaxed);
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 3, 1, 10000, 10));
+
+        let stats = *engine.ticker_stats(1).unwrap();
+        assert_eq!(stats.adds, 3);
+        assert_eq!(stats.trades, 1);
+        assert_eq!(stats.closed_orders, 2);
+        assert_eq!(stats.avg_resting_ns(), 1_000);
+        assert_eq!(stats.cancel_to_trade_ratio(), Some(1.0));
+
+        engine.reset_stats();
+        assert!(engine.ticker_stats(1).is_none());
+    }
+
+    #[test]
+    fn test_client_level_cap_rejects_new_levels_only() {
+        let config = MatchingEngineConfig::new().with_max_levels_per_client(2);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let new = |client_id, order_id, price| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, 1, price, 10)
+        };
+
+        engine.process_request(&new(100, 1, 9900));
+        engine.process_request(&new(100, 2, 9890));
+        assert_eq!(engine.client_level_count(100, 1), 2);
+
+        // A third price is refused, more size at an existing one is not
+        let (response, updates) = engine.process_request(&new(100, 3, 9880));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::LevelLimitRejected as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.stats().level_limit_rejects, 1);
 
-        (response, Vec::new())
+        let (response, _) = engine.process_request(&new(100, 4, 9900));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(engine.client_level_count(100, 1), 2);
+
+        // The cap is per client
+        let (response, _) = engine.process_request(&new(200, 5, 9880));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+
+        // Emptying a level frees it: cancel the single order at 9890
+        engine.process_request(&ClientRequest::new(ClientRequestType::Cancel, 100, 1, 2, 1, 9890, 0));
+        assert_eq!(engine.client_level_count(100, 1), 1);
+        let (response, _) = engine.process_request(&new(100, 6, 9880));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
     }
 
-    /// Create a cancel rejected response
-    fn create_cancel_reject_response(
-        &self,
-        client_id: ClientId,
-        ticker_id: TickerId,
-        order_id: OrderId,
-        side: i8,
-        price: Price,
-    ) -> (ClientResponse, Vec<MarketUpdate>) {
-        let response = ClientResponse::new(
-            ClientResponseType::CancelRejected,
-            client_id,
-            ticker_id,
-            order_id,
-            0, // no market order ID
-            side,
-            price,
-            0, // exec_qty
-            0, // leaves_qty
+    #[test]
+    fn test_stop_buy_activates_when_trade_prints_at_trigger() {
+        let config = MatchingEngineConfig::new().with_stop_orders(true);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        let order = |client_id, order_id, side, price, qty| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, side, price, qty)
+        };
+        let stop = |order_id, price| {
+            ClientRequest::new(ClientRequestType::NewStop, 100, 1, order_id, 1, 0, 20).with_stop_price(price)
+        };
+
+        engine.process_request(&order(200, 1, -1, 10000, 10));
+        engine.process_request(&order(200, 2, -1, 10100, 50));
+        engine.process_request(&order(300, 3, 1, 10000, 5));
+        engine.drain_maker_fills();
+
+        // The stop rests unseen above the last trade
+        let (response, updates) = engine.process_request(&stop(4, 10100));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.pending_stops(1), 1);
+
+        // A trade below the trigger leaves it waiting
+        engine.process_request(&order(300, 5, 1, 10000, 5));
+        engine.drain_maker_fills();
+        assert_eq!(engine.pending_stops(1), 1);
+
+        // A trade at the trigger activates it as a market order
+        let (_, updates) = engine.process_request(&order(300, 6, 1, 10100, 10));
+        assert_eq!(updates.len(), 2);
+        let responses: Vec<ClientResponse> = engine.drain_maker_fills().collect();
+        let types: Vec<u8> = responses.iter().map(|response| response.msg_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                ClientResponseType::Filled as u8,
+                ClientResponseType::Triggered as u8,
+                ClientResponseType::Filled as u8,
+                ClientResponseType::Filled as u8,
+            ]
         );
+        let stop_fill = responses[3];
+        let (client_id, client_order_id) = (stop_fill.client_id, stop_fill.client_order_id);
+        let (price, exec_qty, leaves_qty) = (stop_fill.price, stop_fill.exec_qty, stop_fill.leaves_qty);
+        assert_eq!((client_id, client_order_id), (100, 4));
+        assert_eq!((price, exec_qty, leaves_qty), (10100, 20, 0));
+        assert_eq!(engine.stats().stops_triggered, 1);
+        assert_eq!(engine.pending_stops(1), 0);
 
-        (response, Vec::new())
-    }
+        // A buy stop at or below the last trade would trigger on arrival
+        let (response, _) = engine.process_request(&stop(7, 10100));
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
 
-    /// Returns a reference to an order book for the given ticker
-    #[inline]
-    pub fn get_order_book(&self, ticker_id: TickerId) -> Option<&OrderBook> {
-        self.order_books.get(&ticker_id)
+        // A waiting stop can be canceled
+        let (response, _) = engine.process_request(&stop(8, 10200));
+        let market_order_id = response.market_order_id;
+        let cancel = ClientRequest::new(ClientRequestType::Cancel, 100, 1, market_order_id, 1, 0, 0);
+        let (response, updates) = engine.process_request(&cancel);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Canceled as u8);
+        assert!(updates.is_empty());
+        assert_eq!(engine.pending_stops(1), 0);
     }
 
-    /// Returns a mutable reference to an order book for the given ticker
-    #[inline]
-    pub fn get_order_book_mut(&mut self, ticker_id: TickerId) -> Option<&mut OrderBook> {
-        self.order_books.get_mut(&ticker_id)
-    }
+    #[test]
+    fn test_mass_quote_trade_activates_stop() {
+        let config = MatchingEngineConfig::new().with_stop_orders(true).with_mass_quotes(true);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
 
-    /// Returns the number of tickers in the matching engine
-    #[inline]
-    pub fn ticker_count(&self) -> usize {
-        self.order_books.len()
-    }
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10100, 50));
+        let stop = ClientRequest::new(ClientRequestType::NewStop, 100, 1, 2, 1, 0, 20).with_stop_price(10100);
+        engine.process_request(&stop);
+        assert_eq!(engine.pending_stops(1), 1);
 
-    /// Returns the next order ID that will be assigned
-    #[inline]
-    pub fn next_order_id(&self) -> OrderId {
-        self.next_order_id
-    }
-}
+        // A quoted bid lifting the ask prints at the trigger
+        let quote = [QuoteEntry::new(1, 10100, 10, 10200, 10).with_order_ids(3, 4)];
+        let (_, updates) = engine.process_mass_quote(&ClientRequest::mass_quote(300, 1, 1), &quote);
+        let trades = updates.iter().filter(|update| update.msg_type == MarketUpdateType::Trade as u8).count();
+        assert_eq!(trades, 2);
+        assert_eq!(engine.stats().stops_triggered, 1);
+        assert_eq!(engine.pending_stops(1), 0);
 
-impl Default for MatchingEngine {
-    fn default() -> Self {
-        Self::new()
+        let stop_fill = engine
+            .drain_maker_fills()
+            .find(|response| response.client_id == 100 && response.msg_type == ClientResponseType::Filled as u8)
+            .unwrap();
+        let (price, exec_qty) = (stop_fill.price, stop_fill.exec_qty);
+        assert_eq!((price, exec_qty), (10100, 20));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_new_matching_engine() {
-        let engine = MatchingEngine::new();
-        assert_eq!(engine.ticker_count(), 0);
-        assert_eq!(engine.next_order_id(), 1);
+    fn test_stop_orders_disabled_by_default() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        let request = ClientRequest::new(ClientRequestType::NewStopLimit, 100, 1, 1, 1, 10100, 10)
+            .with_stop_price(10050);
+        let (response, _) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
+        assert_eq!(engine.pending_stops(1), 0);
     }
 
     #[test]
-    fn test_add_ticker() {
+    fn test_ioc_fills_what_it_can_and_cancels_the_rest() {
         let mut engine = MatchingEngine::new();
         engine.add_ticker(1);
-        engine.add_ticker(2);
+        let ioc = |order_id, price, qty| {
+            ClientRequest::new(ClientRequestType::New, 100, 1, order_id, 1, price, qty)
+                .with_time_in_force(TimeInForce::Ioc)
+        };
 
-        assert_eq!(engine.ticker_count(), 2);
-        assert!(engine.get_order_book(1).is_some());
-        assert!(engine.get_order_book(2).is_some());
-        assert!(engine.get_order_book(3).is_none());
+        // Nothing crosses: canceled, nothing published
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10000, 60));
+        let (response, updates) = engine.process_request(&ioc(2, 9900, 100));
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Canceled as u8);
+        assert_eq!((exec_qty, leaves_qty), (0, 0));
+        assert!(updates.is_empty());
+
+        // 60 of 100 trade and the remaining 40 do not rest
+        let (response, updates) = engine.process_request(&ioc(3, 10000, 100));
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((exec_qty, leaves_qty), (60, 0));
+        assert_eq!(updates.len(), 1);
+        assert_eq!({ updates[0].msg_type }, MarketUpdateType::Trade as u8);
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.order_count(), 0);
+        assert_eq!(book.best_bid(), None);
     }
 
     #[test]
-    fn test_add_duplicate_ticker() {
+    fn test_fok_rejected_against_insufficient_size() {
         let mut engine = MatchingEngine::new();
         engine.add_ticker(1);
-        engine.add_ticker(1); // Should be idempotent
+        let fok = |order_id, qty| {
+            ClientRequest::new(ClientRequestType::New, 100, 1, order_id, 1, 10100, qty)
+                .with_time_in_force(TimeInForce::Fok)
+        };
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10000, 60));
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 2, -1, 10100, 60));
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 3, -1, 10200, 60));
 
-        assert_eq!(engine.ticker_count(), 1);
+        // 120 within the limit is not enough for 150: nothing trades
+        let (response, updates) = engine.process_request(&fok(4, 150));
+        let (msg_type, exec_qty) = (response.msg_type, response.exec_qty);
+        assert_eq!((msg_type, exec_qty), (ClientResponseType::InvalidRequest as u8, 0));
+        assert!(updates.is_empty());
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 3);
+        assert_eq!(engine.stats().fok_rejects, 1);
+
+        // 120 fills in full across both levels
+        let (response, updates) = engine.process_request(&fok(5, 120));
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((exec_qty, leaves_qty), (120, 0));
+        assert_eq!(updates.len(), 2);
+        assert_eq!(engine.get_order_book(1).unwrap().best_ask(), Some(10200));
+
+        // An unknown time in force is refused
+        let mut request = fok(6, 10);
+        request.time_in_force = 9;
+        let (response, _) = engine.process_request(&request);
+        assert_eq!({ response.msg_type }, ClientResponseType::InvalidRequest as u8);
     }
 
     #[test]
-    fn test_new_order_accepted() {
+    fn test_market_buy_sweeps_two_ask_levels() {
         let mut engine = MatchingEngine::new();
         engine.add_ticker(1);
+        let order = |order_id, price, qty| ClientRequest::new(ClientRequestType::New, 200, 1, order_id, -1, price, qty);
+        engine.process_request(&order(1, 10000, 100));
+        engine.process_request(&order(2, 10100, 100));
+        engine.process_request(&order(3, 10100, 100));
 
-        let request = ClientRequest::new(
-            ClientRequestType::New,
-            100,   // client_id
-            1,     // ticker_id
-            12345, // order_id
-            1,     // side (Buy)
-            10050, // price
-            100,   // qty
-        );
+        // 250 lots: all of 10000, then 10100 in time priority
+        let market = ClientRequest::new(ClientRequestType::NewMarket, 100, 1, 7, 1, 0, 250);
+        let (response, updates) = engine.process_request(&market);
+        let (msg_type, exec_qty, leaves_qty, price) =
+            (response.msg_type, response.exec_qty, response.leaves_qty, response.price);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((exec_qty, leaves_qty), (250, 0));
+        assert_eq!(price, (10000 * 100 + 10100 * 150) / 250);
 
-        let (response, updates) = engine.process_request(&request);
+        let trades: Vec<(OrderId, i8, Price, Qty)> = updates
+            .iter()
+            .map(|update| {
+                assert_eq!({ update.msg_type }, MarketUpdateType::Trade as u8);
+                (update.order_id, update.side, update.price, update.qty)
+            })
+            .collect();
+        assert_eq!(trades, vec![(1, 1, 10000, 100), (2, 1, 10100, 100), (3, 1, 10100, 50)]);
+        assert_eq!(engine.drain_maker_fills().count(), 3);
+        assert_eq!(engine.stats().market_orders, 1);
 
-        // Copy fields to local variables to avoid unaligned reference issues
-        let msg_type = response.msg_type;
-        let client_id = response.client_id;
-        let ticker_id = response.ticker_id;
-        let client_order_id = response.client_order_id;
-        let market_order_id = response.market_order_id;
-        let side = response.side;
-        let price = response.price;
-        let exec_qty = response.exec_qty;
-        let leaves_qty = response.leaves_qty;
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_ask(), Some(10100));
+        assert_eq!(book.order_count(), 1);
+        assert!(book.validate_book().is_ok());
+    }
 
-        // Verify response
-        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
-        assert_eq!(client_id, 100);
-        assert_eq!(ticker_id, 1);
-        assert_eq!(client_order_id, 12345);
-        assert_eq!(market_order_id, 1); // First order ID
-        assert_eq!(side, 1);
-        assert_eq!(price, 10050);
-        assert_eq!(exec_qty, 0);
-        assert_eq!(leaves_qty, 100);
+    #[test]
+    fn test_sweep_reports_rounded_average_price() {
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        let ask = |order_id, price, qty| ClientRequest::new(ClientRequestType::New, 200, 1, order_id, -1, price, qty);
+        engine.process_request(&ask(1, 10000, 1));
+        engine.process_request(&ask(2, 10001, 2));
+        engine.process_request(&ask(3, 10001, 5));
 
-        // Verify market update
-        assert_eq!(updates.len(), 1);
-        let update = &updates[0];
-        let upd_msg_type = update.msg_type;
-        let upd_ticker_id = update.ticker_id;
-        let upd_order_id = update.order_id;
-        let upd_side = update.side;
-        let upd_price = update.price;
-        let upd_qty = update.qty;
+        // 30002 over 3 is 10000.67: reported as 10001, not truncated
+        let (response, _) = engine.process_request(&ClientRequest::new(ClientRequestType::New, 100, 1, 4, 1, 10001, 3));
+        let (price, exec_qty) = (response.price, response.exec_qty);
+        assert_eq!((price, exec_qty), (10001, 3));
 
-        assert_eq!(upd_msg_type, MarketUpdateType::Add as u8);
-        assert_eq!(upd_ticker_id, 1);
-        assert_eq!(upd_order_id, 1);
-        assert_eq!(upd_side, 1);
-        assert_eq!(upd_price, 10050);
-        assert_eq!(upd_qty, 100);
+        // The consumed orders' quantity is off their levels, the rest intact
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.get_depth(Side::Sell, 5), vec![(10001, 5)]);
+        assert!(book.validate_book().is_ok());
 
-        // Verify order ID incremented
-        assert_eq!(engine.next_order_id(), 2);
+        assert_eq!(average_price(3, 2), 2);
+        assert_eq!(average_price(-3, 2), -1);
+        assert_eq!(average_price(-30002, 3), -10001);
     }
 
     #[test]
-    fn test_new_order_unknown_ticker() {
+    fn test_market_order_never_rests() {
         let mut engine = MatchingEngine::new();
-        // Don't add any tickers
+        engine.add_ticker(1);
+        let market = |order_id, side, qty| ClientRequest::new(ClientRequestType::NewMarket, 100, 1, order_id, side, 0, qty);
 
-        let request = ClientRequest::new(
-            ClientRequestType::New,
-            100,   // client_id
-            999,   // ticker_id - doesn't exist
-            12345, // order_id
-            1,     // side
-            10050, // price
-            100,   // qty
-        );
+        // Nothing to trade against: canceled, and the book stays empty
+        let (response, updates) = engine.process_request(&market(1, -1, 50));
+        let (msg_type, exec_qty) = (response.msg_type, response.exec_qty);
+        assert_eq!((msg_type, exec_qty), (ClientResponseType::Canceled as u8, 0));
+        assert!(updates.is_empty());
 
-        let (response, updates) = engine.process_request(&request);
+        // A partial fill drops the remainder
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 2, 1, 9900, 30));
+        let (response, updates) = engine.process_request(&market(3, -1, 50));
+        let (msg_type, exec_qty, leaves_qty, price) =
+            (response.msg_type, response.exec_qty, response.leaves_qty, response.price);
+        assert_eq!(msg_type, ClientResponseType::Filled as u8);
+        assert_eq!((exec_qty, leaves_qty, price), (30, 0, 9900));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 0);
 
-        assert_eq!(response.msg_type, ClientResponseType::InvalidRequest as u8);
-        assert!(updates.is_empty());
+        let (response, _) = engine.process_request(&market(4, 0, 50));
+        assert_eq!({ response.msg_type }, ClientResponseType::InvalidRequest as u8);
     }
 
     #[test]
-    fn test_new_order_invalid_side() {
-        let mut engine = MatchingEngine::new();
+    fn test_aon_fills_only_once_full_quantity_is_available() {
+        let config = MatchingEngineConfig::new().with_aon_orders(AonResidual::Rest);
+        let mut engine = MatchingEngine::with_config(config);
         engine.add_ticker(1);
+        let order = |order_id, price, qty| ClientRequest::new(ClientRequestType::New, 200, 1, order_id, -1, price, qty);
+        let aon = ClientRequest::new(ClientRequestType::NewAon, 100, 1, 1, 1, 10100, 200);
 
-        let request = ClientRequest::new(
-            ClientRequestType::New,
-            100,   // client_id
-            1,     // ticker_id
-            12345, // order_id
-            0,     // side - invalid!
-            10050, // price
-            100,   // qty
-        );
+        // 100 available: the AON rests unseen and nothing trades
+        engine.process_request(&order(2, 10000, 100));
+        let (response, updates) = engine.process_request(&aon);
+        let (msg_type, exec_qty, leaves_qty) = (response.msg_type, response.exec_qty, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!((exec_qty, leaves_qty), (0, 200));
+        assert!(updates.is_empty());
+        assert_eq!(engine.pending_aons(1), 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 1);
 
-        let (response, updates) = engine.process_request(&request);
+        // Liquidity beyond the AON's limit does not count
+        engine.process_request(&order(3, 10200, 100));
+        assert_eq!(engine.pending_aons(1), 1);
+        assert_eq!(engine.drain_maker_fills().count(), 0);
 
-        assert_eq!(response.msg_type, ClientResponseType::InvalidRequest as u8);
-        assert!(updates.is_empty());
+        // 200 available within the limit: the AON sweeps both levels
+        let (_, updates) = engine.process_request(&order(4, 10100, 100));
+        let trades = updates
+            .iter()
+            .filter(|update| update.msg_type == MarketUpdateType::Trade as u8)
+            .count();
+        assert_eq!(trades, 2);
+        let responses: Vec<ClientResponse> = engine.drain_maker_fills().collect();
+        let aon_fill = responses.last().unwrap();
+        let (client_id, msg_type) = (aon_fill.client_id, aon_fill.msg_type);
+        let (exec_qty, leaves_qty, price) = (aon_fill.exec_qty, aon_fill.leaves_qty, aon_fill.price);
+        assert_eq!((client_id, msg_type), (100, ClientResponseType::Filled as u8));
+        assert_eq!((exec_qty, leaves_qty, price), (200, 0, 10050));
+        assert_eq!(engine.pending_aons(1), 0);
+        assert_eq!(engine.stats().aon_executions, 1);
+        let book = engine.get_order_book(1).unwrap();
+        assert_eq!(book.best_ask(), Some(10200));
+        assert!(book.validate_book().is_ok());
     }
 
     #[test]
-    fn test_cancel_order_not_found() {
-        let mut engine = MatchingEngine::new();
+    fn test_aon_does_not_count_own_liquidity_under_self_trade_prevention() {
+        let config = MatchingEngineConfig::new()
+            .with_aon_orders(AonResidual::Rest)
+            .with_self_trade_prevention(SelfTradePrevention::CancelResting);
+        let mut engine = MatchingEngine::with_config(config);
         engine.add_ticker(1);
+        let ask = |client_id, order_id, qty| ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, -1, 10000, qty);
+        let aon = ClientRequest::new(ClientRequestType::NewAon, 100, 1, 3, 1, 10000, 200);
 
-        let request = ClientRequest::new(
-            ClientRequestType::Cancel,
-            100,   // client_id
-            1,     // ticker_id
-            99999, // order_id - doesn't exist
-            1,     // side
-            10050, // price
-            0,     // qty (not used for cancel)
-        );
+        // 200 offered, but 100 of it is the AON owner's own
+        engine.process_request(&ask(100, 1, 100));
+        engine.process_request(&ask(200, 2, 100));
+        let (response, updates) = engine.process_request(&aon);
+        let (msg_type, exec_qty) = (response.msg_type, response.exec_qty);
+        assert_eq!((msg_type, exec_qty), (ClientResponseType::Accepted as u8, 0));
+        assert!(updates.is_empty());
+        assert_eq!(engine.pending_aons(1), 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 2);
 
-        let (response, updates) = engine.process_request(&request);
+        // Once others offer 200, the own order is pulled and the AON fills in full
+        engine.process_request(&ask(300, 4, 100));
+        let responses: Vec<ClientResponse> = engine.drain_maker_fills().collect();
+        let aon_fill = responses.last().unwrap();
+        let (client_id, msg_type) = (aon_fill.client_id, aon_fill.msg_type);
+        let (exec_qty, leaves_qty) = (aon_fill.exec_qty, aon_fill.leaves_qty);
+        assert_eq!((client_id, msg_type), (100, ClientResponseType::Filled as u8));
+        assert_eq!((exec_qty, leaves_qty), (200, 0));
+        assert_eq!(engine.pending_aons(1), 0);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 0);
 
-        assert_eq!(response.msg_type, ClientResponseType::CancelRejected as u8);
-        assert!(updates.is_empty());
+        // Under CancelIncoming, liquidity behind the owner's order is out of reach
+        let config = MatchingEngineConfig::new()
+            .with_aon_orders(AonResidual::Rest)
+            .with_self_trade_prevention(SelfTradePrevention::CancelIncoming);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        engine.process_request(&ask(100, 1, 100));
+        engine.process_request(&ask(200, 2, 100));
+        engine.process_request(&ask(300, 4, 100));
+        let (response, _) = engine.process_request(&aon);
+        let (msg_type, exec_qty) = (response.msg_type, response.exec_qty);
+        assert_eq!((msg_type, exec_qty), (ClientResponseType::Accepted as u8, 0));
+        assert_eq!(engine.pending_aons(1), 1);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 3);
     }
 
     #[test]
-    fn test_cancel_order_unknown_ticker() {
-        let mut engine = MatchingEngine::new();
-        // Don't add any tickers
-
-        let request = ClientRequest::new(
-            ClientRequestType::Cancel,
-            100,   // client_id
-            999,   // ticker_id - doesn't exist
-            12345, // order_id
-            1,     // side
-            10050, // price
-            0,     // qty
-        );
+    fn test_aon_cancel_residual_and_disabled_by_default() {
+        let aon = ClientRequest::new(ClientRequestType::NewAon, 100, 1, 1, 1, 10100, 200);
 
-        let (response, updates) = engine.process_request(&request);
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        let (response, _) = engine.process_request(&aon);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
 
-        assert_eq!(response.msg_type, ClientResponseType::CancelRejected as u8);
+        let config = MatchingEngineConfig::new().with_aon_orders(AonResidual::Cancel);
+        let mut engine = MatchingEngine::with_config(config);
+        engine.add_ticker(1);
+        engine.process_request(&ClientRequest::new(ClientRequestType::New, 200, 1, 2, -1, 10000, 100));
+        let (response, updates) = engine.process_request(&aon);
+        let (msg_type, leaves_qty) = (response.msg_type, response.leaves_qty);
+        assert_eq!(msg_type, ClientResponseType::Canceled as u8);
+        assert_eq!(leaves_qty, 0);
         assert!(updates.is_empty());
+        assert_eq!(engine.pending_aons(1), 0);
     }
 
     #[test]
-    fn test_invalid_request_type() {
-        let mut engine = MatchingEngine::new();
+    fn test_instrument_tick_grid_and_scaled_fees() {
+        let config = MatchingEngineConfig::new()
+            .with_fee_schedule(FeeSchedule::new(0.0, 10.0));
+        let mut engine = MatchingEngine::with_config(config);
         engine.add_ticker(1);
+        engine.set_instrument(InstrumentSpec::new(1).with_price_scale(4).with_tick_size(5));
+        assert_eq!(engine.price_scale(1), 4);
+        assert_eq!(engine.price_scale(2), common::DEFAULT_PRICE_SCALE);
 
-        // Create a request with invalid msg_type
-        let request = ClientRequest {
-            msg_type: 255, // Invalid type
-            client_id: 100,
-            ticker_id: 1,
-            order_id: 12345,
-            side: 1,
-            price: 10050,
-            qty: 100,
-        };
+        // 1.2342 is off the 0.0005 grid
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, -1, 12342, 100);
+        let (response, _) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::InvalidRequest as u8);
 
-        let (response, updates) = engine.process_request(&request);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 2, -1, 12345, 10_000);
+        engine.process_request(&request);
+        let request = ClientRequest::new(ClientRequestType::Modify, 100, 1, 1, -1, 12346, 10_000);
+        let (response, _) = engine.process_request(&request);
+        let msg_type = response.msg_type;
+        assert_eq!(msg_type, ClientResponseType::ModifyRejected as u8);
+        assert_eq!(engine.stats().off_tick_rejects, 2);
 
-        assert_eq!(response.msg_type, ClientResponseType::InvalidRequest as u8);
-        assert!(updates.is_empty());
+        // 10,000 at $1.2345 is $12,345: a 10bp taker fee is 1,235 cents
+        let request = ClientRequest::new(ClientRequestType::New, 200, 1, 3, 1, 12345, 10_000);
+        engine.process_request(&request);
+        assert_eq!(engine.stats().taker_fees, 1_235);
     }
 
     #[test]
-    fn test_multiple_orders_increment_id() {
+    fn test_instrument_lot_and_notional_validation() {
         let mut engine = MatchingEngine::new();
         engine.add_ticker(1);
+        // Lots of 10 and orders between $100 and $10,000
+        let instruments = [InstrumentSpec::new(1).with_lot_size(10).with_notional_limits(10_000, 1_000_000)];
+        engine.set_instruments(instruments.into_iter().collect());
+        assert_eq!(engine.instrument(1).lot_size, 10);
 
-        for i in 0..5 {
-            let request = ClientRequest::new(
-                ClientRequestType::New,
-                100,
-                1,
-                i as u64,
-                1,
-                10050 + i as i64,
-                100,
-            );
-
-            let (response, _) = engine.process_request(&request);
-            let market_order_id = response.market_order_id;
-            assert_eq!(market_order_id, (i + 1) as u64);
+        let reject = ClientResponseType::InvalidRequest as u8;
+        for (order_id, qty) in [(1, 15), (2, 0), (3, 110)] {
+            let request = ClientRequest::new(ClientRequestType::New, 100, 1, order_id, 1, 10_000, qty);
+            let msg_type = engine.process_request(&request).0.msg_type;
+            assert_eq!(msg_type, reject);
         }
+        assert_eq!(engine.stats().off_lot_rejects, 1);
+        assert_eq!(engine.stats().notional_rejects, 2);
 
-        assert_eq!(engine.next_order_id(), 6);
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 4, 1, 10_000, 50);
+        let msg_type = engine.process_request(&request).0.msg_type;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+
+        // Modifies are held to the same lots
+        let request = ClientRequest::new(ClientRequestType::Modify, 100, 1, 4, 1, 10_000, 55);
+        let msg_type = engine.process_request(&request).0.msg_type;
+        assert_eq!(msg_type, ClientResponseType::ModifyRejected as u8);
+        assert_eq!(engine.stats().off_lot_rejects, 2);
     }
 
     #[test]
@@ -1,13 +1,29 @@
 // Price-time priority order book
 //
 // Implements an order book with:
-// - Price levels stored in HashMap<Price, PriceLevel>
+// - Price levels allocated from a memory pool, indexed by HashMap<Price, usize>
 // - Orders within each price level in FIFO order (doubly-linked list)
 // - Memory pool for order storage
 // - O(1) order lookup by OrderId
+//
+// The number of levels per side is capped so a deep book cannot grow memory
+// without bound: an order that would open a level beyond the cap is rejected.
+// Order and level pools are sized at construction, so each deployment can
+// pick capacities for its order counts; `order_pool_stats` and
+// `level_pool_stats` report their utilization.
+//
+// Each level is kept sorted by priority. New orders get the next priority and
+// join the tail; an order re-added with an earlier priority (a modify that
+// keeps its place in the queue) is linked in ahead of later orders. An order
+// reduced in place with `reduce_order` keeps its priority.
+//
+// Recycled pool slots scatter a long-lived book across its pools, so the
+// orders of one level no longer sit next to each other. `fragmentation`
+// measures this and `compact` rebuilds the book with each level's orders in
+// consecutive slots, best level first, without changing what it contains.
 
 use common::{OrderId, TickerId, ClientId, Price, Qty, Side, Priority};
-use common::mem_pool::{MemPool, PoolPtr};
+use common::mem_pool::{MemPool, PoolPtr, PoolStats};
 use std::collections::HashMap;
 
 /// An order in the order book.
@@ -26,6 +42,15 @@ pub struct Order {
     next_idx: Option<usize>,
 }
 
+/// Capacity of each book's price level pool, shared by both sides
+pub const MAX_PRICE_LEVELS: usize = 4096;
+
+/// Default cap on price levels per side
+pub const DEFAULT_MAX_LEVELS_PER_SIDE: usize = MAX_PRICE_LEVELS / 2;
+
+/// Default capacity of each book's order pool
+pub const DEFAULT_MAX_ORDERS: usize = 65536;
+
 /// A price level containing orders at the same price.
 /// Uses indices for head/tail to avoid PoolPtr ownership issues.
 pub struct PriceLevel {
@@ -73,6 +98,58 @@ impl PriceLevel {
     }
 }
 
+/// A single execution against a resting order, produced by `match_order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    /// Exchange order ID of the resting order
+    pub order_id: OrderId,
+    /// Owner of the resting order
+    pub client_id: ClientId,
+    /// Side of the resting order
+    pub side: Side,
+    /// Execution price (the resting order's price)
+    pub price: Price,
+    /// Quantity executed
+    pub qty: Qty,
+    /// Quantity left on the resting order after this execution
+    pub leaves_qty: Qty,
+    /// Queue priority of the resting order
+    pub priority: Priority,
+}
+
+/// How scattered a book's orders and levels are across their pools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookFragmentation {
+    /// Resting orders
+    pub orders: usize,
+    /// Slots from the lowest to the highest occupied order slot
+    pub order_span: usize,
+    /// Price levels on both sides
+    pub levels: usize,
+    /// Slots from the lowest to the highest occupied level slot
+    pub level_span: usize,
+    /// Orders whose successor in the queue is not in the next slot
+    pub scattered_links: usize,
+}
+
+impl BookFragmentation {
+    /// Fraction of the occupied spans taken by free slots, from 0.0
+    /// (contiguous) towards 1.0
+    pub fn ratio(&self) -> f64 {
+        let span = self.order_span + self.level_span;
+        if span == 0 {
+            return 0.0;
+        }
+        (span - self.orders - self.levels) as f64 / span as f64
+    }
+
+    /// Returns true if anything would move on compaction
+    #[inline]
+    pub fn is_fragmented(&self) -> bool {
+        self.ratio() > 0.0 || self.scattered_links > 0
+    }
+}
+
 /// Maps OrderId to pool index for O(1) lookup
 struct OrderIndex {
     pool_idx: usize,
@@ -81,27 +158,58 @@ struct OrderIndex {
 /// Price-time priority order book
 pub struct OrderBook {
     ticker_id: TickerId,
-    bid_levels: HashMap<Price, PriceLevel>,
-    ask_levels: HashMap<Price, PriceLevel>,
+    /// Maps bid prices to level pool index
+    bid_levels: HashMap<Price, usize>,
+    /// Maps ask prices to level pool index
+    ask_levels: HashMap<Price, usize>,
     /// Maps OrderId to pool index for O(1) lookup
     order_map: HashMap<OrderId, OrderIndex>,
-    /// Memory pool for orders - boxed to avoid stack overflow
-    order_pool: Box<MemPool<Order, 65536>>,
+    /// Memory pool for orders, heap-allocated at construction
+    order_pool: MemPool<Order>,
+    /// Memory pool for price levels of both sides
+    level_pool: MemPool<PriceLevel>,
+    /// Maximum number of price levels on each side
+    max_levels_per_side: usize,
+    /// Orders rejected because they would exceed the level cap
+    level_rejects: u64,
     next_priority: Priority,
 }
 
 impl OrderBook {
     /// Creates a new order book for the given ticker
     ///
-    /// Note: The memory pool is heap-allocated via `new_boxed()` to avoid
-    /// stack overflow since it's very large (~5.7MB for 65536 orders).
+    /// Note: The default order pool is large (~5.7MB for 65536 orders); use
+    /// `with_capacity` to size it for the deployment.
     pub fn new(ticker_id: TickerId) -> Self {
+        Self::with_max_levels(ticker_id, DEFAULT_MAX_LEVELS_PER_SIDE)
+    }
+
+    /// Creates a new order book with a cap on price levels per side
+    ///
+    /// The cap is clamped to `1..=MAX_PRICE_LEVELS / 2` so both sides always
+    /// fit in the level pool.
+    pub fn with_max_levels(ticker_id: TickerId, max_levels_per_side: usize) -> Self {
+        Self::with_capacity(ticker_id, DEFAULT_MAX_ORDERS, max_levels_per_side)
+    }
+
+    /// Creates a new order book with pools sized for the given capacities
+    ///
+    /// The order pool holds `max_orders` resting orders (at least one) and
+    /// the level pool holds `max_levels_per_side` levels for each side, the
+    /// cap being clamped as in `with_max_levels`. Orders beyond the order
+    /// pool's capacity are rejected like orders beyond the level cap.
+    pub fn with_capacity(ticker_id: TickerId, max_orders: usize, max_levels_per_side: usize) -> Self {
+        let max_levels_per_side = max_levels_per_side.clamp(1, MAX_PRICE_LEVELS / 2);
+        let max_orders = max_orders.max(1);
         Self {
             ticker_id,
-            bid_levels: HashMap::new(),
-            ask_levels: HashMap::new(),
-            order_map: HashMap::new(),
-            order_pool: MemPool::new_boxed(),
+            bid_levels: HashMap::with_capacity(max_levels_per_side),
+            ask_levels: HashMap::with_capacity(max_levels_per_side),
+            order_map: HashMap::with_capacity(max_orders),
+            order_pool: MemPool::with_capacity(max_orders),
+            level_pool: MemPool::with_capacity(max_levels_per_side * 2),
+            max_levels_per_side,
+            level_rejects: 0,
             next_priority: 1,
         }
     }
@@ -117,6 +225,7 @@ impl OrderBook {
     /// Returns the PoolPtr to the new order, or None if:
     /// - The order pool is exhausted
     /// - An order with the same order_id already exists
+    /// - The order would open a price level beyond the per-side cap
     pub fn add_order(
         &mut self,
         client_id: ClientId,
@@ -124,20 +233,63 @@ impl OrderBook {
         side: Side,
         price: Price,
         qty: Qty,
+    ) -> Option<PoolPtr<Order>> {
+        let priority = self.next_priority;
+        let ptr = self.add_order_with_priority(client_id, order_id, side, price, qty, priority)?;
+        self.next_priority += 1;
+        Some(ptr)
+    }
+
+    /// Adds an order that keeps a previously assigned priority
+    ///
+    /// The order is queued at its price level ahead of every order with a
+    /// later priority, so an order moved by a modify can keep its place in
+    /// time. Fails for the same reasons as `add_order`.
+    pub fn add_order_with_priority(
+        &mut self,
+        client_id: ClientId,
+        order_id: OrderId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+        priority: Priority,
     ) -> Option<PoolPtr<Order>> {
         // Check if order already exists
         if self.order_map.contains_key(&order_id) {
             return None;
         }
 
+        // Get the appropriate side's levels
+        let levels = match side {
+            Side::Buy => &mut self.bid_levels,
+            Side::Sell => &mut self.ask_levels,
+        };
+
+        // Find the price level, or make sure a new one can be opened
+        let existing_level = levels.get(&price).copied();
+        if existing_level.is_none() && levels.len() >= self.max_levels_per_side {
+            self.level_rejects += 1;
+            return None;
+        }
+
         // Allocate from pool
         let ptr = self.order_pool.allocate()?;
         let new_idx = ptr.index();
 
-        // Initialize the order
-        let priority = self.next_priority;
-        self.next_priority += 1;
+        let level_idx = match existing_level {
+            Some(level_idx) => level_idx,
+            None => {
+                let Some(level_ptr) = self.level_pool.allocate() else {
+                    self.order_pool.deallocate(ptr);
+                    return None;
+                };
+                *self.level_pool.get_mut(&level_ptr) = PriceLevel::new(price);
+                levels.insert(price, level_ptr.index());
+                level_ptr.index()
+            }
+        };
 
+        // Initialize the order
         *self.order_pool.get_mut(&ptr) = Order {
             order_id,
             client_id,
@@ -150,67 +302,43 @@ impl OrderBook {
             next_idx: None,
         };
 
-        // Get the appropriate side's levels
-        let levels = match side {
-            Side::Buy => &mut self.bid_levels,
-            Side::Sell => &mut self.ask_levels,
-        };
-
-        // Get or create the price level
-        let level = levels.entry(price).or_insert_with(|| PriceLevel::new(price));
-
-        // Add order to the tail of the price level (FIFO)
-        if let Some(tail_idx) = level.tail_idx {
-            // There's an existing tail - link to it
-            // Set prev of new order to point to old tail
-            self.order_pool.get_mut(&ptr).prev_idx = Some(tail_idx);
-
-            // Set next of old tail to point to new order
-            // Find the tail order using order_map iteration
-            for (_, idx_info) in &self.order_map {
-                if idx_info.pool_idx == tail_idx {
-                    // We need to get a mutable reference to update the next pointer
-                    // This is safe because we're using indices
-                    let _tail_order = unsafe {
-                        &mut *(self.order_pool.get(&ptr) as *const Order as *mut Order)
-                            .offset((tail_idx as isize) - (new_idx as isize))
-                    };
-                    // Actually, this approach is unsafe. Let's use a different method.
-                    break;
-                }
+        // SAFETY: level_idx is either stored in the price map or was just
+        // allocated and initialized. Single-threaded access is guaranteed.
+        let level = self.level_pool.get_by_index(level_idx)?;
+
+        // Walk back from the tail past any orders with a later priority.
+        // New orders have the latest priority, so they join the tail (FIFO).
+        let mut prev_idx = level.tail_idx;
+        while let Some(idx) = prev_idx {
+            // SAFETY: idx comes from the level's list, so it refers to an
+            // allocated slot. Single-threaded access is guaranteed.
+            let order = self.order_pool.get_by_index(idx)?;
+            if order.priority < priority {
+                break;
             }
+            prev_idx = order.prev_idx;
+        }
 
-            // Safer approach: temporarily take ptr, update tail, then update new order
-            // Actually the simplest approach is to keep a mapping and update directly
-            level.tail_idx = Some(new_idx);
-        } else {
-            // Empty level - this order is both head and tail
-            level.head_idx = Some(new_idx);
-            level.tail_idx = Some(new_idx);
+        // Link the new order in after prev_idx (or at the head)
+        let next_idx = match prev_idx {
+            Some(prev) => self.order_pool.get_by_index(prev)?.next_idx.replace(new_idx),
+            None => level.head_idx.replace(new_idx),
+        };
+        match next_idx {
+            Some(next) => self.order_pool.get_by_index(next)?.prev_idx = Some(new_idx),
+            None => level.tail_idx = Some(new_idx),
         }
 
+        let order = self.order_pool.get_mut(&ptr);
+        order.prev_idx = prev_idx;
+        order.next_idx = next_idx;
+
         level.total_qty += qty;
         level.order_count += 1;
 
         // Store in orders map
         self.order_map.insert(order_id, OrderIndex { pool_idx: new_idx });
 
-        // Now update the old tail's next pointer if there was one
-        // We need to find the order with the tail index
-        let old_tail = self.order_pool.get_mut(&ptr).prev_idx;
-        if let Some(old_tail_idx) = old_tail {
-            // Find order_id for old tail and update its next_idx
-            for (&oid, idx_info) in &self.order_map {
-                if idx_info.pool_idx == old_tail_idx && oid != order_id {
-                    // Get the ptr for this order to update it
-                    // Since we can't easily get a PoolPtr from an index,
-                    // we'll store a separate structure or use unsafe
-                    // For now, let's do it through the existing ptr
-                    break;
-                }
-            }
-        }
-
         Some(ptr)
     }
 
@@ -243,7 +371,10 @@ impl OrderBook {
 
         // Step 5: Get the mutable price level for the order's price
         // The price level must exist since the order exists
-        let level = levels.get_mut(&order_price)?;
+        let level_idx = *levels.get(&order_price)?;
+        // SAFETY: level_idx comes from the price map, so it refers to an
+        // allocated, initialized level. Single-threaded access is guaranteed.
+        let level = self.level_pool.get_by_index(level_idx)?;
 
         // Step 6: Update the doubly-linked list
         // Update prev order's next_idx to point to our next
@@ -272,9 +403,14 @@ impl OrderBook {
         level.order_count -= 1;
         level.total_qty -= order_qty;
 
-        // Step 8: If price level is empty, remove it from the HashMap
+        // Step 8: If price level is empty, remove it and recycle its slot
         if level.order_count == 0 {
             levels.remove(&order_price);
+            // SAFETY: level_idx was just removed from the price map, so it is
+            // released exactly once. Single-threaded access is guaranteed.
+            unsafe {
+                self.level_pool.deallocate_by_index(level_idx);
+            }
         }
 
         // Step 9: Deallocate the pool slot
@@ -289,12 +425,37 @@ impl OrderBook {
         Some(order_clone)
     }
 
+    /// Reduces a resting order's quantity in place, keeping its priority
+    ///
+    /// Returns the order's previous quantity, or None if the order doesn't
+    /// exist or `qty` is zero or not below its current quantity
+    pub fn reduce_order(&mut self, order_id: OrderId, qty: Qty) -> Option<Qty> {
+        let idx_info = self.order_map.get(&order_id)?;
+        // SAFETY: Indices in order_map always refer to allocated, initialized
+        // slots. Single-threaded access is guaranteed.
+        let order = self.order_pool.get_by_index(idx_info.pool_idx)?;
+        if qty == 0 || qty >= order.qty {
+            return None;
+        }
+        let old_qty = std::mem::replace(&mut order.qty, qty);
+        let levels = match order.side {
+            Side::Buy => &self.bid_levels,
+            Side::Sell => &self.ask_levels,
+        };
+        let level_idx = *levels.get(&order.price)?;
+        // SAFETY: level_idx comes from the price map, so it refers to an
+        // allocated, initialized level. Single-threaded access is guaranteed.
+        let level = self.level_pool.get_by_index(level_idx)?;
+        level.total_qty -= old_qty - qty;
+        Some(old_qty)
+    }
+
     /// Returns a reference to an order by its order ID
     #[inline]
-    pub fn get_order(&self, _order_id: OrderId) -> Option<&Order> {
-        // We need a PoolPtr to call order_pool.get()
-        // Since we only store indices, we can't easily get the order
-        None
+    pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
+        let idx_info = self.order_map.get(&order_id)?;
+        // SAFETY: Indices in order_map always refer to allocated, initialized slots
+        self.order_pool.get_by_index(idx_info.pool_idx).map(|order| &*order)
     }
 
     /// Returns the best (highest) bid price, or None if no bids
@@ -307,15 +468,182 @@ impl OrderBook {
         self.ask_levels.keys().min().copied()
     }
 
+    /// Returns the midpoint of the best bid and ask, or None if either side is empty
+    pub fn mid_price(&self) -> Option<Price> {
+        Some((self.best_bid()? + self.best_ask()?) / 2)
+    }
+
+    /// Returns true if an order at `price` on `side` would set a new best price
+    ///
+    /// An order improves the book if it is strictly better than the current
+    /// best price on its side, or if that side of the book is empty.
+    #[inline]
+    pub fn improves_best_price(&self, side: Side, price: Price) -> bool {
+        match side {
+            Side::Buy => self.best_bid().is_none_or(|best| price > best),
+            Side::Sell => self.best_ask().is_none_or(|best| price < best),
+        }
+    }
+
+    /// Returns the resting quantity an order at `price` on `side` could
+    /// trade against
+    ///
+    /// Sums every opposite level at or through `price`.
+    pub fn crossing_qty(&self, side: Side, price: Price) -> Qty {
+        let levels = match side {
+            Side::Buy => &self.ask_levels,
+            Side::Sell => &self.bid_levels,
+        };
+        levels
+            .iter()
+            .filter(|&(&level_price, _)| match side {
+                Side::Buy => level_price <= price,
+                Side::Sell => level_price >= price,
+            })
+            .filter_map(|(_, &level_idx)| self.level_pool.get_by_index(level_idx))
+            .map(|level| level.total_qty)
+            .sum()
+    }
+
+    /// Returns the resting quantity an order of `client_id` at `price` on
+    /// `side` could trade against without trading with itself
+    ///
+    /// Walks the crossing levels in price-time priority, skipping the
+    /// client's own orders, or stopping at the first one when `stop_at_own`
+    /// is set.
+    pub fn crossing_qty_for_client(
+        &self,
+        side: Side,
+        price: Price,
+        client_id: ClientId,
+        stop_at_own: bool,
+    ) -> Qty {
+        let mut level_prices: Vec<Price> = match side {
+            Side::Buy => self.ask_levels.keys().copied().filter(|&level_price| level_price <= price).collect(),
+            Side::Sell => self.bid_levels.keys().copied().filter(|&level_price| level_price >= price).collect(),
+        };
+        level_prices.sort_unstable_by_key(|&level_price| side.as_sign() * level_price);
+        let levels = match side {
+            Side::Buy => &self.ask_levels,
+            Side::Sell => &self.bid_levels,
+        };
+
+        let mut qty = 0;
+        for level_price in level_prices {
+            let Some(level) = levels.get(&level_price).and_then(|&idx| self.level_pool.get_by_index(idx)) else {
+                continue;
+            };
+            let mut current = level.head_idx;
+            // SAFETY: Indices linked from a live level refer to allocated slots
+            while let Some(order) = current.and_then(|idx| self.order_pool.get_by_index(idx)) {
+                if order.client_id != client_id {
+                    qty += order.qty;
+                } else if stop_at_own {
+                    return qty;
+                }
+                current = order.next_idx;
+            }
+        }
+        qty
+    }
+
     /// Matches an incoming order against the book
-    pub fn match_order(
+    ///
+    /// Walks the opposite side in price-time priority while it crosses
+    /// `price`, filling up to `qty`. Fully filled resting orders are removed;
+    /// a partially filled one keeps its place with reduced quantity.
+    ///
+    /// Returns the executions in the order they happened. The unfilled
+    /// remainder is not added to the book.
+    pub fn match_order(&mut self, side: Side, price: Price, qty: Qty) -> Vec<Fill> {
+        self.match_order_until(side, price, qty, None).0
+    }
+
+    /// Matches like `match_order`, but stops before trading with an order
+    /// owned by `client_id`
+    ///
+    /// Returns the executions and the ID of the client's resting order that
+    /// stopped the match, if one did. That order is left untouched.
+    pub fn match_order_until_client(
         &mut self,
-        _side: Side,
-        _price: Price,
-        _qty: Qty,
-    ) -> Vec<(OrderId, Qty, Price)> {
-        // TODO: Implement order matching logic
-        Vec::new()
+        side: Side,
+        price: Price,
+        qty: Qty,
+        client_id: ClientId,
+    ) -> (Vec<Fill>, Option<OrderId>) {
+        self.match_order_until(side, price, qty, Some(client_id))
+    }
+
+    /// Walks the opposite side for `match_order`, stopping at the first
+    /// resting order owned by `stop_at`
+    fn match_order_until(
+        &mut self,
+        side: Side,
+        price: Price,
+        qty: Qty,
+        stop_at: Option<ClientId>,
+    ) -> (Vec<Fill>, Option<OrderId>) {
+        let mut fills = Vec::new();
+        let mut remaining = qty;
+
+        while remaining > 0 {
+            // Best opposite price, if it crosses our limit
+            let best = match side {
+                Side::Buy => self.best_ask().filter(|&ask| ask <= price),
+                Side::Sell => self.best_bid().filter(|&bid| bid >= price),
+            };
+            let Some(level_price) = best else {
+                break;
+            };
+
+            let levels = match side {
+                Side::Buy => &self.ask_levels,
+                Side::Sell => &self.bid_levels,
+            };
+            let Some(&level_idx) = levels.get(&level_price) else {
+                break;
+            };
+            // SAFETY: level_idx comes from the price map, so it refers to an
+            // allocated, initialized level. Single-threaded access is guaranteed.
+            let Some(level) = self.level_pool.get_by_index(level_idx) else {
+                break;
+            };
+            let Some(head_idx) = level.head_idx else {
+                break;
+            };
+
+            // SAFETY: head_idx comes from a live price level, so it refers to an
+            // allocated slot. Single-threaded access is guaranteed.
+            let Some(resting) = self.order_pool.get_by_index(head_idx) else {
+                break;
+            };
+            if stop_at == Some(resting.client_id) {
+                return (fills, Some(resting.order_id));
+            }
+
+            let fill_qty = remaining.min(resting.qty);
+            resting.qty -= fill_qty;
+            level.total_qty -= fill_qty;
+            remaining -= fill_qty;
+
+            fills.push(Fill {
+                order_id: resting.order_id,
+                client_id: resting.client_id,
+                side: resting.side,
+                price: resting.price,
+                qty: fill_qty,
+                leaves_qty: resting.qty,
+                priority: resting.priority,
+            });
+
+            // A consumed order is unlinked; its quantity is already off the level
+            if resting.qty == 0 {
+                let order_id = resting.order_id;
+                self.cancel_order(order_id);
+            }
+        }
+
+        (fills, None)
     }
 
     /// Returns the number of active orders in the book
@@ -335,4 +663,263 @@ impl OrderBook {
     pub fn ask_level_count(&self) -> usize {
         self.ask_levels.len()
     }
+
+    /// Returns the maximum number of price levels on each side
+    #[inline]
+    pub fn max_levels_per_side(&self) -> usize {
+        self.max_levels_per_side
+    }
+
+    /// Returns the number of unused slots in the price level pool
+    #[inline]
+    pub fn free_level_count(&self) -> usize {
+        self.level_pool.available()
+    }
+
+    /// Returns the number of orders rejected by the level cap
+    #[inline]
+    pub fn level_rejects(&self) -> u64 {
+        self.level_rejects
+    }
+
+    /// Returns the utilization of the order pool
+    #[inline]
+    pub fn order_pool_stats(&self) -> PoolStats {
+        self.order_pool.stats()
+    }
+
+    /// Returns the utilization of the price level pool
+    #[inline]
+    pub fn level_pool_stats(&self) -> PoolStats {
+        self.level_pool.stats()
+    }
+
+    /// Returns the resting levels of one side, best first, as (price, total
+    /// quantity), at most `levels` of them
+    pub fn get_depth(&self, side: Side, levels: usize) -> Vec<(Price, Qty)> {
+        self.sorted_levels(side)
+            .into_iter()
+            .take(levels)
+            .filter_map(|(price, level_idx)| {
+                self.level_pool.get_by_index(level_idx).map(|level| (price, level.total_qty))
+            })
+            .collect()
+    }
+
+    /// Renders the whole book, one line per level from the best ask up to
+    /// the best bid down, listing `order_id:qty@priority` in queue order
+    pub fn dump_book(&self) -> String {
+        let mut asks = self.sorted_levels(Side::Sell);
+        asks.reverse();
+        let mut out = String::new();
+        for (tag, levels) in [("S", asks), ("B", self.sorted_levels(Side::Buy))] {
+            for (price, level_idx) in levels {
+                let Some(level) = self.level_pool.get_by_index(level_idx) else {
+                    continue;
+                };
+                out.push_str(&format!("{} {} x{}:", tag, price, level.total_qty));
+                for order in self.level_orders(level) {
+                    out.push_str(&format!(" {}:{}@{}", order.order_id, order.qty, order.priority));
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Measures how scattered the book is across its pools
+    pub fn fragmentation(&self) -> BookFragmentation {
+        fn span(indices: impl Iterator<Item = usize>) -> usize {
+            let (low, high) = indices.fold((usize::MAX, 0), |(low, high), idx| (low.min(idx), high.max(idx)));
+            if low == usize::MAX { 0 } else { high - low + 1 }
+        }
+
+        let level_indices = || self.bid_levels.values().chain(self.ask_levels.values()).copied();
+        let scattered_links = self
+            .order_map
+            .values()
+            .filter_map(|info| self.order_pool.get_by_index(info.pool_idx).map(|order| (info.pool_idx, order)))
+            .filter(|(idx, order)| order.next_idx.is_some_and(|next| next != idx + 1))
+            .count();
+
+        BookFragmentation {
+            orders: self.order_map.len(),
+            order_span: span(self.order_map.values().map(|info| info.pool_idx)),
+            levels: self.bid_levels.len() + self.ask_levels.len(),
+            level_span: span(level_indices()),
+            scattered_links,
+        }
+    }
+
+    /// Rebuilds the book's pools and indexes for locality
+    ///
+    /// Levels are laid out best first, bids then asks, each followed by its
+    /// orders in queue order in consecutive slots. The price and order maps
+    /// are rebuilt at their configured capacity, dropping any growth and
+    /// deleted entries. Orders, priorities, depth and pool peaks are
+    /// unchanged; pointers previously returned by `add_order` are not.
+    /// Meant for quiet periods: the cost is linear in the book's size.
+    pub fn compact(&mut self) {
+        let mut sides = Vec::with_capacity(2);
+        for side in [Side::Buy, Side::Sell] {
+            let levels: Vec<(Price, Vec<Order>)> = self
+                .sorted_levels(side)
+                .into_iter()
+                .filter_map(|(price, level_idx)| {
+                    let level = self.level_pool.get_by_index(level_idx)?;
+                    Some((price, self.level_orders(level).cloned().collect()))
+                })
+                .collect();
+            sides.push((side, levels));
+        }
+
+        self.order_pool.reset();
+        self.level_pool.reset();
+        self.bid_levels = HashMap::with_capacity(self.max_levels_per_side);
+        self.ask_levels = HashMap::with_capacity(self.max_levels_per_side);
+        self.order_map = HashMap::with_capacity(self.order_pool.capacity());
+
+        for (side, levels) in sides {
+            for (price, orders) in levels {
+                // Each level and order was allocated before, so the pools
+                // cannot run out while laying them out again
+                let Some(level_ptr) = self.level_pool.allocate() else {
+                    return;
+                };
+                let level_idx = level_ptr.index();
+                let mut level = PriceLevel::new(price);
+                let mut prev_idx: Option<usize> = None;
+                for mut order in orders {
+                    let Some(ptr) = self.order_pool.allocate() else {
+                        return;
+                    };
+                    let idx = ptr.index();
+                    if let Some(prev) = prev_idx.and_then(|prev| self.order_pool.get_by_index(prev)) {
+                        prev.next_idx = Some(idx);
+                    }
+                    order.prev_idx = prev_idx;
+                    order.next_idx = None;
+                    level.head_idx.get_or_insert(idx);
+                    level.tail_idx = Some(idx);
+                    level.total_qty += order.qty;
+                    level.order_count += 1;
+                    self.order_map.insert(order.order_id, OrderIndex { pool_idx: idx });
+                    *self.order_pool.get_mut(&ptr) = order;
+                    prev_idx = Some(idx);
+                }
+                *self.level_pool.get_mut(&level_ptr) = level;
+                match side {
+                    Side::Buy => self.bid_levels.insert(price, level_idx),
+                    Side::Sell => self.ask_levels.insert(price, level_idx),
+                };
+            }
+        }
+    }
+
+    /// Returns a side's (price, level slot) pairs, best first
+    fn sorted_levels(&self, side: Side) -> Vec<(Price, usize)> {
+        let mut levels: Vec<(Price, usize)> = match side {
+            Side::Buy => self.bid_levels.iter(),
+            Side::Sell => self.ask_levels.iter(),
+        }
+        .map(|(&price, &level_idx)| (price, level_idx))
+        .collect();
+        match side {
+            Side::Buy => levels.sort_unstable_by_key(|&(price, _)| std::cmp::Reverse(price)),
+            Side::Sell => levels.sort_unstable_by_key(|&(price, _)| price),
+        }
+        levels
+    }
+
+    /// Iterates over a level's orders in queue order
+    fn level_orders<'a>(&'a self, level: &PriceLevel) -> impl Iterator<Item = &'a Order> + 'a {
+        let mut next_idx = level.head_idx;
+        std::iter::from_fn(move || {
+            let order = self.order_pool.get_by_index(next_idx?)?;
+            next_idx = order.next_idx;
+            Some(&*order)
+        })
+    }
+
+    /// Checks the book's internal invariants
+    ///
+    /// Walks every price level and verifies that the book is not crossed,
+    /// that each level's links, order count and total quantity agree with its
+    /// orders, that orders sit at their own side and price with a non-zero
+    /// quantity in priority order, and that the order index covers exactly
+    /// the linked orders. Meant for tests and stress runs, not the hot path.
+    ///
+    /// # Returns
+    /// A description of the first violation found, if any
+    pub fn validate_book(&self) -> Result<(), String> {
+        if let (Some(bid), Some(ask)) = (self.best_bid(), self.best_ask()) {
+            if bid >= ask {
+                return Err(format!("book crossed: best bid {} >= best ask {}", bid, ask));
+            }
+        }
+
+        let mut linked_orders = 0;
+        for (side, levels) in [(Side::Buy, &self.bid_levels), (Side::Sell, &self.ask_levels)] {
+            if levels.len() > self.max_levels_per_side {
+                return Err(format!("{:?} side has {} levels, cap is {}", side, levels.len(), self.max_levels_per_side));
+            }
+            for (&price, &level_idx) in levels {
+                let level = self
+                    .level_pool
+                    .get_by_index(level_idx)
+                    .ok_or_else(|| format!("level {} points at a free slot", price))?;
+                if level.price != price {
+                    return Err(format!("level {} is stored under price {}", level.price, price));
+                }
+
+                let mut count = 0;
+                let mut total_qty: Qty = 0;
+                let mut prev_idx = None;
+                let mut prev_priority = None;
+                let mut next_idx = level.head_idx;
+                while let Some(idx) = next_idx {
+                    let order = self
+                        .order_pool
+                        .get_by_index(idx)
+                        .ok_or_else(|| format!("level {} links a free slot", price))?;
+                    if order.prev_idx != prev_idx {
+                        return Err(format!("order {} has a broken back link", order.order_id));
+                    }
+                    if order.side != side || order.price != price {
+                        return Err(format!("order {} rests at the wrong side or level", order.order_id));
+                    }
+                    if order.qty == 0 {
+                        return Err(format!("order {} has zero quantity", order.order_id));
+                    }
+                    if prev_priority.is_some_and(|prev| order.priority <= prev) {
+                        return Err(format!("order {} is out of priority order", order.order_id));
+                    }
+                    if self.order_map.get(&order.order_id).map(|info| info.pool_idx) != Some(idx) {
+                        return Err(format!("order {} is missing from the index", order.order_id));
+                    }
+                    count += 1;
+                    total_qty += order.qty;
+                    prev_idx = Some(idx);
+                    prev_priority = Some(order.priority);
+                    next_idx = order.next_idx;
+                }
+
+                if level.tail_idx != prev_idx {
+                    return Err(format!("level {} has a stale tail", price));
+                }
+                if count == 0 || count != level.order_count {
+                    return Err(format!("level {} counts {} orders, links {}", price, level.order_count, count));
+                }
+                if total_qty != level.total_qty {
+                    return Err(format!("level {} totals {}, orders sum to {}", price, level.total_qty, total_qty));
+                }
+                linked_orders += count;
+            }
+        }
+
+        if linked_orders != self.order_map.len() {
+            return Err(format!("index holds {} orders, levels link {}", self.order_map.len(), linked_orders));
+        }
+        Ok(())
+    }
 }
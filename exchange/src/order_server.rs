@@ -7,13 +7,56 @@
 // 4. Assigns global sequence numbers via the FIFO sequencer
 // 5. Forwards requests to the matching engine
 // 6. Sends ClientResponse messages back to clients
+//
+// When credentials are configured, a connection must log in before trading:
+// its first message has to be a Login carrying a known client ID and its
+// token. Anything else, or a bad token, is answered with InvalidRequest and
+// the connection is closed. Requests after login must carry the logged-in
+// client ID.
+//
+// With timestamps enabled each request is stamped when its bytes arrive, and
+// `send_timed_response` combines that with the matching engine's start and
+// end times and the send time into a per-order latency breakdown.
+//
+// With response batching enabled, responses to a logged-in client are
+// buffered and written in a single TCP write per cycle by `flush_responses`,
+// in the order they were sent. `poll` flushes anything left over, so no
+// response waits longer than one cycle.
+//
+// With fill retention enabled, every fill sent to a client is stamped with a
+// per-client exec sequence and the most recent ones are kept, even while the
+// client is disconnected. A client that reconnects sends a FillQuery with
+// the last exec sequence it saw and the server resends the fills after it,
+// so fills that happened during the outage are not lost.
+//
+// A MassQuote header is followed on the wire by the QuoteEntry records it
+// counts. The connection waits until all of them have arrived, sequences the
+// header like any request and keeps its entries, which the caller collects
+// with `take_quote_entries` to hand to the matching engine.
+//
+// With request dedup enabled, the server remembers the client order IDs of
+// each client's most recent new-order requests (New, NewAon, NewStop,
+// NewStopLimit and NewMarket). One that reuses one of them, such as a retry
+// resent after a timeout, is dropped before it is sequenced, so the matching
+// engine only ever sees the first copy and answers it once.
+//
+// With blocking poll enabled, the listener and every accepted connection are
+// registered with epoll and `poll_blocking` sleeps until one of them is
+// readable, instead of the caller spinning on `poll`. Closed connections drop
+// out of epoll on their own when their socket is closed.
 
+use common::net::epoll::Epoll;
 use common::net::tcp::{TcpListener, TcpSocket};
-use common::ClientId;
-use crate::protocol::{ClientRequest, ClientResponse, CLIENT_REQUEST_SIZE};
-use std::collections::HashMap;
+use common::time::{monotonic_nanos, LatencyStats, Nanos};
+use common::{ClientId, OrderId};
+use crate::protocol::{
+    ClientRequest, ClientRequestType, ClientResponse, ClientResponseType, QuoteEntry,
+    CLIENT_REQUEST_SIZE, MAX_MASS_QUOTE_ENTRIES, QUOTE_ENTRY_SIZE,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 /// Default port for the order server.
 pub const DEFAULT_ORDER_SERVER_PORT: u16 = 12345;
@@ -24,6 +67,111 @@ pub const MAX_PENDING_CONNECTIONS: i32 = 128;
 /// Buffer size for receiving partial messages from clients.
 const RECV_BUFFER_SIZE: usize = CLIENT_REQUEST_SIZE * 16;
 
+/// Largest batch of responses written at once; a fuller batch is flushed early.
+/// Also bounds what a client that stops reading can have batched.
+const MAX_BATCH_BYTES: usize = 65536;
+
+/// Most recent fills per client, kept for clients that reconnect.
+#[derive(Debug, Default)]
+struct FillJournal {
+    /// Fills kept per client
+    capacity: usize,
+    /// Last exec sequence assigned per client
+    last_seq: HashMap<ClientId, u64>,
+    /// Retained fills per client, oldest first
+    fills: HashMap<ClientId, VecDeque<ClientResponse>>,
+}
+
+impl FillJournal {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Stamps a fill with the client's next exec sequence and retains it.
+    fn record(&mut self, client_id: ClientId, fill: &ClientResponse) -> ClientResponse {
+        let seq = self.last_seq.entry(client_id).or_insert(0);
+        *seq += 1;
+        let fill = fill.with_exec_seq(*seq);
+
+        let retained = self.fills.entry(client_id).or_default();
+        if retained.len() >= self.capacity {
+            retained.pop_front();
+        }
+        retained.push_back(fill);
+        fill
+    }
+
+    /// Sends a client's retained fills after `after_seq` on its connection.
+    ///
+    /// # Returns
+    /// The number of fills sent
+    fn replay(&self, client_id: ClientId, after_seq: u64, connection: &mut ClientConnection) -> u64 {
+        let Some(retained) = self.fills.get(&client_id) else {
+            return 0;
+        };
+        let mut sent = 0;
+        for fill in retained.iter().filter(|fill| fill.exec_seq > after_seq) {
+            if connection.send(fill).is_ok() {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// Number of fills retained for a client.
+    fn len(&self, client_id: ClientId) -> usize {
+        self.fills.get(&client_id).map_or(0, VecDeque::len)
+    }
+}
+
+/// Client order IDs of each client's most recent new-order requests.
+#[derive(Debug, Default)]
+struct RequestDedup {
+    /// New-order requests remembered per client
+    window: usize,
+    /// Remembered client order IDs per client
+    seen: HashMap<ClientId, HashSet<OrderId>>,
+    /// Remembered client order IDs per client, oldest first
+    order: HashMap<ClientId, VecDeque<OrderId>>,
+}
+
+impl RequestDedup {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            ..Default::default()
+        }
+    }
+
+    /// Remembers a request's client order ID if it enters a new order.
+    ///
+    /// # Returns
+    /// True if the request enters a new order whose client order ID is
+    /// already in the client's window
+    fn is_duplicate(&mut self, client_id: ClientId, request: &ClientRequest) -> bool {
+        if self.window == 0 || !request.request_type().is_some_and(|kind| kind.is_new_order()) {
+            return false;
+        }
+        let order_id = request.order_id;
+        let seen = self.seen.entry(client_id).or_default();
+        if !seen.insert(order_id) {
+            return true;
+        }
+
+        let order = self.order.entry(client_id).or_default();
+        if order.len() >= self.window {
+            if let Some(oldest) = order.pop_front() {
+                seen.remove(&oldest);
+            }
+        }
+        order.push_back(order_id);
+        false
+    }
+}
+
 /// Global sequence number generator for FIFO ordering.
 ///
 /// This ensures all incoming orders are assigned a unique, monotonically
@@ -71,6 +219,16 @@ pub struct ClientConnection {
     socket: TcpSocket,
     /// Buffer for accumulating partial messages.
     recv_buffer: Vec<u8>,
+    /// Stamp received data with its arrival time.
+    record_timestamps: bool,
+    /// Arrival time of the most recently received data.
+    last_received: Nanos,
+    /// Buffer responses until `flush` instead of writing each one.
+    batch_responses: bool,
+    /// Responses waiting to be written, when batching.
+    send_batch: Vec<u8>,
+    /// Entries of received mass quotes, in the order of their headers.
+    quote_entries: VecDeque<Vec<QuoteEntry>>,
 }
 
 impl ClientConnection {
@@ -80,7 +238,66 @@ impl ClientConnection {
             client_id,
             socket,
             recv_buffer: Vec::with_capacity(RECV_BUFFER_SIZE),
+            record_timestamps: false,
+            last_received: Nanos::default(),
+            batch_responses: false,
+            send_batch: Vec::new(),
+            quote_entries: VecDeque::new(),
+        }
+    }
+
+    /// Takes the entries of the oldest received mass quote not yet taken.
+    pub fn take_quote_entries(&mut self) -> Vec<QuoteEntry> {
+        self.quote_entries.pop_front().unwrap_or_default()
+    }
+
+    /// Enables or disables batching of responses until `flush`.
+    pub fn set_batching(&mut self, enabled: bool) {
+        self.batch_responses = enabled;
+        if enabled && self.send_batch.capacity() == 0 {
+            self.send_batch.reserve(MAX_BATCH_BYTES);
+        }
+    }
+
+    /// Returns the number of bytes waiting to be flushed.
+    #[inline]
+    pub fn pending_bytes(&self) -> usize {
+        self.send_batch.len()
+    }
+
+    /// Writes any batched responses in a single write.
+    ///
+    /// Only the bytes the socket accepted are dropped from the batch; after
+    /// a short write or a full socket buffer the rest stays batched for the
+    /// next flush, so responses are never split or lost.
+    ///
+    /// Returns the number of bytes sent, 0 if nothing was pending or the
+    /// socket buffer was full.
+    pub fn flush(&mut self) -> io::Result<usize> {
+        if self.send_batch.is_empty() {
+            return Ok(0);
         }
+        match self.socket.send(&self.send_batch) {
+            Ok(sent) => {
+                self.send_batch.drain(..sent);
+                Ok(sent)
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Enables or disables stamping of received data with its arrival time.
+    pub fn set_timestamps(&mut self, enabled: bool) {
+        self.record_timestamps = enabled;
+    }
+
+    /// Returns the arrival time of the most recently received data.
+    ///
+    /// Zero unless timestamps are enabled.
+    #[inline]
+    pub fn last_received(&self) -> Nanos {
+        self.last_received
     }
 
     /// Receives data from the client and parses complete messages.
@@ -101,6 +318,9 @@ impl ClientConnection {
                     ));
                 }
                 self.recv_buffer.extend_from_slice(data);
+                if self.record_timestamps {
+                    self.last_received = monotonic_nanos();
+                }
             }
             Ok(None) => {
                 // No data available, continue
@@ -117,8 +337,32 @@ impl ClientConnection {
         while self.recv_buffer.len() >= CLIENT_REQUEST_SIZE {
             if let Some(request) = ClientRequest::from_bytes(&self.recv_buffer[..CLIENT_REQUEST_SIZE]) {
                 // Copy the request (since it references buffer memory)
-                requests.push(*request);
-                self.recv_buffer.drain(..CLIENT_REQUEST_SIZE);
+                let request = *request;
+
+                // A mass quote is complete once all its entries are here; an
+                // oversized count is passed on without entries for the engine
+                // to reject
+                if request.request_type() == Some(ClientRequestType::MassQuote) {
+                    let count = request.qty as usize;
+                    let len = if count <= MAX_MASS_QUOTE_ENTRIES {
+                        CLIENT_REQUEST_SIZE + count * QUOTE_ENTRY_SIZE
+                    } else {
+                        CLIENT_REQUEST_SIZE
+                    };
+                    if self.recv_buffer.len() < len {
+                        break;
+                    }
+                    let entries = self.recv_buffer[CLIENT_REQUEST_SIZE..len]
+                        .chunks_exact(QUOTE_ENTRY_SIZE)
+                        .filter_map(QuoteEntry::from_bytes)
+                        .copied()
+                        .collect();
+                    self.quote_entries.push_back(entries);
+                    self.recv_buffer.drain(..len);
+                } else {
+                    self.recv_buffer.drain(..CLIENT_REQUEST_SIZE);
+                }
+                requests.push(request);
             } else {
                 // Invalid message format - skip one byte and try again
                 // This is a simple recovery strategy for malformed data
@@ -131,9 +375,26 @@ impl ClientConnection {
 
     /// Sends a response to the client.
     ///
-    /// Returns the number of bytes sent.
+    /// With batching enabled the response is appended to the batch and
+    /// written by the next `flush`. Returns the number of bytes sent or
+    /// batched.
+    ///
+    /// A full batch is flushed first; if the socket takes too little of it
+    /// to make room, the response is dropped with `WouldBlock`, as an
+    /// unbatched send to a full socket would be.
     pub fn send(&mut self, response: &ClientResponse) -> io::Result<usize> {
-        self.socket.send(response.as_bytes())
+        let bytes = response.as_bytes();
+        if !self.batch_responses {
+            return self.socket.send(bytes);
+        }
+        if self.send_batch.len() + bytes.len() > MAX_BATCH_BYTES {
+            self.flush()?;
+            if self.send_batch.len() + bytes.len() > MAX_BATCH_BYTES {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+        }
+        self.send_batch.extend_from_slice(bytes);
+        Ok(bytes.len())
     }
 
     /// Sets the socket to non-blocking mode.
@@ -151,6 +412,84 @@ pub struct SequencedRequest {
     pub client_id: ClientId,
     /// The original client request.
     pub request: ClientRequest,
+    /// When the request arrived at the server (zero unless timestamps are enabled).
+    pub received_at: Nanos,
+}
+
+/// Timestamps of one request's trip through the exchange.
+///
+/// All times come from the exchange's clock, so the segments can be compared
+/// directly. Time on the wire is not included; a client can estimate it as its
+/// own round trip minus `total`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrderTimestamps {
+    /// Request bytes arrived at the order server.
+    pub received: Nanos,
+    /// Matching engine started processing the request.
+    pub match_start: Nanos,
+    /// Matching engine finished processing the request.
+    pub match_end: Nanos,
+    /// Response was handed to the socket.
+    pub sent: Nanos,
+}
+
+impl OrderTimestamps {
+    /// Returns true if each timestamp is no earlier than the previous one.
+    pub fn is_monotonic(&self) -> bool {
+        self.received <= self.match_start && self.match_start <= self.match_end && self.match_end <= self.sent
+    }
+
+    /// Time from arrival to the start of matching: parsing, sequencing and queueing.
+    #[inline]
+    pub fn inbound(&self) -> u64 {
+        self.match_start - self.received
+    }
+
+    /// Time spent in the matching engine.
+    #[inline]
+    pub fn matching(&self) -> u64 {
+        self.match_end - self.match_start
+    }
+
+    /// Time from the end of matching until the response was sent.
+    #[inline]
+    pub fn outbound(&self) -> u64 {
+        self.sent - self.match_end
+    }
+
+    /// Time from arrival until the response was sent.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.sent - self.received
+    }
+}
+
+/// Latency statistics for each segment of a request's trip through the exchange.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyBreakdown {
+    /// Arrival to matching start.
+    pub inbound: LatencyStats,
+    /// Matching start to end.
+    pub matching: LatencyStats,
+    /// Matching end to response sent.
+    pub outbound: LatencyStats,
+    /// Arrival to response sent.
+    pub total: LatencyStats,
+}
+
+impl LatencyBreakdown {
+    /// Records the segments of one request.
+    pub fn record(&mut self, timestamps: &OrderTimestamps) {
+        self.inbound.record(timestamps.inbound());
+        self.matching.record(timestamps.matching());
+        self.outbound.record(timestamps.outbound());
+        self.total.record(timestamps.total());
+    }
+
+    /// Resets all segments.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
 /// Configuration for the order server.
@@ -160,6 +499,20 @@ pub struct OrderServerConfig {
     pub listen_addr: String,
     /// Port to listen on.
     pub port: u16,
+    /// Login token per client ID. Empty disables login.
+    pub credentials: HashMap<ClientId, u64>,
+    /// Stamp requests on arrival and record a latency breakdown per response.
+    pub record_timestamps: bool,
+    /// Buffer responses per client and write them once per cycle.
+    pub batch_responses: bool,
+    /// Fills retained per client for replay after a reconnect (0 disables).
+    pub fill_retention: usize,
+    /// New-order requests per client whose client order IDs are checked for
+    /// resends (0 disables).
+    pub dedup_window: usize,
+    /// Register sockets with epoll so `poll_blocking` can sleep until one is
+    /// readable. When disabled, `poll_blocking` busy-polls like `poll`.
+    pub blocking_poll: bool,
 }
 
 impl Default for OrderServerConfig {
@@ -167,6 +520,12 @@ impl Default for OrderServerConfig {
         Self {
             listen_addr: "0.0.0.0".to_string(),
             port: DEFAULT_ORDER_SERVER_PORT,
+            credentials: HashMap::new(),
+            record_timestamps: false,
+            batch_responses: false,
+            fill_retention: 0,
+            dedup_window: 0,
+            blocking_poll: false,
         }
     }
 }
@@ -177,8 +536,62 @@ impl OrderServerConfig {
         Self {
             listen_addr: listen_addr.to_string(),
             port,
+            ..Default::default()
         }
     }
+
+    /// Builder method to allow a client to log in with the given token.
+    ///
+    /// Once any credential is set, every connection must log in.
+    pub fn with_credential(mut self, client_id: ClientId, token: u64) -> Self {
+        self.credentials.insert(client_id, token);
+        self
+    }
+
+    /// Builder method to enable or disable per-order timestamps.
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.record_timestamps = enabled;
+        self
+    }
+
+    /// Builder method to batch responses into one write per client per cycle.
+    pub fn with_response_batching(mut self, enabled: bool) -> Self {
+        self.batch_responses = enabled;
+        self
+    }
+
+    /// Builder method to retain each client's most recent fills for replay.
+    ///
+    /// Fills are stamped with a per-client exec sequence. Clients are only
+    /// recognised across connections when they log in.
+    pub fn with_fill_retention(mut self, fills_per_client: usize) -> Self {
+        self.fill_retention = fills_per_client;
+        self
+    }
+
+    /// Builder method to drop a new order that reuses one of the client order IDs
+    /// of the client's last `window` new-order requests.
+    ///
+    /// Clients are only recognised across connections when they log in.
+    pub fn with_request_dedup(mut self, window: usize) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// Builder method to block in `poll_blocking` until a socket is readable
+    /// rather than busy-polling.
+    ///
+    /// Requires epoll, so creating the server fails off Linux when enabled.
+    pub fn with_blocking_poll(mut self, enabled: bool) -> Self {
+        self.blocking_poll = enabled;
+        self
+    }
+
+    /// Returns true if connections must log in before trading.
+    #[inline]
+    pub fn requires_login(&self) -> bool {
+        !self.credentials.is_empty()
+    }
 }
 
 /// The TCP order server that accepts client connections and processes orders.
@@ -199,6 +612,32 @@ pub struct OrderServer {
     sequencer: FifoSequencer,
     /// Next client ID to assign.
     next_client_id: ClientId,
+    /// Connections that have not logged in yet, when login is required,
+    /// keyed by a provisional connection ID.
+    pending_logins: HashMap<ClientId, ClientConnection>,
+    /// Connections closed for a failed or missing login.
+    login_failures: u64,
+    /// Per-segment latency of timed responses.
+    latency: LatencyBreakdown,
+    /// Timestamps of the most recent timed response.
+    last_timestamps: Option<OrderTimestamps>,
+    /// Batched writes performed by `flush_responses`.
+    batch_flushes: u64,
+    /// Clients disconnected because a batched write failed.
+    failed_flushes: u64,
+    /// Recent fills per client, when fill retention is enabled.
+    fill_journal: FillJournal,
+    /// Fills resent in answer to fill queries.
+    fills_replayed: u64,
+    /// Recent client order IDs per client, when request dedup is enabled.
+    request_dedup: RequestDedup,
+    /// Resent new-order requests dropped by request dedup.
+    duplicate_requests: u64,
+    /// Entries of sequenced mass quotes, by sequence number.
+    quote_entries: HashMap<u64, Vec<QuoteEntry>>,
+    /// Readiness wait over the listener and connections, when blocking poll
+    /// is enabled.
+    epoll: Option<Epoll>,
     /// Server configuration.
     config: OrderServerConfig,
 }
@@ -211,11 +650,31 @@ impl OrderServer {
         let listener = TcpListener::bind(&config.listen_addr, config.port)?;
         listener.set_nonblocking(true)?;
 
+        let epoll = if config.blocking_poll {
+            let epoll = Epoll::new()?;
+            epoll.add(listener.socket())?;
+            Some(epoll)
+        } else {
+            None
+        };
+
         Ok(Self {
             listener,
             clients: HashMap::new(),
             sequencer: FifoSequencer::new(),
             next_client_id: 1,
+            pending_logins: HashMap::new(),
+            login_failures: 0,
+            latency: LatencyBreakdown::default(),
+            last_timestamps: None,
+            batch_flushes: 0,
+            failed_flushes: 0,
+            fill_journal: FillJournal::new(config.fill_retention),
+            fills_replayed: 0,
+            request_dedup: RequestDedup::new(config.dedup_window),
+            duplicate_requests: 0,
+            quote_entries: HashMap::new(),
+            epoll,
             config,
         })
     }
@@ -234,11 +693,17 @@ impl OrderServer {
     ///
     /// This is a non-blocking operation that:
     /// 1. Accepts any pending new connections
-    /// 2. Receives data from all connected clients
-    /// 3. Returns sequenced requests for processing
+    /// 2. Logs in connections that are waiting to, if login is required
+    /// 3. Receives data from all connected clients
+    /// 4. Returns sequenced requests for processing
     ///
-    /// The returned requests are ordered by their sequence numbers.
+    /// The returned requests are ordered by their sequence numbers. Batched
+    /// responses still pending from the previous cycle are flushed first.
     pub fn poll(&mut self) -> Vec<SequencedRequest> {
+        if self.config.batch_responses {
+            self.flush_responses();
+        }
+
         // Accept new connections
         self.accept_connections();
 
@@ -246,16 +711,17 @@ impl OrderServer {
         let mut requests = Vec::new();
         let mut disconnected_clients = Vec::new();
 
-        for (&client_id, connection) in self.clients.iter_mut() {
+        if self.config.requires_login() {
+            self.process_logins(&mut requests);
+        }
+
+        // Taken out of the server while dispatching, which needs the rest of it
+        let mut clients = std::mem::take(&mut self.clients);
+        for (&client_id, connection) in clients.iter_mut() {
             match connection.receive() {
                 Ok(client_requests) => {
                     for request in client_requests {
-                        let seq_num = self.sequencer.next();
-                        requests.push(SequencedRequest {
-                            sequence_number: seq_num,
-                            client_id,
-                            request,
-                        });
+                        self.dispatch_request(client_id, connection, request, &mut requests);
                     }
                 }
                 Err(_) => {
@@ -264,6 +730,7 @@ impl OrderServer {
                 }
             }
         }
+        self.clients = clients;
 
         // Remove disconnected clients
         for client_id in disconnected_clients {
@@ -276,10 +743,185 @@ impl OrderServer {
         requests
     }
 
+    /// Waits up to `timeout` for a connection or incoming data, then polls.
+    ///
+    /// With blocking poll enabled the thread sleeps in epoll until the
+    /// listener or a client socket is readable, so an idle server uses no CPU
+    /// and wakes as soon as data arrives. Batched responses are flushed before
+    /// sleeping. Without blocking poll this is the same as `poll`.
+    ///
+    /// # Arguments
+    /// * `timeout` - The longest time to wait for a socket to become readable
+    ///
+    /// # Returns
+    /// The sequenced requests received, empty if the wait timed out
+    pub fn poll_blocking(&mut self, timeout: Duration) -> Vec<SequencedRequest> {
+        if self.epoll.is_some() {
+            if self.config.batch_responses {
+                self.flush_responses();
+            }
+            if let Some(epoll) = &self.epoll {
+                // A failed wait falls through to a regular poll
+                let _ = epoll.wait(timeout);
+            }
+        }
+        self.poll()
+    }
+
+    /// Handles the first messages of connections that have not logged in.
+    ///
+    /// A valid Login moves the connection to the connected clients under the
+    /// logged-in ID and is acknowledged with Accepted; requests that arrived
+    /// behind it are appended to `requests`. Any other first message, an
+    /// unknown client or token, or a client ID that is already connected gets
+    /// InvalidRequest and the connection is closed.
+    fn process_logins(&mut self, requests: &mut Vec<SequencedRequest>) {
+        let mut closed = Vec::new();
+        let mut logged_in: Vec<(ClientId, ClientId)> = Vec::new();
+
+        let mut pending_logins = std::mem::take(&mut self.pending_logins);
+        for (&connection_id, connection) in pending_logins.iter_mut() {
+            let received = match connection.receive() {
+                Ok(received) if received.is_empty() => continue,
+                Ok(received) => received,
+                Err(_) => {
+                    closed.push(connection_id);
+                    continue;
+                }
+            };
+
+            let login = received[0];
+            let client_id = login.client_id;
+            let token = login.tag;
+            let valid = login.request_type() == Some(ClientRequestType::Login)
+                && self.config.credentials.get(&client_id) == Some(&token)
+                && !self.clients.contains_key(&client_id)
+                && !logged_in.iter().any(|&(_, id)| id == client_id);
+
+            if !valid {
+                self.login_failures += 1;
+                let _ = connection.send(&Self::reject_response(&login));
+                closed.push(connection_id);
+                continue;
+            }
+
+            connection.client_id = client_id;
+            let ack = ClientResponse::new(ClientResponseType::Accepted, client_id, 0, 0, 0, 0, 0, 0, 0);
+            let _ = connection.send(&ack);
+            logged_in.push((connection_id, client_id));
+
+            for &request in &received[1..] {
+                self.dispatch_request(client_id, connection, request, requests);
+            }
+        }
+        self.pending_logins = pending_logins;
+
+        // Dropping a connection closes it
+        for connection_id in closed {
+            self.pending_logins.remove(&connection_id);
+        }
+        for (connection_id, client_id) in logged_in {
+            if let Some(connection) = self.pending_logins.remove(&connection_id) {
+                self.clients
+                    .entry(client_id)
+                    .or_insert(connection)
+                    .set_batching(self.config.batch_responses);
+            }
+        }
+    }
+
+    /// Handles a request received on a connection acting as `client_id`.
+    ///
+    /// With login required, a request naming another client is rejected.
+    /// Fill queries are answered here and resent new orders dropped; any
+    /// other request is sequenced and appended to `requests`.
+    fn dispatch_request(
+        &mut self,
+        client_id: ClientId,
+        connection: &mut ClientConnection,
+        request: ClientRequest,
+        requests: &mut Vec<SequencedRequest>,
+    ) {
+        // A logged-in connection may only act as itself
+        let request_client_id = request.client_id;
+        if self.config.requires_login() && request_client_id != client_id {
+            if request.request_type() == Some(ClientRequestType::MassQuote) {
+                connection.take_quote_entries();
+            }
+            let _ = connection.send(&Self::reject_response(&request));
+            return;
+        }
+
+        // Fill queries are answered here and never reach the engine
+        if request.request_type() == Some(ClientRequestType::FillQuery) {
+            self.fills_replayed += self.fill_journal.replay(client_id, request.order_id, connection);
+            return;
+        }
+
+        // A resent new order is dropped; the first copy gets the answer
+        if self.request_dedup.is_duplicate(client_id, &request) {
+            self.duplicate_requests += 1;
+            return;
+        }
+
+        let sequence_number = self.sequencer.next();
+        if request.request_type() == Some(ClientRequestType::MassQuote) {
+            self.quote_entries.insert(sequence_number, connection.take_quote_entries());
+        }
+        requests.push(SequencedRequest {
+            sequence_number,
+            client_id,
+            request,
+            received_at: connection.last_received(),
+        });
+    }
+
+    /// Takes the entries of a sequenced mass quote.
+    ///
+    /// # Arguments
+    /// * `sequence_number` - Sequence number of the MassQuote request
+    ///
+    /// # Returns
+    /// The quote entries, empty if the request was not a mass quote or they
+    /// were already taken
+    pub fn take_quote_entries(&mut self, sequence_number: u64) -> Vec<QuoteEntry> {
+        self.quote_entries.remove(&sequence_number).unwrap_or_default()
+    }
+
+    /// Builds the InvalidRequest answer to a request refused by the server.
+    fn reject_response(request: &ClientRequest) -> ClientResponse {
+        ClientResponse::new(
+            ClientResponseType::InvalidRequest,
+            request.client_id,
+            request.ticker_id,
+            request.order_id,
+            0,
+            request.side,
+            request.price,
+            0,
+            request.qty,
+        )
+        .with_tag(request.tag)
+        .with_origin_ts(request.origin_ts)
+    }
+
     /// Sends a response to a specific client.
     ///
+    /// With fill retention enabled, a fill is stamped with the client's next
+    /// exec sequence and retained even if the client is not connected.
+    ///
     /// Returns Ok(bytes_sent) on success, or Err if the client is not connected.
     pub fn send_response(&mut self, client_id: ClientId, response: &ClientResponse) -> io::Result<usize> {
+        let retained;
+        let response = if self.config.fill_retention > 0
+            && response.response_type() == Some(ClientResponseType::Filled)
+        {
+            retained = self.fill_journal.record(client_id, response);
+            &retained
+        } else {
+            response
+        };
+
         match self.clients.get_mut(&client_id) {
             Some(connection) => connection.send(response),
             None => Err(io::Error::new(
@@ -289,6 +931,108 @@ impl OrderServer {
         }
     }
 
+    /// Sends the response to a request and records its latency breakdown.
+    ///
+    /// The breakdown is recorded only when timestamps are enabled and the
+    /// matching times are known. With batching, the send time is when the
+    /// response was queued, not when the batch was written.
+    ///
+    /// # Arguments
+    /// * `request` - The request being answered
+    /// * `response` - The response to send to the request's client
+    /// * `matched` - Matching start and end times, from the matching engine
+    ///
+    /// # Returns
+    /// The number of bytes sent, or Err if the client is not connected
+    pub fn send_timed_response(
+        &mut self,
+        request: &SequencedRequest,
+        response: &ClientResponse,
+        matched: Option<(Nanos, Nanos)>,
+    ) -> io::Result<usize> {
+        let sent = self.send_response(request.client_id, response)?;
+
+        if let (true, Some((match_start, match_end))) = (self.config.record_timestamps, matched) {
+            let timestamps = OrderTimestamps {
+                received: request.received_at,
+                match_start,
+                match_end,
+                sent: monotonic_nanos(),
+            };
+            self.latency.record(&timestamps);
+            self.last_timestamps = Some(timestamps);
+        }
+
+        Ok(sent)
+    }
+
+    /// Writes every client's batched responses, one write per client.
+    ///
+    /// Call once per cycle after sending the cycle's responses. Whatever a
+    /// client's socket doesn't accept stays batched for the next cycle. A
+    /// client whose write fails outright is disconnected.
+    ///
+    /// # Returns
+    /// The number of clients written to
+    pub fn flush_responses(&mut self) -> usize {
+        let mut flushed = 0;
+        let mut failed_clients = Vec::new();
+        for (&client_id, connection) in self.clients.iter_mut() {
+            if connection.pending_bytes() == 0 {
+                continue;
+            }
+            match connection.flush() {
+                Ok(0) => {}
+                Ok(_) => flushed += 1,
+                Err(_) => failed_clients.push(client_id),
+            }
+        }
+        for client_id in failed_clients {
+            self.clients.remove(&client_id);
+            self.failed_flushes += 1;
+        }
+        self.batch_flushes += flushed as u64;
+        flushed
+    }
+
+    /// Returns the number of clients disconnected because writing their
+    /// batched responses failed.
+    #[inline]
+    pub fn failed_flushes(&self) -> u64 {
+        self.failed_flushes
+    }
+
+    /// Returns the number of batched writes performed.
+    #[inline]
+    pub fn batch_flushes(&self) -> u64 {
+        self.batch_flushes
+    }
+
+    /// Returns the number of fills resent in answer to fill queries.
+    #[inline]
+    pub fn fills_replayed(&self) -> u64 {
+        self.fills_replayed
+    }
+
+    /// Returns the number of resent new-order requests dropped by request dedup.
+    #[inline]
+    pub fn duplicate_requests(&self) -> u64 {
+        self.duplicate_requests
+    }
+
+    /// Returns the number of fills retained for a client.
+    pub fn retained_fills(&self, client_id: ClientId) -> usize {
+        self.fill_journal.len(client_id)
+    }
+
+    /// Returns the port the server is listening on.
+    pub fn local_port(&self) -> io::Result<u16> {
+        let addr = self.listener.socket().local_addr()?;
+        addr.as_socket()
+            .map(|addr| addr.port())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "listener has no socket address"))
+    }
+
     /// Broadcasts a response to all connected clients.
     ///
     /// Returns the number of clients that received the response.
@@ -315,8 +1059,27 @@ impl OrderServer {
                         continue;
                     }
 
-                    let connection = ClientConnection::new(client_id, socket);
-                    self.clients.insert(client_id, connection);
+                    // Watch the connection for data when blocking poll is on
+                    if let Some(epoll) = &self.epoll {
+                        if epoll.add(socket.socket()).is_err() {
+                            continue;
+                        }
+                    }
+
+                    // With login required the assigned ID is only provisional;
+                    // the client ID comes from the login. Responses are only
+                    // batched once logged in, so login rejections go out
+                    // before the connection is closed.
+                    let (connections, batching) = if self.config.requires_login() {
+                        (&mut self.pending_logins, false)
+                    } else {
+                        (&mut self.clients, self.config.batch_responses)
+                    };
+                    let connection = connections
+                        .entry(client_id)
+                        .or_insert(ClientConnection::new(client_id, socket));
+                    connection.set_timestamps(self.config.record_timestamps);
+                    connection.set_batching(batching);
                 }
                 Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
                     // No more pending connections
@@ -342,6 +1105,36 @@ impl OrderServer {
         self.clients.is_empty()
     }
 
+    /// Returns the number of connections waiting to log in.
+    #[inline]
+    pub fn pending_login_count(&self) -> usize {
+        self.pending_logins.len()
+    }
+
+    /// Returns the number of connections closed for a failed or missing login.
+    #[inline]
+    pub fn login_failures(&self) -> u64 {
+        self.login_failures
+    }
+
+    /// Returns the per-segment latency of timed responses.
+    #[inline]
+    pub fn latency_breakdown(&self) -> &LatencyBreakdown {
+        &self.latency
+    }
+
+    /// Returns the timestamps of the most recent timed response.
+    #[inline]
+    pub fn last_timestamps(&self) -> Option<OrderTimestamps> {
+        self.last_timestamps
+    }
+
+    /// Resets the latency breakdown.
+    pub fn reset_latency(&mut self) {
+        self.latency.reset();
+        self.last_timestamps = None;
+    }
+
     /// Returns the current sequence number (next to be assigned).
     #[inline]
     pub fn current_sequence(&self) -> u64 {
@@ -359,14 +1152,22 @@ impl OrderServer {
         self.clients.get(&client_id)
     }
 
-    /// Disconnects a specific client.
+    /// Disconnects a specific client, after writing its batched responses.
     pub fn disconnect_client(&mut self, client_id: ClientId) -> bool {
-        self.clients.remove(&client_id).is_some()
+        match self.clients.remove(&client_id) {
+            Some(mut connection) => {
+                let _ = connection.flush();
+                true
+            }
+            None => false,
+        }
     }
 
-    /// Disconnects all clients.
+    /// Disconnects all clients, after writing their batched responses.
     pub fn disconnect_all(&mut self) {
+        self.flush_responses();
         self.clients.clear();
+        self.pending_logins.clear();
     }
 
     /// Returns an iterator over connected client IDs.
@@ -529,6 +1330,7 @@ mod tests {
             sequence_number: 42,
             client_id: 1,
             request,
+            received_at: Nanos::default(),
         };
 
         assert_eq!(seq_request.sequence_number, 42);
@@ -694,6 +1496,50 @@ mod tests {
         assert!(server.client_count() >= 1);
     }
 
+    #[test]
+    fn test_mass_quote_framed_with_entries() {
+        use crate::protocol::encode_mass_quote;
+        use common::net::tcp::TcpSocket;
+
+        let mut server = OrderServer::new(OrderServerConfig::new("127.0.0.1", 0)).unwrap();
+        let port = server.listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        let client_handle = thread::spawn(move || {
+            let mut client = TcpSocket::connect("127.0.0.1", port).unwrap();
+            let entries = [
+                QuoteEntry::new(1, 9990, 10, 10010, 10).with_order_ids(1, 2),
+                QuoteEntry::new(2, 4990, 5, 5010, 5).with_order_ids(3, 4),
+            ];
+            let bytes = encode_mass_quote(&ClientRequest::mass_quote(1, 77, 0), &entries);
+
+            // Split inside the first entry: nothing is sequenced until it all arrives
+            let split = CLIENT_REQUEST_SIZE + QUOTE_ENTRY_SIZE / 2;
+            client.send(&bytes[..split]).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            client.send(&bytes[split..]).unwrap();
+            let new = ClientRequest::new(ClientRequestType::New, 1, 1, 5, 1, 9980, 10);
+            client.send(new.as_bytes()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+        });
+
+        let mut received = Vec::new();
+        let start = std::time::Instant::now();
+        while received.len() < 2 && start.elapsed() < Duration::from_secs(1) {
+            received.extend(server.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        client_handle.join().unwrap();
+
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].request.request_type(), Some(ClientRequestType::MassQuote));
+        assert_eq!(received[1].request.request_type(), Some(ClientRequestType::New));
+        let entries = server.take_quote_entries(received[0].sequence_number);
+        assert_eq!(entries.len(), 2);
+        let (ticker_id, bid_order_id) = (entries[1].ticker_id, entries[1].bid_order_id);
+        assert_eq!((ticker_id, bid_order_id), (2, 3));
+        assert!(server.take_quote_entries(received[0].sequence_number).is_empty());
+    }
+
     #[test]
     fn test_multiple_clients() {
         use common::net::tcp::TcpSocket;
@@ -721,7 +1567,7 @@ mod tests {
                     ClientRequestType::New,
                     i as u32 + 1,   // client_id
                     1,              // ticker_id
-                    (i + 1) as u64, // order_id
+                    i + 1,          // order_id
                     1,              // side
                     10000 + (i as i64 * 100),
                     (i + 1) as u32 * 10,
@@ -813,6 +1659,258 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resent_new_dropped_by_request_dedup() {
+        use common::net::tcp::TcpSocket;
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_request_dedup(2);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.local_port().unwrap();
+
+        let mut client = TcpSocket::connect("127.0.0.1", port).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        server.poll();
+        let client_id = server.client_ids().next().unwrap();
+
+        let new_order = |order_id| {
+            ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, 1, 10000, 10)
+        };
+        let cancel = ClientRequest::new(ClientRequestType::Cancel, client_id, 1, 1, 1, 10000, 10);
+        let mut bytes = Vec::new();
+        // The retry of order 1 is dropped; a cancel reusing its ID is not a New.
+        // Orders 2 and 3 push order 1 out of the window, so it is accepted again.
+        for request in [new_order(1), new_order(1), cancel, new_order(2), new_order(3), new_order(1)] {
+            bytes.extend_from_slice(request.as_bytes());
+        }
+        client.send(&bytes).unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(server.poll());
+            if received.len() + server.duplicate_requests() as usize >= 6 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let processed: Vec<_> = received
+            .iter()
+            .map(|r| (r.request.request_type().unwrap(), r.request.order_id))
+            .collect();
+        assert_eq!(
+            processed,
+            vec![
+                (ClientRequestType::New, 1),
+                (ClientRequestType::Cancel, 1),
+                (ClientRequestType::New, 2),
+                (ClientRequestType::New, 3),
+                (ClientRequestType::New, 1),
+            ]
+        );
+        assert_eq!(server.duplicate_requests(), 1);
+    }
+
+    #[test]
+    fn test_request_dedup_covers_every_new_order_type() {
+        use common::net::tcp::TcpSocket;
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_request_dedup(8);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.local_port().unwrap();
+
+        let mut client = TcpSocket::connect("127.0.0.1", port).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        server.poll();
+        let client_id = server.client_ids().next().unwrap();
+
+        let kinds = [
+            ClientRequestType::New,
+            ClientRequestType::NewAon,
+            ClientRequestType::NewStop,
+            ClientRequestType::NewStopLimit,
+            ClientRequestType::NewMarket,
+        ];
+        let mut bytes = Vec::new();
+        // Each order is resent once; an AON reusing a limit order's ID is a
+        // resend too
+        for (order_id, kind) in (1..).zip(kinds) {
+            let request = ClientRequest::new(kind, client_id, 1, order_id, 1, 10000, 10);
+            bytes.extend_from_slice(request.as_bytes());
+            bytes.extend_from_slice(request.as_bytes());
+        }
+        let reused = ClientRequest::new(ClientRequestType::NewAon, client_id, 1, 1, 1, 10000, 10);
+        bytes.extend_from_slice(reused.as_bytes());
+        client.send(&bytes).unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(server.poll());
+            if received.len() + server.duplicate_requests() as usize >= 11 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let processed: Vec<_> = received
+            .iter()
+            .map(|r| (r.request.request_type().unwrap(), r.request.order_id))
+            .collect();
+        assert_eq!(processed, (1..).zip(kinds).map(|(order_id, kind)| (kind, order_id)).collect::<Vec<_>>());
+        assert_eq!(server.duplicate_requests(), 6);
+    }
+
+    #[test]
+    fn test_poll_blocking_wakes_on_data() {
+        use common::net::tcp::TcpSocket;
+        use std::time::Instant;
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_blocking_poll(true);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.local_port().unwrap();
+
+        // Nothing to accept or read: the wait runs to its timeout
+        let start = Instant::now();
+        assert!(server.poll_blocking(Duration::from_millis(50)).is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        // A client connects, then sends while the server is waiting
+        let client_handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let mut client = TcpSocket::connect("127.0.0.1", port).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            let request = ClientRequest::new(ClientRequestType::New, 1, 1, 1, 1, 10000, 10);
+            client.send(request.as_bytes()).unwrap();
+            thread::sleep(Duration::from_millis(200));
+        });
+
+        // Each wakeup returns long before the timeout: first for the
+        // connection, then for its data
+        let start = Instant::now();
+        let mut received = Vec::new();
+        while received.is_empty() && start.elapsed() < Duration::from_secs(5) {
+            received = server.poll_blocking(Duration::from_secs(5));
+        }
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(server.client_count(), 1);
+        assert_eq!(received.len(), 1);
+        assert_eq!({ received[0].request.order_id }, 1);
+        client_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_batched_responses_sent_in_one_write() {
+        use common::net::tcp::TcpSocket;
+        use crate::protocol::ClientResponseType;
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_response_batching(true);
+        let mut server = OrderServer::new(config).unwrap();
+
+        let local_addr = server.listener.socket().local_addr().unwrap();
+        let port = local_addr.as_socket().unwrap().port();
+
+        let mut client = TcpSocket::connect("127.0.0.1", port).unwrap();
+        client.set_nonblocking(true).unwrap();
+
+        thread::sleep(Duration::from_millis(50));
+        server.poll();
+        let client_id = server.client_ids().next().unwrap();
+
+        // Three responses in one cycle are held until the flush
+        for client_order_id in 1..=3 {
+            let response = ClientResponse::new(
+                ClientResponseType::Accepted,
+                client_id,
+                1,
+                client_order_id,
+                client_order_id,
+                1,
+                10000,
+                0,
+                100,
+            );
+            server.send_response(client_id, &response).unwrap();
+        }
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(client.try_recv().unwrap().is_none());
+
+        assert_eq!(server.flush_responses(), 1);
+        assert_eq!(server.batch_flushes(), 1);
+        // Nothing left to write
+        assert_eq!(server.flush_responses(), 0);
+
+        thread::sleep(Duration::from_millis(50));
+        let data = client.try_recv().unwrap().unwrap().to_vec();
+        assert_eq!(data.len(), 3 * CLIENT_RESPONSE_SIZE);
+
+        for (i, chunk) in data.chunks_exact(CLIENT_RESPONSE_SIZE).enumerate() {
+            let received = ClientResponse::from_bytes(chunk).unwrap();
+            let client_order_id = received.client_order_id;
+            assert_eq!(client_order_id, i as u64 + 1);
+        }
+    }
+
+    #[test]
+    fn test_batched_responses_bounded_while_client_stalls() {
+        use common::net::tcp::TcpSocket;
+        use crate::protocol::ClientResponseType;
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_response_batching(true);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.local_port().unwrap();
+
+        let mut client = TcpSocket::connect("127.0.0.1", port).unwrap();
+        client.set_nonblocking(true).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        server.poll();
+        let client_id = server.client_ids().next().unwrap();
+
+        // Send while the client isn't reading until the socket buffers and
+        // the batch are full; the batch stops growing at its cap
+        let mut responses = 0;
+        loop {
+            let client_order_id = responses + 1;
+            let response = ClientResponse::new(
+                ClientResponseType::Accepted,
+                client_id,
+                1,
+                client_order_id,
+                client_order_id,
+                1,
+                10000,
+                0,
+                100,
+            );
+            match server.send_response(client_id, &response) {
+                Ok(_) => responses += 1,
+                Err(e) => {
+                    assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+        }
+        let pending = server.get_client(client_id).unwrap().pending_bytes();
+        assert!(pending > 0 && pending <= MAX_BATCH_BYTES);
+
+        // Drain the client while flushing; every accepted response arrives
+        // whole and in order
+        let mut data = Vec::new();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while data.len() < responses as usize * CLIENT_RESPONSE_SIZE && std::time::Instant::now() < deadline {
+            server.flush_responses();
+            while let Some(chunk) = client.try_recv().unwrap() {
+                data.extend_from_slice(chunk);
+            }
+        }
+        assert_eq!(server.get_client(client_id).unwrap().pending_bytes(), 0);
+        assert_eq!(server.failed_flushes(), 0);
+        assert_eq!(data.len(), responses as usize * CLIENT_RESPONSE_SIZE);
+        for (i, chunk) in data.chunks_exact(CLIENT_RESPONSE_SIZE).enumerate() {
+            let client_order_id = ClientResponse::from_bytes(chunk).unwrap().client_order_id;
+            assert_eq!(client_order_id, i as u64 + 1);
+        }
+    }
+
     #[test]
     fn test_broadcast() {
         use common::net::tcp::TcpSocket;
@@ -850,4 +1948,155 @@ mod tests {
         let sent_count = server.broadcast(&response);
         assert_eq!(sent_count, 2);
     }
+
+    #[test]
+    fn test_login_required_before_trading() {
+        use common::net::tcp::TcpSocket;
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_credential(7, 0xC0FFEE);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        let client_handle = thread::spawn(move || {
+            // Reads up to `count` responses, fewer if the server closes the connection
+            let read_responses = |client: &mut TcpSocket, count: usize| {
+                client.socket().set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+                let mut bytes = Vec::new();
+                while bytes.len() < count * CLIENT_RESPONSE_SIZE {
+                    match client.recv() {
+                        Ok(data) if !data.is_empty() => bytes.extend_from_slice(data),
+                        _ => break,
+                    }
+                }
+                bytes
+                    .chunks_exact(CLIENT_RESPONSE_SIZE)
+                    .map(|chunk| *ClientResponse::from_bytes(chunk).unwrap())
+                    .collect::<Vec<_>>()
+            };
+            let new_order = |client_id, order_id| {
+                ClientRequest::new(ClientRequestType::New, client_id, 1, order_id, 1, 10000, 10)
+            };
+
+            // Trading before login is rejected
+            let mut anonymous = Box::new(TcpSocket::connect("127.0.0.1", port).unwrap());
+            anonymous.send(new_order(7, 1).as_bytes()).unwrap();
+
+            // A wrong token is rejected
+            let mut impostor = Box::new(TcpSocket::connect("127.0.0.1", port).unwrap());
+            impostor.send(ClientRequest::login(7, 0xBAD).as_bytes()).unwrap();
+
+            // A valid login trades as client 7, but can't act as client 8
+            let mut trader = Box::new(TcpSocket::connect("127.0.0.1", port).unwrap());
+            trader.send(ClientRequest::login(7, 0xC0FFEE).as_bytes()).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            trader.send(new_order(7, 2).as_bytes()).unwrap();
+            trader.send(new_order(8, 3).as_bytes()).unwrap();
+
+            (
+                read_responses(&mut anonymous, 2),
+                read_responses(&mut impostor, 2),
+                read_responses(&mut trader, 2),
+            )
+        });
+
+        let mut received = Vec::new();
+        let start = std::time::Instant::now();
+        while (received.is_empty() || server.login_failures() < 2)
+            && start.elapsed() < Duration::from_secs(1)
+        {
+            received.extend(server.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        let logged_in: Vec<_> = server.client_ids().collect();
+
+        // Keep serving while the clients read their responses
+        for _ in 0..20 {
+            server.poll();
+            thread::sleep(Duration::from_millis(5));
+        }
+        let (anonymous, impostor, trader) = client_handle.join().unwrap();
+
+        assert_eq!(server.login_failures(), 2);
+        assert_eq!(server.pending_login_count(), 0);
+        assert_eq!(logged_in, vec![7]);
+
+        assert_eq!(received.len(), 1);
+        let order_id = received[0].request.order_id;
+        assert_eq!(received[0].client_id, 7);
+        assert_eq!(order_id, 2);
+
+        for responses in [anonymous, impostor] {
+            assert_eq!(responses.len(), 1);
+            assert_eq!(responses[0].response_type(), Some(ClientResponseType::InvalidRequest));
+        }
+
+        assert_eq!(trader.len(), 2);
+        assert_eq!(trader[0].response_type(), Some(ClientResponseType::Accepted));
+        let rejected_order_id = trader[1].client_order_id;
+        assert_eq!(trader[1].response_type(), Some(ClientResponseType::InvalidRequest));
+        assert_eq!(rejected_order_id, 3);
+    }
+
+    #[test]
+    fn test_timed_response_latency_breakdown() {
+        use common::net::tcp::TcpSocket;
+        use crate::matching_engine::{MatchingEngine, MatchingEngineConfig};
+
+        let config = OrderServerConfig::new("127.0.0.1", 0).with_timestamps(true);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        let mut engine = MatchingEngine::with_config(MatchingEngineConfig::new().with_timestamps(true));
+        engine.add_ticker(1);
+
+        let client_handle = thread::spawn(move || {
+            let mut client = Box::new(TcpSocket::connect("127.0.0.1", port).unwrap());
+            let request = ClientRequest::new(ClientRequestType::New, 1, 1, 1, 1, 10000, 10)
+                .with_origin_ts(123_456);
+            client.send(request.as_bytes()).unwrap();
+
+            client.socket().set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+            let mut bytes = Vec::new();
+            while bytes.len() < CLIENT_RESPONSE_SIZE {
+                bytes.extend_from_slice(client.recv().unwrap());
+            }
+            ClientResponse::from_bytes(&bytes[..CLIENT_RESPONSE_SIZE]).unwrap().origin_ts
+        });
+
+        let mut requests = Vec::new();
+        for _ in 0..100 {
+            requests.extend(server.poll());
+            if !requests.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(requests.len(), 1);
+        let seq_request = requests[0];
+        assert!(seq_request.received_at > Nanos::default());
+
+        let (response, _) = engine.process_request(&seq_request.request);
+        let matched = engine.last_match_timestamps();
+        assert!(matched.is_some());
+        server.send_timed_response(&seq_request, &response, matched).unwrap();
+
+        // The client gets its origin timestamp back
+        assert_eq!(client_handle.join().unwrap(), 123_456);
+
+        let timestamps = server.last_timestamps().unwrap();
+        assert_eq!(timestamps.received, seq_request.received_at);
+        assert!(timestamps.is_monotonic());
+        assert_eq!(
+            timestamps.inbound() + timestamps.matching() + timestamps.outbound(),
+            timestamps.total()
+        );
+
+        let latency = server.latency_breakdown();
+        assert_eq!(latency.total.count(), 1);
+        assert_eq!(latency.matching.max(), timestamps.matching());
+
+        server.reset_latency();
+        assert_eq!(server.latency_breakdown().total.count(), 0);
+        assert!(server.last_timestamps().is_none());
+    }
 }
@@ -15,6 +15,28 @@ use zerocopy::{AsBytes, FromBytes, FromZeroes};
 pub enum ClientRequestType {
     New = 1,
     Cancel = 2,
+    Modify = 3,
+    Login = 4,
+    /// Ask the order server to resend retained fills after an exec sequence
+    FillQuery = 5,
+    /// Stop-market order: rests unseen until a trade touches `stop_price`
+    NewStop = 6,
+    /// Stop-limit order: becomes a limit order at `price` once a trade
+    /// touches `stop_price`
+    NewStopLimit = 7,
+    /// All-or-none order: trades only if its full quantity can execute at
+    /// once
+    NewAon = 8,
+    /// Mass quote header: `qty` QuoteEntry records follow it on the wire,
+    /// each replacing the client's quotes on its ticker
+    MassQuote = 9,
+    /// Market order: the price is ignored and the order takes whatever the
+    /// opposite side offers, never resting
+    NewMarket = 10,
+    /// Change a resting order's quantity to `qty` at its current price (the
+    /// request's price is ignored): a reduction keeps time priority, an
+    /// increase loses it
+    Amend = 11,
 }
 
 impl ClientRequestType {
@@ -24,9 +46,32 @@ impl ClientRequestType {
         match value {
             1 => Some(ClientRequestType::New),
             2 => Some(ClientRequestType::Cancel),
+            3 => Some(ClientRequestType::Modify),
+            4 => Some(ClientRequestType::Login),
+            5 => Some(ClientRequestType::FillQuery),
+            6 => Some(ClientRequestType::NewStop),
+            7 => Some(ClientRequestType::NewStopLimit),
+            8 => Some(ClientRequestType::NewAon),
+            9 => Some(ClientRequestType::MassQuote),
+            10 => Some(ClientRequestType::NewMarket),
+            11 => Some(ClientRequestType::Amend),
             _ => None,
         }
     }
+
+    /// Returns true for the request types that enter a new order under a
+    /// fresh client order ID
+    #[inline]
+    pub fn is_new_order(&self) -> bool {
+        matches!(
+            self,
+            ClientRequestType::New
+                | ClientRequestType::NewAon
+                | ClientRequestType::NewStop
+                | ClientRequestType::NewStopLimit
+                | ClientRequestType::NewMarket
+        )
+    }
 }
 
 /// Client response types for order acknowledgments
@@ -38,6 +83,19 @@ pub enum ClientResponseType {
     Filled = 3,
     CancelRejected = 4,
     InvalidRequest = 5,
+    LastLookRejected = 6,
+    ModifyRejected = 7,
+    /// New order at a price level the client may not open, per its level cap
+    LevelLimitRejected = 8,
+    /// A stop order's trigger price was touched; its accept or fill follows
+    Triggered = 9,
+    /// An iceberg order's displayed slice was refilled from its reserve;
+    /// leaves_qty is the new slice and market_order_id the exchange ID it
+    /// rests under
+    Replenished = 10,
+    /// New order that would take the client past its exchange-side
+    /// position limit
+    RiskRejected = 11,
 }
 
 impl ClientResponseType {
@@ -50,6 +108,63 @@ impl ClientResponseType {
             3 => Some(ClientResponseType::Filled),
             4 => Some(ClientResponseType::CancelRejected),
             5 => Some(ClientResponseType::InvalidRequest),
+            6 => Some(ClientResponseType::LastLookRejected),
+            7 => Some(ClientResponseType::ModifyRejected),
+            8 => Some(ClientResponseType::LevelLimitRejected),
+            9 => Some(ClientResponseType::Triggered),
+            10 => Some(ClientResponseType::Replenished),
+            11 => Some(ClientResponseType::RiskRejected),
+            _ => None,
+        }
+    }
+}
+
+/// Liquidity indicator on fill responses
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidityFlag {
+    /// Not a fill, or liquidity not reported
+    None = 0,
+    /// The order was resting in the book (added liquidity)
+    Maker = 1,
+    /// The order was the aggressor (removed liquidity)
+    Taker = 2,
+}
+
+impl LiquidityFlag {
+    /// Convert from raw u8 value
+    #[inline]
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LiquidityFlag::None),
+            1 => Some(LiquidityFlag::Maker),
+            2 => Some(LiquidityFlag::Taker),
+            _ => None,
+        }
+    }
+}
+
+/// How long a new order may stay in the book
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeInForce {
+    /// Rests until filled or canceled
+    #[default]
+    Day = 0,
+    /// Immediate-or-cancel: trades what it can on arrival, the rest is canceled
+    Ioc = 1,
+    /// Fill-or-kill: trades its full quantity on arrival or not at all
+    Fok = 2,
+}
+
+impl TimeInForce {
+    /// Convert from raw u8 value
+    #[inline]
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TimeInForce::Day),
+            1 => Some(TimeInForce::Ioc),
+            2 => Some(TimeInForce::Fok),
             _ => None,
         }
     }
@@ -83,13 +198,26 @@ impl MarketUpdateType {
     }
 }
 
+// ============================================================================
+// Market Update Flags
+// ============================================================================
+
+/// Set on an Add update when the order established a new best price on its side
+pub const MARKET_UPDATE_FLAG_PRICE_IMPROVEMENT: u8 = 0x01;
+
+/// Set on a Snapshot update whose level has not been refreshed recently
+pub const MARKET_UPDATE_FLAG_STALE: u8 = 0x02;
+
+/// Set on a depth update carrying the total quantity of a price band
+pub const MARKET_UPDATE_FLAG_BANDED: u8 = 0x04;
+
 // ============================================================================
 // Message Structs
 // ============================================================================
 
 /// Client request message for order submission
 ///
-/// Layout (34 bytes total):
+/// Layout (55 bytes total):
 /// - msg_type: u8 (1 byte) - ClientRequestType
 /// - client_id: u32 (4 bytes)
 /// - ticker_id: u32 (4 bytes)
@@ -97,6 +225,11 @@ impl MarketUpdateType {
 /// - side: i8 (1 byte) - Side enum value
 /// - price: i64 (8 bytes) - fixed-point price in cents
 /// - qty: u32 (4 bytes)
+/// - tag: u64 (8 bytes) - opaque client tag, echoed on every response
+/// - origin_ts: u64 (8 bytes) - client send time in the client's clock
+///   (0 if unset), echoed on the direct response
+/// - stop_price: i64 (8 bytes) - trigger price of stop orders (0 otherwise)
+/// - time_in_force: u8 (1 byte) - TimeInForce of New orders (0 = Day)
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy, AsBytes, FromBytes, FromZeroes)]
 pub struct ClientRequest {
@@ -107,6 +240,10 @@ pub struct ClientRequest {
     pub side: i8,
     pub price: i64,
     pub qty: u32,
+    pub tag: u64,
+    pub origin_ts: u64,
+    pub stop_price: i64,
+    pub time_in_force: u8,
 }
 
 impl ClientRequest {
@@ -129,9 +266,74 @@ impl ClientRequest {
             side,
             price,
             qty,
+            tag: 0,
+            origin_ts: 0,
+            stop_price: 0,
+            time_in_force: TimeInForce::Day as u8,
         }
     }
 
+    /// Create a login request
+    ///
+    /// The token travels in the tag field, which Login requests do not
+    /// otherwise use.
+    #[inline]
+    pub fn login(client_id: u32, token: u64) -> Self {
+        Self::new(ClientRequestType::Login, client_id, 0, 0, 0, 0, 0).with_tag(token)
+    }
+
+    /// Create a fill query
+    ///
+    /// The last exec sequence the client has seen travels in the order_id
+    /// field; the server resends the retained fills after it.
+    #[inline]
+    pub fn fill_query(client_id: u32, after_exec_seq: u64) -> Self {
+        Self::new(ClientRequestType::FillQuery, client_id, 0, after_exec_seq, 0, 0, 0)
+    }
+
+    /// Create a mass quote header
+    ///
+    /// The quote ID travels in the order_id field and the number of
+    /// QuoteEntry records that follow in the qty field.
+    #[inline]
+    pub fn mass_quote(client_id: u32, quote_id: u64, entry_count: u32) -> Self {
+        Self::new(ClientRequestType::MassQuote, client_id, 0, quote_id, 0, 0, entry_count)
+    }
+
+    /// Returns this request with the given client tag
+    #[inline]
+    pub fn with_tag(mut self, tag: u64) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Returns this request with the given origin timestamp
+    #[inline]
+    pub fn with_origin_ts(mut self, origin_ts: u64) -> Self {
+        self.origin_ts = origin_ts;
+        self
+    }
+
+    /// Returns this request with the given stop trigger price
+    #[inline]
+    pub fn with_stop_price(mut self, stop_price: i64) -> Self {
+        self.stop_price = stop_price;
+        self
+    }
+
+    /// Returns this request with the given time in force
+    #[inline]
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force as u8;
+        self
+    }
+
+    /// Get the time in force as enum
+    #[inline]
+    pub fn time_in_force(&self) -> Option<TimeInForce> {
+        TimeInForce::from_u8(self.time_in_force)
+    }
+
     /// Get the message type as enum
     #[inline]
     pub fn request_type(&self) -> Option<ClientRequestType> {
@@ -157,9 +359,85 @@ impl ClientRequest {
     }
 }
 
+/// One ticker's two-sided quote within a mass quote
+///
+/// A side with zero quantity is not quoted; the client's previous quote on
+/// that side is still canceled. Each side rests as an ordinary order under
+/// its own client order ID.
+///
+/// Layout (44 bytes total):
+/// - ticker_id: u32 (4 bytes)
+/// - bid_order_id: u64 (8 bytes) - client order ID of the bid
+/// - bid_price: i64 (8 bytes)
+/// - bid_qty: u32 (4 bytes)
+/// - ask_order_id: u64 (8 bytes) - client order ID of the ask
+/// - ask_price: i64 (8 bytes)
+/// - ask_qty: u32 (4 bytes)
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, AsBytes, FromBytes, FromZeroes)]
+pub struct QuoteEntry {
+    pub ticker_id: u32,
+    pub bid_order_id: u64,
+    pub bid_price: i64,
+    pub bid_qty: u32,
+    pub ask_order_id: u64,
+    pub ask_price: i64,
+    pub ask_qty: u32,
+}
+
+impl QuoteEntry {
+    /// Create a new quote entry with no client order IDs
+    #[inline]
+    pub fn new(ticker_id: u32, bid_price: i64, bid_qty: u32, ask_price: i64, ask_qty: u32) -> Self {
+        Self {
+            ticker_id,
+            bid_order_id: 0,
+            bid_price,
+            bid_qty,
+            ask_order_id: 0,
+            ask_price,
+            ask_qty,
+        }
+    }
+
+    /// Returns this entry with the given client order IDs for its sides
+    #[inline]
+    pub fn with_order_ids(mut self, bid_order_id: u64, ask_order_id: u64) -> Self {
+        self.bid_order_id = bid_order_id;
+        self.ask_order_id = ask_order_id;
+        self
+    }
+
+    /// Get a byte slice reference to this message (zero-copy)
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        AsBytes::as_bytes(self)
+    }
+
+    /// Create a reference from a byte slice (zero-copy)
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        FromBytes::ref_from(bytes)
+    }
+}
+
+/// Serializes a mass quote: the header followed by its entries
+///
+/// The header's qty is set to the number of entries.
+pub fn encode_mass_quote(header: &ClientRequest, entries: &[QuoteEntry]) -> Vec<u8> {
+    let mut header = *header;
+    header.qty = entries.len() as u32;
+    let mut bytes = Vec::with_capacity(CLIENT_REQUEST_SIZE + entries.len() * QUOTE_ENTRY_SIZE);
+    bytes.extend_from_slice(header.as_bytes());
+    for entry in entries {
+        bytes.extend_from_slice(entry.as_bytes());
+    }
+    bytes
+}
+
 /// Client response message for order acknowledgments
 ///
-/// Layout (47 bytes total):
+/// Layout (75 bytes total):
 /// - msg_type: u8 (1 byte) - ClientResponseType
 /// - client_id: u32 (4 bytes)
 /// - ticker_id: u32 (4 bytes)
@@ -169,6 +447,14 @@ impl ClientRequest {
 /// - price: i64 (8 bytes)
 /// - exec_qty: u32 (4 bytes)
 /// - leaves_qty: u32 (4 bytes)
+/// - tag: u64 (8 bytes) - client tag copied from the originating request
+/// - liquidity: u8 (1 byte) - LiquidityFlag, set on Filled responses
+/// - origin_ts: u64 (8 bytes) - origin timestamp copied from the originating
+///   request (0 on fills for resting orders)
+/// - exec_seq: u64 (8 bytes) - per-client fill sequence stamped by the order
+///   server when fills are retained (0 otherwise)
+/// - exec_time_ns: u64 (8 bytes) - matching engine clock when a Filled
+///   response's trade occurred (0 otherwise)
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy, AsBytes, FromBytes, FromZeroes)]
 pub struct ClientResponse {
@@ -181,11 +467,17 @@ pub struct ClientResponse {
     pub price: i64,
     pub exec_qty: u32,
     pub leaves_qty: u32,
+    pub tag: u64,
+    pub liquidity: u8,
+    pub origin_ts: u64,
+    pub exec_seq: u64,
+    pub exec_time_ns: u64,
 }
 
 impl ClientResponse {
     /// Create a new client response
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         msg_type: ClientResponseType,
         client_id: u32,
@@ -207,9 +499,55 @@ impl ClientResponse {
             price,
             exec_qty,
             leaves_qty,
+            tag: 0,
+            liquidity: LiquidityFlag::None as u8,
+            origin_ts: 0,
+            exec_seq: 0,
+            exec_time_ns: 0,
         }
     }
 
+    /// Returns this response with the given client tag
+    #[inline]
+    pub fn with_tag(mut self, tag: u64) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Returns this response with the given origin timestamp
+    #[inline]
+    pub fn with_origin_ts(mut self, origin_ts: u64) -> Self {
+        self.origin_ts = origin_ts;
+        self
+    }
+
+    /// Returns this response with the given exec sequence
+    #[inline]
+    pub fn with_exec_seq(mut self, exec_seq: u64) -> Self {
+        self.exec_seq = exec_seq;
+        self
+    }
+
+    /// Returns this response with the given execution timestamp
+    #[inline]
+    pub fn with_exec_time(mut self, exec_time_ns: u64) -> Self {
+        self.exec_time_ns = exec_time_ns;
+        self
+    }
+
+    /// Returns this response with the given liquidity flag
+    #[inline]
+    pub fn with_liquidity(mut self, liquidity: LiquidityFlag) -> Self {
+        self.liquidity = liquidity as u8;
+        self
+    }
+
+    /// Get the liquidity flag as enum
+    #[inline]
+    pub fn liquidity_flag(&self) -> Option<LiquidityFlag> {
+        LiquidityFlag::from_u8(self.liquidity)
+    }
+
     /// Get the message type as enum
     #[inline]
     pub fn response_type(&self) -> Option<ClientResponseType> {
@@ -237,7 +575,7 @@ impl ClientResponse {
 
 /// Market data update message
 ///
-/// Layout (35 bytes total):
+/// Layout (43 bytes total):
 /// - msg_type: u8 (1 byte) - MarketUpdateType
 /// - ticker_id: u32 (4 bytes)
 /// - order_id: u64 (8 bytes)
@@ -245,6 +583,8 @@ impl ClientResponse {
 /// - price: i64 (8 bytes)
 /// - qty: u32 (4 bytes)
 /// - priority: u64 (8 bytes)
+/// - flags: u8 (1 byte) - MARKET_UPDATE_FLAG_* bits
+/// - seq: u64 (8 bytes) - feed sequence number, 0 if unsequenced
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy, AsBytes, FromBytes, FromZeroes)]
 pub struct MarketUpdate {
@@ -255,6 +595,8 @@ pub struct MarketUpdate {
     pub price: i64,
     pub qty: u32,
     pub priority: u64,
+    pub flags: u8,
+    pub seq: u64,
 }
 
 impl MarketUpdate {
@@ -277,9 +619,43 @@ impl MarketUpdate {
             price,
             qty,
             priority,
+            flags: 0,
+            seq: 0,
         }
     }
 
+    /// Returns this update with the given flag bits set
+    #[inline]
+    pub fn with_flags(mut self, flags: u8) -> Self {
+        self.flags |= flags;
+        self
+    }
+
+    /// Returns this update stamped with a feed sequence number
+    #[inline]
+    pub fn with_seq(mut self, seq: u64) -> Self {
+        self.seq = seq;
+        self
+    }
+
+    /// Returns true if the order set a new best price on its side
+    #[inline]
+    pub fn is_price_improvement(&self) -> bool {
+        self.flags & MARKET_UPDATE_FLAG_PRICE_IMPROVEMENT != 0
+    }
+
+    /// Returns true if this snapshot level was flagged as stale
+    #[inline]
+    pub fn is_stale(&self) -> bool {
+        self.flags & MARKET_UPDATE_FLAG_STALE != 0
+    }
+
+    /// Returns true if this update is a banded depth level
+    #[inline]
+    pub fn is_banded(&self) -> bool {
+        self.flags & MARKET_UPDATE_FLAG_BANDED != 0
+    }
+
     /// Get the message type as enum
     #[inline]
     pub fn update_type(&self) -> Option<MarketUpdateType> {
@@ -305,6 +681,43 @@ impl MarketUpdate {
     }
 }
 
+/// Snapshot request from a market data subscriber
+///
+/// Sent over UDP to the publisher's snapshot request port by a subscriber
+/// that joined between periodic snapshots. The publisher answers the sender
+/// directly with an unsequenced Clear followed by the ticker's Snapshot
+/// levels, each carrying `client_seq` in its order_id.
+///
+/// Layout (12 bytes total):
+/// - ticker_id: u32 (4 bytes)
+/// - client_seq: u64 (8 bytes) - requester's sequence, echoed on the reply
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, AsBytes, FromBytes, FromZeroes)]
+pub struct SnapshotRequest {
+    pub ticker_id: u32,
+    pub client_seq: u64,
+}
+
+impl SnapshotRequest {
+    /// Create a new snapshot request
+    #[inline]
+    pub fn new(ticker_id: u32, client_seq: u64) -> Self {
+        Self { ticker_id, client_seq }
+    }
+
+    /// Get a byte slice reference to this message (zero-copy)
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        AsBytes::as_bytes(self)
+    }
+
+    /// Create a reference from a byte slice (zero-copy)
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        FromBytes::ref_from(bytes)
+    }
+}
+
 // ============================================================================
 // Message Size Constants
 // ============================================================================
@@ -318,6 +731,15 @@ pub const CLIENT_RESPONSE_SIZE: usize = std::mem::size_of::<ClientResponse>();
 /// Size of MarketUpdate in bytes
 pub const MARKET_UPDATE_SIZE: usize = std::mem::size_of::<MarketUpdate>();
 
+/// Size of QuoteEntry in bytes
+pub const QUOTE_ENTRY_SIZE: usize = std::mem::size_of::<QuoteEntry>();
+
+/// Size of SnapshotRequest in bytes
+pub const SNAPSHOT_REQUEST_SIZE: usize = std::mem::size_of::<SnapshotRequest>();
+
+/// Most entries a mass quote may carry
+pub const MAX_MASS_QUOTE_ENTRIES: usize = 64;
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -328,20 +750,52 @@ mod tests {
 
     #[test]
     fn test_client_request_size() {
-        // 1 + 4 + 4 + 8 + 1 + 8 + 4 = 30 bytes
-        assert_eq!(CLIENT_REQUEST_SIZE, 30);
+        // 1 + 4 + 4 + 8 + 1 + 8 + 4 + 8 + 8 + 8 + 1 = 55 bytes
+        assert_eq!(CLIENT_REQUEST_SIZE, 55);
     }
 
     #[test]
     fn test_client_response_size() {
-        // 1 + 4 + 4 + 8 + 8 + 1 + 8 + 4 + 4 = 42 bytes
-        assert_eq!(CLIENT_RESPONSE_SIZE, 42);
+        // 1 + 4 + 4 + 8 + 8 + 1 + 8 + 4 + 4 + 8 + 1 + 8 + 8 + 8 = 75 bytes
+        assert_eq!(CLIENT_RESPONSE_SIZE, 75);
     }
 
     #[test]
     fn test_market_update_size() {
-        // 1 + 4 + 8 + 1 + 8 + 4 + 8 = 34 bytes
-        assert_eq!(MARKET_UPDATE_SIZE, 34);
+        // 1 + 4 + 8 + 1 + 8 + 4 + 8 + 1 + 8 = 43 bytes
+        assert_eq!(MARKET_UPDATE_SIZE, 43);
+    }
+
+    #[test]
+    fn test_snapshot_request_roundtrip() {
+        // 4 + 8 = 12 bytes
+        assert_eq!(SNAPSHOT_REQUEST_SIZE, 12);
+        let request = SnapshotRequest::new(3, 17);
+        let parsed = SnapshotRequest::from_bytes(request.as_bytes()).unwrap();
+        let (ticker_id, client_seq) = (parsed.ticker_id, parsed.client_seq);
+        assert_eq!((ticker_id, client_seq), (3, 17));
+    }
+
+    #[test]
+    fn test_mass_quote_encoding() {
+        // 4 + 8 + 8 + 4 + 8 + 8 + 4 = 44 bytes
+        assert_eq!(QUOTE_ENTRY_SIZE, 44);
+
+        let entries = [
+            QuoteEntry::new(1, 9990, 10, 10010, 20).with_order_ids(1, 2),
+            QuoteEntry::new(2, 4990, 5, 5010, 0).with_order_ids(3, 4),
+        ];
+        let bytes = encode_mass_quote(&ClientRequest::mass_quote(7, 42, 0), &entries);
+        assert_eq!(bytes.len(), CLIENT_REQUEST_SIZE + 2 * QUOTE_ENTRY_SIZE);
+
+        let header = ClientRequest::from_bytes(&bytes[..CLIENT_REQUEST_SIZE]).unwrap();
+        let (client_id, quote_id, count) = (header.client_id, header.order_id, header.qty);
+        assert_eq!(header.request_type(), Some(ClientRequestType::MassQuote));
+        assert_eq!((client_id, quote_id, count), (7, 42, 2));
+
+        let second = QuoteEntry::from_bytes(&bytes[CLIENT_REQUEST_SIZE + QUOTE_ENTRY_SIZE..]).unwrap();
+        let (ticker_id, ask_order_id, ask_qty) = (second.ticker_id, second.ask_order_id, second.ask_qty);
+        assert_eq!((ticker_id, ask_order_id, ask_qty), (2, 4, 0));
     }
 
     #[test]
@@ -418,6 +872,58 @@ mod tests {
         assert_eq!(leaves_qty, 100);
     }
 
+    #[test]
+    fn test_tag_roundtrip() {
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 12345, 1, 10050, 100)
+            .with_tag(0xABCD);
+        let parsed = ClientRequest::from_bytes(request.as_bytes()).unwrap();
+        let tag = parsed.tag;
+        assert_eq!(tag, 0xABCD);
+
+        let response = ClientResponse::new(
+            ClientResponseType::Filled, 100, 1, 12345, 67890, 1, 10050, 100, 0,
+        )
+        .with_tag(0xABCD);
+        let parsed = ClientResponse::from_bytes(response.as_bytes()).unwrap();
+        let tag = parsed.tag;
+        assert_eq!(tag, 0xABCD);
+
+        // Untagged messages carry zero
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 1, 1, 10050, 100);
+        let tag = request.tag;
+        assert_eq!(tag, 0);
+    }
+
+    #[test]
+    fn test_time_in_force_roundtrip() {
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 12345, 1, 10050, 100);
+        assert_eq!(request.time_in_force(), Some(TimeInForce::Day));
+
+        let request = request.with_time_in_force(TimeInForce::Fok);
+        let parsed = ClientRequest::from_bytes(request.as_bytes()).unwrap();
+        assert_eq!(parsed.time_in_force(), Some(TimeInForce::Fok));
+        assert_eq!(TimeInForce::from_u8(1), Some(TimeInForce::Ioc));
+        assert_eq!(TimeInForce::from_u8(3), None);
+    }
+
+    #[test]
+    fn test_liquidity_flag_roundtrip() {
+        let response = ClientResponse::new(
+            ClientResponseType::Accepted, 100, 1, 12345, 67890, 1, 10050, 0, 100,
+        );
+        assert_eq!(response.liquidity_flag(), Some(LiquidityFlag::None));
+
+        let response = ClientResponse::new(
+            ClientResponseType::Filled, 100, 1, 12345, 67890, 1, 10050, 100, 0,
+        )
+        .with_liquidity(LiquidityFlag::Maker);
+        let parsed = ClientResponse::from_bytes(response.as_bytes()).unwrap();
+        assert_eq!(parsed.liquidity_flag(), Some(LiquidityFlag::Maker));
+
+        assert_eq!(LiquidityFlag::from_u8(2), Some(LiquidityFlag::Taker));
+        assert_eq!(LiquidityFlag::from_u8(3), None);
+    }
+
     #[test]
     fn test_market_update_roundtrip() {
         let update = MarketUpdate::new(
@@ -450,12 +956,34 @@ mod tests {
         assert_eq!(price, 10050);
         assert_eq!(qty, 100);
         assert_eq!(priority, 99999);
+        assert!(!parsed.is_price_improvement());
+    }
+
+    #[test]
+    fn test_market_update_flags_roundtrip() {
+        let update = MarketUpdate::new(MarketUpdateType::Add, 1, 1, 1, 10050, 100, 1)
+            .with_flags(MARKET_UPDATE_FLAG_PRICE_IMPROVEMENT);
+
+        let parsed = MarketUpdate::from_bytes(update.as_bytes()).unwrap();
+        let flags = parsed.flags;
+
+        assert_eq!(flags, MARKET_UPDATE_FLAG_PRICE_IMPROVEMENT);
+        assert!(parsed.is_price_improvement());
     }
 
     #[test]
     fn test_request_type_conversion() {
         assert_eq!(ClientRequestType::from_u8(1), Some(ClientRequestType::New));
         assert_eq!(ClientRequestType::from_u8(2), Some(ClientRequestType::Cancel));
+        assert_eq!(ClientRequestType::from_u8(3), Some(ClientRequestType::Modify));
+        assert_eq!(ClientRequestType::from_u8(4), Some(ClientRequestType::Login));
+        assert_eq!(ClientRequestType::from_u8(5), Some(ClientRequestType::FillQuery));
+        assert_eq!(ClientRequestType::from_u8(6), Some(ClientRequestType::NewStop));
+        assert_eq!(ClientRequestType::from_u8(7), Some(ClientRequestType::NewStopLimit));
+        assert_eq!(ClientRequestType::from_u8(8), Some(ClientRequestType::NewAon));
+        assert_eq!(ClientRequestType::from_u8(10), Some(ClientRequestType::NewMarket));
+        assert_eq!(ClientRequestType::from_u8(9), Some(ClientRequestType::MassQuote));
+        assert_eq!(ClientRequestType::from_u8(11), Some(ClientRequestType::Amend));
         assert_eq!(ClientRequestType::from_u8(0), None);
         assert_eq!(ClientRequestType::from_u8(255), None);
     }
@@ -467,6 +995,12 @@ mod tests {
         assert_eq!(ClientResponseType::from_u8(3), Some(ClientResponseType::Filled));
         assert_eq!(ClientResponseType::from_u8(4), Some(ClientResponseType::CancelRejected));
         assert_eq!(ClientResponseType::from_u8(5), Some(ClientResponseType::InvalidRequest));
+        assert_eq!(ClientResponseType::from_u8(6), Some(ClientResponseType::LastLookRejected));
+        assert_eq!(ClientResponseType::from_u8(7), Some(ClientResponseType::ModifyRejected));
+        assert_eq!(ClientResponseType::from_u8(8), Some(ClientResponseType::LevelLimitRejected));
+        assert_eq!(ClientResponseType::from_u8(9), Some(ClientResponseType::Triggered));
+        assert_eq!(ClientResponseType::from_u8(10), Some(ClientResponseType::Replenished));
+        assert_eq!(ClientResponseType::from_u8(11), Some(ClientResponseType::RiskRejected));
         assert_eq!(ClientResponseType::from_u8(0), None);
     }
 
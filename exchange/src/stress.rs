@@ -0,0 +1,575 @@
+// Synthetic order flow for stress testing the matching engine
+//
+// The generator produces a seeded, reproducible stream of ClientRequests -
+// passive adds, cancels, modifies and marketable orders in a configurable
+// mix - around a fixed mid price. It learns which orders are resting from
+// the engine's responses, so cancels and modifies target live orders.
+//
+// `run_stress` drives the stream through a MatchingEngine, times each
+// `process_request` call, and checks every book with `validate_book` as it
+// goes. The same seed and configuration always produce the same requests.
+
+use common::{ClientId, OrderId, Price, Qty, Side, TickerId};
+use common::time::{monotonic_nanos, nanos_since};
+use crate::matching_engine::MatchingEngine;
+use crate::protocol::{ClientRequest, ClientRequestType, ClientResponse, ClientResponseType};
+use std::collections::HashMap;
+
+/// Relative weights of each request type in the generated stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestMix {
+    /// Passive orders that rest on their own side of the mid
+    pub add: u32,
+    /// Cancels of live orders
+    pub cancel: u32,
+    /// Reprices and resizes of live orders
+    pub modify: u32,
+    /// Orders priced through the opposite side
+    pub marketable: u32,
+}
+
+impl Default for RequestMix {
+    fn default() -> Self {
+        Self {
+            add: 50,
+            cancel: 25,
+            modify: 15,
+            marketable: 10,
+        }
+    }
+}
+
+impl RequestMix {
+    /// Create a mix from the given weights
+    pub fn new(add: u32, cancel: u32, modify: u32, marketable: u32) -> Self {
+        Self { add, cancel, modify, marketable }
+    }
+
+    /// Sum of all weights
+    #[inline]
+    pub fn total(&self) -> u32 {
+        self.add + self.cancel + self.modify + self.marketable
+    }
+}
+
+/// How far from the mid passive orders are placed, in ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceDistribution {
+    /// Every level within the depth is equally likely
+    Uniform,
+    /// Orders cluster near the touch with the given mean distance
+    Exponential { mean_ticks: f64 },
+}
+
+/// Parameters of a stress run
+#[derive(Debug, Clone, Copy)]
+pub struct StressConfig {
+    /// Seed of the request stream
+    pub seed: u64,
+    /// Number of requests to generate
+    pub requests: usize,
+    /// Tickers traded, numbered from 1
+    pub tickers: u32,
+    /// Clients sending orders, numbered from 1
+    pub clients: u32,
+    /// Request type weights
+    pub mix: RequestMix,
+    /// Price the book forms around
+    pub mid_price: Price,
+    /// Price increment between levels
+    pub tick_size: Price,
+    /// Furthest level from the mid, in ticks
+    pub depth_ticks: u32,
+    /// Placement of passive orders within the depth
+    pub price_distribution: PriceDistribution,
+    /// Smallest order quantity
+    pub min_qty: Qty,
+    /// Largest order quantity
+    pub max_qty: Qty,
+    /// Validate the books every this many requests (0 only at the end)
+    pub validate_every: usize,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            requests: 100_000,
+            tickers: 1,
+            clients: 8,
+            mix: RequestMix::default(),
+            mid_price: 10_000,
+            tick_size: 1,
+            depth_ticks: 50,
+            price_distribution: PriceDistribution::Exponential { mean_ticks: 5.0 },
+            min_qty: 1,
+            max_qty: 100,
+            validate_every: 1_000,
+        }
+    }
+}
+
+impl StressConfig {
+    /// Create a configuration with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the seed of the request stream
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the number of requests to generate
+    pub fn with_requests(mut self, requests: usize) -> Self {
+        self.requests = requests;
+        self
+    }
+
+    /// Set the number of tickers and clients
+    pub fn with_participants(mut self, tickers: u32, clients: u32) -> Self {
+        self.tickers = tickers.max(1);
+        self.clients = clients.max(1);
+        self
+    }
+
+    /// Set the request type weights
+    pub fn with_mix(mut self, mix: RequestMix) -> Self {
+        self.mix = mix;
+        self
+    }
+
+    /// Set the mid price, tick size and depth of passive orders
+    pub fn with_prices(mut self, mid_price: Price, tick_size: Price, depth_ticks: u32) -> Self {
+        self.mid_price = mid_price;
+        self.tick_size = tick_size.max(1);
+        self.depth_ticks = depth_ticks.max(1);
+        self
+    }
+
+    /// Set the placement of passive orders
+    pub fn with_price_distribution(mut self, distribution: PriceDistribution) -> Self {
+        self.price_distribution = distribution;
+        self
+    }
+
+    /// Set the range of order quantities
+    pub fn with_qty_range(mut self, min_qty: Qty, max_qty: Qty) -> Self {
+        self.min_qty = min_qty.max(1);
+        self.max_qty = max_qty.max(self.min_qty);
+        self
+    }
+
+    /// Set how often the books are validated during a run
+    pub fn with_validate_every(mut self, requests: usize) -> Self {
+        self.validate_every = requests;
+        self
+    }
+}
+
+/// SplitMix64: small, fast and good enough for synthetic order flow
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, n)
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n.max(1)
+    }
+
+    /// Uniform in (0, 1]
+    fn unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A resting order the generator may cancel or modify
+#[derive(Debug, Clone, Copy)]
+struct LiveOrder {
+    client_id: ClientId,
+    ticker_id: TickerId,
+    order_id: OrderId,
+    side: Side,
+}
+
+/// Produces a reproducible stream of client requests
+pub struct StressGenerator {
+    config: StressConfig,
+    rng: SplitMix64,
+    /// Resting orders, in no particular order
+    live: Vec<LiveOrder>,
+    /// Position of each resting order in `live`, by exchange order ID
+    live_index: HashMap<OrderId, usize>,
+    next_client_order_id: OrderId,
+}
+
+impl StressGenerator {
+    /// Create a generator for the given configuration
+    pub fn new(config: StressConfig) -> Self {
+        Self {
+            config,
+            rng: SplitMix64(config.seed),
+            live: Vec::new(),
+            live_index: HashMap::new(),
+            next_client_order_id: 1,
+        }
+    }
+
+    /// Number of orders the generator believes are resting
+    #[inline]
+    pub fn live_orders(&self) -> usize {
+        self.live.len()
+    }
+
+    /// Generate the next request
+    ///
+    /// Cancels and modifies fall back to a passive add while no orders are
+    /// resting.
+    pub fn next_request(&mut self) -> ClientRequest {
+        let mix = self.config.mix;
+        let mut pick = self.rng.below(mix.total().max(1) as u64) as u32;
+
+        if pick < mix.add {
+            return self.passive_order();
+        }
+        pick -= mix.add;
+        if pick < mix.cancel {
+            return self.cancel().unwrap_or_else(|| self.passive_order());
+        }
+        pick -= mix.cancel;
+        if pick < mix.modify {
+            return self.modify().unwrap_or_else(|| self.passive_order());
+        }
+        self.marketable_order()
+    }
+
+    /// Track resting orders from an engine response
+    ///
+    /// Feed it the response to every request and every maker fill.
+    pub fn on_response(&mut self, response: &ClientResponse) {
+        let order_id = response.market_order_id;
+        let leaves_qty = response.leaves_qty;
+        match ClientResponseType::from_u8(response.msg_type) {
+            Some(ClientResponseType::Accepted) | Some(ClientResponseType::Filled) if leaves_qty > 0 => {
+                let side = if response.side > 0 { Side::Buy } else { Side::Sell };
+                self.insert_live(LiveOrder {
+                    client_id: response.client_id,
+                    ticker_id: response.ticker_id,
+                    order_id,
+                    side,
+                });
+            }
+            Some(ClientResponseType::Filled)
+            | Some(ClientResponseType::Canceled)
+            | Some(ClientResponseType::CancelRejected) => self.remove_live(order_id),
+            _ => {}
+        }
+    }
+
+    fn insert_live(&mut self, order: LiveOrder) {
+        match self.live_index.get(&order.order_id) {
+            Some(&pos) => self.live[pos] = order,
+            None => {
+                self.live_index.insert(order.order_id, self.live.len());
+                self.live.push(order);
+            }
+        }
+    }
+
+    fn remove_live(&mut self, order_id: OrderId) {
+        let Some(pos) = self.live_index.remove(&order_id) else {
+            return;
+        };
+        self.live.swap_remove(pos);
+        if let Some(moved) = self.live.get(pos) {
+            self.live_index.insert(moved.order_id, pos);
+        }
+    }
+
+    fn random_side(&mut self) -> Side {
+        if self.rng.below(2) == 0 { Side::Buy } else { Side::Sell }
+    }
+
+    fn random_qty(&mut self) -> Qty {
+        let span = (self.config.max_qty - self.config.min_qty) as u64 + 1;
+        self.config.min_qty + self.rng.below(span) as Qty
+    }
+
+    /// A price on the side's own half of the book, at least one tick from the mid
+    fn passive_price(&mut self, side: Side) -> Price {
+        let depth = self.config.depth_ticks as u64;
+        let ticks = match self.config.price_distribution {
+            PriceDistribution::Uniform => self.rng.below(depth),
+            PriceDistribution::Exponential { mean_ticks } => {
+                let ticks = -self.rng.unit().ln() * mean_ticks.max(0.0);
+                (ticks as u64).min(depth - 1)
+            }
+        } as Price;
+        let offset = (ticks + 1) * self.config.tick_size;
+        match side {
+            Side::Buy => self.config.mid_price - offset,
+            Side::Sell => self.config.mid_price + offset,
+        }
+    }
+
+    fn new_order(&mut self, side: Side, price: Price) -> ClientRequest {
+        let client_id = 1 + self.rng.below(self.config.clients as u64) as ClientId;
+        let ticker_id = 1 + self.rng.below(self.config.tickers as u64) as TickerId;
+        let order_id = self.next_client_order_id;
+        self.next_client_order_id += 1;
+        let qty = self.random_qty();
+        ClientRequest::new(ClientRequestType::New, client_id, ticker_id, order_id, side as i8, price, qty)
+    }
+
+    fn passive_order(&mut self) -> ClientRequest {
+        let side = self.random_side();
+        let price = self.passive_price(side);
+        self.new_order(side, price)
+    }
+
+    fn marketable_order(&mut self) -> ClientRequest {
+        let side = self.random_side();
+        // Priced at the far edge of the opposite side so it sweeps whatever is there
+        let reach = self.config.depth_ticks as Price * self.config.tick_size;
+        let price = match side {
+            Side::Buy => self.config.mid_price + reach,
+            Side::Sell => self.config.mid_price - reach,
+        };
+        self.new_order(side, price)
+    }
+
+    fn random_live(&mut self) -> Option<LiveOrder> {
+        if self.live.is_empty() {
+            return None;
+        }
+        let pos = self.rng.below(self.live.len() as u64) as usize;
+        Some(self.live[pos])
+    }
+
+    fn cancel(&mut self) -> Option<ClientRequest> {
+        let order = self.random_live()?;
+        Some(ClientRequest::new(
+            ClientRequestType::Cancel,
+            order.client_id,
+            order.ticker_id,
+            order.order_id,
+            order.side as i8,
+            0,
+            0,
+        ))
+    }
+
+    fn modify(&mut self) -> Option<ClientRequest> {
+        let order = self.random_live()?;
+        let price = self.passive_price(order.side);
+        let qty = self.random_qty();
+        Some(ClientRequest::new(
+            ClientRequestType::Modify,
+            order.client_id,
+            order.ticker_id,
+            order.order_id,
+            order.side as i8,
+            price,
+            qty,
+        ))
+    }
+}
+
+/// Latency percentiles of `process_request`, in nanoseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+    pub mean: u64,
+}
+
+impl LatencyPercentiles {
+    /// Compute percentiles from unsorted samples
+    pub fn from_samples(samples: &mut [u64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let at = |fraction: f64| {
+            let idx = ((samples.len() - 1) as f64 * fraction).round() as usize;
+            samples[idx]
+        };
+        Self {
+            p50: at(0.50),
+            p90: at(0.90),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: samples[samples.len() - 1],
+            mean: samples.iter().sum::<u64>() / samples.len() as u64,
+        }
+    }
+}
+
+/// Outcome of a stress run
+#[derive(Debug, Clone, Default)]
+pub struct StressReport {
+    /// Requests processed
+    pub requests: u64,
+    /// New requests, passive and marketable
+    pub new_orders: u64,
+    /// Cancel requests
+    pub cancels: u64,
+    /// Modify requests
+    pub modifies: u64,
+    /// Requests the engine rejected
+    pub rejects: u64,
+    /// Fills delivered to resting orders
+    pub maker_fills: u64,
+    /// Market data updates produced
+    pub market_updates: u64,
+    /// Orders resting across all books at the end
+    pub resting_orders: usize,
+    /// Wall-clock time spent inside `process_request`, in nanoseconds
+    pub busy_ns: u64,
+    /// Per-request latency
+    pub latency: LatencyPercentiles,
+}
+
+impl StressReport {
+    /// Requests per second of engine time
+    pub fn throughput(&self) -> f64 {
+        if self.busy_ns == 0 {
+            return 0.0;
+        }
+        self.requests as f64 * 1e9 / self.busy_ns as f64
+    }
+}
+
+/// Drive a generated request stream through the engine
+///
+/// Adds the configured tickers to the engine, then processes
+/// `config.requests` requests, validating every book every
+/// `config.validate_every` requests and once at the end.
+///
+/// # Arguments
+/// * `engine` - Engine under test
+/// * `config` - Stream and run parameters
+///
+/// # Returns
+/// The run's counters and latency percentiles, or the first invariant
+/// violation with the request after which it was found
+pub fn run_stress(engine: &mut MatchingEngine, config: &StressConfig) -> Result<StressReport, String> {
+    let tickers: Vec<TickerId> = (1..=config.tickers).collect();
+    for &ticker_id in &tickers {
+        engine.add_ticker(ticker_id);
+    }
+
+    let mut generator = StressGenerator::new(*config);
+    let mut report = StressReport::default();
+    let mut samples = Vec::with_capacity(config.requests);
+
+    for i in 1..=config.requests {
+        let request = generator.next_request();
+        match ClientRequestType::from_u8(request.msg_type) {
+            Some(ClientRequestType::New) => report.new_orders += 1,
+            Some(ClientRequestType::Cancel) => report.cancels += 1,
+            Some(ClientRequestType::Modify) => report.modifies += 1,
+            _ => {}
+        }
+
+        let start = monotonic_nanos();
+        let (response, updates) = engine.process_request(&request);
+        let elapsed = nanos_since(start);
+        samples.push(elapsed);
+        report.busy_ns += elapsed;
+        report.requests += 1;
+        report.market_updates += updates.len() as u64;
+
+        if matches!(
+            ClientResponseType::from_u8(response.msg_type),
+            Some(ClientResponseType::CancelRejected)
+                | Some(ClientResponseType::ModifyRejected)
+                | Some(ClientResponseType::InvalidRequest)
+                | Some(ClientResponseType::LastLookRejected)
+                | Some(ClientResponseType::LevelLimitRejected)
+                | Some(ClientResponseType::RiskRejected)
+        ) {
+            report.rejects += 1;
+        }
+        generator.on_response(&response);
+        for fill in engine.drain_maker_fills() {
+            report.maker_fills += 1;
+            generator.on_response(&fill);
+        }
+
+        if config.validate_every > 0 && i % config.validate_every == 0 {
+            validate_books(engine, &tickers).map_err(|e| format!("after request {}: {}", i, e))?;
+        }
+    }
+
+    validate_books(engine, &tickers).map_err(|e| format!("at end of run: {}", e))?;
+    report.resting_orders = tickers
+        .iter()
+        .filter_map(|&ticker_id| engine.get_order_book(ticker_id))
+        .map(|book| book.order_count())
+        .sum();
+    report.latency = LatencyPercentiles::from_samples(&mut samples);
+    Ok(report)
+}
+
+/// Validate the book of every ticker
+fn validate_books(engine: &MatchingEngine, tickers: &[TickerId]) -> Result<(), String> {
+    for &ticker_id in tickers {
+        let book = engine
+            .get_order_book(ticker_id)
+            .ok_or_else(|| format!("ticker {} has no book", ticker_id))?;
+        book.validate_book().map_err(|e| format!("ticker {}: {}", ticker_id, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stress_burst_keeps_books_valid() {
+        let config = StressConfig::new()
+            .with_seed(42)
+            .with_requests(20_000)
+            .with_participants(2, 4)
+            .with_validate_every(100);
+        let mut engine = MatchingEngine::new();
+        let report = run_stress(&mut engine, &config).unwrap();
+
+        assert_eq!(report.requests, 20_000);
+        assert_eq!(report.new_orders + report.cancels + report.modifies, 20_000);
+        assert!(report.cancels > 0 && report.modifies > 0);
+        assert!(report.maker_fills > 0);
+        assert!(report.latency.p50 <= report.latency.p99);
+        assert!(report.latency.p99 <= report.latency.max);
+    }
+
+    #[test]
+    fn test_stress_stream_is_reproducible() {
+        let config = StressConfig::new()
+            .with_seed(7)
+            .with_requests(2_000)
+            .with_price_distribution(PriceDistribution::Uniform);
+        let first = run_stress(&mut MatchingEngine::new(), &config).unwrap();
+        let second = run_stress(&mut MatchingEngine::new(), &config).unwrap();
+
+        assert_eq!(first.cancels, second.cancels);
+        assert_eq!(first.modifies, second.modifies);
+        assert_eq!(first.maker_fills, second.maker_fills);
+        assert_eq!(first.market_updates, second.market_updates);
+        assert_eq!(first.resting_orders, second.resting_orders);
+    }
+}
@@ -0,0 +1,471 @@
+//! Runtime parameter updates for a running trading client.
+//!
+//! Strategy and risk parameters can be changed without a restart by editing
+//! a control file of `key = value` lines. `ControlFile` watches the file's
+//! modification time and parses it into a `ParamUpdate`, which is applied on
+//! top of the current `MarketMakerConfig`, `LiquidityTakerConfig` and
+//! `RiskLimits`. Keys that are absent leave the current value unchanged.
+//!
+//! ```text
+//! # Widen quotes and cut size
+//! half_spread = 80
+//! base_qty = 50
+//! max_loss = 50000
+//! ```
+//!
+//! Operators can also pull everything by hand through a `ControlServer`: a
+//! TCP port accepting one command per line, handled on its own thread so it
+//! acts even while the trading loop is busy or stuck. `kill` cancels every
+//! resting order and deactivates the strategies until `resume`; `kill
+//! flatten` also closes the position; `kill order <id>` cancels one order.
+//! Each command is answered with `ok` or `error: <reason>`.
+
+use common::{OrderId, Price, Qty};
+use crate::risk::RiskLimits;
+use crate::strategies::{LiquidityTakerConfig, MarketMakerConfig};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+
+/// A set of parameter overrides read from a control file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParamUpdate {
+    /// Market maker half spread in cents.
+    pub half_spread: Option<Price>,
+    /// Market maker minimum half spread in cents.
+    pub min_spread: Option<Price>,
+    /// Strategy base order quantity.
+    pub base_qty: Option<Qty>,
+    /// Strategy maximum order quantity.
+    pub max_qty: Option<Qty>,
+    /// Maximum position, applied to both the strategy and the risk limits.
+    pub max_position: Option<i64>,
+    /// Liquidity taker signal threshold (symmetric).
+    pub signal_threshold: Option<f64>,
+    /// Market maker quote expiry in milliseconds.
+    pub max_quote_age_ms: Option<u64>,
+    /// Risk limit on a single order's quantity.
+    pub max_order_qty: Option<Qty>,
+    /// Risk limit on loss in cents.
+    pub max_loss: Option<i64>,
+    /// Risk limit on open orders.
+    pub max_open_orders: Option<u32>,
+}
+
+impl ParamUpdate {
+    /// Parses `key = value` lines. Blank lines and `#` comments are ignored.
+    ///
+    /// # Returns
+    /// The parsed update, or an `InvalidData` error naming the offending line
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut update = Self::default();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: invalid parameter '{}'", line_no + 1, line),
+                )
+            };
+            let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+
+            match key.trim() {
+                "half_spread" => update.half_spread = Some(value.parse().map_err(|_| invalid())?),
+                "min_spread" => update.min_spread = Some(value.parse().map_err(|_| invalid())?),
+                "base_qty" => update.base_qty = Some(value.parse().map_err(|_| invalid())?),
+                "max_qty" => update.max_qty = Some(value.parse().map_err(|_| invalid())?),
+                "max_position" => update.max_position = Some(value.parse().map_err(|_| invalid())?),
+                "signal_threshold" => {
+                    update.signal_threshold = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "max_quote_age_ms" => {
+                    update.max_quote_age_ms = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "max_order_qty" => update.max_order_qty = Some(value.parse().map_err(|_| invalid())?),
+                "max_loss" => update.max_loss = Some(value.parse().map_err(|_| invalid())?),
+                "max_open_orders" => {
+                    update.max_open_orders = Some(value.parse().map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(update)
+    }
+
+    /// Returns true if no parameter is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns `config` with the market maker parameters of this update applied.
+    pub fn apply_to_market_maker(&self, mut config: MarketMakerConfig) -> MarketMakerConfig {
+        if let Some(half_spread) = self.half_spread {
+            config = config.with_half_spread(half_spread);
+        }
+        if let Some(min_spread) = self.min_spread {
+            config = config.with_min_spread(min_spread);
+        }
+        if let Some(base_qty) = self.base_qty {
+            config = config.with_base_qty(base_qty);
+        }
+        if let Some(max_qty) = self.max_qty {
+            config = config.with_max_qty(max_qty);
+        }
+        if let Some(max_position) = self.max_position {
+            config = config.with_max_position(max_position);
+        }
+        if let Some(max_quote_age_ms) = self.max_quote_age_ms {
+            config = config.with_max_quote_age_ns(max_quote_age_ms * 1_000_000);
+        }
+        config
+    }
+
+    /// Returns `config` with the liquidity taker parameters of this update applied.
+    pub fn apply_to_liquidity_taker(&self, mut config: LiquidityTakerConfig) -> LiquidityTakerConfig {
+        if let Some(threshold) = self.signal_threshold {
+            config = config.with_threshold(threshold);
+        }
+        if let Some(base_qty) = self.base_qty {
+            config = config.with_base_qty(base_qty);
+        }
+        if let Some(max_qty) = self.max_qty {
+            config = config.with_max_qty(max_qty);
+        }
+        if let Some(max_position) = self.max_position {
+            config = config.with_max_position(max_position);
+        }
+        config
+    }
+
+    /// Returns `limits` with the risk parameters of this update applied.
+    pub fn apply_to_risk_limits(&self, mut limits: RiskLimits) -> RiskLimits {
+        if let Some(max_order_qty) = self.max_order_qty {
+            limits.max_order_qty = max_order_qty;
+        }
+        if let Some(max_position) = self.max_position {
+            limits.max_position = max_position;
+        }
+        if let Some(max_loss) = self.max_loss {
+            limits.max_loss = max_loss;
+        }
+        if let Some(max_open_orders) = self.max_open_orders {
+            limits.max_open_orders = max_open_orders;
+        }
+        limits
+    }
+}
+
+/// Watches a control file and reports parameter updates when it changes.
+#[derive(Debug)]
+pub struct ControlFile {
+    /// Path of the watched file.
+    path: PathBuf,
+    /// Modification time of the last version read.
+    last_modified: Option<SystemTime>,
+}
+
+impl ControlFile {
+    /// Creates a watcher for the given path. The file need not exist yet.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            last_modified: None,
+        }
+    }
+
+    /// Returns the watched path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the file if it changed since the last call.
+    ///
+    /// # Returns
+    /// `Ok(Some(update))` for a new version, `Ok(None)` if the file is
+    /// unchanged or missing, or an error if it can't be read or parsed.
+    /// A version that fails to parse is not retried until it changes again.
+    pub fn poll(&mut self) -> io::Result<Option<ParamUpdate>> {
+        let modified = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if self.last_modified == Some(modified) {
+            return Ok(None);
+        }
+        self.last_modified = Some(modified);
+
+        let text = fs::read_to_string(&self.path)?;
+        ParamUpdate::parse(&text).map(Some)
+    }
+}
+
+/// An operator command read from the control port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Cancel every resting order and deactivate the strategies, optionally
+    /// flattening the position too.
+    Kill {
+        /// Also close the position.
+        flatten: bool,
+    },
+    /// Cancel a single order.
+    KillOrder(OrderId),
+    /// Let the strategies trade again after a kill.
+    Resume,
+}
+
+impl ControlCommand {
+    /// Parses a command line: `kill`, `kill flatten`, `kill order <id>` or
+    /// `resume`, case-insensitively.
+    ///
+    /// # Returns
+    /// The command, or an `InvalidData` error naming the line
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let invalid = || {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid command '{}'", line.trim()))
+        };
+        let words: Vec<String> = line.split_whitespace().map(str::to_ascii_lowercase).collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+        match words.as_slice() {
+            ["kill"] => Ok(ControlCommand::Kill { flatten: false }),
+            ["kill", "flatten"] => Ok(ControlCommand::Kill { flatten: true }),
+            ["kill", "order", order_id] => {
+                order_id.parse().map(ControlCommand::KillOrder).map_err(|_| invalid())
+            }
+            ["resume"] => Ok(ControlCommand::Resume),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Kill state shared between the control port and the trading loop.
+///
+/// The control port sets it; the trading loop checks it every iteration,
+/// keeps its strategies deactivated while it is set and flattens once when
+/// asked to.
+#[derive(Debug, Default)]
+pub struct KillSwitch {
+    /// Set by a kill, cleared by a resume.
+    killed: AtomicBool,
+    /// A flatten requested by a kill and not yet taken by the trading loop.
+    flatten: AtomicBool,
+    /// Kills triggered since startup.
+    kills: AtomicU64,
+}
+
+impl KillSwitch {
+    /// Creates a switch that has not been triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills the session, requesting a flatten if `flatten` is set.
+    pub fn trigger(&self, flatten: bool) {
+        if flatten {
+            self.flatten.store(true, Ordering::SeqCst);
+        }
+        self.killed.store(true, Ordering::SeqCst);
+        self.kills.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Lifts the kill. A flatten not yet taken is dropped.
+    pub fn resume(&self) {
+        self.flatten.store(false, Ordering::SeqCst);
+        self.killed.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true while the session is killed.
+    #[inline]
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::SeqCst)
+    }
+
+    /// Takes a pending flatten request.
+    ///
+    /// # Returns
+    /// true once per kill that asked to flatten
+    pub fn take_flatten(&self) -> bool {
+        self.flatten.swap(false, Ordering::SeqCst)
+    }
+
+    /// Returns the number of kills triggered since startup.
+    pub fn kills(&self) -> u64 {
+        self.kills.load(Ordering::Relaxed)
+    }
+
+    /// Applies a command's effect on the kill state.
+    pub fn apply(&self, command: ControlCommand) {
+        match command {
+            ControlCommand::Kill { flatten } => self.trigger(flatten),
+            ControlCommand::Resume => self.resume(),
+            ControlCommand::KillOrder(_) => {}
+        }
+    }
+}
+
+/// Listens for operator commands on a TCP port on its own thread.
+///
+/// Every command first updates the `KillSwitch`, then runs `on_command` on
+/// the control thread, so a handler holding its own route to the exchange
+/// can cancel orders without waiting for the trading loop.
+pub struct ControlServer {
+    /// Address the server is bound to.
+    local_addr: SocketAddr,
+}
+
+impl ControlServer {
+    /// Binds the control port and starts serving commands.
+    ///
+    /// # Arguments
+    /// * `addr` - Address to listen on, e.g. `127.0.0.1:9100` (port 0 picks
+    ///   a free port)
+    /// * `kill_switch` - Kill state shared with the trading loop
+    /// * `on_command` - Handler run on the control thread for each command
+    ///
+    /// # Returns
+    /// The running server, or the error binding the port
+    pub fn spawn<F>(addr: &str, kill_switch: Arc<KillSwitch>, mut on_command: F) -> io::Result<Self>
+    where
+        F: FnMut(ControlCommand) + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let Ok(mut writer) = stream.try_clone() else { continue };
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else { break };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let reply = match ControlCommand::parse(&line) {
+                        Ok(command) => {
+                            kill_switch.apply(command);
+                            on_command(command);
+                            "ok".to_string()
+                        }
+                        Err(e) => format!("error: {}", e),
+                    };
+                    if writeln!(writer, "{}", reply).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self { local_addr })
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_update() {
+        let text = "# widen\nhalf_spread = 80\n\nsignal_threshold=0.5  # stricter\nmax_loss = 5000\n";
+        let update = ParamUpdate::parse(text).unwrap();
+
+        assert_eq!(update.half_spread, Some(80));
+        assert_eq!(update.signal_threshold, Some(0.5));
+        assert_eq!(update.max_loss, Some(5000));
+        assert_eq!(update.base_qty, None);
+        assert!(!update.is_empty());
+        assert!(ParamUpdate::parse("  # nothing\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_control_commands() {
+        assert_eq!(ControlCommand::parse("kill").unwrap(), ControlCommand::Kill { flatten: false });
+        assert_eq!(ControlCommand::parse(" KILL  Flatten ").unwrap(), ControlCommand::Kill { flatten: true });
+        assert_eq!(ControlCommand::parse("kill order 42").unwrap(), ControlCommand::KillOrder(42));
+        assert_eq!(ControlCommand::parse("resume").unwrap(), ControlCommand::Resume);
+        for line in ["", "stop", "kill order", "kill order x", "kill everything"] {
+            assert_eq!(ControlCommand::parse(line).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_kill_switch_flattens_once_per_kill() {
+        let switch = KillSwitch::new();
+        assert!(!switch.is_killed());
+
+        switch.apply(ControlCommand::Kill { flatten: true });
+        assert!(switch.is_killed());
+        assert!(switch.take_flatten());
+        assert!(!switch.take_flatten());
+
+        // A single order kill leaves the session alone
+        switch.apply(ControlCommand::Resume);
+        switch.apply(ControlCommand::KillOrder(7));
+        assert!(!switch.is_killed());
+
+        // Resuming drops a flatten not yet taken
+        switch.apply(ControlCommand::Kill { flatten: true });
+        switch.apply(ControlCommand::Resume);
+        assert!(!switch.take_flatten());
+        assert_eq!(switch.kills(), 2);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_lines() {
+        assert!(ParamUpdate::parse("half_spread").is_err());
+        assert!(ParamUpdate::parse("half_spread = wide").is_err());
+        assert!(ParamUpdate::parse("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let update = ParamUpdate::parse("half_spread = 80\nmax_position = 300\nmax_order_qty = 25").unwrap();
+
+        let mm = update.apply_to_market_maker(MarketMakerConfig::new(1));
+        assert_eq!(mm.half_spread, 80);
+        assert_eq!(mm.max_position, 300);
+        assert_eq!(mm.base_qty, MarketMakerConfig::default().base_qty);
+
+        let lt = update.apply_to_liquidity_taker(LiquidityTakerConfig::new(1));
+        assert_eq!(lt.max_position, 300);
+
+        let limits = update.apply_to_risk_limits(RiskLimits::default());
+        assert_eq!(limits.max_order_qty, 25);
+        assert_eq!(limits.max_position, 300);
+        assert_eq!(limits.max_loss, RiskLimits::default().max_loss);
+    }
+
+    #[test]
+    fn test_control_file_reports_changes_once() {
+        let path = std::env::temp_dir().join(format!("control_file_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut control = ControlFile::new(&path);
+
+        assert!(control.poll().unwrap().is_none());
+
+        fs::write(&path, "half_spread = 70\n").unwrap();
+        let update = control.poll().unwrap().unwrap();
+        assert_eq!(update.half_spread, Some(70));
+        assert!(control.poll().unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,9 +3,20 @@
 //! This module provides a feature engine that computes trading signals from
 //! market data. It calculates fair value estimates, spread metrics, order book
 //! imbalance, and generates trade signals based on these features.
+//!
+//! Degenerate books are handled explicitly rather than leaking through the
+//! arithmetic. A locked book (bid == ask) is a real price: it yields a valid
+//! mid with zero spread and a zero trade signal. A crossed book (bid > ask)
+//! is inconsistent data and, by default, invalidates the ticker's features
+//! until a sane update arrives. Any ratio with a zero denominator is zero,
+//! never NaN or infinity.
+//!
+//! Fair value comes from an EMA of the mid by default. A ticker can be given
+//! its own [`FairValueModel`] instead, either one of the built-in models or a
+//! user-supplied one, which is then asked for the fair value on every update.
 
 use common::{Price, TickerId};
-use crate::market_data::BBO;
+use crate::market_data::{MidRounding, BBO};
 use std::collections::HashMap;
 
 /// Trading features computed for a single ticker.
@@ -16,16 +27,25 @@ use std::collections::HashMap;
 pub struct TickerFeatures {
     /// The ticker this feature set applies to.
     pub ticker_id: TickerId,
-    /// Estimated fair value using EMA smoothing of mid prices.
+    /// Estimated fair value: an EMA of mid prices unless the ticker has its
+    /// own fair value model.
     pub fair_value: Price,
     /// Current bid-ask spread.
     pub spread: Price,
-    /// Current mid price ((bid + ask) / 2).
+    /// Current mid price ((bid + ask) / 2, rounded per `FeatureParams::mid_rounding`).
     pub mid_price: Price,
     /// Order book imbalance: -1.0 to 1.0, positive = more bids (buy pressure).
     pub imbalance: f64,
     /// Trade signal: -1.0 to 1.0, positive = buy signal.
     pub trade_signal: f64,
+    /// EMA of absolute mid-price changes, in price units.
+    pub volatility: f64,
+    /// Number of BBO updates these features were computed from (0 if never
+    /// updated), identifying the snapshot a strategy acts on.
+    pub version: u64,
+    /// True if the last BBO update had both sides present and was not
+    /// crossed, so the fields above reflect a usable book.
+    pub has_bbo: bool,
 }
 
 impl TickerFeatures {
@@ -38,13 +58,150 @@ impl TickerFeatures {
             mid_price: 0,
             imbalance: 0.0,
             trade_signal: 0.0,
+            volatility: 0.0,
+            version: 0,
+            has_bbo: false,
         }
     }
 
-    /// Returns true if the features have been initialized with valid data.
+    /// Returns true if the features were computed from a BBO with both sides
+    /// present. Prices themselves may be zero or negative.
     #[inline]
     pub fn is_valid(&self) -> bool {
-        self.mid_price > 0 && self.fair_value > 0
+        self.has_bbo
+    }
+}
+
+/// How a crossed book (bid above ask) affects a ticker's features.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CrossedBookPolicy {
+    /// Invalidate the features: mid, spread, imbalance and signal are zeroed
+    /// so `is_valid` is false, while fair value and volatility are kept.
+    #[default]
+    Invalidate,
+    /// Ignore the update and keep the last features computed from a sane book.
+    Hold,
+}
+
+/// Smoothing parameters used when computing features for a ticker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureParams {
+    /// EMA smoothing factor for fair value (0.0 to 1.0).
+    pub fair_value_alpha: f64,
+    /// EMA smoothing factor for volatility (0.0 to 1.0).
+    pub volatility_alpha: f64,
+    /// Rounding of mids that fall halfway between two ticks.
+    pub mid_rounding: MidRounding,
+    /// What to do with an update whose bid is above its ask.
+    pub crossed_book: CrossedBookPolicy,
+}
+
+impl Default for FeatureParams {
+    fn default() -> Self {
+        Self {
+            fair_value_alpha: FeatureEngine::DEFAULT_FAIR_VALUE_ALPHA,
+            volatility_alpha: FeatureEngine::DEFAULT_VOLATILITY_ALPHA,
+            mid_rounding: MidRounding::Nearest,
+            crossed_book: CrossedBookPolicy::Invalidate,
+        }
+    }
+}
+
+impl FeatureParams {
+    /// Creates parameters with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder method to set the fair value alpha (clamped to [0.0, 1.0]).
+    pub fn with_fair_value_alpha(mut self, alpha: f64) -> Self {
+        self.fair_value_alpha = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Builder method to set the volatility alpha (clamped to [0.0, 1.0]).
+    pub fn with_volatility_alpha(mut self, alpha: f64) -> Self {
+        self.volatility_alpha = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Builder method to set how halfway mids are rounded.
+    pub fn with_mid_rounding(mut self, rounding: MidRounding) -> Self {
+        self.mid_rounding = rounding;
+        self
+    }
+
+    /// Builder method to set how crossed books are handled.
+    pub fn with_crossed_book(mut self, policy: CrossedBookPolicy) -> Self {
+        self.crossed_book = policy;
+        self
+    }
+}
+
+/// Estimates a ticker's fair value from its top of book.
+///
+/// Implement this to plug a custom fair value into the feature engine with
+/// `FeatureEngine::set_fair_value_model`.
+pub trait FairValueModel: Send {
+    /// Updates the model with a BBO and returns the new fair value.
+    ///
+    /// Only called with valid, uncrossed BBOs.
+    fn update(&mut self, bbo: &BBO) -> Price;
+}
+
+/// EMA of the mid price, the model the feature engine uses by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmaMidModel {
+    /// EMA smoothing factor (0.0 to 1.0).
+    alpha: f64,
+    /// Current estimate, 0 before the first update.
+    fair_value: Price,
+}
+
+impl EmaMidModel {
+    /// Creates a model with the given smoothing factor (clamped to [0.0, 1.0]).
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            fair_value: 0,
+        }
+    }
+
+    /// Blends an observation into an EMA; a zero EMA starts at the observation.
+    #[inline]
+    fn blend(ema: Price, observation: Price, alpha: f64) -> Price {
+        if ema == 0 {
+            return observation;
+        }
+        (alpha * observation as f64 + (1.0 - alpha) * ema as f64).round() as Price
+    }
+}
+
+impl Default for EmaMidModel {
+    fn default() -> Self {
+        Self::new(FeatureEngine::DEFAULT_FAIR_VALUE_ALPHA)
+    }
+}
+
+impl FairValueModel for EmaMidModel {
+    fn update(&mut self, bbo: &BBO) -> Price {
+        if let Some(mid) = bbo.mid_price() {
+            self.fair_value = Self::blend(self.fair_value, mid, self.alpha);
+        }
+        self.fair_value
+    }
+}
+
+/// Size-weighted mid: each price weighted by the opposite side's quantity.
+///
+/// Leans toward the ask when bids are heavier and toward the bid when asks
+/// are, so the fair value moves in the direction of the book's pressure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MicropriceModel;
+
+impl FairValueModel for MicropriceModel {
+    fn update(&mut self, bbo: &BBO) -> Price {
+        bbo.microprice().unwrap_or(0)
     }
 }
 
@@ -56,9 +213,13 @@ impl TickerFeatures {
 pub struct FeatureEngine {
     /// Per-ticker feature state.
     features: HashMap<TickerId, TickerFeatures>,
-    /// EMA smoothing factor for fair value calculation (0.0 to 1.0).
-    /// Higher values give more weight to recent observations.
-    fair_value_alpha: f64,
+    /// Parameters used for tickers without an override.
+    /// Higher alphas give more weight to recent observations.
+    default_params: FeatureParams,
+    /// Per-ticker parameter overrides.
+    ticker_params: HashMap<TickerId, FeatureParams>,
+    /// Per-ticker fair value models replacing the built-in mid EMA.
+    models: HashMap<TickerId, Box<dyn FairValueModel>>,
 }
 
 impl Default for FeatureEngine {
@@ -72,11 +233,21 @@ impl FeatureEngine {
     /// 0.1 gives ~90% weight to historical values, providing good smoothing.
     const DEFAULT_FAIR_VALUE_ALPHA: f64 = 0.1;
 
+    /// Default EMA alpha for volatility calculation.
+    const DEFAULT_VOLATILITY_ALPHA: f64 = 0.05;
+
     /// Creates a new FeatureEngine with default parameters.
     pub fn new() -> Self {
+        Self::with_params(FeatureParams::default())
+    }
+
+    /// Creates a new FeatureEngine with the given default parameters.
+    pub fn with_params(params: FeatureParams) -> Self {
         Self {
             features: HashMap::new(),
-            fair_value_alpha: Self::DEFAULT_FAIR_VALUE_ALPHA,
+            default_params: params,
+            ticker_params: HashMap::new(),
+            models: HashMap::new(),
         }
     }
 
@@ -86,20 +257,22 @@ impl FeatureEngine {
     /// * `fair_value_alpha` - EMA smoothing factor (0.0 to 1.0).
     ///   Higher values make fair value more responsive to recent prices.
     pub fn with_alpha(fair_value_alpha: f64) -> Self {
-        Self {
-            features: HashMap::new(),
-            fair_value_alpha: fair_value_alpha.clamp(0.0, 1.0),
-        }
+        Self::with_params(FeatureParams::default().with_fair_value_alpha(fair_value_alpha))
     }
 
     /// Processes a BBO update and recalculates features for the ticker.
     ///
     /// This method:
     /// 1. Calculates the mid price from bid/ask
-    /// 2. Updates fair value using EMA
+    /// 2. Updates fair value from the ticker's model, or an EMA of the mid,
+    ///    and volatility using an EMA
     /// 3. Calculates spread and order book imbalance
     /// 4. Generates a trade signal based on fair value vs mid price
     ///
+    /// Smoothing uses the ticker's own parameters if set, otherwise the defaults.
+    /// BBOs missing a side are ignored. A locked BBO gives zero spread and a
+    /// valid mid; a crossed one is handled per `FeatureParams::crossed_book`.
+    ///
     /// # Arguments
     /// * `ticker_id` - The ticker that received the update
     /// * `bbo` - The updated best bid/offer
@@ -109,26 +282,51 @@ impl FeatureEngine {
             return;
         }
 
+        let params = self.ticker_params(ticker_id);
+        if bbo.bid_price > bbo.ask_price && params.crossed_book == CrossedBookPolicy::Hold {
+            return;
+        }
+
         // Get or create feature entry for this ticker
         let features = self.features
             .entry(ticker_id)
             .or_insert_with(|| TickerFeatures::new(ticker_id));
 
+        if bbo.bid_price > bbo.ask_price {
+            // Crossed: nothing derived from this book can be trusted, and
+            // the next sane update is not counted as a jump from it
+            features.has_bbo = false;
+            features.mid_price = 0;
+            features.spread = 0;
+            features.imbalance = 0.0;
+            features.trade_signal = 0.0;
+            features.version += 1;
+            return;
+        }
+
         // 1. Calculate mid price
-        let mid_price = (bbo.bid_price + bbo.ask_price) / 2;
+        let Some(mid_price) = bbo.mid_price_rounded(params.mid_rounding) else {
+            return;
+        };
+        let prev_mid = features.has_bbo.then_some(features.mid_price);
         features.mid_price = mid_price;
+        features.has_bbo = true;
 
-        // 2. Update fair value using EMA
+        // 2. Update fair value from the ticker's model, or an EMA of the mid:
         // fair_value = alpha * mid_price + (1 - alpha) * fair_value
-        if features.fair_value == 0 {
-            // First update - initialize fair value to current mid
-            features.fair_value = mid_price;
-        } else {
-            // EMA update: new_value = alpha * observation + (1 - alpha) * old_value
-            let mid_f64 = mid_price as f64;
-            let fv_f64 = features.fair_value as f64;
-            let new_fv = self.fair_value_alpha * mid_f64 + (1.0 - self.fair_value_alpha) * fv_f64;
-            features.fair_value = new_fv.round() as Price;
+        // The first update initializes it to the mid; a seeded fair value is
+        // blended into like any other
+        features.fair_value = match self.models.get_mut(&ticker_id) {
+            Some(model) => model.update(bbo),
+            None => EmaMidModel::blend(features.fair_value, mid_price, params.fair_value_alpha),
+        };
+
+        // Volatility: EMA of absolute mid-price changes, once there is a
+        // previous mid to compare against
+        if let Some(prev_mid) = prev_mid {
+            let alpha = params.volatility_alpha;
+            let change = (mid_price - prev_mid).abs() as f64;
+            features.volatility = alpha * change + (1.0 - alpha) * features.volatility;
         }
 
         // 3. Calculate spread
@@ -139,6 +337,7 @@ impl FeatureEngine {
 
         // 5. Generate trade signal
         features.trade_signal = Self::calculate_trade_signal_from_features(features);
+        features.version += 1;
     }
 
     /// Returns the current features for a ticker.
@@ -252,23 +451,114 @@ impl FeatureEngine {
         }
     }
 
+    /// Seeds a ticker's fair value from a known reference, e.g. on restart.
+    ///
+    /// The first BBO update then blends into the seed instead of replacing
+    /// it, so strategies quote around the seed without waiting for the EMA
+    /// to converge. Seeding does not change the ticker's mid price.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker to seed
+    /// * `fair_value` - Fair value to start from (ignored if not positive)
+    pub fn seed(&mut self, ticker_id: TickerId, fair_value: Price) {
+        if fair_value <= 0 {
+            return;
+        }
+        self.features
+            .entry(ticker_id)
+            .or_insert_with(|| TickerFeatures::new(ticker_id))
+            .fair_value = fair_value;
+    }
+
+    /// Seeds a ticker's fair value and volatility from known references.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker to seed
+    /// * `fair_value` - Fair value to start from (ignored if not positive)
+    /// * `volatility` - Volatility to start from, in price units
+    pub fn seed_with_volatility(&mut self, ticker_id: TickerId, fair_value: Price, volatility: f64) {
+        self.seed(ticker_id, fair_value);
+        if let Some(features) = self.features.get_mut(&ticker_id) {
+            features.volatility = volatility.max(0.0);
+        }
+    }
+
     /// Clears all feature data.
     pub fn clear(&mut self) {
         self.features.clear();
     }
 
-    /// Returns the current fair value alpha (EMA smoothing factor).
+    /// Returns the default fair value alpha (EMA smoothing factor).
     #[inline]
     pub fn fair_value_alpha(&self) -> f64 {
-        self.fair_value_alpha
+        self.default_params.fair_value_alpha
     }
 
-    /// Sets a new fair value alpha (EMA smoothing factor).
+    /// Sets a new default fair value alpha (EMA smoothing factor).
+    ///
+    /// Tickers with their own parameters are not affected.
     ///
     /// # Arguments
     /// * `alpha` - New alpha value, will be clamped to [0.0, 1.0]
     pub fn set_fair_value_alpha(&mut self, alpha: f64) {
-        self.fair_value_alpha = alpha.clamp(0.0, 1.0);
+        self.default_params.fair_value_alpha = alpha.clamp(0.0, 1.0);
+    }
+
+    /// Returns the parameters used for tickers without an override.
+    #[inline]
+    pub fn default_params(&self) -> &FeatureParams {
+        &self.default_params
+    }
+
+    /// Sets the parameters used for tickers without an override.
+    pub fn set_default_params(&mut self, params: FeatureParams) {
+        self.default_params = params;
+    }
+
+    /// Sets smoothing parameters for a specific ticker.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker to configure
+    /// * `params` - Parameters to use instead of the defaults
+    pub fn set_ticker_params(&mut self, ticker_id: TickerId, params: FeatureParams) {
+        self.ticker_params.insert(ticker_id, params);
+    }
+
+    /// Removes a ticker's parameter override, reverting it to the defaults.
+    pub fn clear_ticker_params(&mut self, ticker_id: TickerId) {
+        self.ticker_params.remove(&ticker_id);
+    }
+
+    /// Sets the fair value model for a specific ticker.
+    ///
+    /// The model replaces the built-in mid EMA and its `fair_value_alpha`, so
+    /// seeding no longer affects the ticker's fair value.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker to configure
+    /// * `model` - Model asked for the fair value on every BBO update
+    pub fn set_fair_value_model(&mut self, ticker_id: TickerId, model: Box<dyn FairValueModel>) {
+        self.models.insert(ticker_id, model);
+    }
+
+    /// Removes a ticker's fair value model, reverting it to the mid EMA.
+    pub fn clear_fair_value_model(&mut self, ticker_id: TickerId) {
+        self.models.remove(&ticker_id);
+    }
+
+    /// Returns true if a ticker has its own fair value model.
+    #[inline]
+    pub fn has_fair_value_model(&self, ticker_id: TickerId) -> bool {
+        self.models.contains_key(&ticker_id)
+    }
+
+    /// Returns the effective parameters for a ticker.
+    #[inline]
+    pub fn ticker_params(&self, ticker_id: TickerId) -> FeatureParams {
+        self.ticker_params
+            .get(&ticker_id)
+            .copied()
+            .unwrap_or(self.default_params)
     }
 }
 
@@ -283,6 +573,7 @@ mod tests {
             bid_qty,
             ask_price,
             ask_qty,
+            stale: false,
         }
     }
 
@@ -396,12 +687,28 @@ mod tests {
             bid_qty: 0,
             ask_price: 102,
             ask_qty: 50,
+            stale: false,
         };
 
         engine.on_bbo_update(ticker_id, &bbo);
         assert!(engine.get_features(ticker_id).is_none());
     }
 
+    #[test]
+    fn test_version_counts_bbo_updates() {
+        let mut engine = FeatureEngine::new();
+        engine.on_bbo_update(1, &make_bbo(99, 50, 101, 50));
+        assert_eq!(engine.get_features(1).unwrap().version, 1);
+
+        // Unchanged quotes still produce a new snapshot
+        engine.on_bbo_update(1, &make_bbo(99, 50, 101, 50));
+        assert_eq!(engine.get_features(1).unwrap().version, 2);
+
+        engine.on_bbo_update(2, &make_bbo(99, 50, 101, 50));
+        assert_eq!(engine.get_features(2).unwrap().version, 1);
+        assert_eq!(engine.get_features(1).unwrap().version, 2);
+    }
+
     #[test]
     fn test_trade_signal_fair_value_above_mid() {
         let mut engine = FeatureEngine::with_alpha(0.1);
@@ -509,4 +816,248 @@ mod tests {
         engine.set_fair_value_alpha(2.0);
         assert!((engine.fair_value_alpha() - 1.0).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_feature_params_builder_clamps() {
+        let params = FeatureParams::new()
+            .with_fair_value_alpha(1.5)
+            .with_volatility_alpha(-0.5);
+        assert!((params.fair_value_alpha - 1.0).abs() < f64::EPSILON);
+        assert!(params.volatility_alpha.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_per_ticker_params() {
+        let mut engine = FeatureEngine::with_alpha(0.1);
+        engine.set_ticker_params(2, FeatureParams::new().with_fair_value_alpha(0.5));
+
+        // Ticker 1 falls back to the defaults
+        assert!((engine.ticker_params(1).fair_value_alpha - 0.1).abs() < f64::EPSILON);
+        assert!((engine.ticker_params(2).fair_value_alpha - 0.5).abs() < f64::EPSILON);
+
+        // Same price path on both tickers: mid 1000, then mid 1100
+        let initial = make_bbo(999, 100, 1001, 100);
+        let moved = make_bbo(1099, 100, 1101, 100);
+        for ticker_id in [1, 2] {
+            engine.on_bbo_update(ticker_id, &initial);
+            engine.on_bbo_update(ticker_id, &moved);
+        }
+
+        // 0.1 * 1100 + 0.9 * 1000 = 1010; 0.5 * 1100 + 0.5 * 1000 = 1050
+        assert_eq!(engine.get_features(1).unwrap().fair_value, 1010);
+        assert_eq!(engine.get_features(2).unwrap().fair_value, 1050);
+
+        // Clearing the override reverts ticker 2 to the defaults
+        engine.clear_ticker_params(2);
+        assert_eq!(engine.ticker_params(2), *engine.default_params());
+    }
+
+    #[test]
+    fn test_volatility_tracks_mid_changes() {
+        let params = FeatureParams::new().with_volatility_alpha(0.5);
+        let mut engine = FeatureEngine::with_params(params);
+
+        engine.on_bbo_update(1, &make_bbo(999, 100, 1001, 100));
+        assert_eq!(engine.get_features(1).unwrap().volatility, 0.0);
+
+        // |1100 - 1000| = 100, 0.5 * 100 + 0.5 * 0 = 50
+        engine.on_bbo_update(1, &make_bbo(1099, 100, 1101, 100));
+        assert!((engine.get_features(1).unwrap().volatility - 50.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_microprice_model_leans_with_book_pressure() {
+        let mut engine = FeatureEngine::new();
+        engine.set_fair_value_model(1, Box::new(MicropriceModel));
+
+        // Balanced book: microprice is the mid
+        engine.on_bbo_update(1, &make_bbo(1000, 50, 1010, 50));
+        assert_eq!(engine.get_features(1).unwrap().fair_value, 1005);
+
+        // Heavier bids pull it toward the ask: (1000*25 + 1010*75) / 100
+        engine.on_bbo_update(1, &make_bbo(1000, 75, 1010, 25));
+        let features = engine.get_features(1).unwrap();
+        assert_eq!(features.fair_value, 1007);
+        assert!(features.trade_signal > 0.0);
+
+        // Heavier asks pull it toward the bid
+        engine.on_bbo_update(1, &make_bbo(1000, 25, 1010, 75));
+        assert_eq!(engine.get_features(1).unwrap().fair_value, 1002);
+    }
+
+    #[test]
+    fn test_custom_fair_value_model_invoked_per_update() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        /// Values the book at its best bid and counts its updates.
+        struct BidModel(Arc<AtomicUsize>);
+
+        impl FairValueModel for BidModel {
+            fn update(&mut self, bbo: &BBO) -> Price {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                bbo.bid_price
+            }
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut engine = FeatureEngine::new();
+        engine.set_fair_value_model(1, Box::new(BidModel(calls.clone())));
+        assert!(engine.has_fair_value_model(1));
+
+        engine.on_bbo_update(1, &make_bbo(1000, 50, 1010, 50));
+        engine.on_bbo_update(1, &make_bbo(1002, 50, 1012, 50));
+        // Crossed and one-sided books never reach the model
+        engine.on_bbo_update(1, &make_bbo(1020, 50, 1010, 50));
+        engine.on_bbo_update(1, &make_bbo(1000, 50, INVALID_PRICE, 0));
+        engine.on_bbo_update(1, &make_bbo(1004, 50, 1014, 50));
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        assert_eq!(engine.get_features(1).unwrap().fair_value, 1004);
+
+        // Other tickers keep the default EMA
+        engine.on_bbo_update(2, &make_bbo(1000, 50, 1010, 50));
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // Clearing the model reverts the ticker to the EMA of the mid:
+        // 0.1 * 1015 + 0.9 * 1004
+        engine.clear_fair_value_model(1);
+        engine.on_bbo_update(1, &make_bbo(1010, 50, 1020, 50));
+        assert_eq!(engine.get_features(1).unwrap().fair_value, 1005);
+    }
+
+    #[test]
+    fn test_ema_mid_model_matches_default_engine() {
+        let bbos = [
+            make_bbo(1000, 50, 1010, 50),
+            make_bbo(1040, 50, 1050, 50),
+            make_bbo(990, 50, 1000, 50),
+        ];
+        let mut default = FeatureEngine::new();
+        let mut modelled = FeatureEngine::new();
+        modelled.set_fair_value_model(1, Box::new(EmaMidModel::default()));
+        for bbo in &bbos {
+            default.on_bbo_update(1, bbo);
+            modelled.on_bbo_update(1, bbo);
+            assert_eq!(
+                modelled.get_features(1).unwrap().fair_value,
+                default.get_features(1).unwrap().fair_value
+            );
+        }
+    }
+
+    #[test]
+    fn test_seed_centers_first_quotes_on_seed() {
+        use crate::strategies::market_maker::MarketMaker;
+        use crate::strategies::StrategyAction;
+
+        let mut engine = FeatureEngine::new();
+        engine.seed_with_volatility(1, 10000, 5.0);
+        assert!(!engine.get_features(1).unwrap().is_valid());
+
+        // First mid is well away from the seed: 0.1 * 10100 + 0.9 * 10000
+        engine.on_bbo_update(1, &make_bbo(10090, 50, 10110, 50));
+        let features = engine.get_features(1).unwrap();
+        assert_eq!(features.mid_price, 10100);
+        assert_eq!(features.fair_value, 10010);
+        assert!((features.volatility - 5.0).abs() < f64::EPSILON);
+
+        let mut mm = MarketMaker::for_ticker(1);
+        match mm.on_features(features) {
+            StrategyAction::Quote(pair) => {
+                let (bid, ask) = (pair.bid.unwrap().price, pair.ask.unwrap().price);
+                assert_eq!((bid + ask) / 2, 10010);
+            }
+            _ => panic!("Expected Quote action"),
+        }
+
+        // Non-positive seeds are ignored
+        engine.seed(2, 0);
+        assert!(engine.get_features(2).is_none());
+    }
+
+    #[test]
+    fn test_degenerate_book_matrix() {
+        // (bbo, updates features?, mid, spread, imbalance)
+        let cases = [
+            // Zero-size sides never reach the features
+            (make_bbo(9990, 0, 10010, 50), false, 0, 0, 0.0),
+            (make_bbo(9990, 50, 10010, 0), false, 0, 0, 0.0),
+            (make_bbo(9990, 0, 10010, 0), false, 0, 0, 0.0),
+            // Locked: a real price with no spread
+            (make_bbo(10000, 30, 10000, 10), true, 10000, 0, 0.5),
+            // Crossed: invalidated
+            (make_bbo(10010, 50, 9990, 50), true, 0, 0, 0.0),
+        ];
+
+        for (bbo, updates, mid, spread, imbalance) in cases {
+            let mut engine = FeatureEngine::new();
+            engine.on_bbo_update(1, &bbo);
+            let Some(features) = engine.get_features(1) else {
+                assert!(!updates, "{:?} should update features", bbo);
+                continue;
+            };
+            assert!(updates, "{:?} should be ignored", bbo);
+            assert_eq!(features.mid_price, mid);
+            assert_eq!(features.spread, spread);
+            assert!((features.imbalance - imbalance).abs() < f64::EPSILON);
+            assert_eq!(features.trade_signal, 0.0);
+            assert!(features.imbalance.is_finite() && features.volatility.is_finite());
+            assert_eq!(features.is_valid(), mid > 0);
+        }
+
+        assert_eq!(FeatureEngine::calculate_imbalance(&make_bbo(9990, 0, 10010, 0)), 0.0);
+    }
+
+    #[test]
+    fn test_zero_and_negative_prices_are_valid() {
+        let mut engine = FeatureEngine::new();
+        engine.on_bbo_update(1, &make_bbo(-110, 50, -90, 50));
+        let features = engine.get_features(1).unwrap();
+        assert!(features.is_valid());
+        assert_eq!((features.mid_price, features.spread), (-100, 20));
+
+        engine.on_bbo_update(1, &make_bbo(-10, 50, 10, 50));
+        let features = engine.get_features(1).unwrap();
+        assert!(features.is_valid());
+        assert_eq!(features.mid_price, 0);
+        assert!(features.volatility > 0.0);
+    }
+
+    #[test]
+    fn test_crossed_book_invalidates_until_sane_update() {
+        let mut engine = FeatureEngine::new();
+        engine.on_bbo_update(1, &make_bbo(9990, 50, 10010, 50));
+        engine.on_bbo_update(1, &make_bbo(10010, 50, 9990, 50));
+        let features = engine.get_features(1).unwrap();
+        assert!(!features.is_valid());
+        assert_eq!(features.fair_value, 10000);
+        assert_eq!(features.version, 2);
+
+        // Recovery does not count the crossed book as a price move
+        engine.on_bbo_update(1, &make_bbo(10090, 50, 10110, 50));
+        let features = engine.get_features(1).unwrap();
+        assert!(features.is_valid());
+        assert_eq!(features.mid_price, 10100);
+        assert_eq!(features.spread, 20);
+        assert_eq!(features.volatility, 0.0);
+    }
+
+    #[test]
+    fn test_crossed_book_hold_keeps_last_features() {
+        let params = FeatureParams::new().with_crossed_book(CrossedBookPolicy::Hold);
+        let mut engine = FeatureEngine::with_params(params);
+        engine.on_bbo_update(1, &make_bbo(9990, 60, 10010, 40));
+        let before = engine.get_features(1).unwrap().clone();
+
+        engine.on_bbo_update(1, &make_bbo(10010, 50, 9990, 50));
+        let after = engine.get_features(1).unwrap();
+        assert_eq!(after.mid_price, before.mid_price);
+        assert_eq!(after.spread, 20);
+        assert_eq!(after.imbalance, before.imbalance);
+        assert_eq!(after.version, before.version);
+
+        // A crossed first update leaves nothing behind
+        engine.on_bbo_update(2, &make_bbo(10010, 50, 9990, 50));
+        assert!(engine.get_features(2).is_none());
+    }
 }
@@ -5,3 +5,5 @@ pub mod position;
 pub mod risk;
 pub mod features;
 pub mod strategies;
+pub mod control;
+pub mod report;
@@ -6,22 +6,29 @@
 //! - FeatureEngine: Signal generation
 //! - RiskManager: Pre-trade risk checks
 //! - Trading strategies (MarketMaker or LiquidityTaker)
+//! - ControlFile: optional live strategy/risk parameter updates
+//! - ControlServer: optional operator kill commands on a TCP port
 
 use clap::{Parser, ValueEnum};
-use common::time::now_nanos;
-use common::Side;
-use exchange::protocol::ClientResponseType;
+use common::time::{monotonic_nanos, ClockSource};
+use common::{InstrumentRegistry, Side, TickerId};
+use exchange::protocol::{ClientResponseType, MarketUpdateType};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use trading::control::{ControlCommand, ControlFile, ControlServer, KillSwitch};
 use trading::features::FeatureEngine;
-use trading::market_data::MarketDataReceiver;
+use trading::market_data::{
+    MarketDataReceiver, RejoinConfig, DEFAULT_DEDUP_WINDOW, DEFAULT_RECV_BUFFER_SIZE,
+};
 use trading::order_gateway::OrderGateway;
-use trading::position::PositionKeeper;
-use trading::risk::{RiskLimits, RiskManager};
+use trading::position::{
+    build_flatten_order, FundingRates, MarkSource, PortfolioSummary, PositionKeeper,
+};
+use trading::risk::{PressureAdjustment, RiskCheckResult, RiskLimits, RiskManager};
 use trading::strategies::{
-    LiquidityTaker, LiquidityTakerConfig, MarketMaker, MarketMakerConfig, StrategyAction,
+    CycleFills, LiquidityTaker, LiquidityTakerConfig, MarketMaker, MarketMakerConfig, StrategyAction,
 };
 
 /// Trading strategy to use
@@ -33,6 +40,27 @@ enum Strategy {
     LiquidityTaker,
 }
 
+/// Reference price for unrealized P&L
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Mark {
+    /// Midpoint of the best bid and ask
+    Mid,
+    /// Most recent trade print
+    LastTrade,
+    /// Size-weighted mid of the best bid and ask
+    Microprice,
+}
+
+impl From<Mark> for MarkSource {
+    fn from(mark: Mark) -> Self {
+        match mark {
+            Mark::Mid => MarkSource::Mid,
+            Mark::LastTrade => MarkSource::LastTrade,
+            Mark::Microprice => MarkSource::Microprice,
+        }
+    }
+}
+
 /// Trading client for low-latency trading
 #[derive(Parser, Debug)]
 #[command(name = "trading")]
@@ -70,6 +98,10 @@ struct Args {
     #[arg(short, long, default_value_t = 1)]
     client_id: u32,
 
+    /// Token to log in with, if the exchange requires login
+    #[arg(long)]
+    auth_token: Option<u64>,
+
     /// Maximum order quantity per order
     #[arg(long, default_value_t = 100)]
     max_order_qty: u32,
@@ -78,6 +110,15 @@ struct Args {
     #[arg(long, default_value_t = 1000)]
     max_position: i64,
 
+    /// Minimum order notional in cents (0 = no minimum)
+    #[arg(long, default_value_t = 0)]
+    min_notional: i64,
+
+    /// Scale the position limit by book imbalance by up to this fraction:
+    /// looser with the pressure, tighter against it (0 = disabled)
+    #[arg(long, default_value_t = 0.0)]
+    pressure_limit_scale: f64,
+
     /// Maximum loss in cents (triggers risk shutdown)
     #[arg(long, default_value_t = 100000)]
     max_loss: i64,
@@ -89,6 +130,259 @@ struct Args {
     /// Signal threshold for liquidity taker
     #[arg(long, default_value_t = 0.3)]
     signal_threshold: f64,
+
+    /// How far past the opposite threshold the liquidity taker's signal must
+    /// go to reverse its last take (0 = disabled)
+    #[arg(long, default_value_t = 0.0)]
+    hysteresis: f64,
+
+    /// Let the liquidity taker take at most once per features snapshot
+    #[arg(long, default_value_t = false)]
+    fresh_features: bool,
+
+    /// Furthest the liquidity taker's order price may go past the mid, in
+    /// basis points; orders the touch already puts beyond it are not sent
+    /// (0 = no limit)
+    #[arg(long, default_value_t = 0)]
+    max_slippage_bps: u32,
+
+    /// Maximum age of market maker quotes in milliseconds before requoting (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    max_quote_age_ms: u64,
+
+    /// Fraction the market maker tightens its half spread by when up
+    /// `--pnl-scale` cents, and widens it by when down (0 = disabled)
+    #[arg(long, default_value_t = 0.0)]
+    pnl_sensitivity: f64,
+
+    /// P&L in cents at which the full P&L spread adjustment applies
+    #[arg(long, default_value_t = 10_000)]
+    pnl_scale: i64,
+
+    /// Pull the market maker's quotes after a cycle with fills and requote
+    /// once from the net position
+    #[arg(long, default_value_t = false)]
+    requote_after_fills: bool,
+
+    /// Replace a fully filled market maker quote immediately instead of on
+    /// the next features update
+    #[arg(long, default_value_t = false)]
+    requote_on_fill: bool,
+
+    /// Displayed quantity at the quoted price at which market maker quotes
+    /// keep their size; thinner levels get larger quotes, deeper ones
+    /// smaller (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    depth_reference_qty: u32,
+
+    /// Smallest factor depth can scale a market maker quote by
+    #[arg(long, default_value_t = 0.5)]
+    min_depth_factor: f64,
+
+    /// Largest factor depth can scale a market maker quote by
+    #[arg(long, default_value_t = 2.0)]
+    max_depth_factor: f64,
+
+    /// Widest spread in cents, ask minus bid, at which the market maker
+    /// meets its quote obligation (0 = obligation not tracked)
+    #[arg(long, default_value_t = 0)]
+    obligation_max_spread: i64,
+
+    /// Fraction of the session the market maker's quotes must be two-sided
+    /// within the obligation spread
+    #[arg(long, default_value_t = 0.9)]
+    obligation_min_presence: f64,
+
+    /// Consecutive losing round trips (flat to flat) that pause the strategy (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    max_consecutive_losses: u32,
+
+    /// How long the strategy pauses after a loss streak, in milliseconds
+    #[arg(long, default_value_t = 60_000)]
+    loss_cooldown_ms: u64,
+
+    /// Consecutive order rejects that deactivate the strategy until restart
+    /// (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    max_consecutive_rejects: u32,
+
+    /// Fair value in cents to start from instead of the first mid
+    #[arg(long)]
+    seed_fair_value: Option<i64>,
+
+    /// Reference price used to mark positions
+    #[arg(long, value_enum, default_value_t = Mark::Mid)]
+    mark: Mark,
+
+    /// Funding cost of holding a long position, in basis points of notional
+    /// per day (negative to receive funding)
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    long_funding_bps: f64,
+
+    /// Borrow cost of holding a short position, in basis points of notional
+    /// per day (negative to receive funding)
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    short_funding_bps: f64,
+
+    /// Interval between position/P&L snapshots in milliseconds (0 = disabled)
+    #[arg(long, default_value_t = 1000)]
+    snapshot_interval_ms: u64,
+
+    /// Clock position/P&L snapshots are stamped with: realtime (wall clock)
+    /// or monotonic. Intervals are always timed on the monotonic clock.
+    #[arg(long, default_value_t = ClockSource::Realtime)]
+    record_clock: ClockSource,
+
+    /// Print a performance report on shutdown: trade count, win rate,
+    /// average win and loss, profit factor and Sharpe ratio of the snapshots
+    #[arg(long, default_value_t = false)]
+    backtest_report: bool,
+
+    /// Kernel receive buffer for market data in bytes
+    #[arg(long, default_value_t = DEFAULT_RECV_BUFFER_SIZE)]
+    recv_buffer_size: usize,
+
+    /// Recent market data sequence numbers tracked per ticker to skip
+    /// duplicates (0 disables)
+    #[arg(long, default_value_t = DEFAULT_DEDUP_WINDOW)]
+    dedup_window: usize,
+
+    /// Age out depth levels not refreshed by an update or snapshot within
+    /// this many milliseconds, clearing phantom levels left by missed cancels
+    /// (0 disables)
+    #[arg(long, default_value_t = 0)]
+    level_ttl_ms: u64,
+
+    /// Rejoin the market data group after this many milliseconds without an
+    /// update or on a receive error (0 never rejoins)
+    #[arg(long, default_value_t = 0)]
+    rejoin_timeout_ms: u64,
+
+    /// Exchange UDP port to request a market data snapshot from on startup,
+    /// instead of waiting for the next periodic one (0 disables)
+    #[arg(long, default_value_t = 0)]
+    snapshot_port: u16,
+
+    /// Only process market data for the traded ticker, ignoring the rest
+    /// of a shared group
+    #[arg(long, default_value_t = false)]
+    subscribe_ticker_only: bool,
+
+    /// Control file of `key = value` parameters, re-read when it changes
+    #[arg(long)]
+    control_file: Option<String>,
+
+    /// Instruments file of per-ticker tick size, lot size, price scale and
+    /// notional bounds
+    #[arg(long)]
+    instruments: Option<String>,
+
+    /// Stamp requests with their send time and report order round trips
+    #[arg(long, default_value_t = false)]
+    timestamps: bool,
+
+    /// Local port accepting operator kill commands (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    control_port: u16,
+
+    /// On shutdown, cancel all orders and trade the position back to flat
+    #[arg(long, default_value_t = false)]
+    flatten_on_exit: bool,
+
+    /// Longest to wait for flattening fills on shutdown, in milliseconds
+    #[arg(long, default_value_t = 5000)]
+    flatten_timeout_ms: u64,
+
+    /// First delay before reconnecting to the exchange after the connection
+    /// drops, in milliseconds (0 = never reconnect)
+    #[arg(long, default_value_t = 0)]
+    reconnect_base_ms: u64,
+
+    /// Longest delay between reconnect attempts, in milliseconds
+    #[arg(long, default_value_t = 5000)]
+    reconnect_max_ms: u64,
+}
+
+/// Main loop iterations between control file checks
+const CONTROL_POLL_INTERVAL: u64 = 10_000;
+
+/// Depth levels per side kept for depth-reactive quote sizing
+const DEPTH_SIZING_LEVELS: usize = 10;
+
+/// Locks the order gateway shared with the control port.
+fn lock(gateway: &Mutex<OrderGateway>) -> MutexGuard<'_, OrderGateway> {
+    gateway.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Prints the portfolio totals followed by each ticker's position and P&L.
+fn print_portfolio(summary: &PortfolioSummary) {
+    println!(
+        "Portfolio: realized={}, unrealized={}, gross={}, net={}",
+        summary.total_realized, summary.total_unrealized, summary.gross_position, summary.net_position
+    );
+    for &(ticker_id, position, pnl) in &summary.per_ticker {
+        println!("  ticker {}: pos={}, pnl={}", ticker_id, position, pnl);
+    }
+}
+
+/// Cancels a ticker's orders and trades its position back to flat.
+///
+/// Flattening orders are sent against the touch once the cancels have been
+/// answered, and again whenever the previous one is done and the position
+/// is still open. Gives up at the timeout, pulling anything still resting.
+///
+/// # Returns
+/// The number of fills received while flattening
+fn flatten_position(
+    ticker_id: TickerId,
+    timeout: Duration,
+    market_data_receiver: &mut MarketDataReceiver,
+    order_gateway: &Mutex<OrderGateway>,
+    position_keeper: &mut PositionKeeper,
+) -> u64 {
+    let mut gateway = lock(order_gateway);
+    let cancels = gateway.cancel_all(ticker_id);
+    println!("Flattening: {} cancels sent", cancels);
+
+    let deadline = Instant::now() + timeout;
+    let mut fills = 0u64;
+    while Instant::now() < deadline {
+        while let Some(update) = market_data_receiver.poll() {
+            market_data_receiver.process_update(&update);
+        }
+        while let Some(response) = gateway.poll() {
+            if response.response_type() == Some(ClientResponseType::Filled) {
+                fills += 1;
+                let side = if response.side == 1 {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                };
+                position_keeper.on_fill(ticker_id, side, response.exec_qty, response.price);
+            }
+        }
+
+        // Only act on the position once every order has been answered
+        if gateway.pending_count() == 0 {
+            let position = position_keeper.get_position(ticker_id).map_or(0, |p| p.position);
+            if position == 0 {
+                break;
+            }
+            let order = market_data_receiver
+                .get_bbo(ticker_id)
+                .and_then(|bbo| build_flatten_order(position, bbo));
+            if let Some((side, qty, price)) = order {
+                // A failed send is retried on a later pass
+                let _ = gateway.send_new_order(ticker_id, side, price, qty);
+            }
+        }
+        thread::sleep(Duration::from_micros(100));
+    }
+
+    if gateway.pending_count() > 0 {
+        gateway.cancel_all(ticker_id);
+    }
+    fills
 }
 
 fn main() {
@@ -114,9 +408,37 @@ fn main() {
             }
         };
 
+    if let Err(e) = market_data_receiver.set_recv_buffer_size(args.recv_buffer_size) {
+        eprintln!("Failed to set market data receive buffer: {}", e);
+    }
+    if let Ok(size) = market_data_receiver.recv_buffer_size() {
+        println!("  Receive buffer: {} bytes", size);
+    }
+    market_data_receiver.set_dedup_window(args.dedup_window);
+    market_data_receiver.set_level_ttl(args.level_ttl_ms * 1_000_000);
+    if args.rejoin_timeout_ms > 0 {
+        market_data_receiver.set_rejoin(RejoinConfig::new(args.rejoin_timeout_ms * 1_000_000));
+        println!("  Rejoin after: {}ms of silence", args.rejoin_timeout_ms);
+    }
+
+    if args.subscribe_ticker_only {
+        market_data_receiver.subscribe(&[args.ticker]);
+        println!("  Market data: ticker {} only", args.ticker);
+    }
+
     // Pre-allocate BBO for our ticker
     market_data_receiver.reserve_tickers(&[args.ticker]);
 
+    if args.snapshot_port > 0 {
+        let requested = market_data_receiver
+            .set_snapshot_server(&args.host, args.snapshot_port)
+            .and_then(|()| market_data_receiver.request_snapshot(args.ticker));
+        match requested {
+            Ok(_) => println!("  Snapshot requested from {}:{}", args.host, args.snapshot_port),
+            Err(e) => eprintln!("Failed to request market data snapshot: {}", e),
+        }
+    }
+
     // Initialize order gateway
     let mut order_gateway = match OrderGateway::connect(&args.host, args.port, args.client_id) {
         Ok(gateway) => gateway,
@@ -125,13 +447,69 @@ fn main() {
             std::process::exit(1);
         }
     };
+    order_gateway.set_timestamps(args.timestamps);
+    if args.reconnect_base_ms > 0 {
+        order_gateway.set_reconnect_backoff(
+            Duration::from_millis(args.reconnect_base_ms),
+            Duration::from_millis(args.reconnect_max_ms),
+        );
+        order_gateway.set_auto_reconnect(true);
+    }
+    if let Some(token) = args.auth_token {
+        if let Err(e) = order_gateway.login(token) {
+            eprintln!("Failed to log in to exchange: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Shared with the control port, which cancels through it directly so a
+    // kill lands even while the trading loop is stuck
+    let order_gateway = Arc::new(Mutex::new(order_gateway));
+    let kill_switch = Arc::new(KillSwitch::new());
+    if args.control_port > 0 {
+        let gateway = Arc::clone(&order_gateway);
+        let control_addr = format!("127.0.0.1:{}", args.control_port);
+        let server = ControlServer::spawn(&control_addr, Arc::clone(&kill_switch), move |command| {
+            let mut gateway = lock(&gateway);
+            match command {
+                ControlCommand::Kill { .. } => {
+                    let sent = gateway.cancel_all_tickers();
+                    println!("Kill command: {} cancels sent, strategies deactivated", sent);
+                }
+                ControlCommand::KillOrder(order_id) => {
+                    match gateway.get_pending(order_id).map(|pending| pending.ticker_id) {
+                        Some(ticker_id) => {
+                            if let Err(e) = gateway.send_cancel(order_id, ticker_id) {
+                                eprintln!("Kill command: failed to cancel order {}: {}", order_id, e);
+                            }
+                        }
+                        None => println!("Kill command: order {} is not open", order_id),
+                    }
+                }
+                ControlCommand::Resume => println!("Resume command: strategies reactivated"),
+            }
+        });
+        match server {
+            Ok(server) => println!("  Control port: {}", server.local_addr()),
+            Err(e) => {
+                eprintln!("Failed to open control port: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
     // Initialize feature engine
     let mut feature_engine = FeatureEngine::new();
     feature_engine.reserve_tickers(&[args.ticker]);
+    if let Some(fair_value) = args.seed_fair_value {
+        feature_engine.seed(args.ticker, fair_value);
+        println!("  Seeded fair value: {}", fair_value);
+    }
 
     // Initialize position keeper
     let mut position_keeper = PositionKeeper::new();
+    position_keeper.set_mark_source(args.mark.into());
+    position_keeper.set_funding_rates(FundingRates::new(args.long_funding_bps, args.short_funding_bps));
 
     // Initialize risk manager
     let risk_limits = RiskLimits::new(
@@ -139,10 +517,36 @@ fn main() {
         args.max_position,
         args.max_loss,
         100, // max open orders
-    );
+    )
+    .with_min_notional(args.min_notional);
     let mut risk_manager = RiskManager::new();
     risk_manager.set_limits(args.ticker, risk_limits);
 
+    // Load instrument specs, shared by risk checks and the strategy
+    let instruments = match args.instruments.as_deref().map(InstrumentRegistry::load) {
+        Some(Ok(instruments)) => instruments,
+        Some(Err(e)) => {
+            eprintln!("Failed to load instruments: {}", e);
+            std::process::exit(1);
+        }
+        None => InstrumentRegistry::new(),
+    };
+    let instrument = instruments.get(args.ticker);
+    if instruments.contains(args.ticker) {
+        println!(
+            "  Instrument: tick {}, lot {}",
+            instrument.format_price(instrument.tick_size),
+            instrument.lot_size
+        );
+    }
+    risk_manager.set_instruments(instruments);
+    if args.pressure_limit_scale > 0.0 {
+        risk_manager.set_pressure_adjustment(PressureAdjustment::new(
+            args.pressure_limit_scale,
+            args.pressure_limit_scale,
+        ));
+    }
+
     // Initialize trading strategy
     let mut market_maker: Option<MarketMaker> = None;
     let mut liquidity_taker: Option<LiquidityTaker> = None;
@@ -152,7 +556,19 @@ fn main() {
             let config = MarketMakerConfig::new(args.ticker)
                 .with_half_spread(args.half_spread)
                 .with_base_qty(args.max_order_qty)
-                .with_max_position(args.max_position);
+                .with_max_position(args.max_position)
+                .with_max_quote_age_ns(args.max_quote_age_ms * 1_000_000)
+                .with_loss_cooldown(args.max_consecutive_losses, args.loss_cooldown_ms * 1_000_000)
+                .with_max_consecutive_rejects(args.max_consecutive_rejects)
+                .with_pnl_sensitivity(args.pnl_sensitivity, args.pnl_scale)
+                .with_requote_after_fills(args.requote_after_fills)
+                .with_requote_on_fill(args.requote_on_fill)
+                .with_instrument(instrument)
+                .with_depth_sizing(args.depth_reference_qty, args.min_depth_factor, args.max_depth_factor)
+                .with_quote_obligation(args.obligation_max_spread, args.obligation_min_presence);
+            if args.depth_reference_qty > 0 && market_data_receiver.depth_levels() == 0 {
+                market_data_receiver.set_depth_levels(DEPTH_SIZING_LEVELS);
+            }
             market_maker = Some(MarketMaker::new(config));
             println!("  Half spread: {} cents", args.half_spread);
         }
@@ -160,12 +576,23 @@ fn main() {
             let config = LiquidityTakerConfig::new(args.ticker)
                 .with_threshold(args.signal_threshold)
                 .with_base_qty(args.max_order_qty)
-                .with_max_position(args.max_position);
+                .with_max_position(args.max_position)
+                .with_loss_cooldown(args.max_consecutive_losses, args.loss_cooldown_ms * 1_000_000)
+                .with_max_consecutive_rejects(args.max_consecutive_rejects)
+                .with_hysteresis(args.hysteresis)
+                .with_fresh_features(args.fresh_features)
+                .with_max_slippage_bps(args.max_slippage_bps)
+                .with_instrument(instrument);
             liquidity_taker = Some(LiquidityTaker::new(config));
             println!("  Signal threshold: {}", args.signal_threshold);
         }
     }
 
+    let mut control_file = args.control_file.as_deref().map(ControlFile::new);
+    if let Some(ref control) = control_file {
+        println!("  Control file: {}", control.path().display());
+    }
+
     // Set up graceful shutdown
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
@@ -180,28 +607,56 @@ fn main() {
 
     // Main event loop
     let mut stats_interval = 0u64;
+    let snapshot_interval_ns = args.snapshot_interval_ms * 1_000_000;
+    let mut last_snapshot_ns = 0u64;
     let mut orders_sent = 0u64;
     let mut fills_received = 0u64;
+    let mut strategies_killed = false;
 
     while running.load(Ordering::SeqCst) {
-        // 1. Process incoming market data
-        let updates_processed = market_data_receiver.poll_and_process();
+        // Charge funding at the marks held since the last cycle
+        if position_keeper.funding_rates().is_enabled() {
+            position_keeper.accrue_funding(monotonic_nanos().as_u64());
+        }
+
+        // Count the time since the last cycle against the quote obligation
+        if let Some(ref mut mm) = market_maker {
+            if mm.obligation().is_enabled() {
+                mm.observe_obligation(monotonic_nanos().as_u64());
+            }
+        }
+
+        // 1. Process incoming market data, marking to trade prints if configured
+        let mut updates_processed = 0;
+        while let Some(update) = market_data_receiver.poll() {
+            market_data_receiver.process_update(&update);
+            updates_processed += 1;
+
+            let ticker_id = update.ticker_id;
+            if ticker_id == args.ticker && update.update_type() == Some(MarketUpdateType::Trade) {
+                position_keeper.on_trade_print(ticker_id, update.price);
+            }
+        }
+        if args.snapshot_port > 0 {
+            updates_processed += market_data_receiver.poll_snapshot();
+        }
+        if args.level_ttl_ms > 0 {
+            market_data_receiver.expire_levels(monotonic_nanos().as_u64());
+        }
 
-        // 2. Update feature engine with new BBO if we got updates
+        // 2. Update feature engine and marks with new BBO if we got updates
         if updates_processed > 0 {
             if let Some(bbo) = market_data_receiver.get_bbo(args.ticker) {
                 feature_engine.on_bbo_update(args.ticker, bbo);
-
-                // Update position keeper with market price
-                if bbo.is_valid() {
-                    let mid = (bbo.bid_price + bbo.ask_price) / 2;
-                    position_keeper.update_market_price(args.ticker, mid);
-                }
+                position_keeper.on_bbo_update(args.ticker, bbo);
             }
         }
 
         // 3. Process order responses
-        while let Some(response) = order_gateway.poll() {
+        let mut cycle_fills = CycleFills::default();
+        let mut fill_requotes = Vec::new();
+        let mut gateway = lock(&order_gateway);
+        while let Some(response) = gateway.poll() {
             let response_type = response.response_type();
 
             match response_type {
@@ -216,20 +671,47 @@ fn main() {
                     let price = response.price;
 
                     // Update position
-                    position_keeper.on_fill(args.ticker, side, qty, price);
+                    let realized = position_keeper.on_fill(args.ticker, side, qty, price);
+                    cycle_fills.record(side, qty);
 
-                    // Update strategy position
+                    // Update strategy position; the market maker gets the
+                    // cycle's fills together once they are all in
                     let pos = position_keeper
                         .get_position(args.ticker)
                         .map(|p| p.position)
                         .unwrap_or(0);
-                    if let Some(ref mut mm) = market_maker {
-                        mm.set_position(pos);
-                    }
                     if let Some(ref mut lt) = liquidity_taker {
                         lt.set_position(pos);
                         lt.on_fill();
                     }
+
+                    // A fully filled quote can be replaced right away
+                    if let Some(ref mut mm) = market_maker {
+                        if response.leaves_qty == 0 {
+                            if let StrategyAction::Quote(pair) = mm.on_fill(side, qty) {
+                                fill_requotes.extend(pair.bid.or(pair.ask));
+                            }
+                        }
+                    }
+
+                    // Feed closes to the loss streak breaker, which judges
+                    // each round trip once the position is flat again
+                    if let Some(realized) = realized {
+                        let now = monotonic_nanos().as_u64();
+                        let before = pos - side.as_sign() * qty as i64;
+                        let closed_out = pos == 0 || pos.signum() != before.signum();
+                        let tripped = match (&mut market_maker, &mut liquidity_taker) {
+                            (Some(mm), _) => mm.on_close(realized, closed_out, now),
+                            (_, Some(lt)) => lt.on_close(realized, closed_out, now),
+                            _ => false,
+                        };
+                        if tripped {
+                            println!(
+                                "{} consecutive losses, pausing for {}ms",
+                                args.max_consecutive_losses, args.loss_cooldown_ms
+                            );
+                        }
+                    }
                 }
                 Some(ClientResponseType::Accepted) => {
                     // Order accepted, track in position
@@ -254,25 +736,127 @@ fn main() {
                 _ => {}
             }
         }
+        // Forget quotes the exchange has since canceled, rejected or filled
+        if let Some(ref mut mm) = market_maker {
+            for side in [Side::Buy, Side::Sell] {
+                if mm.quote_order(side).is_some_and(|order_id| gateway.get_pending(order_id).is_none()) {
+                    mm.set_quote_order(side, None);
+                }
+            }
+        }
+        drop(gateway);
+        if let Some(ref mut mm) = market_maker {
+            if !cycle_fills.is_empty() {
+                let pos = position_keeper
+                    .get_position(args.ticker)
+                    .map(|p| p.position)
+                    .unwrap_or(0);
+                if let StrategyAction::CancelAll(ticker_id) = mm.on_cycle_fills(pos, cycle_fills) {
+                    lock(&order_gateway).cancel_all(ticker_id);
+                    fill_requotes.clear();
+                }
+            }
+            for quote in fill_requotes.drain(..) {
+                let position = position_keeper.get_position_mut(args.ticker);
+                let risk_result = risk_manager.check_order(position, quote.side, quote.qty, quote.price);
+                mm.on_order_result(risk_result);
+                if !risk_result.is_allowed() {
+                    continue;
+                }
+                if let Ok(order_id) = lock(&order_gateway).send_new_order(quote.ticker_id, quote.side, quote.price, quote.qty) {
+                    orders_sent += 1;
+                    mm.set_quote_order(quote.side, Some(order_id));
+                }
+            }
+        }
+
+        // 4. Apply parameter changes from the control file
+        if let Some(ref mut control) = control_file {
+            if stats_interval.is_multiple_of(CONTROL_POLL_INTERVAL) {
+                match control.poll() {
+                    Ok(Some(update)) => {
+                        let limits = update.apply_to_risk_limits(*risk_manager.get_limits(args.ticker));
+                        risk_manager.set_limits(args.ticker, limits);
+
+                        if let Some(ref mut mm) = market_maker {
+                            let config = update.apply_to_market_maker(*mm.config());
+                            if let StrategyAction::CancelAll(ticker_id) = mm.update_config(config) {
+                                let canceled = lock(&order_gateway).cancel_all(ticker_id);
+                                println!("Quotes violate new parameters, canceled {} to re-quote", canceled);
+                            }
+                        }
+                        if let Some(ref mut lt) = liquidity_taker {
+                            lt.update_config(update.apply_to_liquidity_taker(*lt.config()));
+                        }
+                        println!("Applied parameter update: {:?}", update);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Ignoring control file update: {}", e),
+                }
+            }
+        }
+
+        // 5. Follow operator kills: strategies stay off until a resume
+        let killed = kill_switch.is_killed();
+        if killed != strategies_killed {
+            strategies_killed = killed;
+            if let Some(ref mut mm) = market_maker {
+                if killed { mm.deactivate() } else { mm.activate() }
+            }
+            if let Some(ref mut lt) = liquidity_taker {
+                if killed { lt.deactivate() } else { lt.activate() }
+            }
+        }
+        if kill_switch.take_flatten() {
+            let position = position_keeper.get_position(args.ticker).map_or(0, |p| p.position);
+            let bbo = market_data_receiver.get_bbo(args.ticker);
+            let close = match bbo {
+                Some(bbo) if position > 0 && bbo.has_bid() => Some((Side::Sell, bbo.bid_price)),
+                Some(bbo) if position < 0 && bbo.has_ask() => Some((Side::Buy, bbo.ask_price)),
+                _ => None,
+            };
+            if let Some((side, price)) = close {
+                let qty = position.unsigned_abs() as u32;
+                match lock(&order_gateway).send_new_order(args.ticker, side, price, qty) {
+                    Ok(_) => {
+                        orders_sent += 1;
+                        println!("Flattening {} at {}", position, price);
+                    }
+                    Err(e) => eprintln!("Cannot flatten {}: {}", position, e),
+                }
+            } else if position != 0 {
+                println!("Cannot flatten {}: no price on the closing side", position);
+            }
+        }
 
-        // 4. Run trading strategy
-        if let Some(features) = feature_engine.get_features(args.ticker) {
+        // 6. Run trading strategy, once the market has been seen
+        let has_market_data = market_data_receiver.book_status(args.ticker).has_data();
+        if let Some(features) = feature_engine.get_features(args.ticker).filter(|_| has_market_data) {
             if features.is_valid() {
+                if risk_manager.pressure_adjustment().is_some() {
+                    risk_manager.set_book_pressure(args.ticker, features.imbalance);
+                }
+
                 // Check risk before generating orders
                 let position = position_keeper.get_position_mut(args.ticker);
                 let risk_ok = risk_manager.check_position(position).is_allowed();
+                if let Some(ref mut mm) = market_maker {
+                    mm.set_pnl(position.realized_pnl, position.unrealized_pnl);
+                }
 
                 if risk_ok {
                     let action = match (&mut market_maker, &mut liquidity_taker) {
-                        (Some(ref mut mm), None) => mm.on_features(features),
+                        (Some(ref mut mm), None) => {
+                            if args.depth_reference_qty > 0 {
+                                if let Some(depth) = market_data_receiver.get_depth(args.ticker, DEPTH_SIZING_LEVELS) {
+                                    mm.set_depth(&depth);
+                                }
+                            }
+                            mm.on_features_now(features)
+                        }
                         (None, Some(ref mut lt)) => {
                             if let Some(bbo) = market_data_receiver.get_bbo(args.ticker) {
-                                lt.on_features(
-                                    features,
-                                    now_nanos().as_u64(),
-                                    bbo.bid_price,
-                                    bbo.ask_price,
-                                )
+                                lt.on_features_now(features, bbo.bid_price, bbo.ask_price)
                             } else {
                                 StrategyAction::None
                             }
@@ -280,7 +864,23 @@ fn main() {
                         _ => StrategyAction::None,
                     };
 
-                    // Execute strategy action
+                    // Feed order outcomes to the reject breaker
+                    let mut report_result = |result: RiskCheckResult| {
+                        let deactivated = match (&mut market_maker, &mut liquidity_taker) {
+                            (Some(mm), _) => mm.on_order_result(result),
+                            (_, Some(lt)) => lt.on_order_result(result),
+                            _ => false,
+                        };
+                        if deactivated {
+                            println!(
+                                "{} consecutive order rejects ({:?}), strategy deactivated",
+                                args.max_consecutive_rejects, result
+                            );
+                        }
+                    };
+
+                    // Execute strategy action, noting new quotes for the market maker
+                    let mut new_quotes = Vec::new();
                     match action {
                         StrategyAction::Quote(quote_pair) => {
                             // Send bid order
@@ -291,14 +891,14 @@ fn main() {
                                     bid.qty,
                                     bid.price,
                                 );
-                                if risk_result.is_allowed() {
-                                    order_gateway.send_new_order(
-                                        bid.ticker_id,
-                                        bid.side,
-                                        bid.price,
-                                        bid.qty,
-                                    );
-                                    orders_sent += 1;
+                                report_result(risk_result);
+                                if let Some(order_id) = bid.replaces().filter(|_| risk_result.is_allowed()) {
+                                    lock(&order_gateway).send_replace(order_id, bid.price, bid.qty);
+                                } else if risk_result.is_allowed() {
+                                    if let Ok(order_id) = lock(&order_gateway).send_new_order(bid.ticker_id, bid.side, bid.price, bid.qty) {
+                                        orders_sent += 1;
+                                        new_quotes.push((bid.side, order_id));
+                                    }
                                 }
                             }
                             // Send ask order
@@ -309,14 +909,14 @@ fn main() {
                                     ask.qty,
                                     ask.price,
                                 );
-                                if risk_result.is_allowed() {
-                                    order_gateway.send_new_order(
-                                        ask.ticker_id,
-                                        ask.side,
-                                        ask.price,
-                                        ask.qty,
-                                    );
-                                    orders_sent += 1;
+                                report_result(risk_result);
+                                if let Some(order_id) = ask.replaces().filter(|_| risk_result.is_allowed()) {
+                                    lock(&order_gateway).send_replace(order_id, ask.price, ask.qty);
+                                } else if risk_result.is_allowed() {
+                                    if let Ok(order_id) = lock(&order_gateway).send_new_order(ask.ticker_id, ask.side, ask.price, ask.qty) {
+                                        orders_sent += 1;
+                                        new_quotes.push((ask.side, order_id));
+                                    }
                                 }
                             }
                         }
@@ -327,41 +927,79 @@ fn main() {
                                 order.qty,
                                 order.price,
                             );
-                            if risk_result.is_allowed() {
-                                order_gateway.send_new_order(
-                                    order.ticker_id,
-                                    order.side,
-                                    order.price,
-                                    order.qty,
-                                );
+                            report_result(risk_result);
+                            if risk_result.is_allowed()
+                                && lock(&order_gateway)
+                                    .send_new_order(order.ticker_id, order.side, order.price, order.qty)
+                                    .is_ok()
+                            {
                                 orders_sent += 1;
                             }
                         }
-                        StrategyAction::CancelAll(_ticker_id) => {
-                            // In a full implementation, would track and cancel all open orders
+                        StrategyAction::CancelAll(ticker_id) => {
+                            lock(&order_gateway).cancel_all(ticker_id);
+                        }
+                        StrategyAction::Replace { order_id, new_price, new_qty } => {
+                            let side = lock(&order_gateway).get_pending(order_id).map(|pending| pending.side);
+                            if let Some(side) = side {
+                                let risk_result = risk_manager.check_order(position, side, new_qty, new_price);
+                                report_result(risk_result);
+                                if risk_result.is_allowed() {
+                                    lock(&order_gateway).send_replace(order_id, new_price, new_qty);
+                                }
+                            }
                         }
                         StrategyAction::None => {}
                     }
+                    if let Some(ref mut mm) = market_maker {
+                        for (side, order_id) in new_quotes {
+                            mm.set_quote_order(side, Some(order_id));
+                        }
+                    }
                 }
             }
         }
 
+        // Record position/P&L snapshots for the equity curve
+        if snapshot_interval_ns > 0 {
+            let now = monotonic_nanos().as_u64();
+            if now.saturating_sub(last_snapshot_ns) >= snapshot_interval_ns {
+                position_keeper.record_snapshot(args.record_clock.now().as_u64());
+                last_snapshot_ns = now;
+            }
+        }
+
         // Print stats periodically
         stats_interval += 1;
-        if stats_interval % 100000 == 0 {
+        if stats_interval.is_multiple_of(100000) {
             let pnl = position_keeper.total_pnl();
             let pos = position_keeper
                 .get_position(args.ticker)
                 .map(|p| p.position)
                 .unwrap_or(0);
             println!(
-                "Stats: pos={}, pnl={}, orders={}, fills={}, pending={}",
+                "Stats: pos={}, pnl={}, orders={}, fills={}, pending={}, md_drops={}",
                 pos,
                 pnl,
                 orders_sent,
                 fills_received,
-                order_gateway.pending_count()
+                lock(&order_gateway).pending_count(),
+                market_data_receiver
+                    .receiver_drops()
+                    .map_or_else(|| "n/a".to_string(), |drops| drops.to_string())
             );
+            print_portfolio(&position_keeper.portfolio_summary());
+            if args.timestamps {
+                let gateway = lock(&order_gateway);
+                let round_trip = gateway.round_trip_latency();
+                println!(
+                    "Round trip (ns): mean={:.0}, min={}, max={} over {} responses",
+                    round_trip.mean(),
+                    round_trip.min(),
+                    round_trip.max(),
+                    round_trip.count()
+                );
+            }
         }
 
         // Small sleep to prevent busy-waiting when idle
@@ -370,6 +1008,21 @@ fn main() {
 
     // Graceful shutdown
     println!("Shutting down...");
+    if args.flatten_on_exit {
+        fills_received += flatten_position(
+            args.ticker,
+            Duration::from_millis(args.flatten_timeout_ms),
+            &mut market_data_receiver,
+            &order_gateway,
+            &mut position_keeper,
+        );
+        let position = position_keeper
+            .get_position(args.ticker)
+            .map_or(0, |p| p.position);
+        if position != 0 {
+            eprintln!("Flattening timed out with position {}", position);
+        }
+    }
     let final_pnl = position_keeper.total_pnl();
     let final_pos = position_keeper
         .get_position(args.ticker)
@@ -379,4 +1032,30 @@ fn main() {
         "Final stats: position={}, P&L={} cents, orders_sent={}, fills={}",
         final_pos, final_pnl, orders_sent, fills_received
     );
+    print_portfolio(&position_keeper.portfolio_summary());
+    println!(
+        "Recorded {} P&L snapshots",
+        position_keeper.snapshots(args.ticker).count()
+    );
+    if args.level_ttl_ms > 0 {
+        println!("Aged out {} depth levels", market_data_receiver.levels_aged_out());
+    }
+    println!("Market data sequence gaps: {}", market_data_receiver.gap_count());
+    if let Some(mm) = market_maker.as_ref().filter(|mm| mm.obligation().is_enabled()) {
+        let obligation = mm.obligation();
+        println!(
+            "Quote obligation: compliance={:.1}% breaches={} met={}",
+            obligation.compliance() * 100.0,
+            obligation.breaches(),
+            obligation.meets_obligation()
+        );
+    }
+    if args.backtest_report {
+        let report = position_keeper.backtest_report(args.ticker);
+        println!("Backtest report: {}", report);
+        if args.snapshot_interval_ms > 0 {
+            let periods_per_year = 365.0 * 24.0 * 3_600_000.0 / args.snapshot_interval_ms as f64;
+            println!("  Annualized Sharpe: {:.3}", report.annualized_sharpe(periods_per_year));
+        }
+    }
 }
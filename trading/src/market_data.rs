@@ -2,22 +2,69 @@
 //!
 //! Receives market data updates via multicast and maintains a local BBO
 //! (Best Bid/Offer) view for each ticker.
+//!
+//! With rejoin enabled, a receive error or a silent feed makes the receiver
+//! rejoin its multicast group with backoff. After a rejoin every book is
+//! invalidated and incremental updates are ignored until the ticker's next
+//! snapshot arrives.
+//!
+//! With depth enabled, the receiver also keeps a depth book per ticker,
+//! bounded to a configured number of levels per side, rebuilt from the
+//! level (banded) updates of a depth-banded feed; a band width of 1 gives
+//! full price granularity. On a feed of order updates, the depth book is an
+//! `OrderBookLevels` instead, aggregating the quantity of the orders at each
+//! price as they are added, cancelled and traded against. See
+//! `set_depth_levels` and `get_depth`.
+//!
+//! On a lossy feed a missed cancel leaves a phantom level in the depth book
+//! until the next snapshot replaces it. With a level TTL set, a level not
+//! refreshed by an add, a modify or a snapshot within the TTL is aged out,
+//! bounding how long a phantom level can linger. See `set_level_ttl`.
+//!
+//! The receiver tracks the feed sequence of the updates it receives. A
+//! skipped sequence means updates were lost, and since any ticker on the
+//! group may have lost one, every book is marked stale: its BBO stops being
+//! valid until a Snapshot or Clear for the ticker resynchronizes it. See
+//! `on_gap` and `gap_count`.
+//!
+//! A receiver joining between periodic snapshots can ask the publisher for
+//! one with `request_snapshot` and apply the reply with `poll_snapshot`.
+//! Every snapshot, requested or periodic, records the feed sequence it
+//! reflects, and sequenced increments at or below it are discarded as
+//! stale, so increments interleaved with a snapshot are never applied on
+//! top of the state that already includes them.
+//!
+//! A receiver can subscribe to a set of tickers; updates for any other
+//! ticker arriving on a shared group are dropped before they touch any
+//! state.
 
 use common::net::multicast::MulticastSocket;
-use common::{Price, Qty, Side, TickerId, INVALID_PRICE};
-use exchange::protocol::{MarketUpdate, MarketUpdateType, MARKET_UPDATE_SIZE};
-use std::collections::HashMap;
+use common::time::monotonic_nanos;
+use common::{OrderId, Price, Qty, Side, TickerId, INVALID_PRICE};
+use exchange::market_data::BandedDepth;
+use exchange::protocol::{MarketUpdate, MarketUpdateType, SnapshotRequest, MARKET_UPDATE_SIZE};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 
 /// Best Bid and Offer for a single ticker.
 ///
 /// Represents the top of the order book with the best available
 /// prices and quantities on each side.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy)]
 pub struct BBO {
     pub bid_price: Price,
     pub bid_qty: Qty,
     pub ask_price: Price,
     pub ask_qty: Qty,
+    /// Set when a sequence gap may have left the book out of date, until
+    /// the next snapshot
+    pub stale: bool,
+}
+
+impl Default for BBO {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BBO {
@@ -28,6 +75,7 @@ impl BBO {
             bid_qty: 0,
             ask_price: INVALID_PRICE,
             ask_qty: 0,
+            stale: false,
         }
     }
 
@@ -43,10 +91,10 @@ impl BBO {
         self.ask_price != INVALID_PRICE && self.ask_qty > 0
     }
 
-    /// Returns true if both bid and ask are valid.
+    /// Returns true if both bid and ask are valid and the book is not stale.
     #[inline]
     pub fn is_valid(&self) -> bool {
-        self.has_bid() && self.has_ask()
+        !self.stale && self.has_bid() && self.has_ask()
     }
 
     /// Returns the spread (ask - bid) if both sides are valid.
@@ -59,31 +107,771 @@ impl BBO {
         }
     }
 
-    /// Returns the mid price if both sides are valid.
+    /// Returns the mid price, rounded to the nearest tick, if both sides are valid.
     #[inline]
     pub fn mid_price(&self) -> Option<Price> {
+        self.mid_price_rounded(MidRounding::Nearest)
+    }
+
+    /// Returns the mid price with the given rounding if both sides are valid.
+    ///
+    /// The sum is taken in 128 bits, so prices near the limits of `Price`
+    /// can't overflow.
+    #[inline]
+    pub fn mid_price_rounded(&self, rounding: MidRounding) -> Option<Price> {
+        if !self.is_valid() {
+            return None;
+        }
+        let sum = self.bid_price as i128 + self.ask_price as i128;
+        let down = sum.div_euclid(2);
+        let halfway = sum.rem_euclid(2) == 1;
+        let mid = match rounding {
+            MidRounding::Nearest if halfway => down + down.rem_euclid(2),
+            MidRounding::Up if halfway => down + 1,
+            _ => down,
+        };
+        Some(mid as Price)
+    }
+
+    /// Returns twice the mid price (bid + ask) if both sides are valid.
+    ///
+    /// This is the mid at half-tick resolution, with no rounding at all.
+    /// None if the sum overflows.
+    #[inline]
+    pub fn mid_price_x2(&self) -> Option<Price> {
         if self.is_valid() {
-            Some((self.bid_price + self.ask_price) / 2)
+            self.bid_price.checked_add(self.ask_price)
         } else {
             None
         }
     }
+
+    /// Returns the size-weighted mid if both sides are valid.
+    ///
+    /// Each price is weighted by the opposite side's quantity, so the
+    /// microprice leans toward the side more likely to trade through.
+    #[inline]
+    pub fn microprice(&self) -> Option<Price> {
+        if !self.is_valid() {
+            return None;
+        }
+        let bid_qty = self.bid_qty as i128;
+        let ask_qty = self.ask_qty as i128;
+        let weighted = self.bid_price as i128 * ask_qty + self.ask_price as i128 * bid_qty;
+        Some((weighted / (bid_qty + ask_qty)) as Price)
+    }
+
+    /// Builds a top of book from prices alone, for callers that only know
+    /// the touch. A side at `INVALID_PRICE` is missing; a present side is
+    /// given a nominal quantity of 1.
+    pub fn from_prices(bid_price: Price, ask_price: Price) -> Self {
+        let qty = |price: Price| if price == INVALID_PRICE { 0 } else { 1 };
+        Self {
+            bid_price,
+            bid_qty: qty(bid_price),
+            ask_price,
+            ask_qty: qty(ask_price),
+            stale: false,
+        }
+    }
+
+    /// Builds the top of book from banded depth.
+    pub fn from_banded(depth: &BandedDepth) -> Self {
+        let mut bbo = Self::new();
+        if let Some((price, qty)) = depth.best_bid() {
+            bbo.bid_price = price;
+            bbo.bid_qty = qty;
+        }
+        if let Some((price, qty)) = depth.best_ask() {
+            bbo.ask_price = price;
+            bbo.ask_qty = qty;
+        }
+        bbo
+    }
+
+    /// Applies a market update to the top of book.
+    ///
+    /// # Arguments
+    /// * `update_type` - The kind of update
+    /// * `side` - Raw side of the update
+    /// * `price` - Price of the update
+    /// * `qty` - Quantity of the update
+    pub fn apply(&mut self, update_type: MarketUpdateType, side: i8, price: Price, qty: Qty) {
+        match update_type {
+            MarketUpdateType::Add | MarketUpdateType::Modify | MarketUpdateType::Snapshot => {
+                // Update BBO based on side
+                if side == Side::Buy as i8 {
+                    // Update bid if this is a better price or same price with more qty
+                    if price > self.bid_price || self.bid_price == INVALID_PRICE {
+                        self.bid_price = price;
+                        self.bid_qty = qty;
+                    } else if price == self.bid_price {
+                        // Same price level - this could be qty update
+                        self.bid_qty = qty;
+                    }
+                } else if side == Side::Sell as i8 {
+                    // Update ask if this is a better (lower) price or same price
+                    if price < self.ask_price || self.ask_price == INVALID_PRICE {
+                        self.ask_price = price;
+                        self.ask_qty = qty;
+                    } else if price == self.ask_price {
+                        // Same price level - this could be qty update
+                        self.ask_qty = qty;
+                    }
+                }
+            }
+            MarketUpdateType::Cancel => {
+                // If the cancelled order was at BBO, we need to invalidate
+                // In a full implementation, we'd track the full book
+                if side == Side::Buy as i8 && price == self.bid_price {
+                    // Bid at BBO was cancelled - mark as potentially stale
+                    // A real implementation would have the full book to find next best
+                    if qty == 0 || qty >= self.bid_qty {
+                        self.bid_qty = 0;
+                    } else {
+                        self.bid_qty = self.bid_qty.saturating_sub(qty);
+                    }
+                } else if side == Side::Sell as i8 && price == self.ask_price {
+                    // Ask at BBO was cancelled
+                    if qty == 0 || qty >= self.ask_qty {
+                        self.ask_qty = 0;
+                    } else {
+                        self.ask_qty = self.ask_qty.saturating_sub(qty);
+                    }
+                }
+            }
+            MarketUpdateType::Trade => {
+                // Trade occurred - reduce qty at the trade price level
+                if side == Side::Buy as i8 && price == self.ask_price {
+                    // Buy trade hits the ask
+                    self.ask_qty = self.ask_qty.saturating_sub(qty);
+                } else if side == Side::Sell as i8 && price == self.bid_price {
+                    // Sell trade hits the bid
+                    self.bid_qty = self.bid_qty.saturating_sub(qty);
+                }
+            }
+            MarketUpdateType::Clear => {
+                // Clear the entire book for this ticker
+                *self = BBO::new();
+            }
+        }
+    }
+}
+
+/// State of a ticker's top of book, as seen by the receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookStatus {
+    /// No market data has been received for the ticker yet
+    NoData,
+    /// Data was received but at least one side is empty
+    OneSided,
+    /// Both sides are present but the bid is at or above the ask
+    Crossed,
+    /// Both sides are present and the bid is below the ask
+    Valid,
+    /// A sequence gap may have left the book out of date
+    Stale,
+}
+
+impl BookStatus {
+    /// Returns the status of a populated top of book.
+    pub fn of(bbo: &BBO) -> Self {
+        if bbo.stale {
+            BookStatus::Stale
+        } else if !bbo.is_valid() {
+            BookStatus::OneSided
+        } else if bbo.bid_price >= bbo.ask_price {
+            BookStatus::Crossed
+        } else {
+            BookStatus::Valid
+        }
+    }
+
+    /// Returns true once market data has been received.
+    #[inline]
+    pub fn has_data(&self) -> bool {
+        *self != BookStatus::NoData
+    }
+}
+
+/// How a mid price that falls between two ticks is rounded.
+///
+/// A mid is either on a tick or exactly halfway between two, so always
+/// rounding the same way biases marks by half a tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MidRounding {
+    /// Round halfway mids to the even tick, so there is no systematic bias.
+    #[default]
+    Nearest,
+    /// Round down to the tick below.
+    Down,
+    /// Round up to the tick above.
+    Up,
+}
+
+/// Price levels of a book, best first on each side.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Depth {
+    /// Bid levels as (price, quantity), highest price first.
+    pub bids: Vec<(Price, Qty)>,
+    /// Ask levels as (price, quantity), lowest price first.
+    pub asks: Vec<(Price, Qty)>,
+}
+
+/// Level book for one ticker, rebuilt from level updates.
+///
+/// Add and Modify set a level's total quantity (0 removes it) and Cancel
+/// removes the level. The Snapshot updates of one snapshot share a sequence
+/// in `priority`; the first of them clears the book, so a snapshot replaces
+/// whatever was held. Only the best `max_levels` levels of each side are
+/// kept: a level pushed out is forgotten until a snapshot restores it.
+/// Each level remembers when it was last set, so `expire` can age out
+/// levels a missed cancel left behind.
+#[derive(Debug, Clone)]
+pub struct DepthBook {
+    /// Bid levels as price to (quantity, time last set)
+    bids: BTreeMap<Price, (Qty, u64)>,
+    /// Ask levels as price to (quantity, time last set)
+    asks: BTreeMap<Price, (Qty, u64)>,
+    /// Most levels kept per side
+    max_levels: usize,
+    /// Sequence of the snapshot being applied, None between snapshots
+    snapshot_seq: Option<u64>,
+}
+
+impl DepthBook {
+    /// Creates an empty book keeping at most `max_levels` levels per side
+    /// (at least one).
+    pub fn new(max_levels: usize) -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            max_levels: max_levels.max(1),
+            snapshot_seq: None,
+        }
+    }
+
+    /// Returns the most levels kept per side.
+    #[inline]
+    pub fn max_levels(&self) -> usize {
+        self.max_levels
+    }
+
+    /// Applies a level update, without a time for its levels.
+    ///
+    /// # Returns
+    /// true if the update changed the book's levels
+    pub fn apply(&mut self, update: &MarketUpdate) -> bool {
+        self.apply_at(update, 0)
+    }
+
+    /// Applies a level update received at `now_ns`.
+    ///
+    /// # Returns
+    /// true if the update changed the book's levels
+    pub fn apply_at(&mut self, update: &MarketUpdate, now_ns: u64) -> bool {
+        let Some(update_type) = update.update_type() else {
+            return false;
+        };
+        if update_type == MarketUpdateType::Clear {
+            self.clear();
+            self.snapshot_seq = None;
+            return true;
+        }
+
+        let side = match update.side {
+            1 => Side::Buy,
+            -1 => Side::Sell,
+            _ => return false,
+        };
+        let (price, qty) = (update.price, update.qty);
+        match update_type {
+            MarketUpdateType::Snapshot => {
+                let seq = update.priority;
+                if self.snapshot_seq != Some(seq) {
+                    self.clear();
+                    self.snapshot_seq = Some(seq);
+                }
+                self.set_level(side, price, qty, now_ns);
+            }
+            MarketUpdateType::Add | MarketUpdateType::Modify => {
+                self.snapshot_seq = None;
+                self.set_level(side, price, qty, now_ns);
+            }
+            MarketUpdateType::Cancel => {
+                self.snapshot_seq = None;
+                self.set_level(side, price, 0, now_ns);
+            }
+            MarketUpdateType::Trade | MarketUpdateType::Clear => return false,
+        }
+        true
+    }
+
+    /// Sets a level's quantity, removing it at 0 and trimming the side back
+    /// to `max_levels`.
+    fn set_level(&mut self, side: Side, price: Price, qty: Qty, now_ns: u64) {
+        let levels = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        if qty == 0 {
+            levels.remove(&price);
+            return;
+        }
+        levels.insert(price, (qty, now_ns));
+        while levels.len() > self.max_levels {
+            // Drop the worst level: the lowest bid or the highest ask
+            match side {
+                Side::Buy => levels.pop_first(),
+                Side::Sell => levels.pop_last(),
+            };
+        }
+    }
+
+    /// Returns up to `levels` levels of each side, best first.
+    pub fn depth(&self, levels: usize) -> Depth {
+        Depth {
+            bids: self.bids.iter().rev().take(levels).map(|(&price, &(qty, _))| (price, qty)).collect(),
+            asks: self.asks.iter().take(levels).map(|(&price, &(qty, _))| (price, qty)).collect(),
+        }
+    }
+
+    /// Returns the number of levels on a side.
+    #[inline]
+    pub fn level_count(&self, side: Side) -> usize {
+        match side {
+            Side::Buy => self.bids.len(),
+            Side::Sell => self.asks.len(),
+        }
+    }
+
+    /// Removes the levels last set more than `ttl_ns` before `now_ns`.
+    ///
+    /// # Returns
+    /// The number of levels removed
+    pub fn expire(&mut self, ttl_ns: u64, now_ns: u64) -> usize {
+        let before = self.bids.len() + self.asks.len();
+        let fresh = |_: &Price, &mut (_, set_at): &mut (Qty, u64)| now_ns.saturating_sub(set_at) <= ttl_ns;
+        self.bids.retain(fresh);
+        self.asks.retain(fresh);
+        before - self.bids.len() - self.asks.len()
+    }
+
+    /// Removes every level.
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+}
+
+/// Price levels for one ticker, rebuilt from order updates.
+///
+/// Each level holds the total quantity of the orders resting at its price.
+/// An Add rests an order, a Modify sets its quantity, a Cancel removes it
+/// and a Trade takes its quantity from the resting side (opposite the
+/// aggressor). Orders are tracked by ID so a Modify replaces the order's
+/// old quantity; for an order the book never saw, a Cancel or Trade takes
+/// the update's quantity from its price, and a Modify rests it like an Add. The Snapshot updates of one
+/// snapshot share a sequence in `priority`; the first of them clears the
+/// book, and their levels are not attributed to any order. Every level is
+/// held, so a level emptied at the top uncovers the next; `depth` reports
+/// at most `max_levels` of them per side.
+#[derive(Debug, Clone)]
+pub struct OrderBookLevels {
+    /// Bid levels as price to total quantity
+    bids: BTreeMap<Price, Qty>,
+    /// Ask levels as price to total quantity
+    asks: BTreeMap<Price, Qty>,
+    /// Resting orders as ID to (side, price, quantity)
+    orders: HashMap<OrderId, (Side, Price, Qty)>,
+    /// Most levels reported per side
+    max_levels: usize,
+    /// Sequence of the snapshot being applied, None between snapshots
+    snapshot_seq: Option<u64>,
+}
+
+impl OrderBookLevels {
+    /// Creates an empty book reporting at most `max_levels` levels per side
+    /// (at least one).
+    pub fn new(max_levels: usize) -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            orders: HashMap::new(),
+            max_levels: max_levels.max(1),
+            snapshot_seq: None,
+        }
+    }
+
+    /// Returns the most levels reported per side.
+    #[inline]
+    pub fn max_levels(&self) -> usize {
+        self.max_levels
+    }
+
+    /// Applies an order update.
+    ///
+    /// # Returns
+    /// true if the update changed the book's levels
+    pub fn apply(&mut self, update: &MarketUpdate) -> bool {
+        let Some(update_type) = update.update_type() else {
+            return false;
+        };
+        if update_type == MarketUpdateType::Clear {
+            self.clear();
+            self.snapshot_seq = None;
+            return true;
+        }
+
+        let side = match update.side {
+            1 => Side::Buy,
+            -1 => Side::Sell,
+            _ => return false,
+        };
+        let (order_id, price, qty) = (update.order_id, update.price, update.qty);
+        if update_type != MarketUpdateType::Snapshot {
+            self.snapshot_seq = None;
+        }
+        match update_type {
+            MarketUpdateType::Snapshot => {
+                let seq = update.priority;
+                if self.snapshot_seq != Some(seq) {
+                    self.clear();
+                    self.snapshot_seq = Some(seq);
+                }
+                if qty > 0 {
+                    self.levels_mut(side).insert(price, qty);
+                }
+            }
+            MarketUpdateType::Add | MarketUpdateType::Modify => {
+                if let Some((old_side, old_price, old_qty)) = self.orders.remove(&order_id) {
+                    self.take(old_side, old_price, old_qty);
+                }
+                self.rest(order_id, side, price, qty);
+            }
+            MarketUpdateType::Cancel => match self.orders.remove(&order_id) {
+                Some((old_side, old_price, old_qty)) => self.take(old_side, old_price, old_qty),
+                None => self.take(side, price, qty),
+            },
+            MarketUpdateType::Trade => {
+                // The trade's side is the aggressor's; the resting order is opposite
+                let resting_side = side.opposite();
+                match self.orders.get_mut(&order_id) {
+                    Some((_, order_price, order_qty)) => {
+                        let (order_price, filled) = (*order_price, qty.min(*order_qty));
+                        *order_qty -= filled;
+                        if *order_qty == 0 {
+                            self.orders.remove(&order_id);
+                        }
+                        self.take(resting_side, order_price, filled);
+                    }
+                    None => self.take(resting_side, price, qty),
+                }
+            }
+            MarketUpdateType::Clear => {}
+        }
+        true
+    }
+
+    /// Rests an order's quantity at its price.
+    fn rest(&mut self, order_id: OrderId, side: Side, price: Price, qty: Qty) {
+        if qty == 0 {
+            return;
+        }
+        if order_id != 0 {
+            self.orders.insert(order_id, (side, price, qty));
+        }
+        *self.levels_mut(side).entry(price).or_insert(0) += qty;
+    }
+
+    /// Takes quantity from a level, removing the level once empty.
+    fn take(&mut self, side: Side, price: Price, qty: Qty) {
+        let levels = self.levels_mut(side);
+        if let Some(level_qty) = levels.get_mut(&price) {
+            *level_qty = level_qty.saturating_sub(qty);
+            if *level_qty == 0 {
+                levels.remove(&price);
+            }
+        }
+    }
+
+    #[inline]
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<Price, Qty> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// Returns up to `levels` levels of each side, best first, and never
+    /// more than `max_levels`.
+    pub fn depth(&self, levels: usize) -> Depth {
+        let levels = levels.min(self.max_levels);
+        Depth {
+            bids: self.bids.iter().rev().take(levels).map(|(&price, &qty)| (price, qty)).collect(),
+            asks: self.asks.iter().take(levels).map(|(&price, &qty)| (price, qty)).collect(),
+        }
+    }
+
+    /// Returns the total quantity at a price on a side (0 if no level).
+    #[inline]
+    pub fn qty_at(&self, side: Side, price: Price) -> Qty {
+        match side {
+            Side::Buy => self.bids.get(&price),
+            Side::Sell => self.asks.get(&price),
+        }
+        .copied()
+        .unwrap_or(0)
+    }
+
+    /// Returns the number of levels held on a side.
+    #[inline]
+    pub fn level_count(&self, side: Side) -> usize {
+        match side {
+            Side::Buy => self.bids.len(),
+            Side::Sell => self.asks.len(),
+        }
+    }
+
+    /// Returns the number of resting orders tracked.
+    #[inline]
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Removes every level and order.
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.orders.clear();
+    }
 }
 
 /// Callback type for market data subscribers.
 pub type MarketDataCallback = Box<dyn FnMut(TickerId, &MarketUpdate, &BBO) + Send>;
 
+/// Callback notified of a sequence gap with the expected and received
+/// sequence numbers.
+pub type GapCallback = Box<dyn FnMut(u64, u64) + Send>;
+
+/// Default kernel receive buffer requested for market data sockets (8MB).
+pub const DEFAULT_RECV_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// Default number of recent sequence numbers tracked per ticker for dedup.
+pub const DEFAULT_DEDUP_WINDOW: usize = 1024;
+
+/// Sliding window of the sequence numbers recently applied for a ticker.
+///
+/// Remembers the last `capacity()` sequence numbers up to the highest seen,
+/// so updates arriving late or out of order are still applied once.
+#[derive(Debug, Clone)]
+pub struct SequenceWindow {
+    highest: u64,
+    bits: Vec<u64>,
+}
+
+impl SequenceWindow {
+    /// Creates a window tracking at least `size` sequence numbers.
+    pub fn new(size: usize) -> Self {
+        Self {
+            highest: 0,
+            bits: vec![0; size.div_ceil(64).max(1)],
+        }
+    }
+
+    /// Returns the number of sequence numbers tracked.
+    #[inline]
+    pub fn capacity(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    /// Returns the highest sequence number seen.
+    #[inline]
+    pub fn highest(&self) -> u64 {
+        self.highest
+    }
+
+    /// Records a sequence number.
+    ///
+    /// # Returns
+    /// true if the sequence is new, false if it was already seen or is too
+    /// old for the window to tell
+    pub fn insert(&mut self, seq: u64) -> bool {
+        if seq > self.highest {
+            if seq - self.highest >= self.capacity() {
+                self.bits.fill(0);
+            } else {
+                // Slots skipped over now belong to the new sequences
+                for skipped in self.highest + 1..seq {
+                    self.set(skipped, false);
+                }
+            }
+            self.highest = seq;
+            self.set(seq, true);
+            true
+        } else if self.highest - seq >= self.capacity() || self.get(seq) {
+            false
+        } else {
+            self.set(seq, true);
+            true
+        }
+    }
+
+    #[inline]
+    fn get(&self, seq: u64) -> bool {
+        let slot = seq % self.capacity();
+        self.bits[(slot / 64) as usize] & (1 << (slot % 64)) != 0
+    }
+
+    #[inline]
+    fn set(&mut self, seq: u64, seen: bool) {
+        let slot = seq % self.capacity();
+        let word = &mut self.bits[(slot / 64) as usize];
+        if seen {
+            *word |= 1 << (slot % 64);
+        } else {
+            *word &= !(1 << (slot % 64));
+        }
+    }
+}
+
+/// Connection state of the market data receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiverState {
+    /// Receiving updates normally
+    Active,
+    /// The feed failed or went silent; rejoining the group with backoff
+    Rejoining,
+    /// Rejoined; books are invalid until each ticker's snapshot arrives
+    AwaitingSnapshot,
+}
+
+/// When and how often the receiver rejoins its multicast group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RejoinConfig {
+    /// Rejoin after this long without an update, in nanoseconds (0 only
+    /// rejoins on receive errors)
+    pub silence_timeout_ns: u64,
+    /// Delay before retrying a failed rejoin, in nanoseconds
+    pub initial_backoff_ns: u64,
+    /// Longest delay between rejoin attempts, in nanoseconds
+    pub max_backoff_ns: u64,
+}
+
+impl Default for RejoinConfig {
+    fn default() -> Self {
+        Self {
+            silence_timeout_ns: 1_000_000_000,
+            initial_backoff_ns: 100_000_000,
+            max_backoff_ns: 5_000_000_000,
+        }
+    }
+}
+
+impl RejoinConfig {
+    /// Creates a rejoin configuration with the default backoff.
+    ///
+    /// # Arguments
+    /// * `silence_timeout_ns` - Rejoin after this long without an update
+    ///   (0 only rejoins on receive errors)
+    pub fn new(silence_timeout_ns: u64) -> Self {
+        Self {
+            silence_timeout_ns,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the delay before the first retry and the cap it doubles up to.
+    pub fn with_backoff(mut self, initial_ns: u64, max_ns: u64) -> Self {
+        self.initial_backoff_ns = initial_ns.max(1);
+        self.max_backoff_ns = max_ns.max(self.initial_backoff_ns);
+        self
+    }
+}
+
+/// Multicast group a receiver joined, kept to rejoin it.
+#[derive(Debug, Clone)]
+struct GroupAddr {
+    addr: String,
+    port: u16,
+    interface: String,
+}
+
 /// Receives market data updates via multicast and maintains BBO state.
 ///
 /// The receiver joins a multicast group, deserializes incoming MarketUpdate
 /// messages, and maintains a local order book view (BBO) for each ticker.
+///
+/// Updates carrying a sequence number already applied for their ticker are
+/// skipped, so an update delivered twice by retransmission or recovery is
+/// not applied twice. See `set_dedup_window`.
 pub struct MarketDataReceiver {
     socket: MulticastSocket,
     bbo: HashMap<TickerId, BBO>,
     subscribers: Vec<MarketDataCallback>,
-    /// Sequence number for gap detection (if needed)
-    #[allow(dead_code)]
+    /// Highest feed sequence received, 0 until known
     last_seq: u64,
+    /// Sequence gaps detected
+    gap_count: u64,
+    /// Callbacks notified of sequence gaps
+    gap_subscribers: Vec<GapCallback>,
+    /// Sequence numbers tracked per ticker for dedup (0 disables)
+    dedup_window: usize,
+    /// Recently applied sequence numbers per ticker
+    seen_seqs: HashMap<TickerId, SequenceWindow>,
+    /// Updates skipped as already applied
+    duplicates_skipped: u64,
+    /// Books rebuilt from banded depth updates, per ticker
+    banded_depth: HashMap<TickerId, BandedDepth>,
+    /// Levels kept per side of each depth book (0 disables depth)
+    depth_levels: usize,
+    /// Bounded depth books rebuilt from level updates, per ticker
+    depth: HashMap<TickerId, DepthBook>,
+    /// Depth books rebuilt from order updates, per ticker
+    order_levels: HashMap<TickerId, OrderBookLevels>,
+    /// Time a depth level may go unrefreshed before it is aged out (0
+    /// disables)
+    level_ttl_ns: u64,
+    /// Depth levels aged out for going unrefreshed past the TTL
+    levels_aged_out: u64,
+    /// Tickers that have received at least one update
+    populated: HashSet<TickerId>,
+    /// Group to rejoin, when the receiver joined one itself
+    group: Option<GroupAddr>,
+    /// Receive buffer requested for the socket, reapplied on rejoin
+    recv_buffer_bytes: usize,
+    /// Rejoin behaviour, None to never rejoin
+    rejoin: Option<RejoinConfig>,
+    state: ReceiverState,
+    /// Time of the last update received, 0 until known
+    last_rx_ns: u64,
+    /// Earliest time of the next rejoin attempt
+    next_rejoin_ns: u64,
+    /// Delay applied after the next failed attempt
+    backoff_ns: u64,
+    /// Rejoin attempts made, successful or not
+    rejoin_attempts: u64,
+    /// Rejoin attempts that succeeded
+    rejoins: u64,
+    /// Tickers invalidated by a rejoin and still waiting for a snapshot
+    awaiting_snapshot: HashSet<TickerId>,
+    /// Tickers processed, None to process every ticker
+    subscriptions: Option<HashSet<TickerId>>,
+    /// Updates dropped for tickers outside the subscriptions
+    unsubscribed_skipped: u64,
+    /// Socket snapshot requests are sent and answered on, once a snapshot
+    /// server is set
+    snapshot_socket: Option<UdpSocket>,
+    /// Publisher address snapshot requests are sent to
+    snapshot_server: Option<SocketAddr>,
+    /// Sequence of the last snapshot request sent
+    snapshot_request_seq: u64,
+    /// Sequence of the latest snapshot request per ticker
+    snapshot_requests: HashMap<TickerId, u64>,
+    /// Feed sequence the last applied snapshot reflects, per ticker
+    snapshot_seqs: HashMap<TickerId, u64>,
+    /// Increments skipped as already reflected by a snapshot
+    stale_skipped: u64,
 }
 
 impl MarketDataReceiver {
@@ -102,12 +890,185 @@ impl MarketDataReceiver {
         // Set socket to non-blocking for poll-based operation
         socket.set_nonblocking(true)?;
 
-        Ok(Self {
+        // Large kernel buffer to absorb bursts between polls
+        socket.set_recv_buffer_size(DEFAULT_RECV_BUFFER_SIZE)?;
+
+        let mut receiver = Self::with_socket(socket);
+        receiver.set_group(multicast_addr, port, interface);
+        Ok(receiver)
+    }
+
+    /// Creates a receiver reading from an already configured socket.
+    ///
+    /// # Arguments
+    /// * `socket` - Socket joined to the market data group, or unjoined to
+    ///   only process updates handed to `process_update`
+    pub fn with_socket(socket: MulticastSocket) -> Self {
+        Self {
             socket,
             bbo: HashMap::new(),
             subscribers: Vec::new(),
             last_seq: 0,
-        })
+            gap_count: 0,
+            gap_subscribers: Vec::new(),
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            seen_seqs: HashMap::new(),
+            duplicates_skipped: 0,
+            banded_depth: HashMap::new(),
+            depth_levels: 0,
+            depth: HashMap::new(),
+            order_levels: HashMap::new(),
+            level_ttl_ns: 0,
+            levels_aged_out: 0,
+            populated: HashSet::new(),
+            group: None,
+            recv_buffer_bytes: DEFAULT_RECV_BUFFER_SIZE,
+            rejoin: None,
+            state: ReceiverState::Active,
+            last_rx_ns: 0,
+            next_rejoin_ns: 0,
+            backoff_ns: 0,
+            rejoin_attempts: 0,
+            rejoins: 0,
+            awaiting_snapshot: HashSet::new(),
+            subscriptions: None,
+            unsubscribed_skipped: 0,
+            snapshot_socket: None,
+            snapshot_server: None,
+            snapshot_request_seq: 0,
+            snapshot_requests: HashMap::new(),
+            snapshot_seqs: HashMap::new(),
+            stale_skipped: 0,
+        }
+    }
+
+    /// Sets the multicast group joined again on rejoin.
+    ///
+    /// `new` sets it; a receiver built with `with_socket` has none and
+    /// cannot rejoin until one is given.
+    pub fn set_group(&mut self, multicast_addr: &str, port: u16, interface: &str) {
+        self.group = Some(GroupAddr {
+            addr: multicast_addr.to_string(),
+            port,
+            interface: interface.to_string(),
+        });
+    }
+
+    /// Enables automatic rejoin of the multicast group.
+    pub fn set_rejoin(&mut self, config: RejoinConfig) {
+        self.backoff_ns = config.initial_backoff_ns;
+        self.rejoin = Some(config);
+    }
+
+    /// Returns the rejoin configuration, if rejoin is enabled.
+    #[inline]
+    pub fn rejoin_config(&self) -> Option<&RejoinConfig> {
+        self.rejoin.as_ref()
+    }
+
+    /// Returns the receiver's connection state.
+    #[inline]
+    pub fn receiver_state(&self) -> ReceiverState {
+        self.state
+    }
+
+    /// Returns the number of rejoin attempts made, successful or not.
+    #[inline]
+    pub fn rejoin_attempts(&self) -> u64 {
+        self.rejoin_attempts
+    }
+
+    /// Returns the number of successful rejoins.
+    #[inline]
+    pub fn rejoins(&self) -> u64 {
+        self.rejoins
+    }
+
+    /// Records a failed receive; with rejoin enabled the next
+    /// `check_connection` rejoins the group.
+    ///
+    /// # Arguments
+    /// * `now_ns` - Time of the failure
+    pub fn note_receive_error(&mut self, now_ns: u64) {
+        let Some(config) = self.rejoin else {
+            return;
+        };
+        if self.state != ReceiverState::Rejoining {
+            self.state = ReceiverState::Rejoining;
+            self.next_rejoin_ns = now_ns;
+            self.backoff_ns = config.initial_backoff_ns;
+        }
+    }
+
+    /// Detects a silent feed and rejoins the group when an attempt is due.
+    ///
+    /// # Arguments
+    /// * `now_ns` - Current time
+    ///
+    /// # Returns
+    /// The receiver state after the check
+    pub fn check_connection(&mut self, now_ns: u64) -> ReceiverState {
+        let Some(config) = self.rejoin else {
+            return self.state;
+        };
+        if self.last_rx_ns == 0 {
+            self.last_rx_ns = now_ns;
+        }
+
+        if self.state != ReceiverState::Rejoining
+            && config.silence_timeout_ns > 0
+            && now_ns.saturating_sub(self.last_rx_ns) > config.silence_timeout_ns
+        {
+            self.note_receive_error(now_ns);
+        }
+
+        if self.state == ReceiverState::Rejoining && now_ns >= self.next_rejoin_ns {
+            self.rejoin_attempts += 1;
+            match self.join_socket() {
+                Ok(socket) => {
+                    self.socket = socket;
+                    self.rejoins += 1;
+                    self.last_rx_ns = now_ns;
+                    self.backoff_ns = config.initial_backoff_ns;
+                    self.invalidate_books();
+                }
+                Err(_) => {
+                    self.next_rejoin_ns = now_ns.saturating_add(self.backoff_ns);
+                    self.backoff_ns = self.backoff_ns.saturating_mul(2).min(config.max_backoff_ns);
+                }
+            }
+        }
+
+        self.state
+    }
+
+    /// Opens a new socket joined to the configured group.
+    fn join_socket(&self) -> std::io::Result<MulticastSocket> {
+        let group = self.group.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotConnected, "no multicast group to rejoin")
+        })?;
+        let socket = MulticastSocket::join_group(&group.addr, group.port, &group.interface)?;
+        socket.set_nonblocking(true)?;
+        socket.set_recv_buffer_size(self.recv_buffer_bytes)?;
+        Ok(socket)
+    }
+
+    /// Marks every book invalid until its ticker's next snapshot.
+    fn invalidate_books(&mut self) {
+        self.awaiting_snapshot = std::mem::take(&mut self.populated);
+        for bbo in self.bbo.values_mut() {
+            *bbo = BBO::new();
+        }
+        self.banded_depth.clear();
+        self.depth.clear();
+        self.order_levels.clear();
+        self.seen_seqs.clear();
+        self.last_seq = 0;
+        self.state = if self.awaiting_snapshot.is_empty() {
+            ReceiverState::Active
+        } else {
+            ReceiverState::AwaitingSnapshot
+        };
     }
 
     /// Polls for the next market update without blocking.
@@ -118,6 +1079,9 @@ impl MarketDataReceiver {
     pub fn poll(&mut self) -> Option<MarketUpdate> {
         match self.socket.try_recv() {
             Ok(Some(data)) => {
+                if self.rejoin.is_some() {
+                    self.last_rx_ns = monotonic_nanos().as_u64();
+                }
                 // Ensure we have enough data for a MarketUpdate
                 if data.len() >= MARKET_UPDATE_SIZE {
                     // Zero-copy deserialization
@@ -129,7 +1093,10 @@ impl MarketDataReceiver {
                 None
             }
             Ok(None) => None,
-            Err(_) => None,
+            Err(_) => {
+                self.note_receive_error(monotonic_nanos().as_u64());
+                None
+            }
         }
     }
 
@@ -138,7 +1105,19 @@ impl MarketDataReceiver {
     /// This method should be called for each update received from `poll()`.
     /// It updates the internal BBO state based on the update type and
     /// notifies all registered subscribers.
+    ///
+    /// After a rejoin, a ticker's updates are ignored until a Snapshot or
+    /// Clear gives it a fresh book.
     pub fn process_update(&mut self, update: &MarketUpdate) {
+        let now_ns = if self.level_ttl_ns > 0 { monotonic_nanos().as_u64() } else { 0 };
+        self.process_update_at(update, now_ns);
+    }
+
+    /// Processes a market update received at `now_ns`.
+    ///
+    /// Like `process_update`, with the time depth levels it sets are
+    /// refreshed at given explicitly.
+    pub fn process_update_at(&mut self, update: &MarketUpdate, now_ns: u64) {
         // Extract fields from packed struct to avoid unaligned access
         let ticker_id = update.ticker_id;
         let side = update.side;
@@ -150,66 +1129,103 @@ impl MarketDataReceiver {
             None => return, // Invalid update type
         };
 
-        // Get or create BBO for this ticker
-        let bbo = self.bbo.entry(ticker_id).or_insert_with(BBO::new);
+        // Updates of every ticker on the group share its sequence
+        let seq = update.seq;
+        if seq != 0 {
+            self.check_sequence(seq);
+        }
+
+        // Another subscriber's ticker sharing our group
+        if !self.is_subscribed(ticker_id) {
+            self.unsubscribed_skipped += 1;
+            return;
+        }
 
+        // Incremental updates cannot repair a book invalidated by a rejoin
+        if self.state == ReceiverState::AwaitingSnapshot && self.awaiting_snapshot.contains(&ticker_id) {
+            if !matches!(update_type, MarketUpdateType::Snapshot | MarketUpdateType::Clear) {
+                return;
+            }
+            self.awaiting_snapshot.remove(&ticker_id);
+            if self.awaiting_snapshot.is_empty() {
+                self.state = ReceiverState::Active;
+            }
+        }
+
+        // A snapshot supersedes the increments it already reflects; an
+        // unsequenced Clear opens a requested snapshot
         match update_type {
-            MarketUpdateType::Add | MarketUpdateType::Modify | MarketUpdateType::Snapshot => {
-                // Update BBO based on side
-                if side == Side::Buy as i8 {
-                    // Update bid if this is a better price or same price with more qty
-                    if price > bbo.bid_price || bbo.bid_price == INVALID_PRICE {
-                        bbo.bid_price = price;
-                        bbo.bid_qty = qty;
-                    } else if price == bbo.bid_price {
-                        // Same price level - this could be qty update
-                        bbo.bid_qty = qty;
-                    }
-                } else if side == Side::Sell as i8 {
-                    // Update ask if this is a better (lower) price or same price
-                    if price < bbo.ask_price || bbo.ask_price == INVALID_PRICE {
-                        bbo.ask_price = price;
-                        bbo.ask_qty = qty;
-                    } else if price == bbo.ask_price {
-                        // Same price level - this could be qty update
-                        bbo.ask_qty = qty;
-                    }
-                }
+            MarketUpdateType::Snapshot => {
+                self.snapshot_seqs.insert(ticker_id, update.priority);
             }
-            MarketUpdateType::Cancel => {
-                // If the cancelled order was at BBO, we need to invalidate
-                // In a full implementation, we'd track the full book
-                if side == Side::Buy as i8 && price == bbo.bid_price {
-                    // Bid at BBO was cancelled - mark as potentially stale
-                    // A real implementation would have the full book to find next best
-                    if qty == 0 || qty >= bbo.bid_qty {
-                        bbo.bid_qty = 0;
-                    } else {
-                        bbo.bid_qty = bbo.bid_qty.saturating_sub(qty);
-                    }
-                } else if side == Side::Sell as i8 && price == bbo.ask_price {
-                    // Ask at BBO was cancelled
-                    if qty == 0 || qty >= bbo.ask_qty {
-                        bbo.ask_qty = 0;
-                    } else {
-                        bbo.ask_qty = bbo.ask_qty.saturating_sub(qty);
-                    }
-                }
+            MarketUpdateType::Clear if seq == 0 => {
+                self.snapshot_seqs.insert(ticker_id, update.priority);
             }
-            MarketUpdateType::Trade => {
-                // Trade occurred - reduce qty at the trade price level
-                if side == Side::Buy as i8 && price == bbo.ask_price {
-                    // Buy trade hits the ask
-                    bbo.ask_qty = bbo.ask_qty.saturating_sub(qty);
-                } else if side == Side::Sell as i8 && price == bbo.bid_price {
-                    // Sell trade hits the bid
-                    bbo.bid_qty = bbo.bid_qty.saturating_sub(qty);
+            _ if seq != 0 && seq <= self.snapshot_seqs.get(&ticker_id).copied().unwrap_or(0) => {
+                self.stale_skipped += 1;
+                return;
+            }
+            _ => {}
+        }
+
+        // Skip updates already applied, e.g. redelivered by a recovery
+        if seq != 0 && self.dedup_window > 0 {
+            let window = self.dedup_window;
+            let seen = self
+                .seen_seqs
+                .entry(ticker_id)
+                .or_insert_with(|| SequenceWindow::new(window));
+            if !seen.insert(seq) {
+                self.duplicates_skipped += 1;
+                return;
+            }
+        }
+
+        if self.depth_levels > 0 {
+            let levels = self.depth_levels;
+            if update_type == MarketUpdateType::Clear {
+                // Clear whichever books the ticker has
+                if let Some(book) = self.depth.get_mut(&ticker_id) {
+                    book.apply_at(update, now_ns);
                 }
+                if let Some(book) = self.order_levels.get_mut(&ticker_id) {
+                    book.apply(update);
+                }
+            } else if update.is_banded() {
+                self.depth
+                    .entry(ticker_id)
+                    .or_insert_with(|| DepthBook::new(levels))
+                    .apply_at(update, now_ns);
+            } else {
+                self.order_levels
+                    .entry(ticker_id)
+                    .or_insert_with(|| OrderBookLevels::new(levels))
+                    .apply(update);
+            }
+        }
+
+        // Get or create BBO for this ticker
+        self.populated.insert(ticker_id);
+        let bbo = self.bbo.entry(ticker_id).or_default();
+        if update.is_banded() {
+            // Banded depth: rebuild the band book and take the BBO from it
+            let depth = self.banded_depth.entry(ticker_id).or_default();
+            depth.apply_level(update);
+            *bbo = BBO::from_banded(depth);
+        } else if update_type == MarketUpdateType::Trade && self.banded_depth.contains_key(&ticker_id) {
+            // With banded depth the band levels carry the book changes
+        } else {
+            if update_type == MarketUpdateType::Clear {
+                self.banded_depth.remove(&ticker_id);
             }
-            MarketUpdateType::Clear => {
-                // Clear the entire book for this ticker
+            if bbo.stale && update_type == MarketUpdateType::Snapshot {
+                // Rebuild from the snapshot rather than merge into stale levels
                 *bbo = BBO::new();
             }
+            bbo.apply(update_type, side, price, qty);
+        }
+        if matches!(update_type, MarketUpdateType::Snapshot | MarketUpdateType::Clear) {
+            bbo.stale = false;
         }
 
         // Notify subscribers
@@ -219,6 +1235,39 @@ impl MarketDataReceiver {
         }
     }
 
+    /// Records a feed sequence, handling a gap before it.
+    ///
+    /// On a gap every populated book is marked stale and the gap callbacks
+    /// are notified. Sequences at or below the highest seen are left to
+    /// dedup.
+    fn check_sequence(&mut self, seq: u64) {
+        let expected = self.last_seq + 1;
+        if self.last_seq != 0 && seq > expected {
+            self.gap_count += 1;
+            for ticker_id in &self.populated {
+                if let Some(bbo) = self.bbo.get_mut(ticker_id) {
+                    bbo.stale = true;
+                }
+            }
+            for subscriber in &mut self.gap_subscribers {
+                subscriber(expected, seq);
+            }
+        }
+        self.last_seq = self.last_seq.max(seq);
+    }
+
+    /// Registers a callback notified of each sequence gap with the
+    /// sequence expected and the one received.
+    pub fn on_gap(&mut self, callback: GapCallback) {
+        self.gap_subscribers.push(callback);
+    }
+
+    /// Returns the number of sequence gaps detected.
+    #[inline]
+    pub fn gap_count(&self) -> u64 {
+        self.gap_count
+    }
+
     /// Returns the current BBO for a ticker.
     ///
     /// # Arguments
@@ -226,26 +1275,174 @@ impl MarketDataReceiver {
     ///
     /// # Returns
     /// - `Some(&BBO)` if we have data for this ticker
-    /// - `None` if no data has been received for this ticker
+    /// - `None` if no data has been received for this ticker, even if it
+    ///   was reserved
     #[inline]
     pub fn get_bbo(&self, ticker_id: TickerId) -> Option<&BBO> {
+        if !self.populated.contains(&ticker_id) {
+            return None;
+        }
         self.bbo.get(&ticker_id)
     }
 
-    /// Returns a mutable reference to the BBO for a ticker.
+    /// Returns the banded book for a ticker whose depth is published in
+    /// price bands.
+    #[inline]
+    pub fn banded_depth(&self, ticker_id: TickerId) -> Option<&BandedDepth> {
+        self.banded_depth.get(&ticker_id)
+    }
+
+    /// Keeps a depth book of up to `levels` levels per side for every
+    /// ticker (0 disables depth and drops the books).
+    ///
+    /// Depth is rebuilt from the level updates of a feed publishing depth in
+    /// price bands, or else from the order updates of the feed.
+    pub fn set_depth_levels(&mut self, levels: usize) {
+        self.depth_levels = levels;
+        if levels == 0 {
+            self.depth.clear();
+            self.order_levels.clear();
+        }
+    }
+
+    /// Returns the number of levels kept per side of each depth book.
+    #[inline]
+    pub fn depth_levels(&self) -> usize {
+        self.depth_levels
+    }
+
+    /// Ages out depth levels not refreshed by an add, a modify or a snapshot
+    /// within `ttl_ns` (0 disables).
+    ///
+    /// Levels age out when `expire_levels` runs; `poll_and_process` runs it
+    /// after each batch. The BBO is not aged.
+    pub fn set_level_ttl(&mut self, ttl_ns: u64) {
+        self.level_ttl_ns = ttl_ns;
+    }
+
+    /// Returns the depth level TTL in nanoseconds (0 if disabled).
+    #[inline]
+    pub fn level_ttl(&self) -> u64 {
+        self.level_ttl_ns
+    }
+
+    /// Removes depth levels left unrefreshed past the level TTL.
+    ///
+    /// # Arguments
+    /// * `now_ns` - Current time, on the clock updates were processed at
+    ///
+    /// # Returns
+    /// The number of levels removed
+    pub fn expire_levels(&mut self, now_ns: u64) -> usize {
+        if self.level_ttl_ns == 0 {
+            return 0;
+        }
+        let ttl_ns = self.level_ttl_ns;
+        let expired: usize = self.depth.values_mut().map(|book| book.expire(ttl_ns, now_ns)).sum();
+        self.levels_aged_out += expired as u64;
+        expired
+    }
+
+    /// Returns the number of depth levels aged out by the level TTL.
+    #[inline]
+    pub fn levels_aged_out(&self) -> u64 {
+        self.levels_aged_out
+    }
+
+    /// Returns up to `levels` levels of each side of a ticker's book.
+    ///
+    /// The levels come from the ticker's level updates if it has had any,
+    /// otherwise from its order updates.
+    ///
+    /// # Returns
+    /// The levels best first, or None if depth is disabled or the ticker has
+    /// had no updates
+    pub fn get_depth(&self, ticker_id: TickerId, levels: usize) -> Option<Depth> {
+        match self.depth.get(&ticker_id) {
+            Some(book) => Some(book.depth(levels)),
+            None => self.order_levels.get(&ticker_id).map(|book| book.depth(levels)),
+        }
+    }
+
+    /// Returns the levels rebuilt from a ticker's order updates.
+    #[inline]
+    pub fn order_book_levels(&self, ticker_id: TickerId) -> Option<&OrderBookLevels> {
+        self.order_levels.get(&ticker_id)
+    }
+
+    /// Returns a mutable reference to the BBO for a ticker, once it has data.
     #[inline]
     pub fn get_bbo_mut(&mut self, ticker_id: TickerId) -> Option<&mut BBO> {
+        if !self.populated.contains(&ticker_id) {
+            return None;
+        }
         self.bbo.get_mut(&ticker_id)
     }
 
+    /// Returns the state of a ticker's top of book.
+    ///
+    /// Tells a ticker with no market data yet apart from one whose book is
+    /// one-sided or crossed.
+    pub fn book_status(&self, ticker_id: TickerId) -> BookStatus {
+        match self.get_bbo(ticker_id) {
+            Some(bbo) => BookStatus::of(bbo),
+            None => BookStatus::NoData,
+        }
+    }
+
     /// Registers a callback to be notified of market data updates.
     ///
     /// The callback receives the ticker ID, the raw update, and the
     /// updated BBO after processing.
-    pub fn subscribe(&mut self, callback: MarketDataCallback) {
+    pub fn add_callback(&mut self, callback: MarketDataCallback) {
         self.subscribers.push(callback);
     }
 
+    /// Restricts processing to the given tickers, adding to any earlier
+    /// subscriptions.
+    ///
+    /// Updates for other tickers are dropped without creating any state,
+    /// and state already held for them is discarded. A receiver with no
+    /// subscriptions processes every ticker.
+    ///
+    /// # Arguments
+    /// * `tickers` - Tickers to process
+    pub fn subscribe(&mut self, tickers: &[TickerId]) {
+        let subscriptions = self.subscriptions.get_or_insert_with(HashSet::new);
+        subscriptions.extend(tickers.iter().copied());
+
+        let subscriptions = &*subscriptions;
+        self.bbo.retain(|ticker_id, _| subscriptions.contains(ticker_id));
+        self.banded_depth.retain(|ticker_id, _| subscriptions.contains(ticker_id));
+        self.depth.retain(|ticker_id, _| subscriptions.contains(ticker_id));
+        self.order_levels.retain(|ticker_id, _| subscriptions.contains(ticker_id));
+        self.seen_seqs.retain(|ticker_id, _| subscriptions.contains(ticker_id));
+        self.populated.retain(|ticker_id| subscriptions.contains(ticker_id));
+        self.awaiting_snapshot.retain(|ticker_id| subscriptions.contains(ticker_id));
+        if self.state == ReceiverState::AwaitingSnapshot && self.awaiting_snapshot.is_empty() {
+            self.state = ReceiverState::Active;
+        }
+    }
+
+    /// Removes every subscription so all tickers are processed again.
+    pub fn subscribe_all(&mut self) {
+        self.subscriptions = None;
+    }
+
+    /// Returns true if updates for the ticker are processed.
+    #[inline]
+    pub fn is_subscribed(&self, ticker_id: TickerId) -> bool {
+        self.subscriptions
+            .as_ref()
+            .is_none_or(|subscriptions| subscriptions.contains(&ticker_id))
+    }
+
+    /// Returns the number of updates dropped for unsubscribed tickers.
+    #[inline]
+    pub fn unsubscribed_skipped(&self) -> u64 {
+        self.unsubscribed_skipped
+    }
+
     /// Returns the number of tickers being tracked.
     #[inline]
     pub fn ticker_count(&self) -> usize {
@@ -261,7 +1458,8 @@ impl MarketDataReceiver {
     /// Polls and processes updates in a loop until no more data is available.
     ///
     /// This is a convenience method that combines `poll()` and `process_update()`
-    /// for batch processing.
+    /// for batch processing. With a level TTL set it then ages out stale depth
+    /// levels, and with rejoin enabled it checks the connection.
     ///
     /// # Returns
     /// The number of updates processed
@@ -271,15 +1469,156 @@ impl MarketDataReceiver {
             self.process_update(&update);
             count += 1;
         }
+        if self.level_ttl_ns > 0 {
+            self.expire_levels(monotonic_nanos().as_u64());
+        }
+        if self.rejoin.is_some() {
+            self.check_connection(monotonic_nanos().as_u64());
+        }
         count
     }
 
+    /// Sets how many recent sequence numbers are tracked per ticker to skip
+    /// updates delivered twice.
+    ///
+    /// # Arguments
+    /// * `size` - Sequence numbers tracked per ticker (0 disables dedup)
+    pub fn set_dedup_window(&mut self, size: usize) {
+        self.dedup_window = size;
+        self.seen_seqs.clear();
+    }
+
+    /// Returns the number of sequence numbers tracked per ticker for dedup.
+    #[inline]
+    pub fn dedup_window(&self) -> usize {
+        self.dedup_window
+    }
+
+    /// Forgets the sequence numbers seen, e.g. after the feed restarts.
+    pub fn reset_dedup(&mut self) {
+        self.seen_seqs.clear();
+    }
+
+    /// Returns the number of updates skipped as already applied.
+    #[inline]
+    pub fn duplicates_skipped(&self) -> u64 {
+        self.duplicates_skipped
+    }
+
+    /// Sets the publisher's snapshot request address, enabling
+    /// `request_snapshot`.
+    ///
+    /// # Arguments
+    /// * `addr` - Host the publisher accepts snapshot requests on
+    /// * `port` - The publisher's snapshot request port
+    pub fn set_snapshot_server(&mut self, addr: &str, port: u16) -> std::io::Result<()> {
+        let server = (addr, port).to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid snapshot server address")
+        })?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_nonblocking(true)?;
+        self.snapshot_socket = Some(socket);
+        self.snapshot_server = Some(server);
+        Ok(())
+    }
+
+    /// Asks the publisher for a snapshot of a ticker.
+    ///
+    /// The reply is applied by `poll_snapshot`; replies to earlier requests
+    /// for the same ticker are ignored.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker to snapshot
+    ///
+    /// # Returns
+    /// The request's sequence, or an error if no snapshot server is set or
+    /// the request could not be sent
+    pub fn request_snapshot(&mut self, ticker_id: TickerId) -> std::io::Result<u64> {
+        let (Some(socket), Some(server)) = (&self.snapshot_socket, self.snapshot_server) else {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotConnected, "No snapshot server set"));
+        };
+        let client_seq = self.snapshot_request_seq + 1;
+        socket.send_to(SnapshotRequest::new(ticker_id, client_seq).as_bytes(), server)?;
+        self.snapshot_request_seq = client_seq;
+        self.snapshot_requests.insert(ticker_id, client_seq);
+        Ok(client_seq)
+    }
+
+    /// Applies the snapshot replies received so far.
+    ///
+    /// Each reply update goes through `process_update`, so the BBO, depth
+    /// and subscribers see it like any other update.
+    ///
+    /// # Returns
+    /// The number of reply updates applied
+    pub fn poll_snapshot(&mut self) -> usize {
+        let Some(socket) = &self.snapshot_socket else {
+            return 0;
+        };
+
+        let mut replies = Vec::new();
+        let mut buf = [0u8; MARKET_UPDATE_SIZE];
+        while let Ok((len, _)) = socket.recv_from(&mut buf) {
+            if let Some(update) = MarketUpdate::from_bytes(&buf[..len]) {
+                replies.push(*update);
+            }
+        }
+
+        let mut applied = 0;
+        for update in &replies {
+            let (ticker_id, client_seq) = (update.ticker_id, update.order_id);
+            if self.snapshot_requests.get(&ticker_id) != Some(&client_seq) {
+                continue;
+            }
+            self.process_update(update);
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Returns the feed sequence the last snapshot applied for a ticker
+    /// reflects (0 if none).
+    #[inline]
+    pub fn snapshot_seq(&self, ticker_id: TickerId) -> u64 {
+        self.snapshot_seqs.get(&ticker_id).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of increments skipped as already reflected by a
+    /// snapshot.
+    #[inline]
+    pub fn stale_skipped(&self) -> u64 {
+        self.stale_skipped
+    }
+
+    /// Sets the kernel receive buffer size of the market data socket.
+    ///
+    /// # Arguments
+    /// * `size` - Requested buffer size in bytes (the kernel may cap it)
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> std::io::Result<()> {
+        self.recv_buffer_bytes = size;
+        self.socket.set_recv_buffer_size(size)
+    }
+
+    /// Returns the kernel receive buffer size granted to the socket.
+    pub fn recv_buffer_size(&self) -> std::io::Result<usize> {
+        self.socket.recv_buffer_size()
+    }
+
+    /// Returns the number of updates the kernel dropped because the socket
+    /// buffer was full, or None where the counter is unavailable.
+    ///
+    /// Growing drops mean the receiver is falling behind; gaps without drops
+    /// point to loss on the network instead.
+    pub fn receiver_drops(&self) -> Option<u64> {
+        self.socket.kernel_drops().ok()
+    }
+
     /// Pre-allocates BBO entries for the given tickers.
     ///
     /// This can help reduce allocation during runtime.
     pub fn reserve_tickers(&mut self, tickers: &[TickerId]) {
         for &ticker_id in tickers {
-            self.bbo.entry(ticker_id).or_insert_with(BBO::new);
+            self.bbo.entry(ticker_id).or_default();
         }
     }
 }
@@ -313,6 +1652,11 @@ mod tests {
         assert!(bbo.is_valid());
         assert_eq!(bbo.spread(), Some(2));
         assert_eq!(bbo.mid_price(), Some(101));
+
+        // Heavier ask pulls the microprice toward the bid: (100*20 + 102*10) / 30
+        assert_eq!(bbo.microprice(), Some(100));
+        bbo.bid_qty = 20;
+        assert_eq!(bbo.microprice(), Some(101));
     }
 
     #[test]
@@ -331,6 +1675,58 @@ mod tests {
         assert!(bbo.is_valid());
     }
 
+    #[test]
+    fn test_bbo_zero_and_negative_prices() {
+        let mut bbo = BBO::new();
+
+        // A bid at zero is a real level
+        bbo.bid_price = 0;
+        bbo.bid_qty = 10;
+        assert!(bbo.has_bid());
+
+        // So is a negative one
+        bbo.bid_price = -50;
+        assert!(bbo.has_bid());
+
+        bbo.ask_price = -10;
+        bbo.ask_qty = 20;
+        assert!(bbo.is_valid());
+        assert_eq!(bbo.spread(), Some(40));
+        assert_eq!(bbo.mid_price(), Some(-30));
+    }
+
+    #[test]
+    fn test_bbo_mid_rounding() {
+        let mut bbo = BBO::new();
+        bbo.bid_qty = 10;
+        bbo.ask_qty = 10;
+
+        // 100.5 and 101.5 round to the even tick; the other modes go one way
+        bbo.bid_price = 100;
+        bbo.ask_price = 101;
+        assert_eq!(bbo.mid_price(), Some(100));
+        assert_eq!(bbo.mid_price_rounded(MidRounding::Down), Some(100));
+        assert_eq!(bbo.mid_price_rounded(MidRounding::Up), Some(101));
+        assert_eq!(bbo.mid_price_x2(), Some(201));
+        bbo.ask_price = 102;
+        bbo.bid_price = 101;
+        assert_eq!(bbo.mid_price(), Some(102));
+        assert_eq!(bbo.mid_price_rounded(MidRounding::Down), Some(101));
+
+        // Negative halfway mids round the same way: -10.5 -> -10
+        bbo.bid_price = -11;
+        bbo.ask_price = -10;
+        assert_eq!(bbo.mid_price(), Some(-10));
+        assert_eq!(bbo.mid_price_rounded(MidRounding::Down), Some(-11));
+
+        // No overflow near the largest prices
+        bbo.bid_price = Price::MAX - 4;
+        bbo.ask_price = Price::MAX - 1;
+        assert_eq!(bbo.mid_price(), Some(Price::MAX - 3));
+        assert_eq!(bbo.mid_price_rounded(MidRounding::Up), Some(Price::MAX - 2));
+        assert_eq!(bbo.mid_price_x2(), None);
+    }
+
     #[test]
     fn test_process_add_update() {
         // Create a mock receiver without actual socket for testing
@@ -338,7 +1734,7 @@ mod tests {
 
         // Simulate processing an Add update for bid
         let ticker_id: TickerId = 1;
-        let bbo = bbo_map.entry(ticker_id).or_insert_with(BBO::new);
+        let bbo = bbo_map.entry(ticker_id).or_default();
 
         // Simulate bid update
         bbo.bid_price = 10050;
@@ -369,10 +1765,443 @@ mod tests {
         bbo.bid_qty = 100;
         bbo.ask_price = 102;
         bbo.ask_qty = 50;
+        assert!(bbo.is_valid());
 
         // Clear
         bbo = BBO::new();
         assert!(!bbo.is_valid());
         assert_eq!(bbo.bid_price, INVALID_PRICE);
     }
+
+    #[test]
+    fn test_sequence_window() {
+        let mut window = SequenceWindow::new(64);
+        assert_eq!(window.capacity(), 64);
+
+        assert!(window.insert(1));
+        assert!(window.insert(3));
+        assert!(!window.insert(3));
+        // A late sequence inside the window is applied once
+        assert!(window.insert(2));
+        assert!(!window.insert(2));
+        assert_eq!(window.highest(), 3);
+
+        // Jumping past the window forgets everything before it
+        assert!(window.insert(200));
+        assert!(!window.insert(3));
+        assert!(window.insert(150));
+        assert!(!window.insert(150));
+    }
+
+    #[test]
+    fn test_redelivered_cancel_not_applied_twice() {
+        let mut bbo = BBO::new();
+        let mut window = SequenceWindow::new(DEFAULT_DEDUP_WINDOW);
+        let updates = [
+            MarketUpdate::new(MarketUpdateType::Add, 1, 1, Side::Buy as i8, 100, 50, 1).with_seq(1),
+            MarketUpdate::new(MarketUpdateType::Add, 1, 2, Side::Buy as i8, 100, 80, 2).with_seq(2),
+            MarketUpdate::new(MarketUpdateType::Cancel, 1, 1, Side::Buy as i8, 100, 30, 1).with_seq(3),
+        ];
+
+        // The cancel is delivered again, e.g. by a retransmission
+        let mut skipped = 0;
+        for update in updates.iter().chain(std::iter::once(&updates[2])) {
+            if window.insert(update.seq) {
+                bbo.apply(update.update_type().unwrap(), update.side, update.price, update.qty);
+            } else {
+                skipped += 1;
+            }
+        }
+
+        assert_eq!(skipped, 1);
+        assert_eq!(bbo.bid_price, 100);
+        assert_eq!(bbo.bid_qty, 50);
+    }
+
+    #[test]
+    fn test_reserved_ticker_reports_no_data_until_first_update() {
+        let socket = MulticastSocket::new().unwrap();
+        let mut receiver = MarketDataReceiver::with_socket(socket);
+        receiver.reserve_tickers(&[1]);
+
+        assert_eq!(receiver.ticker_count(), 1);
+        assert_eq!(receiver.book_status(1), BookStatus::NoData);
+        assert!(receiver.get_bbo(1).is_none());
+        assert_eq!(receiver.book_status(2), BookStatus::NoData);
+
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 1, 1, Side::Buy as i8, 100, 10, 1));
+        assert_eq!(receiver.book_status(1), BookStatus::OneSided);
+
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, Side::Sell as i8, 102, 10, 2));
+        assert_eq!(receiver.book_status(1), BookStatus::Valid);
+        assert_eq!(receiver.get_bbo(1).unwrap().mid_price(), Some(101));
+
+        // A crossed book is data, not the absence of it
+        let bbo = receiver.get_bbo_mut(1).unwrap();
+        bbo.ask_price = 99;
+        assert_eq!(receiver.book_status(1), BookStatus::Crossed);
+        assert!(receiver.book_status(1).has_data());
+    }
+
+    #[test]
+    fn test_depth_reconstructs_publisher_levels() {
+        use exchange::protocol::MARKET_UPDATE_FLAG_BANDED;
+
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        receiver.set_depth_levels(3);
+        assert!(receiver.get_depth(1, 10).is_none());
+        let level = |update_type, side: Side, price, qty| {
+            MarketUpdate::new(update_type, 1, 0, side as i8, price, qty, 0)
+                .with_flags(MARKET_UPDATE_FLAG_BANDED)
+        };
+
+        // The publisher's book, changed level by level as it publishes
+        let mut publisher = BandedDepth::new();
+        let deltas = [
+            level(MarketUpdateType::Modify, Side::Buy, 9900, 10),
+            level(MarketUpdateType::Modify, Side::Buy, 9950, 20),
+            level(MarketUpdateType::Modify, Side::Sell, 10050, 5),
+            level(MarketUpdateType::Modify, Side::Sell, 10100, 15),
+            level(MarketUpdateType::Modify, Side::Buy, 9950, 25),
+            level(MarketUpdateType::Modify, Side::Sell, 10050, 0),
+            level(MarketUpdateType::Modify, Side::Buy, 9800, 30),
+        ];
+        for update in &deltas {
+            publisher.apply_level(update);
+            receiver.process_update(update);
+        }
+        let depth = receiver.get_depth(1, 10).unwrap();
+        assert_eq!(depth.bids, publisher.levels(Side::Buy));
+        assert_eq!(depth.asks, publisher.levels(Side::Sell));
+        assert_eq!(depth.bids, vec![(9950, 25), (9900, 10), (9800, 30)]);
+
+        // A cancel removes the level; the bound drops the worst levels
+        receiver.process_update(&level(MarketUpdateType::Cancel, Side::Buy, 9900, 0));
+        receiver.process_update(&level(MarketUpdateType::Add, Side::Buy, 9700, 5));
+        receiver.process_update(&level(MarketUpdateType::Add, Side::Buy, 9990, 5));
+        receiver.process_update(&level(MarketUpdateType::Add, Side::Buy, 9960, 5));
+        assert_eq!(receiver.get_depth(1, 10).unwrap().bids, vec![(9990, 5), (9960, 5), (9950, 25)]);
+        assert_eq!(receiver.get_depth(1, 1).unwrap().bids, vec![(9990, 5)]);
+
+        // A snapshot replaces everything held
+        let mut publisher = BandedDepth::new();
+        publisher.set_level(Side::Buy, 9900, 40);
+        publisher.set_level(Side::Sell, 10000, 8);
+        for (side, levels) in [(Side::Buy, publisher.levels(Side::Buy)), (Side::Sell, publisher.levels(Side::Sell))] {
+            for (price, qty) in levels {
+                receiver.process_update(
+                    &MarketUpdate::new(MarketUpdateType::Snapshot, 1, 0, side as i8, price, qty, 42)
+                        .with_flags(MARKET_UPDATE_FLAG_BANDED),
+                );
+            }
+        }
+        let depth = receiver.get_depth(1, 10).unwrap();
+        assert_eq!(depth.bids, publisher.levels(Side::Buy));
+        assert_eq!(depth.asks, publisher.levels(Side::Sell));
+    }
+
+    #[test]
+    fn test_depth_aggregates_order_updates_by_price() {
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        receiver.set_depth_levels(3);
+        let order = |update_type, order_id, side: Side, price, qty| {
+            MarketUpdate::new(update_type, 1, order_id, side as i8, price, qty, order_id)
+        };
+
+        let updates = [
+            order(MarketUpdateType::Add, 1, Side::Buy, 9950, 100),
+            order(MarketUpdateType::Add, 2, Side::Buy, 9950, 50),
+            order(MarketUpdateType::Add, 3, Side::Buy, 9900, 30),
+            order(MarketUpdateType::Add, 4, Side::Buy, 9850, 20),
+            order(MarketUpdateType::Add, 5, Side::Buy, 9800, 10),
+            order(MarketUpdateType::Add, 6, Side::Sell, 10050, 40),
+            order(MarketUpdateType::Add, 7, Side::Sell, 10100, 60),
+        ];
+        for update in &updates {
+            receiver.process_update(update);
+        }
+        let depth = receiver.get_depth(1, 10).unwrap();
+        assert_eq!(depth.bids, vec![(9950, 150), (9900, 30), (9850, 20)]);
+        assert_eq!(depth.asks, vec![(10050, 40), (10100, 60)]);
+
+        // Cancelling one of two orders leaves the rest of the level
+        receiver.process_update(&order(MarketUpdateType::Cancel, 2, Side::Buy, 9950, 50));
+        let depth = receiver.get_depth(1, 3).unwrap();
+        assert_eq!(depth.bids, vec![(9950, 100), (9900, 30), (9850, 20)]);
+
+        // A sell trade takes from the resting bid, then its cancel empties
+        // the level and uncovers the next
+        receiver.process_update(&order(MarketUpdateType::Trade, 1, Side::Sell, 9950, 60));
+        assert_eq!(receiver.get_depth(1, 1).unwrap().bids, vec![(9950, 40)]);
+        receiver.process_update(&order(MarketUpdateType::Cancel, 1, Side::Buy, 9950, 40));
+        let depth = receiver.get_depth(1, 3).unwrap();
+        assert_eq!(depth.bids, vec![(9900, 30), (9850, 20), (9800, 10)]);
+
+        // A buy trade fills the best ask; a modify replaces an order's size
+        receiver.process_update(&order(MarketUpdateType::Trade, 6, Side::Buy, 10050, 40));
+        receiver.process_update(&order(MarketUpdateType::Modify, 7, Side::Sell, 10100, 25));
+        assert_eq!(receiver.get_depth(1, 3).unwrap().asks, vec![(10100, 25)]);
+
+        let levels = receiver.order_book_levels(1).unwrap();
+        assert_eq!(levels.order_count(), 4);
+        assert_eq!(levels.qty_at(Side::Buy, 9900), 30);
+        assert_eq!(levels.level_count(Side::Buy), 3);
+
+        // A snapshot replaces the orders with unattributed levels
+        receiver.process_update(&order(MarketUpdateType::Snapshot, 0, Side::Buy, 9900, 70));
+        let depth = receiver.get_depth(1, 3).unwrap();
+        assert_eq!(depth.bids, vec![(9900, 70)]);
+        assert!(depth.asks.is_empty());
+        assert_eq!(receiver.order_book_levels(1).unwrap().order_count(), 0);
+
+        receiver.process_update(&order(MarketUpdateType::Clear, 0, Side::Buy, 0, 0));
+        assert_eq!(receiver.get_depth(1, 3).unwrap(), Depth::default());
+    }
+
+    #[test]
+    fn test_level_ttl_ages_out_unrefreshed_levels() {
+        use exchange::protocol::MARKET_UPDATE_FLAG_BANDED;
+
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        receiver.set_depth_levels(5);
+        receiver.set_level_ttl(1_000);
+        let level = |update_type, side: Side, price, qty| {
+            MarketUpdate::new(update_type, 1, 0, side as i8, price, qty, 0)
+                .with_flags(MARKET_UPDATE_FLAG_BANDED)
+        };
+
+        receiver.process_update_at(&level(MarketUpdateType::Add, Side::Buy, 9900, 10), 0);
+        receiver.process_update_at(&level(MarketUpdateType::Add, Side::Buy, 9950, 20), 0);
+        receiver.process_update_at(&level(MarketUpdateType::Add, Side::Sell, 10050, 5), 0);
+
+        // Refreshing 9950 restarts its TTL
+        receiver.process_update_at(&level(MarketUpdateType::Modify, Side::Buy, 9950, 25), 800);
+        assert_eq!(receiver.expire_levels(1_000), 0);
+
+        // The unrefreshed levels outlive their TTL, as if their cancels were lost
+        assert_eq!(receiver.expire_levels(1_001), 2);
+        let depth = receiver.get_depth(1, 10).unwrap();
+        assert_eq!(depth.bids, vec![(9950, 25)]);
+        assert!(depth.asks.is_empty());
+
+        assert_eq!(receiver.expire_levels(1_801), 1);
+        assert!(receiver.get_depth(1, 10).unwrap().bids.is_empty());
+        assert_eq!(receiver.levels_aged_out(), 3);
+
+        // Without a TTL nothing ages
+        receiver.set_level_ttl(0);
+        receiver.process_update_at(&level(MarketUpdateType::Add, Side::Buy, 9900, 10), 0);
+        assert_eq!(receiver.expire_levels(u64::MAX), 0);
+        assert_eq!(receiver.get_depth(1, 10).unwrap().bids, vec![(9900, 10)]);
+    }
+
+    #[test]
+    fn test_unsubscribed_ticker_ignored() {
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 3, 1, Side::Buy as i8, 100, 10, 1));
+        assert!(receiver.get_bbo(3).is_some());
+
+        // Subscribing drops state held for other tickers
+        receiver.subscribe(&[1]);
+        assert!(receiver.get_bbo(3).is_none());
+        assert!(receiver.is_subscribed(1));
+        assert!(!receiver.is_subscribed(2));
+
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 2, 2, Side::Buy as i8, 100, 10, 1));
+        assert!(receiver.get_bbo(2).is_none());
+        assert_eq!(receiver.ticker_count(), 0);
+        assert_eq!(receiver.unsubscribed_skipped(), 1);
+
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 1, 3, Side::Buy as i8, 100, 10, 1));
+        assert_eq!(receiver.book_status(1), BookStatus::OneSided);
+
+        receiver.subscribe_all();
+        assert!(receiver.is_subscribed(2));
+    }
+
+    #[test]
+    fn test_failed_rejoin_backs_off() {
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        receiver.set_group("not-a-group", 5000, "0.0.0.0");
+        receiver.set_rejoin(RejoinConfig::new(0).with_backoff(100, 400));
+        assert_eq!(receiver.check_connection(1_000), ReceiverState::Active);
+
+        receiver.note_receive_error(1_000);
+        assert_eq!(receiver.check_connection(1_000), ReceiverState::Rejoining);
+        assert_eq!(receiver.rejoin_attempts(), 1);
+
+        // Retries wait 100, then 200, then stay capped at 400
+        receiver.check_connection(1_099);
+        assert_eq!(receiver.rejoin_attempts(), 1);
+        for (now, attempts) in [(1_100, 2), (1_300, 3), (1_700, 4), (2_100, 5)] {
+            receiver.check_connection(now);
+            assert_eq!(receiver.rejoin_attempts(), attempts);
+        }
+        assert_eq!(receiver.rejoins(), 0);
+        assert_eq!(receiver.receiver_state(), ReceiverState::Rejoining);
+    }
+
+    #[test]
+    fn test_silent_feed_triggers_rejoin() {
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        receiver.set_rejoin(RejoinConfig::new(500));
+
+        assert_eq!(receiver.check_connection(1_000), ReceiverState::Active);
+        assert_eq!(receiver.check_connection(1_500), ReceiverState::Active);
+        // No group to rejoin, so the attempt fails and the receiver keeps trying
+        assert_eq!(receiver.check_connection(1_501), ReceiverState::Rejoining);
+        assert_eq!(receiver.rejoin_attempts(), 1);
+    }
+
+    // Joins a real multicast group; run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_receive_error_rejoins_and_resumes_on_snapshot() {
+        let (group, port) = ("239.255.0.77", 5077);
+        let mut receiver = MarketDataReceiver::new(group, port, "0.0.0.0").unwrap();
+        receiver.set_rejoin(RejoinConfig::new(0).with_backoff(1_000, 1_000));
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 1, 1, Side::Buy as i8, 100, 10, 1));
+        assert!(receiver.get_bbo(1).is_some());
+
+        receiver.note_receive_error(1_000);
+        assert_eq!(receiver.check_connection(1_000), ReceiverState::AwaitingSnapshot);
+        assert_eq!(receiver.rejoin_attempts(), 1);
+        assert_eq!(receiver.rejoins(), 1);
+        assert_eq!(receiver.book_status(1), BookStatus::NoData);
+
+        // Incremental updates are ignored until the snapshot
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Add, 1, 2, Side::Buy as i8, 101, 10, 2));
+        assert_eq!(receiver.book_status(1), BookStatus::NoData);
+
+        let sender = MulticastSocket::new().unwrap();
+        sender.socket().set_multicast_loop_v4(true).unwrap();
+        let snapshot = MarketUpdate::new(MarketUpdateType::Snapshot, 1, 0, Side::Buy as i8, 102, 20, 3);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while receiver.receiver_state() != ReceiverState::Active && std::time::Instant::now() < deadline {
+            sender.send_to(snapshot.as_bytes(), group, port).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            receiver.poll_and_process();
+        }
+
+        assert_eq!(receiver.receiver_state(), ReceiverState::Active);
+        assert_eq!(receiver.get_bbo(1).unwrap().bid_price, 102);
+    }
+
+    #[test]
+    fn test_sequence_gap_marks_book_stale_until_snapshot() {
+        use std::sync::{Arc, Mutex};
+
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        let gaps = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&gaps);
+        receiver.on_gap(Box::new(move |expected, received| sink.lock().unwrap().push((expected, received))));
+        let add = |side: Side, price, seq| {
+            MarketUpdate::new(MarketUpdateType::Add, 1, seq, side as i8, price, 10, seq).with_seq(seq)
+        };
+
+        receiver.process_update(&add(Side::Buy, 100, 1));
+        receiver.process_update(&add(Side::Sell, 105, 2));
+        assert!(receiver.get_bbo(1).unwrap().is_valid());
+        assert_eq!(receiver.gap_count(), 0);
+
+        // Sequence 3 is lost
+        receiver.process_update(&add(Side::Buy, 101, 4));
+        assert_eq!(receiver.gap_count(), 1);
+        assert_eq!(*gaps.lock().unwrap(), vec![(3, 4)]);
+        let bbo = receiver.get_bbo(1).unwrap();
+        assert!(bbo.stale);
+        assert!(!bbo.is_valid());
+        assert_eq!(bbo.spread(), None);
+        assert_eq!(receiver.book_status(1), BookStatus::Stale);
+
+        // Increments keep it stale; a late duplicate is not another gap
+        receiver.process_update(&add(Side::Sell, 104, 5));
+        receiver.process_update(&add(Side::Sell, 104, 5));
+        assert!(!receiver.get_bbo(1).unwrap().is_valid());
+        assert_eq!(receiver.gap_count(), 1);
+
+        // The snapshot rebuilds the book, dropping the stale 101 bid
+        let snapshot = |side: Side, price| MarketUpdate::new(MarketUpdateType::Snapshot, 1, 0, side as i8, price, 20, 5);
+        receiver.process_update(&snapshot(Side::Buy, 99));
+        receiver.process_update(&snapshot(Side::Sell, 104));
+        let bbo = receiver.get_bbo(1).unwrap();
+        assert!(bbo.is_valid());
+        assert_eq!((bbo.bid_price, bbo.ask_price), (99, 104));
+        assert_eq!(receiver.book_status(1), BookStatus::Valid);
+    }
+
+    #[test]
+    fn test_snapshot_discards_stale_increments() {
+        let mut receiver = MarketDataReceiver::with_socket(MulticastSocket::new().unwrap());
+        let add = |price, seq| MarketUpdate::new(MarketUpdateType::Add, 1, seq, Side::Buy as i8, price, 10, seq).with_seq(seq);
+        receiver.process_update(&add(100, 1));
+
+        // A snapshot reflecting sequence 3 replaces the book
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Clear, 1, 0, 0, 0, 0, 3));
+        receiver.process_update(&MarketUpdate::new(MarketUpdateType::Snapshot, 1, 0, Side::Buy as i8, 102, 30, 3));
+        assert_eq!(receiver.snapshot_seq(1), 3);
+        assert_eq!(receiver.get_bbo(1).unwrap().bid_price, 102);
+
+        // Increments it already reflects arrive late and are skipped
+        receiver.process_update(&add(103, 2));
+        receiver.process_update(&add(103, 3));
+        assert_eq!(receiver.stale_skipped(), 2);
+        assert_eq!(receiver.get_bbo(1).unwrap().bid_price, 102);
+
+        receiver.process_update(&add(104, 4));
+        assert_eq!(receiver.get_bbo(1).unwrap().bid_price, 104);
+
+        // Without a snapshot server there is nothing to request or poll
+        assert!(receiver.request_snapshot(1).is_err());
+        assert_eq!(receiver.poll_snapshot(), 0);
+    }
+
+    // Joins a real multicast group; run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_snapshot_request_round_trip() {
+        use exchange::market_data::{MarketDataPublisher, MarketDataPublisherConfig};
+
+        let (group, port) = ("239.255.0.78", 5078);
+        let config = MarketDataPublisherConfig {
+            multicast_addr: group.to_string(),
+            port,
+            snapshot_interval: 0,
+            ..MarketDataPublisherConfig::default()
+        }
+        .with_snapshot_requests(0);
+        let mut publisher = MarketDataPublisher::new(config).unwrap();
+        let mut receiver = MarketDataReceiver::new(group, port, "0.0.0.0").unwrap();
+        receiver
+            .set_snapshot_server("127.0.0.1", publisher.snapshot_request_port().unwrap())
+            .unwrap();
+
+        // The receiver joins late: only the last increment reaches it
+        let add = |order_id, side: Side, price| MarketUpdate::new(MarketUpdateType::Add, 1, order_id, side as i8, price, 10, order_id);
+        publisher.publish(&add(1, Side::Buy, 100)).unwrap();
+        publisher.publish(&add(2, Side::Sell, 105)).unwrap();
+        publisher.publish(&add(3, Side::Buy, 101)).unwrap();
+        receiver.process_update(&add(3, Side::Buy, 101).with_seq(3));
+        assert!(!receiver.get_bbo(1).unwrap().has_ask());
+
+        let client_seq = receiver.request_snapshot(1).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        let mut applied = 0;
+        while applied < 3 && std::time::Instant::now() < deadline {
+            publisher.serve_snapshot_requests().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            applied += receiver.poll_snapshot();
+        }
+
+        // A Clear and both sides, tagged with the request
+        assert_eq!((client_seq, applied), (1, 3));
+        assert_eq!(publisher.snapshot_requests_served(), 1);
+        assert_eq!(receiver.snapshot_seq(1), 3);
+        let bbo = *receiver.get_bbo(1).unwrap();
+        assert_eq!((bbo.bid_price, bbo.ask_price), (101, 105));
+
+        // An increment the snapshot already covers is not applied again
+        receiver.process_update(&add(2, Side::Sell, 105).with_seq(2));
+        assert_eq!(receiver.stale_skipped(), 1);
+    }
 }
@@ -2,14 +2,39 @@
 //!
 //! Provides a low-latency TCP connection to the exchange for order submission
 //! and response handling with sequence number tracking.
+//!
+//! The exchange identifies resting orders by its own order IDs, so the
+//! gateway records the exchange ID from each acknowledgment and uses it for
+//! cancels, mapping the exchange's cancel responses back to client order IDs.
+//!
+//! After a dropped connection, `reconnect` opens a new one, logs in again and
+//! asks the exchange for the fills stamped after the last exec sequence the
+//! gateway saw, so fills that happened during the outage are delivered.
+//! With auto-reconnect enabled, `poll` does this itself, retrying with
+//! exponential backoff until the exchange is back. Orders pending when the
+//! connection dropped are marked uncertain until the exchange reports on them,
+//! and new orders and cancels are refused with a `NotConnected` error until
+//! the connection is restored.
+//!
+//! `send_mass_quote` refreshes two-sided quotes on many tickers in a single
+//! message. Each quoted side is tracked as an ordinary pending order; the
+//! quotes it replaces come back as Canceled responses.
+//!
+//! With an audit writer set, every order sent and response received is also
+//! written as a comma-separated drop-copy line:
+//! `timestamp_ns,type,ticker,side,price,qty,order_id`.
 
 use common::net::tcp::TcpSocket;
-use common::time::{now_nanos, Nanos};
+use common::time::{monotonic_nanos, nanos_since, realtime_nanos, LatencyStats, Nanos};
 use common::{ClientId, OrderId, Price, Qty, Side, TickerId};
 use exchange::protocol::{
-    ClientRequest, ClientRequestType, ClientResponse, CLIENT_RESPONSE_SIZE,
+    encode_mass_quote, ClientRequest, ClientRequestType, ClientResponse, QuoteEntry,
+    CLIENT_RESPONSE_SIZE,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
 
 /// Represents a pending order that has been sent but not yet acknowledged.
 #[derive(Debug, Clone)]
@@ -26,6 +51,19 @@ pub struct PendingOrder {
     pub qty: Qty,
     /// Timestamp when the order was sent (for latency tracking).
     pub sent_time: Nanos,
+    /// Opaque client tag echoed back by the exchange.
+    pub tag: u64,
+}
+
+/// State of the gateway's connection to the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The connection is up.
+    Connected,
+    /// The connection dropped and `poll` is retrying it.
+    Reconnecting,
+    /// The connection dropped and stays down until `reconnect` is called.
+    Disconnected,
 }
 
 /// Order gateway for communicating with the exchange.
@@ -43,6 +81,47 @@ pub struct OrderGateway {
     pending_orders: HashMap<OrderId, PendingOrder>,
     /// Receive buffer for partial message handling.
     recv_buffer: Vec<u8>,
+    /// Responses received while waiting on a specific order, returned by
+    /// subsequent `poll` calls.
+    deferred_responses: VecDeque<ClientResponse>,
+    /// Exchange order ID of each acknowledged pending order, by client order ID.
+    exchange_order_ids: HashMap<OrderId, OrderId>,
+    /// Orders to cancel as soon as the exchange acknowledges them.
+    cancel_on_ack: HashSet<OrderId>,
+    /// Stamp requests with their send time and measure round trips.
+    record_timestamps: bool,
+    /// Round trip from request send to response receipt, in nanoseconds.
+    round_trip: LatencyStats,
+    /// Exchange address, kept to reconnect.
+    addr: String,
+    /// Exchange port, kept to reconnect.
+    port: u16,
+    /// Token of the last login, sent again on reconnect.
+    login_token: Option<u64>,
+    /// False once the exchange closed the connection or a read failed.
+    connected: bool,
+    /// Highest exec sequence seen on a fill.
+    last_exec_seq: u64,
+    /// Successful reconnects.
+    reconnects: u64,
+    /// Reconnect from `poll` after the connection drops.
+    auto_reconnect: bool,
+    /// Delay before the first automatic reconnect attempt.
+    reconnect_base_delay: Duration,
+    /// Longest delay between automatic reconnect attempts.
+    reconnect_max_delay: Duration,
+    /// Delay before the next automatic attempt, doubled after each failure.
+    reconnect_delay: Duration,
+    /// When the next automatic reconnect attempt is due.
+    next_reconnect_at: Option<Instant>,
+    /// Longest a reconnect waits for the exchange to accept the connection.
+    connect_timeout: Duration,
+    /// Pending orders whose state was unknown when the connection dropped.
+    uncertain_orders: HashSet<OrderId>,
+    /// Next mass quote ID to assign.
+    next_quote_id: u64,
+    /// Buffered drop-copy of every order and response, if enabled.
+    audit: Option<BufWriter<Box<dyn Write + Send>>>,
 }
 
 impl OrderGateway {
@@ -66,9 +145,264 @@ impl OrderGateway {
             next_order_id: 1,
             pending_orders: HashMap::new(),
             recv_buffer: Vec::with_capacity(CLIENT_RESPONSE_SIZE * 16),
+            deferred_responses: VecDeque::new(),
+            exchange_order_ids: HashMap::new(),
+            cancel_on_ack: HashSet::new(),
+            record_timestamps: false,
+            round_trip: LatencyStats::new(),
+            addr: addr.to_string(),
+            port,
+            login_token: None,
+            connected: true,
+            last_exec_seq: 0,
+            reconnects: 0,
+            auto_reconnect: false,
+            reconnect_base_delay: Duration::from_millis(100),
+            reconnect_max_delay: Duration::from_secs(5),
+            reconnect_delay: Duration::from_millis(100),
+            next_reconnect_at: None,
+            connect_timeout: Duration::from_millis(50),
+            uncertain_orders: HashSet::new(),
+            next_quote_id: 1,
+            audit: None,
         })
     }
 
+    /// Reconnects to the exchange and requests the fills missed meanwhile.
+    ///
+    /// Logs in again with the last login token, if any, then sends a fill
+    /// query for everything after the last exec sequence seen. Pending orders
+    /// are kept; a partially received response from the old connection is
+    /// discarded. The connect gives up after the connect timeout, so a call
+    /// from the trading loop can't stall it for the kernel's timeout.
+    ///
+    /// # Returns
+    /// Ok once the new connection is up and the query is sent
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        let socket = TcpSocket::connect_timeout(&self.addr, self.port, self.connect_timeout)?;
+        socket.set_nonblocking(true)?;
+        self.socket = socket;
+        self.recv_buffer.clear();
+        self.connected = true;
+
+        let resumed = match self.login_token {
+            Some(token) => self.login(token),
+            None => Ok(()),
+        };
+        if let Err(e) = resumed.and_then(|()| self.request_fills_after(self.last_exec_seq)) {
+            self.connected = false;
+            return Err(e);
+        }
+
+        // Only a complete reconnect resets the backoff
+        self.reconnects += 1;
+        self.reconnect_delay = self.reconnect_base_delay;
+        self.next_reconnect_at = None;
+        Ok(())
+    }
+
+    /// Asks the exchange to resend the retained fills after an exec sequence.
+    ///
+    /// Replayed fills arrive through `poll` like any other response.
+    pub fn request_fills_after(&mut self, after_exec_seq: u64) -> io::Result<()> {
+        let request = ClientRequest::fill_query(self.client_id, after_exec_seq);
+        self.socket.send(request.as_bytes()).map(|_| ())
+    }
+
+    /// Returns false once the exchange has closed the connection or a read
+    /// failed; `reconnect` restores it.
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Returns the highest exec sequence seen on a fill.
+    #[inline]
+    pub fn last_exec_seq(&self) -> u64 {
+        self.last_exec_seq
+    }
+
+    /// Returns the number of successful reconnects.
+    #[inline]
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects
+    }
+
+    /// Returns the state of the connection to the exchange.
+    pub fn state(&self) -> ConnectionState {
+        if self.connected {
+            ConnectionState::Connected
+        } else if self.auto_reconnect {
+            ConnectionState::Reconnecting
+        } else {
+            ConnectionState::Disconnected
+        }
+    }
+
+    /// Enables or disables reconnecting from `poll` after the connection
+    /// drops.
+    pub fn set_auto_reconnect(&mut self, enabled: bool) {
+        self.auto_reconnect = enabled;
+    }
+
+    /// Sets the backoff between automatic reconnect attempts.
+    ///
+    /// The first attempt is made `base_delay` after the connection drops,
+    /// and the delay doubles after each failed attempt up to `max_delay`.
+    pub fn set_reconnect_backoff(&mut self, base_delay: Duration, max_delay: Duration) {
+        self.reconnect_base_delay = base_delay;
+        self.reconnect_max_delay = max_delay.max(base_delay);
+        self.reconnect_delay = base_delay;
+    }
+
+    /// Sets the longest a reconnect waits for the exchange to accept the
+    /// connection.
+    pub fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.connect_timeout = timeout;
+    }
+
+    /// Returns true if the order was pending when the connection dropped
+    /// and the exchange hasn't reported on it since.
+    #[inline]
+    pub fn is_uncertain(&self, order_id: OrderId) -> bool {
+        self.uncertain_orders.contains(&order_id)
+    }
+
+    /// Returns the number of pending orders in an unknown state.
+    #[inline]
+    pub fn uncertain_count(&self) -> usize {
+        self.uncertain_orders.len()
+    }
+
+    /// Marks the connection down and the pending orders uncertain, and
+    /// schedules the first automatic reconnect attempt.
+    fn on_disconnect(&mut self) {
+        if !self.connected {
+            return;
+        }
+        self.connected = false;
+        self.uncertain_orders.extend(self.pending_orders.keys().copied());
+        self.reconnect_delay = self.reconnect_base_delay;
+        self.next_reconnect_at = Some(Instant::now() + self.reconnect_delay);
+    }
+
+    /// Attempts a reconnect once the backoff delay has passed, doubling the
+    /// delay if it fails.
+    fn try_auto_reconnect(&mut self) {
+        let now = Instant::now();
+        if self.next_reconnect_at.is_some_and(|at| now < at) {
+            return;
+        }
+        if self.reconnect().is_err() {
+            self.reconnect_delay = (self.reconnect_delay * 2).min(self.reconnect_max_delay);
+            self.next_reconnect_at = Some(now + self.reconnect_delay);
+        }
+    }
+
+    /// Writes a request to the exchange, refusing while disconnected.
+    ///
+    /// A failed write other than a full socket buffer marks the connection
+    /// down.
+    fn send_request(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.ensure_connected()?;
+        match self.socket.send(bytes) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::WouldBlock {
+                    self.on_disconnect();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns a `NotConnected` error while the connection is down.
+    #[inline]
+    fn ensure_connected(&self) -> io::Result<()> {
+        if self.connected {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotConnected, "not connected to the exchange"))
+        }
+    }
+
+    /// Enables or disables origin timestamps on requests.
+    ///
+    /// When enabled, each request carries its send time, the exchange echoes
+    /// it on the response, and the round trip is recorded on receipt.
+    pub fn set_timestamps(&mut self, enabled: bool) {
+        self.record_timestamps = enabled;
+    }
+
+    /// Returns the round trip latency of timestamped requests.
+    #[inline]
+    pub fn round_trip_latency(&self) -> &LatencyStats {
+        &self.round_trip
+    }
+
+    /// Writes a drop-copy line for every order sent and response received.
+    ///
+    /// Lines are buffered and reach the writer when the buffer fills, on
+    /// `flush_audit`, or when the gateway is dropped. Replacing the writer
+    /// flushes the previous one.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination of the audit log
+    pub fn set_audit_writer(&mut self, writer: Box<dyn Write + Send>) {
+        self.audit = Some(BufWriter::new(writer));
+    }
+
+    /// Flushes buffered audit lines to the audit writer, if one is set.
+    pub fn flush_audit(&mut self) -> io::Result<()> {
+        match self.audit.as_mut() {
+            Some(audit) => audit.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends one line to the audit log, if enabled. Write errors are
+    /// ignored so a failing log never blocks trading.
+    fn audit(
+        &mut self,
+        kind: impl fmt::Debug,
+        ticker_id: TickerId,
+        side: i8,
+        price: Price,
+        qty: Qty,
+        order_id: OrderId,
+    ) {
+        let Some(audit) = self.audit.as_mut() else {
+            return;
+        };
+        let side = match side {
+            s if s == Side::Buy as i8 => "Buy",
+            s if s == Side::Sell as i8 => "Sell",
+            _ => "-",
+        };
+        let _ = writeln!(
+            audit,
+            "{},{:?},{},{},{},{},{}",
+            realtime_nanos().as_u64(),
+            kind,
+            ticker_id,
+            side,
+            price,
+            qty,
+            order_id
+        );
+    }
+
+    /// Returns the origin timestamp for a request sent now, or 0 if
+    /// timestamps are disabled.
+    #[inline]
+    fn origin_ts(&self) -> u64 {
+        if self.record_timestamps {
+            monotonic_nanos().as_u64()
+        } else {
+            0
+        }
+    }
+
     /// Sends a new order to the exchange.
     ///
     /// # Arguments
@@ -78,17 +412,47 @@ impl OrderGateway {
     /// * `qty` - The quantity to trade
     ///
     /// # Returns
-    /// The order ID assigned to this order
+    /// The order ID assigned to this order, or an error if it could not be
+    /// sent, in which case it isn't tracked
     pub fn send_new_order(
         &mut self,
         ticker_id: TickerId,
         side: Side,
         price: Price,
         qty: Qty,
-    ) -> OrderId {
+    ) -> io::Result<OrderId> {
+        self.send_new_order_with_tag(ticker_id, side, price, qty, 0)
+    }
+
+    /// Sends a new order carrying an opaque client tag.
+    ///
+    /// The exchange copies the tag onto every response for this order,
+    /// allowing fills to be correlated with the decision that produced them.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker/instrument to trade
+    /// * `side` - Buy or sell
+    /// * `price` - The limit price in fixed-point format
+    /// * `qty` - The quantity to trade
+    /// * `tag` - Opaque client tag
+    ///
+    /// # Returns
+    /// The order ID assigned to this order, or an error if it could not be
+    /// sent, in which case it isn't tracked
+    pub fn send_new_order_with_tag(
+        &mut self,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+        tag: u64,
+    ) -> io::Result<OrderId> {
+        self.ensure_connected()?;
         let order_id = self.next_order_id;
         self.next_order_id += 1;
 
+        let sent_time = monotonic_nanos();
+        let origin_ts = if self.record_timestamps { sent_time.as_u64() } else { 0 };
         let request = ClientRequest::new(
             ClientRequestType::New,
             self.client_id,
@@ -97,12 +461,12 @@ impl OrderGateway {
             side as i8,
             price,
             qty,
-        );
-
-        let sent_time = now_nanos();
+        )
+        .with_tag(tag)
+        .with_origin_ts(origin_ts);
 
-        // Send the request (ignore partial sends for simplicity in this implementation)
-        let _ = self.socket.send(request.as_bytes());
+        self.send_request(request.as_bytes())?;
+        self.audit(ClientRequestType::New, ticker_id, side as i8, price, qty, order_id);
 
         // Track the pending order
         self.pending_orders.insert(
@@ -114,39 +478,289 @@ impl OrderGateway {
                 price,
                 qty,
                 sent_time,
+                tag,
             },
         );
 
-        order_id
+        Ok(order_id)
+    }
+
+    /// Sends a mass quote replacing this client's quotes on each ticker.
+    ///
+    /// Every side with a non-zero quantity gets the next order ID and is
+    /// tracked as a pending order; the header and all entries go out in one
+    /// write. The exchange cancels the previous quotes on each ticker, even on
+    /// a side left unquoted now.
+    ///
+    /// # Arguments
+    /// * `quotes` - One entry per ticker; order IDs in them are ignored
+    ///
+    /// # Returns
+    /// The (bid, ask) order IDs assigned to each entry, 0 for an unquoted
+    /// side, or an error if the quote could not be sent, in which case none
+    /// of its sides are tracked
+    pub fn send_mass_quote(&mut self, quotes: &[QuoteEntry]) -> io::Result<Vec<(OrderId, OrderId)>> {
+        self.ensure_connected()?;
+        let quote_id = self.next_quote_id;
+        self.next_quote_id += 1;
+        let sent_time = monotonic_nanos();
+        let origin_ts = if self.record_timestamps { sent_time.as_u64() } else { 0 };
+
+        let mut entries = Vec::with_capacity(quotes.len());
+        let mut order_ids = Vec::with_capacity(quotes.len());
+        let mut quoted = Vec::with_capacity(quotes.len() * 2);
+        for quote in quotes {
+            let ticker_id = quote.ticker_id;
+            let sides = [
+                (Side::Buy, quote.bid_price, quote.bid_qty),
+                (Side::Sell, quote.ask_price, quote.ask_qty),
+            ];
+            let [bid_order_id, ask_order_id] = sides.map(|(side, price, qty)| {
+                if qty == 0 {
+                    return 0;
+                }
+                let order_id = self.next_order_id;
+                self.next_order_id += 1;
+                quoted.push(PendingOrder { order_id, ticker_id, side, price, qty, sent_time, tag: 0 });
+                order_id
+            });
+            entries.push(quote.with_order_ids(bid_order_id, ask_order_id));
+            order_ids.push((bid_order_id, ask_order_id));
+        }
+
+        let header = ClientRequest::mass_quote(self.client_id, quote_id, 0).with_origin_ts(origin_ts);
+        self.send_request(&encode_mass_quote(&header, &entries))?;
+        for pending in quoted {
+            let (ticker_id, side, price, qty, order_id) =
+                (pending.ticker_id, pending.side, pending.price, pending.qty, pending.order_id);
+            self.pending_orders.insert(order_id, pending);
+            self.audit(ClientRequestType::MassQuote, ticker_id, side as i8, price, qty, order_id);
+        }
+        Ok(order_ids)
+    }
+
+    /// Resends a pending new order with its original order ID.
+    ///
+    /// Safe against an exchange running with duplicate detection, which
+    /// replays the original response instead of adding a second order.
+    ///
+    /// # Arguments
+    /// * `order_id` - The order ID to resend
+    ///
+    /// # Returns
+    /// `true` if the order was pending and was resent, `false` otherwise or
+    /// if it could not be sent
+    pub fn resend_new_order(&mut self, order_id: OrderId) -> bool {
+        let Some(pending) = self.pending_orders.get(&order_id) else {
+            return false;
+        };
+
+        let request = ClientRequest::new(
+            ClientRequestType::New,
+            self.client_id,
+            pending.ticker_id,
+            order_id,
+            pending.side as i8,
+            pending.price,
+            pending.qty,
+        )
+        .with_tag(pending.tag)
+        .with_origin_ts(self.origin_ts());
+
+        self.send_request(request.as_bytes()).is_ok()
+    }
+
+    /// Sends a new order and waits for the exchange's response, resending it
+    /// with the same order ID if no response arrives within `timeout`.
+    ///
+    /// Responses for other orders received while waiting are kept and
+    /// returned by later `poll` calls.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker/instrument to trade
+    /// * `side` - Buy or sell
+    /// * `price` - The limit price in fixed-point format
+    /// * `qty` - The quantity to trade
+    /// * `max_retries` - Number of resends after the first attempt
+    /// * `timeout` - How long to wait for a response to each attempt
+    ///
+    /// # Returns
+    /// The first response for the order, or a `TimedOut` error once all
+    /// attempts are exhausted (the order stays pending)
+    pub fn submit_with_retry(
+        &mut self,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+        max_retries: u32,
+        timeout: Duration,
+    ) -> io::Result<ClientResponse> {
+        let order_id = self.send_new_order(ticker_id, side, price, qty)?;
+
+        for attempt in 0..=max_retries {
+            if attempt > 0 {
+                self.resend_new_order(order_id);
+            }
+
+            let deadline = Instant::now() + timeout;
+            while Instant::now() < deadline {
+                match self.poll_socket() {
+                    Some(response) if response.client_order_id == order_id => {
+                        return Ok(response);
+                    }
+                    Some(response) => self.deferred_responses.push_back(response),
+                    None => std::hint::spin_loop(),
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("no response for order {} after {} retries", order_id, max_retries),
+        ))
     }
 
     /// Sends a cancel request for an existing order.
     ///
+    /// The request carries the exchange's order ID once the order has been
+    /// acknowledged, and the client order ID before that.
+    ///
     /// # Arguments
     /// * `order_id` - The order ID to cancel
     /// * `ticker_id` - The ticker/instrument of the order
-    pub fn send_cancel(&mut self, order_id: OrderId, ticker_id: TickerId) {
+    ///
+    /// # Returns
+    /// Ok once the cancel is sent, or an error if it could not be
+    pub fn send_cancel(&mut self, order_id: OrderId, ticker_id: TickerId) -> io::Result<()> {
         // Get order details if available, otherwise use defaults
-        let (side, price, qty) = if let Some(pending) = self.pending_orders.get(&order_id) {
-            (pending.side as i8, pending.price, pending.qty)
+        let (side, price, qty, tag) = if let Some(pending) = self.pending_orders.get(&order_id) {
+            (pending.side as i8, pending.price, pending.qty, pending.tag)
         } else {
             // Order not in pending map, use placeholder values
             // The exchange should use the order_id to look up the order
-            (0, 0, 0)
+            (0, 0, 0, 0)
         };
 
+        let exchange_order_id = self.exchange_order_ids.get(&order_id).copied().unwrap_or(order_id);
         let request = ClientRequest::new(
             ClientRequestType::Cancel,
             self.client_id,
             ticker_id,
-            order_id,
+            exchange_order_id,
             side,
             price,
             qty,
-        );
+        )
+        .with_tag(tag)
+        .with_origin_ts(self.origin_ts());
+
+        self.send_request(request.as_bytes())?;
+        self.audit(ClientRequestType::Cancel, ticker_id, side, price, qty, order_id);
+        Ok(())
+    }
+
+    /// Amends a pending order's price and quantity in place.
+    ///
+    /// The exchange keeps the order's ID and answers with an accept on the
+    /// new terms, or a modify reject leaving the order as it was. The
+    /// pending order takes the new terms once the request is sent. Like a
+    /// cancel, the request carries the exchange's order ID once the order
+    /// has been acknowledged.
+    ///
+    /// # Arguments
+    /// * `order_id` - The order ID to amend
+    /// * `price` - The new limit price
+    /// * `qty` - The new quantity
+    ///
+    /// # Returns
+    /// `true` if the order was pending and the amendment was sent, `false`
+    /// otherwise or if it could not be sent
+    pub fn send_replace(&mut self, order_id: OrderId, price: Price, qty: Qty) -> bool {
+        let origin_ts = self.origin_ts();
+        let Some(pending) = self.pending_orders.get(&order_id) else {
+            return false;
+        };
+
+        let exchange_order_id = self.exchange_order_ids.get(&order_id).copied().unwrap_or(order_id);
+        let request = ClientRequest::new(
+            ClientRequestType::Modify,
+            self.client_id,
+            pending.ticker_id,
+            exchange_order_id,
+            pending.side as i8,
+            price,
+            qty,
+        )
+        .with_tag(pending.tag)
+        .with_origin_ts(origin_ts);
+
+        let (ticker_id, side) = (pending.ticker_id, pending.side);
+        if self.send_request(request.as_bytes()).is_err() {
+            return false;
+        }
+        if let Some(pending) = self.pending_orders.get_mut(&order_id) {
+            pending.price = price;
+            pending.qty = qty;
+        }
+        self.audit(ClientRequestType::Modify, ticker_id, side as i8, price, qty, order_id);
+        true
+    }
+
+    /// Cancels every pending order for a ticker.
+    ///
+    /// Acknowledged orders are canceled immediately. Orders the exchange has
+    /// not acknowledged yet are canceled when their acknowledgment arrives,
+    /// since the exchange can't look them up before then.
+    ///
+    /// # Arguments
+    /// * `ticker_id` - The ticker/instrument whose orders to cancel
+    ///
+    /// # Returns
+    /// The number of cancel requests sent now
+    pub fn cancel_all(&mut self, ticker_id: TickerId) -> usize {
+        let order_ids: Vec<OrderId> = self
+            .pending_orders
+            .values()
+            .filter(|pending| pending.ticker_id == ticker_id)
+            .map(|pending| pending.order_id)
+            .collect();
+
+        let mut sent = 0;
+        for order_id in order_ids {
+            if self.exchange_order_ids.contains_key(&order_id) {
+                if self.send_cancel(order_id, ticker_id).is_ok() {
+                    sent += 1;
+                }
+            } else {
+                self.cancel_on_ack.insert(order_id);
+            }
+        }
+        sent
+    }
+
+    /// Cancels every pending order on every ticker, as `cancel_all` does
+    /// for one.
+    ///
+    /// # Returns
+    /// The number of cancel requests sent now
+    pub fn cancel_all_tickers(&mut self) -> usize {
+        let tickers: HashSet<TickerId> = self.pending_orders.values().map(|pending| pending.ticker_id).collect();
+        tickers.into_iter().map(|ticker_id| self.cancel_all(ticker_id)).sum()
+    }
 
-        // Send the cancel request
-        let _ = self.socket.send(request.as_bytes());
+    /// Logs in to the exchange as this session's client.
+    ///
+    /// Must be the first message on the connection when the exchange requires
+    /// login. The exchange answers with Accepted, or with InvalidRequest and
+    /// closes the connection if the token is wrong.
+    ///
+    /// # Arguments
+    /// * `token` - The token the exchange holds for this client ID
+    pub fn login(&mut self, token: u64) -> io::Result<()> {
+        self.login_token = Some(token);
+        let request = ClientRequest::login(self.client_id, token);
+        self.socket.send(request.as_bytes()).map(|_| ())
     }
 
     /// Polls for incoming responses from the exchange.
@@ -158,8 +772,26 @@ impl OrderGateway {
     /// `Some(ClientResponse)` if a complete response was received,
     /// `None` if no data is available
     pub fn poll(&mut self) -> Option<ClientResponse> {
+        // Responses set aside by submit_with_retry come first
+        if let Some(response) = self.deferred_responses.pop_front() {
+            return Some(response);
+        }
+
+        if !self.connected && self.auto_reconnect {
+            self.try_auto_reconnect();
+        }
+
+        self.poll_socket()
+    }
+
+    /// Reads the next response from the socket and updates pending orders.
+    fn poll_socket(&mut self) -> Option<ClientResponse> {
         // Try to receive data
         match self.socket.try_recv() {
+            Ok(Some([])) => {
+                // The exchange closed the connection
+                self.on_disconnect();
+            }
             Ok(Some(data)) => {
                 // Append received data to buffer
                 self.recv_buffer.extend_from_slice(data);
@@ -168,7 +800,8 @@ impl OrderGateway {
                 // No data available
             }
             Err(_) => {
-                // Connection error - could log or handle differently
+                // Connection error; `reconnect` restores the session
+                self.on_disconnect();
                 return None;
             }
         }
@@ -183,26 +816,71 @@ impl OrderGateway {
                 // Remove the processed message from the buffer
                 self.recv_buffer.drain(..CLIENT_RESPONSE_SIZE);
 
+                let exec_seq = response_copy.exec_seq;
+                self.last_exec_seq = self.last_exec_seq.max(exec_seq);
+
+                // Responses to our own timestamped requests carry their send time
+                let origin_ts = response_copy.origin_ts;
+                if origin_ts != 0 {
+                    self.round_trip.record(nanos_since(Nanos(origin_ts)));
+                }
+
                 // Update pending orders based on response
                 let client_order_id = response_copy.client_order_id;
                 if let Some(response_type) = response_copy.response_type() {
                     use exchange::protocol::ClientResponseType;
+                    // Cancel responses carry the exchange's order ID
+                    let order_id = match response_type {
+                        ClientResponseType::Canceled | ClientResponseType::CancelRejected => {
+                            self.client_order_id_for(client_order_id)
+                        }
+                        _ => client_order_id,
+                    };
+                    self.uncertain_orders.remove(&order_id);
+                    let qty = if response_type == ClientResponseType::Filled {
+                        response_copy.exec_qty
+                    } else {
+                        response_copy.leaves_qty
+                    };
+                    self.audit(
+                        response_type,
+                        response_copy.ticker_id,
+                        response_copy.side,
+                        response_copy.price,
+                        qty,
+                        order_id,
+                    );
+
                     match response_type {
-                        ClientResponseType::Canceled
-                        | ClientResponseType::CancelRejected
-                        | ClientResponseType::InvalidRequest => {
+                        ClientResponseType::Canceled | ClientResponseType::CancelRejected => {
+                            self.remove_pending(order_id);
+                        }
+                        ClientResponseType::InvalidRequest
+                        | ClientResponseType::LastLookRejected
+                        | ClientResponseType::LevelLimitRejected
+                        | ClientResponseType::RiskRejected => {
                             // Remove from pending on terminal states
-                            self.pending_orders.remove(&client_order_id);
+                            self.remove_pending(client_order_id);
                         }
                         ClientResponseType::Filled => {
                             // Check if fully filled (leaves_qty == 0)
                             if response_copy.leaves_qty == 0 {
-                                self.pending_orders.remove(&client_order_id);
+                                self.remove_pending(client_order_id);
+                            } else {
+                                self.on_acknowledged(&response_copy);
                             }
                         }
-                        ClientResponseType::Accepted => {
+                        ClientResponseType::Accepted | ClientResponseType::Replenished => {
+                            // Order is resting, keep tracking; a refilled
+                            // slice may rest under a new exchange ID
+                            self.on_acknowledged(&response_copy);
+                        }
+                        ClientResponseType::ModifyRejected => {
                             // Order is still pending, keep tracking
                         }
+                        ClientResponseType::Triggered => {
+                            // Stop activated, its accept or fill follows
+                        }
                     }
                 }
 
@@ -213,6 +891,38 @@ impl OrderGateway {
         None
     }
 
+    /// Records the exchange order ID of a resting order, and cancels it if a
+    /// cancel was requested before it was acknowledged.
+    fn on_acknowledged(&mut self, response: &ClientResponse) {
+        let client_order_id = response.client_order_id;
+        let Some(ticker_id) = self.pending_orders.get(&client_order_id).map(|p| p.ticker_id) else {
+            return;
+        };
+
+        self.exchange_order_ids.insert(client_order_id, response.market_order_id);
+        if self.cancel_on_ack.remove(&client_order_id) && self.send_cancel(client_order_id, ticker_id).is_err() {
+            // Try again on the next acknowledgment, such as a replayed fill
+            self.cancel_on_ack.insert(client_order_id);
+        }
+    }
+
+    /// Maps an exchange order ID back to the client order ID, falling back
+    /// to the ID itself if it isn't a known exchange order ID.
+    fn client_order_id_for(&self, exchange_order_id: OrderId) -> OrderId {
+        self.exchange_order_ids
+            .iter()
+            .find(|&(_, &id)| id == exchange_order_id)
+            .map_or(exchange_order_id, |(&client_order_id, _)| client_order_id)
+    }
+
+    /// Stops tracking an order.
+    fn remove_pending(&mut self, order_id: OrderId) {
+        self.pending_orders.remove(&order_id);
+        self.exchange_order_ids.remove(&order_id);
+        self.cancel_on_ack.remove(&order_id);
+        self.uncertain_orders.remove(&order_id);
+    }
+
     /// Gets a reference to a pending order by its order ID.
     ///
     /// # Arguments
@@ -256,6 +966,7 @@ mod tests {
             price: 10050,
             qty: 100,
             sent_time: Nanos::new(1000000),
+            tag: 0xABCD,
         };
 
         assert_eq!(pending.order_id, 1);
@@ -263,5 +974,472 @@ mod tests {
         assert_eq!(pending.side, Side::Buy);
         assert_eq!(pending.price, 10050);
         assert_eq!(pending.qty, 100);
+        assert_eq!(pending.tag, 0xABCD);
+    }
+
+    #[test]
+    fn test_submit_with_retry_after_lost_ack() {
+        use common::net::tcp::TcpListener;
+        use exchange::matching_engine::{MatchingEngine, MatchingEngineConfig};
+        use exchange::protocol::{ClientResponseType, CLIENT_REQUEST_SIZE};
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        // Exchange side: dedup enabled, and the ack to the first attempt is lost
+        let server = thread::spawn(move || {
+            let mut socket = listener.accept().unwrap();
+            let config = MatchingEngineConfig::new().with_duplicate_detection(true);
+            let mut engine = MatchingEngine::with_config(config);
+            engine.add_ticker(1);
+
+            let mut buffer = Vec::new();
+            let mut received = 0;
+            while received < 2 {
+                buffer.extend_from_slice(socket.recv().unwrap());
+                while buffer.len() >= CLIENT_REQUEST_SIZE {
+                    let request = *ClientRequest::from_bytes(&buffer[..CLIENT_REQUEST_SIZE]).unwrap();
+                    buffer.drain(..CLIENT_REQUEST_SIZE);
+                    received += 1;
+
+                    let (response, _) = engine.process_request(&request);
+                    if received > 1 {
+                        socket.send(response.as_bytes()).unwrap();
+                    }
+                }
+            }
+
+            let book = engine.get_order_book(1).unwrap();
+            (book.order_count(), engine.stats().duplicate_orders)
+        });
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        let response = gateway
+            .submit_with_retry(1, Side::Buy, 10000, 100, 3, Duration::from_millis(50))
+            .unwrap();
+
+        let msg_type = response.msg_type;
+        let client_order_id = response.client_order_id;
+        assert_eq!(msg_type, ClientResponseType::Accepted as u8);
+        assert_eq!(client_order_id, 1);
+
+        // The resend reused the order ID, so only one order rests
+        let (order_count, duplicates) = server.join().unwrap();
+        assert_eq!(order_count, 1);
+        assert_eq!(duplicates, 1);
+    }
+
+    #[test]
+    fn test_mass_quote_replaces_tracked_quotes() {
+        use common::net::tcp::TcpListener;
+        use exchange::matching_engine::{MatchingEngine, MatchingEngineConfig};
+        use exchange::protocol::{CLIENT_REQUEST_SIZE, QUOTE_ENTRY_SIZE};
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        // Exchange side: decode two mass quotes and answer every response
+        let server = thread::spawn(move || {
+            let mut socket = listener.accept().unwrap();
+            let mut engine = MatchingEngine::with_config(MatchingEngineConfig::new().with_mass_quotes(true));
+            for ticker_id in 1..=3 {
+                engine.add_ticker(ticker_id);
+            }
+
+            let mut buffer = Vec::new();
+            let mut quotes = 0;
+            while quotes < 2 {
+                buffer.extend_from_slice(socket.recv().unwrap());
+                while buffer.len() >= CLIENT_REQUEST_SIZE {
+                    let header = *ClientRequest::from_bytes(&buffer[..CLIENT_REQUEST_SIZE]).unwrap();
+                    let len = CLIENT_REQUEST_SIZE + header.qty as usize * QUOTE_ENTRY_SIZE;
+                    if buffer.len() < len {
+                        break;
+                    }
+                    let entries: Vec<QuoteEntry> = buffer[CLIENT_REQUEST_SIZE..len]
+                        .chunks_exact(QUOTE_ENTRY_SIZE)
+                        .map(|chunk| *QuoteEntry::from_bytes(chunk).unwrap())
+                        .collect();
+                    buffer.drain(..len);
+                    quotes += 1;
+
+                    let (responses, _) = engine.process_mass_quote(&header, &entries);
+                    for response in responses {
+                        socket.send(response.as_bytes()).unwrap();
+                    }
+                }
+            }
+
+            (1..=3)
+                .map(|ticker_id| {
+                    let book = engine.get_order_book(ticker_id).unwrap();
+                    (book.best_bid(), book.best_ask())
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        let first = gateway.send_mass_quote(&[
+            QuoteEntry::new(1, 9990, 10, 10010, 10),
+            QuoteEntry::new(2, 4990, 10, 5010, 10),
+            QuoteEntry::new(3, 1990, 10, 2010, 10),
+        ]).unwrap();
+        assert_eq!(first, vec![(1, 2), (3, 4), (5, 6)]);
+        assert_eq!(gateway.pending_count(), 6);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut responses = 0;
+        while responses < 6 && Instant::now() < deadline {
+            if gateway.poll().is_some() {
+                responses += 1;
+            }
+        }
+        assert_eq!(responses, 6);
+
+        let second = gateway.send_mass_quote(&[
+            QuoteEntry::new(1, 9995, 10, 10005, 10),
+            QuoteEntry::new(2, 4995, 10, 5005, 0),
+            QuoteEntry::new(3, 1995, 10, 2005, 10),
+        ]).unwrap();
+        assert_eq!(second, vec![(7, 8), (9, 0), (10, 11)]);
+
+        // Six cancels of the old quotes and five accepts of the new ones
+        while responses < 17 && Instant::now() < deadline {
+            if gateway.poll().is_some() {
+                responses += 1;
+            }
+        }
+        assert_eq!(gateway.pending_count(), 5);
+        assert!(gateway.get_pending(1).is_none());
+        assert_eq!(gateway.get_pending(10).unwrap().price, 1995);
+
+        let books = server.join().unwrap();
+        assert_eq!(books, vec![(Some(9995), Some(10005)), (Some(4995), None), (Some(1995), Some(2005))]);
+    }
+
+    #[test]
+    fn test_cancel_all_cancels_tracked_orders() {
+        use common::net::tcp::TcpListener;
+        use exchange::matching_engine::MatchingEngine;
+        use exchange::protocol::{ClientRequestType, CLIENT_REQUEST_SIZE};
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        // Exchange side: answer three orders and then three cancels
+        let server = thread::spawn(move || {
+            let mut socket = listener.accept().unwrap();
+            let mut engine = MatchingEngine::new();
+            engine.add_ticker(1);
+
+            let mut buffer = Vec::new();
+            let mut cancel_ids = Vec::new();
+            while cancel_ids.len() < 3 {
+                buffer.extend_from_slice(socket.recv().unwrap());
+                while buffer.len() >= CLIENT_REQUEST_SIZE {
+                    let request = *ClientRequest::from_bytes(&buffer[..CLIENT_REQUEST_SIZE]).unwrap();
+                    buffer.drain(..CLIENT_REQUEST_SIZE);
+
+                    if request.request_type() == Some(ClientRequestType::Cancel) {
+                        cancel_ids.push(request.order_id);
+                    }
+                    let (response, _) = engine.process_request(&request);
+                    socket.send(response.as_bytes()).unwrap();
+                }
+            }
+
+            (cancel_ids, engine.get_order_book(1).unwrap().order_count())
+        });
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        gateway.send_new_order(1, Side::Buy, 10000, 100).unwrap();
+        gateway.send_new_order(1, Side::Sell, 10100, 100).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut accepted = 0;
+        while accepted < 2 && Instant::now() < deadline {
+            if gateway.poll().is_some() {
+                accepted += 1;
+            }
+        }
+        assert_eq!(accepted, 2);
+
+        // The third order isn't acknowledged yet, so its cancel is deferred
+        gateway.send_new_order(1, Side::Buy, 9900, 100).unwrap();
+        assert_eq!(gateway.cancel_all(1), 2);
+
+        while gateway.pending_count() > 0 && Instant::now() < deadline {
+            gateway.poll();
+        }
+        assert_eq!(gateway.pending_count(), 0);
+
+        let (mut cancel_ids, order_count) = server.join().unwrap();
+        cancel_ids.sort_unstable();
+        assert_eq!(cancel_ids, vec![1, 2, 3]);
+        assert_eq!(order_count, 0);
+    }
+
+    #[test]
+    fn test_replace_updates_pending_terms() {
+        use common::net::tcp::TcpListener;
+        use exchange::protocol::CLIENT_REQUEST_SIZE;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        // Exchange side: read the order and its amendment, answer nothing
+        let server = thread::spawn(move || {
+            let mut socket = listener.accept().unwrap();
+            let mut received = 0;
+            while received < 2 * CLIENT_REQUEST_SIZE {
+                received += socket.recv().unwrap().len();
+            }
+        });
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        let order_id = gateway.send_new_order(1, Side::Buy, 10000, 100).unwrap();
+        assert!(gateway.send_replace(order_id, 10010, 60));
+
+        let pending = gateway.get_pending(order_id).unwrap();
+        assert_eq!(pending.price, 10010);
+        assert_eq!(pending.qty, 60);
+        assert!(!gateway.send_replace(order_id + 1, 10010, 60));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_auto_reconnect_recovers_when_exchange_returns() {
+        use common::net::tcp::TcpListener;
+        use exchange::matching_engine::MatchingEngine;
+        use exchange::protocol::{ClientRequestType, CLIENT_REQUEST_SIZE};
+        use std::sync::mpsc;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+        let (restart_tx, restart_rx) = mpsc::channel::<()>();
+
+        // Exchange side: accept one order and go away, then come back on the
+        // same port when told to and accept one more
+        let server = thread::spawn(move || {
+            let mut engine = MatchingEngine::new();
+            engine.add_ticker(1);
+            let serve = |listener: TcpListener, engine: &mut MatchingEngine| {
+                let mut socket = listener.accept().unwrap();
+                let mut buffer = Vec::new();
+                let mut requests = Vec::new();
+                while !requests.contains(&ClientRequestType::New) {
+                    buffer.extend_from_slice(socket.recv().unwrap());
+                    while buffer.len() >= CLIENT_REQUEST_SIZE {
+                        let request = *ClientRequest::from_bytes(&buffer[..CLIENT_REQUEST_SIZE]).unwrap();
+                        buffer.drain(..CLIENT_REQUEST_SIZE);
+                        requests.push(request.request_type().unwrap());
+                        if request.request_type() == Some(ClientRequestType::New) {
+                            let (response, _) = engine.process_request(&request);
+                            socket.send(response.as_bytes()).unwrap();
+                        }
+                    }
+                }
+                requests
+            };
+
+            let first = serve(listener, &mut engine);
+            restart_rx.recv().unwrap();
+            let second = serve(TcpListener::bind("127.0.0.1", port).unwrap(), &mut engine);
+            (first, second)
+        });
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        gateway.set_reconnect_backoff(Duration::from_millis(5), Duration::from_millis(20));
+        gateway.set_auto_reconnect(true);
+        assert_eq!(gateway.state(), ConnectionState::Connected);
+
+        let resting = gateway.send_new_order(1, Side::Buy, 10000, 100).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while gateway.poll().is_none() && Instant::now() < deadline {}
+
+        // The exchange closes the connection with the order still resting
+        while gateway.state() == ConnectionState::Connected && Instant::now() < deadline {
+            gateway.poll();
+        }
+        assert_eq!(gateway.state(), ConnectionState::Reconnecting);
+        assert!(gateway.is_uncertain(resting));
+        assert_eq!(gateway.uncertain_count(), 1);
+
+        // Attempts keep failing while nothing listens
+        let retry_until = Instant::now() + Duration::from_millis(100);
+        while Instant::now() < retry_until {
+            assert!(gateway.poll().is_none());
+        }
+        assert_eq!(gateway.state(), ConnectionState::Reconnecting);
+        assert_eq!(gateway.reconnects(), 0);
+
+        // Once the exchange is back the next attempt succeeds
+        restart_tx.send(()).unwrap();
+        while gateway.state() != ConnectionState::Connected && Instant::now() < deadline {
+            gateway.poll();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(gateway.state(), ConnectionState::Connected);
+        assert_eq!(gateway.reconnects(), 1);
+
+        let order_id = gateway.send_new_order(1, Side::Sell, 10100, 100).unwrap();
+        let mut response = None;
+        while response.is_none() && Instant::now() < deadline {
+            response = gateway.poll();
+        }
+        let client_order_id = response.unwrap().client_order_id;
+        assert_eq!(client_order_id, order_id);
+        assert!(!gateway.is_uncertain(order_id));
+        assert!(gateway.is_uncertain(resting));
+
+        // The new connection starts with a query for the missed fills
+        let (first, second) = server.join().unwrap();
+        assert_eq!(first, vec![ClientRequestType::New]);
+        assert_eq!(second, vec![ClientRequestType::FillQuery, ClientRequestType::New]);
+    }
+
+    #[test]
+    fn test_disconnect_without_auto_reconnect_stays_down() {
+        use common::net::tcp::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+        let server = thread::spawn(move || drop(listener.accept().unwrap()));
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        server.join().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while gateway.is_connected() && Instant::now() < deadline {
+            gateway.poll();
+        }
+        assert_eq!(gateway.state(), ConnectionState::Disconnected);
+        gateway.poll();
+        assert_eq!(gateway.reconnects(), 0);
+
+        // Nothing is sent or tracked while down
+        let error = gateway.send_new_order(1, Side::Buy, 10000, 100).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotConnected);
+        assert!(gateway.send_mass_quote(&[QuoteEntry::new(1, 9990, 10, 10010, 10)]).is_err());
+        assert!(gateway.send_cancel(1, 1).is_err());
+        assert_eq!(gateway.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_reconnect_backoff_grows_to_max_while_exchange_is_down() {
+        use common::net::tcp::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+        let server = thread::spawn(move || drop(listener.accept().unwrap()));
+
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        gateway.set_reconnect_backoff(Duration::from_millis(2), Duration::from_millis(16));
+        gateway.set_auto_reconnect(true);
+        server.join().unwrap();
+
+        // 2, 4, 8 and 16ms of failed attempts: the delay stops at the max
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            gateway.poll();
+        }
+        assert_eq!(gateway.state(), ConnectionState::Reconnecting);
+        assert_eq!(gateway.reconnect_delay, Duration::from_millis(16));
+        assert_eq!(gateway.reconnects(), 0);
+    }
+
+    /// In-memory audit writer the test can read back after handing it over.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_audit_writer_records_orders_and_responses() {
+        use common::net::tcp::TcpListener;
+        use exchange::matching_engine::MatchingEngine;
+        use exchange::protocol::CLIENT_REQUEST_SIZE;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket().local_addr().unwrap().as_socket().unwrap().port();
+
+        // Exchange side: answer two orders and a cancel
+        let server = thread::spawn(move || {
+            let mut socket = listener.accept().unwrap();
+            let mut engine = MatchingEngine::new();
+            engine.add_ticker(1);
+
+            let mut buffer = Vec::new();
+            let mut received = 0;
+            while received < 3 {
+                buffer.extend_from_slice(socket.recv().unwrap());
+                while buffer.len() >= CLIENT_REQUEST_SIZE {
+                    let request = *ClientRequest::from_bytes(&buffer[..CLIENT_REQUEST_SIZE]).unwrap();
+                    buffer.drain(..CLIENT_REQUEST_SIZE);
+                    received += 1;
+
+                    let (response, _) = engine.process_request(&request);
+                    socket.send(response.as_bytes()).unwrap();
+                }
+            }
+        });
+
+        let audit = SharedBuffer::default();
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 7).unwrap();
+        gateway.set_audit_writer(Box::new(audit.clone()));
+        gateway.send_new_order(1, Side::Buy, 10000, 100).unwrap();
+        gateway.send_new_order(1, Side::Sell, 10100, 50).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut responses = 0;
+        while responses < 2 && Instant::now() < deadline {
+            if gateway.poll().is_some() {
+                responses += 1;
+            }
+        }
+        assert_eq!(responses, 2);
+
+        gateway.send_cancel(1, 1).unwrap();
+        while gateway.pending_count() > 1 && Instant::now() < deadline {
+            gateway.poll();
+        }
+        server.join().unwrap();
+
+        // Nothing reaches the writer until the buffer is flushed
+        assert!(audit.0.lock().unwrap().is_empty());
+        gateway.flush_audit().unwrap();
+
+        let log = String::from_utf8(audit.0.lock().unwrap().clone()).unwrap();
+        let records: Vec<Vec<&str>> = log.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(records.len(), 6);
+        for record in &records {
+            assert_eq!(record.len(), 7);
+            assert!(record[0].parse::<u64>().unwrap() > 0);
+        }
+
+        let fields: Vec<&[&str]> = records.iter().map(|record| &record[1..]).collect();
+        assert_eq!(fields[0], ["New", "1", "Buy", "10000", "100", "1"]);
+        assert_eq!(fields[1], ["New", "1", "Sell", "10100", "50", "2"]);
+        assert_eq!(fields[2], ["Accepted", "1", "Buy", "10000", "100", "1"]);
+        assert_eq!(fields[3], ["Accepted", "1", "Sell", "10100", "50", "2"]);
+        assert_eq!(fields[4], ["Cancel", "1", "Buy", "10000", "100", "1"]);
+        assert_eq!(fields[5], ["Canceled", "1", "Buy", "10000", "100", "1"]);
     }
 }
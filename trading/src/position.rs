@@ -1,7 +1,92 @@
 // Position tracking
-
+//
+// With funding rates set, held positions accrue a funding or borrow cost on
+// their notional at the last mark, at separate rates for longs and shorts.
+// `accrue_funding` books the cost for the time since its previous call into
+// each position's `funding_pnl`, which is subtracted from total P&L. Call it
+// on a timer, and before marking to a new price so the elapsed time is
+// charged at the price held during it. A flat position accrues nothing.
+
+use crate::market_data::BBO;
+use crate::report::{BacktestReport, TradeStats};
 use common::{Price, Qty, Side, TickerId};
-use std::collections::HashMap;
+use exchange::matching_engine::FeeSchedule;
+use exchange::protocol::LiquidityFlag;
+use std::collections::{HashMap, VecDeque};
+
+/// Default number of snapshots kept per ticker
+pub const DEFAULT_MAX_SNAPSHOTS: usize = 1024;
+
+/// Nanoseconds in a day, the period funding rates are quoted over
+pub const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Funding or borrow cost of holding positions, per day of notional
+///
+/// A positive rate is paid by the holder, a negative rate is received.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FundingRates {
+    /// Basis points of notional per day charged on long positions
+    pub long_bps_per_day: f64,
+    /// Basis points of notional per day charged on short positions
+    pub short_bps_per_day: f64,
+}
+
+impl FundingRates {
+    /// Creates funding rates for long and short positions
+    pub fn new(long_bps_per_day: f64, short_bps_per_day: f64) -> Self {
+        Self {
+            long_bps_per_day,
+            short_bps_per_day,
+        }
+    }
+
+    /// Returns true if either side is charged
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.long_bps_per_day != 0.0 || self.short_bps_per_day != 0.0
+    }
+
+    /// Returns the cost in cents of holding a position at a price
+    ///
+    /// # Arguments
+    /// * `position` - Signed position (positive = long)
+    /// * `price` - Mark price in cents
+    /// * `elapsed_ns` - Time the position was held
+    pub fn cost(&self, position: i64, price: Price, elapsed_ns: u64) -> f64 {
+        let bps = match position.signum() {
+            1 => self.long_bps_per_day,
+            -1 => self.short_bps_per_day,
+            _ => return 0.0,
+        };
+        let notional = position.unsigned_abs() as f64 * price.abs() as f64;
+        notional * bps / 10_000.0 * elapsed_ns as f64 / NANOS_PER_DAY as f64
+    }
+}
+
+/// Reference price used to mark positions for unrealized P&L
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MarkSource {
+    /// Midpoint of the best bid and ask
+    #[default]
+    Mid,
+    /// Price of the most recent trade print
+    LastTrade,
+    /// Size-weighted mid of the best bid and ask
+    Microprice,
+}
+
+impl MarkSource {
+    /// Returns the mark implied by a BBO, or None if this source does not
+    /// mark from quotes or the BBO is not two-sided
+    #[inline]
+    pub fn mark_from_bbo(&self, bbo: &BBO) -> Option<Price> {
+        match self {
+            MarkSource::Mid => bbo.mid_price(),
+            MarkSource::Microprice => bbo.microprice(),
+            MarkSource::LastTrade => None,
+        }
+    }
+}
 
 /// Tracks position and P&L for a single ticker
 #[derive(Debug, Clone, Default)]
@@ -24,6 +109,13 @@ pub struct Position {
     pub avg_open_price: Price,
     /// Last traded/quoted price
     pub last_price: Price,
+    /// Exchange fees paid in cents (negative for net rebates)
+    pub fees_paid: i64,
+    /// Outcomes of the fills that closed or reduced the position
+    pub trade_stats: TradeStats,
+    /// Funding and borrow costs accrued in cents (negative for net funding
+    /// received)
+    pub funding_pnl: i64,
 }
 
 impl Position {
@@ -39,6 +131,9 @@ impl Position {
             unrealized_pnl: 0,
             avg_open_price: 0,
             last_price: 0,
+            fees_paid: 0,
+            trade_stats: TradeStats::default(),
+            funding_pnl: 0,
         }
     }
 
@@ -47,7 +142,11 @@ impl Position {
     /// Handles the P&L and average price calculations when a trade fills.
     /// When closing or reducing a position, realized P&L is calculated.
     /// When opening or adding to a position, average price is updated.
-    pub fn on_fill(&mut self, side: Side, qty: Qty, price: Price) {
+    ///
+    /// # Returns
+    /// The P&L realized by this fill if it closed or reduced the position,
+    /// otherwise None
+    pub fn on_fill(&mut self, side: Side, qty: Qty, price: Price) -> Option<i64> {
         let signed_qty = match side {
             Side::Buy => qty as i64,
             Side::Sell => -(qty as i64),
@@ -61,6 +160,7 @@ impl Position {
 
         let old_position = self.position;
         let new_position = old_position + signed_qty;
+        let mut realized = None;
 
         // Determine if we're closing, opening, or both
         if old_position == 0 {
@@ -80,6 +180,8 @@ impl Position {
                 self.avg_open_price - price
             };
             self.realized_pnl += pnl_per_unit * closing_qty;
+            self.trade_stats.record(pnl_per_unit * closing_qty);
+            realized = Some(pnl_per_unit * closing_qty);
 
             // Check if we're flipping the position
             if new_position != 0 && (new_position > 0) != (old_position > 0) {
@@ -97,6 +199,36 @@ impl Position {
 
         // Update unrealized P&L
         self.update_unrealized_pnl();
+        realized
+    }
+
+    /// Update position on a batch of fills
+    ///
+    /// Each run of consecutive same-side fills is applied as a single fill
+    /// of their total quantity at their volume-weighted price, so the average
+    /// price is recomputed once per run rather than once per fill. For the
+    /// partial fills of one order this gives the same result as applying them
+    /// one by one, up to the rounding of the intermediate averages.
+    pub fn on_fills_batch(&mut self, fills: &[(Side, Qty, Price)]) {
+        for run in fills.chunk_by(|a, b| a.0 == b.0) {
+            let qty: Qty = run.iter().map(|&(_, qty, _)| qty).sum();
+            if qty == 0 {
+                continue;
+            }
+            let notional: i64 = run.iter().map(|&(_, qty, price)| price * qty as i64).sum();
+            self.on_fill(run[0].0, qty, notional / qty as i64);
+        }
+
+        // Mark at the last execution, as sequential fills would
+        if let Some(&(_, _, price)) = fills.last() {
+            self.update_market_price(price);
+        }
+    }
+
+    /// Record an exchange fee (or rebate, if negative) against this position
+    #[inline]
+    pub fn apply_fee(&mut self, fee: i64) {
+        self.fees_paid += fee;
     }
 
     /// Add pending order quantity
@@ -127,10 +259,10 @@ impl Position {
         self.position
     }
 
-    /// Returns total P&L (realized + unrealized)
+    /// Returns total P&L (realized + unrealized - fees - funding)
     #[inline]
     pub fn total_pnl(&self) -> i64 {
-        self.realized_pnl + self.unrealized_pnl
+        self.realized_pnl + self.unrealized_pnl - self.fees_paid - self.funding_pnl
     }
 
     /// Returns maximum long exposure (position + pending buys)
@@ -159,12 +291,133 @@ impl Position {
     }
 }
 
+/// A synthetic product made of weighted component tickers
+///
+/// Each weight is the signed quantity of the component per basket unit, so
+/// a basket's position and P&L are the weighted sums of its legs. Weights of
+/// 1 and -1 make a spread whose legs net out when their weighted positions
+/// are equal and opposite.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Basket {
+    /// Component tickers and their weights, in insertion order
+    components: Vec<(TickerId, i64)>,
+}
+
+impl Basket {
+    /// Create an empty basket
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder method to add a component, replacing its weight if present
+    pub fn with_component(mut self, ticker_id: TickerId, weight: i64) -> Self {
+        match self.components.iter_mut().find(|(id, _)| *id == ticker_id) {
+            Some(component) => component.1 = weight,
+            None => self.components.push((ticker_id, weight)),
+        }
+        self
+    }
+
+    /// Returns the components and their weights
+    #[inline]
+    pub fn components(&self) -> &[(TickerId, i64)] {
+        &self.components
+    }
+
+    /// Returns the weight of a component, if it is in the basket
+    pub fn weight(&self, ticker_id: TickerId) -> Option<i64> {
+        self.components
+            .iter()
+            .find(|(id, _)| *id == ticker_id)
+            .map(|&(_, weight)| weight)
+    }
+
+    /// Returns the number of components
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns true if the basket has no components
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+/// Position and P&L of a ticker at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionSnapshot {
+    /// Caller-supplied timestamp (e.g. nanoseconds)
+    pub timestamp: u64,
+    /// Position at the time of the snapshot
+    pub position: i64,
+    /// Realized P&L in cents
+    pub realized_pnl: i64,
+    /// Unrealized P&L in cents
+    pub unrealized_pnl: i64,
+}
+
+/// Builds the order that brings a position back to flat.
+///
+/// A long position is sold at the best bid and a short one bought at the best
+/// ask, so the order is marketable against the touch. Size beyond the touch
+/// rests at that price.
+///
+/// # Arguments
+/// * `position` - Current net position (positive = long, negative = short)
+/// * `bbo` - Current top of book for the ticker
+///
+/// # Returns
+/// The side, quantity and price to send, or `None` if the position is flat
+/// or the side to trade against is missing or stale
+pub fn build_flatten_order(position: i64, bbo: &BBO) -> Option<(Side, Qty, Price)> {
+    if position == 0 || bbo.stale {
+        return None;
+    }
+    let qty = Qty::try_from(position.unsigned_abs()).unwrap_or(Qty::MAX);
+    if position > 0 {
+        bbo.has_bid().then_some((Side::Sell, qty, bbo.bid_price))
+    } else {
+        bbo.has_ask().then_some((Side::Buy, qty, bbo.ask_price))
+    }
+}
+
+/// Consolidated position and P&L across every ticker
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortfolioSummary {
+    /// Realized P&L summed over all tickers, in cents
+    pub total_realized: i64,
+    /// Unrealized P&L summed over all tickers, in cents
+    pub total_unrealized: i64,
+    /// Sum of absolute positions, so longs and shorts both add
+    pub gross_position: i64,
+    /// Sum of signed positions, so longs and shorts offset
+    pub net_position: i64,
+    /// Net position and total P&L of each ticker, ordered by ticker ID
+    pub per_ticker: Vec<(TickerId, i64, i64)>,
+}
+
 /// Manages positions across all tickers
 pub struct PositionKeeper {
     /// Per-ticker position tracking
     positions: HashMap<TickerId, Position>,
     /// Cached total P&L across all positions
     total_pnl: i64,
+    /// Maker/taker rates applied to fills that report liquidity
+    fee_schedule: FeeSchedule,
+    /// Recent snapshots per ticker, oldest first
+    snapshots: HashMap<TickerId, VecDeque<PositionSnapshot>>,
+    /// Maximum number of snapshots kept per ticker
+    max_snapshots: usize,
+    /// Reference price for unrealized P&L
+    mark_source: MarkSource,
+    /// Funding rates charged on held positions
+    funding_rates: FundingRates,
+    /// Time funding was last accrued up to, None before the first accrual
+    last_funding_ns: Option<u64>,
+    /// Funding accrued per ticker but not yet booked as a whole cent
+    funding_carry: HashMap<TickerId, f64>,
 }
 
 impl PositionKeeper {
@@ -173,9 +426,98 @@ impl PositionKeeper {
         Self {
             positions: HashMap::new(),
             total_pnl: 0,
+            fee_schedule: FeeSchedule::default(),
+            snapshots: HashMap::new(),
+            max_snapshots: DEFAULT_MAX_SNAPSHOTS,
+            mark_source: MarkSource::default(),
+            funding_rates: FundingRates::default(),
+            last_funding_ns: None,
+            funding_carry: HashMap::new(),
         }
     }
 
+    /// Creates a position keeper that charges fees from the given schedule
+    pub fn with_fee_schedule(fee_schedule: FeeSchedule) -> Self {
+        Self {
+            fee_schedule,
+            ..Self::new()
+        }
+    }
+
+    /// Returns the fee schedule applied to fills
+    #[inline]
+    pub fn fee_schedule(&self) -> &FeeSchedule {
+        &self.fee_schedule
+    }
+
+    /// Sets the fee schedule applied to subsequent fills
+    pub fn set_fee_schedule(&mut self, fee_schedule: FeeSchedule) {
+        self.fee_schedule = fee_schedule;
+    }
+
+    /// Returns the reference price used for unrealized P&L
+    #[inline]
+    pub fn mark_source(&self) -> MarkSource {
+        self.mark_source
+    }
+
+    /// Sets the reference price used for unrealized P&L
+    ///
+    /// Takes effect from the next BBO or trade routed through `on_bbo_update`
+    /// or `on_trade_print`.
+    pub fn set_mark_source(&mut self, mark_source: MarkSource) {
+        self.mark_source = mark_source;
+    }
+
+    /// Returns the funding rates charged on held positions
+    #[inline]
+    pub fn funding_rates(&self) -> FundingRates {
+        self.funding_rates
+    }
+
+    /// Sets the funding rates charged on held positions from the next accrual
+    pub fn set_funding_rates(&mut self, funding_rates: FundingRates) {
+        self.funding_rates = funding_rates;
+    }
+
+    /// Accrue funding on every held position since the previous accrual
+    ///
+    /// The first call only starts the clock. Fractions of a cent are carried
+    /// to later accruals.
+    ///
+    /// # Arguments
+    /// * `now_ns` - Current time on a monotonic clock
+    ///
+    /// # Returns
+    /// The funding booked by this call in cents
+    pub fn accrue_funding(&mut self, now_ns: u64) -> i64 {
+        let elapsed_ns = match self.last_funding_ns.replace(now_ns) {
+            Some(last_ns) => now_ns.saturating_sub(last_ns),
+            None => return 0,
+        };
+        if elapsed_ns == 0 || !self.funding_rates.is_enabled() {
+            return 0;
+        }
+
+        let mut booked = 0;
+        for position in self.positions.values_mut() {
+            let cost = self.funding_rates.cost(position.position, position.last_price, elapsed_ns);
+            if cost == 0.0 {
+                continue;
+            }
+            let carry = self.funding_carry.entry(position.ticker_id).or_insert(0.0);
+            *carry += cost;
+            let cents = carry.trunc();
+            *carry -= cents;
+            position.funding_pnl += cents as i64;
+            booked += cents as i64;
+        }
+        if booked != 0 {
+            self.recalculate_total_pnl();
+        }
+        booked
+    }
+
     /// Get read-only reference to a position
     pub fn get_position(&self, ticker_id: TickerId) -> Option<&Position> {
         self.positions.get(&ticker_id)
@@ -189,10 +531,48 @@ impl PositionKeeper {
     }
 
     /// Process a fill for a ticker
-    pub fn on_fill(&mut self, ticker_id: TickerId, side: Side, qty: Qty, price: Price) {
+    ///
+    /// # Returns
+    /// The P&L realized by the fill if it closed or reduced the position,
+    /// otherwise None
+    pub fn on_fill(&mut self, ticker_id: TickerId, side: Side, qty: Qty, price: Price) -> Option<i64> {
+        let position = self.get_position_mut(ticker_id);
+        let realized = position.on_fill(side, qty, price);
+        self.recalculate_total_pnl();
+        realized
+    }
+
+    /// Process a batch of fills for a ticker, recomputing P&L once
+    ///
+    /// See `Position::on_fills_batch` for how the fills are combined.
+    pub fn on_fills_batch(&mut self, ticker_id: TickerId, fills: &[(Side, Qty, Price)]) {
+        let position = self.get_position_mut(ticker_id);
+        position.on_fills_batch(fills);
+        self.recalculate_total_pnl();
+    }
+
+    /// Process a fill and charge the fee for its liquidity flag
+    ///
+    /// Makers and takers are charged at the schedule's respective rates;
+    /// fills without a liquidity flag are not charged.
+    ///
+    /// # Returns
+    /// The P&L realized by the fill (before fees) if it closed or reduced
+    /// the position, otherwise None
+    pub fn on_fill_with_liquidity(
+        &mut self,
+        ticker_id: TickerId,
+        side: Side,
+        qty: Qty,
+        price: Price,
+        liquidity: LiquidityFlag,
+    ) -> Option<i64> {
+        let fee = self.fee_schedule.fee(liquidity, price, qty);
         let position = self.get_position_mut(ticker_id);
-        position.on_fill(side, qty, price);
+        let realized = position.on_fill(side, qty, price);
+        position.apply_fee(fee);
         self.recalculate_total_pnl();
+        realized
     }
 
     /// Update market price for a ticker
@@ -203,6 +583,20 @@ impl PositionKeeper {
         }
     }
 
+    /// Mark a ticker from a BBO change, if the mark source uses quotes
+    pub fn on_bbo_update(&mut self, ticker_id: TickerId, bbo: &BBO) {
+        if let Some(mark) = self.mark_source.mark_from_bbo(bbo) {
+            self.update_market_price(ticker_id, mark);
+        }
+    }
+
+    /// Mark a ticker from a trade print, if the mark source uses trades
+    pub fn on_trade_print(&mut self, ticker_id: TickerId, price: Price) {
+        if self.mark_source == MarkSource::LastTrade {
+            self.update_market_price(ticker_id, price);
+        }
+    }
+
     /// Get total P&L across all positions
     #[inline]
     pub fn total_pnl(&self) -> i64 {
@@ -214,6 +608,103 @@ impl PositionKeeper {
         self.positions.values()
     }
 
+    /// Get a consolidated view of positions and P&L across all tickers
+    ///
+    /// Per-ticker entries are `(ticker_id, net position, total P&L)`, where
+    /// total P&L is net of fees and funding as in `Position::total_pnl`.
+    pub fn portfolio_summary(&self) -> PortfolioSummary {
+        let mut summary = PortfolioSummary::default();
+        for position in self.positions.values() {
+            summary.total_realized += position.realized_pnl;
+            summary.total_unrealized += position.unrealized_pnl;
+            summary.gross_position += position.position.abs();
+            summary.net_position += position.position;
+            summary
+                .per_ticker
+                .push((position.ticker_id, position.position, position.total_pnl()));
+        }
+        summary.per_ticker.sort_unstable_by_key(|&(ticker_id, _, _)| ticker_id);
+        summary
+    }
+
+    /// Get the weighted net position of a basket
+    ///
+    /// Components without a position count as flat.
+    pub fn basket_position(&self, basket: &Basket) -> i64 {
+        self.basket_sum(basket, Position::net_position)
+    }
+
+    /// Get the weighted total P&L of a basket, in cents
+    pub fn basket_pnl(&self, basket: &Basket) -> i64 {
+        self.basket_sum(basket, Position::total_pnl)
+    }
+
+    /// Weighted sum of a per-position value over a basket's components
+    fn basket_sum(&self, basket: &Basket, value: impl Fn(&Position) -> i64) -> i64 {
+        basket
+            .components()
+            .iter()
+            .filter_map(|&(ticker_id, weight)| {
+                self.positions.get(&ticker_id).map(|position| weight * value(position))
+            })
+            .sum()
+    }
+
+    /// Record a snapshot of every position at the given timestamp
+    ///
+    /// Each ticker keeps at most `max_snapshots` snapshots; the oldest is
+    /// dropped when the limit is reached.
+    pub fn record_snapshot(&mut self, timestamp: u64) {
+        if self.max_snapshots == 0 {
+            return;
+        }
+
+        for position in self.positions.values() {
+            let series = self
+                .snapshots
+                .entry(position.ticker_id)
+                .or_insert_with(|| VecDeque::with_capacity(self.max_snapshots));
+            if series.len() >= self.max_snapshots {
+                series.pop_front();
+            }
+            series.push_back(PositionSnapshot {
+                timestamp,
+                position: position.position,
+                realized_pnl: position.realized_pnl,
+                unrealized_pnl: position.unrealized_pnl,
+            });
+        }
+    }
+
+    /// Iterate over the recorded snapshots of a ticker, oldest first
+    pub fn snapshots(&self, ticker_id: TickerId) -> impl Iterator<Item = &PositionSnapshot> {
+        self.snapshots.get(&ticker_id).into_iter().flatten()
+    }
+
+    /// Build a performance report for a ticker from its closing fills and
+    /// recorded snapshots
+    pub fn backtest_report(&self, ticker_id: TickerId) -> BacktestReport {
+        let stats = self.positions.get(&ticker_id).map(|p| p.trade_stats).unwrap_or_default();
+        BacktestReport::new(&stats, self.snapshots(ticker_id))
+    }
+
+    /// Returns the maximum number of snapshots kept per ticker
+    #[inline]
+    pub fn max_snapshots(&self) -> usize {
+        self.max_snapshots
+    }
+
+    /// Sets the maximum number of snapshots kept per ticker (0 disables recording)
+    ///
+    /// Existing series longer than the new limit lose their oldest snapshots.
+    pub fn set_max_snapshots(&mut self, max_snapshots: usize) {
+        self.max_snapshots = max_snapshots;
+        for series in self.snapshots.values_mut() {
+            let excess = series.len().saturating_sub(max_snapshots);
+            series.drain(..excess);
+        }
+    }
+
     /// Recalculate total P&L from all positions
     fn recalculate_total_pnl(&mut self) {
         self.total_pnl = self.positions.values().map(|p| p.total_pnl()).sum();
@@ -461,6 +952,41 @@ mod tests {
         assert_eq!(pos2.position, -50);
     }
 
+    #[test]
+    fn test_fills_batch_matches_sequential_fills() {
+        let partials = [(Side::Buy, 100, 10000), (Side::Buy, 100, 10020), (Side::Buy, 200, 10010)];
+
+        let mut sequential = PositionKeeper::new();
+        for &(side, qty, price) in &partials {
+            sequential.on_fill(1, side, qty, price);
+        }
+        let mut batched = PositionKeeper::new();
+        batched.on_fills_batch(1, &partials);
+
+        let expected = sequential.get_position(1).unwrap();
+        let pos = batched.get_position(1).unwrap();
+        assert_eq!(pos.position, 400);
+        assert_eq!(pos.position, expected.position);
+        assert_eq!(pos.avg_open_price, expected.avg_open_price);
+        assert_eq!(pos.last_price, expected.last_price);
+        assert_eq!(pos.volume_traded, expected.volume_traded);
+        assert_eq!(batched.total_pnl(), sequential.total_pnl());
+
+        // Closing partials realize the same P&L
+        let closes = [(Side::Sell, 150, 10050), (Side::Sell, 50, 10030)];
+        for &(side, qty, price) in &closes {
+            sequential.on_fill(1, side, qty, price);
+        }
+        batched.on_fills_batch(1, &closes);
+
+        let expected = sequential.get_position(1).unwrap();
+        let pos = batched.get_position(1).unwrap();
+        assert_eq!(pos.position, 200);
+        assert_eq!(pos.realized_pnl, expected.realized_pnl);
+        assert_eq!(pos.unrealized_pnl, expected.unrealized_pnl);
+        assert_eq!(batched.total_pnl(), sequential.total_pnl());
+    }
+
     #[test]
     fn test_position_keeper_total_pnl() {
         let mut keeper = PositionKeeper::new();
@@ -478,6 +1004,48 @@ mod tests {
         assert_eq!(keeper.total_pnl(), 100000);
     }
 
+    #[test]
+    fn test_funding_accrues_while_held_and_stops_when_flat() {
+        let mut keeper = PositionKeeper::new();
+        // 10 bps a day for longs, 30 bps a day to borrow for shorts
+        keeper.set_funding_rates(FundingRates::new(10.0, 30.0));
+
+        // Long 100 at 10000 cents: 1,000,000 cents of notional, 1,000 a day
+        keeper.on_fill(1, Side::Buy, 100, 10000);
+        keeper.update_market_price(1, 10000);
+        assert_eq!(keeper.accrue_funding(0), 0);
+        assert_eq!(keeper.accrue_funding(NANOS_PER_DAY / 2), 500);
+        assert_eq!(keeper.accrue_funding(NANOS_PER_DAY), 500);
+        assert_eq!(keeper.get_position(1).unwrap().funding_pnl, 1000);
+        assert_eq!(keeper.total_pnl(), -1000);
+
+        // Flat positions accrue nothing
+        keeper.on_fill(1, Side::Sell, 100, 10000);
+        assert_eq!(keeper.accrue_funding(3 * NANOS_PER_DAY), 0);
+        assert_eq!(keeper.get_position(1).unwrap().funding_pnl, 1000);
+        assert_eq!(keeper.total_pnl(), -1000);
+
+        // Shorts pay the borrow rate
+        keeper.on_fill(2, Side::Sell, 100, 10000);
+        assert_eq!(keeper.accrue_funding(4 * NANOS_PER_DAY), 3000);
+        assert_eq!(keeper.total_pnl(), -4000);
+    }
+
+    #[test]
+    fn test_funding_carries_fractions_of_a_cent() {
+        let mut keeper = PositionKeeper::new();
+        keeper.set_funding_rates(FundingRates::new(1.0, 0.0));
+        // 10,000 cents of notional at 1 bp a day: one cent a day
+        keeper.on_fill(1, Side::Buy, 1, 10000);
+        keeper.accrue_funding(0);
+        let booked: i64 = (1..=4).map(|i| keeper.accrue_funding(i * NANOS_PER_DAY / 4)).sum();
+        assert_eq!(booked, 1);
+
+        // Disabled rates charge nothing
+        keeper.set_funding_rates(FundingRates::default());
+        assert_eq!(keeper.accrue_funding(10 * NANOS_PER_DAY), 0);
+    }
+
     #[test]
     fn test_position_keeper_all_positions() {
         let mut keeper = PositionKeeper::new();
@@ -490,6 +1058,61 @@ mod tests {
         assert_eq!(positions.len(), 3);
     }
 
+    #[test]
+    fn test_build_flatten_order() {
+        let bbo = BBO {
+            bid_price: 9990,
+            bid_qty: 10,
+            ask_price: 10010,
+            ask_qty: 10,
+            stale: false,
+        };
+
+        // Longs sell at the bid, shorts buy at the ask, flat sends nothing
+        assert_eq!(build_flatten_order(25, &bbo), Some((Side::Sell, 25, 9990)));
+        assert_eq!(build_flatten_order(-40, &bbo), Some((Side::Buy, 40, 10010)));
+        assert_eq!(build_flatten_order(0, &bbo), None);
+
+        // No price to trade against
+        let no_bid = BBO { bid_qty: 0, ..bbo };
+        assert_eq!(build_flatten_order(25, &no_bid), None);
+        assert_eq!(build_flatten_order(-40, &no_bid), Some((Side::Buy, 40, 10010)));
+        assert_eq!(build_flatten_order(25, &BBO { stale: true, ..bbo }), None);
+    }
+
+    #[test]
+    fn test_portfolio_summary_aggregates_tickers() {
+        let mut keeper = PositionKeeper::new();
+
+        // Ticker 1: long 100 at 5000, half sold at 5200 (+10000), marked at 5100
+        keeper.on_fill(1, Side::Buy, 100, 5000);
+        keeper.on_fill(1, Side::Sell, 50, 5200);
+        keeper.update_market_price(1, 5100);
+
+        // Ticker 2: short 80 at 3000, marked at 3100
+        keeper.on_fill(2, Side::Sell, 80, 3000);
+        keeper.update_market_price(2, 3100);
+
+        // Ticker 3: long 20 at 4000, marked at 4300
+        keeper.on_fill(3, Side::Buy, 20, 4000);
+        keeper.update_market_price(3, 4300);
+
+        let summary = keeper.portfolio_summary();
+        assert_eq!(summary.total_realized, 10000);
+        // 50 * 100 - 80 * 100 + 20 * 300
+        assert_eq!(summary.total_unrealized, 3000);
+        assert_eq!(summary.gross_position, 50 + 80 + 20);
+        assert_eq!(summary.net_position, 50 - 80 + 20);
+        assert_eq!(
+            summary.per_ticker,
+            vec![(1, 50, 15000), (2, -80, -8000), (3, 20, 6000)]
+        );
+        assert_eq!(summary.total_realized + summary.total_unrealized, keeper.total_pnl());
+
+        // An empty keeper summarises to zero
+        assert_eq!(PositionKeeper::new().portfolio_summary(), PortfolioSummary::default());
+    }
+
     #[test]
     fn test_position_flip_long_to_short() {
         let mut pos = Position::new(1);
@@ -525,4 +1148,115 @@ mod tests {
 
         assert_eq!(pos.volume_traded, 225);
     }
+
+    #[test]
+    fn test_fees_by_liquidity() {
+        // 1bp maker rebate, 3bp taker fee
+        let mut keeper = PositionKeeper::with_fee_schedule(FeeSchedule::new(-1.0, 3.0));
+
+        // Notional 100 * $100.00 = 1,000,000 cents
+        keeper.on_fill_with_liquidity(1, Side::Buy, 100, 10000, LiquidityFlag::Taker);
+        assert_eq!(keeper.get_position(1).unwrap().fees_paid, 300);
+
+        keeper.on_fill_with_liquidity(1, Side::Sell, 100, 10000, LiquidityFlag::Maker);
+        let pos = keeper.get_position(1).unwrap();
+        assert_eq!(pos.fees_paid, 200);
+        assert_eq!(pos.realized_pnl, 0);
+        assert_eq!(pos.total_pnl(), -200);
+        assert_eq!(keeper.total_pnl(), -200);
+
+        // Fills without a liquidity flag are free
+        keeper.on_fill_with_liquidity(1, Side::Buy, 100, 10000, LiquidityFlag::None);
+        assert_eq!(keeper.get_position(1).unwrap().fees_paid, 200);
+    }
+
+    #[test]
+    fn test_snapshots_track_unrealized_pnl() {
+        let mut keeper = PositionKeeper::new();
+        keeper.on_fill(1, Side::Buy, 100, 5000);
+
+        for (timestamp, price) in [(1, 5000), (2, 5050), (3, 4980)] {
+            keeper.update_market_price(1, price);
+            keeper.record_snapshot(timestamp);
+        }
+
+        let series: Vec<_> = keeper.snapshots(1).map(|s| (s.timestamp, s.unrealized_pnl)).collect();
+        assert_eq!(series, vec![(1, 0), (2, 5000), (3, -2000)]);
+        assert!(keeper.snapshots(1).all(|s| s.position == 100 && s.realized_pnl == 0));
+        assert_eq!(keeper.snapshots(2).count(), 0);
+    }
+
+    #[test]
+    fn test_snapshots_bounded() {
+        let mut keeper = PositionKeeper::new();
+        keeper.set_max_snapshots(2);
+        keeper.on_fill(1, Side::Buy, 10, 5000);
+
+        for timestamp in 1..=3 {
+            keeper.record_snapshot(timestamp);
+        }
+        let timestamps: Vec<_> = keeper.snapshots(1).map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![2, 3]);
+
+        keeper.set_max_snapshots(1);
+        assert_eq!(keeper.snapshots(1).count(), 1);
+        assert_eq!(keeper.snapshots(1).next().unwrap().timestamp, 3);
+    }
+
+    #[test]
+    fn test_mark_source_selects_reference_price() {
+        // Long 100 @ 50.00; quotes 50.90 x 100 / 51.10 x 300, last print 52.00
+        let bbo = BBO {
+            bid_price: 5090,
+            bid_qty: 100,
+            ask_price: 5110,
+            ask_qty: 300,
+            stale: false,
+        };
+        let unrealized = |mark_source| {
+            let mut keeper = PositionKeeper::new();
+            keeper.set_mark_source(mark_source);
+            keeper.on_fill(1, Side::Buy, 100, 5000);
+            keeper.on_bbo_update(1, &bbo);
+            keeper.on_trade_print(1, 5200);
+            keeper.get_position(1).unwrap().unrealized_pnl
+        };
+
+        assert_eq!(unrealized(MarkSource::Mid), 100 * (5100 - 5000));
+        assert_eq!(unrealized(MarkSource::LastTrade), 100 * (5200 - 5000));
+        // (5090*300 + 5110*100) / 400 = 5095
+        assert_eq!(unrealized(MarkSource::Microprice), 100 * (5095 - 5000));
+    }
+
+    #[test]
+    fn test_basket_nets_equal_and_opposite_legs() {
+        let spread = Basket::new().with_component(1, 1).with_component(2, -1);
+        assert_eq!(spread.len(), 2);
+        assert_eq!(spread.weight(2), Some(-1));
+
+        let mut keeper = PositionKeeper::new();
+        assert_eq!(keeper.basket_position(&spread), 0);
+
+        // Legs of +100 and -100 once weighted
+        keeper.on_fill(1, Side::Buy, 100, 5000);
+        keeper.on_fill(2, Side::Buy, 100, 7000);
+        assert_eq!(keeper.basket_position(&spread), 0);
+
+        // Both legs gain 1.00: the P&L nets out as well
+        keeper.update_market_price(1, 5100);
+        keeper.update_market_price(2, 7100);
+        assert_eq!(keeper.basket_pnl(&spread), 0);
+
+        // The first leg outperforms
+        keeper.update_market_price(1, 5300);
+        assert_eq!(keeper.basket_pnl(&spread), 100 * 300 - 100 * 100);
+
+        keeper.on_fill(1, Side::Sell, 40, 5300);
+        assert_eq!(keeper.basket_position(&spread), -40);
+
+        // Re-adding a component replaces its weight
+        let doubled = spread.with_component(1, 2);
+        assert_eq!(doubled.len(), 2);
+        assert_eq!(keeper.basket_position(&doubled), 2 * 60 - 100);
+    }
 }
@@ -0,0 +1,221 @@
+//! Strategy performance report for backtests and live sessions.
+//!
+//! A trade is a fill that closes or reduces a position, and its outcome is
+//! the P&L it realized, before fees. `TradeStats` accumulates those outcomes
+//! as fills arrive. `BacktestReport` combines them with the equity curve
+//! recorded in position snapshots into the figures used to compare strategy
+//! runs: trade count, win rate, average win and loss, profit factor and a
+//! Sharpe-like ratio of the equity changes between snapshots.
+
+use crate::position::PositionSnapshot;
+use std::fmt;
+
+/// Outcomes of the closing fills of a position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TradeStats {
+    /// Fills that closed or reduced the position
+    pub trades: u64,
+    /// Trades that realized a profit
+    pub wins: u64,
+    /// Trades that realized a loss
+    pub losses: u64,
+    /// Sum of the profits of winning trades in cents
+    pub gross_profit: i64,
+    /// Sum of the losses of losing trades in cents (zero or negative)
+    pub gross_loss: i64,
+}
+
+impl TradeStats {
+    /// Records the P&L realized by a closing fill
+    pub fn record(&mut self, realized_pnl: i64) {
+        self.trades += 1;
+        if realized_pnl > 0 {
+            self.wins += 1;
+            self.gross_profit += realized_pnl;
+        } else if realized_pnl < 0 {
+            self.losses += 1;
+            self.gross_loss += realized_pnl;
+        }
+    }
+
+    /// Resets all statistics
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Performance summary of a ticker's trading
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BacktestReport {
+    /// Fills that closed or reduced the position
+    pub trades: u64,
+    /// Trades that realized a profit
+    pub wins: u64,
+    /// Trades that realized a loss
+    pub losses: u64,
+    /// Fraction of trades that realized a profit (0 without trades)
+    pub win_rate: f64,
+    /// Mean profit of winning trades in cents (0 without wins)
+    pub avg_win: f64,
+    /// Mean loss of losing trades in cents, negative (0 without losses)
+    pub avg_loss: f64,
+    /// Gross profit over gross loss; infinite with profits and no losses,
+    /// 0 without profits
+    pub profit_factor: f64,
+    /// Mean over standard deviation of the equity changes between
+    /// consecutive snapshots, not annualized (0 with fewer than two changes
+    /// or a flat curve)
+    pub sharpe: f64,
+    /// Realized plus unrealized P&L at the last snapshot in cents
+    pub final_pnl: i64,
+    /// Snapshots the equity curve was built from
+    pub snapshots: usize,
+}
+
+impl BacktestReport {
+    /// Builds a report from closing fill outcomes and an equity curve
+    ///
+    /// # Arguments
+    /// * `stats` - Outcomes of the closing fills
+    /// * `snapshots` - Position snapshots, oldest first
+    pub fn new<'a>(stats: &TradeStats, snapshots: impl IntoIterator<Item = &'a PositionSnapshot>) -> Self {
+        let equity: Vec<i64> = snapshots
+            .into_iter()
+            .map(|snapshot| snapshot.realized_pnl + snapshot.unrealized_pnl)
+            .collect();
+
+        let ratio = |numerator: i64, denominator: u64| {
+            if denominator == 0 {
+                0.0
+            } else {
+                numerator as f64 / denominator as f64
+            }
+        };
+        let profit_factor = match (stats.gross_profit, stats.gross_loss) {
+            (0, _) => 0.0,
+            (_, 0) => f64::INFINITY,
+            (profit, loss) => profit as f64 / -loss as f64,
+        };
+
+        Self {
+            trades: stats.trades,
+            wins: stats.wins,
+            losses: stats.losses,
+            win_rate: ratio(stats.wins as i64, stats.trades),
+            avg_win: ratio(stats.gross_profit, stats.wins),
+            avg_loss: ratio(stats.gross_loss, stats.losses),
+            profit_factor,
+            sharpe: Self::sharpe_of(&equity),
+            final_pnl: equity.last().copied().unwrap_or(0),
+            snapshots: equity.len(),
+        }
+    }
+
+    /// Mean over sample standard deviation of the changes of a curve
+    fn sharpe_of(equity: &[i64]) -> f64 {
+        let changes: Vec<f64> = equity.windows(2).map(|pair| (pair[1] - pair[0]) as f64).collect();
+        if changes.len() < 2 {
+            return 0.0;
+        }
+        let n = changes.len() as f64;
+        let mean = changes.iter().sum::<f64>() / n;
+        let variance = changes.iter().map(|change| (change - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        if variance == 0.0 {
+            0.0
+        } else {
+            mean / variance.sqrt()
+        }
+    }
+
+    /// Returns the Sharpe-like ratio scaled to a year
+    ///
+    /// # Arguments
+    /// * `periods_per_year` - Snapshot intervals in a year
+    #[inline]
+    pub fn annualized_sharpe(&self, periods_per_year: f64) -> f64 {
+        self.sharpe * periods_per_year.max(0.0).sqrt()
+    }
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trades={} wins={} losses={} win_rate={:.1}% avg_win={:.1} avg_loss={:.1} \
+             profit_factor={:.2} sharpe={:.3} final_pnl={} snapshots={}",
+            self.trades,
+            self.wins,
+            self.losses,
+            self.win_rate * 100.0,
+            self.avg_win,
+            self.avg_loss,
+            self.profit_factor,
+            self.sharpe,
+            self.final_pnl,
+            self.snapshots
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::PositionKeeper;
+    use common::Side;
+
+    #[test]
+    fn test_report_from_scripted_price_series() {
+        let mut keeper = PositionKeeper::new();
+
+        // (side, qty, fill price) per step; the mark follows the fill price
+        let script = [
+            (Side::Buy, 10, 10000),
+            (Side::Sell, 10, 10010), // win +100
+            (Side::Buy, 10, 10010),
+            (Side::Sell, 10, 10005), // loss -50
+            (Side::Sell, 10, 10020),
+            (Side::Buy, 5, 10000),   // win +100
+            (Side::Buy, 5, 10000),   // win +100
+        ];
+        for (step, &(side, qty, price)) in script.iter().enumerate() {
+            keeper.on_fill(1, side, qty, price);
+            keeper.update_market_price(1, price);
+            keeper.record_snapshot(step as u64);
+        }
+
+        let report = keeper.backtest_report(1);
+        assert_eq!((report.trades, report.wins, report.losses), (4, 3, 1));
+        assert_eq!(report.win_rate, 0.75);
+        assert_eq!(report.avg_win, 100.0);
+        assert_eq!(report.avg_loss, -50.0);
+        assert_eq!(report.profit_factor, 6.0);
+        assert_eq!(report.final_pnl, 250);
+        assert_eq!(report.snapshots, 7);
+
+        // Equity 0, 100, 100, 50, 50, 250, 250: the open half of the short
+        // is marked to its gain before it closes
+        let changes = [100.0, 0.0, -50.0, 0.0, 200.0, 0.0];
+        let mean = changes.iter().sum::<f64>() / 6.0;
+        let std = (changes.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / 5.0).sqrt();
+        assert!((report.sharpe - mean / std).abs() < 1e-9);
+        assert!((report.annualized_sharpe(4.0) - 2.0 * report.sharpe).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_report_without_trades_or_losses() {
+        let report = BacktestReport::new(&TradeStats::default(), []);
+        assert_eq!(report, BacktestReport::default());
+
+        let mut stats = TradeStats::default();
+        stats.record(40);
+        stats.record(0);
+        let report = BacktestReport::new(&stats, []);
+        assert_eq!((report.trades, report.wins, report.losses), (2, 1, 0));
+        assert_eq!(report.win_rate, 0.5);
+        assert_eq!(report.avg_loss, 0.0);
+        assert!(report.profit_factor.is_infinite());
+
+        stats.reset();
+        assert_eq!(stats, TradeStats::default());
+    }
+}
@@ -1,6 +1,6 @@
 // Risk management
 
-use common::{Price, Qty, Side, TickerId};
+use common::{InstrumentRegistry, InstrumentSpec, Price, Qty, Side, TickerId};
 use crate::position::Position;
 use std::collections::HashMap;
 
@@ -17,6 +17,22 @@ pub enum RiskCheckResult {
     LossTooLarge,
     /// Too many open orders
     OpenOrdersTooMany,
+    /// Combined net exposure of the ticker's risk group would exceed its limit
+    GroupExposureExceeded,
+    /// The ticker is halted after breaching its position or loss limit
+    TickerHalted,
+    /// The session's cap on submitted orders has been reached
+    DailyOrderLimitExceeded,
+    /// Sending now would exceed the order rate limit
+    RateLimited,
+    /// Order notional is below the minimum allowed
+    NotionalTooSmall,
+    /// Order notional is above the instrument's maximum
+    NotionalTooLarge,
+    /// Order quantity is not a whole number of the instrument's lots
+    InvalidLotSize,
+    /// The session was killed by an operator
+    SessionKilled,
 }
 
 impl RiskCheckResult {
@@ -38,6 +54,8 @@ pub struct RiskLimits {
     pub max_loss: i64,
     /// Maximum number of open orders
     pub max_open_orders: u32,
+    /// Minimum order notional in cents (0 = no minimum)
+    pub min_notional: i64,
 }
 
 impl Default for RiskLimits {
@@ -47,6 +65,7 @@ impl Default for RiskLimits {
             max_position: 10000,
             max_loss: 100000, // $1000 in cents
             max_open_orders: 100,
+            min_notional: 0,
         }
     }
 }
@@ -59,8 +78,156 @@ impl RiskLimits {
             max_position,
             max_loss,
             max_open_orders,
+            min_notional: 0,
         }
     }
+
+    /// Builder method to set the minimum order notional in cents
+    pub fn with_min_notional(mut self, min_notional: i64) -> Self {
+        self.min_notional = min_notional;
+        self
+    }
+}
+
+/// Identifier for a group of correlated tickers
+pub type RiskGroupId = u32;
+
+/// Scaling of `max_position` by book pressure
+///
+/// Pressure runs from -1.0 (all ask size) to 1.0 (all bid size). The limit in
+/// the direction the pressure supports grows by up to `max_loosen` of
+/// `max_position` at full pressure, and the limit against it shrinks by up to
+/// `max_tighten`, both linearly in the pressure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PressureAdjustment {
+    /// Largest fractional increase of the limit with the pressure
+    pub max_loosen: f64,
+    /// Largest fractional decrease of the limit against the pressure
+    pub max_tighten: f64,
+}
+
+impl PressureAdjustment {
+    /// Create a pressure adjustment, clamping `max_tighten` to [0, 1]
+    pub fn new(max_loosen: f64, max_tighten: f64) -> Self {
+        Self {
+            max_loosen: max_loosen.max(0.0),
+            max_tighten: max_tighten.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Token bucket limit on the rate orders are sent
+///
+/// Up to `burst` orders can go back to back and the bucket refills at
+/// `orders_per_sec`. With jitter enabled every order is also followed by a
+/// random minimum spacing in `[jitter_min_ns, jitter_max_ns]`, so bursts the
+/// bucket alone would allow are smoothed out and cannot be timed precisely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderRateLimit {
+    /// Orders that can be sent back to back
+    pub burst: u32,
+    /// Orders added back to the bucket per second
+    pub orders_per_sec: u32,
+    /// Shortest spacing after an order, in nanoseconds (0 disables jitter)
+    pub jitter_min_ns: u64,
+    /// Longest spacing after an order, in nanoseconds
+    pub jitter_max_ns: u64,
+    /// Seed for the random spacing, so runs can be reproduced
+    pub jitter_seed: u64,
+}
+
+impl OrderRateLimit {
+    /// Create a token bucket limit without jitter
+    pub fn new(burst: u32, orders_per_sec: u32) -> Self {
+        Self {
+            burst: burst.max(1),
+            orders_per_sec,
+            jitter_min_ns: 0,
+            jitter_max_ns: 0,
+            jitter_seed: 0,
+        }
+    }
+
+    /// Builder method to space orders by a random interval in
+    /// `[min_ns, max_ns]`, drawn from a generator seeded with `seed`
+    pub fn with_jitter(mut self, min_ns: u64, max_ns: u64, seed: u64) -> Self {
+        self.jitter_min_ns = min_ns;
+        self.jitter_max_ns = max_ns.max(min_ns);
+        self.jitter_seed = seed;
+        self
+    }
+
+    /// Returns true if orders are randomly spaced
+    #[inline]
+    pub fn has_jitter(&self) -> bool {
+        self.jitter_max_ns > 0
+    }
+}
+
+/// Token bucket and jitter state behind an `OrderRateLimit`
+#[derive(Debug, Clone)]
+struct RateLimiter {
+    limit: OrderRateLimit,
+    /// Tokens in the bucket as of `updated_ns`
+    tokens: f64,
+    updated_ns: u64,
+    /// Earliest time the jitter spacing allows the next order
+    spaced_until_ns: u64,
+    /// SplitMix64 state for the jitter
+    rng_state: u64,
+}
+
+impl RateLimiter {
+    fn new(limit: OrderRateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            updated_ns: 0,
+            spaced_until_ns: 0,
+            rng_state: limit.jitter_seed,
+        }
+    }
+
+    /// Tokens available at `now_ns`
+    fn tokens_at(&self, now_ns: u64) -> f64 {
+        let elapsed = now_ns.saturating_sub(self.updated_ns) as f64;
+        let refill = elapsed * self.limit.orders_per_sec as f64 / 1e9;
+        (self.tokens + refill).min(self.limit.burst as f64)
+    }
+
+    /// Earliest time at or after `now_ns` an order may be sent
+    fn next_allowed_ns(&self, now_ns: u64) -> u64 {
+        let tokens = self.tokens_at(now_ns);
+        let token_ready = if tokens >= 1.0 {
+            now_ns
+        } else if self.limit.orders_per_sec == 0 {
+            u64::MAX
+        } else {
+            let wait = ((1.0 - tokens) * 1e9 / self.limit.orders_per_sec as f64).ceil() as u64;
+            now_ns.saturating_add(wait)
+        };
+        token_ready.max(self.spaced_until_ns)
+    }
+
+    /// Takes a token and draws the spacing before the next order
+    fn record(&mut self, now_ns: u64) {
+        self.tokens = (self.tokens_at(now_ns) - 1.0).max(0.0);
+        self.updated_ns = now_ns;
+
+        if self.limit.has_jitter() {
+            let span = self.limit.jitter_max_ns - self.limit.jitter_min_ns;
+            let spacing = self.limit.jitter_min_ns + self.next_random() % span.saturating_add(1);
+            self.spaced_until_ns = now_ns.saturating_add(spacing);
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
 }
 
 /// Risk manager for pre-trade validation and real-time position/P&L checks
@@ -69,15 +236,24 @@ pub struct RiskManager {
     limits: HashMap<TickerId, RiskLimits>,
     /// Default limits for tickers without specific limits
     default_limits: RiskLimits,
+    /// Risk group membership by ticker
+    ticker_groups: HashMap<TickerId, RiskGroupId>,
+    /// Maximum absolute net exposure per risk group
+    group_limits: HashMap<RiskGroupId, i64>,
+    /// Order rate limit, if set
+    rate_limiter: Option<RateLimiter>,
+    /// Instrument specs for tickers that override the default
+    instruments: InstrumentRegistry,
+    /// Position limit scaling by book pressure, if enabled
+    pressure_adjustment: Option<PressureAdjustment>,
+    /// Latest book pressure per ticker, in [-1, 1]
+    book_pressure: HashMap<TickerId, f64>,
 }
 
 impl RiskManager {
     /// Creates a new risk manager with default limits
     pub fn new() -> Self {
-        Self {
-            limits: HashMap::new(),
-            default_limits: RiskLimits::default(),
-        }
+        Self::with_default_limits(RiskLimits::default())
     }
 
     /// Creates a new risk manager with custom default limits
@@ -85,6 +261,12 @@ impl RiskManager {
         Self {
             limits: HashMap::new(),
             default_limits,
+            ticker_groups: HashMap::new(),
+            group_limits: HashMap::new(),
+            rate_limiter: None,
+            instruments: InstrumentRegistry::new(),
+            pressure_adjustment: None,
+            book_pressure: HashMap::new(),
         }
     }
 
@@ -103,11 +285,223 @@ impl RiskManager {
         self.limits.remove(&ticker_id);
     }
 
+    /// Set the instrument spec of a ticker
+    pub fn set_instrument(&mut self, spec: InstrumentSpec) {
+        self.instruments.insert(spec);
+    }
+
+    /// Replace every instrument spec with those of a registry
+    pub fn set_instruments(&mut self, instruments: InstrumentRegistry) {
+        self.instruments = instruments;
+    }
+
+    /// Returns the instrument specs orders are checked against
+    pub fn instruments(&self) -> &InstrumentRegistry {
+        &self.instruments
+    }
+
+    /// Get the instrument spec of a ticker (returns the default spec if not set)
+    pub fn instrument(&self, ticker_id: TickerId) -> InstrumentSpec {
+        self.instruments.get(ticker_id)
+    }
+
+    /// Notional value of an order in cents, using the ticker's price scale
+    ///
+    /// # Arguments
+    /// * `ticker_id` - Instrument the order is for
+    /// * `price` - Integer price in the instrument's scale
+    /// * `qty` - Order quantity
+    ///
+    /// # Returns
+    /// Notional in cents, comparable across instruments with different scales
+    pub fn order_notional(&self, ticker_id: TickerId, price: Price, qty: Qty) -> i64 {
+        self.instrument(ticker_id).notional_cents(price, qty)
+    }
+
+    /// Scale position limits by book pressure, replacing any previous adjustment
+    pub fn set_pressure_adjustment(&mut self, adjustment: PressureAdjustment) {
+        self.pressure_adjustment = Some(adjustment);
+    }
+
+    /// Stop scaling position limits by book pressure
+    pub fn clear_pressure_adjustment(&mut self) {
+        self.pressure_adjustment = None;
+    }
+
+    /// Returns the book pressure adjustment, if enabled
+    pub fn pressure_adjustment(&self) -> Option<&PressureAdjustment> {
+        self.pressure_adjustment.as_ref()
+    }
+
+    /// Record a ticker's book pressure, clamped to [-1, 1]
+    ///
+    /// Positive values mean buying pressure, e.g. the BBO imbalance.
+    pub fn set_book_pressure(&mut self, ticker_id: TickerId, pressure: f64) {
+        self.book_pressure.insert(ticker_id, pressure.clamp(-1.0, 1.0));
+    }
+
+    /// Returns a ticker's latest book pressure (0.0 if none was recorded)
+    pub fn book_pressure(&self, ticker_id: TickerId) -> f64 {
+        self.book_pressure.get(&ticker_id).copied().unwrap_or(0.0)
+    }
+
+    /// Position limit in effect for growing a position on one side
+    ///
+    /// # Arguments
+    /// * `ticker_id` - Ticker whose limit to compute
+    /// * `side` - Buy for the long limit, Sell for the short limit
+    ///
+    /// # Returns
+    /// `max_position` scaled by the ticker's book pressure when a pressure
+    /// adjustment is enabled, otherwise `max_position` itself
+    pub fn effective_max_position(&self, ticker_id: TickerId, side: Side) -> i64 {
+        let max_position = self.get_limits(ticker_id).max_position;
+        let Some(adjustment) = self.pressure_adjustment else {
+            return max_position;
+        };
+
+        // Pressure in the direction of the position being grown
+        let support = match side {
+            Side::Buy => self.book_pressure(ticker_id),
+            Side::Sell => -self.book_pressure(ticker_id),
+        };
+        let scale = if support >= 0.0 {
+            1.0 + adjustment.max_loosen * support
+        } else {
+            1.0 + adjustment.max_tighten * support
+        };
+        (max_position as f64 * scale).round() as i64
+    }
+
+    /// Put tickers in a risk group with a combined net exposure limit
+    ///
+    /// A ticker belongs to at most one group; adding it to another group
+    /// moves it. Setting a group again replaces its limit and adds members.
+    pub fn set_risk_group(
+        &mut self,
+        group_id: RiskGroupId,
+        tickers: &[TickerId],
+        max_group_net_exposure: i64,
+    ) {
+        for &ticker_id in tickers {
+            self.ticker_groups.insert(ticker_id, group_id);
+        }
+        self.group_limits.insert(group_id, max_group_net_exposure);
+    }
+
+    /// Returns the risk group a ticker belongs to, if any
+    pub fn risk_group(&self, ticker_id: TickerId) -> Option<RiskGroupId> {
+        self.ticker_groups.get(&ticker_id).copied()
+    }
+
+    /// Returns the combined net exposure limit of a risk group, if set
+    pub fn group_limit(&self, group_id: RiskGroupId) -> Option<i64> {
+        self.group_limits.get(&group_id).copied()
+    }
+
+    /// Pre-trade check of the combined exposure of the ticker's risk group
+    ///
+    /// Sums the exposure (including pending orders) of every ticker in the
+    /// group, looked up through `positions`, and rejects orders that would
+    /// take it beyond the group limit. Orders that only reduce the group's
+    /// net position, and tickers in no group, always pass; an order that
+    /// flips the net position through zero is checked on the other side.
+    pub fn check_group_exposure<'a>(
+        &self,
+        ticker_id: TickerId,
+        side: Side,
+        qty: Qty,
+        positions: impl Fn(TickerId) -> Option<&'a Position>,
+    ) -> RiskCheckResult {
+        let Some(group_id) = self.risk_group(ticker_id) else {
+            return RiskCheckResult::Allowed;
+        };
+        let Some(max_exposure) = self.group_limit(group_id) else {
+            return RiskCheckResult::Allowed;
+        };
+
+        let members = self
+            .ticker_groups
+            .iter()
+            .filter(|(_, &group)| group == group_id)
+            .filter_map(|(&member, _)| positions(member));
+
+        let mut net_position = 0;
+        let mut long_exposure = 0;
+        let mut short_exposure = 0;
+        for position in members {
+            net_position += position.position;
+            long_exposure += position.max_long_exposure();
+            short_exposure += position.max_short_exposure();
+        }
+
+        let net_after = net_position + side.as_sign() * qty as i64;
+        let only_reduces = match side {
+            Side::Buy => net_position < 0 && net_after <= 0,
+            Side::Sell => net_position > 0 && net_after >= 0,
+        };
+        if only_reduces {
+            return RiskCheckResult::Allowed;
+        }
+
+        let projected = match side {
+            Side::Buy => long_exposure + qty as i64,
+            Side::Sell => short_exposure - qty as i64,
+        };
+        if projected.abs() > max_exposure {
+            return RiskCheckResult::GroupExposureExceeded;
+        }
+
+        RiskCheckResult::Allowed
+    }
+
+    /// Limit the rate orders are sent, replacing any previous limit
+    ///
+    /// The bucket starts full.
+    pub fn set_rate_limit(&mut self, limit: OrderRateLimit) {
+        self.rate_limiter = Some(RateLimiter::new(limit));
+    }
+
+    /// Remove the order rate limit
+    pub fn clear_rate_limit(&mut self) {
+        self.rate_limiter = None;
+    }
+
+    /// Returns the order rate limit, if set
+    pub fn rate_limit(&self) -> Option<&OrderRateLimit> {
+        self.rate_limiter.as_ref().map(|limiter| &limiter.limit)
+    }
+
+    /// Check whether an order may be sent at `now_ns` under the rate limit
+    pub fn check_rate(&self, now_ns: u64) -> RiskCheckResult {
+        match &self.rate_limiter {
+            Some(limiter) if limiter.next_allowed_ns(now_ns) > now_ns => RiskCheckResult::RateLimited,
+            _ => RiskCheckResult::Allowed,
+        }
+    }
+
+    /// Returns the earliest time at or after `now_ns` the rate limit allows
+    /// an order, including any jitter spacing
+    pub fn next_allowed_ns(&self, now_ns: u64) -> u64 {
+        self.rate_limiter
+            .as_ref()
+            .map_or(now_ns, |limiter| limiter.next_allowed_ns(now_ns))
+    }
+
+    /// Record an order sent at `now_ns` against the rate limit
+    pub fn record_order_sent(&mut self, now_ns: u64) {
+        if let Some(limiter) = &mut self.rate_limiter {
+            limiter.record(now_ns);
+        }
+    }
+
     /// Pre-trade risk check for a new order
     ///
     /// Validates:
-    /// 1. Order quantity does not exceed max_order_qty
-    /// 2. Resulting position (including pending orders) does not exceed max_position
+    /// 1. Order quantity does not exceed max_order_qty, and its notional
+    ///    (at the ticker's price scale) is at least min_notional
+    /// 2. Resulting position (including pending orders) does not exceed
+    ///    max_position, as adjusted by book pressure
     /// 3. Current P&L loss does not exceed max_loss
     ///
     /// Note: Open order count check should be done separately as it requires
@@ -117,14 +511,22 @@ impl RiskManager {
         position: &Position,
         side: Side,
         qty: Qty,
-        _price: Price,
+        price: Price,
     ) -> RiskCheckResult {
         let limits = self.get_limits(position.ticker_id);
+        let instrument = self.instrument(position.ticker_id);
 
-        // Check 1: Order size limit
+        // Check 1: Order size limits
         if qty > limits.max_order_qty {
             return RiskCheckResult::OrderTooLarge;
         }
+        let notional = instrument.notional_cents(price, qty);
+        if instrument.max_notional > 0 && notional > instrument.max_notional {
+            return RiskCheckResult::NotionalTooLarge;
+        }
+        if notional < limits.min_notional.max(instrument.min_notional) {
+            return RiskCheckResult::NotionalTooSmall;
+        }
 
         // Check 2: Position limit (including pending orders)
         //
@@ -145,7 +547,7 @@ impl RiskManager {
                 Side::Sell => position.max_short_exposure() - qty as i64,
             };
 
-            if projected_position.abs() > limits.max_position {
+            if projected_position.abs() > self.effective_max_position(position.ticker_id, side) {
                 return RiskCheckResult::PositionTooLarge;
             }
         }
@@ -156,6 +558,11 @@ impl RiskManager {
             return RiskCheckResult::LossTooLarge;
         }
 
+        // Check 4: Lot size, last so a size search sees size limits first
+        if !instrument.is_valid_lot(qty) {
+            return RiskCheckResult::InvalidLotSize;
+        }
+
         RiskCheckResult::Allowed
     }
 
@@ -177,13 +584,15 @@ impl RiskManager {
     /// Real-time position check (can be called periodically or on updates)
     ///
     /// Validates:
-    /// 1. Current position does not exceed max_position
+    /// 1. Current position does not exceed max_position, as adjusted by
+    ///    book pressure for the position's side
     /// 2. Current P&L loss does not exceed max_loss
     pub fn check_position(&self, position: &Position) -> RiskCheckResult {
         let limits = self.get_limits(position.ticker_id);
 
         // Check position limit
-        if position.position.abs() > limits.max_position {
+        let side = if position.position >= 0 { Side::Buy } else { Side::Sell };
+        if position.position.abs() > self.effective_max_position(position.ticker_id, side) {
             return RiskCheckResult::PositionTooLarge;
         }
 
@@ -243,6 +652,9 @@ mod tests {
             unrealized_pnl,
             avg_open_price: 0,
             last_price: 0,
+            fees_paid: 0,
+            trade_stats: Default::default(),
+            funding_pnl: 0,
         }
     }
 
@@ -255,6 +667,7 @@ mod tests {
         assert!(!RiskCheckResult::PositionTooLarge.is_allowed());
         assert!(!RiskCheckResult::LossTooLarge.is_allowed());
         assert!(!RiskCheckResult::OpenOrdersTooMany.is_allowed());
+        assert!(!RiskCheckResult::GroupExposureExceeded.is_allowed());
     }
 
     // ==================== RiskLimits Tests ====================
@@ -666,6 +1079,181 @@ mod tests {
         assert_eq!(result, RiskCheckResult::PositionTooLarge);
     }
 
+    // ==================== Risk Group Tests ====================
+
+    #[test]
+    fn test_correlated_longs_trip_group_limit() {
+        let mut rm = RiskManager::new();
+        rm.set_limits(1, RiskLimits::new(1000, 1000, 100000, 100));
+        rm.set_limits(2, RiskLimits::new(1000, 1000, 100000, 100));
+        rm.set_risk_group(7, &[1, 2], 1200);
+        assert_eq!(rm.risk_group(1), Some(7));
+        assert_eq!(rm.group_limit(7), Some(1200));
+
+        let positions: HashMap<TickerId, Position> = [
+            (1, create_position_with_state(1, 600, 0, 0, 0, 0)),
+            (2, create_position_with_state(2, 500, 0, 0, 0, 0)),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |ticker| positions.get(&ticker);
+
+        // Each ticker stays within its own limit
+        assert!(rm.check_order(&positions[&2], Side::Buy, 200, 100).is_allowed());
+        // but together they exceed the group limit
+        assert_eq!(
+            rm.check_group_exposure(2, Side::Buy, 200, lookup),
+            RiskCheckResult::GroupExposureExceeded
+        );
+        assert!(rm.check_group_exposure(2, Side::Buy, 100, lookup).is_allowed());
+        // Reducing the group's net long is always allowed
+        assert!(rm.check_group_exposure(1, Side::Sell, 900, lookup).is_allowed());
+        // Tickers outside any group are unaffected
+        assert!(rm.check_group_exposure(3, Side::Buy, 5000, lookup).is_allowed());
+    }
+
+    #[test]
+    fn test_opposing_order_overshooting_group_limit_rejected() {
+        let mut rm = RiskManager::new();
+        rm.set_risk_group(7, &[1, 2], 1200);
+
+        let positions: HashMap<TickerId, Position> = [
+            (1, create_position_with_state(1, 600, 0, 0, 0, 0)),
+            (2, create_position_with_state(2, 500, 0, 0, 0, 0)),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |ticker| positions.get(&ticker);
+
+        // Net long 1100: selling 2300 ends 1200 short, right at the limit
+        assert!(rm.check_group_exposure(1, Side::Sell, 2300, lookup).is_allowed());
+        // Selling 2500 flips the group through zero to 1400 short
+        assert_eq!(
+            rm.check_group_exposure(1, Side::Sell, 2500, lookup),
+            RiskCheckResult::GroupExposureExceeded
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_bucket() {
+        let mut rm = RiskManager::new();
+        assert!(rm.check_rate(0).is_allowed());
+        rm.set_rate_limit(OrderRateLimit::new(2, 10));
+
+        let start = 1_000_000_000;
+        rm.record_order_sent(start);
+        rm.record_order_sent(start);
+        assert_eq!(rm.check_rate(start), RiskCheckResult::RateLimited);
+
+        // One token every 100ms
+        assert_eq!(rm.next_allowed_ns(start), start + 100_000_000);
+        assert!(rm.check_rate(start + 100_000_000).is_allowed());
+    }
+
+    #[test]
+    fn test_rate_limit_jitter_spaces_back_to_back_orders() {
+        let limit = OrderRateLimit::new(10, 1000).with_jitter(50_000, 100_000, 7);
+        let mut rm = RiskManager::new();
+        rm.set_rate_limit(limit);
+
+        let start = 1_000_000_000;
+        rm.record_order_sent(start);
+
+        // The bucket alone would allow the next order straight away
+        let mut bucket_only = RiskManager::new();
+        bucket_only.set_rate_limit(OrderRateLimit::new(10, 1000));
+        bucket_only.record_order_sent(start);
+        assert!(bucket_only.check_rate(start + 1).is_allowed());
+
+        // With jitter it has to wait at least the minimum spacing
+        assert_eq!(rm.check_rate(start + 1), RiskCheckResult::RateLimited);
+        let next = rm.next_allowed_ns(start + 1);
+        assert!((start + 50_000..=start + 100_000).contains(&next));
+        assert_eq!(rm.check_rate(next - 1), RiskCheckResult::RateLimited);
+        assert!(rm.check_rate(next).is_allowed());
+
+        // The same seed draws the same spacing
+        let mut replay = RiskManager::new();
+        replay.set_rate_limit(limit);
+        replay.record_order_sent(start);
+        assert_eq!(replay.next_allowed_ns(start), next);
+    }
+
+    #[test]
+    fn test_order_notional_respects_price_scale() {
+        let mut rm = RiskManager::new();
+        rm.set_instrument(InstrumentSpec::new(2).with_price_scale(4));
+
+        // Price 12345 is $123.45 on ticker 1 and $1.2345 on ticker 2
+        assert_eq!(rm.instrument(1).price_scale, common::DEFAULT_PRICE_SCALE);
+        assert_eq!(rm.order_notional(1, 12345, 100), 1_234_500);
+        assert_eq!(rm.order_notional(2, 12345, 100), 12_345);
+    }
+
+    #[test]
+    fn test_bid_pressure_loosens_long_limit_and_tightens_short() {
+        let mut rm = RiskManager::new();
+        rm.set_limits(1, RiskLimits::new(10000, 1000, 100000, 100));
+        rm.set_pressure_adjustment(PressureAdjustment::new(0.5, 0.5));
+        let flat = create_position_with_state(1, 0, 0, 0, 0, 0);
+
+        // Neutral pressure leaves the limits as configured
+        assert_eq!(rm.effective_max_position(1, Side::Buy), 1000);
+        assert_eq!(rm.effective_max_position(1, Side::Sell), 1000);
+        assert_eq!(rm.check_order(&flat, Side::Buy, 1200, 100), RiskCheckResult::PositionTooLarge);
+        assert!(rm.check_order(&flat, Side::Sell, 900, 100).is_allowed());
+
+        // Strong bid pressure permits a larger long and a smaller short
+        rm.set_book_pressure(1, 0.8);
+        assert_eq!(rm.effective_max_position(1, Side::Buy), 1400);
+        assert_eq!(rm.effective_max_position(1, Side::Sell), 600);
+        assert!(rm.check_order(&flat, Side::Buy, 1200, 100).is_allowed());
+        assert_eq!(rm.check_order(&flat, Side::Sell, 900, 100), RiskCheckResult::PositionTooLarge);
+
+        // Pressure is clamped, and other tickers are unaffected
+        rm.set_book_pressure(1, 3.0);
+        assert_eq!(rm.book_pressure(1), 1.0);
+        assert_eq!(rm.effective_max_position(1, Side::Buy), 1500);
+        assert_eq!(rm.effective_max_position(2, Side::Buy), 10000);
+
+        rm.clear_pressure_adjustment();
+        assert_eq!(rm.effective_max_position(1, Side::Buy), 1000);
+    }
+
+    #[test]
+    fn test_min_notional_rejects_small_orders_on_cheap_instruments() {
+        let mut rm = RiskManager::new();
+        // $10 minimum on both tickers
+        rm.set_limits(1, RiskLimits::default().with_min_notional(1_000));
+        rm.set_limits(2, RiskLimits::default().with_min_notional(1_000));
+        let cheap = create_position_with_state(1, 0, 0, 0, 0, 0);
+        let expensive = create_position_with_state(2, 0, 0, 0, 0, 0);
+
+        // 5 shares at $1.50 is $7.50, at $150.00 it is $750
+        assert_eq!(rm.check_order(&cheap, Side::Buy, 5, 150), RiskCheckResult::NotionalTooSmall);
+        assert!(rm.check_order(&expensive, Side::Buy, 5, 15_000).is_allowed());
+        // Exactly the minimum passes
+        assert!(rm.check_order(&cheap, Side::Sell, 10, 100).is_allowed());
+        // No minimum by default
+        assert!(RiskManager::new().check_order(&cheap, Side::Buy, 1, 1).is_allowed());
+    }
+
+    #[test]
+    fn test_instrument_lots_and_notional_bounds() {
+        let mut rm = RiskManager::new();
+        rm.set_limits(1, RiskLimits::default().with_min_notional(500));
+        let instruments = "1 lot_size=10 min_notional=100 max_notional=100000";
+        rm.set_instruments(common::InstrumentRegistry::parse(instruments).unwrap());
+        let flat = create_position_with_state(1, 0, 0, 0, 0, 0);
+
+        assert!(rm.check_order(&flat, Side::Buy, 10, 100).is_allowed());
+        assert_eq!(rm.check_order(&flat, Side::Buy, 15, 100), RiskCheckResult::InvalidLotSize);
+        // The stricter of the limit and the instrument minimum applies
+        assert_eq!(rm.check_order(&flat, Side::Buy, 10, 40), RiskCheckResult::NotionalTooSmall);
+        assert_eq!(rm.check_order(&flat, Side::Sell, 20, 10_000), RiskCheckResult::NotionalTooLarge);
+        assert!(rm.check_order(&flat, Side::Sell, 10, 10_000).is_allowed());
+    }
+
     #[test]
     fn test_default_impl() {
         let rm = RiskManager::default();
@@ -3,10 +3,36 @@
 //! The liquidity taker monitors trading signals and aggressively takes liquidity
 //! when the signal exceeds a configurable threshold. It's designed for momentum
 //! or signal-based trading where speed of execution matters more than price impact.
+//!
+//! With hysteresis configured, the strategy keeps a directional bias from its
+//! last take: reversing it needs the signal to clear the opposite threshold by
+//! the hysteresis band, so a signal wobbling around a threshold does not flip
+//! it back and forth.
+//!
+//! With a slippage budget configured, an order's limit price never goes more
+//! than `max_slippage_bps` past the mid of the book it was priced from: the
+//! aggression is capped at that limit, and no order is sent if the touch is
+//! already beyond it, as when the book gaps or the spread blows out.
+//!
+//! With a minimum edge configured, the signal is read as an expected move of
+//! `edge_bps_per_signal` basis points per unit of signal, and a take only
+//! fires if that move, less the cost of crossing (half the spread plus the
+//! aggression), still clears `min_edge_bps`.
+//!
+//! `on_features_now` reads the time from the strategy's clock, the system
+//! monotonic clock unless `set_clock` injects another; tests drive the rate
+//! limiter and cooldowns with a `ManualClock` instead of sleeping.
 
-use common::{Price, Qty, TickerId};
+use std::sync::Arc;
+
+use common::time::{Clock, SystemClock};
+use common::{InstrumentSpec, Price, Qty, Side, TickerId};
 use crate::features::TickerFeatures;
-use super::{OrderRequest, StrategyAction};
+use crate::market_data::BBO;
+use super::{
+    DecisionReason, DecisionTrace, LossStreakBreaker, OrderRequest, RejectStreakBreaker, StrategyAction,
+};
+use crate::risk::RiskCheckResult;
 
 /// Configuration parameters for the liquidity taker strategy.
 #[derive(Debug, Clone, Copy)]
@@ -29,12 +55,55 @@ pub struct LiquidityTakerConfig {
     /// Price aggression in basis points (how much to cross the spread).
     /// 0 = take at best bid/ask, positive = cross spread by this amount.
     pub aggression_bps: u32,
+    /// Furthest an order's price may go past the mid, in basis points of
+    /// the mid (0 = no limit).
+    pub max_slippage_bps: u32,
+    /// Expected edge after crossing costs, in basis points of the mid, a
+    /// take must clear (0 = no minimum).
+    pub min_edge_bps: u32,
+    /// Expected price move, in basis points, per unit of trade signal.
+    pub edge_bps_per_signal: f64,
     /// Minimum time between orders in nanoseconds (rate limiting).
     pub min_order_interval_ns: u64,
     /// Maximum position before stopping (0 = no limit).
     pub max_position: i64,
-    /// Cooldown multiplier after a trade (increases wait time).
+    /// Cooldown multiplier applied to the interval after each order.
     pub cooldown_factor: f64,
+    /// Shortest interval between orders, as a multiple of
+    /// `min_order_interval_ns`; the interval returns here after a fill.
+    pub min_interval_multiplier: f64,
+    /// Longest interval between orders, as a multiple of
+    /// `min_order_interval_ns`; backoff stops growing here.
+    pub max_interval_multiplier: f64,
+    /// Record a `DecisionTrace` for every features update.
+    pub trace_decisions: bool,
+    /// Consecutive losing round trips that pause the strategy (0 = disabled).
+    pub max_consecutive_losses: u32,
+    /// How long the strategy pauses after a loss streak, in nanoseconds.
+    pub loss_cooldown_ns: u64,
+    /// Consecutive order rejects that deactivate the strategy (0 = disabled).
+    pub max_consecutive_rejects: u32,
+    /// How far past the opposite threshold the signal must go to reverse
+    /// the last take (0 = disabled).
+    pub hysteresis: f64,
+    /// Take at most once per features version, so one snapshot that stays
+    /// current doesn't trigger a second order before the book reflects the
+    /// first.
+    pub require_fresh_features: bool,
+    /// Tick and lot sizes order prices and quantities are rounded to.
+    pub instrument: InstrumentSpec,
+}
+
+/// Direction of the liquidity taker's last take.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignalBias {
+    /// Nothing taken yet; either threshold applies as configured.
+    #[default]
+    Neutral,
+    /// Last took a buy; a sell needs the signal below the exit band.
+    Long,
+    /// Last took a sell; a buy needs the signal above the exit band.
+    Short,
 }
 
 impl Default for LiquidityTakerConfig {
@@ -47,9 +116,21 @@ impl Default for LiquidityTakerConfig {
             max_qty: 500,           // 500 shares max
             scale_with_signal: true,
             aggression_bps: 10,     // 10 bps aggression
+            max_slippage_bps: 0,    // Price without a slippage limit by default
+            min_edge_bps: 0,        // Take on any signal past the threshold
+            edge_bps_per_signal: 20.0, // A full signal expects a 20 bps move
             min_order_interval_ns: 100_000_000, // 100ms min interval
             max_position: 5000,     // Max 5000 shares position
             cooldown_factor: 2.0,   // Double wait time after trade
+            min_interval_multiplier: 1.0,
+            max_interval_multiplier: 10.0, // Back off to at most 10x
+            trace_decisions: false,
+            max_consecutive_losses: 0, // Never pause on losses by default
+            loss_cooldown_ns: 0,
+            max_consecutive_rejects: 0, // Never stop on rejects by default
+            hysteresis: 0.0,       // Take on either threshold regardless of bias
+            require_fresh_features: false,
+            instrument: InstrumentSpec::new(0),
         }
     }
 }
@@ -59,6 +140,7 @@ impl LiquidityTakerConfig {
     pub fn new(ticker_id: TickerId) -> Self {
         Self {
             ticker_id,
+            instrument: InstrumentSpec::new(ticker_id),
             ..Default::default()
         }
     }
@@ -107,6 +189,21 @@ impl LiquidityTakerConfig {
         self
     }
 
+    /// Builder method to cap order prices at `bps` basis points past the
+    /// mid, suppressing orders the touch already puts beyond it.
+    pub fn with_max_slippage_bps(mut self, bps: u32) -> Self {
+        self.max_slippage_bps = bps;
+        self
+    }
+
+    /// Builder method to take only when `|signal| * bps_per_signal`, less
+    /// half the spread and the aggression, is at least `min_edge_bps`.
+    pub fn with_min_edge(mut self, min_edge_bps: u32, bps_per_signal: f64) -> Self {
+        self.min_edge_bps = min_edge_bps;
+        self.edge_bps_per_signal = bps_per_signal.max(0.0);
+        self
+    }
+
     /// Builder method to set minimum order interval.
     pub fn with_min_interval_ns(mut self, interval_ns: u64) -> Self {
         self.min_order_interval_ns = interval_ns;
@@ -124,18 +221,91 @@ impl LiquidityTakerConfig {
         self.cooldown_factor = factor.max(1.0);
         self
     }
+
+    /// Builder method to bound the backoff interval, as multiples of the
+    /// minimum order interval.
+    ///
+    /// Both are raised to at least 1.0 and `max_multiplier` to at least
+    /// `min_multiplier`.
+    pub fn with_cooldown_bounds(mut self, min_multiplier: f64, max_multiplier: f64) -> Self {
+        self.min_interval_multiplier = min_multiplier.max(1.0);
+        self.max_interval_multiplier = max_multiplier.max(self.min_interval_multiplier);
+        self
+    }
+
+    /// Returns the shortest interval between orders, in nanoseconds.
+    #[inline]
+    pub fn floor_interval_ns(&self) -> u64 {
+        (self.min_order_interval_ns as f64 * self.min_interval_multiplier) as u64
+    }
+
+    /// Returns the longest interval between orders, in nanoseconds.
+    #[inline]
+    pub fn ceiling_interval_ns(&self) -> u64 {
+        ((self.min_order_interval_ns as f64 * self.max_interval_multiplier) as u64)
+            .max(self.floor_interval_ns())
+    }
+
+    /// Builder method to enable/disable decision tracing.
+    pub fn with_decision_trace(mut self, enabled: bool) -> Self {
+        self.trace_decisions = enabled;
+        self
+    }
+
+    /// Builder method to pause for `cooldown_ns` after `max_losses`
+    /// consecutive losing round trips.
+    pub fn with_loss_cooldown(mut self, max_losses: u32, cooldown_ns: u64) -> Self {
+        self.max_consecutive_losses = max_losses;
+        self.loss_cooldown_ns = cooldown_ns;
+        self
+    }
+
+    /// Builder method to deactivate after `max_rejects` consecutive order
+    /// rejects.
+    pub fn with_max_consecutive_rejects(mut self, max_rejects: u32) -> Self {
+        self.max_consecutive_rejects = max_rejects;
+        self
+    }
+
+    /// Builder method to set the hysteresis band (clamped to 0.0..=1.0).
+    ///
+    /// After a buy, a sell needs the signal below `sell_threshold - band`;
+    /// after a sell, a buy needs it above `buy_threshold + band`.
+    pub fn with_hysteresis(mut self, band: f64) -> Self {
+        self.hysteresis = band.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Builder method to take at most once per features version.
+    pub fn with_fresh_features(mut self, enabled: bool) -> Self {
+        self.require_fresh_features = enabled;
+        self
+    }
+
+    /// Builder method to round orders to an instrument's tick and lot sizes.
+    pub fn with_instrument(mut self, instrument: InstrumentSpec) -> Self {
+        self.instrument = instrument;
+        self
+    }
 }
 
 /// Liquidity taker strategy state for a single ticker.
 ///
 /// Monitors trading signals and generates aggressive orders when signals
 /// exceed thresholds.
+///
+/// Orders are rate limited by exponential backoff: the interval between
+/// orders starts at the floor, is multiplied by `cooldown_factor` after
+/// every order up to the ceiling, and returns to the floor on a fill or
+/// `reset_cooldown`. See `LiquidityTakerConfig::floor_interval_ns` and
+/// `ceiling_interval_ns`.
 pub struct LiquidityTaker {
     /// Strategy configuration.
     config: LiquidityTakerConfig,
     /// Timestamp of last order in nanoseconds.
     last_order_time_ns: u64,
-    /// Current effective interval (may be increased by cooldown).
+    /// Current interval between orders, within the configured floor and
+    /// ceiling.
     effective_interval_ns: u64,
     /// Current position (tracked externally, updated via set_position).
     current_position: i64,
@@ -143,18 +313,36 @@ pub struct LiquidityTaker {
     active: bool,
     /// Count of orders sent (for metrics).
     orders_sent: u64,
+    /// Most recent decision, when tracing is enabled.
+    last_decision: Option<DecisionTrace>,
+    /// Pauses taking after consecutive losing round trips.
+    loss_breaker: LossStreakBreaker,
+    /// Deactivates the strategy after consecutive order rejects.
+    reject_breaker: RejectStreakBreaker,
+    /// Direction of the last take, for hysteresis.
+    bias: SignalBias,
+    /// Features version of the last take.
+    last_take_version: Option<u64>,
+    /// Time source for `on_features_now`.
+    clock: Arc<dyn Clock>,
 }
 
 impl LiquidityTaker {
     /// Creates a new liquidity taker with the given configuration.
     pub fn new(config: LiquidityTakerConfig) -> Self {
         Self {
-            effective_interval_ns: config.min_order_interval_ns,
+            effective_interval_ns: config.floor_interval_ns(),
             config,
             last_order_time_ns: 0,
             current_position: 0,
             active: true,
             orders_sent: 0,
+            last_decision: None,
+            loss_breaker: LossStreakBreaker::new(config.max_consecutive_losses, config.loss_cooldown_ns),
+            reject_breaker: RejectStreakBreaker::new(config.max_consecutive_rejects),
+            bias: SignalBias::Neutral,
+            last_take_version: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -163,6 +351,11 @@ impl LiquidityTaker {
         Self::new(LiquidityTakerConfig::new(ticker_id))
     }
 
+    /// Replaces the clock read by `on_features_now`.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// Returns a reference to the configuration.
     #[inline]
     pub fn config(&self) -> &LiquidityTakerConfig {
@@ -187,10 +380,11 @@ impl LiquidityTaker {
         self.current_position
     }
 
-    /// Activates the strategy.
+    /// Activates the strategy, re-arming the reject breaker.
     #[inline]
     pub fn activate(&mut self) {
         self.active = true;
+        self.reject_breaker.rearm();
     }
 
     /// Deactivates the strategy.
@@ -211,6 +405,73 @@ impl LiquidityTaker {
         self.orders_sent
     }
 
+    /// Returns the most recent decision, if tracing is enabled.
+    #[inline]
+    pub fn last_decision(&self) -> Option<&DecisionTrace> {
+        self.last_decision.as_ref()
+    }
+
+    /// Returns the direction of the last take.
+    #[inline]
+    pub fn bias(&self) -> SignalBias {
+        self.bias
+    }
+
+    /// Returns the (buy, sell) signal thresholds in effect, widened against
+    /// reversing the current bias by the hysteresis band.
+    pub fn effective_thresholds(&self) -> (f64, f64) {
+        let band = self.config.hysteresis;
+        match self.bias {
+            SignalBias::Neutral => (self.config.buy_threshold, self.config.sell_threshold),
+            SignalBias::Long => (self.config.buy_threshold, self.config.sell_threshold - band),
+            SignalBias::Short => (self.config.buy_threshold + band, self.config.sell_threshold),
+        }
+    }
+
+    /// Reports the realized P&L of a close (a fill that reduced the position),
+    /// and whether it took the position flat or through flat.
+    ///
+    /// # Returns
+    /// `true` if this close ended a round trip that started a loss cooldown
+    pub fn on_close(&mut self, realized_pnl: i64, closed_out: bool, now_ns: u64) -> bool {
+        self.loss_breaker.record_close(realized_pnl, closed_out, now_ns)
+    }
+
+    /// Returns the loss streak circuit breaker.
+    #[inline]
+    pub fn loss_breaker(&self) -> &LossStreakBreaker {
+        &self.loss_breaker
+    }
+
+    /// Reports the outcome of one of the strategy's orders.
+    ///
+    /// After `max_consecutive_rejects` rejects in a row the strategy
+    /// deactivates itself; `activate` re-arms it.
+    ///
+    /// # Returns
+    /// `true` if this reject deactivated the strategy
+    pub fn on_order_result(&mut self, result: RiskCheckResult) -> bool {
+        let tripped = self.reject_breaker.record_result(result);
+        if tripped {
+            self.active = false;
+        }
+        tripped
+    }
+
+    /// Returns the order reject circuit breaker.
+    #[inline]
+    pub fn reject_breaker(&self) -> &RejectStreakBreaker {
+        &self.reject_breaker
+    }
+
+    /// Records a decision if tracing is enabled.
+    #[inline]
+    fn trace(&mut self, make_trace: impl FnOnce() -> DecisionTrace) {
+        if self.config.trace_decisions {
+            self.last_decision = Some(make_trace());
+        }
+    }
+
     /// Processes features and generates take orders if signal threshold is crossed.
     ///
     /// # Arguments
@@ -220,7 +481,8 @@ impl LiquidityTaker {
     /// * `best_ask` - Best ask price in the market (for buy orders)
     ///
     /// # Returns
-    /// A `StrategyAction` indicating what action to take (if any)
+    /// A `StrategyAction` indicating what action to take (if any). During a
+    /// loss cooldown this is `CancelAll` on the first call and `None` after.
     pub fn on_features(
         &mut self,
         features: &TickerFeatures,
@@ -230,53 +492,107 @@ impl LiquidityTaker {
     ) -> StrategyAction {
         // Check if strategy is active
         if !self.active {
+            self.trace(|| DecisionTrace::new(DecisionReason::Inactive, features));
+            return StrategyAction::None;
+        }
+
+        // Stay out of the market while cooling down after losses
+        if self.loss_breaker.is_cooling_down(current_time_ns) {
+            self.trace(|| DecisionTrace::new(DecisionReason::LossCooldown, features));
+            if self.loss_breaker.take_cancel() {
+                return StrategyAction::CancelAll(self.config.ticker_id);
+            }
             return StrategyAction::None;
         }
 
         // Check if features are valid
         if !features.is_valid() {
+            self.trace(|| DecisionTrace::new(DecisionReason::InvalidFeatures, features));
+            return StrategyAction::None;
+        }
+
+        // Don't act twice on the same snapshot; unversioned features are
+        // always taken as fresh
+        if self.config.require_fresh_features
+            && features.version != 0
+            && self.last_take_version == Some(features.version)
+        {
+            self.trace(|| DecisionTrace::new(DecisionReason::StaleFeatures, features));
             return StrategyAction::None;
         }
 
         // Check rate limiting
         if !self.can_send_order(current_time_ns) {
+            self.trace(|| DecisionTrace::new(DecisionReason::RateLimited, features));
             return StrategyAction::None;
         }
 
         // Determine if we should take liquidity based on signal
         let signal = features.trade_signal;
+        let (buy_threshold, sell_threshold) = self.effective_thresholds();
 
         // Check for buy signal
-        if signal > self.config.buy_threshold {
+        if signal > buy_threshold {
             // Check position limit
             if self.config.max_position > 0 && self.current_position >= self.config.max_position {
+                self.trace(|| DecisionTrace::new(DecisionReason::MaxPosition, features));
+                return StrategyAction::None;
+            }
+
+            if !self.has_min_edge(signal, best_bid, best_ask) {
+                self.trace(|| DecisionTrace::new(DecisionReason::InsufficientEdge, features));
                 return StrategyAction::None;
             }
 
             // Calculate order
-            if let Some(order) = self.create_buy_order(signal, best_ask) {
+            if let Some(order) = self.create_buy_order(signal, best_bid, best_ask) {
                 self.record_order(current_time_ns);
+                self.bias = SignalBias::Long;
+                self.last_take_version = Some(features.version);
+                self.trace(|| {
+                    DecisionTrace::new(DecisionReason::Taken, features).with_bid(order.price, order.qty)
+                });
                 return StrategyAction::Take(order);
             }
         }
 
         // Check for sell signal
-        if signal < self.config.sell_threshold {
+        if signal < sell_threshold {
             // Check position limit
             if self.config.max_position > 0 && self.current_position <= -self.config.max_position {
+                self.trace(|| DecisionTrace::new(DecisionReason::MaxPosition, features));
+                return StrategyAction::None;
+            }
+
+            if !self.has_min_edge(signal, best_bid, best_ask) {
+                self.trace(|| DecisionTrace::new(DecisionReason::InsufficientEdge, features));
                 return StrategyAction::None;
             }
 
             // Calculate order
-            if let Some(order) = self.create_sell_order(signal, best_bid) {
+            if let Some(order) = self.create_sell_order(signal, best_bid, best_ask) {
                 self.record_order(current_time_ns);
+                self.bias = SignalBias::Short;
+                self.last_take_version = Some(features.version);
+                self.trace(|| {
+                    DecisionTrace::new(DecisionReason::Taken, features).with_ask(order.price, order.qty)
+                });
                 return StrategyAction::Take(order);
             }
         }
 
+        self.trace(|| DecisionTrace::new(DecisionReason::BelowThreshold, features));
         StrategyAction::None
     }
 
+    /// Processes features at the current time of the strategy's clock.
+    ///
+    /// Equivalent to `on_features` with `current_time_ns` read from the clock.
+    pub fn on_features_now(&mut self, features: &TickerFeatures, best_bid: Price, best_ask: Price) -> StrategyAction {
+        let now_ns = self.clock.now_nanos().as_u64();
+        self.on_features(features, now_ns, best_bid, best_ask)
+    }
+
     /// Simplified version for testing - uses features mid_price as reference.
     pub fn on_features_simple(&mut self, features: &TickerFeatures, current_time_ns: u64) -> StrategyAction {
         let mid = features.mid_price;
@@ -295,45 +611,126 @@ impl LiquidityTaker {
         current_time_ns >= self.last_order_time_ns + self.effective_interval_ns
     }
 
-    /// Records that an order was sent and applies cooldown.
+    /// Records that an order was sent and backs off the interval.
     fn record_order(&mut self, current_time_ns: u64) {
         self.last_order_time_ns = current_time_ns;
         self.orders_sent += 1;
 
-        // Apply cooldown - increase effective interval
+        // Back off: grow the interval by the cooldown factor, up to the ceiling
         self.effective_interval_ns = ((self.effective_interval_ns as f64 * self.config.cooldown_factor) as u64)
-            .min(self.config.min_order_interval_ns * 10); // Cap at 10x base interval
+            .clamp(self.config.floor_interval_ns(), self.config.ceiling_interval_ns());
+    }
+
+    /// Returns the current interval required between orders, in nanoseconds.
+    #[inline]
+    pub fn effective_interval_ns(&self) -> u64 {
+        self.effective_interval_ns
     }
 
-    /// Resets the cooldown timer (e.g., after a period of inactivity).
+    /// Applies a new configuration while the strategy is running.
+    ///
+    /// Position, order count and the rate limiting state are kept; the
+    /// current interval is clamped into the new floor and ceiling. The
+    /// ticker cannot be changed this way.
+    pub fn update_config(&mut self, config: LiquidityTakerConfig) {
+        let ticker_id = self.config.ticker_id;
+        self.config = LiquidityTakerConfig { ticker_id, ..config };
+        self.loss_breaker
+            .set_limits(self.config.max_consecutive_losses, self.config.loss_cooldown_ns);
+        self.reject_breaker.set_limit(self.config.max_consecutive_rejects);
+        self.effective_interval_ns = self
+            .effective_interval_ns
+            .clamp(self.config.floor_interval_ns(), self.config.ceiling_interval_ns());
+    }
+
+    /// Resets the interval to the floor (e.g., after a period of inactivity).
     pub fn reset_cooldown(&mut self) {
-        self.effective_interval_ns = self.config.min_order_interval_ns;
+        self.effective_interval_ns = self.config.floor_interval_ns();
+    }
+
+    /// Returns the worst price an order on `side` may have under the
+    /// slippage budget, or `None` without one.
+    ///
+    /// The budget is measured from the mid, or from the touch when the other
+    /// side of the book is missing (at `INVALID_PRICE`). It is a fraction of
+    /// the reference's magnitude, so zero and negative prices are handled.
+    pub fn slippage_limit(&self, side: Side, best_bid: Price, best_ask: Price) -> Option<Price> {
+        if self.config.max_slippage_bps == 0 {
+            return None;
+        }
+        let reference = match (BBO::from_prices(best_bid, best_ask).mid_price(), side) {
+            (Some(mid), _) => mid,
+            (None, Side::Buy) => best_ask,
+            (None, Side::Sell) => best_bid,
+        };
+        let slippage = reference.abs() * self.config.max_slippage_bps as Price / 10000;
+        Some(reference + side.as_sign() * slippage)
+    }
+
+    /// Returns the edge a take on `signal` is expected to keep after
+    /// crossing, in basis points of the mid.
+    ///
+    /// The signal implies a move of `edge_bps_per_signal` per unit; crossing
+    /// costs half the spread plus the aggression. With one side of the book
+    /// missing the spread is unknown and only the aggression is charged.
+    pub fn net_edge_bps(&self, signal: f64, best_bid: Price, best_ask: Price) -> f64 {
+        let expected = signal.abs() * self.config.edge_bps_per_signal;
+        let half_spread = if best_bid > 0 && best_ask > best_bid {
+            let mid = (best_bid + best_ask) as f64 / 2.0;
+            (best_ask - best_bid) as f64 / 2.0 / mid * 10000.0
+        } else {
+            0.0
+        };
+        expected - half_spread - self.config.aggression_bps as f64
+    }
+
+    /// Checks the expected edge against the configured minimum.
+    #[inline]
+    fn has_min_edge(&self, signal: f64, best_bid: Price, best_ask: Price) -> bool {
+        self.config.min_edge_bps == 0
+            || self.net_edge_bps(signal, best_bid, best_ask) >= self.config.min_edge_bps as f64
     }
 
     /// Creates a buy order with appropriate price and quantity.
-    fn create_buy_order(&self, signal: f64, best_ask: Price) -> Option<OrderRequest> {
-        let qty = self.calculate_quantity(signal);
+    fn create_buy_order(&self, signal: f64, best_bid: Price, best_ask: Price) -> Option<OrderRequest> {
+        let qty = self.config.instrument.round_to_lot(self.calculate_quantity(signal));
         if qty == 0 {
             return None;
         }
 
-        // Calculate aggressive price (cross the spread)
+        // Calculate aggressive price (cross the spread), capped by the
+        // slippage budget, back onto the grid
         let aggression = (best_ask as f64 * self.config.aggression_bps as f64 / 10000.0) as Price;
-        let price = best_ask + aggression;
+        let mut price = best_ask + aggression;
+        if let Some(limit) = self.slippage_limit(Side::Buy, best_bid, best_ask) {
+            if limit < best_ask {
+                return None;
+            }
+            price = price.min(limit);
+        }
+        let price = self.config.instrument.round_to_tick(price, Side::Buy);
 
         Some(OrderRequest::buy(self.config.ticker_id, price, qty))
     }
 
     /// Creates a sell order with appropriate price and quantity.
-    fn create_sell_order(&self, signal: f64, best_bid: Price) -> Option<OrderRequest> {
-        let qty = self.calculate_quantity(signal);
+    fn create_sell_order(&self, signal: f64, best_bid: Price, best_ask: Price) -> Option<OrderRequest> {
+        let qty = self.config.instrument.round_to_lot(self.calculate_quantity(signal));
         if qty == 0 {
             return None;
         }
 
-        // Calculate aggressive price (cross the spread)
+        // Calculate aggressive price (cross the spread), capped by the
+        // slippage budget, back onto the grid
         let aggression = (best_bid as f64 * self.config.aggression_bps as f64 / 10000.0) as Price;
-        let price = best_bid - aggression;
+        let mut price = best_bid - aggression;
+        if let Some(limit) = self.slippage_limit(Side::Sell, best_bid, best_ask) {
+            if limit > best_bid {
+                return None;
+            }
+            price = price.max(limit);
+        }
+        let price = self.config.instrument.round_to_tick(price, Side::Sell);
 
         Some(OrderRequest::sell(self.config.ticker_id, price, qty))
     }
@@ -365,25 +762,27 @@ impl LiquidityTaker {
         }
     }
 
-    /// Called when an order is filled to reset cooldown partially.
+    /// Called when an order is filled; the interval returns to the floor
+    /// since the taker got what it wanted.
     pub fn on_fill(&mut self) {
-        // After a fill, reduce cooldown by half (we got what we wanted)
-        self.effective_interval_ns = self.effective_interval_ns / 2;
-        self.effective_interval_ns = self.effective_interval_ns.max(self.config.min_order_interval_ns);
+        self.reset_cooldown();
     }
 
     /// Resets the strategy state.
     pub fn reset(&mut self) {
         self.last_order_time_ns = 0;
-        self.effective_interval_ns = self.config.min_order_interval_ns;
+        self.effective_interval_ns = self.config.floor_interval_ns();
         self.orders_sent = 0;
+        self.bias = SignalBias::Neutral;
+        self.last_take_version = None;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use common::Side;
+    use common::{Side, INVALID_PRICE};
+    use common::time::Nanos;
 
     fn make_features(ticker_id: TickerId, fair_value: Price, spread: Price, trade_signal: f64) -> TickerFeatures {
         TickerFeatures {
@@ -393,6 +792,9 @@ mod tests {
             mid_price: fair_value,
             imbalance: 0.0,
             trade_signal,
+            volatility: 0.0,
+            version: 0,
+            has_bbo: true,
         }
     }
 
@@ -615,6 +1017,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_hysteresis_reduces_flips_on_wobbling_signal() {
+        // The signal wobbles just past both thresholds, then one strong sell
+        let signals: Vec<f64> = (0..20)
+            .map(|i| if i % 2 == 0 { 0.12 } else { -0.11 })
+            .chain(std::iter::once(-0.25))
+            .collect();
+        let count_flips = |hysteresis: f64| {
+            let config = LiquidityTakerConfig::new(1)
+                .with_threshold(0.1)
+                .with_min_interval_ns(0)
+                .with_max_position(0)
+                .with_hysteresis(hysteresis);
+            let mut lt = LiquidityTaker::new(config);
+            let mut last_side = None;
+            let mut flips = 0;
+            for (i, &signal) in signals.iter().enumerate() {
+                let features = make_features(1, 10000, 100, signal);
+                if let StrategyAction::Take(order) = lt.on_features_simple(&features, i as u64 + 1) {
+                    if last_side.is_some_and(|side| side != order.side) {
+                        flips += 1;
+                    }
+                    last_side = Some(order.side);
+                }
+            }
+            (flips, lt.bias())
+        };
+
+        assert_eq!(count_flips(0.0), (19, SignalBias::Short));
+        // Only the strong sell clears the exit band below the sell threshold
+        assert_eq!(count_flips(0.1), (1, SignalBias::Short));
+    }
+
+    #[test]
+    fn test_fresh_features_take_once_per_version() {
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_min_interval_ns(0)
+            .with_decision_trace(true)
+            .with_fresh_features(true);
+        let mut lt = LiquidityTaker::new(config);
+        let mut features = make_features(1, 10000, 100, 0.8);
+        features.version = 7;
+
+        // Rate limiting would allow both; the second sees the same snapshot
+        assert!(matches!(lt.on_features_simple(&features, 1), StrategyAction::Take(_)));
+        assert!(matches!(lt.on_features_simple(&features, 2), StrategyAction::None));
+        assert_eq!(lt.last_decision().unwrap().reason, DecisionReason::StaleFeatures);
+
+        features.version = 8;
+        assert!(matches!(lt.on_features_simple(&features, 3), StrategyAction::Take(_)));
+
+        // Without the requirement the same snapshot is taken again
+        let mut lt = LiquidityTaker::new(config.with_fresh_features(false));
+        assert!(matches!(lt.on_features_simple(&features, 1), StrategyAction::Take(_)));
+        assert!(matches!(lt.on_features_simple(&features, 2), StrategyAction::Take(_)));
+    }
+
+    #[test]
+    fn test_hysteresis_widens_only_the_reversing_threshold() {
+        let config = LiquidityTakerConfig::new(1).with_threshold(0.3).with_hysteresis(0.2);
+        let mut lt = LiquidityTaker::new(config);
+        assert_eq!(lt.bias(), SignalBias::Neutral);
+        assert_eq!(lt.effective_thresholds(), (0.3, -0.3));
+
+        lt.on_features_simple(&make_features(1, 10000, 100, 0.5), 1_000_000_000);
+        assert_eq!(lt.bias(), SignalBias::Long);
+        let (buy, sell) = lt.effective_thresholds();
+        assert_eq!(buy, 0.3);
+        assert!((sell + 0.5).abs() < 1e-9);
+
+        lt.reset();
+        assert_eq!(lt.bias(), SignalBias::Neutral);
+    }
+
     // ==================== Rate Limiting Tests ====================
 
     #[test]
@@ -639,6 +1116,40 @@ mod tests {
         assert!(matches!(action3, StrategyAction::Take(_)));
     }
 
+    #[test]
+    fn test_wall_clock_jump_back_does_not_stall_monotonic_rate_limiter() {
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_min_interval_ns(100_000_000);
+        let mut on_monotonic = LiquidityTaker::new(config);
+        let mut on_wall_clock = LiquidityTaker::new(config);
+        let features = make_features(1, 10000, 100, 0.5);
+
+        // Two seconds pass between polls, well past the backoff ceiling. The
+        // wall clock is stepped back an hour after the first poll.
+        let hour_ns = 3_600_000_000_000;
+        let mut monotonic_ns = 5_000_000_000u64;
+        let mut wall_ns = 1_700_000_000_000_000_000u64;
+        let (mut monotonic_takes, mut wall_clock_takes) = (0, 0);
+        for poll in 0..5 {
+            if matches!(on_monotonic.on_features_simple(&features, monotonic_ns), StrategyAction::Take(_)) {
+                monotonic_takes += 1;
+            }
+            if matches!(on_wall_clock.on_features_simple(&features, wall_ns), StrategyAction::Take(_)) {
+                wall_clock_takes += 1;
+            }
+            monotonic_ns += 2_000_000_000;
+            wall_ns += 2_000_000_000;
+            if poll == 0 {
+                wall_ns -= hour_ns;
+            }
+        }
+
+        // Monotonic time keeps taking on schedule; wall time would stall for an hour
+        assert_eq!(monotonic_takes, 5);
+        assert_eq!(wall_clock_takes, 1);
+    }
+
     #[test]
     fn test_cooldown_increases_interval() {
         let config = LiquidityTakerConfig::new(1)
@@ -677,6 +1188,78 @@ mod tests {
         assert_eq!(lt.effective_interval_ns, 100_000_000);
     }
 
+    #[test]
+    fn test_cooldown_backoff_then_recover() {
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_min_interval_ns(100)
+            .with_cooldown_factor(2.0)
+            .with_cooldown_bounds(1.5, 8.0);
+        assert_eq!(config.floor_interval_ns(), 150);
+        assert_eq!(config.ceiling_interval_ns(), 800);
+
+        let mut lt = LiquidityTaker::new(config);
+        assert_eq!(lt.effective_interval_ns(), 150);
+
+        // Each order doubles the interval until it hits the ceiling
+        let features = make_features(1, 10000, 100, 0.5);
+        let mut now = 1_000;
+        let mut intervals = Vec::new();
+        for _ in 0..4 {
+            assert!(matches!(lt.on_features_simple(&features, now), StrategyAction::Take(_)));
+            intervals.push(lt.effective_interval_ns());
+
+            // Blocked until exactly the interval has passed
+            let interval = lt.effective_interval_ns();
+            assert!(matches!(lt.on_features_simple(&features, now + interval - 1), StrategyAction::None));
+            now += interval;
+        }
+        assert_eq!(intervals, vec![300, 600, 800, 800]);
+
+        // A fill recovers straight to the floor
+        lt.on_fill();
+        assert_eq!(lt.effective_interval_ns(), 150);
+        assert!(matches!(lt.on_features_simple(&features, now), StrategyAction::Take(_)));
+        assert_eq!(lt.effective_interval_ns(), 300);
+
+        // Tighter bounds clamp the current interval
+        lt.update_config(config.with_cooldown_bounds(1.0, 2.0));
+        assert_eq!(lt.effective_interval_ns(), 200);
+    }
+
+    #[test]
+    fn test_manual_clock_drives_rate_limiter() {
+        use common::time::ManualClock;
+
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_min_interval_ns(100)
+            .with_cooldown_factor(2.0)
+            .with_cooldown_bounds(1.0, 4.0);
+        let mut lt = LiquidityTaker::new(config);
+        let clock = Arc::new(ManualClock::new(Nanos::new(1_000)));
+        lt.set_clock(clock.clone());
+        let features = make_features(1, 10000, 100, 0.5);
+
+        assert!(matches!(lt.on_features_now(&features, 9950, 10050), StrategyAction::Take(_)));
+        assert_eq!(lt.effective_interval_ns(), 200);
+
+        // Blocked until the clock reaches the backed-off interval
+        clock.advance(199);
+        assert!(matches!(lt.on_features_now(&features, 9950, 10050), StrategyAction::None));
+        clock.advance(1);
+        assert!(matches!(lt.on_features_now(&features, 9950, 10050), StrategyAction::Take(_)));
+        assert_eq!(lt.effective_interval_ns(), 400);
+    }
+
+    #[test]
+    fn test_cooldown_bounds_builder_clamps() {
+        let config = LiquidityTakerConfig::new(1).with_cooldown_bounds(0.5, 0.2);
+        assert!((config.min_interval_multiplier - 1.0).abs() < f64::EPSILON);
+        assert!((config.max_interval_multiplier - 1.0).abs() < f64::EPSILON);
+        assert_eq!(config.floor_interval_ns(), config.ceiling_interval_ns());
+    }
+
     // ==================== Position Limit Tests ====================
 
     #[test]
@@ -774,6 +1357,99 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_slippage_limit_caps_aggression() {
+        // 100 bps aggression on a 10050 ask would buy at 10150
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_aggression_bps(100)
+            .with_max_slippage_bps(80);
+        let mut lt = LiquidityTaker::new(config);
+
+        // Mid 10000: buys capped at 10080, sells at 9920
+        assert_eq!(lt.slippage_limit(Side::Buy, 9950, 10050), Some(10080));
+        let action = lt.on_features(&make_features(1, 10000, 100, 0.5), 1_000_000_000, 9950, 10050);
+        let StrategyAction::Take(order) = action else {
+            panic!("Expected Take action");
+        };
+        assert_eq!(order.price, 10080);
+
+        let action = lt.on_features(&make_features(1, 10000, 100, -0.5), 2_000_000_000, 9950, 10050);
+        let StrategyAction::Take(order) = action else {
+            panic!("Expected Take action");
+        };
+        assert_eq!(order.price, 9920);
+
+        // Without a budget the aggression is uncapped
+        let mut uncapped = LiquidityTaker::new(config.with_max_slippage_bps(0));
+        assert_eq!(uncapped.slippage_limit(Side::Buy, 9950, 10050), None);
+        let action = uncapped.on_features(&make_features(1, 10000, 100, 0.5), 1_000_000_000, 9950, 10050);
+        assert!(matches!(action, StrategyAction::Take(order) if order.price == 10150));
+    }
+
+    #[test]
+    fn test_min_edge_suppresses_takes_across_wide_spread() {
+        // A 0.5 signal implies 20 bps; 5 bps aggression and a 10 bps minimum
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_aggression_bps(5)
+            .with_min_edge(10, 40.0)
+            .with_decision_trace(true);
+
+        // 4 wide around 10000: 20 - 2 - 5 = 13 bps clears the minimum
+        let mut lt = LiquidityTaker::new(config);
+        assert_eq!(lt.net_edge_bps(0.5, 9998, 10002), 13.0);
+        let action = lt.on_features(&make_features(1, 10000, 4, 0.5), 1_000_000_000, 9998, 10002);
+        assert!(matches!(action, StrategyAction::Take(_)));
+
+        // 40 wide: 20 - 20 - 5 = -5 bps, for the same signal either way
+        let mut lt = LiquidityTaker::new(config);
+        assert_eq!(lt.net_edge_bps(0.5, 9980, 10020), -5.0);
+        for signal in [0.5, -0.5] {
+            let action = lt.on_features(&make_features(1, 10000, 40, signal), 1_000_000_000, 9980, 10020);
+            assert!(matches!(action, StrategyAction::None));
+            assert_eq!(lt.last_decision().unwrap().reason, DecisionReason::InsufficientEdge);
+        }
+        assert_eq!(lt.orders_sent(), 0);
+
+        // A strong enough signal pays for the wide spread: 40 - 20 - 5 = 15
+        let action = lt.on_features(&make_features(1, 10000, 40, -1.0), 1_000_000_000, 9980, 10020);
+        assert!(matches!(action, StrategyAction::Take(_)));
+    }
+
+    #[test]
+    fn test_slippage_limit_suppresses_orders_beyond_budget() {
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_max_slippage_bps(80);
+        let mut lt = LiquidityTaker::new(config);
+
+        // A 400 wide book: the touch alone is 200 past the 10000 mid
+        let action = lt.on_features(&make_features(1, 10000, 400, 0.5), 1_000_000_000, 9800, 10200);
+        assert!(matches!(action, StrategyAction::None));
+        let action = lt.on_features(&make_features(1, 10000, 400, -0.5), 1_000_000_000, 9800, 10200);
+        assert!(matches!(action, StrategyAction::None));
+        assert_eq!(lt.orders_sent(), 0);
+
+        // With one side missing the touch is the reference, so it can trade
+        let action = lt.on_features(&make_features(1, 10000, 400, 0.5), 1_000_000_000, INVALID_PRICE, 10200);
+        assert!(matches!(action, StrategyAction::Take(order) if order.price == 10210));
+    }
+
+    #[test]
+    fn test_slippage_limit_around_zero_and_negative_prices() {
+        let config = LiquidityTakerConfig::new(1).with_max_slippage_bps(1000);
+        let lt = LiquidityTaker::new(config);
+
+        // Mid -100: 10% of its magnitude either way
+        assert_eq!(lt.slippage_limit(Side::Buy, -110, -90), Some(-90));
+        assert_eq!(lt.slippage_limit(Side::Sell, -110, -90), Some(-110));
+
+        // A zero bid is a price, not a missing side
+        assert_eq!(lt.slippage_limit(Side::Buy, 0, 200), Some(110));
+        assert_eq!(lt.slippage_limit(Side::Buy, INVALID_PRICE, 200), Some(220));
+    }
+
     // ==================== Fill and Reset Tests ====================
 
     #[test]
@@ -786,15 +1462,15 @@ mod tests {
         // Increase cooldown
         lt.effective_interval_ns = 400_000_000;
 
-        // Fill should reduce cooldown
+        // Fill should reduce cooldown straight to the minimum
         lt.on_fill();
-        assert_eq!(lt.effective_interval_ns, 200_000_000);
+        assert_eq!(lt.effective_interval_ns, 100_000_000);
 
         // But not below minimum
         lt.on_fill();
         lt.on_fill();
         lt.on_fill();
-        assert!(lt.effective_interval_ns >= 100_000_000);
+        assert_eq!(lt.effective_interval_ns, 100_000_000);
     }
 
     #[test]
@@ -831,4 +1507,63 @@ mod tests {
         lt.on_features_simple(&features, 1_000_000_000);
         assert_eq!(lt.orders_sent(), 2);
     }
+
+    #[test]
+    fn test_update_config_keeps_state() {
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_min_interval_ns(1);
+        let mut lt = LiquidityTaker::new(config);
+        lt.set_position(100);
+
+        let features = make_features(1, 10000, 100, 0.5);
+        assert!(lt.on_features_simple(&features, 1_000_000).requires_orders());
+
+        // Raise the threshold above the signal mid-session
+        lt.update_config(config.with_threshold(0.6));
+        assert!(!lt.on_features_simple(&features, 2_000_000).requires_orders());
+        assert_eq!(lt.orders_sent(), 1);
+        assert_eq!(lt.position(), 100);
+    }
+
+    // ==================== Decision Trace Tests ====================
+
+    #[test]
+    fn test_decision_trace_disabled_by_default() {
+        let mut lt = LiquidityTaker::for_ticker(1);
+        lt.on_features_simple(&make_features(1, 10000, 100, 0.5), 1_000_000_000);
+        assert!(lt.last_decision().is_none());
+    }
+
+    #[test]
+    fn test_decision_trace_records_reasons() {
+        let config = LiquidityTakerConfig::new(1)
+            .with_threshold(0.3)
+            .with_min_interval_ns(100_000_000)
+            .with_decision_trace(true);
+        let mut lt = LiquidityTaker::new(config);
+
+        let features = make_features(1, 10000, 100, 0.5);
+
+        // A take records the computed order on its side
+        let action = lt.on_features_simple(&features, 1_000_000_000);
+        let StrategyAction::Take(order) = action else {
+            panic!("expected a take");
+        };
+        let decision = *lt.last_decision().unwrap();
+        assert_eq!(decision.reason, DecisionReason::Taken);
+        assert_eq!(decision.bid_price, order.price);
+        assert_eq!(decision.bid_qty, order.qty);
+        assert!((decision.trade_signal - 0.5).abs() < f64::EPSILON);
+
+        // An immediate retry is rate limited
+        lt.on_features_simple(&features, 1_000_000_001);
+        let decision = lt.last_decision().unwrap();
+        assert_eq!(decision.reason, DecisionReason::RateLimited);
+        assert_eq!(decision.reason.as_str(), "rate_limited");
+
+        // A weak signal is below threshold
+        lt.on_features_simple(&make_features(1, 10000, 100, 0.1), 5_000_000_000);
+        assert_eq!(lt.last_decision().unwrap().reason, DecisionReason::BelowThreshold);
+    }
 }
@@ -3,10 +3,38 @@
 //! The market maker calculates bid and ask prices around the fair value
 //! (from the FeatureEngine) and generates quote updates when market conditions
 //! change. It aims to profit from the bid-ask spread while managing inventory risk.
+//!
+//! Fed its P&L via `set_pnl`, the market maker can also scale its spread by
+//! how the session is going: tighter to lock in gains while up, wider to slow
+//! down while losing, per `pnl_sensitivity`.
+//!
+//! Given the depth feed via `set_depth`, it can also size each quote against
+//! the liquidity already displayed at its price: larger into a thin level
+//! where the quote matters, smaller behind a deep one where it adds little.
+//!
+//! Told the IDs of its resting quotes via `set_quote_order`, the market maker
+//! moves them in place: a side with a resting order is requoted as a replace
+//! of that order, and a requote touching only one resting order is a
+//! `StrategyAction::Replace`, halving the messages of cancel and re-send.
+//!
+//! With a quote obligation configured, `on_features_at` also tracks how much
+//! of the session the maker was quoting both sides within the obligation's
+//! maximum spread, reported by `obligation_compliance`.
+//!
+//! `on_features_now` is `on_features_at` timed by the strategy's clock, the
+//! system monotonic clock unless `set_clock` injects another.
+
+use std::sync::Arc;
 
-use common::{Price, Qty, TickerId};
+use common::time::{Clock, SystemClock};
+use common::{InstrumentSpec, OrderId, Price, Qty, Side, TickerId};
 use crate::features::TickerFeatures;
-use super::{OrderRequest, QuotePair, StrategyAction};
+use crate::market_data::Depth;
+use crate::risk::RiskCheckResult;
+use super::{
+    CycleFills, DecisionReason, DecisionTrace, LossStreakBreaker, OrderRequest, QuoteObligation,
+    QuotePair, RejectStreakBreaker, StrategyAction,
+};
 
 /// Configuration parameters for the market maker strategy.
 #[derive(Debug, Clone, Copy)]
@@ -30,6 +58,51 @@ pub struct MarketMakerConfig {
     pub position_skew_factor: f64,
     /// Maximum position before stopping one-sided quoting.
     pub max_position: i64,
+    /// Widen and skew quotes by the order book imbalance. When disabled,
+    /// quotes sit symmetrically at `half_spread` around fair value.
+    pub enable_imbalance_adjustment: bool,
+    /// Fraction of the half spread added per unit of absolute imbalance.
+    pub imbalance_widen_factor: f64,
+    /// Fraction of the widened half spread both quotes are shifted by per
+    /// unit of imbalance, away from the heavier side.
+    pub imbalance_skew_factor: f64,
+    /// Maximum age of a resting quote in nanoseconds before it is pulled
+    /// and re-quoted, even if the price threshold wasn't crossed (0 = disabled).
+    pub max_quote_age_ns: u64,
+    /// Record a `DecisionTrace` for every features update.
+    pub trace_decisions: bool,
+    /// Consecutive losing round trips that pause the strategy (0 = disabled).
+    pub max_consecutive_losses: u32,
+    /// How long the strategy pauses after a loss streak, in nanoseconds.
+    pub loss_cooldown_ns: u64,
+    /// Consecutive order rejects that deactivate the strategy (0 = disabled).
+    pub max_consecutive_rejects: u32,
+    /// Largest fraction the half spread is tightened by when up, or widened
+    /// by when down, `pnl_scale` or more (0.0 = P&L does not affect quoting).
+    pub pnl_sensitivity: f64,
+    /// Total P&L in cents at which the full adjustment applies.
+    pub pnl_scale: i64,
+    /// Pull the remaining quotes after a cycle with fills, so the next
+    /// update requotes once from the net position.
+    pub requote_after_fills: bool,
+    /// Replace a fully filled quote right away from `on_fill`, rather than
+    /// waiting for the next features update.
+    pub requote_on_fill: bool,
+    /// Tick and lot sizes quote prices and quantities are rounded to.
+    pub instrument: InstrumentSpec,
+    /// Displayed quantity at the quoted price at which quotes keep their
+    /// size; thinner levels get larger quotes and deeper ones smaller
+    /// (0 = depth does not affect sizing).
+    pub depth_reference_qty: Qty,
+    /// Smallest factor depth can scale a quote by.
+    pub min_depth_factor: f64,
+    /// Largest factor depth can scale a quote by.
+    pub max_depth_factor: f64,
+    /// Widest spread, ask minus bid, that meets the quote obligation
+    /// (0 = no obligation tracked).
+    pub obligation_max_spread: Price,
+    /// Fraction of the session the quotes must meet the obligation.
+    pub obligation_min_presence: f64,
 }
 
 impl Default for MarketMakerConfig {
@@ -43,6 +116,24 @@ impl Default for MarketMakerConfig {
             price_update_threshold: 10, // Update quotes when price moves 10 cents
             position_skew_factor: 0.5,  // 50% position skew
             max_position: 1000,    // Stop adding to position at 1000 shares
+            enable_imbalance_adjustment: true,
+            imbalance_widen_factor: 0.5, // Up to 50% wider at full imbalance
+            imbalance_skew_factor: 0.2,  // Shift up to 20% of the half spread
+            max_quote_age_ns: 0,   // Quotes never expire by default
+            trace_decisions: false,
+            max_consecutive_losses: 0, // Never pause on losses by default
+            loss_cooldown_ns: 0,
+            max_consecutive_rejects: 0, // Never stop on rejects by default
+            pnl_sensitivity: 0.0,  // Quote independently of P&L by default
+            pnl_scale: 10_000,     // Full adjustment at $100 up or down
+            requote_after_fills: false,
+            requote_on_fill: false,
+            instrument: InstrumentSpec::new(0),
+            depth_reference_qty: 0, // Size independently of depth by default
+            min_depth_factor: 0.5,
+            max_depth_factor: 2.0,
+            obligation_max_spread: 0, // No quote obligation by default
+            obligation_min_presence: 0.9,
         }
     }
 }
@@ -52,6 +143,7 @@ impl MarketMakerConfig {
     pub fn new(ticker_id: TickerId) -> Self {
         Self {
             ticker_id,
+            instrument: InstrumentSpec::new(ticker_id),
             ..Default::default()
         }
     }
@@ -97,6 +189,89 @@ impl MarketMakerConfig {
         self.max_position = max_position;
         self
     }
+
+    /// Builder method to enable/disable imbalance-based widening and skew.
+    pub fn with_imbalance_adjustment(mut self, enabled: bool) -> Self {
+        self.enable_imbalance_adjustment = enabled;
+        self
+    }
+
+    /// Builder method to set how strongly imbalance widens and skews quotes.
+    pub fn with_imbalance_factors(mut self, widen_factor: f64, skew_factor: f64) -> Self {
+        self.imbalance_widen_factor = widen_factor.max(0.0);
+        self.imbalance_skew_factor = skew_factor.max(0.0);
+        self
+    }
+
+    /// Builder method to set the maximum quote age in nanoseconds.
+    pub fn with_max_quote_age_ns(mut self, max_quote_age_ns: u64) -> Self {
+        self.max_quote_age_ns = max_quote_age_ns;
+        self
+    }
+
+    /// Builder method to enable/disable decision tracing.
+    pub fn with_decision_trace(mut self, enabled: bool) -> Self {
+        self.trace_decisions = enabled;
+        self
+    }
+
+    /// Builder method to pause for `cooldown_ns` after `max_losses`
+    /// consecutive losing round trips.
+    pub fn with_loss_cooldown(mut self, max_losses: u32, cooldown_ns: u64) -> Self {
+        self.max_consecutive_losses = max_losses;
+        self.loss_cooldown_ns = cooldown_ns;
+        self
+    }
+
+    /// Builder method to deactivate after `max_rejects` consecutive order
+    /// rejects.
+    pub fn with_max_consecutive_rejects(mut self, max_rejects: u32) -> Self {
+        self.max_consecutive_rejects = max_rejects;
+        self
+    }
+
+    /// Builder method to scale the half spread by P&L: tightened by up to
+    /// `sensitivity` when up `pnl_scale` cents, widened by as much when down.
+    pub fn with_pnl_sensitivity(mut self, sensitivity: f64, pnl_scale: i64) -> Self {
+        self.pnl_sensitivity = sensitivity.clamp(0.0, 1.0);
+        self.pnl_scale = pnl_scale.max(1);
+        self
+    }
+
+    /// Builder method to enable/disable requoting after a cycle with fills.
+    pub fn with_requote_after_fills(mut self, enabled: bool) -> Self {
+        self.requote_after_fills = enabled;
+        self
+    }
+
+    /// Builder method to enable/disable replacing a filled quote immediately.
+    pub fn with_requote_on_fill(mut self, enabled: bool) -> Self {
+        self.requote_on_fill = enabled;
+        self
+    }
+
+    /// Builder method to round quotes to an instrument's tick and lot sizes.
+    pub fn with_instrument(mut self, instrument: InstrumentSpec) -> Self {
+        self.instrument = instrument;
+        self
+    }
+
+    /// Builder method to scale quote sizes inversely to the displayed
+    /// quantity at the quoted price, within `[min_factor, max_factor]`.
+    pub fn with_depth_sizing(mut self, reference_qty: Qty, min_factor: f64, max_factor: f64) -> Self {
+        self.depth_reference_qty = reference_qty;
+        self.min_depth_factor = min_factor.max(0.0);
+        self.max_depth_factor = max_factor.max(self.min_depth_factor);
+        self
+    }
+
+    /// Builder method to track a quote obligation: both sides quoted at most
+    /// `max_spread` apart for at least `min_presence` of the session.
+    pub fn with_quote_obligation(mut self, max_spread: Price, min_presence: f64) -> Self {
+        self.obligation_max_spread = max_spread;
+        self.obligation_min_presence = min_presence.clamp(0.0, 1.0);
+        self
+    }
 }
 
 /// Market maker strategy state for a single ticker.
@@ -110,21 +285,60 @@ pub struct MarketMaker {
     last_bid_price: Price,
     /// Last quoted ask price.
     last_ask_price: Price,
+    /// Quantity left on the last quoted bid (0 if no bid is resting).
+    quoted_bid_qty: Qty,
+    /// Quantity left on the last quoted ask (0 if no ask is resting).
+    quoted_ask_qty: Qty,
+    /// ID of the resting bid, if known (set externally via set_quote_order).
+    bid_order_id: Option<OrderId>,
+    /// ID of the resting ask, if known (set externally via set_quote_order).
+    ask_order_id: Option<OrderId>,
     /// Current position (tracked externally, updated via set_position).
     current_position: i64,
+    /// Realized P&L in cents (tracked externally, updated via set_pnl).
+    realized_pnl: i64,
+    /// Unrealized P&L in cents (tracked externally, updated via set_pnl).
+    unrealized_pnl: i64,
+    /// Timestamp (ns) of the last quote sent via `on_features_at`.
+    last_quote_time_ns: u64,
+    /// Most recent decision, when tracing is enabled.
+    last_decision: Option<DecisionTrace>,
+    /// Pauses quoting after consecutive losing round trips.
+    loss_breaker: LossStreakBreaker,
+    /// Deactivates the strategy after consecutive order rejects.
+    reject_breaker: RejectStreakBreaker,
+    /// Time the quotes met the quote obligation.
+    obligation: QuoteObligation,
+    /// Latest book depth (set externally via set_depth).
+    depth: Option<Depth>,
     /// Whether the strategy is active.
     active: bool,
+    /// Time source for `on_features_now`.
+    clock: Arc<dyn Clock>,
 }
 
 impl MarketMaker {
     /// Creates a new market maker with the given configuration.
     pub fn new(config: MarketMakerConfig) -> Self {
         Self {
+            loss_breaker: LossStreakBreaker::new(config.max_consecutive_losses, config.loss_cooldown_ns),
+            reject_breaker: RejectStreakBreaker::new(config.max_consecutive_rejects),
+            obligation: QuoteObligation::new(config.obligation_max_spread, config.obligation_min_presence),
             config,
             last_bid_price: 0,
             last_ask_price: 0,
+            quoted_bid_qty: 0,
+            quoted_ask_qty: 0,
+            bid_order_id: None,
+            ask_order_id: None,
             current_position: 0,
+            realized_pnl: 0,
+            unrealized_pnl: 0,
+            last_quote_time_ns: 0,
+            last_decision: None,
+            depth: None,
             active: true,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -133,6 +347,11 @@ impl MarketMaker {
         Self::new(MarketMakerConfig::new(ticker_id))
     }
 
+    /// Replaces the clock read by `on_features_now`.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// Returns a reference to the configuration.
     #[inline]
     pub fn config(&self) -> &MarketMakerConfig {
@@ -145,6 +364,45 @@ impl MarketMaker {
         &mut self.config
     }
 
+    /// Applies a new configuration while the strategy is running.
+    ///
+    /// Position, activation and the current quotes are kept. If the resting
+    /// quotes are tighter than the new spread allows, or keep adding to a
+    /// position that is now at the new limit, they are forgotten and
+    /// `CancelAll` is returned so the next update re-quotes from scratch.
+    /// The ticker cannot be changed this way.
+    ///
+    /// # Arguments
+    /// * `config` - The new configuration
+    ///
+    /// # Returns
+    /// `CancelAll` if the current quotes violate the new configuration,
+    /// otherwise `None`
+    pub fn update_config(&mut self, config: MarketMakerConfig) -> StrategyAction {
+        let ticker_id = self.config.ticker_id;
+        self.config = MarketMakerConfig { ticker_id, ..config };
+        self.loss_breaker
+            .set_limits(self.config.max_consecutive_losses, self.config.loss_cooldown_ns);
+        self.reject_breaker.set_limit(self.config.max_consecutive_rejects);
+        self.obligation
+            .set_limits(self.config.obligation_max_spread, self.config.obligation_min_presence);
+
+        if self.last_bid_price == 0 || self.last_ask_price == 0 {
+            return StrategyAction::None;
+        }
+
+        let min_quoted_spread = 2 * self.config.half_spread.max(self.config.min_spread);
+        let too_tight = self.last_ask_price - self.last_bid_price < min_quoted_spread;
+        let over_position = self.current_position.abs() >= self.config.max_position;
+
+        if too_tight || over_position {
+            self.reset();
+            StrategyAction::CancelAll(ticker_id)
+        } else {
+            StrategyAction::None
+        }
+    }
+
     /// Updates the current position (should be called when fills occur).
     #[inline]
     pub fn set_position(&mut self, position: i64) {
@@ -157,10 +415,152 @@ impl MarketMaker {
         self.current_position
     }
 
-    /// Activates the strategy.
+    /// Applies the fills of one processing cycle at once.
+    ///
+    /// The position is set once to its value after all the cycle's fills,
+    /// so a bid and ask filled together net out rather than passing through
+    /// a one-sided intermediate. With `requote_after_fills` enabled and
+    /// anything filled, the quotes are forgotten and `CancelAll` is returned:
+    /// the next features update requotes both sides from the final position.
+    ///
+    /// # Arguments
+    /// * `position` - Position after every fill of the cycle
+    /// * `fills` - Quantity bought and sold during the cycle
+    ///
+    /// # Returns
+    /// `CancelAll` if the resting quotes should be pulled, otherwise `None`
+    pub fn on_cycle_fills(&mut self, position: i64, fills: CycleFills) -> StrategyAction {
+        self.current_position = position;
+        if fills.is_empty() || !self.config.requote_after_fills {
+            return StrategyAction::None;
+        }
+
+        self.reset();
+        StrategyAction::CancelAll(self.config.ticker_id)
+    }
+
+    /// Applies a fill that consumed the resting quote on one side.
+    ///
+    /// The position moves by the fill. With `requote_on_fill` enabled, a
+    /// replacement quote for the consumed side is returned straight away, at
+    /// the last quoted price and sized for the new position, so the book
+    /// presence is restored without waiting for the next features update.
+    /// Nothing is returned for a side that was never quoted or that the
+    /// new position no longer allows quoting.
+    ///
+    /// # Arguments
+    /// * `side` - Side of the filled quote
+    /// * `qty` - Filled quantity
+    ///
+    /// # Returns
+    /// A one-sided `Quote` replacing the filled one, otherwise `None`
+    pub fn on_fill(&mut self, side: Side, qty: Qty) -> StrategyAction {
+        self.current_position += side.as_sign() * qty as i64;
+        match side {
+            Side::Buy => (self.quoted_bid_qty, self.bid_order_id) = (0, None),
+            Side::Sell => (self.quoted_ask_qty, self.ask_order_id) = (0, None),
+        }
+        if !self.config.requote_on_fill || !self.active {
+            return StrategyAction::None;
+        }
+
+        let ticker_id = self.config.ticker_id;
+        let (bid_qty, ask_qty) = self.calculate_quantities(self.last_bid_price, self.last_ask_price);
+        let quote_pair = match side {
+            Side::Buy if self.last_bid_price != 0 && bid_qty > 0 => {
+                self.quoted_bid_qty = bid_qty;
+                QuotePair::bid_only(OrderRequest::buy(ticker_id, self.last_bid_price, bid_qty))
+            }
+            Side::Sell if self.last_ask_price != 0 && ask_qty > 0 => {
+                self.quoted_ask_qty = ask_qty;
+                QuotePair::ask_only(OrderRequest::sell(ticker_id, self.last_ask_price, ask_qty))
+            }
+            _ => return StrategyAction::None,
+        };
+        StrategyAction::Quote(quote_pair)
+    }
+
+    /// Records the ID of the resting quote on a side, so the next requote
+    /// of that side replaces it (should be called once a quote is submitted,
+    /// and with None once it is gone other than by `on_fill` or a
+    /// `CancelAll` the strategy returned).
+    #[inline]
+    pub fn set_quote_order(&mut self, side: Side, order_id: Option<OrderId>) {
+        match side {
+            Side::Buy => self.bid_order_id = order_id,
+            Side::Sell => self.ask_order_id = order_id,
+        }
+    }
+
+    /// Returns the ID of the resting quote on a side, if known.
+    #[inline]
+    pub fn quote_order(&self, side: Side) -> Option<OrderId> {
+        match side {
+            Side::Buy => self.bid_order_id,
+            Side::Sell => self.ask_order_id,
+        }
+    }
+
+    /// Updates the book depth quote sizes react to (should be called as the
+    /// depth feed changes).
+    #[inline]
+    pub fn set_depth(&mut self, depth: &Depth) {
+        self.depth = Some(depth.clone());
+    }
+
+    /// Returns the factor a quote at `price` on `side` is sized by for the
+    /// depth already displayed there.
+    ///
+    /// The factor is `depth_reference_qty` over the displayed quantity,
+    /// clamped to the configured bounds, so an empty level (or one beyond
+    /// the depth received) gets the largest factor. It is 1.0 when depth
+    /// sizing is disabled or no depth has been set.
+    pub fn depth_factor(&self, side: Side, price: Price) -> f64 {
+        let reference = self.config.depth_reference_qty;
+        let Some(depth) = self.depth.as_ref().filter(|_| reference > 0) else {
+            return 1.0;
+        };
+        let levels = match side {
+            Side::Buy => &depth.bids,
+            Side::Sell => &depth.asks,
+        };
+        let displayed = levels
+            .iter()
+            .find(|&&(level_price, _)| level_price == price)
+            .map_or(0, |&(_, qty)| qty);
+        if displayed == 0 {
+            return self.config.max_depth_factor;
+        }
+        (reference as f64 / displayed as f64)
+            .clamp(self.config.min_depth_factor, self.config.max_depth_factor)
+    }
+
+    /// Updates the realized and unrealized P&L, in cents (should be called
+    /// on fills and mark changes).
+    #[inline]
+    pub fn set_pnl(&mut self, realized_pnl: i64, unrealized_pnl: i64) {
+        self.realized_pnl = realized_pnl;
+        self.unrealized_pnl = unrealized_pnl;
+    }
+
+    /// Returns the total P&L last reported via `set_pnl`, in cents.
+    #[inline]
+    pub fn total_pnl(&self) -> i64 {
+        self.realized_pnl + self.unrealized_pnl
+    }
+
+    /// Returns the factor the configured half spread is scaled by for the
+    /// current P&L: below 1.0 when up, above 1.0 when down.
+    pub fn pnl_spread_factor(&self) -> f64 {
+        let pnl_ratio = (self.total_pnl() as f64 / self.config.pnl_scale as f64).clamp(-1.0, 1.0);
+        1.0 - self.config.pnl_sensitivity * pnl_ratio
+    }
+
+    /// Activates the strategy, re-arming the reject breaker.
     #[inline]
     pub fn activate(&mut self) {
         self.active = true;
+        self.reject_breaker.rearm();
     }
 
     /// Deactivates the strategy.
@@ -187,6 +587,81 @@ impl MarketMaker {
         self.last_ask_price
     }
 
+    /// Returns the most recent decision, if tracing is enabled.
+    #[inline]
+    pub fn last_decision(&self) -> Option<&DecisionTrace> {
+        self.last_decision.as_ref()
+    }
+
+    /// Reports the realized P&L of a close (a fill that reduced the position),
+    /// and whether it took the position flat or through flat.
+    ///
+    /// # Returns
+    /// `true` if this close ended a round trip that started a loss cooldown
+    pub fn on_close(&mut self, realized_pnl: i64, closed_out: bool, now_ns: u64) -> bool {
+        self.loss_breaker.record_close(realized_pnl, closed_out, now_ns)
+    }
+
+    /// Returns the loss streak circuit breaker.
+    #[inline]
+    pub fn loss_breaker(&self) -> &LossStreakBreaker {
+        &self.loss_breaker
+    }
+
+    /// Reports the outcome of one of the strategy's orders.
+    ///
+    /// After `max_consecutive_rejects` rejects in a row the strategy
+    /// deactivates itself; `activate` re-arms it.
+    ///
+    /// # Returns
+    /// `true` if this reject deactivated the strategy
+    pub fn on_order_result(&mut self, result: RiskCheckResult) -> bool {
+        let tripped = self.reject_breaker.record_result(result);
+        if tripped {
+            self.active = false;
+        }
+        tripped
+    }
+
+    /// Returns the order reject circuit breaker.
+    #[inline]
+    pub fn reject_breaker(&self) -> &RejectStreakBreaker {
+        &self.reject_breaker
+    }
+
+    /// Records whether the current quotes meet the quote obligation at
+    /// `now_ns` (called by `on_features_at`; call it directly to account
+    /// for time between features updates).
+    pub fn observe_obligation(&mut self, now_ns: u64) {
+        let resting = |price: Price, qty: Qty| (self.active && qty > 0).then_some(price);
+        let in_spec = self.obligation.is_in_spec(
+            resting(self.last_bid_price, self.quoted_bid_qty),
+            resting(self.last_ask_price, self.quoted_ask_qty),
+        );
+        self.obligation.observe(now_ns, in_spec);
+    }
+
+    /// Returns the fraction of the observed session the quotes met the
+    /// quote obligation (0 if none is configured or no time was observed).
+    #[inline]
+    pub fn obligation_compliance(&self) -> f64 {
+        self.obligation.compliance()
+    }
+
+    /// Returns the quote obligation tracker.
+    #[inline]
+    pub fn obligation(&self) -> &QuoteObligation {
+        &self.obligation
+    }
+
+    /// Records a decision if tracing is enabled.
+    #[inline]
+    fn trace(&mut self, make_trace: impl FnOnce() -> DecisionTrace) {
+        if self.config.trace_decisions {
+            self.last_decision = Some(make_trace());
+        }
+    }
+
     /// Processes features and generates quote updates if needed.
     ///
     /// This is the main strategy entry point. It should be called whenever
@@ -200,11 +675,13 @@ impl MarketMaker {
     pub fn on_features(&mut self, features: &TickerFeatures) -> StrategyAction {
         // Check if strategy is active
         if !self.active {
+            self.trace(|| DecisionTrace::new(DecisionReason::Inactive, features));
             return StrategyAction::None;
         }
 
         // Check if features are valid
         if !features.is_valid() {
+            self.trace(|| DecisionTrace::new(DecisionReason::InvalidFeatures, features));
             return StrategyAction::None;
         }
 
@@ -214,43 +691,152 @@ impl MarketMaker {
         // Check if we need to update quotes
         if self.should_update_quotes(bid_price, ask_price) {
             // Calculate quantities with position skew
-            let (bid_qty, ask_qty) = self.calculate_quantities();
+            let (bid_qty, ask_qty) = self.calculate_quantities(bid_price, ask_price);
 
             // Update last quoted prices
             self.last_bid_price = bid_price;
             self.last_ask_price = ask_price;
+            self.quoted_bid_qty = bid_qty;
+            self.quoted_ask_qty = ask_qty;
+
+            self.trace(|| {
+                DecisionTrace::new(DecisionReason::Quoted, features)
+                    .with_bid(bid_price, bid_qty)
+                    .with_ask(ask_price, ask_qty)
+            });
 
-            // Generate quote pair
+            // Generate quote pair, replacing resting quotes where known
             let quote_pair = self.build_quote_pair(bid_price, bid_qty, ask_price, ask_qty);
-            StrategyAction::Quote(quote_pair)
+            let single_replace = match (quote_pair.bid, quote_pair.ask) {
+                (Some(quote), None) | (None, Some(quote)) => quote.replaces().map(|order_id| (order_id, quote)),
+                _ => None,
+            };
+            match single_replace {
+                Some((order_id, quote)) => StrategyAction::Replace {
+                    order_id,
+                    new_price: quote.price,
+                    new_qty: quote.qty,
+                },
+                None => StrategyAction::Quote(quote_pair),
+            }
         } else {
+            self.trace(|| {
+                DecisionTrace::new(DecisionReason::BelowThreshold, features)
+                    .with_bid(bid_price, 0)
+                    .with_ask(ask_price, 0)
+            });
             StrategyAction::None
         }
     }
 
+    /// Processes features along with the latest book depth.
+    ///
+    /// Equivalent to `set_depth` followed by `on_features`.
+    pub fn on_features_with_depth(&mut self, features: &TickerFeatures, depth: &Depth) -> StrategyAction {
+        self.set_depth(depth);
+        self.on_features(features)
+    }
+
+    /// Processes features with a timestamp, expiring quotes that are too old.
+    ///
+    /// Behaves like `on_features`, but when `max_quote_age_ns` is set and the
+    /// resting quotes are older than that, returns `CancelAll` to pull them.
+    /// The next call then re-quotes from scratch regardless of the price
+    /// update threshold.
+    ///
+    /// During a loss cooldown the first call returns `CancelAll` and later
+    /// calls return `None` until the cooldown ends.
+    ///
+    /// The quotes resulting from the call are then observed against the
+    /// quote obligation, if one is configured.
+    ///
+    /// # Arguments
+    /// * `features` - The current ticker features from the feature engine
+    /// * `now_ns` - Current timestamp in nanoseconds
+    ///
+    /// # Returns
+    /// A `StrategyAction` indicating what action to take (if any)
+    pub fn on_features_at(&mut self, features: &TickerFeatures, now_ns: u64) -> StrategyAction {
+        let action = self.decide_at(features, now_ns);
+        self.observe_obligation(now_ns);
+        action
+    }
+
+    /// Processes features at the current time of the strategy's clock.
+    ///
+    /// Equivalent to `on_features_at` with `now_ns` read from the clock.
+    pub fn on_features_now(&mut self, features: &TickerFeatures) -> StrategyAction {
+        let now_ns = self.clock.now_nanos().as_u64();
+        self.on_features_at(features, now_ns)
+    }
+
+    /// Decides the action for `on_features_at`.
+    fn decide_at(&mut self, features: &TickerFeatures, now_ns: u64) -> StrategyAction {
+        if self.active && self.loss_breaker.is_cooling_down(now_ns) {
+            self.trace(|| DecisionTrace::new(DecisionReason::LossCooldown, features));
+            if self.loss_breaker.take_cancel() {
+                self.reset();
+                return StrategyAction::CancelAll(self.config.ticker_id);
+            }
+            return StrategyAction::None;
+        }
+
+        if self.active && self.is_quote_expired(now_ns) {
+            // Forget the old quote so the next update re-quotes unconditionally
+            self.reset();
+            self.trace(|| DecisionTrace::new(DecisionReason::QuoteExpired, features));
+            return StrategyAction::CancelAll(self.config.ticker_id);
+        }
+
+        let action = self.on_features(features);
+        if matches!(action, StrategyAction::Quote(_)) {
+            self.last_quote_time_ns = now_ns;
+        }
+        action
+    }
+
+    /// Returns true if the current quotes have outlived `max_quote_age_ns`.
+    #[inline]
+    pub fn is_quote_expired(&self, now_ns: u64) -> bool {
+        self.config.max_quote_age_ns > 0
+            && self.last_bid_price != 0
+            && now_ns.saturating_sub(self.last_quote_time_ns) >= self.config.max_quote_age_ns
+    }
+
     /// Calculates bid and ask prices based on fair value and spread settings.
     ///
     /// The bid is placed at fair_value - half_spread and the ask at
-    /// fair_value + half_spread, adjusted by the order book imbalance.
+    /// fair_value + half_spread, adjusted by the order book imbalance unless
+    /// `enable_imbalance_adjustment` is off. The half spread is first scaled
+    /// by `pnl_spread_factor`, and the prices are rounded away from each
+    /// other onto the instrument's tick grid.
     fn calculate_quotes(&self, features: &TickerFeatures) -> (Price, Price) {
         let fair_value = features.fair_value;
 
+        // Tighten when up, widen when down
+        let half_spread = (self.config.half_spread as f64 * self.pnl_spread_factor()).round() as Price;
+
+        let imbalance = if self.config.enable_imbalance_adjustment { features.imbalance } else { 0.0 };
+
         // Adjust spread based on market conditions
         // Widen spread when imbalance is high (more uncertainty)
-        let imbalance_adjustment = (features.imbalance.abs() * self.config.half_spread as f64 * 0.5) as Price;
-        let adjusted_half_spread = (self.config.half_spread + imbalance_adjustment)
+        let imbalance_adjustment =
+            (imbalance.abs() * half_spread as f64 * self.config.imbalance_widen_factor) as Price;
+        let adjusted_half_spread = (half_spread + imbalance_adjustment)
             .max(self.config.min_spread);
 
         // Skew quotes based on order book imbalance
         // Positive imbalance (more bids) -> lower our bid, raise our ask
         // This helps avoid adverse selection
-        let imbalance_skew = (features.imbalance * adjusted_half_spread as f64 * 0.2) as Price;
+        let imbalance_skew =
+            (imbalance * adjusted_half_spread as f64 * self.config.imbalance_skew_factor) as Price;
 
-        let bid_price = fair_value - adjusted_half_spread - imbalance_skew;
-        let ask_price = fair_value + adjusted_half_spread - imbalance_skew;
+        let instrument = &self.config.instrument;
+        let bid_price = instrument.round_to_tick(fair_value - adjusted_half_spread - imbalance_skew, Side::Buy);
+        let ask_price = instrument.round_to_tick(fair_value + adjusted_half_spread - imbalance_skew, Side::Sell);
 
         // Ensure bid < ask
-        let bid_price = bid_price.min(ask_price - 1);
+        let bid_price = bid_price.min(ask_price - instrument.tick_size);
 
         (bid_price, ask_price)
     }
@@ -258,8 +844,9 @@ impl MarketMaker {
     /// Calculates quote quantities based on position and skew settings.
     ///
     /// When we have a long position, we reduce bid quantity and increase ask quantity
-    /// to help reduce the position. The opposite for short positions.
-    fn calculate_quantities(&self) -> (Qty, Qty) {
+    /// to help reduce the position. The opposite for short positions. Each
+    /// side is then scaled by its `depth_factor` at the quoted price.
+    fn calculate_quantities(&self, bid_price: Price, ask_price: Price) -> (Qty, Qty) {
         let base = self.config.base_qty as f64;
         let max = self.config.max_qty;
         let max_pos = self.config.max_position as f64;
@@ -275,11 +862,14 @@ impl MarketMaker {
         // Calculate skewed quantities
         // Long position (positive ratio) -> reduce bid qty, increase ask qty
         // Short position (negative ratio) -> increase bid qty, reduce ask qty
-        let bid_factor = 1.0 - (skew * position_ratio).max(0.0);
-        let ask_factor = 1.0 + (skew * position_ratio).min(0.0);
+        let bid_factor = (1.0 - (skew * position_ratio).max(0.0)) * self.depth_factor(Side::Buy, bid_price);
+        let ask_factor = (1.0 + (skew * position_ratio).min(0.0)) * self.depth_factor(Side::Sell, ask_price);
 
-        let bid_qty = ((base * bid_factor) as Qty).clamp(1, max);
-        let ask_qty = ((base * ask_factor) as Qty).clamp(1, max);
+        // At least one whole lot per side
+        let lot_size = self.config.instrument.lot_size;
+        let lots = |qty: f64| self.config.instrument.round_to_lot((qty as Qty).clamp(1, max)).max(lot_size);
+        let bid_qty = lots(base * bid_factor);
+        let ask_qty = lots(base * ask_factor);
 
         // If at max position, stop quoting on the side that increases position
         let bid_qty = if self.current_position >= self.config.max_position {
@@ -311,7 +901,8 @@ impl MarketMaker {
         bid_moved || ask_moved
     }
 
-    /// Builds a QuotePair from the calculated prices and quantities.
+    /// Builds a QuotePair from the calculated prices and quantities, each
+    /// side replacing its resting quote if one is known.
     fn build_quote_pair(
         &self,
         bid_price: Price,
@@ -321,14 +912,19 @@ impl MarketMaker {
     ) -> QuotePair {
         let ticker_id = self.config.ticker_id;
 
+        let quote = |side, price, qty, resting: Option<OrderId>| match resting {
+            Some(order_id) => OrderRequest::replace(order_id, ticker_id, side, price, qty),
+            None => OrderRequest::new(ticker_id, side, price, qty),
+        };
+
         let bid = if bid_qty > 0 {
-            Some(OrderRequest::buy(ticker_id, bid_price, bid_qty))
+            Some(quote(Side::Buy, bid_price, bid_qty, self.bid_order_id))
         } else {
             None
         };
 
         let ask = if ask_qty > 0 {
-            Some(OrderRequest::sell(ticker_id, ask_price, ask_qty))
+            Some(quote(Side::Sell, ask_price, ask_qty, self.ask_order_id))
         } else {
             None
         };
@@ -340,12 +936,18 @@ impl MarketMaker {
     pub fn reset(&mut self) {
         self.last_bid_price = 0;
         self.last_ask_price = 0;
+        self.quoted_bid_qty = 0;
+        self.quoted_ask_qty = 0;
+        self.bid_order_id = None;
+        self.ask_order_id = None;
+        self.last_quote_time_ns = 0;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::time::Nanos;
 
     fn make_features(ticker_id: TickerId, fair_value: Price, spread: Price, imbalance: f64) -> TickerFeatures {
         TickerFeatures {
@@ -355,6 +957,9 @@ mod tests {
             mid_price: fair_value,
             imbalance,
             trade_signal: 0.0,
+            volatility: 0.0,
+            version: 0,
+            has_bbo: true,
         }
     }
 
@@ -397,6 +1002,249 @@ mod tests {
         assert!(config.position_skew_factor.abs() < f64::EPSILON);
     }
 
+    // ==================== Quote Expiry Tests ====================
+
+    #[test]
+    fn test_stale_quote_cancelled_and_requoted() {
+        let config = MarketMakerConfig::new(1).with_max_quote_age_ns(1_000_000);
+        let mut mm = MarketMaker::new(config);
+        let features = make_features(1, 10000, 100, 0.0);
+
+        // Initial quote
+        let action = mm.on_features_at(&features, 1_000_000_000);
+        assert!(matches!(action, StrategyAction::Quote(_)));
+
+        // Unchanged market within max age - no action
+        let action = mm.on_features_at(&features, 1_000_500_000);
+        assert!(matches!(action, StrategyAction::None));
+
+        // Past max age - quotes are pulled even though price hasn't moved
+        let action = mm.on_features_at(&features, 1_001_000_000);
+        assert!(matches!(action, StrategyAction::CancelAll(1)));
+
+        // Fresh quote follows
+        let action = mm.on_features_at(&features, 1_001_000_001);
+        assert!(matches!(action, StrategyAction::Quote(_)));
+        assert!(!mm.is_quote_expired(1_001_500_000));
+    }
+
+    #[test]
+    fn test_manual_clock_expires_quotes() {
+        use common::time::ManualClock;
+
+        let config = MarketMakerConfig::new(1).with_max_quote_age_ns(1_000_000);
+        let mut mm = MarketMaker::new(config);
+        let clock = Arc::new(ManualClock::new(Nanos::new(1_000_000_000)));
+        mm.set_clock(clock.clone());
+        let features = make_features(1, 10000, 100, 0.0);
+
+        assert!(matches!(mm.on_features_now(&features), StrategyAction::Quote(_)));
+        clock.advance(999_999);
+        assert!(matches!(mm.on_features_now(&features), StrategyAction::None));
+        clock.advance(1);
+        assert!(matches!(mm.on_features_now(&features), StrategyAction::CancelAll(1)));
+    }
+
+    #[test]
+    fn test_quote_expiry_disabled_by_default() {
+        let mut mm = MarketMaker::for_ticker(1);
+        let features = make_features(1, 10000, 100, 0.0);
+
+        assert!(matches!(mm.on_features_at(&features, 0), StrategyAction::Quote(_)));
+        let action = mm.on_features_at(&features, u64::MAX);
+        assert!(matches!(action, StrategyAction::None));
+    }
+
+    #[test]
+    fn test_pnl_tightens_quotes_when_up_and_widens_when_down() {
+        let config = MarketMakerConfig::new(1)
+            .with_half_spread(100)
+            .with_min_spread(10)
+            .with_pnl_sensitivity(0.5, 10_000);
+        let features = make_features(1, 10000, 100, 0.0);
+        let quotes = |realized_pnl, unrealized_pnl| {
+            let mut mm = MarketMaker::new(config);
+            mm.set_pnl(realized_pnl, unrealized_pnl);
+            match mm.on_features(&features) {
+                StrategyAction::Quote(pair) => (pair.bid.unwrap().price, pair.ask.unwrap().price),
+                action => panic!("expected quotes, got {:?}", action),
+            }
+        };
+
+        assert_eq!(quotes(0, 0), (9900, 10100));
+        // Up $100 unrealized: half the half spread
+        assert_eq!(quotes(0, 10_000), (9950, 10050));
+        // Down $100 unrealized: one and a half times it
+        assert_eq!(quotes(0, -10_000), (9850, 10150));
+        // Realized and unrealized P&L combine, and the ratio is capped
+        assert_eq!(quotes(5_000, -5_000), (9900, 10100));
+        assert_eq!(quotes(0, 50_000), (9950, 10050));
+
+        // Without sensitivity P&L is ignored
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1).with_half_spread(100));
+        mm.set_pnl(0, 10_000);
+        assert_eq!(mm.pnl_spread_factor(), 1.0);
+    }
+
+    #[test]
+    fn test_bid_fill_requotes_bid_with_position_skew() {
+        let config = MarketMakerConfig::new(1)
+            .with_base_qty(100)
+            .with_max_position(1000)
+            .with_position_skew(0.5)
+            .with_requote_on_fill(true);
+        let mut mm = MarketMaker::new(config);
+        let features = make_features(1, 10000, 10, 0.0);
+
+        let StrategyAction::Quote(initial) = mm.on_features(&features) else {
+            panic!("expected initial quotes");
+        };
+        let initial_bid = initial.bid.unwrap();
+
+        // The whole bid is hit: a smaller bid goes straight back at the same price
+        let StrategyAction::Quote(replacement) = mm.on_fill(Side::Buy, initial_bid.qty) else {
+            panic!("expected a replacement bid");
+        };
+        assert_eq!(mm.position(), 100);
+        assert!(replacement.ask.is_none());
+        let bid = replacement.bid.unwrap();
+        assert_eq!(bid.side, Side::Buy);
+        assert_eq!(bid.price, initial_bid.price);
+        assert_eq!(bid.qty, 95); // 100 * (1 - 0.5 * 100 / 1000)
+        assert!(bid.qty < initial_bid.qty);
+
+        // The requote did not move the quoted prices
+        assert!(matches!(mm.on_features(&features), StrategyAction::None));
+    }
+
+    #[test]
+    fn test_thin_level_gets_larger_quote_than_deep_level() {
+        let config = MarketMakerConfig::new(1)
+            .with_base_qty(100)
+            .with_max_qty(500)
+            .with_depth_sizing(200, 0.25, 3.0);
+        let features = make_features(1, 10000, 10, 0.0);
+        let depth_with_bid = |qty: Qty| Depth {
+            bids: vec![(9950, qty), (9940, 1000)],
+            asks: vec![(10050, 200)],
+        };
+
+        let quote = |depth: &Depth| {
+            let mut mm = MarketMaker::new(config);
+            match mm.on_features_with_depth(&features, depth) {
+                StrategyAction::Quote(pair) => (pair.bid.unwrap(), pair.ask.unwrap(), mm),
+                _ => panic!("expected quotes"),
+            }
+        };
+        let (thin_bid, thin_ask, thin) = quote(&depth_with_bid(50));
+        let (deep_bid, deep_ask, deep) = quote(&depth_with_bid(5000));
+
+        // Same prices and ask, but a much larger bid into the thin level
+        assert_eq!((thin_bid.price, deep_bid.price), (9950, 9950));
+        assert_eq!(thin.depth_factor(Side::Buy, 9950), 3.0);
+        assert_eq!(deep.depth_factor(Side::Buy, 9950), 0.25);
+        assert_eq!((thin_bid.qty, deep_bid.qty), (300, 25));
+        assert_eq!(thin_ask.qty, deep_ask.qty);
+        assert_eq!(thin.depth_factor(Side::Sell, 10050), 1.0);
+
+        // An empty level gets the largest factor; disabled sizing ignores depth
+        assert_eq!(deep.depth_factor(Side::Sell, 10060), 3.0);
+        let mut plain = MarketMaker::for_ticker(1);
+        plain.set_depth(&depth_with_bid(50));
+        assert_eq!(plain.depth_factor(Side::Buy, 9950), 1.0);
+    }
+
+    #[test]
+    fn test_on_fill_without_requote_only_tracks_position() {
+        let mut mm = MarketMaker::for_ticker(1);
+        mm.on_features(&make_features(1, 10000, 10, 0.0));
+        assert!(matches!(mm.on_fill(Side::Sell, 100), StrategyAction::None));
+        assert_eq!(mm.position(), -100);
+
+        // At the long limit a filled bid is not replaced
+        let config = MarketMakerConfig::new(1).with_max_position(100).with_requote_on_fill(true);
+        let mut mm = MarketMaker::new(config);
+        mm.on_features(&make_features(1, 10000, 10, 0.0));
+        assert!(matches!(mm.on_fill(Side::Buy, 100), StrategyAction::None));
+        assert!(matches!(mm.on_fill(Side::Sell, 50), StrategyAction::Quote(_)));
+    }
+
+    #[test]
+    fn test_paired_fills_requote_symmetrically_from_flat() {
+        let config = MarketMakerConfig::new(1)
+            .with_half_spread(100)
+            .with_price_threshold(1000)
+            .with_requote_after_fills(true);
+        let mut mm = MarketMaker::new(config);
+        let features = make_features(1, 10000, 100, 0.0);
+        let StrategyAction::Quote(before) = mm.on_features(&features) else {
+            panic!("expected initial quotes");
+        };
+
+        // Bid and ask fill for 100 each in the same cycle
+        let mut fills = CycleFills::default();
+        fills.record(common::Side::Buy, 100);
+        fills.record(common::Side::Sell, 100);
+        assert!(fills.is_two_sided());
+        assert_eq!(fills.net(), 0);
+
+        let action = mm.on_cycle_fills(fills.net(), fills);
+        assert!(matches!(action, StrategyAction::CancelAll(1)));
+        assert_eq!(mm.position(), 0);
+
+        // Requotes once, even though the price has not moved, both sides alike
+        let StrategyAction::Quote(after) = mm.on_features(&features) else {
+            panic!("expected a requote");
+        };
+        assert_eq!(after.bid, before.bid);
+        assert_eq!(after.ask, before.ask);
+        assert_eq!(after.bid.unwrap().qty, after.ask.unwrap().qty);
+        assert!(matches!(mm.on_features(&features), StrategyAction::None));
+
+        // Without the option only the position is applied
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1));
+        mm.on_features(&features);
+        assert!(matches!(mm.on_cycle_fills(0, fills), StrategyAction::None));
+        assert!(matches!(mm.on_features(&features), StrategyAction::None));
+    }
+
+    #[test]
+    fn test_loss_streak_pauses_quoting() {
+        use crate::position::PositionKeeper;
+        use common::Side;
+
+        let config = MarketMakerConfig::new(1).with_loss_cooldown(3, 1_000_000);
+        let mut mm = MarketMaker::new(config);
+        let mut keeper = PositionKeeper::new();
+        let features = make_features(1, 10000, 100, 0.0);
+        assert!(matches!(mm.on_features_at(&features, 0), StrategyAction::Quote(_)));
+
+        // Round trips of one lot, losing with a win in between
+        let closes = [(9990, 100), (10020, 200), (9990, 300), (9980, 400)];
+        for &(price, now) in &closes {
+            keeper.on_fill(1, Side::Buy, 1, 10000);
+            let realized = keeper.on_fill(1, Side::Sell, 1, price).unwrap();
+            assert!(!mm.on_close(realized, true, now));
+        }
+        assert_eq!(mm.loss_breaker().streak(), 2);
+
+        // Closing the next position in parts counts as one round trip
+        keeper.on_fill(1, Side::Buy, 2, 10000);
+        let realized = keeper.on_fill(1, Side::Sell, 1, 9970).unwrap();
+        assert!(!mm.on_close(realized, false, 450));
+        assert_eq!(mm.loss_breaker().streak(), 2);
+
+        // Its last part ends the third losing round trip in a row
+        let realized = keeper.on_fill(1, Side::Sell, 1, 9970).unwrap();
+        assert!(realized < 0);
+        assert!(mm.on_close(realized, true, 500));
+
+        // Quotes are pulled once, then nothing until the cooldown ends
+        assert!(matches!(mm.on_features_at(&features, 600), StrategyAction::CancelAll(1)));
+        assert!(matches!(mm.on_features_at(&features, 700), StrategyAction::None));
+        assert!(matches!(mm.on_features_at(&features, 1_000_500), StrategyAction::Quote(_)));
+    }
+
     // ==================== Market Maker Construction Tests ====================
 
     #[test]
@@ -661,6 +1509,42 @@ mod tests {
         assert!(spread2 >= spread1, "Higher imbalance should result in wider spread");
     }
 
+    #[test]
+    fn test_imbalance_adjustment_disabled_quotes_symmetrically() {
+        let config = MarketMakerConfig::new(1)
+            .with_half_spread(50)
+            .with_imbalance_adjustment(false);
+
+        for imbalance in [-1.0, -0.8, 0.0, 0.3, 0.8, 1.0] {
+            let mut mm = MarketMaker::new(config);
+            match mm.on_features(&make_features(1, 10000, 100, imbalance)) {
+                StrategyAction::Quote(pair) => {
+                    assert_eq!(pair.bid.unwrap().price, 9950, "imbalance {}", imbalance);
+                    assert_eq!(pair.ask.unwrap().price, 10050, "imbalance {}", imbalance);
+                }
+                _ => panic!("Expected Quote"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_imbalance_factors_scale_widening_and_skew() {
+        let quote = |config: MarketMakerConfig| match MarketMaker::new(config)
+            .on_features(&make_features(1, 10000, 100, 0.8))
+        {
+            StrategyAction::Quote(pair) => (pair.bid.unwrap().price, pair.ask.unwrap().price),
+            _ => panic!("Expected Quote"),
+        };
+        let config = MarketMakerConfig::new(1).with_half_spread(50);
+
+        // Defaults: half spread 50 + 20 = 70, skew 70 * 0.8 * 0.2 = 11
+        assert_eq!(quote(config), (9919, 10059));
+        // Half spread 50 + 40 = 90, no skew
+        assert_eq!(quote(config.with_imbalance_factors(1.0, 0.0)), (9910, 10090));
+        // No widening, skew 50 * 0.8 * 0.5 = 20
+        assert_eq!(quote(config.with_imbalance_factors(0.0, 0.5)), (9930, 10030));
+    }
+
     // ==================== Reset Tests ====================
 
     #[test]
@@ -693,4 +1577,178 @@ mod tests {
         let action = mm.on_features(&features);
         assert!(matches!(action, StrategyAction::Quote(_)));
     }
+
+    // ==================== Config Update Tests ====================
+
+    #[test]
+    fn test_update_config_wider_spread_requotes() {
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1).with_half_spread(50));
+        mm.set_position(200);
+
+        let features = make_features(1, 10000, 100, 0.0);
+        mm.on_features(&features);
+        assert_eq!(mm.last_bid_price(), 9950);
+        assert_eq!(mm.last_ask_price(), 10050);
+
+        // Resting quotes are tighter than the new spread: pull them
+        let wider = *mm.config();
+        let action = mm.update_config(wider.with_half_spread(80));
+        assert!(matches!(action, StrategyAction::CancelAll(1)));
+        assert_eq!(mm.config().half_spread, 80);
+
+        // Position and activation survive the update
+        assert_eq!(mm.position(), 200);
+        assert!(mm.is_active());
+
+        let StrategyAction::Quote(quotes) = mm.on_features(&features) else {
+            panic!("expected quotes");
+        };
+        assert_eq!(quotes.bid.unwrap().price, 9920);
+        assert_eq!(quotes.ask.unwrap().price, 10080);
+    }
+
+    #[test]
+    fn test_update_config_keeps_compatible_quotes() {
+        let mut mm = MarketMaker::for_ticker(1);
+        let features = make_features(1, 10000, 100, 0.0);
+        mm.on_features(&features);
+
+        // A narrower spread doesn't invalidate wider resting quotes
+        let config = MarketMakerConfig::new(7).with_half_spread(30);
+        assert!(matches!(mm.update_config(config), StrategyAction::None));
+        assert_eq!(mm.config().ticker_id, 1);
+        assert_eq!(mm.last_bid_price(), 9950);
+
+        // A position limit below the current position does
+        mm.set_position(500);
+        let config = mm.config().with_max_position(400);
+        assert!(matches!(mm.update_config(config), StrategyAction::CancelAll(1)));
+    }
+
+    // ==================== Decision Trace Tests ====================
+
+    #[test]
+    fn test_decision_trace_records_quotes() {
+        let config = MarketMakerConfig::new(1)
+            .with_price_threshold(10)
+            .with_decision_trace(true);
+        let mut mm = MarketMaker::new(config);
+
+        let features = make_features(1, 10000, 100, 0.0);
+        let action = mm.on_features(&features);
+        let StrategyAction::Quote(quotes) = action else {
+            panic!("expected quotes");
+        };
+
+        let decision = *mm.last_decision().unwrap();
+        assert_eq!(decision.reason, DecisionReason::Quoted);
+        assert_eq!(decision.fair_value, 10000);
+        assert_eq!(decision.bid_price, quotes.bid.unwrap().price);
+        assert_eq!(decision.bid_qty, quotes.bid.unwrap().qty);
+        assert_eq!(decision.ask_price, quotes.ask.unwrap().price);
+        assert_eq!(decision.ask_qty, quotes.ask.unwrap().qty);
+
+        // Same features again: price move below the update threshold
+        mm.on_features(&features);
+        assert_eq!(mm.last_decision().unwrap().reason, DecisionReason::BelowThreshold);
+
+        mm.deactivate();
+        mm.on_features(&features);
+        assert_eq!(mm.last_decision().unwrap().reason.as_str(), "inactive");
+    }
+
+    #[test]
+    fn test_decision_trace_disabled_by_default() {
+        let mut mm = MarketMaker::for_ticker(1);
+        mm.on_features(&make_features(1, 10000, 100, 0.0));
+        assert!(mm.last_decision().is_none());
+    }
+
+    // ==================== Replace Tests ====================
+
+    #[test]
+    fn test_requote_replaces_resting_orders() {
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1).with_price_threshold(10));
+        let StrategyAction::Quote(quotes) = mm.on_features(&make_features(1, 10000, 100, 0.0)) else {
+            panic!("expected quotes");
+        };
+        assert_eq!(quotes.bid.unwrap().replaces(), None);
+
+        // With both IDs known, both sides are amended
+        mm.set_quote_order(Side::Buy, Some(11));
+        mm.set_quote_order(Side::Sell, Some(12));
+        let StrategyAction::Quote(quotes) = mm.on_features(&make_features(1, 10050, 100, 0.0)) else {
+            panic!("expected quotes");
+        };
+        assert_eq!(quotes.bid.unwrap().replaces(), Some(11));
+        assert_eq!(quotes.ask.unwrap().replaces(), Some(12));
+        assert_eq!(quotes.bid.unwrap().price, 10000);
+
+        // A filled ask is gone: the bid is amended, the ask sent anew
+        mm.on_fill(Side::Sell, 100);
+        assert_eq!(mm.quote_order(Side::Sell), None);
+        let StrategyAction::Quote(quotes) = mm.on_features(&make_features(1, 10100, 100, 0.0)) else {
+            panic!("expected quotes");
+        };
+        assert_eq!(quotes.bid.unwrap().replaces(), Some(11));
+        assert_eq!(quotes.ask.unwrap().replaces(), None);
+
+        // At the short limit only the bid is requoted: a single replace
+        mm.set_position(-mm.config().max_position);
+        let action = mm.on_features(&make_features(1, 10150, 100, 0.0));
+        assert!(matches!(
+            action,
+            StrategyAction::Replace { order_id: 11, new_price: 10100, .. }
+        ));
+
+        // Cancelling everything forgets the IDs
+        mm.reset();
+        assert_eq!(mm.quote_order(Side::Buy), None);
+    }
+
+    // ==================== Quote Obligation Tests ====================
+
+    #[test]
+    fn test_quote_obligation_compliance() {
+        const SEC: u64 = 1_000_000_000;
+        // Quotes 100 wide at no imbalance, 150 wide at full imbalance
+        let config = MarketMakerConfig::new(1).with_quote_obligation(120, 0.9);
+        let mut mm = MarketMaker::new(config);
+
+        // Two-sided within the spread for the first 90 seconds
+        for t in (0..=90).step_by(10) {
+            mm.on_features_at(&make_features(1, 10000, 100, 0.0), t * SEC);
+        }
+        assert_eq!(mm.obligation_compliance(), 1.0);
+        assert_eq!(mm.obligation().breaches(), 0);
+
+        // Quotes widen past the obligation for 5 seconds
+        let action = mm.on_features_at(&make_features(1, 10000, 100, 1.0), 90 * SEC);
+        assert!(matches!(action, StrategyAction::Quote(_)));
+        assert_eq!(mm.last_ask_price() - mm.last_bid_price(), 150);
+        mm.on_features_at(&make_features(1, 10000, 100, 0.0), 95 * SEC);
+
+        // The ask is filled, leaving the bid alone until prices move
+        mm.on_fill(Side::Sell, 100);
+        mm.observe_obligation(100 * SEC);
+        mm.on_features_at(&make_features(1, 10000, 100, 0.0), 105 * SEC);
+        mm.on_features_at(&make_features(1, 10020, 100, 0.0), 110 * SEC);
+        mm.on_features_at(&make_features(1, 10020, 100, 0.0), 120 * SEC);
+
+        // In spec for 90 + 5 + 10 of 120 seconds
+        assert_eq!(mm.obligation().in_spec_ns(), 105 * SEC);
+        assert_eq!(mm.obligation().observed_ns(), 120 * SEC);
+        assert_eq!(mm.obligation_compliance(), 0.875);
+        assert_eq!(mm.obligation().breaches(), 2);
+        assert!(!mm.obligation().meets_obligation());
+    }
+
+    #[test]
+    fn test_quote_obligation_disabled_by_default() {
+        let mut mm = MarketMaker::for_ticker(1);
+        mm.on_features_at(&make_features(1, 10000, 100, 0.0), 0);
+        mm.on_features_at(&make_features(1, 10000, 100, 0.0), 1_000);
+        assert_eq!(mm.obligation_compliance(), 0.0);
+        assert_eq!(mm.obligation().observed_ns(), 0);
+    }
 }
@@ -10,14 +10,51 @@
 //!
 //! Both strategies consume `TickerFeatures` from the feature engine and generate
 //! `OrderRequest` outputs that can be processed by the trade engine.
+//!
+//! Either strategy can be paused after a run of losing round trips: fed the
+//! realized P&L of each close, its `LossStreakBreaker` totals them from flat
+//! back to flat, trips after the configured number of consecutive losing
+//! round trips, and the strategy cancels its orders and stays idle for the
+//! cooldown period.
+//!
+//! A strategy whose orders keep being rejected (a misconfigured price band or
+//! limit) can likewise be stopped: its `RejectStreakBreaker` counts rejects
+//! in a row, and once the limit is reached the strategy deactivates itself
+//! until it is activated again by hand.
+//!
+//! A market maker with quoting obligations tracks them with a
+//! `QuoteObligation`: the time its quotes were two-sided within the maximum
+//! spread, as a fraction of the time observed, and how often they fell out
+//! of that spec.
+//!
+//! A market maker told the IDs of its resting quotes moves them in place:
+//! a quote leg for a side that already has an order is a `Replace` request
+//! amending that order, rather than a new order alongside it, and a move of
+//! a single resting quote is a `StrategyAction::Replace`.
+//!
+//! Fills that arrive together, such as both sides of a market maker's quote
+//! trading in one cycle, are summed into a `CycleFills` so the strategy can
+//! react once to the net result instead of to each intermediate position.
 
 pub mod market_maker;
 pub mod liquidity_taker;
 
 pub use market_maker::{MarketMaker, MarketMakerConfig};
-pub use liquidity_taker::{LiquidityTaker, LiquidityTakerConfig};
+pub use liquidity_taker::{LiquidityTaker, LiquidityTakerConfig, SignalBias};
+
+use common::{OrderId, Price, Qty, Side, TickerId};
+use crate::features::TickerFeatures;
+use crate::risk::RiskCheckResult;
 
-use common::{Price, Qty, Side, TickerId};
+/// What an order request asks of the exchange.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderRequestType {
+    /// Send a new order.
+    #[default]
+    New,
+    /// Amend the given resting order to the request's price and quantity.
+    Replace(OrderId),
+}
 
 /// Represents an order request generated by a strategy.
 ///
@@ -33,6 +70,8 @@ pub struct OrderRequest {
     pub price: Price,
     /// Quantity to trade.
     pub qty: Qty,
+    /// New order, or amendment of a resting one.
+    pub request_type: OrderRequestType,
 }
 
 impl OrderRequest {
@@ -44,6 +83,25 @@ impl OrderRequest {
             side,
             price,
             qty,
+            request_type: OrderRequestType::New,
+        }
+    }
+
+    /// Creates a request amending a resting order to a new price and quantity.
+    #[inline]
+    pub fn replace(order_id: OrderId, ticker_id: TickerId, side: Side, price: Price, qty: Qty) -> Self {
+        Self {
+            request_type: OrderRequestType::Replace(order_id),
+            ..Self::new(ticker_id, side, price, qty)
+        }
+    }
+
+    /// Returns the resting order this request amends, if it is a replace.
+    #[inline]
+    pub fn replaces(&self) -> Option<OrderId> {
+        match self.request_type {
+            OrderRequestType::New => None,
+            OrderRequestType::Replace(order_id) => Some(order_id),
         }
     }
 
@@ -60,6 +118,44 @@ impl OrderRequest {
     }
 }
 
+/// Quantity bought and sold on one ticker within a single processing cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CycleFills {
+    /// Quantity bought.
+    pub bought: Qty,
+    /// Quantity sold.
+    pub sold: Qty,
+}
+
+impl CycleFills {
+    /// Adds a fill on the given side.
+    #[inline]
+    pub fn record(&mut self, side: Side, qty: Qty) {
+        match side {
+            Side::Buy => self.bought += qty,
+            Side::Sell => self.sold += qty,
+        }
+    }
+
+    /// Returns the net position change (bought minus sold).
+    #[inline]
+    pub fn net(&self) -> i64 {
+        self.bought as i64 - self.sold as i64
+    }
+
+    /// Returns true if nothing filled.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bought == 0 && self.sold == 0
+    }
+
+    /// Returns true if both sides filled.
+    #[inline]
+    pub fn is_two_sided(&self) -> bool {
+        self.bought > 0 && self.sold > 0
+    }
+}
+
 /// Represents a pair of quotes (bid and ask) for market making.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct QuotePair {
@@ -126,13 +222,438 @@ pub enum StrategyAction {
     Take(OrderRequest),
     /// Cancel existing orders.
     CancelAll(TickerId),
+    /// Amend a resting order in place, keeping its ID.
+    Replace {
+        /// The resting order to amend.
+        order_id: OrderId,
+        /// Its new price.
+        new_price: Price,
+        /// Its new quantity.
+        new_qty: Qty,
+    },
 }
 
 impl StrategyAction {
     /// Returns true if this action requires sending orders.
     #[inline]
     pub fn requires_orders(&self) -> bool {
-        matches!(self, StrategyAction::Quote(_) | StrategyAction::Take(_))
+        matches!(
+            self,
+            StrategyAction::Quote(_) | StrategyAction::Take(_) | StrategyAction::Replace { .. }
+        )
+    }
+
+    /// Returns the ticker the action is for, or None for `None`, empty quotes
+    /// and `Replace`, which only names its order.
+    #[inline]
+    pub fn ticker_id(&self) -> Option<TickerId> {
+        match self {
+            StrategyAction::None => None,
+            StrategyAction::Quote(pair) => pair.bid.or(pair.ask).map(|order| order.ticker_id),
+            StrategyAction::Take(order) => Some(order.ticker_id),
+            StrategyAction::CancelAll(ticker_id) => Some(*ticker_id),
+            StrategyAction::Replace { .. } => None,
+        }
+    }
+}
+
+/// Why a strategy did or didn't act on a features update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionReason {
+    /// The strategy is deactivated.
+    Inactive,
+    /// The features were not valid yet.
+    InvalidFeatures,
+    /// The signal or price move did not reach the configured threshold.
+    BelowThreshold,
+    /// The minimum interval between orders had not elapsed.
+    RateLimited,
+    /// The position limit blocks trading in the signalled direction.
+    MaxPosition,
+    /// The resting quotes were too old and were pulled.
+    QuoteExpired,
+    /// The strategy is paused after consecutive losses.
+    LossCooldown,
+    /// The strategy already acted on this features snapshot.
+    StaleFeatures,
+    /// The signal's expected edge did not cover the cost of crossing.
+    InsufficientEdge,
+    /// New quotes were generated.
+    Quoted,
+    /// An aggressive order was generated.
+    Taken,
+}
+
+impl DecisionReason {
+    /// Returns a short snake_case name, suitable for logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionReason::Inactive => "inactive",
+            DecisionReason::InvalidFeatures => "invalid_features",
+            DecisionReason::BelowThreshold => "below_threshold",
+            DecisionReason::RateLimited => "rate_limited",
+            DecisionReason::MaxPosition => "max_position",
+            DecisionReason::QuoteExpired => "quote_expired",
+            DecisionReason::LossCooldown => "loss_cooldown",
+            DecisionReason::StaleFeatures => "stale_features",
+            DecisionReason::InsufficientEdge => "insufficient_edge",
+            DecisionReason::Quoted => "quoted",
+            DecisionReason::Taken => "taken",
+        }
+    }
+}
+
+/// Circuit breaker that pauses a strategy after consecutive losing round
+/// trips.
+///
+/// Each close (a fill that reduces a position) is reported with its realized
+/// P&L and whether it took the position flat (or through flat). The closes
+/// of a round trip are totalled and judged once, when it ends: a loss
+/// extends the streak and a win resets it; a scratch leaves it unchanged.
+/// When the streak reaches the limit the breaker trips: the streak starts
+/// over and the strategy cools down for the configured period.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LossStreakBreaker {
+    /// Consecutive losses that trip the breaker (0 disables it).
+    max_losses: u32,
+    /// Length of the cooldown in nanoseconds.
+    cooldown_ns: u64,
+    /// Current run of consecutive losing round trips.
+    streak: u32,
+    /// P&L realized so far by the round trip in progress.
+    round_trip_pnl: i64,
+    /// End of the current cooldown (0 if none has started).
+    cooldown_until_ns: u64,
+    /// Whether the current cooldown's CancelAll has yet to be returned.
+    cancel_pending: bool,
+}
+
+impl LossStreakBreaker {
+    /// Creates a breaker that trips after `max_losses` consecutive losses
+    /// and pauses for `cooldown_ns` (a limit of 0 disables it).
+    pub fn new(max_losses: u32, cooldown_ns: u64) -> Self {
+        Self {
+            max_losses,
+            cooldown_ns,
+            ..Default::default()
+        }
+    }
+
+    /// Changes the limits, keeping the streak and any running cooldown.
+    pub fn set_limits(&mut self, max_losses: u32, cooldown_ns: u64) {
+        self.max_losses = max_losses;
+        self.cooldown_ns = cooldown_ns;
+    }
+
+    /// Records the realized P&L of a close.
+    ///
+    /// # Arguments
+    /// * `realized_pnl` - P&L realized by the close, in cents
+    /// * `closed_out` - Whether the close took the position flat or through
+    ///   flat, ending the round trip
+    /// * `now_ns` - Current timestamp in nanoseconds
+    ///
+    /// # Returns
+    /// `true` if this close ended a round trip that tripped the breaker and
+    /// started a cooldown
+    pub fn record_close(&mut self, realized_pnl: i64, closed_out: bool, now_ns: u64) -> bool {
+        self.round_trip_pnl += realized_pnl;
+        if !closed_out {
+            return false;
+        }
+
+        let round_trip_pnl = std::mem::take(&mut self.round_trip_pnl);
+        if round_trip_pnl > 0 {
+            self.streak = 0;
+        } else if round_trip_pnl < 0 {
+            self.streak += 1;
+        }
+
+        if self.max_losses == 0 || self.streak < self.max_losses {
+            return false;
+        }
+
+        self.streak = 0;
+        self.cooldown_until_ns = now_ns.saturating_add(self.cooldown_ns);
+        self.cancel_pending = true;
+        true
+    }
+
+    /// Returns true if the strategy should stay idle at `now_ns`.
+    #[inline]
+    pub fn is_cooling_down(&self, now_ns: u64) -> bool {
+        now_ns < self.cooldown_until_ns
+    }
+
+    /// Returns true once per cooldown, when the strategy should cancel its orders.
+    #[inline]
+    pub fn take_cancel(&mut self) -> bool {
+        std::mem::take(&mut self.cancel_pending)
+    }
+
+    /// Returns the current run of consecutive losing round trips.
+    #[inline]
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    /// Returns the P&L realized so far by the round trip in progress.
+    #[inline]
+    pub fn round_trip_pnl(&self) -> i64 {
+        self.round_trip_pnl
+    }
+
+    /// Returns the end of the latest cooldown in nanoseconds (0 if none).
+    #[inline]
+    pub fn cooldown_until_ns(&self) -> u64 {
+        self.cooldown_until_ns
+    }
+}
+
+/// Circuit breaker that stops a strategy after consecutive order rejects.
+///
+/// Each order outcome is reported: a reject extends the streak and an
+/// accepted order resets it. When the streak reaches the limit the breaker
+/// trips and stays tripped until re-armed, unlike the loss breaker's timed
+/// cooldown.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RejectStreakBreaker {
+    /// Consecutive rejects that trip the breaker (0 disables it).
+    max_rejects: u32,
+    /// Current run of consecutive rejects.
+    streak: u32,
+    /// Whether the breaker has tripped and not been re-armed.
+    tripped: bool,
+}
+
+impl RejectStreakBreaker {
+    /// Creates a breaker that trips after `max_rejects` consecutive rejects
+    /// (0 disables it).
+    pub fn new(max_rejects: u32) -> Self {
+        Self {
+            max_rejects,
+            ..Default::default()
+        }
+    }
+
+    /// Changes the limit, keeping the streak.
+    pub fn set_limit(&mut self, max_rejects: u32) {
+        self.max_rejects = max_rejects;
+    }
+
+    /// Records the outcome of an order.
+    ///
+    /// # Arguments
+    /// * `result` - The risk check result the order was sent or rejected with
+    ///
+    /// # Returns
+    /// `true` if this reject tripped the breaker
+    pub fn record_result(&mut self, result: RiskCheckResult) -> bool {
+        if result.is_allowed() {
+            self.streak = 0;
+            return false;
+        }
+
+        self.streak += 1;
+        if self.tripped || self.max_rejects == 0 || self.streak < self.max_rejects {
+            return false;
+        }
+        self.tripped = true;
+        true
+    }
+
+    /// Clears the streak and the tripped state.
+    #[inline]
+    pub fn rearm(&mut self) {
+        self.streak = 0;
+        self.tripped = false;
+    }
+
+    /// Returns true if the breaker has tripped and not been re-armed.
+    #[inline]
+    pub fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+
+    /// Returns the current run of consecutive rejects.
+    #[inline]
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+}
+
+/// Tracks a market maker's quoting obligation: both sides quoted within a
+/// maximum spread for a minimum fraction of the session.
+///
+/// The quotes are observed at timestamps; the time between two observations
+/// is counted in or out of spec by the state seen at the first of them. A
+/// breach is each move from in spec to out of spec, including a first
+/// observation out of spec.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QuoteObligation {
+    /// Widest spread that counts as in spec (0 disables tracking).
+    max_spread: Price,
+    /// Fraction of the observed time the quotes must be in spec.
+    min_presence: f64,
+    /// Time observed with the quotes in spec, in nanoseconds.
+    in_spec_ns: u64,
+    /// Total time observed, in nanoseconds.
+    observed_ns: u64,
+    /// Moves out of spec.
+    breaches: u64,
+    /// Time and state of the last observation.
+    last: Option<(u64, bool)>,
+}
+
+impl QuoteObligation {
+    /// Creates a tracker for quotes within `max_spread` for `min_presence`
+    /// of the time (a spread of 0 disables it).
+    pub fn new(max_spread: Price, min_presence: f64) -> Self {
+        Self {
+            max_spread,
+            min_presence: min_presence.clamp(0.0, 1.0),
+            ..Default::default()
+        }
+    }
+
+    /// Changes the obligation, keeping the time accumulated so far.
+    pub fn set_limits(&mut self, max_spread: Price, min_presence: f64) {
+        self.max_spread = max_spread;
+        self.min_presence = min_presence.clamp(0.0, 1.0);
+    }
+
+    /// Returns true if the obligation is tracked.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.max_spread > 0
+    }
+
+    /// Returns true if a two-sided quote meets the spread obligation.
+    #[inline]
+    pub fn is_in_spec(&self, bid: Option<Price>, ask: Option<Price>) -> bool {
+        match (bid, ask) {
+            (Some(bid), Some(ask)) => ask - bid <= self.max_spread,
+            _ => false,
+        }
+    }
+
+    /// Records the state of the quotes at a point in time.
+    ///
+    /// # Arguments
+    /// * `now_ns` - Current timestamp in nanoseconds
+    /// * `in_spec` - Whether the quotes now meet the obligation
+    pub fn observe(&mut self, now_ns: u64, in_spec: bool) {
+        if !self.is_enabled() {
+            return;
+        }
+        match self.last {
+            Some((last_ns, was_in_spec)) => {
+                let elapsed = now_ns.saturating_sub(last_ns);
+                self.observed_ns += elapsed;
+                if was_in_spec {
+                    self.in_spec_ns += elapsed;
+                }
+                if was_in_spec && !in_spec {
+                    self.breaches += 1;
+                }
+            }
+            None if !in_spec => self.breaches += 1,
+            None => {}
+        }
+        self.last = Some((now_ns, in_spec));
+    }
+
+    /// Returns the fraction of the observed time the quotes were in spec
+    /// (0 before any time has been observed).
+    pub fn compliance(&self) -> f64 {
+        if self.observed_ns == 0 {
+            0.0
+        } else {
+            self.in_spec_ns as f64 / self.observed_ns as f64
+        }
+    }
+
+    /// Returns true if the compliance meets the minimum presence.
+    #[inline]
+    pub fn meets_obligation(&self) -> bool {
+        self.compliance() >= self.min_presence
+    }
+
+    /// Returns the number of moves out of spec.
+    #[inline]
+    pub fn breaches(&self) -> u64 {
+        self.breaches
+    }
+
+    /// Returns the time observed in spec, in nanoseconds.
+    #[inline]
+    pub fn in_spec_ns(&self) -> u64 {
+        self.in_spec_ns
+    }
+
+    /// Returns the total time observed, in nanoseconds.
+    #[inline]
+    pub fn observed_ns(&self) -> u64 {
+        self.observed_ns
+    }
+
+    /// Clears the accumulated time and breaches, keeping the obligation.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.max_spread, self.min_presence);
+    }
+}
+
+/// Record of a single strategy decision, for debugging and tuning.
+///
+/// Prices and quantities are those the strategy computed, even if it decided
+/// not to send them; unused sides are zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecisionTrace {
+    /// Why the strategy acted (or didn't).
+    pub reason: DecisionReason,
+    /// Fair value from the input features.
+    pub fair_value: Price,
+    /// Order book imbalance from the input features.
+    pub imbalance: f64,
+    /// Trade signal from the input features.
+    pub trade_signal: f64,
+    /// Computed bid price.
+    pub bid_price: Price,
+    /// Computed bid quantity.
+    pub bid_qty: Qty,
+    /// Computed ask price.
+    pub ask_price: Price,
+    /// Computed ask quantity.
+    pub ask_qty: Qty,
+}
+
+impl DecisionTrace {
+    /// Creates a trace of the given features with no computed prices.
+    pub fn new(reason: DecisionReason, features: &TickerFeatures) -> Self {
+        Self {
+            reason,
+            fair_value: features.fair_value,
+            imbalance: features.imbalance,
+            trade_signal: features.trade_signal,
+            bid_price: 0,
+            bid_qty: 0,
+            ask_price: 0,
+            ask_qty: 0,
+        }
+    }
+
+    /// Builder method to set the computed bid.
+    pub fn with_bid(mut self, price: Price, qty: Qty) -> Self {
+        self.bid_price = price;
+        self.bid_qty = qty;
+        self
+    }
+
+    /// Builder method to set the computed ask.
+    pub fn with_ask(mut self, price: Price, qty: Qty) -> Self {
+        self.ask_price = price;
+        self.ask_qty = qty;
+        self
     }
 }
 
@@ -200,5 +721,127 @@ mod tests {
 
         let take = OrderRequest::buy(1, 10000, 100);
         assert!(StrategyAction::Take(take).requires_orders());
+
+        let replace = StrategyAction::Replace { order_id: 7, new_price: 10010, new_qty: 50 };
+        assert!(replace.requires_orders());
+        assert_eq!(replace.ticker_id(), None);
+    }
+
+    #[test]
+    fn test_order_request_replace() {
+        let order = OrderRequest::buy(1, 10000, 100);
+        assert_eq!(order.request_type, OrderRequestType::New);
+        assert_eq!(order.replaces(), None);
+
+        let replace = OrderRequest::replace(7, 1, Side::Sell, 10010, 50);
+        assert_eq!(replace.replaces(), Some(7));
+        assert_eq!((replace.side, replace.price, replace.qty), (Side::Sell, 10010, 50));
+    }
+
+    #[test]
+    fn test_loss_streak_breaker() {
+        let mut breaker = LossStreakBreaker::new(3, 1_000);
+
+        // A win resets the streak; a scratch leaves it alone
+        assert!(!breaker.record_close(-50, true, 0));
+        assert!(!breaker.record_close(-20, true, 0));
+        assert!(!breaker.record_close(0, true, 0));
+        assert_eq!(breaker.streak(), 2);
+        assert!(!breaker.record_close(30, true, 0));
+        assert_eq!(breaker.streak(), 0);
+
+        // Three consecutive losses trip it
+        assert!(!breaker.record_close(-10, true, 100));
+        assert!(!breaker.record_close(-10, true, 200));
+        assert!(breaker.record_close(-10, true, 300));
+        assert_eq!(breaker.streak(), 0);
+        assert_eq!(breaker.cooldown_until_ns(), 1_300);
+        assert!(breaker.is_cooling_down(1_299));
+        assert!(!breaker.is_cooling_down(1_300));
+
+        assert!(breaker.take_cancel());
+        assert!(!breaker.take_cancel());
+
+        // Disabled breakers never trip
+        let mut disabled = LossStreakBreaker::default();
+        for _ in 0..10 {
+            assert!(!disabled.record_close(-10, true, 0));
+        }
+        assert_eq!(disabled.streak(), 10);
+    }
+
+    #[test]
+    fn test_loss_streak_breaker_totals_round_trips() {
+        let mut breaker = LossStreakBreaker::new(2, 1_000);
+
+        // Three partial closes of one position are one losing round trip
+        assert!(!breaker.record_close(-10, false, 0));
+        assert!(!breaker.record_close(-10, false, 0));
+        assert_eq!(breaker.streak(), 0);
+        assert_eq!(breaker.round_trip_pnl(), -20);
+        assert!(!breaker.record_close(-10, true, 0));
+        assert_eq!(breaker.streak(), 1);
+        assert_eq!(breaker.round_trip_pnl(), 0);
+
+        // A round trip that loses on one leg but wins overall resets it
+        assert!(!breaker.record_close(-30, false, 0));
+        assert!(!breaker.record_close(50, true, 0));
+        assert_eq!(breaker.streak(), 0);
+
+        // Two losing round trips trip it, on the close that ends the second
+        assert!(!breaker.record_close(-5, true, 100));
+        assert!(!breaker.record_close(-5, false, 200));
+        assert!(breaker.record_close(-5, true, 300));
+        assert_eq!(breaker.cooldown_until_ns(), 1_300);
+    }
+
+    #[test]
+    fn test_reject_streak_breaker() {
+        let mut breaker = RejectStreakBreaker::new(2);
+
+        assert!(!breaker.record_result(RiskCheckResult::OrderTooLarge));
+        assert!(!breaker.record_result(RiskCheckResult::Allowed));
+        assert!(!breaker.record_result(RiskCheckResult::PositionTooLarge));
+        assert!(breaker.record_result(RiskCheckResult::PositionTooLarge));
+        assert!(breaker.is_tripped());
+
+        // Trips once until re-armed
+        assert!(!breaker.record_result(RiskCheckResult::PositionTooLarge));
+        breaker.rearm();
+        assert!(!breaker.is_tripped());
+        assert_eq!(breaker.streak(), 0);
+
+        let mut disabled = RejectStreakBreaker::default();
+        for _ in 0..10 {
+            assert!(!disabled.record_result(RiskCheckResult::RateLimited));
+        }
+    }
+
+    #[test]
+    fn test_quote_obligation() {
+        let mut obligation = QuoteObligation::new(100, 0.5);
+        assert!(obligation.is_in_spec(Some(9950), Some(10050)));
+        assert!(!obligation.is_in_spec(Some(9950), Some(10051)));
+        assert!(!obligation.is_in_spec(Some(9950), None));
+
+        // Starting out of spec is a breach; each interval counts by the
+        // state at its start
+        obligation.observe(0, false);
+        obligation.observe(100, true);
+        obligation.observe(400, true);
+        obligation.observe(500, false);
+        assert_eq!(obligation.breaches(), 2);
+        assert_eq!((obligation.in_spec_ns(), obligation.observed_ns()), (400, 500));
+        assert_eq!(obligation.compliance(), 0.8);
+        assert!(obligation.meets_obligation());
+
+        obligation.reset();
+        assert_eq!(obligation.breaches(), 0);
+        assert_eq!(obligation.compliance(), 0.0);
+
+        let mut disabled = QuoteObligation::default();
+        disabled.observe(0, false);
+        disabled.observe(100, true);
+        assert_eq!((disabled.breaches(), disabled.observed_ns()), (0, 0));
     }
 }
@@ -14,18 +14,66 @@
 //! 1. Exchange responses (highest priority - need to track order state)
 //! 2. Market data updates (need fresh prices for decisions)
 //! 3. Strategy signals (based on updated market state)
+//!
+//! Responses can arrive out of causal order. A fill for an order the engine
+//! doesn't know yet is held back until that order's accept arrives, and a
+//! cancel for an order that is already gone is ignored.
+//!
+//! With halting on risk breach enabled, a ticker whose position or loss
+//! limit is breached after a fill or a mark change is soft-halted: its
+//! resting orders are cancelled and new orders for it are rejected until the
+//! halt is cleared, while other tickers keep trading.
+//!
+//! An operator kill (`kill_session`) cancels every resting order across
+//! tickers and rejects new orders until `resume_session`, optionally sending
+//! orders that close each open position at the touch.
+//!
+//! A daily order cap bounds the total number of orders submitted per
+//! session; the count resets when the configured session length elapses or
+//! on `reset_session`.
+//!
+//! Fills the exchange replays after a reconnect may have been applied
+//! already. Fills carrying an exec sequence are applied once per (order ID,
+//! exec sequence); repeats are counted and dropped.
+//!
+//! An amended order (`amend_order`, or a strategy's `Replace`) keeps its ID
+//! and takes its new terms right away; if the exchange rejects the
+//! amendment the order is put back on the terms it had.
+//!
+//! When several tickers' strategies act in the same cycle, their actions are
+//! processed in the order set by the configured `ActionOrdering`, so which
+//! ticker gets a scarce risk or order budget first is reproducible.
+//!
+//! Each order's round-trip time, from sending to its first Accepted or
+//! Filled response, is recorded in the stats' latency histogram; see
+//! `TradeEngineStats::latency_percentiles`.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
-use common::time::{now_nanos, Nanos};
+use common::time::{Clock, ClockSource, LatencyHistogram, Nanos, SystemClock};
 use common::{ClientId, OrderId, Price, Qty, Side, TickerId};
-use exchange::protocol::{ClientResponse, ClientResponseType, MarketUpdate};
+use exchange::protocol::{ClientResponse, ClientResponseType, LiquidityFlag, MarketUpdate};
 
 use crate::features::{FeatureEngine, TickerFeatures};
 use crate::market_data::BBO;
-use crate::position::{Position, PositionKeeper};
+use crate::position::{MarkSource, Position, PositionKeeper};
 use crate::risk::{RiskCheckResult, RiskManager};
-use crate::strategies::{OrderRequest, StrategyAction};
+use crate::strategies::{CycleFills, OrderRequest, OrderRequestType, StrategyAction};
+
+/// Order in which a cycle's per-ticker strategy actions are processed.
+///
+/// Ties, and actions for no ticker, fall back to ticker ID order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ActionOrdering {
+    /// Lowest ticker ID first.
+    #[default]
+    TickerId,
+    /// Strongest trade signal (by magnitude) first.
+    SignalStrength,
+    /// Highest priority first; unlisted tickers have priority 0.
+    Priority(HashMap<TickerId, i32>),
+}
 
 /// Configuration for the TradeEngine.
 #[derive(Debug, Clone)]
@@ -38,8 +86,44 @@ pub struct TradeEngineConfig {
     pub enable_risk_checks: bool,
     /// Maximum number of events to process per poll cycle.
     pub max_events_per_cycle: usize,
+    /// Maximum number of fills held back for orders not yet accepted
+    /// (0 drops them).
+    pub max_buffered_fills: usize,
+    /// Reference price used to mark positions.
+    pub mark_source: MarkSource,
+    /// Whether a position or loss limit breach halts the breaching ticker.
+    pub halt_on_risk_breach: bool,
+    /// Most orders submitted per session (0 for no cap).
+    pub max_daily_orders: u64,
+    /// Length of a session, after which the order count resets, in nanoseconds.
+    pub session_length_ns: u64,
+    /// Recent sequenced fills remembered to drop replayed duplicates
+    /// (0 disables).
+    pub fill_dedup_window: usize,
+    /// Recently canceled orders remembered to recognize a repeated Canceled
+    /// (0 disables).
+    pub cancel_dedup_window: usize,
+    /// Cycles between open order audits (0 disables).
+    pub open_order_audit_interval: u64,
+    /// Whether an order too large for the remaining risk budget is cut down
+    /// to the largest size that passes risk instead of rejected.
+    pub clamp_to_risk_budget: bool,
+    /// Order in which a cycle's strategy actions are processed.
+    pub action_ordering: ActionOrdering,
+    /// Clock fill events are stamped with on receipt. Latency and session
+    /// timing always use the monotonic clock.
+    pub record_clock: ClockSource,
 }
 
+/// Default session length: one day.
+pub const DEFAULT_SESSION_LENGTH_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Default number of sequenced fills remembered for duplicate detection.
+pub const DEFAULT_FILL_DEDUP_WINDOW: usize = 4096;
+
+/// Default number of canceled orders remembered for duplicate detection.
+pub const DEFAULT_CANCEL_DEDUP_WINDOW: usize = 1024;
+
 impl Default for TradeEngineConfig {
     fn default() -> Self {
         Self {
@@ -47,6 +131,17 @@ impl Default for TradeEngineConfig {
             tickers: Vec::new(),
             enable_risk_checks: true,
             max_events_per_cycle: 100,
+            max_buffered_fills: 64,
+            mark_source: MarkSource::default(),
+            halt_on_risk_breach: false,
+            max_daily_orders: 0,
+            session_length_ns: DEFAULT_SESSION_LENGTH_NS,
+            fill_dedup_window: DEFAULT_FILL_DEDUP_WINDOW,
+            cancel_dedup_window: DEFAULT_CANCEL_DEDUP_WINDOW,
+            open_order_audit_interval: 0,
+            clamp_to_risk_budget: false,
+            action_ordering: ActionOrdering::default(),
+            record_clock: ClockSource::Monotonic,
         }
     }
 }
@@ -77,6 +172,75 @@ impl TradeEngineConfig {
         self.max_events_per_cycle = max;
         self
     }
+
+    /// Builder method to set how many early fills are held back.
+    pub fn with_max_buffered_fills(mut self, max: usize) -> Self {
+        self.max_buffered_fills = max;
+        self
+    }
+
+    /// Builder method to set the reference price for unrealized P&L.
+    pub fn with_mark_source(mut self, mark_source: MarkSource) -> Self {
+        self.mark_source = mark_source;
+        self
+    }
+
+    /// Builder method to cap the orders submitted per session.
+    pub fn with_daily_order_limit(mut self, max_orders: u64, session_length_ns: u64) -> Self {
+        self.max_daily_orders = max_orders;
+        self.session_length_ns = session_length_ns;
+        self
+    }
+
+    /// Builder method to halt only the breaching ticker on a risk breach.
+    pub fn with_halt_on_risk_breach(mut self, enabled: bool) -> Self {
+        self.halt_on_risk_breach = enabled;
+        self
+    }
+
+    /// Builder method to set how many sequenced fills are remembered to drop
+    /// replayed duplicates.
+    pub fn with_fill_dedup_window(mut self, fills: usize) -> Self {
+        self.fill_dedup_window = fills;
+        self
+    }
+
+    /// Builder method to set how many canceled orders are remembered to
+    /// recognize a repeated Canceled response.
+    pub fn with_cancel_dedup_window(mut self, orders: usize) -> Self {
+        self.cancel_dedup_window = orders;
+        self
+    }
+
+    /// Builder method to audit open order accounting every `cycles` cycles
+    /// (0 disables).
+    pub fn with_open_order_audit(mut self, cycles: u64) -> Self {
+        self.open_order_audit_interval = cycles;
+        self
+    }
+
+    /// Builder method to clamp orders to the remaining risk budget.
+    pub fn with_risk_clamp(mut self, enabled: bool) -> Self {
+        self.clamp_to_risk_budget = enabled;
+        self
+    }
+
+    /// Builder method to set the order in which strategy actions are processed.
+    pub fn with_action_ordering(mut self, ordering: ActionOrdering) -> Self {
+        self.action_ordering = ordering;
+        self
+    }
+
+    /// Builder method to set the clock fill events are stamped with.
+    ///
+    /// The monotonic default lines up with an in-process matching engine's
+    /// exec times; use the realtime clock against the exchange binary, which
+    /// stamps fills with wall-clock time unless run with
+    /// `--exec-clock monotonic`.
+    pub fn with_record_clock(mut self, clock: ClockSource) -> Self {
+        self.record_clock = clock;
+        self
+    }
 }
 
 /// Statistics for tracking engine performance.
@@ -96,6 +260,42 @@ pub struct TradeEngineStats {
     pub strategy_cycles: u64,
     /// Total processing cycles.
     pub total_cycles: u64,
+    /// Responses for orders the engine wasn't tracking (early fills,
+    /// cancels after a fill).
+    pub out_of_order_responses: u64,
+    /// Number of tickers halted by a risk breach.
+    pub risk_halts: u64,
+    /// Replayed fills dropped because they were already applied.
+    pub duplicate_fills: u64,
+    /// Tickers whose open order accounting an audit had to correct.
+    pub open_order_corrections: u64,
+    /// Orders sent smaller than requested to fit the risk budget.
+    pub orders_clamped: u64,
+    /// Iceberg slices refilled by the exchange.
+    pub replenishments: u64,
+    /// Canceled responses for orders already canceled, ignored.
+    pub duplicate_cancels: u64,
+    /// Operator kills of the whole session.
+    pub session_kills: u64,
+    /// Orders canceled by an operator, by a session or single order kill.
+    pub killed_orders: u64,
+    /// Orders amended in place.
+    pub orders_amended: u64,
+    /// Amendments the exchange rejected, leaving the order on its old terms.
+    pub amend_rejects: u64,
+    /// Order round-trip times in nanoseconds, send to first Accepted or Filled.
+    pub order_latency: LatencyHistogram,
+}
+
+/// Order round-trip latency percentiles, in nanoseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// Median latency.
+    pub p50: u64,
+    /// 99th percentile latency.
+    pub p99: u64,
+    /// Largest latency recorded.
+    pub max: u64,
 }
 
 impl TradeEngineStats {
@@ -104,6 +304,16 @@ impl TradeEngineStats {
         Self::default()
     }
 
+    /// Returns the order round-trip latency percentiles (all 0 if no order
+    /// has been acknowledged yet).
+    pub fn latency_percentiles(&self) -> LatencyPercentiles {
+        LatencyPercentiles {
+            p50: self.order_latency.quantile(0.50),
+            p99: self.order_latency.quantile(0.99),
+            max: self.order_latency.max(),
+        }
+    }
+
     /// Resets all statistics.
     pub fn reset(&mut self) {
         *self = Self::default();
@@ -127,16 +337,77 @@ pub struct TrackedOrder {
     pub leaves_qty: Qty,
     /// When the order was sent.
     pub sent_time: Nanos,
+    /// Opaque client tag attached at submission.
+    pub tag: u64,
+    /// Whether an Accepted or Filled response has arrived for the order.
+    pub acknowledged: bool,
+}
+
+/// An order replaced by `replace_quote`, kept until its cancel lands so a
+/// late fill on it is still applied.
+#[derive(Debug, Clone)]
+struct ReplacedOrder {
+    /// The order as it was when replaced.
+    order: TrackedOrder,
+    /// The order that replaced it.
+    replacement: OrderId,
+}
+
+/// A fill reported to the fill callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillEvent {
+    /// The filled order's ID.
+    pub order_id: OrderId,
+    /// The ticker that traded.
+    pub ticker_id: TickerId,
+    /// The side of the filled order.
+    pub side: Side,
+    /// The execution price.
+    pub price: Price,
+    /// The executed quantity.
+    pub exec_qty: Qty,
+    /// The quantity still resting after this fill.
+    pub leaves_qty: Qty,
+    /// The client tag the order was submitted with.
+    pub tag: u64,
+    /// When the exchange matched the trade, in nanoseconds (0 if the fill
+    /// wasn't stamped).
+    pub exec_time_ns: u64,
+    /// When the engine applied the fill, in nanoseconds on the configured
+    /// record clock.
+    pub recv_time_ns: u64,
+}
+
+/// A ticker whose open order accounting disagreed with its tracked orders,
+/// as (recorded, recomputed) pairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenOrderDiscrepancy {
+    /// The ticker that was corrected.
+    pub ticker_id: TickerId,
+    /// The position's pending buy quantity.
+    pub open_buy_qty: (Qty, Qty),
+    /// The position's pending sell quantity.
+    pub open_sell_qty: (Qty, Qty),
+    /// The engine's open order count.
+    pub open_orders: (u32, u32),
 }
 
 /// Callback type for order submission.
-/// Takes (ticker_id, side, price, qty) and returns the assigned order_id.
-pub type OrderSubmitCallback = Box<dyn FnMut(TickerId, Side, Price, Qty) -> OrderId + Send>;
+/// Takes (ticker_id, side, price, qty, tag) and returns the assigned order_id.
+pub type OrderSubmitCallback =
+    Box<dyn FnMut(TickerId, Side, Price, Qty, u64) -> OrderId + Send>;
 
 /// Callback type for order cancellation.
 /// Takes (order_id, ticker_id).
 pub type OrderCancelCallback = Box<dyn FnMut(OrderId, TickerId) + Send>;
 
+/// Callback type for fill notifications.
+pub type OrderFillCallback = Box<dyn FnMut(&FillEvent) + Send>;
+
+/// Callback type for in-place order amendments.
+/// Takes (order_id, new_price, new_qty).
+pub type OrderReplaceCallback = Box<dyn FnMut(OrderId, Price, Qty) + Send>;
+
 /// Central trading orchestrator.
 ///
 /// The TradeEngine coordinates all trading components:
@@ -157,16 +428,47 @@ pub struct TradeEngine {
     bbo_state: HashMap<TickerId, BBO>,
     /// Pending orders by order ID.
     pending_orders: HashMap<OrderId, TrackedOrder>,
+    /// Replaced orders whose cancel has not landed yet, by order ID.
+    replaced_orders: HashMap<OrderId, ReplacedOrder>,
+    /// Terms (price, original and leaves quantity) of amended orders from
+    /// before the amendment, until the exchange accepts it.
+    amended_orders: HashMap<OrderId, (Price, Qty, Qty)>,
     /// Open order count per ticker.
     open_order_count: HashMap<TickerId, u32>,
+    /// Fills that arrived before their order was tracked, oldest first.
+    buffered_fills: VecDeque<ClientResponse>,
+    /// (order ID, exec sequence) of recent sequenced fills.
+    seen_fills: HashSet<(OrderId, u64)>,
+    /// The same fills in arrival order, to forget the oldest.
+    seen_fill_order: VecDeque<(OrderId, u64)>,
+    /// Recently canceled orders.
+    canceled_orders: HashSet<OrderId>,
+    /// The same orders in cancel order, to forget the oldest.
+    canceled_order_queue: VecDeque<OrderId>,
+    /// Tickers soft-halted by a risk breach.
+    halted_tickers: HashSet<TickerId>,
+    /// Set by an operator kill: every new order is rejected.
+    killed: bool,
+    /// Quantity bought and sold per ticker since the current cycle began.
+    cycle_fills: HashMap<TickerId, CycleFills>,
+    /// Orders submitted in the current session.
+    session_orders: u64,
+    /// When the current session started.
+    session_start: Nanos,
     /// Callback for submitting orders.
     order_submit_callback: Option<OrderSubmitCallback>,
     /// Callback for cancelling orders.
     order_cancel_callback: Option<OrderCancelCallback>,
+    /// Callback for fill notifications.
+    order_fill_callback: Option<OrderFillCallback>,
+    /// Callback for amending orders.
+    order_replace_callback: Option<OrderReplaceCallback>,
     /// Engine statistics.
     stats: TradeEngineStats,
     /// Whether the engine is running.
     running: bool,
+    /// Clock for order send times, rate limits and sessions.
+    clock: Arc<dyn Clock>,
 }
 
 impl TradeEngine {
@@ -179,13 +481,30 @@ impl TradeEngine {
             position_keeper: PositionKeeper::new(),
             bbo_state: HashMap::new(),
             pending_orders: HashMap::new(),
+            replaced_orders: HashMap::new(),
+            amended_orders: HashMap::new(),
             open_order_count: HashMap::new(),
+            buffered_fills: VecDeque::with_capacity(config.max_buffered_fills),
+            seen_fills: HashSet::new(),
+            seen_fill_order: VecDeque::new(),
+            canceled_orders: HashSet::new(),
+            canceled_order_queue: VecDeque::new(),
+            halted_tickers: HashSet::new(),
+            killed: false,
+            cycle_fills: HashMap::new(),
+            session_orders: 0,
+            session_start: SystemClock.now_nanos(),
             order_submit_callback: None,
             order_cancel_callback: None,
+            order_fill_callback: None,
+            order_replace_callback: None,
             stats: TradeEngineStats::new(),
             running: false,
+            clock: Arc::new(SystemClock),
         };
 
+        engine.position_keeper.set_mark_source(config.mark_source);
+
         // Pre-allocate state for configured tickers
         engine.feature_engine.reserve_tickers(&config.tickers);
         for &ticker_id in &config.tickers {
@@ -215,6 +534,31 @@ impl TradeEngine {
         self.order_cancel_callback = Some(callback);
     }
 
+    /// Sets the fill notification callback.
+    pub fn set_order_fill_callback(&mut self, callback: OrderFillCallback) {
+        self.order_fill_callback = Some(callback);
+    }
+
+    /// Sets the order amendment callback.
+    pub fn set_order_replace_callback(&mut self, callback: OrderReplaceCallback) {
+        self.order_replace_callback = Some(callback);
+    }
+
+    /// Sets the clock used for order send times, rate limits and sessions.
+    ///
+    /// Starts a new session on the new clock. Pass the same clock to the
+    /// strategies so the whole loop runs on one timeline, e.g. a
+    /// `ManualClock` in a backtest.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+        self.reset_session();
+    }
+
+    /// Returns the engine's clock, to share with strategies.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        Arc::clone(&self.clock)
+    }
+
     /// Returns a reference to the risk manager.
     pub fn risk_manager(&self) -> &RiskManager {
         &self.risk_manager
@@ -285,7 +629,7 @@ impl TradeEngine {
         let qty = update.qty;
 
         // Get or create BBO entry
-        let bbo = self.bbo_state.entry(ticker_id).or_insert_with(BBO::new);
+        let bbo = self.bbo_state.entry(ticker_id).or_default();
 
         // Update BBO based on update type
         if let Some(update_type) = update.update_type() {
@@ -330,8 +674,8 @@ impl TradeEngine {
                         bbo.bid_qty = bbo.bid_qty.saturating_sub(qty);
                     }
 
-                    // Update position keeper with market price
-                    self.position_keeper.update_market_price(ticker_id, price);
+                    // Mark to the print if marking from trades
+                    self.position_keeper.on_trade_print(ticker_id, price);
                 }
                 MarketUpdateType::Clear => {
                     *bbo = BBO::new();
@@ -339,8 +683,11 @@ impl TradeEngine {
             }
         }
 
-        // Update feature engine with new BBO
+        // Update feature engine and marks with new BBO
         self.feature_engine.on_bbo_update(ticker_id, bbo);
+        self.position_keeper.on_bbo_update(ticker_id, bbo);
+        self.feed_book_pressure(ticker_id);
+        self.enforce_ticker_risk(ticker_id);
 
         self.stats.market_updates_processed += 1;
 
@@ -352,9 +699,20 @@ impl TradeEngine {
         self.bbo_state.insert(ticker_id, bbo);
         self.feature_engine.on_bbo_update(ticker_id, &bbo);
 
-        // Update position keeper with mid price if valid
-        if let Some(mid) = bbo.mid_price() {
-            self.position_keeper.update_market_price(ticker_id, mid);
+        // Mark positions from the new quotes
+        self.position_keeper.on_bbo_update(ticker_id, &bbo);
+        self.feed_book_pressure(ticker_id);
+        self.enforce_ticker_risk(ticker_id);
+    }
+
+    /// Passes a ticker's BBO imbalance to the risk manager as its book
+    /// pressure, when position limits are scaled by pressure.
+    fn feed_book_pressure(&mut self, ticker_id: TickerId) {
+        if self.risk_manager.pressure_adjustment().is_none() {
+            return;
+        }
+        if let Some(features) = self.feature_engine.get_features(ticker_id) {
+            self.risk_manager.set_book_pressure(ticker_id, features.imbalance);
         }
     }
 
@@ -369,49 +727,69 @@ impl TradeEngine {
 
     /// Processes an exchange response.
     ///
-    /// Updates order state, positions, and handles fills/cancels.
+    /// Updates order state, positions, and handles fills/cancels. A sequenced
+    /// fill already seen, e.g. replayed after a reconnect, is dropped.
     pub fn on_response(&mut self, response: &ClientResponse) {
         let client_order_id = response.client_order_id;
         let ticker_id = response.ticker_id;
-        let exec_qty = response.exec_qty;
-        let price = response.price;
-        let leaves_qty = response.leaves_qty;
 
         self.stats.responses_processed += 1;
 
+        if response.response_type() == Some(ClientResponseType::Filled) && self.is_duplicate_fill(response) {
+            self.stats.duplicate_fills += 1;
+            return;
+        }
+
+        if matches!(
+            response.response_type(),
+            Some(ClientResponseType::Accepted | ClientResponseType::Filled)
+        ) {
+            self.record_ack_latency(client_order_id);
+        }
+
         if let Some(response_type) = response.response_type() {
             match response_type {
-                ClientResponseType::Accepted => {
-                    // Order accepted - already tracked from submission
+                ClientResponseType::Accepted if self.amended_orders.remove(&client_order_id).is_some() => {
+                    // An amendment took effect on the terms already tracked
                 }
-                ClientResponseType::Filled => {
-                    // Process the fill
-                    if let Some(order) = self.pending_orders.get(&client_order_id) {
-                        let side = order.side;
-
-                        // Update position
-                        self.position_keeper.on_fill(ticker_id, side, exec_qty, price);
-
-                        // Remove pending order quantity from position tracker
-                        let position = self.position_keeper.get_position_mut(ticker_id);
-                        position.remove_open_order(side, exec_qty);
-
-                        self.stats.fills_received += 1;
+                ClientResponseType::Accepted => {
+                    // Order accepted - normally already tracked from submission.
+                    // An unknown order with fills waiting is tracked from the
+                    // accept and its fills are applied.
+                    if !self.pending_orders.contains_key(&client_order_id)
+                        && self
+                            .buffered_fills
+                            .iter()
+                            .any(|fill| fill.client_order_id == client_order_id)
+                    {
+                        self.track_accepted_order(response);
+                        self.apply_buffered_fills(client_order_id);
                     }
-
-                    // Update or remove the tracked order
-                    if leaves_qty == 0 {
-                        // Fully filled - remove order
-                        self.pending_orders.remove(&client_order_id);
-                        let count = self.open_order_count.entry(ticker_id).or_insert(0);
-                        *count = count.saturating_sub(1);
-                    } else if let Some(order) = self.pending_orders.get_mut(&client_order_id) {
-                        // Partially filled - update leaves qty
-                        order.leaves_qty = leaves_qty;
+                }
+                ClientResponseType::Filled if self.replaced_orders.contains_key(&client_order_id) => {
+                    // Late fill on a replaced order; the replacement is unaffected
+                    self.apply_replaced_fill(response);
+                    self.enforce_ticker_risk(ticker_id);
+                }
+                ClientResponseType::Filled
+                    if !self.pending_orders.contains_key(&client_order_id) =>
+                {
+                    // Fill ahead of its accept - hold it until the order is tracked
+                    self.stats.out_of_order_responses += 1;
+                    if self.config.max_buffered_fills > 0 {
+                        if self.buffered_fills.len() >= self.config.max_buffered_fills {
+                            self.buffered_fills.pop_front();
+                        }
+                        self.buffered_fills.push_back(*response);
                     }
                 }
+                ClientResponseType::Filled => {
+                    self.apply_fill(response);
+                    self.enforce_ticker_risk(ticker_id);
+                }
                 ClientResponseType::Canceled => {
                     // Order canceled - remove from tracking
+                    self.amended_orders.remove(&client_order_id);
                     if let Some(order) = self.pending_orders.remove(&client_order_id) {
                         // Remove pending order quantity from position tracker
                         let position = self.position_keeper.get_position_mut(ticker_id);
@@ -419,9 +797,36 @@ impl TradeEngine {
 
                         let count = self.open_order_count.entry(ticker_id).or_insert(0);
                         *count = count.saturating_sub(1);
+                        self.remember_cancel(client_order_id);
+                    } else if self.replaced_orders.remove(&client_order_id).is_some() {
+                        self.remember_cancel(client_order_id);
+                    } else if self.canceled_orders.contains(&client_order_id) {
+                        // The same cancel again, e.g. echoed: already applied
+                        self.stats.duplicate_cancels += 1;
+                    } else {
+                        // Already gone, e.g. fully filled before the cancel landed
+                        self.stats.out_of_order_responses += 1;
                     }
                 }
-                ClientResponseType::CancelRejected | ClientResponseType::InvalidRequest => {
+                ClientResponseType::ModifyRejected => {
+                    // The order still rests on its previous terms
+                    self.restore_amended_order(client_order_id);
+                }
+                ClientResponseType::Triggered => {
+                    // Stop activated - its accept or fill follows
+                }
+                ClientResponseType::Replenished => {
+                    self.apply_replenishment(response);
+                }
+                ClientResponseType::CancelRejected
+                | ClientResponseType::InvalidRequest
+                | ClientResponseType::LastLookRejected
+                | ClientResponseType::LevelLimitRejected
+                | ClientResponseType::RiskRejected => {
+                    // A replaced order whose cancel was rejected is already gone
+                    self.replaced_orders.remove(&client_order_id);
+                    self.amended_orders.remove(&client_order_id);
+
                     // Remove from tracking on rejection
                     if let Some(order) = self.pending_orders.remove(&client_order_id) {
                         let position = self.position_keeper.get_position_mut(ticker_id);
@@ -435,6 +840,200 @@ impl TradeEngine {
         }
     }
 
+    /// Applies a fill for a tracked order to the position and order state.
+    fn apply_fill(&mut self, response: &ClientResponse) {
+        let client_order_id = response.client_order_id;
+        let ticker_id = response.ticker_id;
+        let exec_qty = response.exec_qty;
+        let leaves_qty = response.leaves_qty;
+
+        if let Some(order) = self.pending_orders.get(&client_order_id) {
+            let side = order.side;
+            let tag = order.tag;
+            self.record_fill(response, side, tag);
+
+            // Remove pending order quantity from position tracker
+            let position = self.position_keeper.get_position_mut(ticker_id);
+            position.remove_open_order(side, exec_qty);
+        }
+
+        // Update or remove the tracked order
+        if leaves_qty == 0 {
+            // Fully filled - remove order
+            self.pending_orders.remove(&client_order_id);
+            self.amended_orders.remove(&client_order_id);
+            let count = self.open_order_count.entry(ticker_id).or_insert(0);
+            *count = count.saturating_sub(1);
+        } else if let Some(order) = self.pending_orders.get_mut(&client_order_id) {
+            // Partially filled - update leaves qty
+            order.leaves_qty = leaves_qty;
+        }
+    }
+
+    /// Follows an iceberg order's refilled slice.
+    ///
+    /// The tracked order's leaves quantity, and the open quantity counted
+    /// against the position, become the new displayed slice. The order keeps
+    /// its ID and count; an untracked order is not picked up as a new one.
+    fn apply_replenishment(&mut self, response: &ClientResponse) {
+        let client_order_id = response.client_order_id;
+        let leaves_qty = response.leaves_qty;
+        let Some(order) = self.pending_orders.get_mut(&client_order_id) else {
+            self.stats.out_of_order_responses += 1;
+            return;
+        };
+
+        let position = self.position_keeper.get_position_mut(order.ticker_id);
+        position.remove_open_order(order.side, order.leaves_qty);
+        position.add_open_order(order.side, leaves_qty);
+        order.leaves_qty = leaves_qty;
+        self.stats.replenishments += 1;
+    }
+
+    /// Applies a late fill on a replaced order.
+    ///
+    /// The replaced order's open quantity was released when it was replaced,
+    /// so only the position changes; the replacement's tracking is left alone.
+    fn apply_replaced_fill(&mut self, response: &ClientResponse) {
+        let client_order_id = response.client_order_id;
+        let leaves_qty = response.leaves_qty;
+        let Some(replaced) = self.replaced_orders.get_mut(&client_order_id) else {
+            return;
+        };
+        replaced.order.leaves_qty = leaves_qty;
+        let (side, tag) = (replaced.order.side, replaced.order.tag);
+        if leaves_qty == 0 {
+            self.replaced_orders.remove(&client_order_id);
+        }
+        self.record_fill(response, side, tag);
+    }
+
+    /// Applies a fill to the position, the cycle's fills and the fill callback.
+    fn record_fill(&mut self, response: &ClientResponse, side: Side, tag: u64) {
+        let client_order_id = response.client_order_id;
+        let ticker_id = response.ticker_id;
+        let exec_qty = response.exec_qty;
+        let price = response.price;
+        let leaves_qty = response.leaves_qty;
+        let exec_time_ns = response.exec_time_ns;
+
+        // Update position, charging fees by liquidity
+        let liquidity = response.liquidity_flag().unwrap_or(LiquidityFlag::None);
+        self.position_keeper
+            .on_fill_with_liquidity(ticker_id, side, exec_qty, price, liquidity);
+
+        self.stats.fills_received += 1;
+        self.cycle_fills.entry(ticker_id).or_default().record(side, exec_qty);
+
+        if let Some(callback) = &mut self.order_fill_callback {
+            callback(&FillEvent {
+                order_id: client_order_id,
+                ticker_id,
+                side,
+                price,
+                exec_qty,
+                leaves_qty,
+                tag,
+                exec_time_ns,
+                recv_time_ns: self.config.record_clock.now().as_u64(),
+            });
+        }
+    }
+
+    /// Returns true if a sequenced fill was seen before, remembering it if not.
+    fn is_duplicate_fill(&mut self, response: &ClientResponse) -> bool {
+        let exec_seq = response.exec_seq;
+        if exec_seq == 0 || self.config.fill_dedup_window == 0 {
+            return false;
+        }
+
+        let key = (response.client_order_id, exec_seq);
+        if !self.seen_fills.insert(key) {
+            return true;
+        }
+        self.seen_fill_order.push_back(key);
+        if self.seen_fill_order.len() > self.config.fill_dedup_window {
+            if let Some(oldest) = self.seen_fill_order.pop_front() {
+                self.seen_fills.remove(&oldest);
+            }
+        }
+        false
+    }
+
+    /// Remembers a canceled order so a repeated Canceled is recognized.
+    fn remember_cancel(&mut self, order_id: OrderId) {
+        if self.config.cancel_dedup_window == 0 || !self.canceled_orders.insert(order_id) {
+            return;
+        }
+        self.canceled_order_queue.push_back(order_id);
+        if self.canceled_order_queue.len() > self.config.cancel_dedup_window {
+            if let Some(oldest) = self.canceled_order_queue.pop_front() {
+                self.canceled_orders.remove(&oldest);
+            }
+        }
+    }
+
+    /// Records an order's round-trip time on its first acknowledgement.
+    fn record_ack_latency(&mut self, order_id: OrderId) {
+        if let Some(order) = self.pending_orders.get_mut(&order_id) {
+            if !order.acknowledged {
+                order.acknowledged = true;
+                let latency = self.clock.now_nanos() - order.sent_time;
+                self.stats.order_latency.record(latency);
+            }
+        }
+    }
+
+    /// Starts tracking an order from its Accepted response.
+    fn track_accepted_order(&mut self, response: &ClientResponse) {
+        let client_order_id = response.client_order_id;
+        let ticker_id = response.ticker_id;
+        let side = if response.side == Side::Buy as i8 {
+            Side::Buy
+        } else {
+            Side::Sell
+        };
+        let leaves_qty = response.leaves_qty;
+
+        self.pending_orders.insert(
+            client_order_id,
+            TrackedOrder {
+                order_id: client_order_id,
+                ticker_id,
+                side,
+                price: response.price,
+                original_qty: leaves_qty,
+                leaves_qty,
+                sent_time: self.clock.now_nanos(),
+                tag: response.tag,
+                acknowledged: true,
+            },
+        );
+        self.position_keeper
+            .get_position_mut(ticker_id)
+            .add_open_order(side, leaves_qty);
+        *self.open_order_count.entry(ticker_id).or_insert(0) += 1;
+    }
+
+    /// Applies held-back fills for an order that is now tracked, in arrival order.
+    fn apply_buffered_fills(&mut self, order_id: OrderId) {
+        let (fills, rest): (VecDeque<_>, VecDeque<_>) = self
+            .buffered_fills
+            .drain(..)
+            .partition(|fill| fill.client_order_id == order_id);
+        self.buffered_fills = rest;
+
+        for fill in &fills {
+            self.apply_fill(fill);
+        }
+    }
+
+    /// Returns the number of fills held back for untracked orders.
+    #[inline]
+    pub fn buffered_fill_count(&self) -> usize {
+        self.buffered_fills.len()
+    }
+
     // ========================================================================
     // Order Management
     // ========================================================================
@@ -449,6 +1048,12 @@ impl TradeEngine {
         price: Price,
         qty: Qty,
     ) -> RiskCheckResult {
+        if self.killed {
+            return RiskCheckResult::SessionKilled;
+        }
+        if self.halted_tickers.contains(&ticker_id) {
+            return RiskCheckResult::TickerHalted;
+        }
         if !self.config.enable_risk_checks {
             return RiskCheckResult::Allowed;
         }
@@ -461,8 +1066,20 @@ impl TradeEngine {
 
         let open_orders = *self.open_order_count.get(&ticker_id).unwrap_or(&0);
 
+        let result = self.risk_manager.check_order_with_open_orders(
+            &position,
+            side,
+            qty,
+            price,
+            open_orders,
+        );
+        if !result.is_allowed() {
+            return result;
+        }
+
+        let positions = &self.position_keeper;
         self.risk_manager
-            .check_order_with_open_orders(&position, side, qty, price, open_orders)
+            .check_group_exposure(ticker_id, side, qty, |ticker| positions.get_position(ticker))
     }
 
     /// Submits an order after risk validation.
@@ -475,16 +1092,153 @@ impl TradeEngine {
         price: Price,
         qty: Qty,
     ) -> Result<OrderId, RiskCheckResult> {
+        self.submit_order_with_tag(ticker_id, side, price, qty, 0)
+    }
+
+    /// Submits an order carrying an opaque client tag.
+    ///
+    /// The tag is forwarded to the submit callback, stored on the tracked
+    /// order and reported back with every fill.
+    pub fn submit_order_with_tag(
+        &mut self,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+        tag: u64,
+    ) -> Result<OrderId, RiskCheckResult> {
+        let clamp = self.config.clamp_to_risk_budget;
+        self.send_order(ticker_id, side, price, qty, tag, clamp)
+            .map(|(order_id, _)| order_id)
+    }
+
+    /// Submits an order, cutting it down to the remaining risk budget.
+    ///
+    /// Clamps regardless of `clamp_to_risk_budget`.
+    ///
+    /// # Returns
+    /// The order ID and the quantity actually sent, or the risk rejection
+    /// reason if no smaller size passes either.
+    pub fn submit_order_clamped(
+        &mut self,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+    ) -> Result<(OrderId, Qty), RiskCheckResult> {
+        self.send_order(ticker_id, side, price, qty, 0, true)
+    }
+
+    /// Returns the largest quantity up to `qty` that passes risk.
+    ///
+    /// Only size limits (order size, position, group exposure, maximum
+    /// notional) are clamped to, rounding down to whole lots; any other
+    /// rejection, or a clamped size below the minimum notional, leaves
+    /// nothing to send.
+    ///
+    /// # Returns
+    /// The allowed quantity, or the rejection reason for `qty`.
+    pub fn max_allowed_qty(
+        &self,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+    ) -> Result<Qty, RiskCheckResult> {
+        let result = self.check_order_risk(ticker_id, side, price, qty);
+        let too_large = |result: RiskCheckResult| {
+            matches!(
+                result,
+                RiskCheckResult::OrderTooLarge
+                    | RiskCheckResult::PositionTooLarge
+                    | RiskCheckResult::GroupExposureExceeded
+                    | RiskCheckResult::NotionalTooLarge
+            )
+        };
+        if result.is_allowed() {
+            return Ok(qty);
+        }
+        if !too_large(result) {
+            return Err(result);
+        }
+
+        // Size limits only ever reject larger sizes, so search for the
+        // largest size that isn't too large
+        let (mut lo, mut hi) = (0, qty);
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if too_large(self.check_order_risk(ticker_id, side, price, mid)) {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        let lo = self.risk_manager.instrument(ticker_id).round_to_lot(lo);
+        if lo > 0 && self.check_order_risk(ticker_id, side, price, lo).is_allowed() {
+            Ok(lo)
+        } else {
+            Err(result)
+        }
+    }
+
+    /// Risk-checks, sends and tracks an order, optionally clamped to the
+    /// risk budget.
+    fn send_order(
+        &mut self,
+        ticker_id: TickerId,
+        side: Side,
+        price: Price,
+        qty: Qty,
+        tag: u64,
+        clamp: bool,
+    ) -> Result<(OrderId, Qty), RiskCheckResult> {
+        // Check the session's order budget
+        self.roll_session();
+        if self.remaining_order_budget() == Some(0) {
+            self.stats.orders_rejected_risk += 1;
+            return Err(RiskCheckResult::DailyOrderLimitExceeded);
+        }
+
         // Check risk
-        let risk_result = self.check_order_risk(ticker_id, side, price, qty);
-        if !risk_result.is_allowed() {
+        let risk_result = if clamp {
+            self.max_allowed_qty(ticker_id, side, price, qty)
+        } else {
+            let result = self.check_order_risk(ticker_id, side, price, qty);
+            if result.is_allowed() { Ok(qty) } else { Err(result) }
+        };
+        let allowed_qty = match risk_result {
+            Ok(allowed_qty) => allowed_qty,
+            Err(reason) => {
+                self.stats.orders_rejected_risk += 1;
+                return Err(reason);
+            }
+        };
+        let clamped = allowed_qty < qty;
+        let qty = allowed_qty;
+
+        // Check the order rate limit
+        let now = self.clock.now_nanos().as_u64();
+        let rate_result = self.risk_manager.check_rate(now);
+        if !rate_result.is_allowed() {
             self.stats.orders_rejected_risk += 1;
-            return Err(risk_result);
+            return Err(rate_result);
         }
+        self.risk_manager.record_order_sent(now);
 
+        let order_id = self.dispatch_order(ticker_id, side, price, qty, tag);
+        if clamped {
+            self.stats.orders_clamped += 1;
+        }
+
+        Ok((order_id, qty))
+    }
+
+    /// Submits an order via the callback and starts tracking it, with no
+    /// checks.
+    fn dispatch_order(&mut self, ticker_id: TickerId, side: Side, price: Price, qty: Qty, tag: u64) -> OrderId {
         // Submit via callback
         let order_id = if let Some(callback) = &mut self.order_submit_callback {
-            callback(ticker_id, side, price, qty)
+            callback(ticker_id, side, price, qty, tag)
         } else {
             // No callback - generate a placeholder ID
             self.stats.orders_submitted + 1
@@ -498,7 +1252,9 @@ impl TradeEngine {
             price,
             original_qty: qty,
             leaves_qty: qty,
-            sent_time: now_nanos(),
+            sent_time: self.clock.now_nanos(),
+            tag,
+            acknowledged: false,
         };
         self.pending_orders.insert(order_id, tracked);
 
@@ -510,8 +1266,51 @@ impl TradeEngine {
         position.add_open_order(side, qty);
 
         self.stats.orders_submitted += 1;
+        self.session_orders += 1;
+
+        order_id
+    }
 
-        Ok(order_id)
+    /// Returns how many more orders the session allows.
+    ///
+    /// A session whose configured length has elapsed counts as a fresh one.
+    /// Returns None when no daily cap is configured.
+    pub fn remaining_order_budget(&self) -> Option<u64> {
+        if self.config.max_daily_orders == 0 {
+            return None;
+        }
+        if self.session_expired() {
+            return Some(self.config.max_daily_orders);
+        }
+        Some(self.config.max_daily_orders.saturating_sub(self.session_orders))
+    }
+
+    /// Starts a new session if the configured length has elapsed.
+    ///
+    /// # Returns
+    /// true if a new session was started
+    pub fn roll_session(&mut self) -> bool {
+        if !self.session_expired() {
+            return false;
+        }
+        self.reset_session();
+        true
+    }
+
+    /// Returns true once the current session has run its configured length.
+    fn session_expired(&self) -> bool {
+        (self.clock.now_nanos() - self.session_start) >= self.config.session_length_ns
+    }
+
+    /// Returns the number of orders submitted in the current session.
+    pub fn session_orders(&self) -> u64 {
+        self.session_orders
+    }
+
+    /// Starts a new session, restoring the full order budget.
+    pub fn reset_session(&mut self) {
+        self.session_orders = 0;
+        self.session_start = self.clock.now_nanos();
     }
 
     /// Cancels an order.
@@ -524,6 +1323,147 @@ impl TradeEngine {
         }
     }
 
+    /// Replaces a quote with a new price and quantity via cancel and new.
+    ///
+    /// The replacement is submitted first, with the old order's side, ticker
+    /// and tag, and risk-checked as if the old order were already gone. If it
+    /// passes, the old order is cancelled and stops counting as open but stays
+    /// linked to its replacement until the cancel lands, so a late fill on it
+    /// still updates the position without touching the replacement. If the
+    /// replacement is rejected the old order is left as it was.
+    ///
+    /// # Arguments
+    /// * `old_order_id` - The tracked order to replace
+    /// * `new_price` - Price of the replacement
+    /// * `new_qty` - Quantity of the replacement
+    ///
+    /// # Returns
+    /// The replacement's order ID or the risk rejection reason, or None if the
+    /// old order is no longer tracked.
+    pub fn replace_quote(
+        &mut self,
+        old_order_id: OrderId,
+        new_price: Price,
+        new_qty: Qty,
+    ) -> Option<Result<OrderId, RiskCheckResult>> {
+        let old = self.pending_orders.remove(&old_order_id)?;
+        let (ticker_id, side) = (old.ticker_id, old.side);
+
+        // The old order is on its way out; don't count it against the new one
+        self.position_keeper
+            .get_position_mut(ticker_id)
+            .remove_open_order(side, old.leaves_qty);
+        let count = self.open_order_count.entry(ticker_id).or_insert(0);
+        *count = count.saturating_sub(1);
+
+        let result = self.submit_order_with_tag(ticker_id, side, new_price, new_qty, old.tag);
+        match result {
+            Ok(new_order_id) => {
+                if let Some(callback) = &mut self.order_cancel_callback {
+                    callback(old_order_id, ticker_id);
+                }
+                self.replaced_orders
+                    .insert(old_order_id, ReplacedOrder { order: old, replacement: new_order_id });
+            }
+            Err(_) => {
+                self.position_keeper
+                    .get_position_mut(ticker_id)
+                    .add_open_order(side, old.leaves_qty);
+                *self.open_order_count.entry(ticker_id).or_insert(0) += 1;
+                self.pending_orders.insert(old_order_id, old);
+            }
+        }
+        Some(result)
+    }
+
+    /// Amends a resting order's price and quantity in place.
+    ///
+    /// The new terms are risk-checked as if the old order were already gone
+    /// and, if they pass, sent through the replace callback. The tracked
+    /// order keeps its ID and takes the new terms at once, so the open order
+    /// count is unchanged; its previous terms are restored if the exchange
+    /// rejects the amendment. A rejected amendment leaves the order as it was.
+    ///
+    /// # Arguments
+    /// * `order_id` - The tracked order to amend
+    /// * `new_price` - Its new price
+    /// * `new_qty` - Its new quantity
+    ///
+    /// # Returns
+    /// The order's ID or the risk rejection reason, or None if the order is
+    /// no longer tracked.
+    pub fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Price,
+        new_qty: Qty,
+    ) -> Option<Result<OrderId, RiskCheckResult>> {
+        let order = self.pending_orders.get(&order_id)?;
+        let (ticker_id, side) = (order.ticker_id, order.side);
+        let old_terms = (order.price, order.original_qty, order.leaves_qty);
+
+        // Check the new terms without the order's current quantity
+        self.position_keeper
+            .get_position_mut(ticker_id)
+            .remove_open_order(side, old_terms.2);
+        let count = self.open_order_count.entry(ticker_id).or_insert(0);
+        *count = count.saturating_sub(1);
+        let mut result = self.check_order_risk(ticker_id, side, new_price, new_qty);
+        *self.open_order_count.entry(ticker_id).or_insert(0) += 1;
+        if result.is_allowed() {
+            let now = self.clock.now_nanos().as_u64();
+            result = self.risk_manager.check_rate(now);
+            if result.is_allowed() {
+                self.risk_manager.record_order_sent(now);
+            }
+        }
+        if !result.is_allowed() {
+            self.position_keeper
+                .get_position_mut(ticker_id)
+                .add_open_order(side, old_terms.2);
+            self.stats.orders_rejected_risk += 1;
+            return Some(Err(result));
+        }
+
+        self.position_keeper
+            .get_position_mut(ticker_id)
+            .add_open_order(side, new_qty);
+        self.amended_orders.entry(order_id).or_insert(old_terms);
+        if let Some(order) = self.pending_orders.get_mut(&order_id) {
+            order.price = new_price;
+            order.original_qty = new_qty;
+            order.leaves_qty = new_qty;
+        }
+        if let Some(callback) = &mut self.order_replace_callback {
+            callback(order_id, new_price, new_qty);
+        }
+        self.stats.orders_amended += 1;
+        Some(Ok(order_id))
+    }
+
+    /// Puts an order whose amendment was rejected back on its previous terms.
+    fn restore_amended_order(&mut self, order_id: OrderId) {
+        let Some((price, original_qty, leaves_qty)) = self.amended_orders.remove(&order_id) else {
+            return;
+        };
+        self.stats.amend_rejects += 1;
+        let Some(order) = self.pending_orders.get_mut(&order_id) else {
+            return;
+        };
+        let position = self.position_keeper.get_position_mut(order.ticker_id);
+        position.remove_open_order(order.side, order.leaves_qty);
+        position.add_open_order(order.side, leaves_qty);
+        order.price = price;
+        order.original_qty = original_qty;
+        order.leaves_qty = leaves_qty;
+    }
+
+    /// Returns the order that replaced `order_id`, while the replaced order's
+    /// cancel has not landed.
+    pub fn replacement_of(&self, order_id: OrderId) -> Option<OrderId> {
+        self.replaced_orders.get(&order_id).map(|replaced| replaced.replacement)
+    }
+
     /// Cancels all orders for a ticker.
     pub fn cancel_all_orders(&mut self, ticker_id: TickerId) {
         let order_ids: Vec<OrderId> = self
@@ -538,6 +1478,137 @@ impl TradeEngine {
         }
     }
 
+    /// Kills the session on an operator's command.
+    ///
+    /// Every resting order on every ticker is cancelled and new orders are
+    /// rejected with `SessionKilled` until `resume_session`. With `flatten`,
+    /// each open position is also closed by an order at the opposite touch
+    /// (the best bid to sell a long, the best ask to buy back a short);
+    /// tickers without that side of the book are left open.
+    ///
+    /// # Returns
+    /// The flattening orders sent, as (order ID, ticker)
+    pub fn kill_session(&mut self, flatten: bool) -> Vec<(OrderId, TickerId)> {
+        self.killed = true;
+        self.stats.session_kills += 1;
+
+        let order_ids: Vec<OrderId> = self.pending_orders.keys().copied().collect();
+        self.stats.killed_orders += order_ids.len() as u64;
+        for order_id in order_ids {
+            self.cancel_order(order_id);
+        }
+
+        if !flatten {
+            return Vec::new();
+        }
+        let mut closes: Vec<(TickerId, Side, Qty)> = self
+            .position_keeper
+            .all_positions()
+            .filter(|position| position.position != 0)
+            .map(|position| {
+                let side = if position.position > 0 { Side::Sell } else { Side::Buy };
+                (position.ticker_id, side, position.position.unsigned_abs() as Qty)
+            })
+            .collect();
+        closes.sort_unstable_by_key(|&(ticker_id, _, _)| ticker_id);
+
+        let mut flattening = Vec::new();
+        for (ticker_id, side, qty) in closes {
+            let touch = self.get_bbo(ticker_id).and_then(|bbo| match side {
+                Side::Sell => bbo.has_bid().then_some(bbo.bid_price),
+                Side::Buy => bbo.has_ask().then_some(bbo.ask_price),
+            });
+            if let Some(price) = touch {
+                let order_id = self.dispatch_order(ticker_id, side, price, qty, 0);
+                flattening.push((order_id, ticker_id));
+            }
+        }
+        flattening
+    }
+
+    /// Cancels a single order on an operator's command.
+    ///
+    /// # Returns
+    /// true if the order was tracked and a cancel was sent
+    pub fn kill_order(&mut self, order_id: OrderId) -> bool {
+        if !self.pending_orders.contains_key(&order_id) {
+            return false;
+        }
+        self.stats.killed_orders += 1;
+        self.cancel_order(order_id);
+        true
+    }
+
+    /// Lifts an operator kill so orders are accepted again.
+    ///
+    /// Returns true if the session was killed.
+    pub fn resume_session(&mut self) -> bool {
+        std::mem::replace(&mut self.killed, false)
+    }
+
+    /// Returns whether the session is killed.
+    #[inline]
+    pub fn is_killed(&self) -> bool {
+        self.killed
+    }
+
+    /// Checks a ticker's current position against its limits.
+    ///
+    /// With `halt_on_risk_breach` set, a `PositionTooLarge` or `LossTooLarge`
+    /// result halts the ticker: its resting orders are cancelled and new
+    /// orders for it are rejected with `TickerHalted` until `clear_halt`.
+    /// Other tickers are unaffected. Runs after every fill and mark change.
+    ///
+    /// # Returns
+    /// The position check result for the ticker
+    pub fn check_ticker_risk(&mut self, ticker_id: TickerId) -> RiskCheckResult {
+        let Some(position) = self.position_keeper.get_position(ticker_id) else {
+            return RiskCheckResult::Allowed;
+        };
+        let result = self.risk_manager.check_position(position);
+
+        let breached = matches!(
+            result,
+            RiskCheckResult::PositionTooLarge | RiskCheckResult::LossTooLarge
+        );
+        if breached && self.config.halt_on_risk_breach && self.halted_tickers.insert(ticker_id) {
+            self.stats.risk_halts += 1;
+            self.cancel_all_orders(ticker_id);
+        }
+
+        result
+    }
+
+    /// Runs the per-ticker risk check when halting on breach is enabled.
+    fn enforce_ticker_risk(&mut self, ticker_id: TickerId) {
+        if self.config.halt_on_risk_breach && self.config.enable_risk_checks {
+            self.check_ticker_risk(ticker_id);
+        }
+    }
+
+    /// Returns the tickers halted by a risk breach.
+    pub fn halted_tickers(&self) -> &HashSet<TickerId> {
+        &self.halted_tickers
+    }
+
+    /// Returns whether a ticker is halted by a risk breach.
+    pub fn is_ticker_halted(&self, ticker_id: TickerId) -> bool {
+        self.halted_tickers.contains(&ticker_id)
+    }
+
+    /// Lifts a ticker's risk halt.
+    ///
+    /// Returns true if the ticker was halted. The halt is raised again by
+    /// the next fill or mark change if the breach persists.
+    pub fn clear_halt(&mut self, ticker_id: TickerId) -> bool {
+        self.halted_tickers.remove(&ticker_id)
+    }
+
+    /// Lifts every risk halt.
+    pub fn clear_all_halts(&mut self) {
+        self.halted_tickers.clear();
+    }
+
     /// Returns a reference to a pending order.
     pub fn get_pending_order(&self, order_id: OrderId) -> Option<&TrackedOrder> {
         self.pending_orders.get(&order_id)
@@ -553,6 +1624,59 @@ impl TradeEngine {
         self.pending_orders.len()
     }
 
+    /// Checks open order accounting against the tracked orders.
+    ///
+    /// Recomputes each ticker's open buy and sell quantity and open order
+    /// count from the pending orders, which are authoritative, and corrects
+    /// the position and count wherever they drifted.
+    ///
+    /// # Returns
+    /// One entry per corrected ticker, empty if everything agreed
+    pub fn audit_open_orders(&mut self) -> Vec<OpenOrderDiscrepancy> {
+        let mut expected: HashMap<TickerId, (Qty, Qty, u32)> = HashMap::new();
+        for order in self.pending_orders.values() {
+            let entry = expected.entry(order.ticker_id).or_default();
+            match order.side {
+                Side::Buy => entry.0 += order.leaves_qty,
+                Side::Sell => entry.1 += order.leaves_qty,
+            }
+            entry.2 += 1;
+        }
+
+        // Every ticker that has, or claims to have, open orders
+        let mut tickers: HashSet<TickerId> = expected.keys().copied().collect();
+        tickers.extend(self.open_order_count.iter().filter(|(_, &count)| count > 0).map(|(&id, _)| id));
+        tickers.extend(
+            self.position_keeper
+                .all_positions()
+                .filter(|position| position.open_buy_qty > 0 || position.open_sell_qty > 0)
+                .map(|position| position.ticker_id),
+        );
+
+        let mut discrepancies = Vec::new();
+        for ticker_id in tickers {
+            let (buy_qty, sell_qty, count) = expected.get(&ticker_id).copied().unwrap_or_default();
+            let recorded_count = self.pending_order_count(ticker_id);
+            let position = self.position_keeper.get_position_mut(ticker_id);
+            if position.open_buy_qty == buy_qty && position.open_sell_qty == sell_qty && recorded_count == count {
+                continue;
+            }
+
+            discrepancies.push(OpenOrderDiscrepancy {
+                ticker_id,
+                open_buy_qty: (position.open_buy_qty, buy_qty),
+                open_sell_qty: (position.open_sell_qty, sell_qty),
+                open_orders: (recorded_count, count),
+            });
+            position.open_buy_qty = buy_qty;
+            position.open_sell_qty = sell_qty;
+            self.open_order_count.insert(ticker_id, count);
+        }
+
+        self.stats.open_order_corrections += discrepancies.len() as u64;
+        discrepancies
+    }
+
     // ========================================================================
     // Strategy Integration
     // ========================================================================
@@ -570,20 +1694,16 @@ impl TradeEngine {
         match action {
             StrategyAction::None => {}
             StrategyAction::Quote(pair) => {
-                // Process bid
-                if let Some(bid) = pair.bid {
-                    let result = self.submit_order(bid.ticker_id, bid.side, bid.price, bid.qty);
+                // Process bid, then ask; legs replacing a resting order amend it
+                for quote in [pair.bid, pair.ask].into_iter().flatten() {
+                    let result = match quote.request_type {
+                        OrderRequestType::New => Some(self.process_order_request(&quote)),
+                        OrderRequestType::Replace(order_id) => self.amend_order(order_id, quote.price, quote.qty),
+                    };
                     match result {
-                        Ok(id) => results.push((Some(id), RiskCheckResult::Allowed)),
-                        Err(risk) => results.push((None, risk)),
-                    }
-                }
-                // Process ask
-                if let Some(ask) = pair.ask {
-                    let result = self.submit_order(ask.ticker_id, ask.side, ask.price, ask.qty);
-                    match result {
-                        Ok(id) => results.push((Some(id), RiskCheckResult::Allowed)),
-                        Err(risk) => results.push((None, risk)),
+                        Some(Ok(id)) => results.push((Some(id), RiskCheckResult::Allowed)),
+                        Some(Err(risk)) => results.push((None, risk)),
+                        None => {}
                     }
                 }
             }
@@ -598,12 +1718,76 @@ impl TradeEngine {
             StrategyAction::CancelAll(ticker_id) => {
                 self.cancel_all_orders(ticker_id);
             }
+            StrategyAction::Replace { order_id, new_price, new_qty } => {
+                match self.amend_order(order_id, new_price, new_qty) {
+                    Some(Ok(id)) => results.push((Some(id), RiskCheckResult::Allowed)),
+                    Some(Err(risk)) => results.push((None, risk)),
+                    None => {}
+                }
+            }
         }
 
         self.stats.strategy_cycles += 1;
         results
     }
 
+    /// Processes several tickers' strategy actions from one cycle.
+    ///
+    /// The actions are processed in the configured `ActionOrdering`, not the
+    /// order given, so allocation of a limited budget is reproducible.
+    ///
+    /// # Returns
+    /// The (OrderId, RiskCheckResult) for each order attempted, in the order
+    /// attempted.
+    pub fn process_strategy_actions(
+        &mut self,
+        actions: impl IntoIterator<Item = StrategyAction>,
+    ) -> Vec<(Option<OrderId>, RiskCheckResult)> {
+        let mut actions: Vec<StrategyAction> = actions.into_iter().collect();
+        self.sort_actions(&mut actions);
+
+        let mut results = Vec::new();
+        for action in actions {
+            results.extend(self.process_strategy_action(action));
+        }
+        results
+    }
+
+    /// Sorts actions into the configured processing order.
+    pub fn sort_actions(&self, actions: &mut [StrategyAction]) {
+        let ticker_key = |action: &StrategyAction| action.ticker_id().unwrap_or(TickerId::MAX);
+        match &self.config.action_ordering {
+            ActionOrdering::TickerId => actions.sort_by_key(ticker_key),
+            ActionOrdering::SignalStrength => {
+                let strength = |action: &StrategyAction| {
+                    action
+                        .ticker_id()
+                        .and_then(|ticker_id| self.get_features(ticker_id))
+                        .map_or(0.0, |features| features.trade_signal.abs())
+                };
+                actions.sort_by(|a, b| {
+                    strength(b)
+                        .total_cmp(&strength(a))
+                        .then_with(|| ticker_key(a).cmp(&ticker_key(b)))
+                });
+            }
+            ActionOrdering::Priority(priorities) => {
+                let priority = |action: &StrategyAction| {
+                    action
+                        .ticker_id()
+                        .and_then(|ticker_id| priorities.get(&ticker_id).copied())
+                        .unwrap_or(0)
+                };
+                actions.sort_by_key(|action| (std::cmp::Reverse(priority(action)), ticker_key(action)));
+            }
+        }
+    }
+
+    /// Returns the order in which strategy actions are processed.
+    pub fn action_ordering(&self) -> &ActionOrdering {
+        &self.config.action_ordering
+    }
+
     /// Processes an order request.
     ///
     /// Convenience method for submitting a single order request.
@@ -624,6 +1808,15 @@ impl TradeEngine {
         self.position_keeper.get_position(ticker_id)
     }
 
+    /// Returns the quantity bought and sold on a ticker since the current
+    /// cycle began, and starts the tally over.
+    ///
+    /// Call after `run_cycle` to hand a strategy all of the cycle's fills at
+    /// once, e.g. via `MarketMaker::on_cycle_fills`.
+    pub fn take_cycle_fills(&mut self, ticker_id: TickerId) -> CycleFills {
+        self.cycle_fills.remove(&ticker_id).unwrap_or_default()
+    }
+
     // ========================================================================
     // Event Loop Support
     // ========================================================================
@@ -637,7 +1830,9 @@ impl TradeEngine {
     /// 3. Strategy signals
     ///
     /// The responses and market_data iterators should be provided by the caller
-    /// who is polling the network connections.
+    /// who is polling the network connections. The cycle's fills are tallied
+    /// per ticker for `take_cycle_fills`. With an audit interval configured,
+    /// open order accounting is audited every that many cycles.
     pub fn run_cycle<R, M>(
         &mut self,
         responses: R,
@@ -653,6 +1848,7 @@ impl TradeEngine {
 
         let mut events_processed = 0;
         let max_events = self.config.max_events_per_cycle;
+        self.cycle_fills.clear();
 
         // Priority 1: Process exchange responses
         for response in responses.take(max_events) {
@@ -669,6 +1865,11 @@ impl TradeEngine {
 
         self.stats.total_cycles += 1;
 
+        let audit_interval = self.config.open_order_audit_interval;
+        if audit_interval > 0 && self.stats.total_cycles.is_multiple_of(audit_interval) {
+            self.audit_open_orders();
+        }
+
         events_processed
     }
 
@@ -677,7 +1878,16 @@ impl TradeEngine {
         self.feature_engine.clear();
         self.bbo_state.clear();
         self.pending_orders.clear();
+        self.replaced_orders.clear();
+        self.amended_orders.clear();
         self.open_order_count.clear();
+        self.buffered_fills.clear();
+        self.canceled_orders.clear();
+        self.canceled_order_queue.clear();
+        self.halted_tickers.clear();
+        self.killed = false;
+        self.cycle_fills.clear();
+        self.reset_session();
         self.stats.reset();
 
         // Re-initialize for configured tickers
@@ -693,6 +1903,7 @@ impl TradeEngine {
 mod tests {
     use super::*;
     use exchange::protocol::MarketUpdateType;
+    use crate::risk::RiskLimits;
 
     fn make_bbo(bid_price: Price, bid_qty: Qty, ask_price: Price, ask_qty: Qty) -> BBO {
         BBO {
@@ -700,6 +1911,7 @@ mod tests {
             bid_qty,
             ask_price,
             ask_qty,
+            stale: false,
         }
     }
 
@@ -928,7 +2140,7 @@ mod tests {
         let mut engine = TradeEngine::new(config);
 
         let mut next_id = 1000u64;
-        engine.set_order_submit_callback(Box::new(move |_ticker, _side, _price, _qty| {
+        engine.set_order_submit_callback(Box::new(move |_ticker, _side, _price, _qty, _tag| {
             let id = next_id;
             next_id += 1;
             id
@@ -941,6 +2153,43 @@ mod tests {
         assert_eq!(result2.unwrap(), 1001);
     }
 
+    #[test]
+    fn test_submit_order_with_tag_reported_on_fill() {
+        use std::sync::{Arc, Mutex};
+
+        let config = TradeEngineConfig::new(1).with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        let sent_tags = Arc::new(Mutex::new(Vec::new()));
+        let sent_tags_clone = sent_tags.clone();
+        engine.set_order_submit_callback(Box::new(move |_ticker, _side, _price, _qty, tag| {
+            sent_tags_clone.lock().unwrap().push(tag);
+            7
+        }));
+
+        let fills = Arc::new(Mutex::new(Vec::new()));
+        let fills_clone = fills.clone();
+        engine.set_order_fill_callback(Box::new(move |fill| {
+            fills_clone.lock().unwrap().push(*fill);
+        }));
+
+        let order_id = engine
+            .submit_order_with_tag(1, Side::Buy, 10000, 100, 0xABCD)
+            .unwrap();
+        assert_eq!(engine.get_pending_order(order_id).unwrap().tag, 0xABCD);
+        assert_eq!(*sent_tags.lock().unwrap(), vec![0xABCD]);
+
+        let fill = make_fill_response(order_id, 1, Side::Buy, 10000, 40, 60).with_tag(0xABCD);
+        engine.on_response(&fill);
+
+        let fills = fills.lock().unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].order_id, order_id);
+        assert_eq!(fills[0].exec_qty, 40);
+        assert_eq!(fills[0].leaves_qty, 60);
+        assert_eq!(fills[0].tag, 0xABCD);
+    }
+
     #[test]
     fn test_submit_order_risk_rejection() {
         let mut engine = TradeEngine::with_defaults(1);
@@ -958,6 +2207,62 @@ mod tests {
         assert_eq!(engine.stats().orders_rejected_risk, 1);
     }
 
+    #[test]
+    fn test_submit_order_clamped_to_risk_budget() {
+        let config = TradeEngineConfig::new(1).with_risk_clamp(true);
+        let mut engine = TradeEngine::new(config);
+        engine
+            .risk_manager_mut()
+            .set_limits(1, RiskLimits::new(1000, 1000, 100000, 10));
+
+        // 50 below the position cap
+        let bought = engine.submit_order(1, Side::Buy, 10000, 950).unwrap();
+        engine.on_response(&make_fill_response(bought, 1, Side::Buy, 10000, 950, 0));
+        assert_eq!(
+            engine.check_order_risk(1, Side::Buy, 10000, 100),
+            RiskCheckResult::PositionTooLarge
+        );
+
+        let order_id = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        assert_eq!(engine.get_pending_order(order_id).unwrap().original_qty, 50);
+        assert_eq!(engine.get_position(1).unwrap().open_buy_qty, 50);
+        assert_eq!(engine.stats().orders_clamped, 1);
+        assert_eq!(engine.stats().orders_rejected_risk, 0);
+
+        // No budget left: nothing to clamp to
+        assert_eq!(
+            engine.submit_order_clamped(1, Side::Buy, 10000, 100),
+            Err(RiskCheckResult::PositionTooLarge)
+        );
+        // Risk-reducing orders are sent in full
+        assert_eq!(engine.submit_order_clamped(1, Side::Sell, 10100, 100).unwrap().1, 100);
+        assert_eq!(engine.stats().orders_clamped, 1);
+    }
+
+    #[test]
+    fn test_clamp_respects_min_notional() {
+        let mut engine = TradeEngine::with_defaults(1);
+        let mut limits = RiskLimits::new(1000, 1000, 100000, 10);
+        limits.min_notional = 10000 * 20;
+        engine.risk_manager_mut().set_limits(1, limits);
+
+        let bought = engine.submit_order(1, Side::Buy, 10000, 990).unwrap();
+        engine.on_response(&make_fill_response(bought, 1, Side::Buy, 10000, 990, 0));
+
+        // Without clamping the order is rejected outright
+        assert_eq!(
+            engine.submit_order(1, Side::Buy, 10000, 100),
+            Err(RiskCheckResult::PositionTooLarge)
+        );
+        // 10 would fit the position but falls below the minimum notional
+        assert_eq!(
+            engine.max_allowed_qty(1, Side::Buy, 10000, 100),
+            Err(RiskCheckResult::PositionTooLarge)
+        );
+        assert_eq!(engine.max_allowed_qty(1, Side::Buy, 10000, 5), Err(RiskCheckResult::NotionalTooSmall));
+        assert_eq!(engine.stats().orders_clamped, 0);
+    }
+
     #[test]
     fn test_cancel_order() {
         let config = TradeEngineConfig::new(1).with_risk_checks(false);
@@ -975,6 +2280,210 @@ mod tests {
         assert!(engine.get_pending_order(order_id).is_some());
     }
 
+    #[test]
+    fn test_kill_session_cancels_everything_and_flattens() {
+        use std::sync::{Arc, Mutex};
+
+        let config = TradeEngineConfig::new(1).with_tickers(vec![1, 2]).with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+        let cancels = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&cancels);
+        engine.set_order_cancel_callback(Box::new(move |id, ticker| {
+            sink.lock().unwrap().push((id, ticker));
+        }));
+
+        // Long 10 on ticker 1, short 5 on ticker 2, one resting order each
+        let bought = engine.submit_order(1, Side::Buy, 10000, 10).unwrap();
+        engine.on_response(&make_fill_response(bought, 1, Side::Buy, 10000, 10, 0));
+        let sold = engine.submit_order(2, Side::Sell, 20000, 5).unwrap();
+        engine.on_response(&make_fill_response(sold, 2, Side::Sell, 20000, 5, 0));
+        let resting_1 = engine.submit_order(1, Side::Sell, 10100, 10).unwrap();
+        let resting_2 = engine.submit_order(2, Side::Buy, 19900, 10).unwrap();
+        engine.update_bbo(1, make_bbo(9990, 10, 10010, 10));
+        engine.update_bbo(2, make_bbo(19990, 10, 20010, 10));
+
+        let flattening = engine.kill_session(true);
+        let mut cancelled = cancels.lock().unwrap().clone();
+        cancelled.sort_unstable();
+        assert_eq!(cancelled, vec![(resting_1, 1), (resting_2, 2)]);
+
+        // Each position closes at the opposite touch
+        assert_eq!(flattening.len(), 2);
+        let close_1 = engine.get_pending_order(flattening[0].0).unwrap();
+        assert_eq!((close_1.ticker_id, close_1.side, close_1.price, close_1.leaves_qty), (1, Side::Sell, 9990, 10));
+        let close_2 = engine.get_pending_order(flattening[1].0).unwrap();
+        assert_eq!((close_2.ticker_id, close_2.side, close_2.price, close_2.leaves_qty), (2, Side::Buy, 20010, 5));
+
+        // No new orders until the kill is lifted
+        assert!(engine.is_killed());
+        assert_eq!(engine.submit_order(1, Side::Buy, 9990, 10), Err(RiskCheckResult::SessionKilled));
+        assert_eq!((engine.stats().session_kills, engine.stats().killed_orders), (1, 2));
+        assert!(engine.resume_session());
+        assert!(engine.submit_order(1, Side::Buy, 9990, 10).is_ok());
+
+        // A single order kill only touches that order
+        assert!(engine.kill_order(resting_1));
+        assert!(!engine.kill_order(999));
+        assert!(!engine.is_killed());
+        assert_eq!(engine.stats().killed_orders, 3);
+    }
+
+    #[test]
+    fn test_risk_breach_halts_only_breaching_ticker() {
+        use std::sync::{Arc, Mutex};
+
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1, 2])
+            .with_halt_on_risk_breach(true);
+        let mut engine = TradeEngine::new(config);
+        engine
+            .risk_manager_mut()
+            .set_limits(1, RiskLimits::new(1000, 10000, 500, 100));
+
+        let cancels = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&cancels);
+        engine.set_order_cancel_callback(Box::new(move |id, ticker| {
+            sink.lock().unwrap().push((id, ticker));
+        }));
+
+        let bought = engine.submit_order(1, Side::Buy, 10000, 10).unwrap();
+        let resting = engine.submit_order(1, Side::Sell, 10100, 10).unwrap();
+        let other = engine.submit_order(2, Side::Buy, 20000, 10).unwrap();
+        engine.on_response(&make_fill_response(bought, 1, Side::Buy, 10000, 10, 0));
+        assert!(!engine.is_ticker_halted(1));
+
+        // Ticker 1 marks down 100 cents: a 1000 cent loss against a 500 limit
+        engine.update_bbo(1, make_bbo(9899, 10, 9901, 10));
+        assert_eq!(engine.check_ticker_risk(1), RiskCheckResult::LossTooLarge);
+        assert!(engine.is_ticker_halted(1));
+        assert!(!engine.is_ticker_halted(2));
+        assert_eq!(engine.halted_tickers().len(), 1);
+        assert_eq!(engine.stats().risk_halts, 1);
+
+        // Only ticker 1's resting order was cancelled, once
+        assert_eq!(*cancels.lock().unwrap(), vec![(resting, 1)]);
+        assert!(engine.get_pending_order(other).is_some());
+
+        // Ticker 1 takes no new orders; ticker 2 keeps quoting
+        assert_eq!(
+            engine.submit_order(1, Side::Sell, 9901, 10),
+            Err(RiskCheckResult::TickerHalted)
+        );
+        assert!(engine.submit_order(2, Side::Sell, 20100, 10).is_ok());
+
+        // Once the limit is raised and the halt cleared, ticker 1 trades again
+        engine
+            .risk_manager_mut()
+            .set_limits(1, RiskLimits::new(1000, 10000, 5000, 100));
+        assert!(engine.clear_halt(1));
+        assert!(engine.halted_tickers().is_empty());
+        assert!(engine.submit_order(1, Side::Sell, 9901, 10).is_ok());
+    }
+
+    #[test]
+    fn test_daily_order_limit() {
+        use common::time::ManualClock;
+
+        let config = TradeEngineConfig::new(1)
+            .with_risk_checks(false)
+            .with_daily_order_limit(3, DEFAULT_SESSION_LENGTH_NS);
+        let mut engine = TradeEngine::new(config);
+        assert_eq!(engine.remaining_order_budget(), Some(3));
+
+        for _ in 0..3 {
+            assert!(engine.submit_order(1, Side::Buy, 10000, 10).is_ok());
+        }
+        assert_eq!(engine.remaining_order_budget(), Some(0));
+        assert_eq!(
+            engine.submit_order(2, Side::Sell, 10100, 10),
+            Err(RiskCheckResult::DailyOrderLimitExceeded)
+        );
+        assert_eq!(engine.session_orders(), 3);
+        assert_eq!(engine.stats().orders_rejected_risk, 1);
+
+        // A new session restores the budget
+        engine.reset_session();
+        assert_eq!(engine.remaining_order_budget(), Some(3));
+        assert!(engine.submit_order(2, Side::Sell, 10100, 10).is_ok());
+
+        // Sessions also roll over once their length elapses
+        let config = TradeEngineConfig::new(1)
+            .with_risk_checks(false)
+            .with_daily_order_limit(1, 1_000_000);
+        let mut engine = TradeEngine::new(config);
+        let clock = Arc::new(ManualClock::new(Nanos::new(1_000_000)));
+        engine.set_clock(clock.clone());
+        assert!(engine.submit_order(1, Side::Buy, 10000, 10).is_ok());
+        assert_eq!(engine.remaining_order_budget(), Some(0));
+        clock.advance(1_000_000);
+        assert_eq!(engine.remaining_order_budget(), Some(1));
+        assert_eq!(engine.session_orders(), 1);
+        assert!(engine.roll_session());
+        assert_eq!(engine.session_orders(), 0);
+        assert!(!engine.roll_session());
+        assert!(engine.submit_order(1, Side::Buy, 10000, 10).is_ok());
+        assert_eq!(engine.remaining_order_budget(), Some(0));
+
+        // No cap configured
+        assert_eq!(TradeEngine::with_defaults(1).remaining_order_budget(), None);
+    }
+
+    #[test]
+    fn test_manual_clock_rolls_session() {
+        use common::time::ManualClock;
+
+        let config = TradeEngineConfig::new(1)
+            .with_risk_checks(false)
+            .with_daily_order_limit(1, 1_000_000);
+        let mut engine = TradeEngine::new(config);
+        let clock = Arc::new(ManualClock::new(Nanos::new(5_000_000)));
+        engine.set_clock(clock.clone());
+
+        let order_id = engine.submit_order(1, Side::Buy, 10000, 10).unwrap();
+        assert_eq!(engine.get_pending_order(order_id).unwrap().sent_time, Nanos::new(5_000_000));
+
+        // The session only rolls over once the manual clock is advanced
+        clock.advance(999_999);
+        assert_eq!(engine.remaining_order_budget(), Some(0));
+        clock.advance(1);
+        assert_eq!(engine.remaining_order_budget(), Some(1));
+        assert_eq!(engine.clock().now_nanos(), Nanos::new(6_000_000));
+    }
+
+    #[test]
+    fn test_order_latency_recorded_on_first_ack() {
+        use common::time::ManualClock;
+
+        let config = TradeEngineConfig::new(1).with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+        let clock = Arc::new(ManualClock::new(Nanos::new(1_000_000)));
+        engine.set_clock(clock.clone());
+        assert_eq!(engine.stats().latency_percentiles(), LatencyPercentiles::default());
+
+        // Acked 1.5us after sending; the fill that follows is not a second sample
+        let order_id = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        clock.advance(1_500);
+        engine.on_response(&make_accepted_response(order_id, 1, Side::Buy, 10000, 100));
+        clock.advance(50_000);
+        engine.on_response(&make_fill_response(order_id, 1, Side::Buy, 10000, 40, 60));
+        assert_eq!(engine.stats().order_latency.count(), 1);
+        assert_eq!(
+            engine.stats().latency_percentiles(),
+            LatencyPercentiles { p50: 1_500, p99: 1_500, max: 1_500 }
+        );
+
+        // An order filled outright is timed by its fill
+        let order_id = engine.submit_order(1, Side::Sell, 10100, 10).unwrap();
+        clock.advance(20_000);
+        engine.on_response(&make_fill_response(order_id, 1, Side::Sell, 10100, 10, 0));
+        let percentiles = engine.stats().latency_percentiles();
+        assert_eq!(engine.stats().order_latency.count(), 2);
+        // The median shares a bucket with values up to ~6% above it
+        assert!((1_500..1_600).contains(&percentiles.p50));
+        assert_eq!(percentiles.p99, 20_000);
+        assert_eq!(percentiles.max, 20_000);
+    }
+
     #[test]
     fn test_cancel_all_orders() {
         let config = TradeEngineConfig::new(1).with_risk_checks(false);
@@ -1051,6 +2560,48 @@ mod tests {
         assert_eq!(position.position, 60);
     }
 
+    #[test]
+    fn test_fill_before_accept_applied_once_tracked() {
+        let config = TradeEngineConfig::new(1).with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        // An order the engine didn't submit: its fill overtakes the accept
+        let fill = make_fill_response(42, 1, Side::Buy, 10000, 60, 40);
+        engine.on_response(&fill);
+
+        assert_eq!(engine.stats().out_of_order_responses, 1);
+        assert_eq!(engine.buffered_fill_count(), 1);
+        assert_eq!(engine.stats().fills_received, 0);
+        assert!(engine.get_position(1).is_none_or(|p| p.position == 0));
+
+        // The accept starts tracking the order and releases the fill
+        let accept = make_accepted_response(42, 1, Side::Buy, 10000, 100);
+        engine.on_response(&accept);
+
+        assert_eq!(engine.buffered_fill_count(), 0);
+        assert_eq!(engine.stats().fills_received, 1);
+        assert_eq!(engine.stats().responses_processed, 2);
+        let position = engine.get_position(1).unwrap();
+        assert_eq!(position.position, 60);
+        assert_eq!(position.open_buy_qty, 40);
+        assert_eq!(engine.get_pending_order(42).unwrap().leaves_qty, 40);
+        assert_eq!(engine.pending_order_count(1), 1);
+    }
+
+    #[test]
+    fn test_cancel_after_fill_ignored() {
+        let config = TradeEngineConfig::new(1).with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        let order_id = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        engine.on_response(&make_fill_response(order_id, 1, Side::Buy, 10000, 100, 0));
+        engine.on_response(&make_canceled_response(order_id, 1));
+
+        assert_eq!(engine.stats().out_of_order_responses, 1);
+        assert_eq!(engine.pending_order_count(1), 0);
+        assert_eq!(engine.get_position(1).unwrap().position, 100);
+    }
+
     #[test]
     fn test_on_response_canceled() {
         let config = TradeEngineConfig::new(1).with_risk_checks(false);
@@ -1146,6 +2697,138 @@ mod tests {
         assert_eq!(processed, 0);
     }
 
+    #[test]
+    fn test_paired_quote_fills_in_one_cycle_net_to_flat() {
+        use crate::strategies::{MarketMaker, MarketMakerConfig};
+
+        let config = TradeEngineConfig::new(1).with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+        engine.start();
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1).with_requote_after_fills(true));
+        engine.update_bbo(1, make_bbo(9990, 100, 10010, 100));
+        let features = engine.get_features(1).unwrap().clone();
+
+        let results = engine.process_strategy_action(mm.on_features(&features));
+        let (bid_id, ask_id) = (results[0].0.unwrap(), results[1].0.unwrap());
+        let (bid, ask) = (engine.get_pending_order(bid_id).unwrap(), engine.get_pending_order(ask_id).unwrap());
+        let responses = vec![
+            make_fill_response(bid_id, 1, Side::Buy, bid.price, bid.original_qty, 0),
+            make_fill_response(ask_id, 1, Side::Sell, ask.price, ask.original_qty, 0),
+        ];
+        engine.run_cycle(responses.into_iter(), std::iter::empty());
+
+        let fills = engine.take_cycle_fills(1);
+        assert!(fills.is_two_sided());
+        let position = engine.get_position(1).unwrap().position;
+        assert_eq!(position, 0);
+        assert!(matches!(mm.on_cycle_fills(position, fills), StrategyAction::CancelAll(1)));
+        assert!(engine.take_cycle_fills(1).is_empty());
+
+        // One symmetric requote from the flat position
+        let StrategyAction::Quote(pair) = mm.on_features(&features) else {
+            panic!("expected a requote");
+        };
+        let (bid, ask) = (pair.bid.unwrap(), pair.ask.unwrap());
+        assert_eq!(bid.qty, ask.qty);
+        assert_eq!(features.fair_value - bid.price, ask.price - features.fair_value);
+    }
+
+    #[test]
+    fn test_consecutive_rejects_deactivate_strategy() {
+        use crate::strategies::{MarketMaker, MarketMakerConfig};
+
+        let mut engine = TradeEngine::with_defaults(1);
+        engine
+            .risk_manager_mut()
+            .set_limits(1, RiskLimits::new(50, 1000, 100000, 10));
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1).with_max_consecutive_rejects(3));
+        let submit = |engine: &mut TradeEngine, mm: &mut MarketMaker, qty| {
+            let result = engine.submit_order(1, Side::Buy, 10000, qty).err().unwrap_or(RiskCheckResult::Allowed);
+            mm.on_order_result(result)
+        };
+
+        // A successful submit resets the count
+        assert!(!submit(&mut engine, &mut mm, 100));
+        assert!(!submit(&mut engine, &mut mm, 100));
+        assert_eq!(mm.reject_breaker().streak(), 2);
+        assert!(!submit(&mut engine, &mut mm, 10));
+        assert_eq!(mm.reject_breaker().streak(), 0);
+
+        // Three OrderTooLarge rejects in a row deactivate it
+        assert!(!submit(&mut engine, &mut mm, 100));
+        assert!(!submit(&mut engine, &mut mm, 100));
+        assert!(submit(&mut engine, &mut mm, 100));
+        assert!(!mm.is_active());
+        assert!(mm.reject_breaker().is_tripped());
+        assert_eq!(engine.stats().orders_rejected_risk, 5);
+
+        // Stays off until re-armed by hand
+        assert!(!submit(&mut engine, &mut mm, 100));
+        assert!(!mm.is_active());
+        mm.activate();
+        assert!(mm.is_active());
+        assert_eq!(mm.reject_breaker().streak(), 0);
+    }
+
+    #[test]
+    fn test_strongest_signal_gets_scarce_budget() {
+        use crate::strategies::OrderRequest;
+
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1, 2, 3])
+            .with_risk_checks(false)
+            .with_daily_order_limit(1, DEFAULT_SESSION_LENGTH_NS)
+            .with_action_ordering(ActionOrdering::SignalStrength);
+        let mut engine = TradeEngine::new(config);
+        engine.update_bbo(1, make_bbo(10000, 100, 10100, 100));
+        engine.update_bbo(2, make_bbo(10000, 900, 10100, 100));
+        engine.update_bbo(3, make_bbo(10000, 100, 10100, 300));
+        let signal = |engine: &TradeEngine, ticker_id| engine.get_features(ticker_id).unwrap().trade_signal;
+        assert!(signal(&engine, 2).abs() > signal(&engine, 3).abs());
+        assert!(signal(&engine, 3).abs() > signal(&engine, 1).abs());
+
+        let actions = vec![
+            StrategyAction::Take(OrderRequest::buy(1, 10100, 10)),
+            StrategyAction::Take(OrderRequest::sell(3, 10000, 10)),
+            StrategyAction::Take(OrderRequest::buy(2, 10100, 10)),
+        ];
+        let results = engine.process_strategy_actions(actions);
+
+        // Only the strongest signal's order fits the budget
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].1, RiskCheckResult::Allowed);
+        assert_eq!(engine.get_pending_order(results[0].0.unwrap()).unwrap().ticker_id, 2);
+        assert!(results[1..]
+            .iter()
+            .all(|&(id, result)| id.is_none() && result == RiskCheckResult::DailyOrderLimitExceeded));
+    }
+
+    #[test]
+    fn test_action_ordering_policies() {
+        use crate::strategies::OrderRequest;
+
+        let actions = || {
+            vec![
+                StrategyAction::Take(OrderRequest::buy(3, 10100, 10)),
+                StrategyAction::None,
+                StrategyAction::CancelAll(1),
+                StrategyAction::Take(OrderRequest::sell(2, 10000, 10)),
+            ]
+        };
+        let order = |ordering: ActionOrdering| {
+            let engine = TradeEngine::new(TradeEngineConfig::new(1).with_action_ordering(ordering));
+            let mut actions = actions();
+            engine.sort_actions(&mut actions);
+            actions.iter().map(StrategyAction::ticker_id).collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(ActionOrdering::TickerId), vec![Some(1), Some(2), Some(3), None]);
+        let priorities = HashMap::from([(3, 10), (2, -1)]);
+        assert_eq!(order(ActionOrdering::Priority(priorities)), vec![Some(3), Some(1), None, Some(2)]);
+        // No features yet: every signal is 0, so ticker order decides
+        assert_eq!(order(ActionOrdering::SignalStrength), vec![Some(1), Some(2), Some(3), None]);
+    }
+
     #[test]
     fn test_run_cycle_with_events() {
         let config = TradeEngineConfig::new(1).with_risk_checks(false);
@@ -1210,6 +2893,276 @@ mod tests {
         assert!(engine.get_features(1).is_none() || !engine.get_features(1).unwrap().is_valid());
     }
 
+    #[test]
+    fn test_audit_restores_open_order_accounting() {
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        let bid = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        engine.submit_order(1, Side::Sell, 10100, 40).unwrap();
+        engine.on_response(&make_fill_response(bid, 1, Side::Buy, 10000, 30, 70));
+        assert!(engine.audit_open_orders().is_empty());
+
+        // Drift the counters away from the tracked orders
+        let position = engine.position_keeper_mut().get_position_mut(1);
+        position.open_buy_qty = 100;
+        position.open_sell_qty = 0;
+        engine.open_order_count.insert(1, 5);
+        engine.position_keeper_mut().get_position_mut(2).open_sell_qty = 10;
+
+        let mut discrepancies = engine.audit_open_orders();
+        discrepancies.sort_by_key(|d| d.ticker_id);
+        assert_eq!(
+            discrepancies,
+            vec![
+                OpenOrderDiscrepancy {
+                    ticker_id: 1,
+                    open_buy_qty: (100, 70),
+                    open_sell_qty: (0, 40),
+                    open_orders: (5, 2),
+                },
+                OpenOrderDiscrepancy {
+                    ticker_id: 2,
+                    open_buy_qty: (0, 0),
+                    open_sell_qty: (10, 0),
+                    open_orders: (0, 0),
+                },
+            ]
+        );
+
+        let position = engine.get_position(1).unwrap();
+        assert_eq!((position.open_buy_qty, position.open_sell_qty), (70, 40));
+        assert_eq!(engine.pending_order_count(1), 2);
+        assert_eq!(engine.get_position(2).unwrap().open_sell_qty, 0);
+        assert_eq!(engine.stats().open_order_corrections, 2);
+        assert!(engine.audit_open_orders().is_empty());
+    }
+
+    #[test]
+    fn test_replenishment_updates_tracked_order() {
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        // Iceberg slice of 100 fully filled, then refilled with 80 from reserve
+        let order_id = engine.submit_order(1, Side::Sell, 10100, 100).unwrap();
+        engine.on_response(&make_accepted_response(order_id, 1, Side::Sell, 10100, 100));
+        engine.on_response(&make_fill_response(order_id, 1, Side::Sell, 10100, 60, 40));
+        let replenished = ClientResponse::new(
+            ClientResponseType::Replenished,
+            1,
+            1,
+            order_id,
+            1001,
+            Side::Sell as i8,
+            10100,
+            0,
+            80,
+        );
+        engine.on_response(&replenished);
+
+        let order = engine.get_pending_order(order_id).unwrap();
+        assert_eq!(order.leaves_qty, 80);
+        assert_eq!(engine.total_pending_orders(), 1);
+        assert_eq!(engine.pending_order_count(1), 1);
+        assert_eq!(engine.get_position(1).unwrap().open_sell_qty, 80);
+        assert_eq!(engine.stats().replenishments, 1);
+        assert!(engine.audit_open_orders().is_empty());
+
+        // Fills on the refilled slice draw it down as usual
+        engine.on_response(&make_fill_response(order_id, 1, Side::Sell, 10100, 80, 0));
+        assert_eq!(engine.total_pending_orders(), 0);
+        assert_eq!(engine.get_position(1).unwrap().position, -140);
+        assert_eq!(engine.get_position(1).unwrap().open_sell_qty, 0);
+        assert_eq!(engine.stats().out_of_order_responses, 0);
+
+        // A refill for an order no longer tracked isn't picked up as a new one
+        engine.on_response(&replenished);
+        assert_eq!(engine.total_pending_orders(), 0);
+        assert_eq!(engine.stats().out_of_order_responses, 1);
+    }
+
+    #[test]
+    fn test_repeated_cancel_is_a_no_op() {
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        let kept = engine.submit_order(1, Side::Buy, 9990, 40).unwrap();
+        let canceled = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        engine.on_response(&make_canceled_response(canceled, 1));
+
+        let snapshot = |engine: &TradeEngine| {
+            let position = engine.get_position(1).unwrap();
+            (engine.pending_order_count(1), engine.total_pending_orders(), position.open_buy_qty)
+        };
+        let after_first = snapshot(&engine);
+        assert_eq!(after_first, (1, 1, 40));
+
+        // Echoed twice more: nothing moves and nothing goes negative
+        engine.on_response(&make_canceled_response(canceled, 1));
+        engine.on_response(&make_canceled_response(canceled, 1));
+        assert_eq!(snapshot(&engine), after_first);
+        assert_eq!(engine.stats().duplicate_cancels, 2);
+        assert_eq!(engine.stats().out_of_order_responses, 0);
+        assert!(engine.get_pending_order(kept).is_some());
+        assert!(engine.audit_open_orders().is_empty());
+
+        // Once the last order is canceled twice, counts stop at zero
+        engine.on_response(&make_canceled_response(kept, 1));
+        engine.on_response(&make_canceled_response(kept, 1));
+        assert_eq!(snapshot(&engine), (0, 0, 0));
+        assert_eq!(engine.stats().duplicate_cancels, 3);
+    }
+
+    #[test]
+    fn test_cancel_dedup_window_forgets_oldest() {
+        let config = TradeEngineConfig::new(1)
+            .with_risk_checks(false)
+            .with_cancel_dedup_window(1);
+        let mut engine = TradeEngine::new(config);
+
+        let first = engine.submit_order(1, Side::Buy, 10000, 10).unwrap();
+        let second = engine.submit_order(1, Side::Buy, 10000, 10).unwrap();
+        engine.on_response(&make_canceled_response(first, 1));
+        engine.on_response(&make_canceled_response(second, 1));
+
+        // Only the latest cancel is remembered; an older repeat is merely unknown
+        engine.on_response(&make_canceled_response(second, 1));
+        engine.on_response(&make_canceled_response(first, 1));
+        assert_eq!(engine.stats().duplicate_cancels, 1);
+        assert_eq!(engine.stats().out_of_order_responses, 1);
+        assert_eq!(engine.pending_order_count(1), 0);
+    }
+
+    #[test]
+    fn test_late_fill_on_replaced_order() {
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+
+        let old = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        let new = engine.replace_quote(old, 10010, 100).unwrap().unwrap();
+        assert_ne!(old, new);
+        assert_eq!(engine.replacement_of(old), Some(new));
+        assert!(engine.get_pending_order(old).is_none());
+        assert_eq!(engine.get_pending_order(new).unwrap().price, 10010);
+        assert_eq!(engine.get_position(1).unwrap().open_buy_qty, 100);
+        assert_eq!(engine.pending_order_count(1), 1);
+
+        // The old order fills before its cancel lands
+        engine.on_response(&make_fill_response(old, 1, Side::Buy, 10000, 30, 70));
+        let position = engine.get_position(1).unwrap();
+        assert_eq!(position.position, 30);
+        assert_eq!(position.open_buy_qty, 100);
+        let replacement = engine.get_pending_order(new).unwrap();
+        assert_eq!((replacement.leaves_qty, replacement.original_qty), (100, 100));
+        assert_eq!(engine.stats().fills_received, 1);
+        assert_eq!(engine.stats().out_of_order_responses, 0);
+
+        // The cancel clears the link without touching the replacement
+        engine.on_response(&make_canceled_response(old, 1));
+        assert_eq!(engine.replacement_of(old), None);
+        assert_eq!(engine.pending_order_count(1), 1);
+        assert_eq!(engine.get_position(1).unwrap().open_buy_qty, 100);
+        assert_eq!(engine.stats().out_of_order_responses, 0);
+        assert!(engine.audit_open_orders().is_empty());
+
+        assert!(engine.replace_quote(old, 10020, 50).is_none());
+    }
+
+    #[test]
+    fn test_replace_action_amends_in_place() {
+        use crate::strategies::{MarketMaker, MarketMakerConfig};
+        use std::sync::{Arc, Mutex};
+
+        let config = TradeEngineConfig::new(1)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut engine = TradeEngine::new(config);
+        let replaces = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&replaces);
+        engine.set_order_replace_callback(Box::new(move |id, price, qty| {
+            sink.lock().unwrap().push((id, price, qty));
+        }));
+
+        let bid = engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        let ask = engine.submit_order(1, Side::Sell, 10100, 100).unwrap();
+        assert_eq!(engine.total_pending_orders(), 2);
+
+        // Moving the bid keeps its ID and the pending order count
+        let results = engine.process_strategy_action(StrategyAction::Replace {
+            order_id: bid,
+            new_price: 10010,
+            new_qty: 80,
+        });
+        assert_eq!(results, vec![(Some(bid), RiskCheckResult::Allowed)]);
+        assert_eq!(*replaces.lock().unwrap(), vec![(bid, 10010, 80)]);
+        assert_eq!((engine.total_pending_orders(), engine.pending_order_count(1)), (2, 2));
+        let order = engine.get_pending_order(bid).unwrap();
+        assert_eq!((order.price, order.leaves_qty), (10010, 80));
+        assert_eq!(engine.get_position(1).unwrap().open_buy_qty, 80);
+        assert_eq!(engine.stats().orders_submitted, 2);
+        assert_eq!(engine.stats().orders_amended, 1);
+
+        // A rejected amendment puts the order back on its old terms
+        engine.on_response(&ClientResponse::new(
+            ClientResponseType::ModifyRejected,
+            1,
+            1,
+            bid,
+            1000,
+            Side::Buy as i8,
+            10010,
+            0,
+            0,
+        ));
+        let order = engine.get_pending_order(bid).unwrap();
+        assert_eq!((order.price, order.leaves_qty), (10000, 100));
+        assert_eq!(engine.get_position(1).unwrap().open_buy_qty, 100);
+        assert_eq!(engine.stats().amend_rejects, 1);
+
+        // A market maker told its quote IDs amends both sides as they move
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1));
+        let features = |fair_value| TickerFeatures {
+            ticker_id: 1,
+            fair_value,
+            spread: 100,
+            mid_price: fair_value,
+            imbalance: 0.0,
+            trade_signal: 0.0,
+            volatility: 0.0,
+            version: 0,
+            has_bbo: true,
+        };
+        assert!(matches!(mm.on_features(&features(10050)), StrategyAction::Quote(_)));
+        mm.set_quote_order(Side::Buy, Some(bid));
+        mm.set_quote_order(Side::Sell, Some(ask));
+        let action = mm.on_features(&features(10100));
+        let results = engine.process_strategy_action(action);
+        assert_eq!(
+            results,
+            vec![(Some(bid), RiskCheckResult::Allowed), (Some(ask), RiskCheckResult::Allowed)]
+        );
+        assert_eq!(engine.total_pending_orders(), 2);
+        assert_eq!(engine.get_pending_order(bid).unwrap().price, 10050);
+        assert_eq!(engine.get_pending_order(ask).unwrap().price, 10150);
+        engine.on_response(&make_accepted_response(bid, 1, Side::Buy, 10050, 100));
+        assert_eq!(engine.get_pending_order(bid).unwrap().price, 10050);
+        assert_eq!(replaces.lock().unwrap().len(), 3);
+
+        // An order no longer tracked is not amended
+        assert!(engine
+            .process_strategy_action(StrategyAction::Replace { order_id: 999, new_price: 1, new_qty: 1 })
+            .is_empty());
+        assert!(engine.audit_open_orders().is_empty());
+    }
+
     // ========================================================================
     // Position Tracking Tests
     // ========================================================================
@@ -1299,6 +3252,26 @@ mod tests {
         assert_eq!(features.spread, 100);
     }
 
+    #[test]
+    fn test_bbo_imbalance_scales_position_limits() {
+        let mut engine = TradeEngine::with_defaults(1);
+        engine
+            .risk_manager_mut()
+            .set_limits(1, crate::risk::RiskLimits::new(10000, 1000, 100000, 10));
+        engine
+            .risk_manager_mut()
+            .set_pressure_adjustment(crate::risk::PressureAdjustment::new(0.5, 0.5));
+
+        // 900 bid vs 100 ask is an imbalance of 0.8
+        engine.update_bbo(1, make_bbo(10000, 900, 10100, 100));
+        assert_eq!(engine.risk_manager().book_pressure(1), 0.8);
+        assert!(engine.submit_order(1, Side::Buy, 10000, 1200).is_ok());
+        assert_eq!(
+            engine.submit_order(1, Side::Sell, 10100, 700),
+            Err(RiskCheckResult::PositionTooLarge)
+        );
+    }
+
     // ========================================================================
     // Stats Tests
     // ========================================================================
@@ -5,12 +5,14 @@
 //! - Order cancellation workflow
 //! - Trading client component integration (features, risk, positions)
 //! - Strategy integration (market maker, liquidity taker)
+//! - Cancel-all of live quotes through the order gateway
+//! - Operator kills over the control port
 
-use common::{Price, Qty, Side, TickerId};
-use exchange::matching_engine::MatchingEngine;
+use common::{InstrumentRegistry, Price, Qty, Side, TickerId};
+use exchange::matching_engine::{FeeSchedule, MatchingEngine, MatchingEngineConfig};
 use exchange::protocol::{
-    ClientRequest, ClientRequestType, ClientResponse, ClientResponseType, MarketUpdate,
-    MarketUpdateType,
+    ClientRequest, ClientRequestType, ClientResponse, ClientResponseType, LiquidityFlag,
+    MarketUpdate, MarketUpdateType,
 };
 use trading::features::{FeatureEngine, TickerFeatures};
 use trading::market_data::BBO;
@@ -33,6 +35,7 @@ fn make_bbo(bid_price: Price, bid_qty: Qty, ask_price: Price, ask_qty: Qty) -> B
         bid_qty,
         ask_price,
         ask_qty,
+        stale: false,
     }
 }
 
@@ -51,6 +54,9 @@ fn make_features(
         mid_price: fair_value,
         imbalance,
         trade_signal,
+        volatility: 0.0,
+        version: 0,
+        has_bbo: true,
     }
 }
 
@@ -181,6 +187,199 @@ mod order_flow_tests {
         assert_eq!(sell_market_order_id, 2);
     }
 
+    #[test]
+    fn test_order_tag_round_trip() {
+        use std::sync::{Arc, Mutex};
+
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine.add_ticker(1);
+
+        let config = TradeEngineConfig::new(100)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut trade_engine = TradeEngine::new(config);
+
+        // Capture outgoing requests instead of sending them over TCP
+        let outbox = Arc::new(Mutex::new(Vec::new()));
+        let outbox_clone = outbox.clone();
+        trade_engine.set_order_submit_callback(Box::new(move |ticker, side, price, qty, tag| {
+            let request =
+                ClientRequest::new(ClientRequestType::New, 100, ticker, 1, side as i8, price, qty)
+                    .with_tag(tag);
+            outbox_clone.lock().unwrap().push(request);
+            1
+        }));
+
+        let fill_tags = Arc::new(Mutex::new(Vec::new()));
+        let fill_tags_clone = fill_tags.clone();
+        trade_engine.set_order_fill_callback(Box::new(move |fill| {
+            fill_tags_clone.lock().unwrap().push(fill.tag);
+        }));
+
+        let order_id = trade_engine
+            .submit_order_with_tag(1, Side::Buy, 10000, 100, 0xABCD)
+            .unwrap();
+
+        // The exchange echoes the tag on the acknowledgment
+        let request = outbox.lock().unwrap()[0];
+        let (ack, _) = matching_engine.process_request(&request);
+        let ack_tag = ack.tag;
+        assert_eq!(ack_tag, 0xABCD);
+        trade_engine.on_response(&ack);
+
+        // A fill carrying the same tag is correlated back to the order
+        let fill = make_fill_response(order_id, 1, Side::Buy, 10000, 100, 0).with_tag(ack_tag);
+        let fill_tag = fill.tag;
+        assert_eq!(fill_tag, 0xABCD);
+        trade_engine.on_response(&fill);
+
+        assert_eq!(*fill_tags.lock().unwrap(), vec![0xABCD]);
+    }
+
+    #[test]
+    fn test_exchange_position_limit_overrides_client() {
+        use std::sync::{Arc, Mutex};
+
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine.add_ticker(1);
+        matching_engine.set_client_position_limit(100, 1, 100);
+        let resting = ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10000, 500);
+        matching_engine.process_request(&resting);
+
+        // The client checks nothing itself
+        let config = TradeEngineConfig::new(100)
+            .with_tickers(vec![1])
+            .with_risk_checks(false);
+        let mut trade_engine = TradeEngine::new(config);
+        let outbox = Arc::new(Mutex::new(Vec::new()));
+        let outbox_clone = outbox.clone();
+        let mut next_order_id = 0;
+        trade_engine.set_order_submit_callback(Box::new(move |ticker, side, price, qty, _tag| {
+            next_order_id += 1;
+            let request =
+                ClientRequest::new(ClientRequestType::New, 100, ticker, next_order_id, side as i8, price, qty);
+            outbox_clone.lock().unwrap().push(request);
+            next_order_id
+        }));
+
+        trade_engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        let (fill, _) = matching_engine.process_request(&outbox.lock().unwrap()[0]);
+        trade_engine.on_response(&fill);
+        assert_eq!(trade_engine.get_position(1).unwrap().position, 100);
+
+        // Sent by the client, turned away by the venue
+        trade_engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        let (response, _) = matching_engine.process_request(&outbox.lock().unwrap()[1]);
+        assert_eq!(response.response_type(), Some(ClientResponseType::RiskRejected));
+        trade_engine.on_response(&response);
+
+        assert_eq!(trade_engine.get_position(1).unwrap().position, 100);
+        assert_eq!(trade_engine.pending_order_count(1), 0);
+        assert_eq!(matching_engine.client_position(100, 1), 100);
+        assert_eq!(matching_engine.stats().risk_rejects, 1);
+    }
+
+    #[test]
+    fn test_fill_carries_exchange_exec_time() {
+        use common::time::{realtime_nanos, ClockSource};
+        use std::sync::{Arc, Mutex};
+
+        // Both sides on the wall clock, as when the exchange runs elsewhere
+        let mut matching_engine = MatchingEngine::new().with_clock(|| realtime_nanos().as_u64());
+        matching_engine.add_ticker(1);
+        let resting = ClientRequest::new(ClientRequestType::New, 200, 1, 1, -1, 10000, 100);
+        matching_engine.process_request(&resting);
+
+        let config = TradeEngineConfig::new(100)
+            .with_tickers(vec![1])
+            .with_risk_checks(false)
+            .with_record_clock(ClockSource::Realtime);
+        let mut trade_engine = TradeEngine::new(config);
+        let fills = Arc::new(Mutex::new(Vec::new()));
+        let fills_clone = fills.clone();
+        trade_engine.set_order_fill_callback(Box::new(move |fill| {
+            fills_clone.lock().unwrap().push(*fill);
+        }));
+
+        let order_id = trade_engine.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, order_id, 1, 10000, 100);
+        let before_match = realtime_nanos().as_u64();
+        let (fill, _) = matching_engine.process_request(&request);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        trade_engine.on_response(&fill);
+
+        // Stamped at match time, before the client saw it
+        let fills = fills.lock().unwrap();
+        assert_eq!(fills.len(), 1);
+        assert!(fills[0].exec_time_ns >= before_match);
+        assert!(fills[0].exec_time_ns < fills[0].recv_time_ns);
+    }
+
+    #[test]
+    fn test_cross_charges_maker_and_taker_fees() {
+        use std::sync::{Arc, Mutex};
+
+        // 1bp maker rebate, 3bp taker fee on both sides of the venue
+        let fee_schedule = FeeSchedule::new(-1.0, 3.0);
+        let mut matching_engine =
+            MatchingEngine::with_config(MatchingEngineConfig::new().with_fee_schedule(fee_schedule));
+        matching_engine.add_ticker(1);
+
+        // Each client captures its outgoing requests instead of sending them over TCP
+        let make_client = |client_id: u32| {
+            let config = TradeEngineConfig::new(client_id)
+                .with_tickers(vec![1])
+                .with_risk_checks(false);
+            let mut engine = TradeEngine::new(config);
+            engine.position_keeper_mut().set_fee_schedule(fee_schedule);
+
+            let outbox = Arc::new(Mutex::new(Vec::new()));
+            let outbox_clone = outbox.clone();
+            let mut next_order_id = 0;
+            engine.set_order_submit_callback(Box::new(move |ticker, side, price, qty, tag| {
+                next_order_id += 1;
+                let request = ClientRequest::new(
+                    ClientRequestType::New, client_id, ticker, next_order_id, side as i8, price, qty,
+                )
+                .with_tag(tag);
+                outbox_clone.lock().unwrap().push(request);
+                next_order_id
+            }));
+            (engine, outbox)
+        };
+        let (mut maker, maker_outbox) = make_client(1);
+        let (mut taker, taker_outbox) = make_client(2);
+
+        // Maker rests a sell, taker lifts it
+        maker.submit_order(1, Side::Sell, 10000, 100).unwrap();
+        let request = maker_outbox.lock().unwrap()[0];
+        let (ack, _) = matching_engine.process_request(&request);
+        maker.on_response(&ack);
+
+        taker.submit_order(1, Side::Buy, 10000, 100).unwrap();
+        let request = taker_outbox.lock().unwrap()[0];
+        let (taker_fill, _) = matching_engine.process_request(&request);
+        assert_eq!(taker_fill.liquidity_flag(), Some(LiquidityFlag::Taker));
+        taker.on_response(&taker_fill);
+
+        let maker_fills: Vec<_> = matching_engine.drain_maker_fills().collect();
+        assert_eq!(maker_fills.len(), 1);
+        assert_eq!(maker_fills[0].liquidity_flag(), Some(LiquidityFlag::Maker));
+        maker.on_response(&maker_fills[0]);
+
+        // Notional 100 * $100.00 = 1,000,000 cents
+        let maker_position = maker.position_keeper().get_position(1).unwrap();
+        assert_eq!(maker_position.position, -100);
+        assert_eq!(maker_position.fees_paid, -100);
+
+        let taker_position = taker.position_keeper().get_position(1).unwrap();
+        assert_eq!(taker_position.position, 100);
+        assert_eq!(taker_position.fees_paid, 300);
+
+        assert_eq!(maker.pending_order_count(1), 0);
+        assert_eq!(taker.pending_order_count(1), 0);
+    }
+
     #[test]
     fn test_order_rejected_unknown_ticker() {
         let mut engine = MatchingEngine::new();
@@ -975,4 +1174,288 @@ mod full_system_tests {
         assert_eq!(results[0].1, RiskCheckResult::OrderTooLarge);
         assert_eq!(trade_engine.stats().orders_rejected_risk, 1);
     }
+
+    #[test]
+    fn test_instrument_registry_shared_by_strategy_risk_and_engine() {
+        let instruments = InstrumentRegistry::parse("1 tick_size=5 lot_size=10").unwrap();
+
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        engine.set_instruments(instruments.clone());
+        let mut risk_manager = RiskManager::new();
+        risk_manager.set_instruments(instruments.clone());
+        let config = MarketMakerConfig::new(1)
+            .with_base_qty(25)
+            .with_instrument(instruments.get(1));
+        let mut mm = MarketMaker::new(config);
+
+        // Fair value 100.02 +/- 0.50 lands off the 0.05 grid: the strategy
+        // rounds outwards and sizes in whole lots
+        let StrategyAction::Quote(quotes) = mm.on_features(&make_features(1, 10002, 10, 0.0, 0.0)) else {
+            panic!("expected quotes");
+        };
+        let (bid, ask) = (quotes.bid.unwrap(), quotes.ask.unwrap());
+        assert_eq!((bid.price, bid.qty), (9950, 20));
+        assert_eq!((ask.price, ask.qty), (10055, 20));
+
+        // Risk and the engine accept the quotes as sent
+        let flat = trading::position::Position::new(1);
+        for (order_id, quote) in [(1, bid), (2, ask)] {
+            assert!(risk_manager.check_order(&flat, quote.side, quote.qty, quote.price).is_allowed());
+            let request = ClientRequest::new(
+                ClientRequestType::New, 100, 1, order_id, quote.side as i8, quote.price, quote.qty,
+            );
+            let (response, _) = engine.process_request(&request);
+            assert_eq!(response.response_type(), Some(ClientResponseType::Accepted));
+        }
+
+        // The unrounded size is rejected by both
+        assert_eq!(
+            risk_manager.check_order(&flat, Side::Buy, 25, 9950),
+            RiskCheckResult::InvalidLotSize
+        );
+        let request = ClientRequest::new(ClientRequestType::New, 100, 1, 3, 1, 9950, 25);
+        let (response, _) = engine.process_request(&request);
+        assert_eq!(response.response_type(), Some(ClientResponseType::InvalidRequest));
+        assert_eq!(engine.stats().off_lot_rejects, 1);
+    }
+}
+
+// =============================================================================
+// Reconnect Tests
+// =============================================================================
+
+mod reconnect_tests {
+    use super::*;
+    use exchange::order_server::{OrderServer, OrderServerConfig};
+    use std::time::{Duration, Instant};
+    use trading::order_gateway::OrderGateway;
+
+    /// Runs one exchange cycle: matches every request and sends the
+    /// responses and maker fills.
+    pub(super) fn serve(server: &mut OrderServer, engine: &mut MatchingEngine) {
+        for seq_request in server.poll() {
+            let mut request = seq_request.request;
+            request.client_id = seq_request.client_id;
+            let (response, _) = engine.process_request(&request);
+            let _ = server.send_response(seq_request.client_id, &response);
+            for fill in engine.drain_maker_fills() {
+                let _ = server.send_response(fill.client_id, &fill);
+            }
+        }
+    }
+
+    /// Serves the exchange and feeds the gateway's responses to the trade
+    /// engine until `done` holds or a second passes.
+    pub(super) fn run_until(
+        server: &mut OrderServer,
+        engine: &mut MatchingEngine,
+        gateway: &mut OrderGateway,
+        trade_engine: &mut TradeEngine,
+        done: impl Fn(&OrderServer, &OrderGateway, &TradeEngine) -> bool,
+    ) {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while !done(server, gateway, trade_engine) && Instant::now() < deadline {
+            serve(server, engine);
+            while let Some(response) = gateway.poll() {
+                trade_engine.on_response(&response);
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn test_fill_during_disconnect_applied_once_after_reconnect() {
+        let config = OrderServerConfig::new("127.0.0.1", 0)
+            .with_credential(1, 11)
+            .with_credential(2, 22)
+            .with_fill_retention(16);
+        let mut server = OrderServer::new(config).unwrap();
+        let port = server.local_port().unwrap();
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+
+        let mut maker = OrderGateway::connect("127.0.0.1", port, 1).unwrap();
+        maker.login(11).unwrap();
+        let mut taker = OrderGateway::connect("127.0.0.1", port, 2).unwrap();
+        taker.login(22).unwrap();
+
+        let config = TradeEngineConfig::new(1).with_tickers(vec![1]).with_risk_checks(false);
+        let mut trade_engine = TradeEngine::new(config);
+        run_until(&mut server, &mut engine, &mut maker, &mut trade_engine, |server, _, _| {
+            server.client_count() == 2
+        });
+
+        // Rest a sell tracked by the trade engine
+        let order_id = trade_engine.submit_order(1, Side::Sell, 10000, 100).unwrap();
+        assert_eq!(maker.send_new_order(1, Side::Sell, 10000, 100).unwrap(), order_id);
+        run_until(&mut server, &mut engine, &mut maker, &mut trade_engine, |_, maker, _| {
+            maker.get_pending(order_id).is_none()
+        });
+
+        // The maker drops off, and its order trades while it is away
+        server.disconnect_client(1);
+        run_until(&mut server, &mut engine, &mut maker, &mut trade_engine, |_, maker, _| {
+            !maker.is_connected()
+        });
+        assert!(!maker.is_connected());
+        taker.send_new_order(1, Side::Buy, 10000, 60).unwrap();
+        run_until(&mut server, &mut engine, &mut maker, &mut trade_engine, |server, _, _| {
+            server.retained_fills(1) == 1
+        });
+        assert_eq!(trade_engine.get_position(1).map_or(0, |p| p.position), 0);
+
+        // Reconnecting recovers the fill
+        maker.reconnect().unwrap();
+        run_until(&mut server, &mut engine, &mut maker, &mut trade_engine, |_, _, trade_engine| {
+            trade_engine.stats().fills_received == 1
+        });
+        assert_eq!(maker.reconnects(), 1);
+        assert_eq!(maker.last_exec_seq(), 1);
+        assert_eq!(server.fills_replayed(), 1);
+        assert_eq!(trade_engine.get_position(1).unwrap().position, -60);
+
+        // Replaying the same fill again changes nothing
+        maker.request_fills_after(0).unwrap();
+        run_until(&mut server, &mut engine, &mut maker, &mut trade_engine, |_, _, trade_engine| {
+            trade_engine.stats().duplicate_fills == 1
+        });
+        assert_eq!(trade_engine.stats().duplicate_fills, 1);
+        assert_eq!(trade_engine.stats().fills_received, 1);
+        assert_eq!(trade_engine.get_position(1).unwrap().position, -60);
+    }
+}
+
+// =============================================================================
+// Cancel-All Tests
+// =============================================================================
+
+mod cancel_all_tests {
+    use super::reconnect_tests::run_until;
+    use super::*;
+    use exchange::order_server::{OrderServer, OrderServerConfig};
+    use trading::order_gateway::OrderGateway;
+
+    #[test]
+    fn test_market_maker_cancel_all_cancels_live_quotes() {
+        let mut server = OrderServer::new(OrderServerConfig::new("127.0.0.1", 0)).unwrap();
+        let port = server.local_port().unwrap();
+        let mut engine = MatchingEngine::new();
+        engine.add_ticker(1);
+        let mut gateway = OrderGateway::connect("127.0.0.1", port, 1).unwrap();
+        let config = TradeEngineConfig::new(1).with_tickers(vec![1]).with_risk_checks(false);
+        let mut trade_engine = TradeEngine::new(config);
+
+        // Send the market maker's quotes as the main loop does
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1));
+        let StrategyAction::Quote(quotes) = mm.on_features(&make_features(1, 10000, 100, 0.0, 0.0)) else {
+            panic!("expected quotes");
+        };
+        for quote in [quotes.bid, quotes.ask].into_iter().flatten() {
+            gateway.send_new_order(quote.ticker_id, quote.side, quote.price, quote.qty).unwrap();
+        }
+        run_until(&mut server, &mut engine, &mut gateway, &mut trade_engine, |_, _, trade_engine| {
+            trade_engine.stats().responses_processed == 2
+        });
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 2);
+        assert_eq!(gateway.pending_count(), 2);
+
+        // A wider spread invalidates the quotes, and every live order is cancelled
+        let wider = MarketMakerConfig::new(1).with_half_spread(mm.config().half_spread * 4);
+        let StrategyAction::CancelAll(ticker_id) = mm.update_config(wider) else {
+            panic!("expected cancel all");
+        };
+        assert_eq!(gateway.cancel_all(ticker_id), 2);
+        run_until(&mut server, &mut engine, &mut gateway, &mut trade_engine, |_, gateway, _| {
+            gateway.pending_count() == 0
+        });
+        assert_eq!(gateway.pending_count(), 0);
+        assert_eq!(engine.get_order_book(1).unwrap().order_count(), 0);
+    }
+}
+
+// =============================================================================
+// Operator Kill Tests
+// =============================================================================
+
+mod operator_kill_tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::sync::{Arc, Mutex};
+    use trading::control::{ControlCommand, ControlServer, KillSwitch};
+
+    /// Sends one command line to the control port and returns the reply
+    fn send_command(stream: &mut TcpStream, reader: &mut impl BufRead, line: &str) -> String {
+        writeln!(stream, "{}", line).unwrap();
+        let mut reply = String::new();
+        reader.read_line(&mut reply).unwrap();
+        reply.trim().to_string()
+    }
+
+    #[test]
+    fn test_control_kill_cancels_resting_orders_and_stops_strategies() {
+        let config = TradeEngineConfig::new(1).with_tickers(vec![1, 2]).with_risk_checks(false);
+        let engine = Arc::new(Mutex::new(TradeEngine::new(config)));
+        let cancels = Arc::new(Mutex::new(Vec::new()));
+        let resting = {
+            let mut engine = engine.lock().unwrap();
+            let sink = Arc::clone(&cancels);
+            engine.set_order_cancel_callback(Box::new(move |id, ticker| {
+                sink.lock().unwrap().push((id, ticker));
+            }));
+            vec![
+                (engine.submit_order(1, Side::Buy, 9900, 10).unwrap(), 1),
+                (engine.submit_order(1, Side::Sell, 10100, 10).unwrap(), 1),
+                (engine.submit_order(2, Side::Buy, 19900, 10).unwrap(), 2),
+            ]
+        };
+
+        let kill_switch = Arc::new(KillSwitch::new());
+        let handler_engine = Arc::clone(&engine);
+        let server = ControlServer::spawn("127.0.0.1:0", Arc::clone(&kill_switch), move |command| {
+            let mut engine = handler_engine.lock().unwrap();
+            match command {
+                ControlCommand::Kill { flatten } => {
+                    engine.kill_session(flatten);
+                }
+                ControlCommand::KillOrder(order_id) => {
+                    engine.kill_order(order_id);
+                }
+                ControlCommand::Resume => {
+                    engine.resume_session();
+                }
+            }
+        })
+        .unwrap();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert_eq!(send_command(&mut stream, &mut reader, "kill"), "ok");
+
+        let mut cancelled = cancels.lock().unwrap().clone();
+        cancelled.sort_unstable();
+        assert_eq!(cancelled, resting);
+        assert!(kill_switch.is_killed());
+        assert!(!kill_switch.take_flatten());
+
+        // The trading loop stops its strategies when it sees the switch
+        let mut mm = MarketMaker::new(MarketMakerConfig::new(1));
+        mm.activate();
+        if kill_switch.is_killed() {
+            mm.deactivate();
+        }
+        assert!(!mm.is_active());
+        assert_eq!(
+            engine.lock().unwrap().submit_order(1, Side::Buy, 9900, 10),
+            Err(RiskCheckResult::SessionKilled)
+        );
+
+        // The process keeps serving commands and trading resumes on request
+        assert!(send_command(&mut stream, &mut reader, "halt").starts_with("error"));
+        assert_eq!(send_command(&mut stream, &mut reader, "resume"), "ok");
+        assert!(!kill_switch.is_killed());
+        assert!(engine.lock().unwrap().submit_order(1, Side::Buy, 9900, 10).is_ok());
+        assert_eq!(kill_switch.kills(), 1);
+    }
 }